@@ -27,6 +27,11 @@ impl ModuleData {
         }
     }
 
+    /// The full path of the module
+    pub fn module_path(&self) -> String {
+        self.full_path.join("::")
+    }
+
     fn child_module(&self, id: String, path: PathBuf) -> Self {
         let mut full_path = self.full_path.clone();
         full_path.push(id.clone());
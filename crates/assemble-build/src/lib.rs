@@ -1,19 +1,41 @@
 //! Designed to be used as a build-dependency. Used to generate plugin descriptors
 
+use crate::function_finder::FunctionFinder;
+use crate::plugin_function::{PluginDescriptor, PluginFunction};
 use std::path::PathBuf;
 
 pub mod function_finder;
 pub mod plugin_function;
 
-/// Creates plugin descriptor information by finding `#[plugin]` attributes
+/// The name of the plugin descriptor created in `OUT_DIR`
+pub const PLUGIN_DESCRIPTOR: &str = "assemble-plugins.txt";
+
+/// Creates plugin descriptor information by finding `#[plugin]` attributes.
+///
+/// The descriptor is written to [`PLUGIN_DESCRIPTOR`](PLUGIN_DESCRIPTOR) in `OUT_DIR`, and its
+/// path is available to the crate in the `ASSEMBLE_PLUGIN_DESCRIPTOR` environment variable.
 pub fn generate_plugin_metadata() -> Result<(), ()> {
     let lib_file = PathBuf::from_iter(&[
         &std::env::var("CARGO_MANIFEST_DIR").unwrap(),
         "src",
         "lib.rs",
     ]);
+    println!("cargo:rerun-if-changed=src");
+
+    let package = std::env::var("CARGO_PKG_NAME").map_err(|_| ())?;
+    let finder = FunctionFinder::find_all(&lib_file, package.replace('-', "_"));
+    let descriptor =
+        PluginDescriptor::new(finder.found().filter_map(|(module, fun)| {
+            PluginFunction::try_create(module.module_path(), fun.clone())
+        }));
 
-    println!("cargo:warning={:?}", lib_file);
+    let out_dir = std::env::var("OUT_DIR").map_err(|_| ())?;
+    let path = PathBuf::from(out_dir).join(PLUGIN_DESCRIPTOR);
+    std::fs::write(&path, descriptor.to_string()).map_err(|_| ())?;
+    println!(
+        "cargo:rustc-env=ASSEMBLE_PLUGIN_DESCRIPTOR={}",
+        path.display()
+    );
 
     Ok(())
 }
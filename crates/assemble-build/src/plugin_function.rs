@@ -1,5 +1,6 @@
 //! Create plugin functions
 
+use std::fmt::{Display, Formatter};
 use syn::parse::{Parse, ParseStream};
 use syn::punctuated::Punctuated;
 use syn::{Ident, ItemFn, Lit, Signature, Token};
@@ -18,8 +19,82 @@ pub struct PluginFunctionMetadata {
 }
 
 impl PluginFunction {
-    pub fn try_create(_module: String, _item: ItemFn) -> Option<Self> {
-        None
+    /// Creates a plugin function if the function has a `#[plugin]` attribute. The module is the
+    /// full path of the module containing the function.
+    pub fn try_create(module: String, item: ItemFn) -> Option<Self> {
+        let attribute = item.attrs.iter().find(|attr| {
+            attr.path
+                .segments
+                .last()
+                .map_or(false, |segment| segment.ident == "plugin")
+        })?;
+        let meta = attribute.parse_args::<PluginFunctionMetadata>().ok()?;
+        Some(Self {
+            module,
+            identifier: item.sig.ident.to_string(),
+            sig: item.sig,
+            meta,
+        })
+    }
+
+    /// The id of the plugin
+    pub fn plugin_id(&self) -> &str {
+        &self.meta.plugin_id
+    }
+
+    /// The full path of the function
+    pub fn path(&self) -> String {
+        format!("{}::{}", self.module, self.identifier)
+    }
+
+    /// The signature of the function
+    pub fn signature(&self) -> &Signature {
+        &self.sig
+    }
+
+    /// The symbol of the entry point exported for the plugin by `#[plugin]`
+    pub fn symbol(&self) -> String {
+        let id: String = self
+            .plugin_id()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        format!("__assemble_plugin_{}", id)
+    }
+}
+
+/// Describes the plugins of a crate, one line per plugin
+#[derive(Debug, Default)]
+pub struct PluginDescriptor {
+    plugins: Vec<PluginFunction>,
+}
+
+impl PluginDescriptor {
+    /// Creates a descriptor from plugin functions
+    pub fn new<I: IntoIterator<Item = PluginFunction>>(plugins: I) -> Self {
+        let mut plugins: Vec<_> = plugins.into_iter().collect();
+        plugins.sort_by(|a, b| a.plugin_id().cmp(b.plugin_id()));
+        Self { plugins }
+    }
+
+    /// The described plugins
+    pub fn plugins(&self) -> &[PluginFunction] {
+        &self.plugins
+    }
+}
+
+impl Display for PluginDescriptor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for plugin in &self.plugins {
+            writeln!(
+                f,
+                "id={} path={} symbol={}",
+                plugin.plugin_id(),
+                plugin.path(),
+                plugin.symbol()
+            )?;
+        }
+        Ok(())
     }
 }
 
@@ -51,16 +126,13 @@ impl Parse for PluginFunctionMetadata {
             let value = assignment.value;
 
             match &*id {
-                "plugin_id" => {
+                "id" | "plugin_id" => {
                     match &value {
                         Lit::Str(s) => {
                             output.plugin_id = s.value();
                         }
                         _ => {
-                            return Err(syn::Error::new(
-                                value.span(),
-                                "plugin_id must be a string",
-                            ));
+                            return Err(syn::Error::new(value.span(), "id must be a string"));
                         }
                     };
                 }
@@ -76,3 +148,31 @@ impl Parse for PluginFunctionMetadata {
         Ok(output)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_plugin_functions() {
+        let function: ItemFn = syn::parse_quote! {
+            #[plugin(id = "org.example.greetings")]
+            pub fn greetings_plugin(project: &mut Project) -> ProjectResult {
+                Ok(())
+            }
+        };
+        let plugin = PluginFunction::try_create("example::plugins".to_string(), function)
+            .expect("is a plugin");
+        assert_eq!(plugin.plugin_id(), "org.example.greetings");
+        assert_eq!(
+            PluginDescriptor::new([plugin]).to_string(),
+            "id=org.example.greetings path=example::plugins::greetings_plugin \
+            symbol=__assemble_plugin_org_example_greetings\n"
+        );
+
+        let function: ItemFn = syn::parse_quote! {
+            pub fn not_a_plugin() {}
+        };
+        assert!(PluginFunction::try_create("example".to_string(), function).is_none());
+    }
+}
@@ -6,6 +6,9 @@ use std::ffi::OsStr;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
 
+pub mod chunked;
+pub mod manager;
+
 /// The assemble cache
 pub struct AssembleCache {
     path: PathBuf,
@@ -0,0 +1,523 @@
+//! Content-defined chunked storage for large cached files.
+//!
+//! Large outputs are split into variable sized chunks whose boundaries are determined by the
+//! content of the file rather than fixed offsets. Because of this, inserting or removing bytes
+//! in the middle of a file only changes the chunks around the edit, and every other chunk keeps
+//! the same hash. A [`ChunkManifest`](ChunkManifest) records the ordered chunk hashes for a file,
+//! and only the chunks missing from a [`ChunkStore`](ChunkStore) are ever transferred.
+
+use crate::cryptography::{hash_sha256, Sha256};
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Configures the sizes of the chunks produced by a [`Chunker`](Chunker).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkerConfig {
+    min_size: usize,
+    avg_size: usize,
+    max_size: usize,
+}
+
+impl ChunkerConfig {
+    /// Creates a new chunker config.
+    ///
+    /// # Panic
+    /// Will panic if the `avg_size` is not a power of two, or if `min_size <= avg_size <= max_size`
+    /// doesn't hold.
+    pub fn new(min_size: usize, avg_size: usize, max_size: usize) -> Self {
+        assert!(
+            avg_size.is_power_of_two(),
+            "average chunk size must be a power of two"
+        );
+        assert!(
+            min_size > 0 && min_size <= avg_size && avg_size <= max_size,
+            "chunk sizes must satisfy 0 < min <= avg <= max"
+        );
+        Self {
+            min_size,
+            avg_size,
+            max_size,
+        }
+    }
+
+    /// The minimum size of a chunk. Only the last chunk of a file can be smaller than this.
+    pub fn min_size(&self) -> usize {
+        self.min_size
+    }
+
+    /// The targeted average size of a chunk
+    pub fn avg_size(&self) -> usize {
+        self.avg_size
+    }
+
+    /// The maximum size of a chunk
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Mask used before reaching the average size. Has more bits than the average requires, making
+    /// a cut less likely.
+    fn mask_small(&self) -> u64 {
+        high_bits_mask(self.avg_size.trailing_zeros() + 1)
+    }
+
+    /// Mask used after reaching the average size. Has less bits than the average requires, making
+    /// a cut more likely.
+    fn mask_large(&self) -> u64 {
+        high_bits_mask(self.avg_size.trailing_zeros().saturating_sub(1))
+    }
+
+    /// Finds the next cut point within `data`. `data` is expected to contain at least `max_size`
+    /// bytes unless the end of the stream has been reached.
+    fn cut_point(&self, data: &[u8]) -> usize {
+        let len = data.len();
+        if len <= self.min_size {
+            return len;
+        }
+        let max = len.min(self.max_size);
+        let normal = self.avg_size.min(max);
+        let mask_small = self.mask_small();
+        let mask_large = self.mask_large();
+
+        let mut hash = 0_u64;
+        let mut index = self.min_size;
+        while index < normal {
+            hash = (hash << 1).wrapping_add(GEAR[data[index] as usize]);
+            if hash & mask_small == 0 {
+                return index + 1;
+            }
+            index += 1;
+        }
+        while index < max {
+            hash = (hash << 1).wrapping_add(GEAR[data[index] as usize]);
+            if hash & mask_large == 0 {
+                return index + 1;
+            }
+            index += 1;
+        }
+        max
+    }
+}
+
+impl Default for ChunkerConfig {
+    /// Chunks between 256 KiB and 4 MiB, averaging 1 MiB.
+    fn default() -> Self {
+        Self::new(256 * 1024, 1024 * 1024, 4 * 1024 * 1024)
+    }
+}
+
+fn high_bits_mask(bits: u32) -> u64 {
+    if bits == 0 {
+        0
+    } else {
+        u64::MAX << (64 - bits.min(64))
+    }
+}
+
+/// Random values used by the gear rolling hash. Generated with splitmix64 so that the table is
+/// stable across builds and platforms.
+const GEAR: [u64; 256] = {
+    let mut table = [0_u64; 256];
+    let mut state = 0x5eed_a55e_b1e5_0000_u64;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// Splits a reader into content-defined chunks. Only holds at most `max_size` bytes in memory at
+/// once, so arbitrarily large files can be chunked.
+pub struct Chunker<R: Read> {
+    reader: R,
+    config: ChunkerConfig,
+    buffer: Vec<u8>,
+    eof: bool,
+}
+
+impl<R: Read> Chunker<R> {
+    /// Creates a new chunker over a reader
+    pub fn new(reader: R, config: ChunkerConfig) -> Self {
+        Self {
+            reader,
+            config,
+            buffer: Vec::with_capacity(config.max_size),
+            eof: false,
+        }
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        let mut read_buf = [0_u8; 64 * 1024];
+        while !self.eof && self.buffer.len() < self.config.max_size {
+            let wanted = (self.config.max_size - self.buffer.len()).min(read_buf.len());
+            match self.reader.read(&mut read_buf[..wanted]) {
+                Ok(0) => self.eof = true,
+                Ok(n) => self.buffer.extend_from_slice(&read_buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for Chunker<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(e) = self.fill() {
+            return Some(Err(e));
+        }
+        if self.buffer.is_empty() {
+            return None;
+        }
+        let cut = self.config.cut_point(&self.buffer);
+        Some(Ok(self.buffer.drain(..cut).collect()))
+    }
+}
+
+/// A reference to a single chunk within a manifest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ChunkRef {
+    /// The hash of the chunk's contents
+    pub hash: Sha256,
+    /// The length of the chunk in bytes
+    pub len: u64,
+}
+
+/// Describes how a file is reconstructed from chunks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkManifest {
+    total_size: u64,
+    file_hash: Sha256,
+    chunks: Vec<ChunkRef>,
+}
+
+impl ChunkManifest {
+    /// Creates a manifest by chunking a file, without storing any chunks.
+    pub fn of_file<P: AsRef<Path>>(path: P, config: ChunkerConfig) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let mut builder = ManifestBuilder::default();
+        for chunk in Chunker::new(BufReader::new(file), config) {
+            builder.push(&chunk?);
+        }
+        Ok(builder.finish())
+    }
+
+    /// The total size of the file described by this manifest
+    pub fn total_size(&self) -> u64 {
+        self.total_size
+    }
+
+    /// The hash of the ordered list of chunk hashes. Two manifests with equal file hashes describe
+    /// the same file.
+    pub fn file_hash(&self) -> Sha256 {
+        self.file_hash
+    }
+
+    /// The chunks that make up the file, in order
+    pub fn chunks(&self) -> &[ChunkRef] {
+        &self.chunks
+    }
+}
+
+#[derive(Default)]
+struct ManifestBuilder {
+    total_size: u64,
+    chunks: Vec<ChunkRef>,
+}
+
+impl ManifestBuilder {
+    fn push(&mut self, chunk: &[u8]) -> ChunkRef {
+        let chunk_ref = ChunkRef {
+            hash: hash_sha256(chunk),
+            len: chunk.len() as u64,
+        };
+        self.total_size += chunk_ref.len;
+        self.chunks.push(chunk_ref);
+        chunk_ref
+    }
+
+    fn finish(self) -> ChunkManifest {
+        let joined = self
+            .chunks
+            .iter()
+            .map(|c| c.hash.to_string())
+            .collect::<String>();
+        ChunkManifest {
+            total_size: self.total_size,
+            file_hash: hash_sha256(&joined),
+            chunks: self.chunks,
+        }
+    }
+}
+
+/// Somewhere chunks can be stored and retrieved by their hash. Remote caches implement this trait
+/// to take part in chunked transfers.
+pub trait ChunkStore: Send + Sync {
+    /// Checks whether a chunk is already present in the store
+    fn contains(&self, hash: &Sha256) -> io::Result<bool>;
+
+    /// Stores a chunk
+    fn put(&self, hash: &Sha256, data: &[u8]) -> io::Result<()>;
+
+    /// Retrieves a chunk
+    fn get(&self, hash: &Sha256) -> io::Result<Vec<u8>>;
+}
+
+/// A chunk store backed by a directory on the local file system. Chunks are sharded into
+/// sub-directories by the first two characters of their hash.
+#[derive(Debug, Clone)]
+pub struct LocalChunkStore {
+    root: PathBuf,
+}
+
+impl LocalChunkStore {
+    /// Creates a new local chunk store at a given directory
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Creates a new local chunk store within the assemble cache
+    pub fn in_cache(cache: &super::AssembleCache) -> Self {
+        Self::new(cache.join("chunks"))
+    }
+
+    fn chunk_path(&self, hash: &Sha256) -> PathBuf {
+        let as_string = hash.to_string();
+        self.root.join(&as_string[..2]).join(&as_string)
+    }
+}
+
+impl ChunkStore for LocalChunkStore {
+    fn contains(&self, hash: &Sha256) -> io::Result<bool> {
+        Ok(self.chunk_path(hash).exists())
+    }
+
+    fn put(&self, hash: &Sha256, data: &[u8]) -> io::Result<()> {
+        let path = self.chunk_path(hash);
+        if path.exists() {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp = path.with_extension("partial");
+        std::fs::write(&temp, data)?;
+        std::fs::rename(temp, path)
+    }
+
+    fn get(&self, hash: &Sha256) -> io::Result<Vec<u8>> {
+        std::fs::read(self.chunk_path(hash))
+    }
+}
+
+/// Statistics about a chunked upload or download
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TransferStats {
+    /// Total number of chunks in the file
+    pub chunks_total: usize,
+    /// Number of chunks that actually had to be transferred
+    pub chunks_transferred: usize,
+    /// Total number of bytes in the file
+    pub bytes_total: u64,
+    /// Number of bytes that actually had to be transferred
+    pub bytes_transferred: u64,
+}
+
+impl TransferStats {
+    fn record(&mut self, len: u64, transferred: bool) {
+        self.chunks_total += 1;
+        self.bytes_total += len;
+        if transferred {
+            self.chunks_transferred += 1;
+            self.bytes_transferred += len;
+        }
+    }
+}
+
+/// Uploads a file to a chunk store. Only chunks not already within the store are written.
+pub fn upload_file<P: AsRef<Path>, S: ChunkStore + ?Sized>(
+    path: P,
+    store: &S,
+    config: ChunkerConfig,
+) -> io::Result<(ChunkManifest, TransferStats)> {
+    let file = File::open(path.as_ref())?;
+    let mut builder = ManifestBuilder::default();
+    let mut stats = TransferStats::default();
+
+    for chunk in Chunker::new(BufReader::new(file), config) {
+        let chunk = chunk?;
+        let chunk_ref = builder.push(&chunk);
+        let missing = !store.contains(&chunk_ref.hash)?;
+        if missing {
+            store.put(&chunk_ref.hash, &chunk)?;
+        }
+        stats.record(chunk_ref.len, missing);
+    }
+
+    let manifest = builder.finish();
+    debug!(
+        "uploaded {:?}: {}/{} chunks ({}/{} bytes) transferred",
+        path.as_ref(),
+        stats.chunks_transferred,
+        stats.chunks_total,
+        stats.bytes_transferred,
+        stats.bytes_total
+    );
+    Ok((manifest, stats))
+}
+
+/// Restores a file described by a manifest from a chunk store.
+///
+/// If a file already exists at `dest`, any chunks it shares with the manifest are reused instead
+/// of being fetched from the store. The file is written to a temporary location then moved into
+/// place, so `dest` is never left partially written.
+pub fn download_file<P: AsRef<Path>, S: ChunkStore + ?Sized>(
+    manifest: &ChunkManifest,
+    store: &S,
+    dest: P,
+    config: ChunkerConfig,
+) -> io::Result<TransferStats> {
+    let dest = dest.as_ref();
+    let mut local_chunks: HashMap<Sha256, (u64, u64)> = HashMap::new();
+    if dest.is_file() {
+        let mut offset = 0_u64;
+        for chunk in Chunker::new(BufReader::new(File::open(dest)?), config) {
+            let chunk = chunk?;
+            let len = chunk.len() as u64;
+            local_chunks
+                .entry(hash_sha256(&chunk))
+                .or_insert((offset, len));
+            offset += len;
+        }
+    }
+
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let temp_path = dest.with_extension("assemble-partial");
+    let mut stats = TransferStats::default();
+    {
+        let mut local = if local_chunks.is_empty() {
+            None
+        } else {
+            Some(File::open(dest)?)
+        };
+        let mut output = BufWriter::new(File::create(&temp_path)?);
+        for chunk_ref in manifest.chunks() {
+            let data = match (local_chunks.get(&chunk_ref.hash), local.as_mut()) {
+                (Some(&(offset, len)), Some(file)) => {
+                    let mut data = vec![0_u8; len as usize];
+                    file.seek(SeekFrom::Start(offset))?;
+                    file.read_exact(&mut data)?;
+                    stats.record(len, false);
+                    data
+                }
+                _ => {
+                    let data = store.get(&chunk_ref.hash)?;
+                    if hash_sha256(&data) != chunk_ref.hash {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("chunk {} is corrupt", chunk_ref.hash),
+                        ));
+                    }
+                    stats.record(chunk_ref.len, true);
+                    data
+                }
+            };
+            output.write_all(&data)?;
+        }
+        output.flush()?;
+    }
+    std::fs::rename(&temp_path, dest)?;
+    debug!(
+        "downloaded {:?}: {}/{} chunks ({}/{} bytes) transferred",
+        dest,
+        stats.chunks_transferred,
+        stats.chunks_total,
+        stats.bytes_transferred,
+        stats.bytes_total
+    );
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use tempfile::TempDir;
+
+    fn small_config() -> ChunkerConfig {
+        ChunkerConfig::new(64, 256, 1024)
+    }
+
+    fn random_bytes(len: usize, seed: u64) -> Vec<u8> {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        (0..len).map(|_| rng.gen()).collect()
+    }
+
+    fn chunk_all(data: &[u8]) -> Vec<Vec<u8>> {
+        Chunker::new(data, small_config())
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn chunks_respect_bounds() {
+        let data = random_bytes(64 * 1024, 1);
+        let chunks = chunk_all(&data);
+        assert_eq!(chunks.concat(), data);
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert!(chunk.len() >= 64 && chunk.len() <= 1024);
+        }
+    }
+
+    #[test]
+    fn insertion_only_changes_nearby_chunks() {
+        let data = random_bytes(64 * 1024, 2);
+        let mut edited = data.clone();
+        edited.splice(32 * 1024..32 * 1024, b"some inserted bytes".iter().cloned());
+
+        let before: Vec<_> = chunk_all(&data).iter().map(hash_sha256).collect();
+        let after: Vec<_> = chunk_all(&edited).iter().map(hash_sha256).collect();
+        let changed = after.iter().filter(|h| !before.contains(h)).count();
+        assert!(
+            changed <= 3,
+            "only chunks near the edit should change (changed = {changed})"
+        );
+    }
+
+    #[test]
+    fn upload_then_download_only_transfers_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = LocalChunkStore::new(temp_dir.path().join("store"));
+        let file = temp_dir.path().join("output.bin");
+
+        let data = random_bytes(64 * 1024, 3);
+        std::fs::write(&file, &data).unwrap();
+        let (manifest, stats) = upload_file(&file, &store, small_config()).unwrap();
+        assert_eq!(stats.chunks_total, stats.chunks_transferred);
+
+        let mut edited = data.clone();
+        edited[100] ^= 0xff;
+        std::fs::write(&file, &edited).unwrap();
+        let (_, stats) = upload_file(&file, &store, small_config()).unwrap();
+        assert!(stats.chunks_transferred < stats.chunks_total);
+
+        let stats = download_file(&manifest, &store, &file, small_config()).unwrap();
+        assert!(stats.chunks_transferred < stats.chunks_total);
+        assert_eq!(std::fs::read(&file).unwrap(), data);
+    }
+}
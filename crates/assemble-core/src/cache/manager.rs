@@ -0,0 +1,381 @@
+//! Manages the caches within the assemble cache directory, evicting entries so they don't grow
+//! forever.
+//!
+//! Each cache has a name and a layout version, and lives at `<cache dir>/<name>/v<version>`. When
+//! the layout of a cache changes its version is bumped, and the directories of older versions are
+//! deleted the next time caches are cleaned. The entries of a cache are the direct children of its
+//! directory.
+//!
+//! Uses of entries are appended to an access log within the cache. Entries that were never used
+//! through the log are considered last used when they were last modified. When a cache is cleaned,
+//! entries that haven't been used within the max age of the [`EvictionPolicy`](EvictionPolicy)
+//! are evicted, then the least recently used entries are evicted until the cache is under the max
+//! size. Only one process cleans the caches at a time.
+
+use crate::cache::AssembleCache;
+use crate::file_lock::FileLock;
+use crate::humanize;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+const ACCESS_LOG: &str = ".access.log";
+const CLEANUP_LOCK: &str = ".cleanup.lock";
+
+/// The cache of resolved dependencies
+pub const DEPENDENCY_CACHE: CacheDescriptor = CacheDescriptor::new("dependencies", 1);
+
+/// How the entries of a cache are evicted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvictionPolicy {
+    /// Entries that weren't used for longer than this are evicted
+    pub max_age: Option<Duration>,
+    /// Once a cache is larger than this many bytes, its least recently used entries are evicted
+    /// until it isn't
+    pub max_size: Option<u64>,
+}
+
+impl EvictionPolicy {
+    /// A policy that never evicts anything
+    pub const fn unbounded() -> Self {
+        Self {
+            max_age: None,
+            max_size: None,
+        }
+    }
+}
+
+impl Default for EvictionPolicy {
+    /// Evicts entries unused for 30 days, and keeps each cache under 5 GiB
+    fn default() -> Self {
+        Self {
+            max_age: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+            max_size: Some(5 * 1024 * 1024 * 1024),
+        }
+    }
+}
+
+/// Describes a cache within the cache directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheDescriptor {
+    name: &'static str,
+    version: u32,
+}
+
+impl CacheDescriptor {
+    /// Describes the cache with a name, at a version of its layout
+    pub const fn new(name: &'static str, version: u32) -> Self {
+        Self { name, version }
+    }
+
+    /// The name of the cache
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// The version of the layout of the cache
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+/// Manages the caches within a directory
+#[derive(Debug, Clone)]
+pub struct CacheManager {
+    root: PathBuf,
+}
+
+impl Default for CacheManager {
+    /// Manages the caches in the assemble cache
+    fn default() -> Self {
+        Self::new(AssembleCache::default())
+    }
+}
+
+impl CacheManager {
+    /// Creates a manager of the caches within a directory
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// The directory containing the caches
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The directory of a cache, without creating it
+    pub fn cache_dir(&self, cache: &CacheDescriptor) -> PathBuf {
+        self.root
+            .join(cache.name)
+            .join(format!("v{}", cache.version))
+    }
+
+    /// Gets a cache, creating its directory if it doesn't exist
+    pub fn cache(&self, cache: &CacheDescriptor) -> io::Result<Cache> {
+        let dir = self.cache_dir(cache);
+        std::fs::create_dir_all(&dir)?;
+        Ok(Cache { dir })
+    }
+
+    /// Cleans every cache, deleting old versions of their layouts and evicting entries according
+    /// to a policy. Returns `None` without cleaning anything if another process is already
+    /// cleaning the caches.
+    pub fn clean(&self, policy: &EvictionPolicy) -> io::Result<Option<CleanupReport>> {
+        self.clean_at(policy, SystemTime::now())
+    }
+
+    fn clean_at(
+        &self,
+        policy: &EvictionPolicy,
+        now: SystemTime,
+    ) -> io::Result<Option<CleanupReport>> {
+        if !self.root.exists() {
+            return Ok(Some(CleanupReport::default()));
+        }
+        let _lock = match FileLock::try_acquire(self.root.join(CLEANUP_LOCK), "clean caches")? {
+            Some(lock) => lock,
+            None => {
+                info!("caches in {:?} are already being cleaned", self.root);
+                return Ok(None);
+            }
+        };
+
+        let mut report = CleanupReport::default();
+        for cache in std::fs::read_dir(&self.root)? {
+            let cache = cache?.path();
+            if !cache.is_dir() {
+                continue;
+            }
+            let mut versions = std::fs::read_dir(&cache)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<_>>>()?
+                .into_iter()
+                .filter_map(|path| {
+                    let version = path
+                        .file_name()?
+                        .to_str()?
+                        .strip_prefix('v')?
+                        .parse::<u32>()
+                        .ok()?;
+                    Some((version, path))
+                })
+                .collect::<Vec<_>>();
+            versions.sort();
+
+            if let Some((_, newest)) = versions.pop() {
+                for (_, old) in versions {
+                    debug!("removing old cache layout {:?}", old);
+                    report.remove(&old)?;
+                }
+                Cache { dir: newest }.evict(policy, now, &mut report)?;
+            }
+        }
+        Ok(Some(report))
+    }
+}
+
+/// A cache within the cache directory
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// The directory of the cache
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Gets the path of an entry of this cache, recording that the entry was used
+    pub fn entry(&self, name: &str) -> io::Result<PathBuf> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(ACCESS_LOG))?;
+        log.write_all(format!("{} {}\n", now, name).as_bytes())?;
+        Ok(self.dir.join(name))
+    }
+
+    /// When each entry was last used, according to the access log
+    fn recorded_uses(&self) -> io::Result<HashMap<String, SystemTime>> {
+        let mut uses = HashMap::new();
+        let log = match File::open(self.dir.join(ACCESS_LOG)) {
+            Ok(log) => log,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(uses),
+            Err(e) => return Err(e),
+        };
+        for line in BufReader::new(log).lines() {
+            let line = line?;
+            if let Some((secs, name)) = line.split_once(' ') {
+                if let Ok(secs) = secs.parse::<u64>() {
+                    let used = UNIX_EPOCH + Duration::from_secs(secs);
+                    let last = uses.entry(name.to_string()).or_insert(used);
+                    *last = (*last).max(used);
+                }
+            }
+        }
+        Ok(uses)
+    }
+
+    fn evict(
+        &self,
+        policy: &EvictionPolicy,
+        now: SystemTime,
+        report: &mut CleanupReport,
+    ) -> io::Result<()> {
+        let recorded = self.recorded_uses()?;
+        let mut entries = vec![];
+        for entry in std::fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+            let last_used = match recorded.get(&name) {
+                Some(used) => *used,
+                None => entry.metadata()?.modified().unwrap_or(UNIX_EPOCH),
+            };
+            entries.push((last_used, name, size_of(&entry.path())));
+        }
+        entries.sort();
+
+        let mut size: u64 = entries.iter().map(|(_, _, size)| size).sum();
+        let mut compacted = String::new();
+        for (last_used, name, entry_size) in entries {
+            let expired = policy.max_age.map_or(false, |max_age| {
+                now.duration_since(last_used).unwrap_or_default() > max_age
+            });
+            let too_large = policy.max_size.map_or(false, |max_size| size > max_size);
+            if expired || too_large {
+                debug!("evicting {:?} from {:?}", name, self.dir);
+                report.remove(&self.dir.join(&name))?;
+                size -= entry_size;
+            } else if recorded.contains_key(&name) {
+                let secs = last_used.duration_since(UNIX_EPOCH).unwrap_or_default();
+                compacted.push_str(&format!("{} {}\n", secs.as_secs(), name));
+            }
+        }
+        std::fs::write(self.dir.join(ACCESS_LOG), compacted)
+    }
+}
+
+/// The total size of the files at a path
+fn size_of(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// What was removed when caches were cleaned
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CleanupReport {
+    /// The paths that were removed
+    pub removed: Vec<PathBuf>,
+    /// The number of bytes freed
+    pub freed: u64,
+}
+
+impl CleanupReport {
+    fn remove(&mut self, path: &Path) -> io::Result<()> {
+        let size = size_of(path);
+        if path.is_dir() {
+            std::fs::remove_dir_all(path)?;
+        } else {
+            std::fs::remove_file(path)?;
+        }
+        self.removed.push(path.to_path_buf());
+        self.freed += size;
+        Ok(())
+    }
+}
+
+impl Display for CleanupReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "removed {}, freeing {}",
+            humanize::count(self.removed.len() as u64, "cache path"),
+            humanize::bytes(self.freed)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const CACHE: CacheDescriptor = CacheDescriptor::new("test", 2);
+
+    #[test]
+    fn old_layouts_and_unused_entries_are_evicted() {
+        let dir = TempDir::new().unwrap();
+        let manager = CacheManager::new(dir.path());
+        let cache = manager.cache(&CACHE).unwrap();
+        assert_eq!(cache.dir(), dir.path().join("test").join("v2"));
+        std::fs::create_dir_all(dir.path().join("test").join("v1")).unwrap();
+
+        std::fs::write(cache.entry("small").unwrap(), [0; 10]).unwrap();
+        std::fs::write(cache.entry("large").unwrap(), [0; 100]).unwrap();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        std::fs::write(
+            cache.dir().join(ACCESS_LOG),
+            format!(
+                "{} large\n{} small\n",
+                now.as_secs() - 100,
+                now.as_secs() - 50
+            ),
+        )
+        .unwrap();
+
+        let policy = EvictionPolicy {
+            max_age: None,
+            max_size: Some(50),
+        };
+        let report = manager.clean(&policy).unwrap().unwrap();
+        assert_eq!(
+            report.removed,
+            [
+                dir.path().join("test").join("v1"),
+                cache.dir().join("large")
+            ]
+        );
+        assert_eq!(report.freed, 100);
+        assert!(cache.dir().join("small").exists());
+
+        let policy = EvictionPolicy {
+            max_age: Some(Duration::from_secs(60)),
+            max_size: None,
+        };
+        let later = SystemTime::now() + Duration::from_secs(120);
+        let report = manager.clean_at(&policy, later).unwrap().unwrap();
+        assert_eq!(report.removed, [cache.dir().join("small")]);
+    }
+
+    #[test]
+    fn only_one_process_cleans() {
+        let dir = TempDir::new().unwrap();
+        let manager = CacheManager::new(dir.path());
+        let lock = FileLock::try_acquire(dir.path().join(CLEANUP_LOCK), "test")
+            .unwrap()
+            .unwrap();
+        assert_eq!(manager.clean(&EvictionPolicy::default()).unwrap(), None);
+        drop(lock);
+        assert!(manager.clean(&EvictionPolicy::default()).unwrap().is_some());
+    }
+}
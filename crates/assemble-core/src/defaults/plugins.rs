@@ -1,25 +1,38 @@
-use crate::defaults::tasks::{Help, TaskReport, WrapperTask};
+use crate::defaults::tasks::{
+    CleanTaskOutputs, DependencyReport, Empty, Help, TaskReport, WrapperTask,
+};
 use crate::dependencies::project_dependency::ProjectDependencyPlugin;
+use crate::error::PayloadError;
 use crate::plugins::{Plugin, PluginAware};
-use crate::project::error::ProjectResult;
+use crate::project::error::{ProjectError, ProjectResult};
 
 use crate::project::GetProjectId;
+use crate::task::task_rule::TaskRule;
+use crate::task::TaskHandle;
 use crate::Project;
 
 /// The base plugin is applied to every project and supplies only needed tasks.
 ///
 /// # Provided Tasks
 /// - `tasks`: lists the available tasks in this project
+/// - `dependencies`: lists the resolved dependencies of each configuration in this project
+///
+/// # Task Rules
+/// - `clean<TaskName>`: deletes the declared outputs of a task
 #[derive(Default)]
 pub struct BasePlugin;
 
 /// The name of the task that reports all tasks in a project.
 pub const TASKS_REPORT_TASK_NAME: &str = "tasks";
+/// The name of the task that reports the resolved dependencies of a project.
+pub const DEPENDENCIES_REPORT_TASK_NAME: &str = "dependencies";
 /// The name of the task that provides help information for the project
 pub const HELP_TASK_NAME: &str = "help";
 /// The name of the task that can create a wrapper for running assemble projects. Only present in the
 /// root project
 pub const WRAPPER_TASK_NAME: &str = "wrapper";
+/// The pattern of the task rule that creates tasks deleting the outputs of another task
+pub const CLEAN_TASK_RULE_PATTERN: &str = "clean<TaskName>";
 /// The assemble group are tasks that are important for the operation of an assemble project
 pub const ASSEMBLE_GROUP: &str = "assemble";
 
@@ -32,6 +45,15 @@ impl Plugin<Project> for BasePlugin {
                 tasks.set_group(ASSEMBLE_GROUP);
                 Ok(())
             })?;
+        project
+            .task_container_mut()
+            .register_task_with::<DependencyReport, _>(
+                DEPENDENCIES_REPORT_TASK_NAME,
+                |task, _| {
+                    task.set_group(ASSEMBLE_GROUP);
+                    Ok(())
+                },
+            )?;
         let mut help = project
             .task_container_mut()
             .register_task::<Help>(HELP_TASK_NAME)?;
@@ -50,17 +72,127 @@ impl Plugin<Project> for BasePlugin {
                 })?;
         }
 
+        project.task_container_mut().add_rule(TaskRule::new(
+            CLEAN_TASK_RULE_PATTERN,
+            "Deletes the outputs of a task",
+            |name, target, project| {
+                let target = project
+                    .task_id_factory()
+                    .create(target)
+                    .map_err(PayloadError::new)?;
+                if project.task_container().get_task(&target).is_none() {
+                    return Ok(());
+                }
+                project
+                    .task_container_mut()
+                    .register_task_with::<CleanTaskOutputs, _>(name, move |task, _| {
+                        task.set_description(&format!("Deletes the outputs of {}", target));
+                        task.set_target(target);
+                        Ok(())
+                    })?;
+                Ok(())
+            },
+        ));
+
         project.apply_plugin::<ProjectDependencyPlugin>()?;
         Ok(())
     }
 }
 
+/// The lifecycle base plugin registers the conventional lifecycle tasks of a project. Lifecycle
+/// tasks have no actions of their own, and only depend on the tasks that plugins attach to them.
+/// Plugins for languages apply this plugin, and attach their tasks to `assemble` and `check`
+/// instead of registering their own entry points.
+///
+/// # Provided Tasks
+/// - `assemble`: builds the outputs of the project
+/// - `check`: runs all verification tasks of the project
+/// - `build`: assembles and checks the project
+/// - `clean`: deletes the outputs of the project
+///
+/// # Example
+/// ```
+/// # use assemble_core::Project;
+/// use assemble_core::defaults::plugins::LifecycleBasePlugin;
+/// use assemble_core::defaults::tasks::Empty;
+/// use assemble_core::plugins::PluginAware;
+/// # let project = Project::temp(None);
+/// project.with_mut(|p| -> assemble_core::project::error::ProjectResult {
+///     p.apply_plugin::<LifecycleBasePlugin>()?;
+///     let lint = p.task_container_mut().register_task::<Empty>("lint")?;
+///     LifecycleBasePlugin::task(p, LifecycleBasePlugin::CHECK)?.configure_with(|check, _| {
+///         check.depends_on(lint);
+///         Ok(())
+///     })
+/// }).unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct LifecycleBasePlugin;
+
+impl LifecycleBasePlugin {
+    pub const ASSEMBLE: &'static str = "assemble";
+    pub const CHECK: &'static str = "check";
+    pub const BUILD: &'static str = "build";
+    pub const CLEAN: &'static str = "clean";
+    pub const BUILD_GROUP: &'static str = "build";
+    pub const VERIFICATION_GROUP: &'static str = "verification";
+
+    /// Gets one of the lifecycle tasks of a project, to attach tasks to it.
+    ///
+    /// # Error
+    /// Errors if the project has no lifecycle task with this name, which happens when this plugin
+    /// hasn't been applied to it.
+    pub fn task(project: &Project, name: &str) -> ProjectResult<TaskHandle<Empty>> {
+        let id = project
+            .task_id_factory()
+            .create(name)
+            .map_err(PayloadError::new)?;
+        project
+            .task_container()
+            .get_task(&id)
+            .and_then(|handle| handle.as_type::<Empty>())
+            .ok_or_else(|| {
+                ProjectError::custom(format!("no lifecycle task {} in {}", name, project)).into()
+            })
+    }
+}
+
+impl Plugin<Project> for LifecycleBasePlugin {
+    fn apply_to(&self, project: &mut Project) -> ProjectResult {
+        let container = project.task_container_mut();
+        let assemble = container.register_task_with::<Empty, _>(Self::ASSEMBLE, |t, _| {
+            t.set_description("Assembles the outputs of the project");
+            t.set_group(Self::BUILD_GROUP);
+            Ok(())
+        })?;
+        let check = container.register_task_with::<Empty, _>(Self::CHECK, |t, _| {
+            t.set_description("Runs all verification tasks of the project");
+            t.set_group(Self::VERIFICATION_GROUP);
+            Ok(())
+        })?;
+        container.register_task_with::<Empty, _>(Self::BUILD, move |t, _| {
+            t.set_description("Assembles and checks the project");
+            t.set_group(Self::BUILD_GROUP);
+            t.depends_on(assemble);
+            t.depends_on(check);
+            Ok(())
+        })?;
+        container.register_task_with::<Empty, _>(Self::CLEAN, |t, _| {
+            t.set_description("Deletes the outputs of the project");
+            t.set_group(Self::BUILD_GROUP);
+            Ok(())
+        })?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
-    use crate::defaults::plugins::TASKS_REPORT_TASK_NAME;
-    use crate::defaults::tasks::TaskReport;
+    use crate::defaults::plugins::{LifecycleBasePlugin, TASKS_REPORT_TASK_NAME};
+    use crate::defaults::tasks::{CleanTaskOutputs, TaskReport};
     use crate::identifier::TaskId;
+    use crate::plugins::PluginAware;
     use crate::project::finder::TaskFinder;
     use crate::Project;
 
@@ -81,4 +213,37 @@ mod tests {
             TASKS_REPORT_TASK_NAME
         );
     }
+
+    #[test]
+    fn clean_rule_creates_tasks() {
+        let project = Project::temp(None);
+        let handle = project
+            .find_task("cleanTasks")
+            .expect("cleanTasks not created");
+        let clean = handle.as_type::<CleanTaskOutputs>();
+        assert!(
+            clean.is_some(),
+            "could not get cleanTasks as CleanTaskOutputs"
+        );
+        assert!(project.find_task("cleanMissing").is_err());
+    }
+
+    #[test]
+    fn lifecycle_tasks() {
+        let project = Project::temp(None);
+        project.with_mut(|p| p.apply_plugin::<LifecycleBasePlugin>().unwrap());
+        for name in [
+            LifecycleBasePlugin::ASSEMBLE,
+            LifecycleBasePlugin::CHECK,
+            LifecycleBasePlugin::BUILD,
+            LifecycleBasePlugin::CLEAN,
+        ] {
+            project
+                .with(|p| LifecycleBasePlugin::task(p, name))
+                .unwrap();
+        }
+
+        let finder = TaskFinder::new(&project);
+        assert!(finder.find("build").unwrap().is_some());
+    }
 }
@@ -6,12 +6,16 @@ use crate::{BuildResult, Executable, Project, Task};
 use std::collections::HashMap;
 use std::fmt::Debug;
 
+mod clean_outputs;
+mod dependency_report;
 mod help;
 mod tasks_report;
 mod wrapper;
 
 use crate::task::create_task::CreateTask;
 use crate::task::initialize_task::InitializeTask;
+pub use clean_outputs::CleanTaskOutputs;
+pub use dependency_report::DependencyReport;
 pub use help::Help;
 pub use tasks_report::TaskReport;
 pub use wrapper::WrapperTask;
@@ -0,0 +1,74 @@
+use crate::__export::TaskId;
+use crate::exception::BuildException;
+use crate::lazy_evaluation::Provider;
+use crate::task::initialize_task::InitializeTask;
+use crate::task::task_io::TaskIO;
+use crate::task::up_to_date::UpToDate;
+use crate::task::work_handler::history::{history_key, open_store};
+use crate::task::ExecutableTask;
+use crate::workspace::Workspace;
+use crate::{BuildResult, Executable, Project, Task};
+use log::{debug, info};
+use std::fs;
+
+/// Deletes the declared outputs and the temporary directory of another task, along with its
+/// execution history so that it's out of date the next time it runs. Created by the `clean<TaskName>` task rule.
+#[derive(Debug, Default)]
+pub struct CleanTaskOutputs {
+    target: Option<TaskId>,
+}
+
+impl CleanTaskOutputs {
+    /// The task whose outputs are deleted
+    pub fn target(&self) -> Option<&TaskId> {
+        self.target.as_ref()
+    }
+
+    /// Sets the task whose outputs are deleted
+    pub fn set_target(&mut self, target: TaskId) {
+        self.target = Some(target);
+    }
+}
+
+impl UpToDate for CleanTaskOutputs {}
+
+impl InitializeTask for CleanTaskOutputs {}
+
+impl TaskIO for CleanTaskOutputs {}
+
+impl Task for CleanTaskOutputs {
+    fn task_action(task: &mut Executable<Self>, project: &Project) -> BuildResult {
+        let target = task
+            .target
+            .clone()
+            .ok_or_else(|| BuildException::custom("no target task to clean"))?;
+        let mut handle = project
+            .task_container()
+            .get_task(&target)
+            .cloned()
+            .ok_or_else(|| BuildException::custom(&format!("no task named {}", target)))?;
+        let full_task = handle.resolve(project)?;
+
+        let temp_dir = Workspace::new(project.build_dir().get()).temp_dir_path(&target);
+        for output in full_task.declared_outputs().into_iter().chain([temp_dir]) {
+            if output.is_dir() {
+                fs::remove_dir_all(&output).map_err(BuildException::from)?;
+            } else if output.exists() {
+                fs::remove_file(&output).map_err(BuildException::from)?;
+            } else {
+                continue;
+            }
+            info!("deleted {:?}", output);
+        }
+
+        let history = project.root_dir().join(".assemble").join("task-cache");
+        debug!(
+            "removing execution history of {} from {:?}",
+            target, history
+        );
+        open_store(history)
+            .and_then(|store| store.remove(&history_key(&target)))
+            .map_err(BuildException::from)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use crate::__export::TaskId;
+use crate::error::PayloadError;
+use crate::exception::BuildException;
+use crate::project::error::ProjectResult;
+use crate::task::create_task::CreateTask;
+use crate::task::flags::{OptionDeclarationBuilder, OptionDeclarations, OptionsDecoder};
+use crate::task::initialize_task::InitializeTask;
+use crate::task::task_io::TaskIO;
+use crate::task::up_to_date::UpToDate;
+use crate::{BuildResult, Executable, Project, Task};
+use colored::Colorize;
+use log::info;
+
+/// Reports the dependencies of the configurations in a project, and the versions selected for them.
+#[derive(Debug)]
+pub struct DependencyReport {
+    configuration: Option<String>,
+}
+
+impl UpToDate for DependencyReport {}
+
+impl InitializeTask for DependencyReport {}
+
+impl CreateTask for DependencyReport {
+    fn new(_using_id: &TaskId, _project: &Project) -> ProjectResult<Self> {
+        Ok(Self {
+            configuration: None,
+        })
+    }
+
+    fn description() -> String {
+        "Lists the resolved dependencies of each configuration in a project".to_string()
+    }
+
+    fn only_in_current() -> bool {
+        true
+    }
+
+    fn options_declarations() -> Option<OptionDeclarations> {
+        Some(OptionDeclarations::new::<DependencyReport, _>([
+            OptionDeclarationBuilder::<String>::new("configuration")
+                .optional(true)
+                .use_from_str()
+                .build(),
+        ]))
+    }
+
+    fn try_set_from_decoder(&mut self, decoder: &OptionsDecoder) -> ProjectResult<()> {
+        self.configuration = decoder
+            .get_value::<String>("configuration")
+            .map_err(PayloadError::new)?;
+        Ok(())
+    }
+}
+
+impl TaskIO for DependencyReport {}
+
+impl Task for DependencyReport {
+    fn task_action(task: &mut Executable<Self>, project: &Project) -> BuildResult {
+        let configurations = project.configurations();
+        let names = match &task.configuration {
+            Some(name) => {
+                configurations.get(name).ok_or_else(|| {
+                    BuildException::custom(&format!(
+                        "no configuration named {:?} in {}",
+                        name, project
+                    ))
+                })?;
+                vec![name.as_str()]
+            }
+            None => configurations.names(),
+        };
+
+        if names.is_empty() {
+            info!("No configurations");
+            return Ok(());
+        }
+
+        for name in names {
+            let configuration = configurations.get(name).unwrap();
+            info!("{}", name.underline());
+            match configuration.resolved() {
+                Ok(resolved) if resolved.results().is_empty() => {
+                    info!("  {}", "No dependencies".dimmed());
+                }
+                Ok(resolved) => {
+                    for result in resolved.results() {
+                        info!("  {}", result);
+                    }
+                }
+                Err(e) => {
+                    info!("  {}", format!("could not resolve: {}", e).red());
+                }
+            }
+            info!("");
+        }
+        Ok(())
+    }
+}
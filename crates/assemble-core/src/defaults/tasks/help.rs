@@ -62,8 +62,25 @@ impl TaskIO for Help {}
 
 impl Task for Help {
     fn task_action(task: &mut Executable<Self>, project: &Project) -> BuildResult {
-        if let Some(_task_request) = &task.task_request {
-            Err(BuildException::custom("help for task requests not implemented").into())
+        if let Some(task_request) = &task.task_request {
+            let ids = TaskFinder::new(&project.as_shared())
+                .find(TaskPath::new(task_request))?
+                .ok_or_else(|| {
+                    BuildException::custom(&format!("no task found for {:?}", task_request))
+                })?;
+
+            let mut text_factory = AssembleFormatter::default();
+            for id in ids {
+                if id == task.task_id() {
+                    describe_task(&mut text_factory, &*task)?;
+                } else {
+                    let mut handle = project.task_container().get_task(&id).unwrap().clone();
+                    let full_task = handle.resolve(project)?;
+                    describe_task(&mut text_factory, &*full_task)?;
+                }
+            }
+            info!("{}", text_factory);
+            Ok(())
         } else {
             let mut text_factory = AssembleFormatter::default();
 
@@ -99,3 +116,38 @@ impl Task for Help {
         }
     }
 }
+
+/// Writes the description, group, options and actions of a task
+fn describe_task<E: ExecutableTask + ?Sized>(
+    text_factory: &mut AssembleFormatter,
+    task: &E,
+) -> BuildResult {
+    writeln!(text_factory.important(), "* Task {}", task.task_id())?;
+    let description = task.description();
+    if !description.is_empty() {
+        writeln!(text_factory, "{}", description)?;
+    }
+    let group = task.group();
+    if !group.is_empty() {
+        writeln!(text_factory, "Group: {}", group)?;
+    }
+    if let Some(options) = task.options_declarations() {
+        let mut list = TextListFactory::new(less_important_string("> ".yellow()));
+        let mut options = options.values().collect::<Vec<_>>();
+        options.sort_by(|left, right| left.flag().cmp(right.flag()));
+        for option in options {
+            list = list.element(format!("--{} {}", option.flag(), option.help()));
+        }
+        writeln!(text_factory, "Options:")?;
+        write!(text_factory, "{}", list.finish())?;
+    }
+
+    let mut list = TextListFactory::new(less_important_string("> ".yellow()));
+    for name in task.action_names() {
+        list = list.element(name.unwrap_or_else(|| "(unnamed)".to_string()));
+    }
+    writeln!(text_factory, "Actions, in the order they run:")?;
+    write!(text_factory, "{}", list.finish())?;
+    writeln!(text_factory)?;
+    Ok(())
+}
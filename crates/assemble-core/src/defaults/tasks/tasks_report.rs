@@ -160,6 +160,20 @@ impl Task for TaskReport {
             }
         }
 
+        let rules = container.rules();
+        if !rules.is_empty() && task.groups.is_none() {
+            info!("{}", "Rules:".underline());
+            for rule in rules {
+                let description = if rule.description().is_empty() {
+                    "".to_string()
+                } else {
+                    format!(" - {}", rule.description().yellow())
+                };
+                info!("  {}{}", rule.pattern().green().bold(), description);
+            }
+            info!("");
+        }
+
         Ok(())
     }
 }
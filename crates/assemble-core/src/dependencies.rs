@@ -9,7 +9,10 @@ pub mod artifact_request;
 pub mod configurations;
 pub mod dependency_container;
 pub mod file_dependency;
+pub mod locking;
 pub mod project_dependency;
+pub mod resolution_strategy;
+pub mod substitution;
 
 pub use dependency_type::*;
 pub use registry_container::*;
@@ -1,6 +1,11 @@
 //! A configuration has two states: resolved and unresolved
 
 use crate::__export::TaskId;
+use crate::dependencies::locking::DependencyLocking;
+use crate::dependencies::resolution_strategy::{
+    DependencyResult, ResolutionStrategy, SelectionReason,
+};
+use crate::dependencies::substitution::DependencySubstitutions;
 use crate::dependencies::{
     AcquisitionError, Dependency, IntoDependency, RegistryContainer, ResolvedDependency,
 };
@@ -13,7 +18,7 @@ use crate::project::buildable::{Buildable, BuildableObject, BuiltByContainer, Ge
 
 use crate::Project;
 use once_cell::sync::OnceCell;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
@@ -26,7 +31,12 @@ pub struct Configuration {
 
 impl Configuration {
     /// Create a new configuration
-    pub(crate) fn new(name: &str, registry_container: &Arc<Mutex<RegistryContainer>>) -> Self {
+    pub(crate) fn new(
+        name: &str,
+        registry_container: &Arc<Mutex<RegistryContainer>>,
+        locking: &DependencyLocking,
+        substitutions: &DependencySubstitutions,
+    ) -> Self {
         Self {
             inner: Arc::new(Mutex::new(ConfigurationInner {
                 name: name.to_string(),
@@ -35,6 +45,9 @@ impl Configuration {
                 resolved: OnceCell::new(),
                 built_by: OnceCell::new(),
                 registry_container: registry_container.clone(),
+                locking: locking.clone(),
+                substitutions: substitutions.clone(),
+                strategy: ResolutionStrategy::default(),
             })),
         }
     }
@@ -52,6 +65,20 @@ impl Configuration {
         (func)(&mut inner)
     }
 
+    /// The name of this configuration
+    pub fn name(&self) -> String {
+        self.inner(|inner| inner.name.clone())
+    }
+
+    /// Configures how version conflicts are resolved in this configuration. See
+    /// [`resolution_strategy`](crate::dependencies::resolution_strategy).
+    pub fn resolution_strategy<R, F: FnOnce(&mut ResolutionStrategy) -> R>(
+        &mut self,
+        configure: F,
+    ) -> R {
+        self.inner_mut(|inner| configure(&mut inner.strategy))
+    }
+
     /// Gets the resolved form of this configuration.
     ///
     /// If the configuration hasn't been resolved yet, resolves it at this point.
@@ -107,6 +134,9 @@ struct ConfigurationInner {
     built_by: OnceCell<BuildableObject>,
 
     registry_container: Arc<Mutex<RegistryContainer>>,
+    locking: DependencyLocking,
+    substitutions: DependencySubstitutions,
+    strategy: ResolutionStrategy,
 }
 
 impl ConfigurationInner {
@@ -114,7 +144,21 @@ impl ConfigurationInner {
         self.resolved
             .get_or_try_init(|| {
                 let mut resolved = vec![];
-                let dependencies = self.dependencies.drain(..).collect::<Vec<_>>();
+                let strategy = &self.strategy;
+                let dependencies = self
+                    .dependencies
+                    .drain(..)
+                    .map(|dependency| self.substitutions.apply(dependency))
+                    .map(|dependency| {
+                        match strategy
+                            .forced_version(&dependency.id())
+                            .and_then(|version| dependency.with_version(version))
+                        {
+                            Some(forced) => forced,
+                            None => dependency,
+                        }
+                    })
+                    .collect::<Vec<_>>();
 
                 let mut built_by = BuiltByContainer::new();
 
@@ -129,7 +173,7 @@ impl ConfigurationInner {
                     for registry in registry_c.supported_registries(&dependency.dep_type()) {
                         match dependency.try_resolve(registry, registry_c.cache_location()) {
                             Ok(resolved_dep) => {
-                                resolved.push(resolved_dep);
+                                resolved.push((dependency.id(), resolved_dep));
 
                                 found = true;
                                 continue 'outer;
@@ -145,18 +189,75 @@ impl ConfigurationInner {
                     }
                 }
 
+                let (dependencies, results) =
+                    select_versions(&self.name, strategy, &self.locking, resolved)?;
+
                 self.built_by
                     .set(BuildableObject::from(built_by))
                     .expect("Shouldn't be set");
 
                 Ok(ResolvedConfiguration {
-                    dependencies: resolved,
+                    dependencies,
+                    results,
                 })
             })
             .map(|res| res.clone())
     }
 }
 
+/// Resolves version conflicts between resolved dependencies with the same id, dropping every
+/// dependency that wasn't selected. Selected versions are checked against the lock file.
+fn select_versions(
+    configuration: &str,
+    strategy: &ResolutionStrategy,
+    locking: &DependencyLocking,
+    resolved: Vec<(String, ResolvedDependency)>,
+) -> Result<(Vec<ResolvedDependency>, Vec<DependencyResult>), AcquisitionError> {
+    let mut versions: HashMap<&str, Vec<String>> = HashMap::new();
+    for (id, dependency) in &resolved {
+        if let Some(version) = dependency.version() {
+            versions
+                .entry(id.as_str())
+                .or_default()
+                .push(version.to_string());
+        }
+    }
+    let mut selected = HashMap::new();
+    for (id, versions) in versions {
+        let selection = strategy.select(configuration, id, &versions)?;
+        locking.check(configuration, id, Some(&selection.version))?;
+        selected.insert(id.to_string(), selection);
+    }
+
+    let mut results: Vec<DependencyResult> = vec![];
+    let mut dependencies = vec![];
+    for (id, dependency) in resolved {
+        match selected.get(&id) {
+            Some(selection) => {
+                if dependency.version() != Some(selection.version.as_str()) {
+                    debug!(
+                        "dropping {} {:?} in favor of {}",
+                        id,
+                        dependency.version(),
+                        selection.version
+                    );
+                    continue;
+                }
+                if !results.iter().any(|result| result.id() == id) {
+                    results.push(DependencyResult::new(
+                        &id,
+                        Some(&selection.version),
+                        selection.reason.clone(),
+                    ));
+                }
+            }
+            None => results.push(DependencyResult::new(&id, None, SelectionReason::Requested)),
+        }
+        dependencies.push(dependency);
+    }
+    Ok((dependencies, results))
+}
+
 impl Buildable for ConfigurationInner {
     /// The dependencies to resolve this configuration
     fn get_dependencies(&self, project: &Project) -> ProjectResult<HashSet<TaskId>> {
@@ -167,7 +268,10 @@ impl Buildable for ConfigurationInner {
                 let mut output = HashSet::new();
                 for dep in &self.dependencies {
                     trace!("Getting dependencies for dependency: {:#?}", dep);
-                    let buildable = dep.as_buildable();
+                    let buildable = match self.substitutions.project_for(dep.as_ref()) {
+                        Some(project) => project.as_buildable(),
+                        None => dep.as_buildable(),
+                    };
                     output.extend(buildable.get_dependencies(project)?);
                 }
                 Ok(output)
@@ -195,6 +299,15 @@ impl Display for ConfigurationInner {
 #[derive(Debug, Clone)]
 pub struct ResolvedConfiguration {
     dependencies: Vec<ResolvedDependency>,
+    results: Vec<DependencyResult>,
+}
+
+impl ResolvedConfiguration {
+    /// The result of resolving each dependency of the configuration, in the order they were
+    /// added
+    pub fn results(&self) -> &[DependencyResult] {
+        &self.results
+    }
 }
 
 impl Display for ResolvedConfiguration {
@@ -1,4 +1,6 @@
 use crate::dependencies::configurations::Configuration;
+use crate::dependencies::locking::DependencyLocking;
+use crate::dependencies::substitution::DependencySubstitutions;
 use crate::dependencies::RegistryContainer;
 use crate::identifier::ProjectId;
 
@@ -10,6 +12,8 @@ pub struct ConfigurationHandler {
     owner: ProjectId,
     registries: Arc<Mutex<RegistryContainer>>,
     configurations: HashMap<String, Configuration>,
+    locking: DependencyLocking,
+    substitutions: DependencySubstitutions,
 }
 
 unsafe impl Send for ConfigurationHandler {}
@@ -17,10 +21,21 @@ unsafe impl Sync for ConfigurationHandler {}
 
 impl ConfigurationHandler {
     pub(crate) fn new(owner: ProjectId, registries: &Arc<Mutex<RegistryContainer>>) -> Self {
+        Self::with_locking(owner, registries, DependencyLocking::disabled())
+    }
+
+    /// Creates a configuration handler where configurations are checked against a lock file
+    pub(crate) fn with_locking(
+        owner: ProjectId,
+        registries: &Arc<Mutex<RegistryContainer>>,
+        locking: DependencyLocking,
+    ) -> Self {
         Self {
             owner,
             registries: registries.clone(),
             configurations: Default::default(),
+            locking,
+            substitutions: registries.lock().unwrap().substitutions().clone(),
         }
     }
 
@@ -34,7 +49,10 @@ impl ConfigurationHandler {
         }
 
         self.configurations
-            .insert(name.to_string(), Configuration::new(name, &self.registries));
+            .insert(
+                name.to_string(),
+                Configuration::new(name, &self.registries, &self.locking, &self.substitutions),
+            );
         self.get_mut(name).unwrap()
     }
 
@@ -62,21 +80,48 @@ impl ConfigurationHandler {
         self.configurations.get_mut(name.as_ref())
     }
 
+    /// The names of the configurations in this handler, in sorted order
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = self
+            .configurations
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
     /// Get the owner of this handler
     pub fn owner(&self) -> &ProjectId {
         &self.owner
     }
+
+    /// Get the dependency locking state used by configurations in this handler
+    pub fn locking(&self) -> &DependencyLocking {
+        &self.locking
+    }
+
+    /// Get the dependency substitutions used by configurations in this handler. These are the same
+    /// substitutions as the [`RegistryContainer`](RegistryContainer) of this handler.
+    pub fn substitutions(&self) -> &DependencySubstitutions {
+        &self.substitutions
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use crate::dependencies::RegistryContainer;
+    use crate::dependencies::file_dependency::FILE_SYSTEM_TYPE;
+    use crate::dependencies::resolution_strategy::{ResolutionStrategy, SelectionReason};
+    use crate::dependencies::{
+        AcquisitionError, Dependency, DependencyType, Registry, RegistryContainer,
+        ResolvedDependency, ResolvedDependencyBuilder,
+    };
     use crate::file_collection::FileCollection;
     use crate::flow::output::SinglePathOutputTask;
 
-    use crate::project::buildable::Buildable;
+    use crate::project::buildable::{Buildable, BuildableObject, GetBuildable};
 
     use crate::task::create_task::CreateTask;
 
@@ -87,7 +132,7 @@ mod tests {
     use crate::{BuildResult, Executable, Project, Task};
     use std::collections::HashSet;
     use std::fmt::Debug;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
     use tempfile::TempDir;
 
     #[test]
@@ -154,4 +199,97 @@ mod tests {
         );
         assert_eq!(built_by, HashSet::from_iter([task.id().clone()]))
     }
+
+    #[derive(Debug, Clone)]
+    struct VersionedDependency {
+        module: String,
+        version: String,
+    }
+
+    impl GetBuildable for VersionedDependency {
+        fn as_buildable(&self) -> BuildableObject {
+            BuildableObject::None
+        }
+    }
+
+    impl Dependency for VersionedDependency {
+        fn id(&self) -> String {
+            self.module.clone()
+        }
+
+        fn dep_type(&self) -> DependencyType {
+            FILE_SYSTEM_TYPE.clone()
+        }
+
+        fn try_resolve(
+            &self,
+            _registry: &dyn Registry,
+            _cache_path: &Path,
+        ) -> Result<ResolvedDependency, AcquisitionError> {
+            Ok(ResolvedDependencyBuilder::new(PathBuf::from(format!(
+                "{}-{}.jar",
+                self.module, self.version
+            )))
+            .version(&self.version)
+            .finish())
+        }
+
+        fn with_version(&self, version: &str) -> Option<Box<dyn Dependency + Send + Sync>> {
+            Some(Box::new(Self {
+                version: version.to_string(),
+                ..self.clone()
+            }))
+        }
+    }
+
+    fn versioned(module: &str, version: &str) -> VersionedDependency {
+        VersionedDependency {
+            module: module.to_string(),
+            version: version.to_string(),
+        }
+    }
+
+    #[test]
+    fn version_conflicts() {
+        let registries = Arc::new(Mutex::new(RegistryContainer::new()));
+        let mut handler = ConfigurationHandler::new(ProjectId::default(), &registries);
+
+        let latest = handler.create_with("latest", |config| {
+            config.add_dependency(versioned("lib", "1.2.0"));
+            config.add_dependency(versioned("lib", "1.10.0"));
+            config.add_dependency(versioned("other", "1.0.0"));
+        });
+        let resolved = latest.resolved().unwrap();
+        assert_eq!(
+            resolved.files(),
+            HashSet::from_iter([
+                PathBuf::from("lib-1.10.0.jar"),
+                PathBuf::from("other-1.0.0.jar")
+            ])
+        );
+        assert_eq!(resolved.results().len(), 2);
+        assert_eq!(resolved.results()[0].version(), Some("1.10.0"));
+
+        let forced = handler.create_with("forced", |config| {
+            config.add_dependency(versioned("lib", "1.2.0"));
+            config.add_dependency(versioned("lib", "1.10.0"));
+            config.resolution_strategy(|strategy| strategy.force("lib", "1.0.0"));
+        });
+        let resolved = forced.resolved().unwrap();
+        assert_eq!(
+            resolved.files(),
+            HashSet::from_iter([PathBuf::from("lib-1.0.0.jar")])
+        );
+        assert_eq!(resolved.results()[0].reason(), &SelectionReason::Forced);
+
+        let strict = handler.create_with("strict", |config| {
+            config.add_dependency(versioned("lib", "1.2.0"));
+            config.add_dependency(versioned("lib", "1.10.0"));
+            config.resolution_strategy(ResolutionStrategy::fail_on_version_conflict);
+        });
+        assert!(matches!(
+            strict.resolved(),
+            Err(AcquisitionError::VersionConflict { .. })
+        ));
+    }
 }
@@ -0,0 +1,248 @@
+//! Dependency locking. Records the versions that configurations resolve to in an `assemble.lock`
+//! file, then enforces that later builds resolve to the same versions.
+//!
+//! Locking has three states:
+//! - If no lock file exists, resolution is unrestricted.
+//! - If a lock file exists, resolution is strict. A dependency resolving to a different version
+//!   than the one recorded, or a dependency missing from the lock file, causes resolution to fail.
+//! - If locks are being written (`--write-locks`), resolved versions are recorded and the lock
+//!   file is rewritten.
+
+use crate::dependencies::AcquisitionError;
+
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// The name of the lock file, created in the directory of each project.
+pub const LOCK_FILE_NAME: &str = "assemble.lock";
+
+/// If set to true, lock files are written instead of checked
+pub static WRITE_DEPENDENCY_LOCKS: AtomicBool = AtomicBool::new(false);
+pub fn write_dependency_locks(value: bool) {
+    WRITE_DEPENDENCY_LOCKS.store(value, Ordering::Relaxed)
+}
+
+/// The contents of a lock file. Maps configuration names to the dependency ids in that configuration
+/// and the versions they were resolved to.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockFile {
+    #[serde(default)]
+    configurations: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl LockFile {
+    /// Reads a lock file from a path
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let string = std::fs::read_to_string(path)?;
+        toml_edit::de::from_str(&string)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Writes this lock file to a path
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let string = toml_edit::ser::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        std::fs::write(
+            path,
+            format!(
+                "# This file is generated by assemble using --write-locks. Do not edit it manually.\n{}",
+                string
+            ),
+        )
+    }
+
+    /// Gets the locked version of a dependency within a configuration
+    pub fn locked_version(&self, configuration: &str, dependency: &str) -> Option<&str> {
+        self.configurations
+            .get(configuration)
+            .and_then(|deps| deps.get(dependency))
+            .map(|s| s.as_str())
+    }
+
+    /// Records the version of a dependency within a configuration
+    pub fn lock(&mut self, configuration: &str, dependency: &str, version: &str) {
+        self.configurations
+            .entry(configuration.to_string())
+            .or_default()
+            .insert(dependency.to_string(), version.to_string());
+    }
+
+    /// Gets whether a configuration has any locked dependencies
+    pub fn is_locked(&self, configuration: &str) -> bool {
+        self.configurations.contains_key(configuration)
+    }
+}
+
+/// The locking state for a single project, shared between all of it's configurations.
+#[derive(Debug, Clone)]
+pub struct DependencyLocking {
+    inner: Arc<Mutex<LockingInner>>,
+}
+
+#[derive(Debug)]
+struct LockingInner {
+    path: PathBuf,
+    lock_file: Option<LockFile>,
+    recorded: LockFile,
+}
+
+impl DependencyLocking {
+    /// Create the locking state using the lock file at the given path. The lock file doesn't need to
+    /// exist.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let lock_file = if path.exists() {
+            match LockFile::read(&path) {
+                Ok(lock_file) => Some(lock_file),
+                Err(e) => {
+                    warn!("could not read lock file {:?}: {}", path, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        Self {
+            inner: Arc::new(Mutex::new(LockingInner {
+                path,
+                lock_file,
+                recorded: LockFile::default(),
+            })),
+        }
+    }
+
+    /// Creates a locking state that never locks anything
+    pub fn disabled() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(LockingInner {
+                path: PathBuf::new(),
+                lock_file: None,
+                recorded: LockFile::default(),
+            })),
+        }
+    }
+
+    /// The location of the lock file
+    pub fn path(&self) -> PathBuf {
+        self.inner.lock().path.clone()
+    }
+
+    /// Checks a resolved version against the lock file, or records it if locks are being written.
+    ///
+    /// Dependencies without a resolved version are never locked.
+    pub fn check(
+        &self,
+        configuration: &str,
+        dependency: &str,
+        version: Option<&str>,
+    ) -> Result<(), AcquisitionError> {
+        let version = match version {
+            None => return Ok(()),
+            Some(version) => version,
+        };
+        let mut inner = self.inner.lock();
+        if WRITE_DEPENDENCY_LOCKS.load(Ordering::Relaxed) {
+            inner.recorded.lock(configuration, dependency, version);
+            if inner.path.as_os_str().is_empty() {
+                return Ok(());
+            }
+            return inner.flush().map_err(|e| {
+                AcquisitionError::custom(format!(
+                    "could not write lock file {:?}: {}",
+                    inner.path, e
+                ))
+            });
+        }
+
+        match &inner.lock_file {
+            None => Ok(()),
+            Some(lock_file) => match lock_file.locked_version(configuration, dependency) {
+                Some(locked) if locked == version => Ok(()),
+                Some(locked) => Err(AcquisitionError::LockMismatch {
+                    configuration: configuration.to_string(),
+                    dependency: dependency.to_string(),
+                    locked: locked.to_string(),
+                    resolved: version.to_string(),
+                }),
+                None => Err(AcquisitionError::NotLocked {
+                    configuration: configuration.to_string(),
+                    dependency: dependency.to_string(),
+                }),
+            },
+        }
+    }
+}
+
+impl LockingInner {
+    /// Writes the recorded locks, keeping locks for configurations that weren't resolved in this
+    /// build.
+    fn flush(&self) -> io::Result<()> {
+        let mut output = self.lock_file.clone().unwrap_or_default();
+        for (configuration, dependencies) in &self.recorded.configurations {
+            output
+                .configurations
+                .insert(configuration.clone(), dependencies.clone());
+        }
+        output.write(&self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn lock_file_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(LOCK_FILE_NAME);
+
+        let mut lock_file = LockFile::default();
+        lock_file.lock("libs", "org.example:library", "1.2.3");
+        lock_file.write(&path).unwrap();
+
+        let read = LockFile::read(&path).unwrap();
+        assert_eq!(read, lock_file);
+        assert_eq!(
+            read.locked_version("libs", "org.example:library"),
+            Some("1.2.3")
+        );
+    }
+
+    #[test]
+    fn strict_resolution_detects_mismatch() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(LOCK_FILE_NAME);
+
+        let mut lock_file = LockFile::default();
+        lock_file.lock("libs", "org.example:library", "1.2.3");
+        lock_file.write(&path).unwrap();
+
+        let locking = DependencyLocking::new(&path);
+        assert!(locking
+            .check("libs", "org.example:library", Some("1.2.3"))
+            .is_ok());
+        assert!(matches!(
+            locking.check("libs", "org.example:library", Some("1.2.4")),
+            Err(AcquisitionError::LockMismatch { .. })
+        ));
+        assert!(matches!(
+            locking.check("libs", "org.example:other", Some("0.1.0")),
+            Err(AcquisitionError::NotLocked { .. })
+        ));
+        assert!(locking.check("libs", "some/file.txt", None).is_ok());
+    }
+
+    #[test]
+    fn no_lock_file_is_unrestricted() {
+        let temp_dir = TempDir::new().unwrap();
+        let locking = DependencyLocking::new(temp_dir.path().join(LOCK_FILE_NAME));
+        assert!(locking
+            .check("libs", "org.example:library", Some("1.2.3"))
+            .is_ok());
+    }
+}
@@ -11,7 +11,7 @@ use crate::prelude::ProjectId;
 use crate::project::buildable::{Buildable, BuildableObject, GetBuildable};
 use crate::project::error::ProjectResult;
 use crate::project::GetProjectId;
-use crate::resources::{ProjectResourceExt, ResourceLocation};
+use crate::resources::{InvalidResourceLocation, ProjectResourceExt, ResourceLocation};
 use crate::Project;
 use crate::__export::TaskId;
 use crate::plugins::PluginAware;
@@ -70,12 +70,27 @@ impl CreateProjectDependencies for SharedProject {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ProjectDependency {
     parent: SharedProject,
     location: ResourceLocation,
 }
 
+impl ProjectDependency {
+    /// Creates a dependency on the project at `path`, relative to `parent`, using its default
+    /// configuration if `configuration` isn't set
+    pub fn new<'a, I: Into<Option<&'a str>>>(
+        parent: &SharedProject,
+        path: &str,
+        configuration: I,
+    ) -> Result<Self, InvalidResourceLocation> {
+        Ok(Self {
+            parent: parent.clone(),
+            location: ResourceLocation::find(&parent.project_id(), path, configuration)?,
+        })
+    }
+}
+
 impl Buildable for ProjectDependency {
     fn get_dependencies(&self, project: &Project) -> ProjectResult<HashSet<TaskId>> {
         let location = self.location.clone();
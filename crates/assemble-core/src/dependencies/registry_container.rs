@@ -1,5 +1,6 @@
-use crate::cache::AssembleCache;
+use crate::cache::manager::{CacheManager, DEPENDENCY_CACHE};
 use crate::dependencies::file_dependency::FileSystem;
+use crate::dependencies::substitution::DependencySubstitutions;
 use crate::dependencies::DependencyType;
 use std::collections::hash_map::IntoValues;
 use std::collections::{HashMap, HashSet};
@@ -8,6 +9,8 @@ use std::fmt::{Debug, Formatter};
 use std::path::PathBuf;
 use url::Url;
 
+use crate::web::{AuthenticatedRepository, WebClient};
+
 /// A registry is some repository that interprets can interpret some [`Url`](url::Url)
 pub trait Registry {
     /// Gets the base url this registry is located at.
@@ -15,6 +18,12 @@ pub trait Registry {
 
     /// The dependency types that this registry supports
     fn supported(&self) -> Vec<DependencyType>;
+
+    /// The client used to make web requests to this registry. Registries requiring authentication
+    /// return a client configured with their credentials.
+    fn web_client(&self) -> WebClient {
+        WebClient::default()
+    }
 }
 
 assert_obj_safe!(Registry);
@@ -24,6 +33,7 @@ pub struct RegistryContainer {
     type_to_registry_index: HashMap<DependencyType, HashSet<usize>>,
     registries: Vec<Box<dyn Registry>>,
     cache_location: PathBuf,
+    substitutions: DependencySubstitutions,
 }
 
 impl Debug for RegistryContainer {
@@ -49,7 +59,8 @@ impl RegistryContainer {
         let mut container = Self {
             type_to_registry_index: Default::default(),
             registries: vec![],
-            cache_location: AssembleCache::default().to_path_buf(),
+            cache_location: CacheManager::default().cache_dir(&DEPENDENCY_CACHE),
+            substitutions: DependencySubstitutions::new(),
         };
         container.add_registry(FileSystem::default());
         container
@@ -70,6 +81,23 @@ impl RegistryContainer {
         }
     }
 
+    /// Adds an [`AuthenticatedRepository`](AuthenticatedRepository) to this container, configured
+    /// by the given function.
+    pub fn repository<F>(
+        &mut self,
+        name: &str,
+        url: &str,
+        configure: F,
+    ) -> Result<(), url::ParseError>
+    where
+        F: FnOnce(&mut AuthenticatedRepository),
+    {
+        let mut repository = AuthenticatedRepository::new(name, url)?;
+        configure(&mut repository);
+        self.add_registry(repository);
+        Ok(())
+    }
+
     /// Gets the amount of registries in this container
     pub fn len(&self) -> usize {
         self.registries.len()
@@ -107,6 +135,12 @@ impl RegistryContainer {
     pub fn cache_location(&self) -> &PathBuf {
         &self.cache_location
     }
+
+    /// The substitutions applied to dependencies resolved with this container. These are shared
+    /// with the configurations of the project.
+    pub fn substitutions(&self) -> &DependencySubstitutions {
+        &self.substitutions
+    }
 }
 
 #[derive(Default)]
@@ -0,0 +1,317 @@
+//! How a configuration picks a version when its dependencies disagree.
+//!
+//! When a configuration resolves more than one version of the same module, the conflict is
+//! resolved by its [`ResolutionStrategy`](ResolutionStrategy). By default the highest version wins,
+//! and every other version of the module is dropped from the configuration. Strategies can instead
+//! fail on any version conflict, force a module to a version before it's resolved, or reject
+//! versions of a module within a range.
+//!
+//! # Example
+//! ```ignore
+//! configuration.resolution_strategy(|strategy| {
+//!     strategy.force("org.example:logging", "1.2.0");
+//!     strategy.reject("org.example:library", ">=2.0.0, <2.1.0")?;
+//!     strategy.fail_on_version_conflict();
+//!     Ok(())
+//! })?;
+//! ```
+
+use crate::dependencies::AcquisitionError;
+use semver::{Version, VersionReq};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// What happens when different versions of a module are resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictResolution {
+    /// The highest version is selected
+    #[default]
+    Latest,
+    /// Resolution fails
+    Fail,
+}
+
+/// The resolution strategy of a configuration
+#[derive(Debug, Clone, Default)]
+pub struct ResolutionStrategy {
+    conflict_resolution: ConflictResolution,
+    forced: HashMap<String, String>,
+    rejected: Vec<(String, VersionReq)>,
+}
+
+impl ResolutionStrategy {
+    /// Creates the default strategy, where the highest version wins
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How version conflicts are resolved
+    pub fn conflict_resolution(&self) -> ConflictResolution {
+        self.conflict_resolution
+    }
+
+    /// Sets how version conflicts are resolved
+    pub fn set_conflict_resolution(&mut self, conflict_resolution: ConflictResolution) {
+        self.conflict_resolution = conflict_resolution;
+    }
+
+    /// Fails resolution when different versions of a module are resolved
+    pub fn fail_on_version_conflict(&mut self) {
+        self.conflict_resolution = ConflictResolution::Fail;
+    }
+
+    /// Forces every dependency on a module to a version before it's resolved
+    pub fn force<M: AsRef<str>, V: AsRef<str>>(&mut self, module: M, version: V) {
+        self.forced
+            .insert(module.as_ref().to_string(), version.as_ref().to_string());
+    }
+
+    /// The version a module is forced to, if any
+    pub fn forced_version(&self, module: &str) -> Option<&str> {
+        self.forced.get(module).map(String::as_str)
+    }
+
+    /// Rejects the versions of a module within a range, such as `>=2.0.0, <2.1.0`
+    pub fn reject<M: AsRef<str>>(&mut self, module: M, range: &str) -> Result<(), semver::Error> {
+        let range = VersionReq::parse(range)?;
+        self.rejected.push((module.as_ref().to_string(), range));
+        Ok(())
+    }
+
+    /// Checks whether a version of a module is rejected. Versions that can't be parsed are never
+    /// rejected.
+    pub fn is_rejected(&self, module: &str, version: &str) -> bool {
+        let version = match lenient_version(version) {
+            Some(version) => version,
+            None => return false,
+        };
+        self.rejected
+            .iter()
+            .any(|(rejected, range)| rejected == module && range.matches(&version))
+    }
+
+    /// Selects the version of a module from the versions resolved for it
+    pub(crate) fn select(
+        &self,
+        configuration: &str,
+        module: &str,
+        versions: &[String],
+    ) -> Result<Selection, AcquisitionError> {
+        for version in versions {
+            if self.is_rejected(module, version) {
+                return Err(AcquisitionError::Rejected {
+                    configuration: configuration.to_string(),
+                    dependency: module.to_string(),
+                    version: version.clone(),
+                });
+            }
+        }
+
+        let mut candidates = versions.to_vec();
+        candidates.sort_by(|l, r| compare_versions(l, r));
+        candidates.dedup();
+        let selected = candidates
+            .last()
+            .cloned()
+            .expect("no versions to select from");
+
+        let reason = if self.forced.contains_key(module) {
+            SelectionReason::Forced
+        } else if candidates.len() > 1 {
+            if self.conflict_resolution == ConflictResolution::Fail {
+                return Err(AcquisitionError::VersionConflict {
+                    configuration: configuration.to_string(),
+                    dependency: module.to_string(),
+                    versions: candidates,
+                });
+            }
+            SelectionReason::ConflictResolution { candidates }
+        } else {
+            SelectionReason::Requested
+        };
+
+        Ok(Selection {
+            version: selected,
+            reason,
+        })
+    }
+}
+
+/// The version selected for a module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Selection {
+    pub version: String,
+    pub reason: SelectionReason,
+}
+
+/// Why a version of a dependency was selected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectionReason {
+    /// The version is the only one requested
+    Requested,
+    /// The version was forced by the resolution strategy
+    Forced,
+    /// The version is the highest of the conflicting versions
+    ConflictResolution {
+        /// Every version that was resolved
+        candidates: Vec<String>,
+    },
+}
+
+impl Display for SelectionReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelectionReason::Requested => write!(f, "requested"),
+            SelectionReason::Forced => write!(f, "forced"),
+            SelectionReason::ConflictResolution { candidates } => {
+                write!(f, "conflict resolution between {}", candidates.join(", "))
+            }
+        }
+    }
+}
+
+/// The result of resolving a dependency of a configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyResult {
+    id: String,
+    version: Option<String>,
+    reason: SelectionReason,
+}
+
+impl DependencyResult {
+    pub(crate) fn new(id: &str, version: Option<&str>, reason: SelectionReason) -> Self {
+        Self {
+            id: id.to_string(),
+            version: version.map(str::to_string),
+            reason,
+        }
+    }
+
+    /// The id of the dependency
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The selected version of the dependency, if it's versioned
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Why this version was selected
+    pub fn reason(&self) -> &SelectionReason {
+        &self.reason
+    }
+}
+
+impl Display for DependencyResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id)?;
+        if let Some(version) = &self.version {
+            write!(f, " -> {}", version)?;
+        }
+        if self.reason != SelectionReason::Requested {
+            write!(f, " ({})", self.reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a version, padding missing minor and patch components with zeroes
+fn lenient_version(version: &str) -> Option<Version> {
+    if let Ok(version) = Version::parse(version) {
+        return Some(version);
+    }
+    let (core, rest) = match version.find(|c| c == '-' || c == '+') {
+        Some(index) => version.split_at(index),
+        None => (version, ""),
+    };
+    let mut parts = core.split('.').collect::<Vec<_>>();
+    if parts.len() > 3 {
+        return None;
+    }
+    while parts.len() < 3 {
+        parts.push("0");
+    }
+    Version::parse(&format!("{}{}", parts.join("."), rest)).ok()
+}
+
+/// Compares two versions. Versions that aren't semantic versions are compared component by
+/// component, numerically where possible.
+fn compare_versions(left: &str, right: &str) -> Ordering {
+    if let (Some(left), Some(right)) = (lenient_version(left), lenient_version(right)) {
+        return left.cmp(&right);
+    }
+    let components = |version: &str| {
+        version
+            .split(|c: char| c == '.' || c == '-')
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    for (left, right) in components(left).iter().zip(components(right).iter()) {
+        let ordering = match (left.parse::<u64>(), right.parse::<u64>()) {
+            (Ok(left), Ok(right)) => left.cmp(&right),
+            _ => left.cmp(right),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    components(left).len().cmp(&components(right).len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highest_version_wins() {
+        let strategy = ResolutionStrategy::new();
+        let selection = strategy
+            .select(
+                "libs",
+                "org.example:library",
+                &["1.10".to_string(), "1.9.2".to_string()],
+            )
+            .unwrap();
+        assert_eq!(selection.version, "1.10");
+        assert_eq!(
+            selection.reason,
+            SelectionReason::ConflictResolution {
+                candidates: vec!["1.9.2".to_string(), "1.10".to_string()]
+            }
+        );
+    }
+
+    #[test]
+    fn fail_on_conflict_and_reject() {
+        let mut strategy = ResolutionStrategy::new();
+        strategy.fail_on_version_conflict();
+        let versions = ["1.0.0".to_string(), "2.0.0".to_string()];
+        assert!(matches!(
+            strategy.select("libs", "org.example:library", &versions),
+            Err(AcquisitionError::VersionConflict { .. })
+        ));
+        assert!(strategy
+            .select("libs", "org.example:library", &versions[..1])
+            .is_ok());
+
+        strategy
+            .reject("org.example:library", ">=2.0.0, <2.1.0")
+            .unwrap();
+        assert!(strategy.is_rejected("org.example:library", "2.0"));
+        assert!(!strategy.is_rejected("org.example:library", "2.1.0"));
+        assert!(!strategy.is_rejected("org.example:other", "2.0.0"));
+        assert!(matches!(
+            strategy.select("libs", "org.example:library", &versions[1..]),
+            Err(AcquisitionError::Rejected { .. })
+        ));
+    }
+
+    #[test]
+    fn non_semantic_versions() {
+        assert_eq!(compare_versions("1.0.0.1", "1.0.0.2"), Ordering::Less);
+        assert_eq!(compare_versions("2.0-rc1", "2.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.2.10", "1.2.9"), Ordering::Greater);
+    }
+}
@@ -13,6 +13,7 @@ use std::path::PathBuf;
 pub struct ResolvedDependency {
     artifacts: HashSet<ImmutableArtifact>,
     files: HashSet<PathBuf>,
+    version: Option<String>,
 }
 
 impl ResolvedDependency {
@@ -26,11 +27,17 @@ impl ResolvedDependency {
         self.artifacts.clone()
     }
 
+    /// Gets the version this dependency was resolved to, if the dependency is versioned
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
     /// Joins two resolved dependency into one
     pub fn join(self, other: Self) -> Self {
         Self {
             artifacts: self.artifacts.union(&other.artifacts).cloned().collect(),
             files: self.files.union(&other.files).cloned().collect(),
+            version: self.version.or(other.version),
         }
     }
 }
@@ -38,6 +45,7 @@ impl ResolvedDependency {
 pub struct ResolvedDependencyBuilder {
     artifacts: HashSet<ImmutableArtifact>,
     built_by: BuiltByContainer,
+    version: Option<String>,
 }
 
 impl ResolvedDependencyBuilder {
@@ -46,6 +54,7 @@ impl ResolvedDependencyBuilder {
         let mut output = Self {
             artifacts: HashSet::new(),
             built_by: Default::default(),
+            version: None,
         };
         output.add(artifact);
         output
@@ -79,6 +88,12 @@ impl ResolvedDependencyBuilder {
         self
     }
 
+    /// Set the version the dependency was resolved to
+    pub fn version<S: AsRef<str>>(mut self, version: S) -> Self {
+        self.version = Some(version.as_ref().to_string());
+        self
+    }
+
     pub fn finish(self) -> ResolvedDependency {
         let files = self
             .artifacts
@@ -89,6 +104,7 @@ impl ResolvedDependencyBuilder {
         ResolvedDependency {
             artifacts: self.artifacts,
             files,
+            version: self.version,
         }
     }
 }
@@ -0,0 +1,144 @@
+//! Substitutes dependencies while configurations are resolved.
+//!
+//! Substitutions are shared by the [`RegistryContainer`](super::RegistryContainer) and every
+//! configuration of a project. A dependency is substituted when its [id](Dependency::id) matches
+//! the module of a substitution, both when the tasks building a configuration are found and when
+//! the configuration is resolved. A module can either be replaced by a project, or have its version
+//! forced.
+//!
+//! # Example
+//! ```ignore
+//! let substitutions = project.configurations().substitutions();
+//! substitutions.substitute_project("org.example:library", project.project(":libs:library"));
+//! substitutions.force_version("org.example:logging", "1.2.0");
+//! ```
+
+use crate::dependencies::project_dependency::ProjectDependency;
+use crate::dependencies::Dependency;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+/// What a module is substituted with
+#[derive(Debug, Clone)]
+pub enum Substitution {
+    /// The module is replaced by a project
+    Project(ProjectDependency),
+    /// The module is requested at exactly this version, whatever version was requested
+    Version(String),
+}
+
+impl Display for Substitution {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Substitution::Project(project) => write!(f, "project {}", project.id()),
+            Substitution::Version(version) => write!(f, "version {}", version),
+        }
+    }
+}
+
+/// The dependency substitutions of a project. Clones share the same substitutions.
+#[derive(Debug, Clone, Default)]
+pub struct DependencySubstitutions {
+    modules: Arc<RwLock<HashMap<String, Substitution>>>,
+}
+
+impl DependencySubstitutions {
+    /// Creates an empty set of substitutions
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Substitutes a project for every dependency on a module, such as `org.example:library`
+    pub fn substitute_project<S: AsRef<str>>(&self, module: S, project: ProjectDependency) {
+        self.substitute(module, Substitution::Project(project));
+    }
+
+    /// Forces every dependency on a module to use a version
+    pub fn force_version<S: AsRef<str>, V: AsRef<str>>(&self, module: S, version: V) {
+        self.substitute(module, Substitution::Version(version.as_ref().to_string()));
+    }
+
+    /// Substitutes every dependency on a module. Replaces any previous substitution of the module.
+    pub fn substitute<S: AsRef<str>>(&self, module: S, substitution: Substitution) {
+        self.modules
+            .write()
+            .insert(module.as_ref().to_string(), substitution);
+    }
+
+    /// The substitution of a dependency, if any
+    pub fn substitution_for(&self, dependency: &dyn Dependency) -> Option<Substitution> {
+        let modules = self.modules.read();
+        if modules.is_empty() {
+            return None;
+        }
+        modules.get(&dependency.id()).cloned()
+    }
+
+    /// The project substituted for a dependency, if any
+    pub fn project_for(&self, dependency: &dyn Dependency) -> Option<ProjectDependency> {
+        match self.substitution_for(dependency)? {
+            Substitution::Project(project) => Some(project),
+            Substitution::Version(_) => None,
+        }
+    }
+
+    /// Whether there are no substitutions
+    pub fn is_empty(&self) -> bool {
+        self.modules.read().is_empty()
+    }
+
+    /// Substitutes a dependency, if there's a substitution for it. Versions can only be forced for
+    /// dependencies that are [versioned](Dependency::with_version), other dependencies are kept
+    /// as they are.
+    pub(crate) fn apply(
+        &self,
+        dependency: Box<dyn Dependency + Send + Sync>,
+    ) -> Box<dyn Dependency + Send + Sync> {
+        match self.substitution_for(dependency.as_ref()) {
+            Some(Substitution::Project(project)) => {
+                debug!("substituting {} for {}", project.id(), dependency.id());
+                Box::new(project)
+            }
+            Some(Substitution::Version(version)) => match dependency.with_version(&version) {
+                Some(forced) => {
+                    debug!("forcing {} to version {}", dependency.id(), version);
+                    forced
+                }
+                None => {
+                    warn!(
+                        "can't force version {} of {}, it isn't versioned",
+                        version,
+                        dependency.id()
+                    );
+                    dependency
+                }
+            },
+            None => dependency,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn clones_share_substitutions() {
+        let substitutions = DependencySubstitutions::new();
+        let file = PathBuf::from("lib.jar");
+        assert!(substitutions.substitution_for(&file).is_none());
+
+        substitutions.clone().force_version(file.id(), "1.0.0");
+        assert!(matches!(
+            substitutions.substitution_for(&file),
+            Some(Substitution::Version(version)) if version == "1.0.0"
+        ));
+        assert!(substitutions.project_for(&file).is_none());
+
+        let applied = substitutions.apply(Box::new(file.clone()));
+        assert_eq!(applied.id(), file.id(), "files aren't versioned");
+    }
+}
@@ -19,6 +19,12 @@ pub trait Dependency: GetBuildable + Debug {
         registry: &dyn Registry,
         cache_path: &Path,
     ) -> Result<ResolvedDependency, AcquisitionError>;
+
+    /// Creates a copy of this dependency requesting exactly `version`. Returns `None` if this
+    /// dependency isn't versioned.
+    fn with_version(&self, _version: &str) -> Option<Box<dyn Dependency + Send + Sync>> {
+        None
+    }
 }
 
 assert_obj_safe!(Dependency);
@@ -62,6 +68,30 @@ pub enum AcquisitionError {
     MissingFile,
     #[error("Errors: {}", inner.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(","))]
     InnerErrors { inner: Vec<AcquisitionError> },
+    #[error("{dependency} in {configuration} resolved to {resolved}, but is locked to {locked} (use --write-locks to update the lock file)")]
+    LockMismatch {
+        configuration: String,
+        dependency: String,
+        locked: String,
+        resolved: String,
+    },
+    #[error("{dependency} in {configuration} is not part of the lock file (use --write-locks to update the lock file)")]
+    NotLocked {
+        configuration: String,
+        dependency: String,
+    },
+    #[error("{dependency} in {configuration} resolved to conflicting versions: {}", versions.join(", "))]
+    VersionConflict {
+        configuration: String,
+        dependency: String,
+        versions: Vec<String>,
+    },
+    #[error("{dependency} in {configuration} resolved to rejected version {version}")]
+    Rejected {
+        configuration: String,
+        dependency: String,
+        version: String,
+    },
 }
 
 impl AcquisitionError {
@@ -0,0 +1,227 @@
+//! Reporting of deprecated features used by a build.
+//!
+//! Deprecated apis call [`nag`](nag) when they're used. Each deprecation is attributed to the
+//! build script or plugin using it, which is known while it's being [entered](DeprecationOrigin),
+//! or else to the code calling the deprecated api. A deprecation is only recorded once per origin,
+//! however often it's used.
+//!
+//! How deprecations are reported is controlled by the [`WarningMode`](WarningMode), so that users
+//! can first see where deprecations are used, then fail builds that still use them.
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::panic::Location;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How deprecations used by a build are reported
+#[derive(Debug, Default, Copy, Clone, clap::ValueEnum, Eq, PartialEq)]
+pub enum WarningMode {
+    /// A single warning at the end of the build counts the deprecations that were used
+    #[default]
+    Summary,
+    /// Every deprecation is warned of when it's first used
+    All,
+    /// Every deprecation is warned of when it's first used, and the build fails at the end if any
+    /// were used
+    Fail,
+}
+
+static WARNING_MODE: AtomicU8 = AtomicU8::new(0);
+
+/// Sets how deprecations used by the build are reported
+pub fn set_warning_mode(mode: WarningMode) {
+    let value = match mode {
+        WarningMode::Summary => 0,
+        WarningMode::All => 1,
+        WarningMode::Fail => 2,
+    };
+    WARNING_MODE.store(value, Ordering::Relaxed)
+}
+
+/// Gets how deprecations used by the build are reported
+pub fn warning_mode() -> WarningMode {
+    match WARNING_MODE.load(Ordering::Relaxed) {
+        1 => WarningMode::All,
+        2 => WarningMode::Fail,
+        _ => WarningMode::Summary,
+    }
+}
+
+/// A deprecated feature used by the build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deprecation {
+    message: String,
+    origins: Vec<String>,
+}
+
+impl Deprecation {
+    /// The message describing the deprecation and what to use instead
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The build scripts, plugins or code that used the deprecated feature, in the order they
+    /// first used it
+    pub fn origins(&self) -> &[String] {
+        &self.origins
+    }
+}
+
+impl Display for Deprecation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        for origin in &self.origins {
+            write!(f, "\n  used by {}", origin)?;
+        }
+        Ok(())
+    }
+}
+
+static DEPRECATIONS: Lazy<Mutex<Vec<Deprecation>>> = Lazy::new(Default::default);
+
+thread_local! {
+    static ORIGINS: RefCell<Vec<String>> = RefCell::new(vec![]);
+}
+
+/// Attributes the deprecations used on the current thread to a build script or plugin until
+/// dropped. Origins can be nested, in which case the innermost origin is used.
+#[derive(Debug)]
+pub struct DeprecationOrigin {
+    _private: (),
+}
+
+impl DeprecationOrigin {
+    /// Attributes deprecations used on the current thread to the given origin, such as
+    /// `plugin rust` or `build script build.assemble.js`
+    pub fn enter(origin: impl Display) -> Self {
+        ORIGINS.with(|origins| origins.borrow_mut().push(origin.to_string()));
+        Self { _private: () }
+    }
+}
+
+impl Drop for DeprecationOrigin {
+    fn drop(&mut self) {
+        ORIGINS.with(|origins| origins.borrow_mut().pop());
+    }
+}
+
+/// Gets the build script or plugin deprecations on the current thread are attributed to, if any
+pub fn current_origin() -> Option<String> {
+    ORIGINS.with(|origins| origins.borrow().last().cloned())
+}
+
+/// Records the use of a deprecated feature. The message should say what's deprecated and what to
+/// use instead, like `"API X is deprecated, use Y"`.
+///
+/// In [`All`](WarningMode::All) and [`Fail`](WarningMode::Fail) mode, a warning is emitted the
+/// first time the deprecation is used by its origin.
+#[track_caller]
+pub fn nag(message: impl Display) {
+    let origin = current_origin().unwrap_or_else(|| Location::caller().to_string());
+    let message = message.to_string();
+
+    let mut deprecations = DEPRECATIONS.lock();
+    let index = match deprecations.iter().position(|d| d.message == message) {
+        Some(index) => index,
+        None => {
+            deprecations.push(Deprecation {
+                message: message.clone(),
+                origins: vec![],
+            });
+            deprecations.len() - 1
+        }
+    };
+    let deprecation = &mut deprecations[index];
+    if deprecation.origins.contains(&origin) {
+        return;
+    }
+    deprecation.origins.push(origin.clone());
+    drop(deprecations);
+
+    if warning_mode() != WarningMode::Summary {
+        warn!("deprecated: {} (used by {})", message, origin);
+    }
+}
+
+/// Gets the deprecations used by the build so far, in the order they were first used
+pub fn deprecations() -> Vec<Deprecation> {
+    DEPRECATIONS.lock().clone()
+}
+
+/// Reports the deprecations used by the build, as required by the [warning mode](warning_mode).
+/// Should be called once at the end of the build. Returns the deprecations that were used.
+pub fn report() -> Vec<Deprecation> {
+    let used = deprecations();
+    if used.is_empty() {
+        return used;
+    }
+    let count = match used.len() {
+        1 => "1 deprecated feature was".to_string(),
+        n => format!("{} deprecated features were", n),
+    };
+    match warning_mode() {
+        WarningMode::Summary => warn!(
+            "{} used in this build, run with --warning-mode all to see where",
+            count
+        ),
+        WarningMode::All => warn!("{} used in this build", count),
+        WarningMode::Fail => {
+            error!("{} used in this build:", count);
+            for deprecation in &used {
+                error!("{}", deprecation);
+            }
+        }
+    }
+    used
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(message: &str) -> Option<Deprecation> {
+        deprecations().into_iter().find(|d| d.message() == message)
+    }
+
+    #[test]
+    fn deprecations_are_deduplicated_per_origin() {
+        let message = "API dedup is deprecated, use dedup2";
+        {
+            let _origin = DeprecationOrigin::enter("plugin first");
+            nag(message);
+            nag(message);
+        }
+        {
+            let _origin = DeprecationOrigin::enter("build script build.assemble.js");
+            nag(message);
+        }
+        let deprecation = find(message).unwrap();
+        assert_eq!(
+            deprecation.origins(),
+            &["plugin first", "build script build.assemble.js"]
+        );
+    }
+
+    #[test]
+    fn innermost_origin_is_used() {
+        let message = "API nested is deprecated, use nested2";
+        {
+            let _outer = DeprecationOrigin::enter("build script build.assemble.js");
+            let _inner = DeprecationOrigin::enter("plugin inner");
+            nag(message);
+        }
+        assert_eq!(current_origin(), None);
+        assert_eq!(find(message).unwrap().origins(), &["plugin inner"]);
+    }
+
+    #[test]
+    fn deprecations_without_origin_use_caller() {
+        let message = "API caller is deprecated, use caller2";
+        nag(message);
+        let origins = find(message).unwrap().origins().to_vec();
+        assert_eq!(origins.len(), 1);
+        assert!(origins[0].contains("deprecations.rs"), "{:?}", origins);
+    }
+}
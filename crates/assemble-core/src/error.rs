@@ -6,11 +6,18 @@ use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::io;
 
-/// An payload with an error
+pub mod category;
+pub mod hints;
+
+/// An payload with an error.
+///
+/// Context can be added to the error as it's passed up, describing what was being done when it
+/// occurred. The alternate display of the error (`{:#}`) includes the context, innermost first.
 #[derive(Debug)]
 pub struct PayloadError<E> {
     kind: E,
     bt: Backtrace,
+    context: Vec<String>,
 }
 
 impl<E> PayloadError<E> {
@@ -31,6 +38,7 @@ impl<E> PayloadError<E> {
         Self {
             kind: kind.into(),
             bt,
+            context: vec![],
         }
     }
 
@@ -44,6 +52,18 @@ impl<E> PayloadError<E> {
         &self.bt
     }
 
+    /// Adds context to the error, describing what was being done when it occurred, such as
+    /// `configuring project :app`
+    pub fn context<C: Display>(mut self, context: C) -> Self {
+        self.context.push(context.to_string());
+        self
+    }
+
+    /// Gets the context of the error, innermost first
+    pub fn context_chain(&self) -> &[String] {
+        &self.context
+    }
+
     /// Convert the error type
     pub fn into<T>(self) -> PayloadError<T>
     where
@@ -52,6 +72,7 @@ impl<E> PayloadError<E> {
         PayloadError {
             kind: self.kind.into(),
             bt: self.bt,
+            context: self.context,
         }
     }
 
@@ -69,7 +90,13 @@ impl<E> From<E> for PayloadError<E> {
 
 impl<E: Display> Display for PayloadError<E> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.kind)
+        write!(f, "{}", self.kind)?;
+        if f.alternate() {
+            for context in &self.context {
+                write!(f, "\n  while {}", context)?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -84,6 +111,25 @@ impl<E> AsRef<E> for PayloadError<E> {
 /// A result with a pay-loaded error
 pub type Result<T, E> = std::result::Result<T, PayloadError<E>>;
 
+/// Adds context to the errors of results
+pub trait ErrorContext {
+    /// Adds context to the error, if this is an error
+    fn context<C: Display>(self, context: C) -> Self;
+
+    /// Adds context to the error, if this is an error. The context is only created when needed.
+    fn with_context<C: Display, F: FnOnce() -> C>(self, context: F) -> Self;
+}
+
+impl<T, E> ErrorContext for Result<T, E> {
+    fn context<C: Display>(self, context: C) -> Self {
+        self.map_err(|e| e.context(context))
+    }
+
+    fn with_context<C: Display, F: FnOnce() -> C>(self, context: F) -> Self {
+        self.map_err(|e| e.context(context()))
+    }
+}
+
 impl From<io::Error> for PayloadError<ProjectError> {
     fn from(e: io::Error) -> Self {
         PayloadError::new(e)
@@ -92,7 +138,7 @@ impl From<io::Error> for PayloadError<ProjectError> {
 
 #[cfg(test)]
 mod tests {
-    use crate::error::PayloadError;
+    use crate::error::{ErrorContext, PayloadError, Result};
 
     #[test]
     fn create_payload() {
@@ -100,4 +146,19 @@ mod tests {
         let bt = res.backtrace();
         println!("{:?}", bt);
     }
+
+    #[test]
+    fn context_is_shown_in_alternate_display() {
+        let res: Result<(), String> = Err(PayloadError::new("file not found".to_string()));
+        let error = res
+            .context("reading settings")
+            .with_context(|| "configuring project :app")
+            .unwrap_err();
+        assert_eq!(error.to_string(), "file not found");
+        assert_eq!(
+            format!("{:#}", error),
+            "file not found\n  while reading settings\n  while configuring project :app"
+        );
+        assert_eq!(error.into::<String>().context_chain().len(), 2);
+    }
 }
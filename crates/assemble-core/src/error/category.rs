@@ -0,0 +1,224 @@
+//! Categories of errors.
+//!
+//! Every error of a build falls into a [category](ErrorCategory), which says whether the error was
+//! caused by the user, like a mistake in a build script, or by a bug in assemble. Errors also have
+//! a stable [code](ErrorCode), so that tools can tell errors apart without parsing their messages.
+//! Codes are never reused for a different error.
+
+use crate::error::hints::Hint;
+use crate::error::PayloadError;
+use crate::exception::BuildException;
+use crate::project::error::ProjectError;
+use crate::startup::init_scripts::InitScriptError;
+use std::fmt::{Display, Formatter};
+
+/// What caused an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    /// Settings, projects or tasks were configured incorrectly, or the build was invoked with
+    /// invalid arguments
+    Configuration,
+    /// A task failed while executing
+    TaskExecution,
+    /// Dependencies of the build couldn't be resolved
+    DependencyResolution,
+    /// The environment the build runs in caused the error, like a file that couldn't be read
+    Environment,
+    /// The build was cancelled
+    Cancelled,
+    /// A bug in assemble
+    Internal,
+}
+
+impl ErrorCategory {
+    /// Whether errors in this category are caused by the user or their environment, and not by a
+    /// bug in assemble
+    pub fn is_user_error(&self) -> bool {
+        !matches!(self, ErrorCategory::Internal)
+    }
+
+    /// The prefix of the codes of errors in this category
+    pub fn code_prefix(&self) -> &'static str {
+        match self {
+            ErrorCategory::Configuration => "CFG",
+            ErrorCategory::TaskExecution => "TSK",
+            ErrorCategory::DependencyResolution => "DEP",
+            ErrorCategory::Environment => "ENV",
+            ErrorCategory::Cancelled => "CAN",
+            ErrorCategory::Internal => "INT",
+        }
+    }
+}
+
+impl Display for ErrorCategory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorCategory::Configuration => write!(f, "configuration error"),
+            ErrorCategory::TaskExecution => write!(f, "task execution failure"),
+            ErrorCategory::DependencyResolution => write!(f, "dependency resolution failure"),
+            ErrorCategory::Environment => write!(f, "environment error"),
+            ErrorCategory::Cancelled => write!(f, "build cancelled"),
+            ErrorCategory::Internal => write!(f, "internal error"),
+        }
+    }
+}
+
+/// The stable code of an error, displayed like `CFG004`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ErrorCode {
+    category: ErrorCategory,
+    number: u16,
+}
+
+impl ErrorCode {
+    /// Creates a new error code
+    pub const fn new(category: ErrorCategory, number: u16) -> Self {
+        Self { category, number }
+    }
+
+    /// The category of the error
+    pub fn category(&self) -> ErrorCategory {
+        self.category
+    }
+
+    /// The number of the error within its category
+    pub fn number(&self) -> u16 {
+        self.number
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{:03}", self.category.code_prefix(), self.number)
+    }
+}
+
+/// An error that has a category and a stable code
+pub trait Categorize {
+    /// The code of the error
+    fn code(&self) -> ErrorCode;
+
+    /// The category of the error
+    fn category(&self) -> ErrorCategory {
+        self.code().category()
+    }
+
+    /// Whether the error was caused by the user or their environment, and not by a bug in
+    /// assemble
+    fn is_user_error(&self) -> bool {
+        self.category().is_user_error()
+    }
+
+    /// Hints on how to resolve the error. Prefer [`hints_for`](crate::error::hints::hints_for),
+    /// which also includes hints from assemble and plugins.
+    fn hints(&self) -> Vec<Hint> {
+        vec![]
+    }
+}
+
+impl<E: Categorize> Categorize for PayloadError<E> {
+    fn code(&self) -> ErrorCode {
+        self.kind().code()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.kind().hints()
+    }
+}
+
+impl Categorize for ProjectError {
+    fn code(&self) -> ErrorCode {
+        use ErrorCategory::*;
+        let (category, number) = match self {
+            ProjectError::ExtensionNotRegistered(_) => (Configuration, 1),
+            ProjectError::NoIdentifiersFound(_) => (Configuration, 2),
+            ProjectError::TooManyIdentifiersFound(_, _) => (Configuration, 3),
+            ProjectError::IdentifierMissing(_) => (Configuration, 4),
+            ProjectError::IdentifierMissingWithMaybes(_, _) => (Configuration, 5),
+            ProjectError::TaskNotFound(_) => (Configuration, 6),
+            ProjectError::ProjectNotFound(_) => (Configuration, 7),
+            ProjectError::UnknownTask(_) => (Configuration, 8),
+            ProjectError::InvalidIdentifier(_) => (Configuration, 9),
+            ProjectError::PluginError(_) => (Configuration, 10),
+            ProjectError::InvalidFileType(_) => (Configuration, 11),
+            ProjectError::OptionsDecoderError(_) => (Configuration, 12),
+            ProjectError::OptionsSlurperError(_) => (Configuration, 13),
+            ProjectError::ProjectUrlError(_) => (Configuration, 14),
+            ProjectError::InvalidResourceLocation(_) => (Configuration, 15),
+            ProjectError::ProviderError(_) => (Configuration, 16),
+            ProjectError::ExtensionError(_) => (Configuration, 17),
+            ProjectError::InvalidProjectGraph(_) => (Configuration, 18),
+            ProjectError::LateMutation(_) => (Configuration, 19),
+            ProjectError::PropertyError(_) => (Configuration, 20),
+            ProjectError::CustomError(_) => (Configuration, 21),
+            ProjectError::AcquisitionError(_) => (DependencyResolution, 1),
+            ProjectError::IoError(_) => (Environment, 1),
+            ProjectError::WorkspaceError(_) => (Environment, 2),
+            ProjectError::SomeError {} => (Internal, 1),
+            ProjectError::Infallible(_) => (Internal, 2),
+            ProjectError::PoisonError => (Internal, 3),
+            ProjectError::ActionsAlreadyQueried => (Internal, 4),
+            ProjectError::NoSharedProjectSet => (Internal, 5),
+            ProjectError::FromUtf8Error(_) => (Internal, 6),
+        };
+        ErrorCode::new(category, number)
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        match self {
+            ProjectError::LateMutation(_) => vec![Hint::new(
+                "register and configure tasks while projects are configured, such as in a plugin \
+                 or the build file, instead of in task actions",
+            )],
+            _ => vec![],
+        }
+    }
+}
+
+impl Categorize for BuildException {
+    fn code(&self) -> ErrorCode {
+        match self {
+            BuildException::Error(_) => ErrorCode::new(ErrorCategory::TaskExecution, 2),
+            // stopping actions and tasks is handled by the task, and never fails the build
+            BuildException::StopAction | BuildException::StopTask => {
+                ErrorCode::new(ErrorCategory::Internal, 7)
+            }
+        }
+    }
+}
+
+impl Categorize for InitScriptError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            InitScriptError::UnknownLanguage(_) => ErrorCode::new(ErrorCategory::Configuration, 22),
+            InitScriptError::Unsupported { .. } => ErrorCode::new(ErrorCategory::Configuration, 23),
+            InitScriptError::InvalidRepository(_) => {
+                ErrorCode::new(ErrorCategory::Configuration, 24)
+            }
+            InitScriptError::Yaml(_) => ErrorCode::new(ErrorCategory::Configuration, 25),
+            InitScriptError::IoError(_) => ErrorCode::new(ErrorCategory::Environment, 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identifier::TaskId;
+    use std::io;
+
+    #[test]
+    fn codes_are_displayed_with_category_prefix() {
+        let error = PayloadError::<ProjectError>::new(ProjectError::IdentifierMissing(
+            TaskId::new("missing").unwrap(),
+        ));
+        assert_eq!(error.code().to_string(), "CFG004");
+        assert_eq!(error.category(), ErrorCategory::Configuration);
+        assert!(error.is_user_error());
+
+        let error = ProjectError::from(io::Error::new(io::ErrorKind::Other, "disk full"));
+        assert_eq!(error.code().to_string(), "ENV001");
+        assert!(ProjectError::PoisonError.code().category() == ErrorCategory::Internal);
+        assert!(!ProjectError::PoisonError.is_user_error());
+    }
+}
@@ -0,0 +1,219 @@
+//! Hints on how to resolve errors.
+//!
+//! When a build fails, the hints for each failure are shown along with it. Hints come from three
+//! places: the [error types](Categorize::hints) themselves, the hints built into assemble for
+//! common failures, and [hint providers](register_hint_provider) registered by plugins, which can
+//! recognize the failures of their own tasks.
+
+use crate::error::category::{Categorize, ErrorCategory, ErrorCode};
+use crate::identifier::TaskId;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Where bugs in assemble are reported
+pub const ISSUES_URL: &str = "https://github.com/joshradin/assemble-rs/issues";
+
+/// A suggestion on how to resolve an error
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Hint {
+    message: String,
+    link: Option<String>,
+}
+
+impl Hint {
+    /// Creates a new hint
+    pub fn new(message: impl Display) -> Self {
+        Self {
+            message: message.to_string(),
+            link: None,
+        }
+    }
+
+    /// Adds a link to documentation explaining the hint
+    pub fn with_link(mut self, link: impl Display) -> Self {
+        self.link = Some(link.to_string());
+        self
+    }
+
+    /// The suggestion
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// A link to documentation explaining the hint, if there is one
+    pub fn link(&self) -> Option<&str> {
+        self.link.as_deref()
+    }
+}
+
+impl Display for Hint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(link) = &self.link {
+            write!(f, " (see {})", link)?;
+        }
+        Ok(())
+    }
+}
+
+/// What's known about a failure when finding hints for it
+#[derive(Debug, Clone)]
+pub struct Failure<'a> {
+    /// The task that failed, if a task failed
+    pub task: Option<&'a TaskId>,
+    /// The message of the error
+    pub message: String,
+    /// The code of the error
+    pub code: ErrorCode,
+    /// Whether the backtrace of the error is shown
+    pub backtrace_shown: bool,
+}
+
+impl<'a> Failure<'a> {
+    /// Describes an error
+    pub fn new<E: Categorize + Display>(error: &E, backtrace_shown: bool) -> Self {
+        Self {
+            task: None,
+            message: error.to_string(),
+            code: error.code(),
+            backtrace_shown,
+        }
+    }
+
+    /// Describes the error of a task
+    pub fn of_task<E: Categorize + Display>(
+        task: &'a TaskId,
+        error: &E,
+        backtrace_shown: bool,
+    ) -> Self {
+        Self {
+            task: Some(task),
+            ..Self::new(error, backtrace_shown)
+        }
+    }
+}
+
+/// Provides hints for failures it recognizes
+pub type HintProvider = Box<dyn Fn(&Failure) -> Vec<Hint> + Send + Sync>;
+
+static HINT_PROVIDERS: Lazy<RwLock<Vec<(usize, HintProvider)>>> = Lazy::new(Default::default);
+static NEXT_HINT_PROVIDER_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Registers a provider of hints for failures. The provider is removed when the returned handle is
+/// dropped.
+pub fn register_hint_provider<F>(provider: F) -> HintProviderHandle
+where
+    F: Fn(&Failure) -> Vec<Hint> + Send + Sync + 'static,
+{
+    let id = NEXT_HINT_PROVIDER_ID.fetch_add(1, Ordering::Relaxed);
+    HINT_PROVIDERS.write().push((id, Box::new(provider)));
+    HintProviderHandle(id)
+}
+
+/// Keeps a hint provider registered until dropped. Use [`forget`](std::mem::forget) to keep the
+/// provider for the rest of the build.
+#[derive(Debug)]
+pub struct HintProviderHandle(usize);
+
+impl Drop for HintProviderHandle {
+    fn drop(&mut self) {
+        HINT_PROVIDERS.write().retain(|(id, _)| *id != self.0);
+    }
+}
+
+/// Gets the hints for an error, from the error itself, assemble and registered providers. Hints
+/// are never repeated.
+pub fn hints_for<E: Categorize + Display>(error: &E, failure: &Failure) -> Vec<Hint> {
+    let mut hints = error.hints();
+    hints.extend(builtin_hints(failure));
+    for (_, provider) in HINT_PROVIDERS.read().iter() {
+        hints.extend(provider(failure));
+    }
+    let mut seen = std::collections::HashSet::new();
+    hints.retain(|hint| seen.insert(hint.clone()));
+    hints
+}
+
+fn builtin_hints(failure: &Failure) -> Vec<Hint> {
+    let mut hints = vec![];
+    if failure.message.contains("has no value") {
+        let hint = match failure.task {
+            Some(task) => format!(
+                "a property used by {} is unset, set it while configuring the task",
+                task
+            ),
+            None => "a property is unset, set it or give it a convention".to_string(),
+        };
+        hints.push(Hint::new(hint));
+    }
+    match (failure.code.category(), failure.code.number()) {
+        (ErrorCategory::Configuration, 2..=8) => hints.push(Hint::new(
+            "run the `tasks --all` task to list the tasks of every project",
+        )),
+        (ErrorCategory::DependencyResolution, _) => hints.push(Hint::new(
+            "check that the repositories of the project are reachable, or run with --offline to \
+             only use cached dependencies",
+        )),
+        (ErrorCategory::Environment, _) => hints.push(Hint::new(
+            "check the permissions of the files used by the build and that the disk isn't full",
+        )),
+        (ErrorCategory::Internal, _) => hints.push(
+            Hint::new("this is a bug in assemble, please report it with the backtrace")
+                .with_link(ISSUES_URL),
+        ),
+        _ => {}
+    }
+    if !failure.backtrace_shown && failure.code.category() != ErrorCategory::Cancelled {
+        hints.push(Hint::new("run with --backtrace for more detail"));
+    }
+    hints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exception::BuildException;
+    use crate::project::error::ProjectError;
+
+    #[test]
+    fn hints_for_unset_properties() {
+        let task = TaskId::new("compile").unwrap();
+        let error = BuildException::custom("\"compile.source\" has no value");
+        let failure = Failure::of_task(&task, &error, false);
+        let hints = hints_for(&error, &failure);
+        assert_eq!(
+            hints,
+            vec![
+                Hint::new(format!(
+                    "a property used by {} is unset, set it while configuring the task",
+                    task
+                )),
+                Hint::new("run with --backtrace for more detail"),
+            ]
+        );
+    }
+
+    #[test]
+    fn registered_providers_contribute() {
+        let error = ProjectError::custom("custom failure from plugin");
+        let failure = Failure::new(&error, true);
+        let handle = register_hint_provider(|failure| {
+            if failure.message.contains("from plugin") {
+                vec![Hint::new("configure the plugin").with_link("https://example.com")]
+            } else {
+                vec![]
+            }
+        });
+        let hints = hints_for(&error, &failure);
+        assert_eq!(hints.len(), 1);
+        assert_eq!(
+            hints[0].to_string(),
+            "configure the plugin (see https://example.com)"
+        );
+
+        drop(handle);
+        assert!(hints_for(&error, &failure).is_empty());
+    }
+}
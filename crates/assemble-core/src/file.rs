@@ -1,4 +1,6 @@
 use crate::project::buildable::{Buildable, BuiltByContainer, IntoBuildable};
+use crate::task::output_ownership::check_write;
+use crate::task::undeclared_io;
 use std::fmt::{Debug, Display, Formatter};
 use std::fs::{File, Metadata, OpenOptions};
 use std::io;
@@ -30,7 +32,16 @@ impl RegularFile {
     /// Opens a file in write-only mode.
     ///
     /// Will create a file if it does not exist, and will truncate if it does.
+    ///
+    /// Creating a file within the output of a different task than the one currently executing is
+    /// checked by [`check_write`](crate::task::output_ownership::check_write), and creating a file
+    /// that isn't a declared output of the executing task is checked by
+    /// [`undeclared_io::check_write`](crate::task::undeclared_io::check_write).
     pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        check_write(path.as_ref())
+            .map_err(|e| io::Error::new(io::ErrorKind::PermissionDenied, e))?;
+        undeclared_io::check_write(path.as_ref())
+            .map_err(|e| io::Error::new(io::ErrorKind::PermissionDenied, e))?;
         Self::with_options(
             path,
             File::options().create(true).write(true).truncate(true),
@@ -38,7 +49,12 @@ impl RegularFile {
     }
 
     /// Attempts to open a file in read-only mode.
+    ///
+    /// Opening a file that isn't a declared input or output of the executing task is checked by
+    /// [`undeclared_io::check_read`](crate::task::undeclared_io::check_read).
     pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        undeclared_io::check_read(path.as_ref())
+            .map_err(|e| io::Error::new(io::ErrorKind::PermissionDenied, e))?;
         Self::with_options(path, File::options().read(true))
     }
 
@@ -22,9 +22,12 @@ use crate::lazy_evaluation::{IntoProvider, Prop, Provider};
 use crate::project::buildable::{Buildable, BuiltByContainer, IntoBuildable};
 
 use crate::project::ProjectResult;
-use crate::utilities::{AndSpec, Spec, True};
+use crate::utilities::{spec, AndSpec, Spec, True};
 use crate::{BuildResult, Project};
 use crate::error::PayloadError;
+use crate::file_collection::patterns::PatternSet;
+
+pub mod patterns;
 
 /// A file set is a collection of files. File collections are intended to be live.
 pub trait FileCollection {
@@ -176,6 +179,36 @@ impl FileSet {
         files.filter = Arc::new(and);
         files
     }
+
+    /// Only keeps the files accepted by a predicate
+    pub fn filter_by<F>(self, predicate: F) -> Self
+    where
+        F: Fn(&Path) -> bool + Send + Sync + 'static,
+    {
+        self.filter(spec(predicate))
+    }
+
+    /// Creates a fileset containing the files of this set and of another
+    pub fn plus<F: Into<FileSet>>(self, other: F) -> Self {
+        self + other
+    }
+
+    /// Creates a fileset containing the files of this set which aren't in another. The files of
+    /// the other set are only looked up when this set is iterated.
+    pub fn minus<F: Into<FileSet>>(self, other: F) -> Self {
+        Self {
+            components: vec![Component::Difference(self, other.into())],
+            ..Default::default()
+        }
+    }
+
+    /// Only keeps the files whose paths are accepted by a set of patterns.
+    ///
+    /// Patterns are matched against the whole path of each file. To match paths relative to a
+    /// directory, use a [`FileTree`](FileTree) instead.
+    pub fn matching(self, patterns: PatternSet) -> Self {
+        self.filter(patterns)
+    }
 }
 
 impl Default for FileSet {
@@ -248,7 +281,11 @@ impl<P: AsRef<Path>> From<P> for FileSet {
 
 impl Buildable for FileSet {
     fn get_dependencies(&self, project: &Project) -> ProjectResult<HashSet<TaskId>> {
-        self.built_by.get_dependencies(project)
+        let mut dependencies = self.built_by.get_dependencies(project)?;
+        for component in &self.components {
+            dependencies.extend(component.get_dependencies(project)?);
+        }
+        Ok(dependencies)
     }
 }
 
@@ -272,6 +309,9 @@ pub enum Component {
     Path(PathBuf),
     Collection(FileSet),
     Provider(Prop<FileSet>),
+    Tree(FileTree),
+    /// The files of the first set which aren't in the second
+    Difference(FileSet, FileSet),
 }
 
 impl Debug for Component {
@@ -287,12 +327,22 @@ impl Debug for Component {
                 Component::Provider(p) => {
                     write!(f, "{:#?}", p)
                 }
+                Component::Tree(t) => {
+                    write!(f, "{:#?}", t)
+                }
+                Component::Difference(c, minus) => {
+                    write!(f, "{:#?} - {:#?}", c, minus)
+                }
             }
         } else {
             match self {
                 Component::Path(p) => f.debug_tuple("Path").field(p).finish(),
                 Component::Collection(c) => f.debug_tuple("Collection").field(c).finish(),
                 Component::Provider(p) => f.debug_tuple("Provider").field(p).finish(),
+                Component::Tree(t) => f.debug_tuple("Tree").field(t).finish(),
+                Component::Difference(c, minus) => {
+                    f.debug_tuple("Difference").field(c).field(minus).finish()
+                }
             }
         }
     }
@@ -318,6 +368,27 @@ impl Component {
                 let component = pro.get();
                 Box::new(component.into_iter())
             }
+            Component::Tree(tree) => Box::new(tree.files().into_iter()),
+            Component::Difference(c, minus) => {
+                let minus = minus.files();
+                Box::new(c.iter().filter(move |p| !minus.contains(p)))
+            }
+        }
+    }
+}
+
+impl Buildable for Component {
+    fn get_dependencies(&self, project: &Project) -> ProjectResult<HashSet<TaskId>> {
+        match self {
+            Component::Path(_) => Ok(HashSet::new()),
+            Component::Collection(c) => c.get_dependencies(project),
+            Component::Provider(pro) => pro.get_dependencies(project),
+            Component::Tree(tree) => tree.get_dependencies(project),
+            Component::Difference(c, minus) => {
+                let mut dependencies = c.get_dependencies(project)?;
+                dependencies.extend(minus.get_dependencies(project)?);
+                Ok(dependencies)
+            }
         }
     }
 }
@@ -350,6 +421,17 @@ impl FileCollection for Component {
                 let component = pro.fallible_get().map_err(PayloadError::<BuildException>::new)?;
                 Box::new(component.into_iter()) as Box<dyn Iterator<Item = PathBuf> + '_>
             }
+            Component::Tree(tree) => {
+                Box::new(tree.try_files()?.into_iter()) as Box<dyn Iterator<Item = PathBuf> + '_>
+            }
+            Component::Difference(c, minus) => {
+                let minus = minus.try_files()?;
+                Box::new(
+                    c.try_files()?
+                        .into_iter()
+                        .filter(move |p| !minus.contains(p)),
+                ) as Box<dyn Iterator<Item = PathBuf> + '_>
+            }
         }
         .collect())
     }
@@ -448,3 +530,168 @@ impl Spec<Path> for &str {
         glob::Pattern::from_str(self).unwrap().accept(value)
     }
 }
+
+/// The files within a directory, which is only walked when the files are requested. Include and
+/// exclude patterns are matched against the paths of files relative to the directory.
+#[derive(Debug, Clone)]
+pub struct FileTree {
+    root: Prop<PathBuf>,
+    patterns: PatternSet,
+}
+
+impl FileTree {
+    /// Creates a tree of the files within a directory
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self::with_provider(root.as_ref().to_path_buf())
+    }
+
+    /// Creates a tree of the files within a directory that's provided. Tasks building the
+    /// directory are dependencies of the tree.
+    pub fn with_provider<P: IntoProvider<PathBuf>>(root: P) -> Self
+    where
+        P::Provider: 'static,
+    {
+        let mut prop = Prop::default();
+        prop.set_with(root).unwrap();
+        Self {
+            root: prop,
+            patterns: PatternSet::default(),
+        }
+    }
+
+    /// Only includes files matching a pattern, or any of the other include patterns.
+    ///
+    /// # Panic
+    /// Panics if the pattern isn't a valid glob.
+    pub fn include(mut self, pattern: impl AsRef<str>) -> Self {
+        let pattern = pattern.as_ref();
+        if let Err(e) = self.patterns.include(pattern) {
+            panic!("{:?} is not a valid pattern: {}", pattern, e);
+        }
+        self
+    }
+
+    /// Excludes files matching a pattern.
+    ///
+    /// # Panic
+    /// Panics if the pattern isn't a valid glob.
+    pub fn exclude(mut self, pattern: impl AsRef<str>) -> Self {
+        let pattern = pattern.as_ref();
+        if let Err(e) = self.patterns.exclude(pattern) {
+            panic!("{:?} is not a valid pattern: {}", pattern, e);
+        }
+        self
+    }
+
+    /// Sets the patterns selecting files within the directory, replacing any patterns added
+    /// before
+    pub fn matching(mut self, patterns: PatternSet) -> Self {
+        self.patterns = patterns;
+        self
+    }
+
+    /// Walks the files under the root. A missing root has no files.
+    fn walk(&self, root: &Path) -> Box<dyn Iterator<Item = walkdir::Result<PathBuf>> + '_> {
+        if !root.exists() {
+            return Box::new(std::iter::empty());
+        }
+        let root = root.to_path_buf();
+        Box::new(
+            WalkDir::new(&root)
+                .into_iter()
+                .filter_ok(|entry| entry.file_type().is_file())
+                .map_ok(|entry| entry.into_path())
+                .filter_ok(move |path| {
+                    self.patterns
+                        .accept(path.strip_prefix(&root).unwrap_or(path))
+                }),
+        )
+    }
+}
+
+impl FileCollection for FileTree {
+    fn files(&self) -> HashSet<PathBuf> {
+        self.walk(&self.root.get()).filter_map(Result::ok).collect()
+    }
+
+    fn try_files(&self) -> BuildResult<HashSet<PathBuf>> {
+        let root = self
+            .root
+            .fallible_get()
+            .map_err(PayloadError::<BuildException>::new)?;
+        self.walk(&root)
+            .map(|r| r.map_err(BuildException::new))
+            .collect::<Result<HashSet<_>, _>>()
+            .map_err(PayloadError::new)
+    }
+}
+
+impl Buildable for FileTree {
+    fn get_dependencies(&self, project: &Project) -> ProjectResult<HashSet<TaskId>> {
+        self.root.get_dependencies(project)
+    }
+}
+
+impl From<FileTree> for FileSet {
+    fn from(tree: FileTree) -> Self {
+        Self {
+            components: vec![Component::Tree(tree)],
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn compose_file_sets() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("nested")).unwrap();
+        for file in ["a.rs", "b.txt", "nested/c.rs", "nested/d.rs"] {
+            fs::write(src.join(file), "").unwrap();
+        }
+
+        let tree = FileTree::new(&src)
+            .include("**/*.rs")
+            .exclude("nested/d.rs");
+        assert_eq!(
+            tree.files(),
+            HashSet::from([src.join("a.rs"), src.join("nested/c.rs")])
+        );
+
+        let files = FileSet::from(tree)
+            .plus(src.join("b.txt"))
+            .minus(src.join("a.rs"));
+        assert_eq!(
+            files.files(),
+            HashSet::from([src.join("b.txt"), src.join("nested/c.rs")])
+        );
+        assert_eq!(
+            files
+                .clone()
+                .filter_by(|p| p.extension().unwrap() == "txt")
+                .files(),
+            HashSet::from([src.join("b.txt")])
+        );
+        assert_eq!(
+            files
+                .matching(PatternSet::with_patterns(["**/*.rs"], Vec::<&str>::new()).unwrap())
+                .try_files()
+                .unwrap(),
+            HashSet::from([src.join("nested/c.rs")])
+        );
+    }
+
+    #[test]
+    fn trees_are_walked_lazily() {
+        let dir = tempfile::tempdir().unwrap();
+        let tree = FileSet::from(FileTree::new(dir.path()));
+        assert!(tree.is_empty());
+        fs::write(dir.path().join("late.txt"), "").unwrap();
+        assert_eq!(tree.files(), HashSet::from([dir.path().join("late.txt")]));
+    }
+}
@@ -0,0 +1,255 @@
+//! Ant-style glob patterns, used to include and exclude files.
+//!
+//! Patterns are matched one path segment at a time. Within a segment, `*` matches any number of
+//! characters, `?` matches a single character, and `[...]` matches a set of characters. A segment
+//! of only `**` matches any number of directories, including none. A pattern ending with `/`
+//! matches everything under the directory, as if it ended with `/**`.
+//!
+//! | Pattern      | Matches                                          |
+//! |--------------|--------------------------------------------------|
+//! | `*.rs`       | `lib.rs`, but not `src/lib.rs`                   |
+//! | `**/*.rs`    | `lib.rs`, `src/lib.rs` and `src/bin/main.rs`     |
+//! | `src/**/*.rs`| `src/lib.rs` and `src/bin/main.rs`               |
+//! | `target/`    | every file under `target`                        |
+//!
+//! A [`PatternSet`](PatternSet) combines include and exclude patterns, and is what file
+//! collections, copy specs and file watchers use to select files.
+
+use crate::utilities::Spec;
+use glob::{MatchOptions, Pattern, PatternError};
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use std::path::{Component, Path};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// `**`, which matches any number of segments
+    AnyDirs,
+    Pattern(Pattern),
+}
+
+/// A compiled ant-style glob pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl Glob {
+    /// Compiles a pattern. Both `/` and `\` separate segments.
+    pub fn new(pattern: &str) -> Result<Self, PatternError> {
+        let mut normalized = pattern.replace('\\', "/");
+        if normalized.ends_with('/') {
+            normalized.push_str("**");
+        }
+
+        let mut segments = vec![];
+        for segment in normalized.split('/').filter(|s| !s.is_empty() && *s != ".") {
+            if segment == "**" {
+                if segments.last() != Some(&Segment::AnyDirs) {
+                    segments.push(Segment::AnyDirs);
+                }
+            } else {
+                segments.push(Segment::Pattern(Pattern::new(segment)?));
+            }
+        }
+        Ok(Self {
+            source: pattern.to_string(),
+            segments,
+        })
+    }
+
+    /// The pattern this glob was compiled from
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether a path matches this pattern, case sensitively
+    pub fn matches(&self, path: impl AsRef<Path>) -> bool {
+        self.matches_with(path, true)
+    }
+
+    /// Whether a path matches this pattern
+    pub fn matches_with(&self, path: impl AsRef<Path>, case_sensitive: bool) -> bool {
+        let components = path
+            .as_ref()
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(segment) => Some(segment.to_string_lossy()),
+                Component::ParentDir => Some(Cow::Borrowed("..")),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let options = MatchOptions {
+            case_sensitive,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+        matches_segments(&self.segments, &components, options)
+    }
+}
+
+fn matches_segments(segments: &[Segment], components: &[Cow<str>], options: MatchOptions) -> bool {
+    match segments.split_first() {
+        None => components.is_empty(),
+        Some((Segment::AnyDirs, rest)) => {
+            (0..=components.len()).any(|skip| matches_segments(rest, &components[skip..], options))
+        }
+        Some((Segment::Pattern(pattern), rest)) => match components.split_first() {
+            Some((component, components)) => {
+                pattern.matches_with(component, options)
+                    && matches_segments(rest, components, options)
+            }
+            None => false,
+        },
+    }
+}
+
+impl FromStr for Glob {
+    type Err = PatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl Display for Glob {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// A set of include and exclude patterns.
+///
+/// A path is accepted if it matches any include pattern, or if there are no include patterns, and
+/// matches no exclude pattern. Patterns are case sensitive unless set otherwise.
+#[derive(Debug, Clone)]
+pub struct PatternSet {
+    includes: Vec<Glob>,
+    excludes: Vec<Glob>,
+    case_sensitive: bool,
+}
+
+impl Default for PatternSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PatternSet {
+    /// Creates a pattern set that accepts everything
+    pub fn new() -> Self {
+        Self {
+            includes: vec![],
+            excludes: vec![],
+            case_sensitive: true,
+        }
+    }
+
+    /// Creates a pattern set from include and exclude patterns
+    pub fn with_patterns<I, E>(includes: I, excludes: E) -> Result<Self, PatternError>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        E: IntoIterator,
+        E::Item: AsRef<str>,
+    {
+        let mut set = Self::new();
+        for include in includes {
+            set.include(include)?;
+        }
+        for exclude in excludes {
+            set.exclude(exclude)?;
+        }
+        Ok(set)
+    }
+
+    /// Adds an include pattern
+    pub fn include(&mut self, pattern: impl AsRef<str>) -> Result<&mut Self, PatternError> {
+        self.includes.push(Glob::new(pattern.as_ref())?);
+        Ok(self)
+    }
+
+    /// Adds an exclude pattern
+    pub fn exclude(&mut self, pattern: impl AsRef<str>) -> Result<&mut Self, PatternError> {
+        self.excludes.push(Glob::new(pattern.as_ref())?);
+        Ok(self)
+    }
+
+    /// Sets whether patterns are matched case sensitively
+    pub fn case_sensitive(&mut self, case_sensitive: bool) -> &mut Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// The include patterns
+    pub fn includes(&self) -> &[Glob] {
+        &self.includes
+    }
+
+    /// The exclude patterns
+    pub fn excludes(&self) -> &[Glob] {
+        &self.excludes
+    }
+
+    /// Whether this set has no patterns, and so accepts everything
+    pub fn is_empty(&self) -> bool {
+        self.includes.is_empty() && self.excludes.is_empty()
+    }
+
+    /// Whether a path is accepted by this set
+    pub fn matches(&self, path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref();
+        let matches = |glob: &Glob| glob.matches_with(path, self.case_sensitive);
+        (self.includes.is_empty() || self.includes.iter().any(matches))
+            && !self.excludes.iter().any(matches)
+    }
+}
+
+impl Spec<Path> for PatternSet {
+    fn accept(&self, value: &Path) -> bool {
+        self.matches(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ant_style_globs() {
+        let glob = Glob::new("**/*.rs").unwrap();
+        assert!(glob.matches("lib.rs"));
+        assert!(glob.matches("src/bin/main.rs"));
+        assert!(!glob.matches("src/lib.rst"));
+
+        let glob = Glob::new("*.rs").unwrap();
+        assert!(glob.matches("lib.rs"));
+        assert!(!glob.matches("src/lib.rs"));
+
+        let glob = Glob::new("src/**/test/*.rs").unwrap();
+        assert!(glob.matches("src/test/a.rs"));
+        assert!(glob.matches("src/a/b/test/a.rs"));
+        assert!(!glob.matches("test/a.rs"));
+
+        let glob = Glob::new("target/").unwrap();
+        assert!(glob.matches("target/debug/app"));
+        assert!(!glob.matches("src/target"));
+
+        assert!(Glob::new("a**b/c").is_err());
+    }
+
+    #[test]
+    fn pattern_sets() {
+        let mut set = PatternSet::with_patterns(["**/*.txt"], ["nested/skip*"]).unwrap();
+        assert!(set.matches("a.txt"));
+        assert!(set.matches("nested/b.txt"));
+        assert!(!set.matches("nested/skip.txt"));
+        assert!(!set.matches("a.log"));
+        assert!(!set.matches("A.TXT"));
+        set.case_sensitive(false);
+        assert!(set.matches("A.TXT"));
+        assert!(PatternSet::new().matches("anything"));
+    }
+}
@@ -0,0 +1,262 @@
+//! Advisory locks on files, shared between processes.
+//!
+//! A lock is a file created next to what it protects, containing the id of the process holding
+//! it. Only one process can create the lock file at a time, and it's deleted when the lock is
+//! released. Processes wanting a held lock wait for it, reporting which process they're waiting on.
+//! A lock held by a process that's no longer running was left behind by a crash, and is taken
+//! over.
+//!
+//! Locks are advisory, so they only exclude other processes that take the same lock. They're also
+//! not reentrant: taking a lock already held by the current process waits forever.
+
+use crate::startup::cancellation::build_cancellation;
+use std::fmt::{Debug, Formatter};
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often a process waiting for a lock reports that it's still waiting
+pub const LOCK_PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A lock file that's still being written by the process that created it
+const OWNER_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// The process holding a lock
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockOwner {
+    /// The id of the process
+    pub pid: u32,
+    /// What the process is doing with the lock
+    pub purpose: String,
+}
+
+/// A held lock, released when dropped
+pub struct FileLock {
+    path: PathBuf,
+}
+
+impl Debug for FileLock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("FileLock").field(&self.path).finish()
+    }
+}
+
+impl FileLock {
+    /// Takes a lock if it's not held by another process
+    pub fn try_acquire(path: impl AsRef<Path>, purpose: &str) -> io::Result<Option<Self>> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        match OpenOptions::new().write(true).create_new(true).open(path) {
+            Ok(mut file) => {
+                let owner = LockOwner {
+                    pid: std::process::id(),
+                    purpose: purpose.to_string(),
+                };
+                file.write_all(&serde_json::to_vec(&owner)?)?;
+                trace!("acquired lock {:?} for {}", path, purpose);
+                Ok(Some(Self {
+                    path: path.to_path_buf(),
+                }))
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                if is_stale(path)? {
+                    warn!(
+                        "taking over lock {:?}, which was held by a process that's no longer running",
+                        path
+                    );
+                    match std::fs::remove_file(path) {
+                        Ok(()) => {}
+                        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                        Err(e) => return Err(e),
+                    }
+                    Self::try_acquire(path, purpose)
+                } else {
+                    Ok(None)
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Takes a lock, waiting for as long as another process holds it. Waiting stops if the build
+    /// is cancelled.
+    pub fn acquire(path: impl AsRef<Path>, purpose: &str) -> io::Result<Self> {
+        Self::acquire_within(path.as_ref(), purpose, None)
+    }
+
+    /// Takes a lock, waiting at most `timeout` for another process to release it
+    pub fn acquire_timeout(
+        path: impl AsRef<Path>,
+        purpose: &str,
+        timeout: Duration,
+    ) -> io::Result<Self> {
+        Self::acquire_within(path.as_ref(), purpose, Some(timeout))
+    }
+
+    fn acquire_within(path: &Path, purpose: &str, timeout: Option<Duration>) -> io::Result<Self> {
+        let start = Instant::now();
+        let mut reported: Option<Instant> = None;
+        loop {
+            if let Some(lock) = Self::try_acquire(path, purpose)? {
+                if reported.is_some() {
+                    info!(
+                        "acquired lock {:?} after waiting {}",
+                        path,
+                        crate::humanize::duration(start.elapsed())
+                    );
+                }
+                return Ok(lock);
+            }
+            if timeout.map_or(false, |timeout| start.elapsed() >= timeout) {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("timed out waiting for lock {:?} to {}", path, purpose),
+                ));
+            }
+            if let Some(reason) = build_cancellation().reason() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    format!("stopped waiting for lock {:?}: {}", path, reason),
+                ));
+            }
+            if reported.map_or(true, |last| last.elapsed() >= LOCK_PROGRESS_INTERVAL) {
+                match Self::owner(path) {
+                    Some(owner) => info!(
+                        "waiting to {}, {:?} is held by process {} to {}",
+                        purpose, path, owner.pid, owner.purpose
+                    ),
+                    None => info!(
+                        "waiting to {}, {:?} is held by another process",
+                        purpose, path
+                    ),
+                }
+                reported = Some(Instant::now());
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// The process holding a lock, if it's held
+    pub fn owner(path: impl AsRef<Path>) -> Option<LockOwner> {
+        let contents = std::fs::read(path).ok()?;
+        serde_json::from_slice(&contents).ok()
+    }
+
+    /// The path of the lock file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        trace!("releasing lock {:?}", self.path);
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            warn!("could not release lock {:?}: {}", self.path, e);
+        }
+    }
+}
+
+/// Whether a lock was left behind by a process that isn't running anymore
+fn is_stale(path: &Path) -> io::Result<bool> {
+    match FileLock::owner(path) {
+        Some(owner) => Ok(!process_alive(owner.pid)),
+        None => {
+            // the owner may not have been written yet
+            let age = match std::fs::metadata(path) {
+                Ok(metadata) => metadata.modified()?.elapsed().unwrap_or_default(),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(true),
+                Err(e) => return Err(e),
+            };
+            Ok(age > OWNER_GRACE_PERIOD)
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn process_alive(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn process_alive(pid: u32) -> bool {
+    std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(true)
+}
+
+#[cfg(windows)]
+fn process_alive(pid: u32) -> bool {
+    std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+        .unwrap_or(true)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn only_one_holder() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("test.lock");
+        let lock = FileLock::try_acquire(&path, "test").unwrap().unwrap();
+        assert_eq!(
+            FileLock::owner(&path),
+            Some(LockOwner {
+                pid: std::process::id(),
+                purpose: "test".to_string()
+            })
+        );
+        assert!(FileLock::try_acquire(&path, "test").unwrap().is_none());
+        let error =
+            FileLock::acquire_timeout(&path, "test", Duration::from_millis(100)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+
+        drop(lock);
+        assert!(!path.exists());
+        assert!(FileLock::try_acquire(&path, "test").unwrap().is_some());
+    }
+
+    #[test]
+    fn locks_of_dead_processes_are_taken_over() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("stale.lock");
+        let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+            .arg("--list")
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        let dead = child.id();
+        child.wait().unwrap();
+        std::fs::write(
+            &path,
+            serde_json::to_vec(&LockOwner {
+                pid: dead,
+                purpose: "crash".to_string(),
+            })
+            .unwrap(),
+        )
+        .unwrap();
+
+        let _lock = FileLock::acquire_timeout(&path, "test", Duration::from_secs(1)).unwrap();
+        assert_eq!(FileLock::owner(&path).unwrap().pid, std::process::id());
+    }
+}
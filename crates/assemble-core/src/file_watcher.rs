@@ -0,0 +1,493 @@
+//! Watches the file system for changes.
+//!
+//! The [`FileWatcher`](FileWatcher) wraps the native watch backend of the platform (inotify,
+//! FSEvents, or ReadDirectoryChangesW) and coalesces the raw events it produces into
+//! [`ChangeSet`s](ChangeSet). Many events for the same path within the quiet period are merged into a
+//! single change. When the backend reports that events were dropped, the affected roots are
+//! rescanned and compared against the last known state instead.
+//!
+//! Any number of subscribers can listen for change sets. Changes can be limited to the paths
+//! accepted by a [`PatternSet`](PatternSet), matched relative to their watched root.
+
+use crate::file_collection::patterns::PatternSet;
+use crossbeam::channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
+
+/// The default amount of time without events before a change set is emitted
+pub const DEFAULT_QUIET_PERIOD: Duration = Duration::from_millis(50);
+
+/// The kind of change that occurred to a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    /// The path was created
+    Created,
+    /// The contents or metadata of the path changed
+    Modified,
+    /// The path was removed
+    Removed,
+}
+
+impl ChangeKind {
+    /// Merges a later change into this change. Returns `None` if the changes cancel each other out.
+    fn merge(self, later: ChangeKind) -> Option<ChangeKind> {
+        use ChangeKind::*;
+        match (self, later) {
+            (Created, Removed) => None,
+            (Created, _) => Some(Created),
+            (Removed, Created) => Some(Modified),
+            (_, later) => Some(later),
+        }
+    }
+}
+
+/// A coalesced set of changes
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ChangeSet {
+    changes: BTreeMap<PathBuf, ChangeKind>,
+    rescanned: Vec<PathBuf>,
+}
+
+impl ChangeSet {
+    /// Adds a change to this change set, merging it with any previous change to the same path.
+    pub fn add<P: AsRef<Path>>(&mut self, path: P, kind: ChangeKind) {
+        let path = path.as_ref().to_path_buf();
+        match self.changes.remove(&path) {
+            None => {
+                self.changes.insert(path, kind);
+            }
+            Some(previous) => {
+                if let Some(merged) = previous.merge(kind) {
+                    self.changes.insert(path, merged);
+                }
+            }
+        }
+    }
+
+    /// Merges another change set into this one
+    pub fn extend(&mut self, other: ChangeSet) {
+        for (path, kind) in other.changes {
+            self.add(path, kind);
+        }
+        self.rescanned.extend(other.rescanned);
+    }
+
+    /// The changes within this change set
+    pub fn changes(&self) -> &BTreeMap<PathBuf, ChangeKind> {
+        &self.changes
+    }
+
+    /// Roots that had to be rescanned because the backend dropped events
+    pub fn rescanned(&self) -> &[PathBuf] {
+        &self.rescanned
+    }
+
+    /// Whether there are any changes
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.rescanned.is_empty()
+    }
+
+    /// Only keeps changes to paths accepted by a predicate
+    fn retain<F: FnMut(&Path) -> bool>(&mut self, mut predicate: F) {
+        self.changes.retain(|path, _| predicate(path));
+    }
+}
+
+/// The last known state of a file, used to detect changes when rescanning
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+    is_dir: bool,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::symlink_metadata(path).ok()?;
+        Some(Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            is_dir: metadata.is_dir(),
+        })
+    }
+}
+
+/// The snapshot of every file under a watched root
+#[derive(Debug, Default)]
+struct RootSnapshot {
+    stamps: HashMap<PathBuf, FileStamp>,
+}
+
+impl RootSnapshot {
+    fn scan(root: &Path) -> Self {
+        let stamps = WalkDir::new(root)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let path = entry.into_path();
+                FileStamp::of(&path).map(|stamp| (path, stamp))
+            })
+            .collect();
+        Self { stamps }
+    }
+
+    /// Rescans the root, returning the differences from the previous snapshot
+    fn rescan(&mut self, root: &Path) -> ChangeSet {
+        let next = Self::scan(root);
+        let mut changes = ChangeSet::default();
+        for (path, stamp) in &next.stamps {
+            match self.stamps.get(path) {
+                None => changes.add(path, ChangeKind::Created),
+                Some(prev) if prev != stamp => changes.add(path, ChangeKind::Modified),
+                _ => {}
+            }
+        }
+        for path in self.stamps.keys() {
+            if !next.stamps.contains_key(path) {
+                changes.add(path, ChangeKind::Removed);
+            }
+        }
+        changes.rescanned.push(root.to_path_buf());
+        *self = next;
+        changes
+    }
+
+    /// Updates the stamp of a single path after a normal event
+    fn update(&mut self, path: &Path) {
+        match FileStamp::of(path) {
+            Some(stamp) => {
+                self.stamps.insert(path.to_path_buf(), stamp);
+            }
+            None => {
+                self.stamps.retain(|p, _| !p.starts_with(path));
+            }
+        }
+    }
+}
+
+/// A raw message from the backend
+enum RawEvent {
+    Event(Event),
+    Overflow(Vec<PathBuf>),
+}
+
+#[derive(Default)]
+struct WatcherState {
+    roots: BTreeMap<PathBuf, RootSnapshot>,
+    subscribers: Vec<Sender<ChangeSet>>,
+    patterns: PatternSet,
+}
+
+impl WatcherState {
+    /// Gets the roots that contain a path. If no paths are given, every root is affected.
+    fn affected_roots(&self, paths: &[PathBuf]) -> Vec<PathBuf> {
+        if paths.is_empty() {
+            return self.roots.keys().cloned().collect();
+        }
+        self.roots
+            .keys()
+            .filter(|root| paths.iter().any(|p| p.starts_with(root)))
+            .cloned()
+            .collect()
+    }
+
+    fn apply(&mut self, event: RawEvent, change_set: &mut ChangeSet) {
+        match event {
+            RawEvent::Event(event) => {
+                if event.need_rescan() {
+                    self.apply(RawEvent::Overflow(event.paths), change_set);
+                    return;
+                }
+                let kinds = classify(&event);
+                for (path, kind) in event.paths.iter().zip(kinds) {
+                    if let Some(kind) = kind {
+                        change_set.add(path, kind);
+                        if let Some(snapshot) = self
+                            .roots
+                            .iter_mut()
+                            .find(|(root, _)| path.starts_with(root))
+                            .map(|(_, s)| s)
+                        {
+                            snapshot.update(path);
+                        }
+                    }
+                }
+            }
+            RawEvent::Overflow(paths) => {
+                for root in self.affected_roots(&paths) {
+                    warn!("file watcher overflowed, rescanning {:?}", root);
+                    if let Some(snapshot) = self.roots.get_mut(&root) {
+                        change_set.extend(snapshot.rescan(&root));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether a path is accepted by the patterns, relative to the root containing it
+    fn accepts(&self, path: &Path) -> bool {
+        let relative = self
+            .roots
+            .keys()
+            .find_map(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path);
+        self.patterns.matches(relative)
+    }
+
+    fn publish(&mut self, mut change_set: ChangeSet) {
+        change_set.retain(|path| self.accepts(path));
+        if change_set.is_empty() {
+            return;
+        }
+        self.subscribers
+            .retain(|subscriber| subscriber.send(change_set.clone()).is_ok());
+    }
+}
+
+/// Determines the change kind for each path within an event
+fn classify(event: &Event) -> Vec<Option<ChangeKind>> {
+    let single = |kind: Option<ChangeKind>| vec![kind; event.paths.len()];
+    match &event.kind {
+        EventKind::Create(_) => single(Some(ChangeKind::Created)),
+        EventKind::Remove(_) => single(Some(ChangeKind::Removed)),
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            single(Some(ChangeKind::Removed))
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => single(Some(ChangeKind::Created)),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            let mut kinds = single(Some(ChangeKind::Created));
+            if let Some(first) = kinds.first_mut() {
+                *first = Some(ChangeKind::Removed);
+            }
+            kinds
+        }
+        EventKind::Modify(_) | EventKind::Any => single(Some(ChangeKind::Modified)),
+        EventKind::Access(_) | EventKind::Other => single(None),
+    }
+}
+
+/// A service that watches roots on the file system and publishes coalesced change sets to
+/// subscribers.
+pub struct FileWatcher {
+    state: Arc<Mutex<WatcherState>>,
+    watcher: Mutex<RecommendedWatcher>,
+    stopped: Arc<AtomicBool>,
+    coalescer: Option<JoinHandle<()>>,
+}
+
+impl FileWatcher {
+    /// Creates a new file watcher with the default quiet period
+    pub fn new() -> io::Result<Self> {
+        Self::with_quiet_period(DEFAULT_QUIET_PERIOD)
+    }
+
+    /// Creates a new file watcher. Change sets are emitted once no events have been received for
+    /// `quiet_period`.
+    pub fn with_quiet_period(quiet_period: Duration) -> io::Result<Self> {
+        let (sender, receiver) = unbounded::<RawEvent>();
+        let watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+            let raw = match result {
+                Ok(event) => RawEvent::Event(event),
+                Err(error) => {
+                    debug!("file watcher backend error: {}", error);
+                    RawEvent::Overflow(error.paths)
+                }
+            };
+            let _ = sender.send(raw);
+        })
+        .map_err(into_io_error)?;
+
+        let state = Arc::new(Mutex::new(WatcherState::default()));
+        let stopped = Arc::new(AtomicBool::new(false));
+        let coalescer = {
+            let state = state.clone();
+            let stopped = stopped.clone();
+            std::thread::Builder::new()
+                .name("file-watcher".to_string())
+                .spawn(move || coalesce(receiver, state, stopped, quiet_period))?
+        };
+
+        Ok(Self {
+            state,
+            watcher: Mutex::new(watcher),
+            stopped,
+            coalescer: Some(coalescer),
+        })
+    }
+
+    /// Starts watching a root directory recursively
+    pub fn watch<P: AsRef<Path>>(&self, root: P) -> io::Result<()> {
+        let root = root.as_ref().to_path_buf();
+        let snapshot = RootSnapshot::scan(&root);
+        self.state.lock().roots.insert(root.clone(), snapshot);
+        self.watcher
+            .lock()
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(into_io_error)
+    }
+
+    /// Stops watching a root directory
+    pub fn unwatch<P: AsRef<Path>>(&self, root: P) -> io::Result<()> {
+        let root = root.as_ref();
+        self.state.lock().roots.remove(root);
+        self.watcher.lock().unwatch(root).map_err(into_io_error)
+    }
+
+    /// Gets the roots currently being watched
+    pub fn roots(&self) -> Vec<PathBuf> {
+        self.state.lock().roots.keys().cloned().collect()
+    }
+
+    /// Only publishes changes to paths accepted by a set of patterns, which are matched against
+    /// paths relative to their watched root. Replaces any patterns set before.
+    pub fn set_patterns(&self, patterns: PatternSet) {
+        self.state.lock().patterns = patterns;
+    }
+
+    /// Subscribe to change sets produced by this watcher
+    pub fn subscribe(&self) -> Receiver<ChangeSet> {
+        let (sender, receiver) = unbounded();
+        self.state.lock().subscribers.push(sender);
+        receiver
+    }
+}
+
+impl Debug for FileWatcher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileWatcher")
+            .field("roots", &self.roots())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.coalescer.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn coalesce(
+    receiver: Receiver<RawEvent>,
+    state: Arc<Mutex<WatcherState>>,
+    stopped: Arc<AtomicBool>,
+    quiet_period: Duration,
+) {
+    let mut pending = ChangeSet::default();
+    while !stopped.load(Ordering::Relaxed) {
+        match receiver.recv_timeout(quiet_period) {
+            Ok(event) => {
+                state.lock().apply(event, &mut pending);
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if !pending.is_empty() {
+                    let change_set = std::mem::take(&mut pending);
+                    trace!("publishing change set: {:#?}", change_set);
+                    state.lock().publish(change_set);
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+fn into_io_error(error: notify::Error) -> io::Error {
+    match error.kind {
+        notify::ErrorKind::Io(io) => io,
+        _ => io::Error::new(io::ErrorKind::Other, error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn coalesce_changes_to_same_path() {
+        let mut change_set = ChangeSet::default();
+        change_set.add("a", ChangeKind::Created);
+        change_set.add("a", ChangeKind::Modified);
+        change_set.add("b", ChangeKind::Modified);
+        change_set.add("b", ChangeKind::Removed);
+        change_set.add("c", ChangeKind::Created);
+        change_set.add("c", ChangeKind::Removed);
+        change_set.add("d", ChangeKind::Removed);
+        change_set.add("d", ChangeKind::Created);
+
+        let changes = change_set.changes();
+        assert_eq!(changes.get(Path::new("a")), Some(&ChangeKind::Created));
+        assert_eq!(changes.get(Path::new("b")), Some(&ChangeKind::Removed));
+        assert_eq!(changes.get(Path::new("c")), None);
+        assert_eq!(changes.get(Path::new("d")), Some(&ChangeKind::Modified));
+    }
+
+    #[test]
+    fn published_changes_match_patterns() {
+        let root = PathBuf::from("root");
+        let mut state = WatcherState::default();
+        state.roots.insert(root.clone(), RootSnapshot::default());
+        state.patterns = PatternSet::with_patterns(["src/**"], ["**/*.tmp"]).unwrap();
+        let (sender, receiver) = unbounded();
+        state.subscribers.push(sender);
+
+        let mut change_set = ChangeSet::default();
+        change_set.add(root.join("src/lib.rs"), ChangeKind::Modified);
+        change_set.add(root.join("src/lib.tmp"), ChangeKind::Created);
+        change_set.add(root.join("target/app"), ChangeKind::Created);
+        state.publish(change_set);
+        let published = receiver.try_recv().unwrap();
+        assert_eq!(
+            published.changes().keys().collect::<Vec<_>>(),
+            [&root.join("src/lib.rs")]
+        );
+
+        let mut ignored = ChangeSet::default();
+        ignored.add(root.join("target/app"), ChangeKind::Modified);
+        state.publish(ignored);
+        assert!(receiver.try_recv().is_err(), "nothing left to publish");
+    }
+
+    #[test]
+    fn overflow_rescans_affected_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_path_buf();
+        let kept = root.join("kept.txt");
+        let removed = root.join("removed.txt");
+        std::fs::write(&kept, "kept").unwrap();
+        std::fs::write(&removed, "removed").unwrap();
+
+        let mut state = WatcherState::default();
+        state.roots.insert(root.clone(), RootSnapshot::scan(&root));
+
+        std::fs::remove_file(&removed).unwrap();
+        let created = root.join("created.txt");
+        std::fs::write(&created, "created").unwrap();
+
+        let mut change_set = ChangeSet::default();
+        state.apply(RawEvent::Overflow(vec![]), &mut change_set);
+
+        assert_eq!(change_set.rescanned(), &[root]);
+        assert_eq!(
+            change_set.changes().get(&created),
+            Some(&ChangeKind::Created)
+        );
+        assert_eq!(
+            change_set.changes().get(&removed),
+            Some(&ChangeKind::Removed)
+        );
+        assert_eq!(change_set.changes().get(&kept), None);
+    }
+}
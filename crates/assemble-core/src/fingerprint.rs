@@ -1,5 +1,7 @@
 //! Provides ways to "fingerprint" something
 
+pub mod files;
+
 pub const FINGER_PRINT_SIZE: usize = 32;
 
 pub trait Fingerprint<const FINGER_PRINT: usize = FINGER_PRINT_SIZE> {
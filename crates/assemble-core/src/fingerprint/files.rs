@@ -0,0 +1,186 @@
+//! Fingerprints of the contents of files.
+//!
+//! Hashing every input file of every task is expensive, so the hash of each file is cached by its
+//! path, size and modification time. A file that hasn't changed since it was last hashed isn't read
+//! again. Many files are hashed in parallel with [`fingerprint_files`](fingerprint_files).
+
+use crate::cryptography::{hash_file_sha256, Sha256};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+/// Fewer files than this are hashed on the calling thread
+const PARALLEL_THRESHOLD: usize = 8;
+
+static GLOBAL_CACHE: Lazy<FingerprintCache> = Lazy::new(FingerprintCache::new);
+
+/// What's known about a file when it was hashed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+/// A cache of the hashes of files, keyed by their path, size and modification time
+#[derive(Debug, Default)]
+pub struct FingerprintCache {
+    hashes: RwLock<HashMap<PathBuf, (FileStamp, Sha256)>>,
+}
+
+impl FingerprintCache {
+    /// Creates an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cache shared by the whole build
+    pub fn global() -> &'static FingerprintCache {
+        &GLOBAL_CACHE
+    }
+
+    /// Gets the hash of the contents of a file, only reading the file if it changed since it was
+    /// last hashed
+    pub fn fingerprint(&self, path: impl AsRef<Path>) -> io::Result<Sha256> {
+        let path = path.as_ref();
+        let metadata = std::fs::metadata(path)?;
+        let stamp = FileStamp {
+            modified: metadata.modified()?,
+            len: metadata.len(),
+        };
+        if let Some((cached, hash)) = self.hashes.read().get(path) {
+            if *cached == stamp {
+                return Ok(*hash);
+            }
+        }
+
+        let hash = hash_file_sha256(path)?;
+        self.hashes
+            .write()
+            .insert(path.to_path_buf(), (stamp, hash));
+        Ok(hash)
+    }
+
+    /// Gets the hashes of many files, reading files that changed in parallel
+    pub fn fingerprint_all<I>(&self, paths: I) -> HashMap<PathBuf, io::Result<Sha256>>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<Path>,
+    {
+        let paths = paths
+            .into_iter()
+            .map(|path| path.as_ref().to_path_buf())
+            .collect::<Vec<_>>();
+        let workers = std::thread::available_parallelism()
+            .map(NonZeroUsize::get)
+            .unwrap_or(1)
+            .min(paths.len() / PARALLEL_THRESHOLD);
+        if workers <= 1 {
+            return paths
+                .into_iter()
+                .map(|path| {
+                    let hash = self.fingerprint(&path);
+                    (path, hash)
+                })
+                .collect();
+        }
+
+        let next = AtomicUsize::new(0);
+        std::thread::scope(|scope| {
+            let handles = (0..workers)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut hashes = vec![];
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            match paths.get(index) {
+                                Some(path) => hashes.push((path.clone(), self.fingerprint(path))),
+                                None => break hashes,
+                            }
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("fingerprinting thread panicked"))
+                .collect()
+        })
+    }
+
+    /// Forgets every cached hash
+    pub fn clear(&self) {
+        self.hashes.write().clear();
+    }
+
+    /// The number of files with a cached hash
+    pub fn len(&self) -> usize {
+        self.hashes.read().len()
+    }
+
+    /// Whether no files have a cached hash
+    pub fn is_empty(&self) -> bool {
+        self.hashes.read().is_empty()
+    }
+}
+
+/// Gets the hash of the contents of a file using the global cache
+pub fn fingerprint_file(path: impl AsRef<Path>) -> io::Result<Sha256> {
+    FingerprintCache::global().fingerprint(path)
+}
+
+/// Gets the hashes of many files in parallel using the global cache
+pub fn fingerprint_files<I>(paths: I) -> HashMap<PathBuf, io::Result<Sha256>>
+where
+    I: IntoIterator,
+    I::Item: AsRef<Path>,
+{
+    FingerprintCache::global().fingerprint_all(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cryptography::hash_sha256;
+    use tempfile::TempDir;
+
+    #[test]
+    fn hashes_files_in_parallel() {
+        let dir = TempDir::new().unwrap();
+        let paths = (0..64)
+            .map(|i| {
+                let path = dir.path().join(format!("{}.txt", i));
+                std::fs::write(&path, i.to_string()).unwrap();
+                path
+            })
+            .collect::<Vec<_>>();
+
+        let cache = FingerprintCache::new();
+        let hashes = cache.fingerprint_all(&paths);
+        assert_eq!(hashes.len(), 64);
+        assert_eq!(cache.len(), 64);
+        for (i, path) in paths.iter().enumerate() {
+            assert_eq!(hashes[path].as_ref().unwrap(), &hash_sha256(&i.to_string()));
+        }
+        assert!(cache
+            .fingerprint_all([dir.path().join("missing")])
+            .values()
+            .all(Result::is_err));
+    }
+
+    #[test]
+    fn changed_files_are_rehashed() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("file.txt");
+        std::fs::write(&path, "first").unwrap();
+        let cache = FingerprintCache::new();
+        assert_eq!(cache.fingerprint(&path).unwrap(), hash_sha256("first"));
+
+        std::fs::write(&path, "second!").unwrap();
+        assert_eq!(cache.fingerprint(&path).unwrap(), hash_sha256("second!"));
+    }
+}
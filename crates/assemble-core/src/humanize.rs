@@ -0,0 +1,143 @@
+//! Human readable durations, byte sizes and counts.
+//!
+//! All text shown to users about how long something took, how large something is, or how many of
+//! something there are should be formatted with the functions in this module, so that values are
+//! rounded the same way everywhere. Output never depends on the locale of the system: decimals
+//! always use a `.`, and units are always in english.
+//!
+//! Formatting is done by the current [`Humanizer`](Humanizer), which is the
+//! [`DefaultHumanizer`](DefaultHumanizer) unless replaced with [`set_humanizer`](set_humanizer).
+//!
+//! # Example
+//! ```
+//! # use std::time::Duration;
+//! # use assemble_core::humanize;
+//! assert_eq!(humanize::duration(Duration::from_millis(1520)), "1.52s");
+//! assert_eq!(humanize::bytes(1536), "1.5 KiB");
+//! assert_eq!(humanize::count(3, "task"), "3 tasks");
+//! ```
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Formats values for people to read
+pub trait Humanizer: Send + Sync {
+    /// Formats a duration
+    fn duration(&self, duration: Duration) -> String;
+    /// Formats a size in bytes
+    fn bytes(&self, bytes: u64) -> String;
+    /// Formats an amount of something, such as `1 task` or `3 tasks`
+    fn count(&self, count: u64, noun: &str) -> String;
+}
+
+/// The humanizer used unless another one is set.
+///
+/// - Durations under a second are shown in milliseconds, durations under a minute in seconds with
+///   two decimals, and longer durations in minutes and seconds, or hours, minutes and seconds.
+/// - Sizes use binary units, with one decimal once they're at least a KiB.
+/// - Counts are pluralized by adding an `s` to the noun when the count isn't 1.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultHumanizer;
+
+impl Humanizer for DefaultHumanizer {
+    fn duration(&self, duration: Duration) -> String {
+        let millis = duration.as_millis();
+        if millis < 1000 {
+            return format!("{}ms", millis);
+        }
+        // round to the hundredth of a second once, so that every branch agrees
+        let centis = (duration.as_micros() + 5_000) / 10_000;
+        if centis < 6_000 {
+            return format!("{}.{:02}s", centis / 100, centis % 100);
+        }
+        let seconds = (centis + 50) / 100;
+        let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+        if hours == 0 {
+            format!("{}m {:02}s", minutes, seconds)
+        } else {
+            format!("{}h {:02}m {:02}s", hours, minutes, seconds)
+        }
+    }
+
+    fn bytes(&self, bytes: u64) -> String {
+        const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+        if bytes < 1024 {
+            return format!("{} B", bytes);
+        }
+        let mut value = bytes as f64 / 1024.0;
+        let mut unit = 0;
+        // values that would round up to 1024.0 are shown in the next unit instead
+        while value >= 1023.95 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+
+    fn count(&self, count: u64, noun: &str) -> String {
+        if count == 1 {
+            format!("{} {}", count, noun)
+        } else {
+            format!("{} {}s", count, noun)
+        }
+    }
+}
+
+static HUMANIZER: Lazy<RwLock<Arc<dyn Humanizer>>> =
+    Lazy::new(|| RwLock::new(Arc::new(DefaultHumanizer)));
+
+/// Replaces the humanizer used by this module
+pub fn set_humanizer<H: Humanizer + 'static>(humanizer: H) {
+    *HUMANIZER.write() = Arc::new(humanizer);
+}
+
+/// Gets the current humanizer
+pub fn humanizer() -> Arc<dyn Humanizer> {
+    HUMANIZER.read().clone()
+}
+
+/// Formats a duration with the current humanizer
+pub fn duration(duration: Duration) -> String {
+    humanizer().duration(duration)
+}
+
+/// Formats a size in bytes with the current humanizer
+pub fn bytes(bytes: u64) -> String {
+    humanizer().bytes(bytes)
+}
+
+/// Formats an amount of something with the current humanizer
+pub fn count(count: u64, noun: &str) -> String {
+    humanizer().count(count, noun)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_formatting() {
+        let humanizer = DefaultHumanizer;
+        let duration = |millis| humanizer.duration(Duration::from_millis(millis));
+        assert_eq!(duration(0), "0ms");
+        assert_eq!(duration(999), "999ms");
+        assert_eq!(duration(1_004), "1.00s");
+        assert_eq!(duration(1_005), "1.01s");
+        assert_eq!(duration(59_994), "59.99s");
+        assert_eq!(duration(59_995), "1m 00s");
+        assert_eq!(duration(61_500), "1m 02s");
+        assert_eq!(duration(3_723_000), "1h 02m 03s");
+
+        assert_eq!(humanizer.bytes(0), "0 B");
+        assert_eq!(humanizer.bytes(1023), "1023 B");
+        assert_eq!(humanizer.bytes(1024), "1.0 KiB");
+        assert_eq!(humanizer.bytes(1024 * 1024 - 1), "1.0 MiB");
+        assert_eq!(humanizer.bytes(5 * 1024 * 1024 * 1024 / 2), "2.5 GiB");
+        assert_eq!(humanizer.bytes(u64::MAX), "16.0 EiB");
+
+        assert_eq!(humanizer.count(0, "task"), "0 tasks");
+        assert_eq!(humanizer.count(1, "task"), "1 task");
+    }
+}
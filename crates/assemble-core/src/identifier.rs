@@ -1,17 +1,20 @@
 //! Identifiers are used by lazy_evaluation, tasks, and projects.
 
-use crate::lazy_evaluation::{Prop, VecProp};
+use crate::lazy_evaluation::{MapProp, Prop, VecProp};
 use crate::prelude::ProjectResult;
 use crate::project::buildable::Buildable;
 
 use crate::Project;
 use itertools::Itertools;
 use once_cell::sync::Lazy;
+use parking_lot::Mutex;
 use regex::Regex;
 use serde::de::Error as _;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::{HashSet, VecDeque};
 use std::error::Error;
+use std::hash::Hash;
+use std::sync::Arc;
 
 use crate::project::finder::TaskFinder;
 use std::fmt::{Debug, Display, Formatter};
@@ -19,6 +22,9 @@ use std::ops::Deref;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+mod interned;
+pub use interned::*;
+
 /// The separator between parts of an identifier
 pub const ID_SEPARATOR: char = ':';
 
@@ -26,10 +32,37 @@ pub const ID_SEPARATOR: char = ':';
 ///
 /// Acts like a path. Consists for two parts, the `this` part and the `parent`. For example, in
 /// `root:inner:task`, the `this` is `task` and the `parent` is `root:inner`.
-#[derive(Default, Clone, Eq, PartialEq, Hash)]
+///
+/// Parents are shared and the parts of an identifier are interned, so cloning an id never
+/// allocates.
+#[derive(Clone, Eq, PartialEq, Hash)]
 pub struct Id {
-    parent: Option<Box<Id>>,
-    this: String,
+    parent: Option<Arc<Id>>,
+    this: Arc<str>,
+}
+
+impl Default for Id {
+    fn default() -> Self {
+        Self {
+            parent: None,
+            this: intern_part(""),
+        }
+    }
+}
+
+/// Interns a part of an identifier, so that every id using the same part shares one allocation
+fn intern_part(part: &str) -> Arc<str> {
+    static PARTS: Lazy<Mutex<HashSet<Arc<str>>>> = Lazy::new(Default::default);
+
+    let mut parts = PARTS.lock();
+    match parts.get(part) {
+        Some(interned) => interned.clone(),
+        None => {
+            let interned: Arc<str> = Arc::from(part);
+            parts.insert(interned.clone());
+            interned
+        }
+    }
 }
 
 impl Serialize for Id {
@@ -88,7 +121,7 @@ impl Id {
             as_str = &as_str[1..];
         }
         let split = as_str.split(ID_SEPARATOR);
-        Self::from_iter(split)
+        Self::from_iter(split).map_err(|e| e.in_input(val.as_ref()))
     }
 
     /// Create a new id that can't be checked
@@ -111,7 +144,7 @@ impl Id {
         let mut iterator = iter.into_iter();
         let start = iterator
             .next()
-            .ok_or(InvalidId::new(""))
+            .ok_or_else(|| InvalidId::new(""))
             .and_then(|u| Self::new_unit(u.as_ref()))?;
 
         iterator.try_fold(start, |accum, obj| {
@@ -123,7 +156,7 @@ impl Id {
     fn new_unit(id: &str) -> Result<Self, InvalidId> {
         is_valid_identifier(id).map(|_| Id {
             parent: None,
-            this: id.to_string(),
+            this: intern_part(id),
         })
     }
 
@@ -144,8 +177,8 @@ impl Id {
 
     fn insert_as_topmost(&mut self, parent: Self) {
         match &mut self.parent {
-            Some(p) => p.insert_as_topmost(parent),
-            missing => *missing = Some(Box::new(parent)),
+            Some(p) => Arc::make_mut(p).insert_as_topmost(parent),
+            missing => *missing = Some(Arc::new(parent)),
         }
     }
 
@@ -161,7 +194,7 @@ impl Id {
 
     /// Returns the parent identifier of this id, if it exists.
     pub fn parent(&self) -> Option<&Id> {
-        self.parent.as_ref().map(|boxed| boxed.as_ref())
+        self.parent.as_deref()
     }
 
     /// Check if the given representation is a valid shorthand.
@@ -194,8 +227,8 @@ impl Id {
         vec_dequeue.push_front(self);
         let mut ptr = self;
         while let Some(parent) = ptr.parent.as_ref() {
-            vec_dequeue.push_back(parent);
-            ptr = parent;
+            vec_dequeue.push_back(&**parent);
+            ptr = &**parent;
         }
 
         vec_dequeue.into_iter()
@@ -266,6 +299,16 @@ impl TaskId {
         Ok(VecProp::new(id))
     }
 
+    /// Creates a new map property. Does not register said property
+    pub fn map_prop<K, V>(&self, name: &str) -> Result<MapProp<K, V>, InvalidId>
+    where
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+        V: Clone + Send + Sync + 'static,
+    {
+        let id = self.join(name)?;
+        Ok(MapProp::new(id))
+    }
+
     /// Gets the project id that contains this task.
     pub fn project_id(&self) -> Option<ProjectId> {
         self.parent().map(|id| ProjectId(id.clone()))
@@ -356,7 +399,10 @@ impl ProjectId {
         }
         let iter = path
             .iter()
-            .map(|s| s.to_str().ok_or(InvalidId::new(&path.to_string_lossy())))
+            .map(|s| {
+                s.to_str()
+                    .ok_or_else(|| InvalidId::new(&path.to_string_lossy()))
+            })
             .collect::<Result<Vec<_>, _>>()?;
         Id::from_iter(iter).map(Self)
     }
@@ -439,7 +485,10 @@ macro_rules! deref_to_id {
             fn from_str(s: &str) -> Result<Self, Self::Err> {
                 let mut output: Option<Id> = None;
                 if !s.starts_with(':') {
-                    return Err(InvalidId::new(s));
+                    return Err(InvalidId::with_reason(
+                        s,
+                        InvalidIdReason::MissingLeadingSeparator,
+                    ));
                 }
 
                 for task_part in s[1..].split(":") {
@@ -450,7 +499,7 @@ macro_rules! deref_to_id {
                 }
                 output
                     .map(|id| <$ty>::from(id))
-                    .ok_or(InvalidId(s.to_string()))
+                    .ok_or_else(|| InvalidId::new(s))
             }
         }
     };
@@ -475,37 +524,186 @@ impl TaskIdFactory {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
-pub struct InvalidId(pub String);
+/// An identifier, or a part of an identifier, is invalid.
+///
+/// The error explains which character was rejected, and when possible suggests a similar name
+/// that is valid.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct InvalidId {
+    id: String,
+    reason: InvalidIdReason,
+    suggestion: Option<String>,
+    input: Option<String>,
+}
+
+/// Why an identifier is invalid
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum InvalidIdReason {
+    /// The identifier, or a part of it, is empty
+    Empty,
+    /// The identifier doesn't start with an ascii letter
+    InvalidStart(char),
+    /// The identifier contains a character that isn't allowed
+    InvalidCharacter {
+        /// The rejected character
+        character: char,
+        /// The byte offset of the character
+        index: usize,
+    },
+    /// A fully qualified identifier was expected, but it didn't start with `:`
+    MissingLeadingSeparator,
+}
+
+impl Display for InvalidIdReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidIdReason::Empty => write!(f, "identifiers can not be empty"),
+            InvalidIdReason::InvalidStart(c) => {
+                write!(
+                    f,
+                    "identifiers must start with an ascii letter, found {:?}",
+                    c
+                )
+            }
+            InvalidIdReason::InvalidCharacter { character, index } => {
+                write!(f, "{:?} at position {} is not allowed", character, index)
+            }
+            InvalidIdReason::MissingLeadingSeparator => {
+                write!(
+                    f,
+                    "fully qualified identifiers must start with {:?}",
+                    ID_SEPARATOR
+                )
+            }
+        }
+    }
+}
 
 impl InvalidId {
+    /// Creates an error for an invalid identifier, determining why it's invalid
     pub fn new(string: impl AsRef<str>) -> Self {
-        Self(string.as_ref().to_string())
+        let id = string.as_ref();
+        let reason = invalid_reason(id).unwrap_or(InvalidIdReason::Empty);
+        Self::with_reason(id, reason)
+    }
+
+    /// Creates an error for an invalid identifier with a known reason
+    pub fn with_reason(string: impl AsRef<str>, reason: InvalidIdReason) -> Self {
+        let id = string.as_ref().to_string();
+        let suggestion = suggest_identifier(&id).filter(|suggestion| suggestion != &id);
+        Self {
+            id,
+            reason,
+            suggestion,
+            input: None,
+        }
+    }
+
+    /// Sets the full input the invalid identifier was found in
+    pub fn in_input(mut self, input: impl AsRef<str>) -> Self {
+        let input = input.as_ref();
+        if input != self.id {
+            self.input = Some(input.to_string());
+        }
+        self
+    }
+
+    /// The invalid identifier
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Why the identifier is invalid
+    pub fn reason(&self) -> &InvalidIdReason {
+        &self.reason
+    }
+
+    /// A valid identifier similar to the invalid one, if one could be found
+    pub fn suggestion(&self) -> Option<&str> {
+        self.suggestion.as_deref()
     }
 }
 
 impl Display for InvalidId {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Invalid Identifier {:?}", self.0)
+        write!(f, "Invalid Identifier {:?}", self.id)?;
+        if let Some(input) = &self.input {
+            write!(f, " in {:?}", input)?;
+        }
+        write!(f, ": {}", self.reason)?;
+        if !matches!(self.reason, InvalidIdReason::MissingLeadingSeparator) {
+            write!(
+                f,
+                " (identifiers start with an ascii letter, followed by letters, digits, '_' or '-')"
+            )?;
+        }
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, ". Did you mean {:?}?", suggestion)?;
+        }
+        Ok(())
     }
 }
 
 impl Error for InvalidId {}
 
+static VALID_ID_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"[a-zA-Z][\w-]*").expect("Invalid Pattern"));
+static VALID_ID_CHAR: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[\w-]$").expect("Invalid Pattern"));
+
+fn is_valid_id_char(c: char) -> bool {
+    VALID_ID_CHAR.is_match(c.encode_utf8(&mut [0; 4]))
+}
+
+/// Finds why a part of an identifier is invalid, if it is
+fn invalid_reason(id: &str) -> Option<InvalidIdReason> {
+    let mut chars = id.char_indices();
+    let (_, first) = match chars.next() {
+        None => return Some(InvalidIdReason::Empty),
+        Some(first) => first,
+    };
+    if !first.is_ascii_alphabetic() {
+        return Some(InvalidIdReason::InvalidStart(first));
+    }
+    chars
+        .find(|(_, c)| !is_valid_id_char(*c))
+        .map(|(index, character)| InvalidIdReason::InvalidCharacter { character, index })
+}
+
+/// Suggests a valid identifier close to an invalid one. Characters that aren't allowed split the
+/// name into words which are joined in camel case, and anything before the first ascii letter is
+/// dropped.
+///
+/// # Example
+/// ```
+/// # use assemble_core::identifier::suggest_identifier;
+/// assert_eq!(suggest_identifier("compile.main"), Some("compileMain".to_string()));
+/// assert_eq!(suggest_identifier("2fast"), Some("fast".to_string()));
+/// assert_eq!(suggest_identifier("123"), None);
+/// ```
+pub fn suggest_identifier(id: &str) -> Option<String> {
+    let start = id.find(|c: char| c.is_ascii_alphabetic())?;
+    let mut suggestion = String::new();
+    for (i, word) in id[start..]
+        .split(|c: char| !is_valid_id_char(c))
+        .filter(|word| !word.is_empty())
+        .enumerate()
+    {
+        let mut chars = word.chars();
+        if i > 0 {
+            suggestion.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+        }
+        suggestion.extend(chars);
+    }
+    invalid_reason(&suggestion).is_none().then(|| suggestion)
+}
+
+/// Checks whether a string is a valid part of an identifier. Parts must start with an ascii
+/// letter, followed by any number of letters, digits, `_` or `-`.
 pub fn is_valid_identifier(id: &str) -> Result<(), InvalidId> {
-    static VALID_ID_PATTERN: Lazy<Regex> =
-        Lazy::new(|| Regex::new(r"[a-zA-Z][\w-]*").expect("Invalid Pattern"));
-
-    VALID_ID_PATTERN
-        .find(id)
-        .ok_or(InvalidId::new(id))
-        .and_then(|mat| {
-            if mat.as_str() == id {
-                Ok(())
-            } else {
-                Err(InvalidId::new(id))
-            }
-        })
+    match VALID_ID_PATTERN.find(id) {
+        Some(mat) if mat.as_str() == id => Ok(()),
+        _ => Err(InvalidId::new(id)),
+    }
 }
 
 pub struct Iter<'id> {
@@ -531,7 +729,7 @@ impl<'i> Iterator for Iter<'i> {
 
 #[cfg(test)]
 mod tests {
-    use crate::identifier::Id;
+    use crate::identifier::{Id, InvalidIdReason};
 
     #[test]
     fn from_string() {
@@ -567,6 +765,40 @@ mod tests {
         assert_eq!(ancestors.next(), None);
     }
 
+    #[test]
+    fn invalid_ids_explain_themselves() {
+        let error = Id::new("root:compile.main").unwrap_err();
+        assert_eq!(error.id(), "compile.main");
+        assert_eq!(
+            error.reason(),
+            &InvalidIdReason::InvalidCharacter {
+                character: '.',
+                index: 7
+            }
+        );
+        assert_eq!(error.suggestion(), Some("compileMain"));
+        let message = error.to_string();
+        assert!(message.contains("\"root:compile.main\""), "{}", message);
+        assert!(
+            message.contains("Did you mean \"compileMain\"?"),
+            "{}",
+            message
+        );
+
+        let error = Id::new("2fast").unwrap_err();
+        assert_eq!(error.reason(), &InvalidIdReason::InvalidStart('2'));
+        assert_eq!(error.suggestion(), Some("fast"));
+
+        assert_eq!(
+            Id::new("gef::as").unwrap_err().reason(),
+            &InvalidIdReason::Empty
+        );
+        assert!(
+            Id::new("tâche").is_ok(),
+            "letters after the first may be unicode"
+        );
+    }
+
     #[test]
     fn is_shorthand() {
         let id = Id::from_iter(&["project", "task"]).unwrap();
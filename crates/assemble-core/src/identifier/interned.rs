@@ -0,0 +1,172 @@
+//! Interned identifiers.
+//!
+//! An [`Interned`](Interned) identifier is a `Copy` handle to an id stored in a process wide
+//! registry. Handles are compared and hashed by their index, which makes them cheap keys for
+//! large maps and graphs. Indices are not stable between processes, so handles are serialized
+//! as the id they point to, and re-interned when deserialized.
+
+use super::{Id, InvalidId, ProjectId, TaskId};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::str::FromStr;
+
+/// Identifier types that can be interned
+pub trait Internable: Deref<Target = Id> + From<Id> + FromStr<Err = InvalidId> {}
+
+impl Internable for TaskId {}
+impl Internable for ProjectId {}
+
+/// An interned task id
+pub type InternedTaskId = Interned<TaskId>;
+/// An interned project id
+pub type InternedProjectId = Interned<ProjectId>;
+
+#[derive(Default)]
+struct IdRegistry {
+    ids: Vec<Id>,
+    indices: HashMap<Id, u32>,
+}
+
+static REGISTRY: Lazy<RwLock<IdRegistry>> = Lazy::new(Default::default);
+
+/// A `Copy` handle to an interned identifier
+pub struct Interned<I: Internable> {
+    index: u32,
+    _kind: PhantomData<fn() -> I>,
+}
+
+impl<I: Internable> Interned<I> {
+    /// Interns an identifier. Interning the same identifier again returns an equal handle.
+    pub fn new(id: &I) -> Self {
+        let id: &Id = id;
+        if let Some(&index) = REGISTRY.read().indices.get(id) {
+            return Self::from_index(index);
+        }
+        let mut registry = REGISTRY.write();
+        if let Some(&index) = registry.indices.get(id) {
+            return Self::from_index(index);
+        }
+        let index = u32::try_from(registry.ids.len()).expect("too many interned identifiers");
+        registry.ids.push(id.clone());
+        registry.indices.insert(id.clone(), index);
+        Self::from_index(index)
+    }
+
+    fn from_index(index: u32) -> Self {
+        Self {
+            index,
+            _kind: PhantomData,
+        }
+    }
+
+    /// Gets the identifier this handle points to
+    pub fn get(&self) -> I {
+        I::from(REGISTRY.read().ids[self.index as usize].clone())
+    }
+}
+
+impl TaskId {
+    /// Interns this task id
+    pub fn intern(&self) -> InternedTaskId {
+        Interned::new(self)
+    }
+}
+
+impl ProjectId {
+    /// Interns this project id
+    pub fn intern(&self) -> InternedProjectId {
+        Interned::new(self)
+    }
+}
+
+impl<I: Internable> Clone for Interned<I> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<I: Internable> Copy for Interned<I> {}
+
+impl<I: Internable> PartialEq for Interned<I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<I: Internable> Eq for Interned<I> {}
+
+impl<I: Internable> Hash for Interned<I> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state)
+    }
+}
+
+impl<I: Internable> From<&I> for Interned<I> {
+    fn from(id: &I) -> Self {
+        Self::new(id)
+    }
+}
+
+impl<I: Internable> Display for Interned<I> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.get().deref())
+    }
+}
+
+impl<I: Internable> Debug for Interned<I> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.get().deref())
+    }
+}
+
+impl<I: Internable> Serialize for Interned<I> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, I: Internable> Deserialize<'de> for Interned<I> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = String::deserialize(deserializer)?;
+        let id = I::from_str(&string).map_err(|e| D::Error::custom(e.to_string()))?;
+        Ok(Self::new(&id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interning_is_idempotent() {
+        let id = TaskId::new("root:inner:build").unwrap();
+        let first = id.intern();
+        let second = TaskId::new(":root:inner:build").unwrap().intern();
+        assert_eq!(first, second);
+        assert_ne!(first, TaskId::new("root:inner:test").unwrap().intern());
+        assert_eq!(first.get(), id);
+        assert_eq!(first.to_string(), ":root:inner:build");
+    }
+
+    #[test]
+    fn serializes_as_id() {
+        let interned = ProjectId::new("root:child").unwrap().intern();
+        let json = serde_json::to_string(&interned).unwrap();
+        assert_eq!(json, "\":root:child\"");
+        let back: InternedProjectId = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, interned);
+    }
+}
@@ -25,13 +25,18 @@
 //!
 
 pub mod anonymous;
+pub mod environment;
 pub mod prop;
 pub mod providers;
+pub mod value_source;
 
-use crate::lazy_evaluation::providers::{FlatMap, Flatten, Map, Zip};
-use crate::Project;
 use crate::__export::{ProjectResult, TaskId};
+use crate::lazy_evaluation::providers::{
+    Filter, FlatMap, Flatten, FnProvider, Map, Memoize, OrElse, Zip, Zip3, ZipMany,
+};
 use crate::project::buildable::Buildable;
+use crate::Project;
+pub use environment::Providers;
 pub use prop::*;
 use std::collections::HashSet;
 use std::fmt::{Debug, Formatter};
@@ -163,6 +168,72 @@ pub trait ProviderExt<T: Clone + Send + Sync>: Provider<T> + Sized {
     {
         Zip::new(self, other, func)
     }
+
+    /// Creates a provider that can map the output of three providers with types `T1`, `T2` and `T3`
+    /// into some other value of type `R`.
+    ///
+    /// `transform`: `fn(T1, T2, T3) -> R`
+    fn zip3<P2, P3, B, C, R, F>(self, second: P2, third: P3, func: F) -> Zip3<T, B, C, R, F>
+    where
+        Self: 'static,
+        P2: IntoProvider<B>,
+        <P2 as IntoProvider<B>>::Provider: 'static,
+        P3: IntoProvider<C>,
+        <P3 as IntoProvider<C>>::Provider: 'static,
+        B: Send + Sync + Clone,
+        C: Send + Sync + Clone,
+        R: Send + Sync + Clone,
+        F: Fn(T, B, C) -> R + Send + Sync,
+    {
+        Zip3::new(self, second, third, func)
+    }
+
+    /// Creates a provider of the values of this provider followed by the values of `others`. The
+    /// created provider only has a value when all providers have a value.
+    fn zip_many<I, P>(self, others: I) -> ZipMany<T>
+    where
+        Self: 'static,
+        I: IntoIterator<Item = P>,
+        P: IntoProvider<T>,
+        <P as IntoProvider<T>>::Provider: 'static,
+    {
+        let mut zipped = ZipMany::new([self]);
+        zipped.extend(others);
+        zipped
+    }
+
+    /// Creates a provider that only has a value when the value of this provider matches the
+    /// predicate.
+    ///
+    /// `predicate`: `fn(&T) -> bool`
+    fn filter<F>(self, predicate: F) -> Filter<T, Self, F>
+    where
+        F: Fn(&T) -> bool + Send + Sync,
+    {
+        Filter::new(self, predicate)
+    }
+
+    /// Creates a provider that uses the value of `other` when this provider has no value.
+    fn or_else<P>(self, other: P) -> OrElse<T, Self, P::Provider>
+    where
+        P: IntoProvider<T>,
+    {
+        OrElse::new(self, other.into_provider())
+    }
+
+    /// Creates a provider that uses `value` when this provider has no value.
+    fn or_value(self, value: T) -> OrElse<T, Self, FnProvider<T>>
+    where
+        T: 'static,
+    {
+        self.or_else(FnProvider::new(move || value.clone()))
+    }
+
+    /// Creates a provider that caches the first value this provider successfully provides. Clones
+    /// of the created provider share the cache.
+    fn memoize(self) -> Memoize<T, Self> {
+        Memoize::new(self)
+    }
 }
 
 impl<P, T> ProviderExt<T> for P
@@ -271,4 +342,73 @@ mod tests {
 
         let _flattend2 = AnonymousProvider::with_value(|| 0).flat_map(|p| provider!(p));
     }
+
+    #[test]
+    fn zip3() {
+        let mut first = Prop::with_value(2);
+        let zipped = first
+            .clone()
+            .zip3(Prop::with_value(3), Prop::with_value(4), |a, b, c| {
+                a * b * c
+            });
+        assert_eq!(zipped.get(), 24);
+        first.set(1).unwrap();
+        assert_eq!(zipped.get(), 12);
+
+        let many = Prop::with_value(1).zip_many([Prop::with_value(2), Prop::with_value(3)]);
+        assert_eq!(many.get(), vec![1, 2, 3]);
+        let missing = Prop::with_value(1).zip_many([Prop::<i32>::with_name("missing")]);
+        assert_eq!(missing.try_get(), None);
+    }
+
+    #[test]
+    fn filter_and_fallbacks() {
+        let mut provider = Prop::with_value(5);
+        let filtered = provider.clone().filter(|v| *v > 3);
+        assert_eq!(filtered.try_get(), Some(5));
+        provider.set(1).unwrap();
+        assert_eq!(filtered.try_get(), None);
+
+        let with_fallback = filtered.clone().or_else(Prop::with_value(10));
+        assert_eq!(with_fallback.get(), 10);
+        assert_eq!(filtered.or_value(15).get(), 15);
+    }
+
+    #[test]
+    fn memoize() {
+        let mut provider = Prop::<i32>::with_name("value");
+        let memoized = provider.clone().map(|v| v * 2).memoize();
+        assert_eq!(memoized.try_get(), None);
+        provider.set(5).unwrap();
+        assert_eq!(memoized.get(), 10);
+        provider.set(6).unwrap();
+        assert_eq!(memoized.clone().get(), 10);
+    }
+
+    #[test]
+    fn combinators_propagate_dependencies() {
+        let task_a = TaskId::new("root:a").unwrap();
+        let task_b = TaskId::new("root:b").unwrap();
+        let task_c = TaskId::new("root:c").unwrap();
+        let a = AnonymousProvider::with_value(1).built_by(task_a.clone());
+        let b = AnonymousProvider::with_value(2).built_by(task_b.clone());
+        let c = AnonymousProvider::with_value(3).built_by(task_c.clone());
+
+        let combined = a
+            .clone()
+            .filter(|v| *v > 0)
+            .or_else(b.clone())
+            .zip3(b, c.clone(), |a, b, c| a + b + c)
+            .memoize();
+        let flat_mapped = a.flat_map(move |_| c.clone());
+
+        let project = Project::temp("root");
+        let deps = project.with(|p| combined.get_dependencies(p)).unwrap();
+        assert_eq!(
+            deps,
+            HashSet::from([task_a.clone(), task_b, task_c.clone()])
+        );
+        let deps = project.with(|p| flat_mapped.get_dependencies(p)).unwrap();
+        assert_eq!(deps, HashSet::from([task_a, task_c]));
+    }
 }
@@ -0,0 +1,192 @@
+//! Providers for values read from the environment of the build.
+//!
+//! Environment variables and system properties can change between builds without any file
+//! changing. When a task reads one of them through a provider from [`Providers`](Providers), the
+//! value that was read is recorded along with the task's execution history. The next time the
+//! task is checked for being up-to-date, it's out of date if any of those values changed.
+
+use crate::identifier::TaskId;
+use crate::lazy_evaluation::value_source::{ValueSource, ValueSourceProvider};
+use crate::lazy_evaluation::Provider;
+use crate::project::buildable::Buildable;
+use crate::project::error::ProjectResult;
+use crate::task::output_ownership::executing_task;
+use crate::Project;
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+/// The values read from the environment by each task while it executed
+static ENVIRONMENT_READS: Lazy<Mutex<HashMap<TaskId, EnvironmentValues>>> =
+    Lazy::new(Default::default);
+
+static SYSTEM_PROPERTIES: Lazy<RwLock<HashMap<String, String>>> = Lazy::new(Default::default);
+
+/// The values of environment sources. A value of `None` means the source was unset.
+pub type EnvironmentValues = BTreeMap<EnvironmentSource, Option<String>>;
+
+/// Somewhere in the environment of the build a value can be read from
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum EnvironmentSource {
+    /// An environment variable of the assemble process
+    EnvVar(String),
+    /// A system property of the build
+    SystemProperty(String),
+}
+
+impl EnvironmentSource {
+    /// Reads the current value of this source, without recording the read
+    pub fn read(&self) -> Option<String> {
+        match self {
+            EnvironmentSource::EnvVar(name) => std::env::var(name).ok(),
+            EnvironmentSource::SystemProperty(key) => system_property(key),
+        }
+    }
+}
+
+impl Display for EnvironmentSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnvironmentSource::EnvVar(name) => write!(f, "environment variable {}", name),
+            EnvironmentSource::SystemProperty(key) => write!(f, "system property {}", key),
+        }
+    }
+}
+
+/// Sets a system property for the rest of the build
+pub fn set_system_property(key: impl AsRef<str>, value: impl AsRef<str>) {
+    SYSTEM_PROPERTIES
+        .write()
+        .insert(key.as_ref().to_string(), value.as_ref().to_string());
+}
+
+/// Removes a system property, returning its previous value
+pub fn remove_system_property(key: &str) -> Option<String> {
+    SYSTEM_PROPERTIES.write().remove(key)
+}
+
+/// Gets the value of a system property
+pub fn system_property(key: &str) -> Option<String> {
+    SYSTEM_PROPERTIES.read().get(key).cloned()
+}
+
+/// Removes and returns the values a task read from the environment while executing
+pub fn take_environment_reads(task: &TaskId) -> EnvironmentValues {
+    ENVIRONMENT_READS.lock().remove(task).unwrap_or_default()
+}
+
+/// Whether a task has read any values from the environment while executing
+pub fn read_environment(task: &TaskId) -> bool {
+    ENVIRONMENT_READS
+        .lock()
+        .get(task)
+        .map_or(false, |reads| !reads.is_empty())
+}
+
+/// The first source whose current value differs from its value in `values`, if any
+pub fn changed_source(values: &EnvironmentValues) -> Option<&EnvironmentSource> {
+    values
+        .iter()
+        .find(|(source, value)| &source.read() != *value)
+        .map(|(source, _)| source)
+}
+
+/// A provider of a value read from the environment. If it's read while a task executes, the
+/// value is recorded as an input of that task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvironmentProvider {
+    source: EnvironmentSource,
+}
+
+impl EnvironmentProvider {
+    /// Creates a provider for a source
+    pub fn new(source: EnvironmentSource) -> Self {
+        Self { source }
+    }
+
+    /// The source values are read from
+    pub fn source(&self) -> &EnvironmentSource {
+        &self.source
+    }
+}
+
+impl Buildable for EnvironmentProvider {
+    fn get_dependencies(&self, _project: &Project) -> ProjectResult<HashSet<TaskId>> {
+        Ok(HashSet::new())
+    }
+}
+
+impl Provider<String> for EnvironmentProvider {
+    fn missing_message(&self) -> String {
+        format!("{} is not set", self.source)
+    }
+
+    fn try_get(&self) -> Option<String> {
+        let value = self.source.read();
+        if let Some(task) = executing_task() {
+            ENVIRONMENT_READS
+                .lock()
+                .entry(task)
+                .or_default()
+                .insert(self.source.clone(), value.clone());
+        }
+        value
+    }
+}
+
+/// Creates providers for values from the environment of the build
+pub struct Providers;
+
+impl Providers {
+    /// A provider of the value of an environment variable
+    pub fn env_var(name: impl AsRef<str>) -> EnvironmentProvider {
+        EnvironmentProvider::new(EnvironmentSource::EnvVar(name.as_ref().to_string()))
+    }
+
+    /// A provider of the value of a system property
+    pub fn property(key: impl AsRef<str>) -> EnvironmentProvider {
+        EnvironmentProvider::new(EnvironmentSource::SystemProperty(key.as_ref().to_string()))
+    }
+
+    /// A provider of the value of a [`ValueSource`](ValueSource), obtained once per build
+    pub fn of<S: ValueSource>(source: S) -> ValueSourceProvider<S> {
+        ValueSourceProvider::new(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::output_ownership::ExecutingTaskGuard;
+
+    #[test]
+    fn reads_are_recorded_for_executing_task() {
+        let task = TaskId::new("env-reader").unwrap();
+        set_system_property("env-test.key", "value");
+        let provider = Providers::property("env-test.key");
+        assert_eq!(provider.get(), "value");
+        assert!(!read_environment(&task), "not read while executing");
+
+        {
+            let _executing = ExecutingTaskGuard::enter(&task);
+            assert_eq!(provider.get(), "value");
+            assert_eq!(
+                Providers::env_var("ASSEMBLE_ENV_TEST_UNSET_VARIABLE").try_get(),
+                None
+            );
+        }
+        let reads = take_environment_reads(&task);
+        assert_eq!(reads.len(), 2);
+        assert_eq!(changed_source(&reads), None);
+
+        set_system_property("env-test.key", "changed");
+        assert_eq!(
+            changed_source(&reads),
+            Some(&EnvironmentSource::SystemProperty(
+                "env-test.key".to_string()
+            ))
+        );
+        remove_system_property("env-test.key");
+    }
+}
@@ -1,8 +1,9 @@
 use std::any::{Any, TypeId};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter};
 use std::fs::{File, OpenOptions};
+use std::hash::Hash;
 
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
@@ -93,11 +94,15 @@ impl<T: Send + Sync + Clone> From<Prop<T>> for AnyProp {
     }
 }
 
-/// A typed prop
+/// A typed prop.
+///
+/// Besides its value, a property can have a [convention](Prop::convention), which is only used
+/// while no value is set. Once a property is [finalized](Prop::finalize_value), its value is fixed
+/// and can no longer be changed.
 pub struct Prop<T: 'static + Send + Sync + Clone> {
     id: Id,
     ty_string: String,
-    inner: Arc<RwLock<PropInner<T>>>,
+    inner: Arc<RwLock<PropState<T>>>,
 }
 
 impl<T: 'static + Send + Sync + Clone> Default for Prop<T> {
@@ -121,7 +126,7 @@ impl<T: 'static + Send + Sync + Clone + Debug> Debug for Prop<T> {
             let id = self.id.clone();
             let read = self.inner.read().unwrap();
 
-            match &*read {
+            match read.current() {
                 PropInner::Unset => {
                     write!(f, "Prop {{ id: {:?} }}>", self.id)
                 }
@@ -151,7 +156,7 @@ impl<T: 'static + Send + Sync + Clone + Display> Display for Prop<T> {
 impl<T: 'static + Send + Sync + Clone + Debug> Buildable for Prop<T> {
     fn get_dependencies(&self, project: &Project) -> ProjectResult<HashSet<TaskId>> {
         let inner = self.inner.read().map_err(PayloadError::new)?;
-        match &*inner {
+        match inner.current() {
             PropInner::Unset => Ok(HashSet::new()),
             PropInner::Provided(p) => p.get_dependencies(project),
         }
@@ -160,7 +165,7 @@ impl<T: 'static + Send + Sync + Clone + Debug> Buildable for Prop<T> {
 
 impl<T: 'static + Send + Sync + Clone + Debug> Provider<T> for Prop<T> {
     fn missing_message(&self) -> String {
-        match &*self.inner.read().unwrap() {
+        match self.inner.read().unwrap().current() {
             PropInner::Unset => {
                 format!("{:?} has no value", self.id)
             }
@@ -178,7 +183,7 @@ impl<T: 'static + Send + Sync + Clone> Prop<T> {
         Self {
             id,
             ty_string: std::any::type_name::<T>().to_string(),
-            inner: Arc::new(RwLock::new(PropInner::Unset)),
+            inner: Arc::new(RwLock::new(PropState::new())),
         }
     }
 
@@ -186,7 +191,7 @@ impl<T: 'static + Send + Sync + Clone> Prop<T> {
         Self {
             id: Id::new(id).unwrap(),
             ty_string: std::any::type_name::<T>().to_string(),
-            inner: Arc::new(RwLock::new(PropInner::Unset)),
+            inner: Arc::new(RwLock::new(PropState::new())),
         }
     }
 
@@ -201,8 +206,11 @@ impl<T: 'static + Send + Sync + Clone> Prop<T> {
         <P as IntoProvider<T>>::Provider: 'static,
     {
         let mut inner = self.inner.write()?;
+        if inner.finalization == Finalization::Finalized {
+            return Err(Error::PropertyFinal(self.id.clone()));
+        }
         let provider = val.into_provider();
-        inner.set(provider);
+        inner.value.set(provider);
         Ok(())
     }
 
@@ -213,9 +221,64 @@ impl<T: 'static + Send + Sync + Clone> Prop<T> {
         self.set_with(Wrapper(val.into()))
     }
 
+    /// Sets the convention of this property, which is used as its value while no value is set
+    pub fn convention<P: IntoProvider<T>>(&mut self, val: P) -> Result<(), Error>
+    where
+        <P as IntoProvider<T>>::Provider: 'static,
+    {
+        let mut inner = self.inner.write()?;
+        if inner.finalization == Finalization::Finalized {
+            return Err(Error::PropertyFinal(self.id.clone()));
+        }
+        inner.convention.set(val.into_provider());
+        Ok(())
+    }
+
+    /// Fixes the value of this property to its current value, or its convention if no value is
+    /// set. Afterwards, changing the value or convention of this property fails. Does nothing if
+    /// the property was already finalized.
+    pub fn finalize_value(&mut self) -> Result<(), Error> {
+        self.finalize()
+    }
+
+    /// Finalizes this property the next time its value is read
+    pub fn finalize_on_read(&mut self) -> Result<(), Error> {
+        let mut inner = self.inner.write()?;
+        if inner.finalization == Finalization::Mutable {
+            inner.finalization = Finalization::FinalizeOnRead;
+        }
+        Ok(())
+    }
+
+    /// Whether this property was finalized
+    pub fn is_final(&self) -> bool {
+        self.inner
+            .read()
+            .map(|inner| inner.finalization == Finalization::Finalized)
+            .unwrap_or(false)
+    }
+
+    fn finalize(&self) -> Result<(), Error> {
+        let mut inner = self.inner.write()?;
+        if inner.finalization == Finalization::Finalized {
+            return Ok(());
+        }
+        let value = inner.current().get();
+        inner.value = match value {
+            Some(value) => PropInner::Provided(Box::new(Wrapper(value))),
+            None => PropInner::Unset,
+        };
+        inner.convention = PropInner::Unset;
+        inner.finalization = Finalization::Finalized;
+        Ok(())
+    }
+
     fn fallible_get(&self) -> Result<T, Error> {
+        if self.inner.read()?.finalization == Finalization::FinalizeOnRead {
+            self.finalize()?;
+        }
         let inner = self.inner.read()?;
-        match &*inner {
+        match inner.current() {
             PropInner::Unset => Err(PropertyNotSet),
             PropInner::Provided(provo) => provo.deref().try_get().ok_or(PropertyNotSet),
         }
@@ -274,6 +337,38 @@ impl<T: 'static + Send + Sync + Clone> Clone for Prop<T> {
     }
 }
 
+/// Whether the value of a property can still change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Finalization {
+    Mutable,
+    FinalizeOnRead,
+    Finalized,
+}
+
+struct PropState<T: Send + Sync + Clone> {
+    value: PropInner<T>,
+    convention: PropInner<T>,
+    finalization: Finalization,
+}
+
+impl<T: Send + Sync + Clone> PropState<T> {
+    fn new() -> Self {
+        Self {
+            value: PropInner::new(),
+            convention: PropInner::new(),
+            finalization: Finalization::Mutable,
+        }
+    }
+
+    /// The value if set, otherwise the convention
+    fn current(&self) -> &PropInner<T> {
+        match &self.value {
+            PropInner::Unset => &self.convention,
+            value => value,
+        }
+    }
+}
+
 enum PropInner<T: Send + Sync + Clone> {
     Unset,
     Provided(Box<dyn Provider<T>>),
@@ -313,6 +408,8 @@ pub enum Error {
     TypeMismatch { expected: TypeId, found: TypeId },
     #[error("Property has no value set")]
     PropertyNotSet,
+    #[error("{0} is final and can no longer be changed")]
+    PropertyFinal(Id),
 }
 
 impl<T> From<PoisonError<T>> for Error {
@@ -507,13 +604,199 @@ where
     }
 }
 
+/// A map prop is a special property that uses a map. Entries are evaluated lazily, and entries
+/// added later replace earlier entries with the same key.
+#[derive(Clone)]
+pub struct MapProp<K, V>
+where
+    K: Eq + Hash + Send + Sync + Clone,
+    V: Send + Sync + Clone,
+{
+    id: Id,
+    prop: Arc<RwLock<Vec<AnonymousProvider<HashMap<K, V>>>>>,
+}
+
+assert_impl_all!(MapProp<String, String>: Provider<HashMap<String, String>>);
+
+impl<K, V> Default for MapProp<K, V>
+where
+    K: 'static + Eq + Hash + Send + Sync + Clone,
+    V: 'static + Send + Sync + Clone,
+{
+    fn default() -> Self {
+        Self::new(Id::default())
+    }
+}
+
+impl<K, V> Buildable for MapProp<K, V>
+where
+    K: 'static + Eq + Hash + Send + Sync + Clone,
+    V: 'static + Send + Sync + Clone,
+{
+    fn get_dependencies(&self, project: &Project) -> ProjectResult<HashSet<TaskId>> {
+        self.prop
+            .read()
+            .map_err(PayloadError::new)?
+            .iter()
+            .map(|s| s.get_dependencies(project))
+            .collect::<ProjectResult<Vec<_>>>()
+            .map(|s| s.into_iter().flatten().collect())
+    }
+}
+
+impl<K, V> Debug for MapProp<K, V>
+where
+    K: 'static + Eq + Hash + Send + Sync + Clone,
+    V: 'static + Send + Sync + Clone,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let inner = self.prop.read().map_err(|_| fmt::Error)?;
+        write!(f, "MapProp ")?;
+        let mut debug_list = f.debug_list();
+        for prov in &*inner {
+            debug_list.entry(prov);
+        }
+        debug_list.finish()
+    }
+}
+
+impl<K, V> Provider<HashMap<K, V>> for MapProp<K, V>
+where
+    K: 'static + Eq + Hash + Send + Sync + Clone,
+    V: 'static + Send + Sync + Clone,
+{
+    fn missing_message(&self) -> String {
+        let read = self.prop.read().expect("poisoned");
+        let first_missing = read
+            .iter()
+            .filter(|p| p.try_get().is_none())
+            .map(|prop| prop.missing_message())
+            .next();
+        match first_missing {
+            None => {
+                format!("{} missing unknown value", self.id)
+            }
+            Some(msg) => format!("{} map missing value > {}", self.id, msg),
+        }
+    }
+
+    fn try_get(&self) -> Option<HashMap<K, V>> {
+        let read = self.prop.read().expect("poisoned");
+        read.iter()
+            .map(|p| p.try_get())
+            .collect::<Option<Vec<HashMap<K, V>>>>()
+            .map(|o| o.into_iter().flatten().collect())
+    }
+
+    fn fallible_get(&self) -> Result<HashMap<K, V>, ProviderError> {
+        let read = self.prop.read().expect("poisoned");
+        read.iter()
+            .map(|p| p.fallible_get())
+            .collect::<Result<Vec<HashMap<K, V>>, _>>()
+            .map(|v| v.into_iter().flatten().collect())
+    }
+}
+
+impl<K, V> MapProp<K, V>
+where
+    K: 'static + Eq + Hash + Send + Sync + Clone,
+    V: 'static + Send + Sync + Clone,
+{
+    /// create a new map prop with a given id
+    pub fn new(id: Id) -> Self {
+        Self {
+            id,
+            prop: Arc::new(RwLock::new(vec![])),
+        }
+    }
+
+    /// Resets this property to contain only the entries from the provider
+    pub fn from<I, P>(&mut self, entries: P)
+    where
+        I: IntoIterator<Item = (K, V)> + Clone + Send + Sync + 'static,
+        P: IntoProvider<I>,
+        P::Provider: 'static,
+    {
+        let mut write = self.prop.write().expect("poisoned");
+        write.clear();
+        let anonymous: AnonymousProvider<HashMap<K, V>> =
+            AnonymousProvider::new(entries.into_provider().map(|v| v.into_iter().collect()));
+        write.push(anonymous);
+    }
+
+    /// Insert an entry into the map, with the value given by a provider
+    pub fn insert_with<Q, P>(&mut self, key: Q, value: P)
+    where
+        Q: Into<K>,
+        P: IntoProvider<V>,
+        P::Provider: 'static,
+    {
+        let key = key.into();
+        let mut write = self.prop.write().expect("map panicked");
+        let anonymous = AnonymousProvider::new(
+            value
+                .into_provider()
+                .map(move |v| HashMap::from([(key.clone(), v)])),
+        );
+        write.push(anonymous);
+    }
+
+    /// Insert an entry into the map
+    pub fn insert<Q, U>(&mut self, key: Q, value: U)
+    where
+        Q: Into<K>,
+        U: Into<V>,
+    {
+        let value = value.into();
+        self.insert_with(key, provider!(move || value.clone()))
+    }
+
+    /// Puts all entries from a provider into the map
+    pub fn put_all<I, P>(&mut self, entries: P)
+    where
+        I: IntoIterator<Item = (K, V)> + Clone + Send + Sync + 'static,
+        P: IntoProvider<I>,
+        P::Provider: 'static,
+    {
+        let mut write = self.prop.write().expect("map panicked");
+        let anonymous = AnonymousProvider::new(
+            entries
+                .into_provider()
+                .map(|v| v.into_iter().collect::<HashMap<_, _>>()),
+        );
+        write.push(anonymous);
+    }
+
+    /// Clears the contents of the map
+    pub fn clear(&mut self) {
+        let mut write = self.prop.write().expect("map panicked");
+        write.clear();
+    }
+}
+
+impl<K, V> Serialize for MapProp<K, V>
+where
+    K: 'static + Eq + Hash + Send + Sync + Clone + Serialize,
+    V: 'static + Send + Sync + Clone + Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.fallible_get()
+            .map_err(S::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::identifier::Id;
     use crate::lazy_evaluation::providers::Zip;
     use crate::lazy_evaluation::{AnyProp, Prop, Provider};
-    use crate::lazy_evaluation::{ProviderExt, VecProp};
+    use crate::lazy_evaluation::{MapProp, ProviderExt, VecProp};
     use crate::provider;
+    use std::collections::HashMap;
 
     #[test]
     fn create_property() {
@@ -530,6 +813,34 @@ mod tests {
         assert_eq!(cloned.get(), 15i32);
     }
 
+    #[test]
+    fn conventions_and_finalization() {
+        let mut prop = Prop::<i32>::new(Id::from("value"));
+        prop.convention(provider!(|| 1)).unwrap();
+        assert_eq!(prop.get(), 1);
+        prop.set(2).unwrap();
+        assert_eq!(prop.get(), 2);
+
+        let mut value = Prop::<i32>::new(Id::from("source"));
+        value.set(3).unwrap();
+        prop.set_with(value.clone()).unwrap();
+        prop.finalize_value().unwrap();
+        value.set(4).unwrap();
+        assert_eq!(prop.get(), 3);
+        assert!(prop.is_final());
+        assert!(prop.set(5).is_err());
+        assert!(prop.convention(provider!(|| 5)).is_err());
+
+        let mut on_read = Prop::<i32>::new(Id::from("on_read"));
+        on_read.convention(provider!(|| 6)).unwrap();
+        on_read.finalize_on_read().unwrap();
+        on_read.set(7).unwrap();
+        assert!(!on_read.is_final());
+        assert_eq!(on_read.get(), 7);
+        assert!(on_read.is_final());
+        assert!(on_read.set(8).is_err());
+    }
+
     #[test]
     fn list_properties_from() {
         let mut prop = VecProp::<i32>::default();
@@ -572,4 +883,30 @@ mod tests {
         prop2.set(0).unwrap();
         assert_eq!(vec_prop.get(), vec![0, 0, 1, 2]);
     }
+
+    #[test]
+    fn map_properties() {
+        let mut prop = MapProp::<String, String>::new(Id::from("env"));
+        let other_prop = prop.clone();
+        let mut value = Prop::new(Id::from("value"));
+        prop.insert("A", "1");
+        prop.insert_with("B", value.clone());
+        prop.put_all(provider!(|| vec![("A".to_string(), "2".to_string())]));
+        assert!(other_prop.try_get().is_none());
+        assert_eq!(
+            other_prop.missing_message(),
+            format!(":env map missing value > {}", value.missing_message())
+        );
+
+        value.set("3".to_string()).unwrap();
+        assert_eq!(
+            other_prop.get(),
+            HashMap::from([
+                ("A".to_string(), "2".to_string()),
+                ("B".to_string(), "3".to_string())
+            ])
+        );
+        let json = serde_json::to_value(&other_prop).unwrap();
+        assert_eq!(json["B"], "3");
+    }
 }
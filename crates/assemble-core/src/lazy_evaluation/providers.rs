@@ -4,7 +4,7 @@ use crate::__export::{ProjectResult, TaskId};
 use crate::lazy_evaluation::{IntoProvider, Provider};
 use crate::project::buildable::Buildable;
 use crate::Project;
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
 use std::collections::HashSet;
 use std::fmt::{Debug, Formatter};
 use std::marker::PhantomData;
@@ -200,8 +200,14 @@ where
     R: Clone + Send + Sync,
     T: Clone + Send + Sync,
 {
+    /// The dependencies of the provider, and of the provider it currently maps to if its value is
+    /// available.
     fn get_dependencies(&self, project: &Project) -> ProjectResult<HashSet<TaskId>> {
-        self.provider.get_dependencies(project)
+        let mut output = self.provider.get_dependencies(project)?;
+        if let Some(value) = self.provider.try_get() {
+            output.extend((self.transform)(value).get_dependencies(project)?);
+        }
+        Ok(output)
     }
 }
 
@@ -291,7 +297,9 @@ where
     T: Clone + Send + Sync,
 {
     fn get_dependencies(&self, project: &Project) -> ProjectResult<HashSet<TaskId>> {
-        self.left.get_dependencies(project)
+        let mut output = self.left.get_dependencies(project)?;
+        output.extend(self.right.get_dependencies(project)?);
+        Ok(output)
     }
 }
 
@@ -302,8 +310,8 @@ where
     R: Clone + Send + Sync,
     T: Clone + Send + Sync,
 {
-    fn fmt(&self, _f: &mut Formatter<'_>) -> std::fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Zip<{:?}, {:?}>", self.left, self.right)
     }
 }
 
@@ -330,6 +338,388 @@ where
     }
 }
 
+/// Zips three providers together
+#[derive(Clone)]
+pub struct Zip3<T, B, C, R, F>
+where
+    T: Send + Sync + Clone,
+    B: Send + Sync + Clone,
+    C: Send + Sync + Clone,
+    R: Send + Sync + Clone,
+    F: Fn(T, B, C) -> R + Send + Sync,
+{
+    first: Arc<dyn Provider<T>>,
+    second: Arc<dyn Provider<B>>,
+    third: Arc<dyn Provider<C>>,
+    transform: F,
+}
+
+impl<T, B, C, R, F> Zip3<T, B, C, R, F>
+where
+    T: Send + Sync + Clone,
+    B: Send + Sync + Clone,
+    C: Send + Sync + Clone,
+    R: Send + Sync + Clone,
+    F: Fn(T, B, C) -> R + Send + Sync,
+{
+    pub fn new<P1, P2, P3>(first: P1, second: P2, third: P3, zip_func: F) -> Self
+    where
+        P1: IntoProvider<T>,
+        <P1 as IntoProvider<T>>::Provider: 'static,
+        P2: IntoProvider<B>,
+        <P2 as IntoProvider<B>>::Provider: 'static,
+        P3: IntoProvider<C>,
+        <P3 as IntoProvider<C>>::Provider: 'static,
+    {
+        Self {
+            first: Arc::new(first.into_provider()),
+            second: Arc::new(second.into_provider()),
+            third: Arc::new(third.into_provider()),
+            transform: zip_func,
+        }
+    }
+}
+
+impl<T, B, C, R, F> Buildable for Zip3<T, B, C, R, F>
+where
+    T: Send + Sync + Clone,
+    B: Send + Sync + Clone,
+    C: Send + Sync + Clone,
+    R: Send + Sync + Clone,
+    F: Fn(T, B, C) -> R + Send + Sync,
+{
+    fn get_dependencies(&self, project: &Project) -> ProjectResult<HashSet<TaskId>> {
+        let mut output = self.first.get_dependencies(project)?;
+        output.extend(self.second.get_dependencies(project)?);
+        output.extend(self.third.get_dependencies(project)?);
+        Ok(output)
+    }
+}
+
+impl<T, B, C, R, F> Debug for Zip3<T, B, C, R, F>
+where
+    T: Send + Sync + Clone,
+    B: Send + Sync + Clone,
+    C: Send + Sync + Clone,
+    R: Send + Sync + Clone,
+    F: Fn(T, B, C) -> R + Send + Sync,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Zip3<{:?}, {:?}, {:?}>",
+            self.first, self.second, self.third
+        )
+    }
+}
+
+impl<T, B, C, R, F> Provider<R> for Zip3<T, B, C, R, F>
+where
+    T: Send + Sync + Clone,
+    B: Send + Sync + Clone,
+    C: Send + Sync + Clone,
+    R: Send + Sync + Clone,
+    F: Fn(T, B, C) -> R + Send + Sync,
+{
+    fn missing_message(&self) -> String {
+        format!(
+            "{}, {} or {}",
+            self.first.missing_message(),
+            self.second.missing_message(),
+            self.third.missing_message()
+        )
+    }
+
+    fn try_get(&self) -> Option<R> {
+        let first = self.first.try_get()?;
+        let second = self.second.try_get()?;
+        let third = self.third.try_get()?;
+        Some((self.transform)(first, second, third))
+    }
+}
+
+/// Zips any number of providers of the same type into a provider of a vector. A value is only
+/// available when every provider has a value.
+#[derive(Clone)]
+pub struct ZipMany<T: Send + Sync + Clone> {
+    providers: Vec<Arc<dyn Provider<T>>>,
+}
+
+impl<T: Send + Sync + Clone> ZipMany<T> {
+    pub fn new<I, P>(providers: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: IntoProvider<T>,
+        <P as IntoProvider<T>>::Provider: 'static,
+    {
+        Self {
+            providers: providers
+                .into_iter()
+                .map(|provider| Arc::new(provider.into_provider()) as Arc<dyn Provider<T>>)
+                .collect(),
+        }
+    }
+}
+
+impl<T: Send + Sync + Clone> ZipMany<T> {
+    /// Adds more providers to zip
+    pub fn extend<I, P>(&mut self, providers: I)
+    where
+        I: IntoIterator<Item = P>,
+        P: IntoProvider<T>,
+        <P as IntoProvider<T>>::Provider: 'static,
+    {
+        self.providers.extend(
+            providers
+                .into_iter()
+                .map(|provider| Arc::new(provider.into_provider()) as Arc<dyn Provider<T>>),
+        );
+    }
+}
+
+impl<T: Send + Sync + Clone> Buildable for ZipMany<T> {
+    fn get_dependencies(&self, project: &Project) -> ProjectResult<HashSet<TaskId>> {
+        let mut output = HashSet::new();
+        for provider in &self.providers {
+            output.extend(provider.get_dependencies(project)?);
+        }
+        Ok(output)
+    }
+}
+
+impl<T: Send + Sync + Clone> Debug for ZipMany<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ZipMany").field(&self.providers).finish()
+    }
+}
+
+impl<T: Send + Sync + Clone> Provider<Vec<T>> for ZipMany<T> {
+    fn missing_message(&self) -> String {
+        self.providers
+            .iter()
+            .find(|provider| provider.try_get().is_none())
+            .map(|provider| provider.missing_message())
+            .unwrap_or_else(|| String::from("Provider has no value set"))
+    }
+
+    fn try_get(&self) -> Option<Vec<T>> {
+        self.providers
+            .iter()
+            .map(|provider| provider.try_get())
+            .collect()
+    }
+}
+
+/// Only provides the value of a provider if it matches a predicate
+#[derive(Clone)]
+pub struct Filter<T, P, F>
+where
+    T: Send + Sync + Clone,
+    P: Provider<T>,
+    F: Fn(&T) -> bool + Send + Sync,
+{
+    provider: P,
+    predicate: F,
+    _data: PhantomData<T>,
+}
+
+impl<T, P, F> Filter<T, P, F>
+where
+    T: Send + Sync + Clone,
+    P: Provider<T>,
+    F: Fn(&T) -> bool + Send + Sync,
+{
+    pub(super) fn new(provider: P, predicate: F) -> Self {
+        Self {
+            provider,
+            predicate,
+            _data: PhantomData,
+        }
+    }
+}
+
+impl<T, P, F> Buildable for Filter<T, P, F>
+where
+    T: Send + Sync + Clone,
+    P: Provider<T>,
+    F: Fn(&T) -> bool + Send + Sync,
+{
+    fn get_dependencies(&self, project: &Project) -> ProjectResult<HashSet<TaskId>> {
+        self.provider.get_dependencies(project)
+    }
+}
+
+impl<T, P, F> Debug for Filter<T, P, F>
+where
+    T: Send + Sync + Clone,
+    P: Provider<T>,
+    F: Fn(&T) -> bool + Send + Sync,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Filter<{:?}>", self.provider)
+    }
+}
+
+impl<T, P, F> Provider<T> for Filter<T, P, F>
+where
+    T: Send + Sync + Clone,
+    P: Provider<T>,
+    F: Fn(&T) -> bool + Send + Sync,
+{
+    fn missing_message(&self) -> String {
+        match self.provider.try_get() {
+            Some(_) => String::from("Provided value did not match the filter"),
+            None => self.provider.missing_message(),
+        }
+    }
+
+    fn try_get(&self) -> Option<T> {
+        self.provider
+            .try_get()
+            .filter(|value| (self.predicate)(value))
+    }
+}
+
+/// Provides the value of a provider, or of a fallback provider if the first has no value
+#[derive(Clone)]
+pub struct OrElse<T, P, Q>
+where
+    T: Send + Sync + Clone,
+    P: Provider<T>,
+    Q: Provider<T>,
+{
+    provider: P,
+    fallback: Q,
+    _data: PhantomData<T>,
+}
+
+impl<T, P, Q> OrElse<T, P, Q>
+where
+    T: Send + Sync + Clone,
+    P: Provider<T>,
+    Q: Provider<T>,
+{
+    pub(super) fn new(provider: P, fallback: Q) -> Self {
+        Self {
+            provider,
+            fallback,
+            _data: PhantomData,
+        }
+    }
+}
+
+impl<T, P, Q> Buildable for OrElse<T, P, Q>
+where
+    T: Send + Sync + Clone,
+    P: Provider<T>,
+    Q: Provider<T>,
+{
+    /// The dependencies of both providers, as which value is used isn't known until the providers
+    /// are queried.
+    fn get_dependencies(&self, project: &Project) -> ProjectResult<HashSet<TaskId>> {
+        let mut output = self.provider.get_dependencies(project)?;
+        output.extend(self.fallback.get_dependencies(project)?);
+        Ok(output)
+    }
+}
+
+impl<T, P, Q> Debug for OrElse<T, P, Q>
+where
+    T: Send + Sync + Clone,
+    P: Provider<T>,
+    Q: Provider<T>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "OrElse<{:?}, {:?}>", self.provider, self.fallback)
+    }
+}
+
+impl<T, P, Q> Provider<T> for OrElse<T, P, Q>
+where
+    T: Send + Sync + Clone,
+    P: Provider<T>,
+    Q: Provider<T>,
+{
+    fn missing_message(&self) -> String {
+        format!(
+            "{} and {}",
+            self.provider.missing_message(),
+            self.fallback.missing_message()
+        )
+    }
+
+    fn try_get(&self) -> Option<T> {
+        self.provider.try_get().or_else(|| self.fallback.try_get())
+    }
+}
+
+/// Caches the first value successfully provided by a provider. Clones share the same cache.
+#[derive(Clone)]
+pub struct Memoize<T, P>
+where
+    T: Send + Sync + Clone,
+    P: Provider<T>,
+{
+    provider: P,
+    cache: Arc<OnceCell<T>>,
+}
+
+impl<T, P> Memoize<T, P>
+where
+    T: Send + Sync + Clone,
+    P: Provider<T>,
+{
+    pub(super) fn new(provider: P) -> Self {
+        Self {
+            provider,
+            cache: Arc::new(OnceCell::new()),
+        }
+    }
+}
+
+impl<T, P> Buildable for Memoize<T, P>
+where
+    T: Send + Sync + Clone,
+    P: Provider<T>,
+{
+    fn get_dependencies(&self, project: &Project) -> ProjectResult<HashSet<TaskId>> {
+        self.provider.get_dependencies(project)
+    }
+}
+
+impl<T, P> Debug for Memoize<T, P>
+where
+    T: Send + Sync + Clone,
+    P: Provider<T>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Memoize<{:?}, cached: {}>",
+            self.provider,
+            self.cache.get().is_some()
+        )
+    }
+}
+
+impl<T, P> Provider<T> for Memoize<T, P>
+where
+    T: Send + Sync + Clone,
+    P: Provider<T>,
+{
+    fn missing_message(&self) -> String {
+        self.provider.missing_message()
+    }
+
+    fn try_get(&self) -> Option<T> {
+        if let Some(cached) = self.cache.get() {
+            return Some(cached.clone());
+        }
+        let value = self.provider.try_get()?;
+        Some(self.cache.get_or_init(|| value).clone())
+    }
+}
+
 impl<T: Send + Sync + Clone + Debug, F: Send + FnOnce() -> T> Buildable for Lazy<T, F> {
     fn get_dependencies(&self, _project: &Project) -> ProjectResult<HashSet<TaskId>> {
         Ok(HashSet::new())
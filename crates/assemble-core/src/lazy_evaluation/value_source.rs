@@ -0,0 +1,300 @@
+//! Values obtained from outside of the build, like the output of a command or the contents of a
+//! file.
+//!
+//! A [`ValueSource`](ValueSource) is a named computation with parameters. Its value is obtained at
+//! most once per build for each set of parameters, no matter how many providers of it are read or
+//! how many tasks use them. For example, every task using the current git revision shares a single
+//! run of `git rev-parse HEAD`.
+//!
+//! Every value obtained is recorded as a configuration input of the build, available from
+//! [`configuration_inputs`](configuration_inputs).
+
+use crate::identifier::TaskId;
+use crate::lazy_evaluation::Provider;
+use crate::project::buildable::Buildable;
+use crate::project::error::ProjectResult;
+use crate::Project;
+use once_cell::sync::{Lazy, OnceCell};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Arc;
+
+type ObtainedCell = Arc<OnceCell<Option<Arc<dyn Any + Send + Sync>>>>;
+
+/// The values obtained by each source, by the type of the source, its name and its parameters
+static OBTAINED: Lazy<Mutex<HashMap<(TypeId, String, Vec<String>), ObtainedCell>>> =
+    Lazy::new(Default::default);
+
+static CONFIGURATION_INPUTS: Lazy<Mutex<Vec<ObtainedValue>>> = Lazy::new(Default::default);
+
+/// A computation obtaining a value from outside of the build.
+///
+/// Sources of the same type with the same name and parameters share their value.
+pub trait ValueSource: Send + Sync + 'static {
+    /// The type of the value
+    type Value: Serialize + Clone + Send + Sync + 'static;
+
+    /// The name of the source, like `git-revision`
+    fn name(&self) -> String;
+
+    /// The parameters of the source, which identify the value along with the name
+    fn parameters(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// Obtains the value, or `None` if there's no value
+    fn obtain(&self) -> Option<Self::Value>;
+}
+
+/// A value obtained by a value source during the build
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObtainedValue {
+    /// The name of the source
+    pub name: String,
+    /// The parameters of the source
+    pub parameters: Vec<String>,
+    /// The value obtained, serialized as json. `None` if the source had no value.
+    pub value: Option<String>,
+}
+
+/// The values obtained by value sources so far, in the order they were obtained
+pub fn configuration_inputs() -> Vec<ObtainedValue> {
+    CONFIGURATION_INPUTS.lock().clone()
+}
+
+/// Gets the value of a source, obtaining it if it wasn't obtained yet during this build
+pub fn obtain<S: ValueSource>(source: &S) -> Option<S::Value> {
+    let name = source.name();
+    let parameters = source.parameters();
+    let cell = OBTAINED
+        .lock()
+        .entry((TypeId::of::<S>(), name.clone(), parameters.clone()))
+        .or_default()
+        .clone();
+    let value = cell.get_or_init(|| {
+        trace!("obtaining value of {} {:?}", name, parameters);
+        let value = source.obtain();
+        CONFIGURATION_INPUTS.lock().push(ObtainedValue {
+            name,
+            parameters,
+            value: value
+                .as_ref()
+                .and_then(|value| serde_json::to_string(value).ok()),
+        });
+        value.map(|value| Arc::new(value) as Arc<dyn Any + Send + Sync>)
+    });
+    value
+        .as_ref()
+        .and_then(|value| value.downcast_ref::<S::Value>())
+        .cloned()
+}
+
+/// A provider of the value of a [`ValueSource`](ValueSource)
+pub struct ValueSourceProvider<S: ValueSource> {
+    source: Arc<S>,
+}
+
+impl<S: ValueSource> ValueSourceProvider<S> {
+    /// Creates a provider for a source
+    pub fn new(source: S) -> Self {
+        Self {
+            source: Arc::new(source),
+        }
+    }
+}
+
+impl<S: ValueSource> Clone for ValueSourceProvider<S> {
+    fn clone(&self) -> Self {
+        Self {
+            source: self.source.clone(),
+        }
+    }
+}
+
+impl<S: ValueSource> Debug for ValueSourceProvider<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ValueSourceProvider")
+            .field("name", &self.source.name())
+            .field("parameters", &self.source.parameters())
+            .finish()
+    }
+}
+
+impl<S: ValueSource> Buildable for ValueSourceProvider<S> {
+    fn get_dependencies(&self, _project: &Project) -> ProjectResult<HashSet<TaskId>> {
+        Ok(HashSet::new())
+    }
+}
+
+impl<S: ValueSource> Provider<S::Value> for ValueSourceProvider<S> {
+    fn missing_message(&self) -> String {
+        format!(
+            "{} {:?} has no value",
+            self.source.name(),
+            self.source.parameters()
+        )
+    }
+
+    fn try_get(&self) -> Option<S::Value> {
+        obtain(&*self.source)
+    }
+}
+
+/// The trimmed standard output of a command. Has no value if the command can't be ran or fails.
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+    program: String,
+    args: Vec<String>,
+    dir: Option<PathBuf>,
+}
+
+impl CommandOutput {
+    /// Creates a source running a program with arguments
+    pub fn new<I, S>(program: impl AsRef<str>, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            program: program.as_ref().to_string(),
+            args: args.into_iter().map(|s| s.as_ref().to_string()).collect(),
+            dir: None,
+        }
+    }
+
+    /// Runs the command in a directory
+    pub fn in_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = Some(dir.into());
+        self
+    }
+}
+
+impl ValueSource for CommandOutput {
+    type Value = String;
+
+    fn name(&self) -> String {
+        "command".to_string()
+    }
+
+    fn parameters(&self) -> Vec<String> {
+        let mut parameters = vec![self.program.clone()];
+        parameters.extend(self.args.iter().cloned());
+        if let Some(dir) = &self.dir {
+            parameters.push(format!("in {:?}", dir));
+        }
+        parameters
+    }
+
+    fn obtain(&self) -> Option<String> {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        if let Some(dir) = &self.dir {
+            command.current_dir(dir);
+        }
+        match command.output() {
+            Ok(output) if output.status.success() => {
+                Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+            }
+            Ok(output) => {
+                debug!("{:?} failed with {}", command, output.status);
+                None
+            }
+            Err(e) => {
+                debug!("could not run {:?}: {}", command, e);
+                None
+            }
+        }
+    }
+}
+
+/// The contents of a text file, like a version file. Has no value if the file can't be read.
+#[derive(Debug, Clone)]
+pub struct FileContents {
+    path: PathBuf,
+}
+
+impl FileContents {
+    /// Creates a source reading a file
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl ValueSource for FileContents {
+    type Value = String;
+
+    fn name(&self) -> String {
+        "file".to_string()
+    }
+
+    fn parameters(&self) -> Vec<String> {
+        vec![self.path.to_string_lossy().to_string()]
+    }
+
+    fn obtain(&self) -> Option<String> {
+        std::fs::read_to_string(&self.path).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static OBTAINED_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Counter(&'static str);
+
+    impl ValueSource for Counter {
+        type Value = usize;
+
+        fn name(&self) -> String {
+            "value-source-test".to_string()
+        }
+
+        fn parameters(&self) -> Vec<String> {
+            vec![self.0.to_string()]
+        }
+
+        fn obtain(&self) -> Option<usize> {
+            Some(OBTAINED_COUNT.fetch_add(1, Ordering::SeqCst))
+        }
+    }
+
+    #[test]
+    fn values_are_obtained_once_per_parameters() {
+        let first = ValueSourceProvider::new(Counter("first"));
+        let same = ValueSourceProvider::new(Counter("first"));
+        let second = ValueSourceProvider::new(Counter("second"));
+        assert_eq!(first.get(), same.get());
+        assert_eq!(first.get(), first.get());
+        assert_ne!(first.get(), second.get());
+        assert_eq!(OBTAINED_COUNT.load(Ordering::SeqCst), 2);
+
+        let inputs = configuration_inputs()
+            .into_iter()
+            .filter(|input| input.name == "value-source-test")
+            .collect::<Vec<_>>();
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(inputs[0].parameters, ["first"]);
+    }
+
+    #[test]
+    fn file_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("version.txt");
+        std::fs::write(&path, "1.2.3").unwrap();
+        let version = ValueSourceProvider::new(FileContents::new(&path));
+        assert_eq!(version.get(), "1.2.3");
+        assert!(
+            ValueSourceProvider::new(FileContents::new(dir.path().join("missing")))
+                .try_get()
+                .is_none()
+        );
+    }
+}
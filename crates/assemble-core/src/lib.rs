@@ -20,12 +20,16 @@ pub mod cargo;
 pub mod cryptography;
 pub mod defaults;
 pub mod dependencies;
+pub mod deprecations;
 pub mod error;
 pub mod exception;
 pub mod file;
 pub mod file_collection;
+pub mod file_lock;
+pub mod file_watcher;
 pub mod fingerprint;
 pub mod flow;
+pub mod humanize;
 pub mod identifier;
 pub mod immutable;
 pub mod lazy_evaluation;
@@ -118,6 +122,6 @@ pub mod __export {
     pub use crate::project::Project;
     pub use crate::task::create_task::CreateTask;
     pub use crate::task::initialize_task::InitializeTask;
-    pub use crate::task::task_io::TaskIO;
+    pub use crate::task::task_io::{NestedTaskIO, TaskIO};
     pub use crate::task::{work_handler::serializer::*, Executable};
 }
@@ -1,11 +1,12 @@
 //! Defines different parts of the logging utilities for assemble-daemon
 
 use crate::identifier::{ProjectId, TaskId};
+use crate::task::task_progress::ProgressState;
 use crate::unstable::text_factory::AssembleFormatter;
 use atty::Stream;
 use colored::Colorize;
 use fern::{Dispatch, FormatCallback, Output};
-use indicatif::MultiProgress;
+use indicatif::{MultiProgress, ProgressDrawTarget};
 use log::{Level, LevelFilter, Log, Record, SetLoggerError};
 use merge::Merge;
 use once_cell::sync::{Lazy, OnceCell};
@@ -16,17 +17,28 @@ use std::collections::{HashMap, VecDeque};
 use std::io::{stdout, ErrorKind, Write};
 use std::path::{Path, PathBuf};
 
+use json_stream::JsonEvent;
+use log_file::{file_output, FileRecord, LogFile, DEFAULT_LOG_FILE_LEVEL};
 use std::ffi::OsStr;
 use std::sync::atomic::AtomicBool;
-use std::sync::mpsc::{channel, Sender};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 use std::{fmt, io, thread};
+use task_groups::TaskGroups;
 use thread_local::ThreadLocal;
 use time::format_description::FormatItem;
 use time::macros::format_description;
 use time::OffsetDateTime;
+use work_in_progress::{completed_line, WorkInProgress, REDRAW_INTERVAL};
+
+pub mod excerpts;
+pub mod json_stream;
+pub mod log_file;
+pub mod task_groups;
+pub mod terminal;
+pub mod work_in_progress;
 
 /// Provides helpful logging args for clap clis
 #[derive(Debug, clap::Args, Clone, merge::Merge)]
@@ -79,7 +91,7 @@ pub struct LoggingArgs {
     #[merge(strategy =merge::bool::overwrite_false)]
     pub show_source: bool,
 
-    /// Outputs everything as json
+    /// Outputs everything as newline delimited json events, for tools driving assemble
     #[clap(long)]
     #[clap(help_heading = "Logging Settings")]
     #[clap(global = true)]
@@ -91,6 +103,27 @@ pub struct LoggingArgs {
     #[clap(help_heading = "Logging Settings")]
     #[clap(global = true)]
     pub console: ConsoleMode,
+
+    /// Overrides the detected width of the console.
+    #[clap(long)]
+    #[clap(help_heading = "Logging Settings")]
+    #[clap(global = true)]
+    #[clap(value_parser = clap::value_parser!(u16).range(1..))]
+    pub console_width: Option<u16>,
+
+    /// Also writes log messages to the given file, with timestamps and origins. The file is
+    /// written at debug level unless `--log-file-level` is given, regardless of the console level.
+    #[clap(long, value_name = "PATH")]
+    #[clap(help_heading = "Logging Settings")]
+    #[clap(global = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// The level of messages written to the log file.
+    #[clap(long, value_name = "LEVEL")]
+    #[clap(requires = "log_file")]
+    #[clap(help_heading = "Logging Settings")]
+    #[clap(global = true)]
+    pub log_file_level: Option<LevelFilter>,
 }
 
 impl Default for LoggingArgs {
@@ -104,6 +137,9 @@ impl Default for LoggingArgs {
             trace: false,
             json: false,
             console: ConsoleMode::Plain,
+            console_width: None,
+            log_file: None,
+            log_file_level: None,
         }
     }
 }
@@ -120,9 +156,14 @@ pub enum OutputType {
 #[derive(Debug, Copy, Clone, clap::ValueEnum, Eq, PartialEq)]
 #[repr(u8)]
 pub enum ConsoleMode {
+    /// Rich when drawing to a terminal, otherwise plain
     Auto,
+    /// Progress and the tasks in flight are drawn below log output
     Rich,
+    /// Only log output, printed as it's logged
     Plain,
+    /// Only log output, with the output of each task printed as one block once the task finishes
+    Grouped,
 }
 
 impl Merge for ConsoleMode {
@@ -134,10 +175,12 @@ impl Merge for ConsoleMode {
 }
 
 impl ConsoleMode {
+    /// Resolves the automatic console mode. Progress is drawn to stderr, so rich mode is only
+    /// used when stderr is a terminal.
     pub fn resolve(self) -> Self {
         match &self {
             ConsoleMode::Auto => {
-                if atty::is(Stream::Stdout) {
+                if atty::is(Stream::Stderr) {
                     ConsoleMode::Rich
                 } else {
                     ConsoleMode::Plain
@@ -145,6 +188,7 @@ impl ConsoleMode {
             }
             ConsoleMode::Rich => self,
             ConsoleMode::Plain => self,
+            ConsoleMode::Grouped => self,
         }
     }
 }
@@ -205,25 +249,45 @@ impl LoggingArgs {
         Self::create_logger_with(filter, mode, false, None).apply()
     }
 
+    /// Gets the level of messages written to the log file, if there is one
+    pub fn log_file_level(&self) -> Option<LevelFilter> {
+        self.log_file
+            .as_ref()
+            .map(|_| self.log_file_level.unwrap_or(DEFAULT_LOG_FILE_LEVEL))
+    }
+
     pub fn create_logger(&self) -> (Dispatch, Option<JoinHandle<()>>) {
         let (filter, output_mode) = self.config_from_settings();
-        let rich: bool = match self.console.resolve() {
+        let console = self.console.resolve();
+        let rich: bool = match console {
             ConsoleMode::Auto => {
                 unreachable!()
             }
             ConsoleMode::Rich => true,
-            ConsoleMode::Plain => false,
+            ConsoleMode::Plain | ConsoleMode::Grouped => false,
         };
-        if !rich {
+        if !rich || self.json || !terminal::stdout_is_terminal() {
             colored::control::set_override(false);
         }
-        let (started, handle) = start_central_logger(rich);
-        let central = CentralLoggerInput { sender: started };
+        terminal::set_console_width_override(self.console_width.map(|w| w as usize));
+        let (started, handle) = start_central_logger(console, self.json, self.log_file.clone());
+        let central = CentralLoggerInput {
+            sender: started.clone(),
+        };
         let output = Output::from(Box::new(central) as Box<dyn Write + Send>);
-        (
-            Self::create_logger_with(filter, output_mode, self.show_source, output),
-            Some(handle),
-        )
+        let console = Self::create_logger_with(filter, output_mode, self.show_source, output);
+        let dispatch = match self.log_file_level() {
+            Some(file_level) => Dispatch::new()
+                .level(filter.max(file_level))
+                .chain(console)
+                .chain(
+                    Dispatch::new()
+                        .level(file_level)
+                        .chain(file_output(started)),
+                ),
+            None => console,
+        };
+        (dispatch, Some(handle))
     }
 
     pub fn create_logger_with(
@@ -275,19 +339,8 @@ impl LoggingArgs {
     }
 
     fn json_message_format(format: FormatCallback, args: &fmt::Arguments, record: &log::Record) {
-        let message = format!("{}", args);
-        let level = record.level();
-        let origin = Origin::None;
-
-        let message_info = JsonMessageInfo {
-            level,
-            origin,
-            message,
-        };
-
-        let as_string = serde_json::to_string(&message_info).unwrap();
-
-        format.finish(format_args!("{}", as_string));
+        let event = JsonEvent::log(record.level(), args, thread_origin());
+        format.finish(format_args!("{}", event.to_json_line()));
     }
 
     fn format_prefix(output_mode: &OutputType, record: &Record, show_source: bool) -> String {
@@ -384,14 +437,6 @@ impl From<TaskId> for Origin {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct JsonMessageInfo {
-    #[serde(with = "LevelDef")]
-    pub level: Level,
-    pub origin: Origin,
-    pub message: String,
-}
-
 #[derive(Serialize, Deserialize)]
 #[serde(remote = "Level")]
 enum LevelDef {
@@ -467,6 +512,24 @@ impl LoggingControl {
         sender.send(LoggingCommand::TaskEnded(id.clone())).unwrap();
     }
 
+    /// Reports the progress of a task, shown on the line of the task in the rich console
+    pub fn task_status(&self, id: &TaskId, state: ProgressState) {
+        if let Some(lock) = LOG_COMMAND_SENDER.get() {
+            let _ = lock
+                .lock()
+                .unwrap()
+                .send(LoggingCommand::TaskStatus(id.clone(), state));
+        }
+    }
+
+    /// Emits an event to the [json log stream](json_stream). Events are ignored unless logging
+    /// in json mode.
+    pub fn emit_event(&self, event: JsonEvent) {
+        if let Some(lock) = LOG_COMMAND_SENDER.get() {
+            let _ = lock.lock().unwrap().send(LoggingCommand::Event(event));
+        }
+    }
+
     /// Start a progress bar. Returns err if a progress bar has already been started. If Ok, the
     /// returned value is a clone of the multi-progress bar
     pub fn start_progress_bar(&self, bar: &MultiProgress) -> Result<MultiProgress, ()> {
@@ -475,6 +538,7 @@ impl LoggingControl {
         sender
             .send(LoggingCommand::StartMultiProgress(bar.clone()))
             .unwrap();
+        *PROGRESS_BAR.lock() = Some(bar.clone());
         Ok(bar.clone())
     }
 
@@ -483,9 +547,45 @@ impl LoggingControl {
         let lock = LOG_COMMAND_SENDER.get().unwrap();
         let sender = lock.lock().unwrap();
 
+        *PROGRESS_BAR.lock() = None;
         sender.send(LoggingCommand::EndMultiProgress).unwrap();
     }
 
+    /// The multi-progress bar of the build, if one has been started. Tasks can add their own
+    /// progress bars to it, which are drawn below the progress of the build.
+    pub fn progress_bar(&self) -> Option<MultiProgress> {
+        PROGRESS_BAR.lock().clone()
+    }
+
+    /// Acquires the terminal for a task that needs to interact with the user directly, such as a
+    /// program that prompts for input.
+    ///
+    /// Blocks until no other task owns the terminal. While the returned guard is held, progress
+    /// rendering is paused and log output is buffered, so the program can freely read from stdin
+    /// and write to the terminal. Buffered output is emitted once the guard is dropped.
+    pub fn acquire_terminal(&self) -> TerminalGuard {
+        let mut owned = TERMINAL_OWNED.lock();
+        while *owned {
+            TERMINAL_RELEASED.wait(&mut owned);
+        }
+        *owned = true;
+        drop(owned);
+
+        if let Some(lock) = LOG_COMMAND_SENDER.get() {
+            let (ack_send, ack_recv) = channel();
+            let sent = lock
+                .lock()
+                .unwrap()
+                .send(LoggingCommand::AcquireTerminal(ack_send))
+                .is_ok();
+            if sent {
+                // the central logger may have already stopped, in which case there's nothing to wait for
+                let _ = ack_recv.recv();
+            }
+        }
+        TerminalGuard(())
+    }
+
     /// Run a closure within an origin context
     #[cfg(feature = "log_origin_control")]
     pub fn with_origin<O: Into<Origin>, F: FnOnce() -> R, R>(&self, origin: O, func: F) -> R {
@@ -507,47 +607,154 @@ impl LoggingControl {
     }
 }
 
+/// Gives a task exclusive access to the terminal. Created with
+/// [`acquire_terminal`](LoggingControl::acquire_terminal), and releases the terminal when dropped.
+#[derive(Debug)]
+pub struct TerminalGuard(());
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        if let Some(lock) = LOG_COMMAND_SENDER.get() {
+            let _ = lock.lock().unwrap().send(LoggingCommand::ReleaseTerminal);
+        }
+        *TERMINAL_OWNED.lock() = false;
+        TERMINAL_RELEASED.notify_one();
+    }
+}
+
+static TERMINAL_OWNED: parking_lot::Mutex<bool> = parking_lot::const_mutex(false);
+static PROGRESS_BAR: parking_lot::Mutex<Option<MultiProgress>> = parking_lot::const_mutex(None);
+static TERMINAL_RELEASED: parking_lot::Condvar = parking_lot::Condvar::new();
+
 static CONTINUE_LOGGING: AtomicBool = AtomicBool::new(true);
 static LOG_COMMAND_SENDER: OnceCell<Arc<Mutex<Sender<LoggingCommand>>>> = OnceCell::new();
 
-fn start_central_logger(rich: bool) -> (Sender<LoggingCommand>, JoinHandle<()>) {
+fn start_central_logger(
+    console: ConsoleMode,
+    json: bool,
+    log_file: Option<PathBuf>,
+) -> (Sender<LoggingCommand>, JoinHandle<()>) {
     let (send, recv) = channel();
     let _ = LOG_COMMAND_SENDER.set(Arc::new(Mutex::new(send.clone())));
     let handle = thread::spawn(move || {
         let mut central_logger = CentralLoggerOutput::new();
+        if console == ConsoleMode::Grouped {
+            central_logger.group_task_output();
+        }
+        let mut log_file = log_file.and_then(|path| match LogFile::create(&path) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                let _ = central_logger.println(format!(
+                    "{}",
+                    format!("could not create log file {:?}: {}", path, e).yellow()
+                ));
+                None
+            }
+        });
+        let mut foreground = false;
         loop {
-            let command = match recv.recv() {
+            let command = match recv.recv_timeout(REDRAW_INTERVAL) {
                 Ok(s) => s,
-                Err(_) => break,
+                Err(RecvTimeoutError::Timeout) => {
+                    central_logger.redraw_work_in_progress();
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
             };
 
             match command {
+                // in json mode, stdout only receives json events
+                LoggingCommand::LogString(_, s) if json => {
+                    let _ = write!(stdout(), "{}", s);
+                }
+                LoggingCommand::TaskStarted(task) if json => {
+                    let _ = central_logger.println(JsonEvent::TaskStarted { task }.to_json_line());
+                }
+                LoggingCommand::TaskEnded(_)
+                | LoggingCommand::StartMultiProgress(_)
+                | LoggingCommand::EndMultiProgress
+                    if json => {}
+                LoggingCommand::Event(event) => {
+                    if json {
+                        let _ = central_logger.println(event.to_json_line());
+                    }
+                }
                 LoggingCommand::LogString(o, s) => {
-                    central_logger.add_output(o, &s);
-                    central_logger.flush_current_origin();
+                    if !central_logger.buffer_task_output(&o, &s) {
+                        central_logger.add_output(o, &s);
+                        if !foreground {
+                            central_logger.flush_current_origin();
+                        }
+                    }
+                }
+                LoggingCommand::LogRecord(record) => {
+                    if let Some(file) = &mut log_file {
+                        if let Err(e) = file.write(&record) {
+                            let _ = central_logger.println(format!(
+                                "{}",
+                                format!("could not write to log file: {}", e).yellow()
+                            ));
+                            log_file = None;
+                        }
+                    }
+                }
+                LoggingCommand::Flush => {
+                    central_logger.flush();
+                    if let Some(file) = &mut log_file {
+                        let _ = file.flush();
+                    }
                 }
-                LoggingCommand::Flush => central_logger.flush(),
                 LoggingCommand::Stop => {
                     break;
                 }
-                LoggingCommand::TaskStarted(s) => {
-                    if !rich {
+                LoggingCommand::TaskStarted(s) => match console {
+                    ConsoleMode::Rich => central_logger.task_started(&s),
+                    ConsoleMode::Grouped => central_logger.start_task_group(&s),
+                    _ => {
                         central_logger.add_output(Origin::Task(s), "");
-                        central_logger.flush_current_origin();
+                        if !foreground {
+                            central_logger.flush_current_origin();
+                        }
+                    }
+                },
+                LoggingCommand::TaskEnded(s) => match console {
+                    ConsoleMode::Rich | ConsoleMode::Grouped => {
+                        central_logger.task_ended(&s);
+                        if !foreground {
+                            central_logger.flush_current_origin();
+                        }
+                    }
+                    _ => {}
+                },
+                LoggingCommand::TaskStatus(id, state) => {
+                    if console == ConsoleMode::Rich {
+                        central_logger.task_status(&id, state);
                     }
                 }
-                LoggingCommand::TaskEnded(_s) => {}
-                LoggingCommand::TaskStatus(_, _) => {}
                 LoggingCommand::StartMultiProgress(b) => {
                     central_logger.start_progress_bar(&b).unwrap();
                 }
                 LoggingCommand::EndMultiProgress => {
                     central_logger.end_progress_bar();
                 }
+                LoggingCommand::AcquireTerminal(ack) => {
+                    foreground = true;
+                    central_logger.suspend_progress_bar();
+                    let _ = ack.send(());
+                }
+                LoggingCommand::ReleaseTerminal => {
+                    foreground = false;
+                    central_logger.resume_progress_bar();
+                    central_logger.flush_pending();
+                }
             }
         }
 
+        central_logger.end_task_groups();
         central_logger.flush();
+        if let Some(file) = &mut log_file {
+            let _ = file.flush();
+        }
     });
     LOGGING_CONTROL.reset();
     (send, handle)
@@ -555,11 +762,20 @@ fn start_central_logger(rich: bool) -> (Sender<LoggingCommand>, JoinHandle<()>)
 
 pub enum LoggingCommand {
     LogString(Origin, String),
+    /// A record to write to the log file
+    LogRecord(FileRecord),
+    /// An event for the json log stream, only emitted in json mode
+    Event(JsonEvent),
     TaskStarted(TaskId),
     TaskEnded(TaskId),
-    TaskStatus(TaskId, String),
+    /// The progress reported by a task
+    TaskStatus(TaskId, ProgressState),
     StartMultiProgress(MultiProgress),
     EndMultiProgress,
+    /// A task wants to use the terminal directly. The sender is used to acknowledge that progress
+    /// rendering has stopped.
+    AcquireTerminal(Sender<()>),
+    ReleaseTerminal,
     Flush,
     Stop,
 }
@@ -575,6 +791,9 @@ impl io::Write for CentralLoggerInput {
         let string = String::from_utf8_lossy(buf).to_string();
 
         let origin = thread_origin();
+        if let Origin::Task(task) = &origin {
+            excerpts::record(task, &string);
+        }
         // println!("sending from origin: {origin:?}");
         self.sender
             .send(LoggingCommand::LogString(origin, string))
@@ -597,6 +816,8 @@ pub struct CentralLoggerOutput {
     previous: Option<Origin>,
     last_query: Option<Instant>,
     progress_bar: Option<MultiProgress>,
+    work_in_progress: WorkInProgress,
+    task_groups: Option<TaskGroups>,
 }
 
 impl CentralLoggerOutput {
@@ -608,6 +829,8 @@ impl CentralLoggerOutput {
             previous: None,
             last_query: None,
             progress_bar: None,
+            work_in_progress: WorkInProgress::new(),
+            task_groups: None,
         }
     }
 
@@ -681,6 +904,13 @@ impl CentralLoggerOutput {
         }
     }
 
+    /// Flushes the complete lines of every origin with pending output
+    pub fn flush_pending(&mut self) {
+        for _ in 0..self.origin_queue.len() {
+            self.flush_current_origin();
+        }
+    }
+
     pub fn flush(&mut self) {
         let printer = self.logger_stdout();
         let drained = self.origin_queue.drain(..).collect::<Vec<_>>();
@@ -694,12 +924,7 @@ impl CentralLoggerOutput {
     }
 
     pub fn println(&self, string: impl AsRef<str>) -> io::Result<()> {
-        match &self.progress_bar {
-            None => {
-                writeln!(stdout(), "{}", string.as_ref())
-            }
-            Some(p) => p.println(string),
-        }
+        self.logger_stdout().println(string)
     }
 
     pub fn logger_stdout(&self) -> LoggerStdout {
@@ -712,14 +937,103 @@ impl CentralLoggerOutput {
     /// returned value is a clone of the multi-progress bar
     pub fn start_progress_bar(&mut self, bar: &MultiProgress) -> Result<MultiProgress, ()> {
         self.progress_bar = Some(bar.clone());
+        self.work_in_progress.attach(bar);
         Ok(bar.clone())
     }
 
-    /// End a progress bar if it exists
+    /// Adds a task to the work in progress region
+    pub fn task_started(&mut self, id: &TaskId) {
+        self.work_in_progress.task_started(id);
+    }
+
+    /// Shows the progress of a task on its line in the work in progress region
+    pub fn task_status(&mut self, id: &TaskId, state: ProgressState) {
+        self.work_in_progress.set_progress(id, state);
+    }
+
+    /// Removes a task from the work in progress region. If the region is drawn, a line for the
+    /// completed task is queued to be printed above it. If task output is grouped, the output of
+    /// the task is queued to be printed as one block.
+    pub fn task_ended(&mut self, id: &TaskId) {
+        if let Some(elapsed) = self.work_in_progress.task_ended(id) {
+            if self.work_in_progress.is_attached() {
+                self.add_output(Origin::None, &format!("{}\n", completed_line(id, elapsed)));
+            }
+        }
+        if let Some(block) = self
+            .task_groups
+            .as_mut()
+            .and_then(|groups| groups.finish(id))
+        {
+            self.add_output(Origin::None, &block);
+        }
+    }
+
+    /// Buffers the output of each task until the task ends, instead of printing it as it's logged
+    pub fn group_task_output(&mut self) {
+        self.task_groups = Some(TaskGroups::new());
+    }
+
+    /// Starts buffering the output of a task, if task output is grouped
+    pub fn start_task_group(&mut self, id: &TaskId) {
+        if let Some(groups) = &mut self.task_groups {
+            groups.start(id);
+        }
+    }
+
+    /// Buffers output if it's from a task whose output is grouped. Returns whether the output was
+    /// buffered.
+    pub fn buffer_task_output(&mut self, origin: &Origin, output: &str) -> bool {
+        match &mut self.task_groups {
+            Some(groups) => groups.buffer(origin, output),
+            None => false,
+        }
+    }
+
+    /// Prints the output of tasks that never ended, such as tasks that were running when the
+    /// build was aborted
+    pub fn end_task_groups(&mut self) {
+        let blocks = match &mut self.task_groups {
+            Some(groups) => groups.finish_all(),
+            None => return,
+        };
+        for block in blocks {
+            self.add_output(Origin::None, &block);
+            self.flush_current_origin();
+        }
+    }
+
+    /// Updates the elapsed times of the tasks in the work in progress region
+    pub fn redraw_work_in_progress(&self) {
+        self.work_in_progress.redraw();
+    }
+
+    /// Stops drawing the progress bar, if one exists, and removes it from the terminal
+    pub fn suspend_progress_bar(&mut self) {
+        if let Some(progress) = &self.progress_bar {
+            let _ = progress.clear();
+            progress.set_draw_target(ProgressDrawTarget::hidden());
+        }
+    }
+
+    /// Resumes drawing a suspended progress bar
+    pub fn resume_progress_bar(&mut self) {
+        if let Some(progress) = &self.progress_bar {
+            progress.set_draw_target(ProgressDrawTarget::stderr_with_hz(u8::MAX));
+        }
+    }
+
+    /// End a progress bar if it exists. The work in progress region is replaced by a summary of
+    /// the tasks that completed while it was drawn.
     pub fn end_progress_bar(&mut self) {
+        let summary = self.work_in_progress.summary();
+        self.work_in_progress.detach();
         let replaced = std::mem::replace(&mut self.progress_bar, None);
         if let Some(replaced) = replaced {
             replaced.clear().unwrap();
+            if let Some(summary) = summary {
+                self.println(summary).unwrap();
+            }
         }
     }
 }
@@ -729,12 +1043,14 @@ pub struct LoggerStdout {
 }
 
 impl LoggerStdout {
+    /// Prints a line to stdout. If progress is being drawn to the same terminal, the line is
+    /// printed above the progress bars. If stdout has been redirected, the line is written directly.
     pub fn println(&self, string: impl AsRef<str>) -> io::Result<()> {
         match &self.progress {
-            None => {
+            Some(p) if terminal::stdout_is_terminal() => p.println(string),
+            _ => {
                 writeln!(stdout(), "{}", string.as_ref())
             }
-            Some(p) => p.println(string),
         }
     }
 }
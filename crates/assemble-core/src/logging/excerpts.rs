@@ -0,0 +1,52 @@
+//! Keeps the last lines logged by every task, so they can be shown in build reports.
+
+use crate::identifier::TaskId;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+
+/// The maximum amount of lines kept for every task
+pub const EXCERPT_LINES: usize = 20;
+
+static EXCERPTS: Lazy<Mutex<HashMap<TaskId, VecDeque<String>>>> = Lazy::new(Default::default);
+
+/// Records a message logged by a task
+pub(crate) fn record(task: &TaskId, message: &str) {
+    let mut excerpts = EXCERPTS.lock();
+    let lines = excerpts.entry(task.clone()).or_default();
+    for line in message.lines().filter(|line| !line.trim().is_empty()) {
+        if lines.len() == EXCERPT_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(console::strip_ansi_codes(line).to_string());
+    }
+}
+
+/// Gets the last lines logged by a task, oldest first
+pub fn task_log_excerpt(task: &TaskId) -> Vec<String> {
+    EXCERPTS
+        .lock()
+        .get(task)
+        .map(|lines| lines.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_last_lines() {
+        let id = TaskId::new(":excerpts:task").unwrap();
+        for i in 0..(EXCERPT_LINES + 5) {
+            record(&id, &format!("\u{1b}[31mline {}\u{1b}[0m\n", i));
+        }
+        let excerpt = task_log_excerpt(&id);
+        assert_eq!(excerpt.len(), EXCERPT_LINES);
+        assert_eq!(excerpt.first().unwrap(), "line 5");
+        assert_eq!(
+            excerpt.last().unwrap(),
+            &format!("line {}", EXCERPT_LINES + 4)
+        );
+    }
+}
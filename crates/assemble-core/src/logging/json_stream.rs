@@ -0,0 +1,161 @@
+//! The machine readable output of `--json`.
+//!
+//! With `--json`, everything assemble prints to stdout is newline delimited json: every line is a
+//! single [`JsonEvent`](JsonEvent) object, so tools and IDEs can follow a build without parsing
+//! human readable output. Progress isn't drawn on stdout in this mode.
+//!
+//! Every event has a `type` field naming the kind of event, and the fields of that event:
+//!
+//! | `type` | Fields |
+//! |---|---|
+//! | `build_started` | `schema`: the version of this schema, `assemble`: the version of assemble |
+//! | `log` | `level`: one of `Error`, `Warn`, `Info`, `Debug` or `Trace`, `message`, and the `project` or `task` the message originated from, if any |
+//! | `task_started` | `task` |
+//! | `task_finished` | `task`, `outcome`: one of `executed`, `skipped`, `up_to_date`, `no_source` or `failed`, `duration_ms`, and `error` if the task failed |
+//! | `build_finished` | `success`, `duration_ms`, `failed_tasks`, and `error` and `code` if the build failed |
+//!
+//! ```text
+//! {"type":"build_started","schema":1,"assemble":"0.2.0"}
+//! {"type":"task_started","task":":root:build"}
+//! {"type":"log","level":"Info","message":"compiling","task":":root:build"}
+//! {"type":"task_finished","task":":root:build","outcome":"executed","duration_ms":1200}
+//! {"type":"build_finished","success":true,"duration_ms":1500,"failed_tasks":[]}
+//! ```
+//!
+//! Fields are only ever added to events, and new kinds of events may be added, so readers should
+//! ignore what they don't recognize. Removing or changing a field increments
+//! [`SCHEMA_VERSION`](SCHEMA_VERSION).
+
+use crate::identifier::{ProjectId, TaskId};
+use crate::logging::{LevelDef, Origin};
+use crate::task::TaskOutcome;
+use log::Level;
+
+/// The version of the schema of json events
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// An event emitted as a line of json in `--json` mode
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JsonEvent {
+    /// The build started. Always the first event.
+    BuildStarted {
+        /// The version of the schema of events
+        schema: u32,
+        /// The version of assemble running the build
+        assemble: String,
+    },
+    /// A log message
+    Log {
+        /// The level of the message
+        #[serde(with = "LevelDef")]
+        level: Level,
+        /// The message
+        message: String,
+        /// The project the message originated from
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        project: Option<ProjectId>,
+        /// The task the message originated from
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        task: Option<TaskId>,
+    },
+    /// A task started executing
+    TaskStarted {
+        /// The task
+        task: TaskId,
+    },
+    /// A task finished
+    TaskFinished {
+        /// The task
+        task: TaskId,
+        /// The outcome of the task
+        outcome: TaskOutcome,
+        /// How long the task executed for, in milliseconds
+        duration_ms: u64,
+        /// Why the task failed
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// The build finished. Always the last event.
+    BuildFinished {
+        /// Whether the build succeeded
+        success: bool,
+        /// How long the build took, in milliseconds
+        duration_ms: u64,
+        /// The tasks that failed
+        failed_tasks: Vec<TaskId>,
+        /// Why the build failed
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        /// The code of the error the build failed with
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        code: Option<String>,
+    },
+}
+
+impl JsonEvent {
+    /// The first event of a build
+    pub fn build_started() -> Self {
+        JsonEvent::BuildStarted {
+            schema: SCHEMA_VERSION,
+            assemble: crate::version::version().version().to_string(),
+        }
+    }
+
+    /// A log message from an origin
+    pub fn log(level: Level, message: impl ToString, origin: Origin) -> Self {
+        let (project, task) = match origin {
+            Origin::Project(project) => (Some(project), None),
+            Origin::Task(task) => (None, Some(task)),
+            Origin::None => (None, None),
+        };
+        JsonEvent::Log {
+            level,
+            message: message.to_string(),
+            project,
+            task,
+        }
+    }
+
+    /// The event as a single line of json, without a trailing newline
+    pub fn to_json_line(&self) -> String {
+        serde_json::to_string(self).expect("events can always be serialized")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn events_match_schema() {
+        let task = TaskId::new("root:build").unwrap();
+        let log = JsonEvent::log(Level::Info, "compiling", Origin::Task(task.clone()));
+        let value: Value = serde_json::from_str(&log.to_json_line()).unwrap();
+        assert_eq!(
+            value,
+            json!({"type": "log", "level": "Info", "message": "compiling", "task": ":root:build"})
+        );
+
+        let finished = JsonEvent::TaskFinished {
+            task,
+            outcome: TaskOutcome::UpToDate,
+            duration_ms: 12,
+            error: None,
+        };
+        let value: Value = serde_json::from_str(&finished.to_json_line()).unwrap();
+        assert_eq!(
+            value,
+            json!({"type": "task_finished", "task": ":root:build", "outcome": "up_to_date", "duration_ms": 12})
+        );
+    }
+
+    #[test]
+    fn events_are_single_lines() {
+        let event = JsonEvent::log(Level::Warn, "first\nsecond", Origin::None);
+        let line = event.to_json_line();
+        assert!(!line.contains('\n'));
+        assert_eq!(serde_json::from_str::<JsonEvent>(&line).unwrap(), event);
+    }
+}
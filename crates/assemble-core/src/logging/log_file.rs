@@ -0,0 +1,163 @@
+//! Writing log records to a file, set with `--log-file`.
+//!
+//! The log file has its own level, independent of the verbosity of the console, so a build can
+//! keep a complete debug log while the console stays quiet. Records are sent to the central logger
+//! thread unformatted and only formatted there, so console and file formatting don't affect each
+//! other. Every line of the file is prefixed with when the record was logged, its level, and the
+//! project or task it originated from.
+
+use crate::logging::{thread_origin, LoggingCommand, Origin};
+use fern::Output;
+use log::{Level, LevelFilter};
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+use std::{fs, io};
+use time::format_description::FormatItem;
+use time::macros::format_description;
+use time::OffsetDateTime;
+
+/// The level of the log file when none is given
+pub const DEFAULT_LOG_FILE_LEVEL: LevelFilter = LevelFilter::Debug;
+
+static TIMESTAMP_FORMAT: &[FormatItem] = format_description!(
+    "[year]-[month]-[day] [hour]:[minute]:[second].[subsecond digits:4] [offset_hour sign:mandatory]:[offset_minute]"
+);
+
+/// A log record sent to the central logger to be written to the log file
+#[derive(Debug, Clone)]
+pub struct FileRecord {
+    time: OffsetDateTime,
+    level: Level,
+    origin: Origin,
+    target: String,
+    message: String,
+}
+
+impl FileRecord {
+    /// Captures a log record on the thread that logged it
+    pub fn new(record: &log::Record) -> Self {
+        Self {
+            time: OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc()),
+            level: record.level(),
+            origin: thread_origin(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        }
+    }
+}
+
+impl Display for FileRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let origin = match &self.origin {
+            Origin::Project(project) => format!("project {}", project),
+            Origin::Task(task) => format!("task {}", task),
+            Origin::None => "build".to_string(),
+        };
+        let prefix = format!(
+            "{} {: <5} [{}] {}:",
+            self.time
+                .format(TIMESTAMP_FORMAT)
+                .map_err(|_| std::fmt::Error)?,
+            self.level,
+            origin,
+            self.target
+        );
+        let mut lines = self.message.lines();
+        writeln!(f, "{} {}", prefix, lines.next().unwrap_or_default())?;
+        for line in lines {
+            writeln!(f, "{:width$} {}", "", line, width = prefix.len())?;
+        }
+        Ok(())
+    }
+}
+
+/// The output of log records to the log file, which sends every record it receives to the central
+/// logger
+pub(crate) fn file_output(sender: Sender<LoggingCommand>) -> Output {
+    let sender = Mutex::new(sender);
+    Output::call(move |record| {
+        if let Ok(sender) = sender.lock() {
+            let _ = sender.send(LoggingCommand::LogRecord(FileRecord::new(record)));
+        }
+    })
+}
+
+/// The log file, owned by the central logger
+#[derive(Debug)]
+pub struct LogFile {
+    writer: BufWriter<File>,
+}
+
+impl LogFile {
+    /// Creates the log file, replacing an existing file at the path
+    pub fn create(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    /// Writes a record to the log file
+    pub fn write(&mut self, record: &FileRecord) -> io::Result<()> {
+        write!(self.writer, "{}", record)
+    }
+
+    /// Flushes the records written so far
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identifier::TaskId;
+    use tempfile::TempDir;
+
+    #[test]
+    fn records_are_prefixed_with_time_level_and_origin() {
+        let record = FileRecord {
+            time: OffsetDateTime::UNIX_EPOCH,
+            level: Level::Debug,
+            origin: Origin::Task(TaskId::new("root:build").unwrap()),
+            target: "assemble_core::task".to_string(),
+            message: "first line\nsecond line".to_string(),
+        };
+        let formatted = record.to_string();
+        let lines = formatted.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "1970-01-01 00:00:00.0000 +00:00 DEBUG [task :root:build] assemble_core::task: first line"
+        );
+        assert!(lines[1].ends_with("second line"));
+        assert!(lines[1].starts_with("      "));
+    }
+
+    #[test]
+    fn log_file_is_written() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("logs").join("build.log");
+        let mut file = LogFile::create(&path).unwrap();
+        let record = FileRecord {
+            time: OffsetDateTime::UNIX_EPOCH,
+            level: Level::Trace,
+            origin: Origin::None,
+            target: "assemble".to_string(),
+            message: "hello".to_string(),
+        };
+        file.write(&record).unwrap();
+        file.flush().unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(
+            contents.contains("TRACE [build] assemble: hello"),
+            "{}",
+            contents
+        );
+    }
+}
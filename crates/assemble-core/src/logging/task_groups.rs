@@ -0,0 +1,102 @@
+//! Grouping of task output, used by the `grouped` console mode.
+//!
+//! When tasks run in parallel, their log lines interleave. In the grouped console mode, the output
+//! of each task is buffered by the central logger while the task runs, then printed as one
+//! contiguous block headed by the task id once the task finishes.
+
+use crate::identifier::TaskId;
+use crate::logging::Origin;
+use crate::unstable::text_factory::AssembleFormatter;
+
+/// The buffered output of running tasks, in the order the tasks started
+#[derive(Debug, Default)]
+pub struct TaskGroups {
+    running: Vec<(TaskId, String)>,
+}
+
+impl TaskGroups {
+    /// Creates an empty set of groups
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts buffering the output of a task
+    pub fn start(&mut self, id: &TaskId) {
+        if !self.running.iter().any(|(running, _)| running == id) {
+            self.running.push((id.clone(), String::new()));
+        }
+    }
+
+    /// Buffers output if it originates from a running task. Returns whether the output was
+    /// buffered, otherwise it should be printed immediately.
+    pub fn buffer(&mut self, origin: &Origin, output: &str) -> bool {
+        let id = match origin {
+            Origin::Task(id) => id,
+            _ => return false,
+        };
+        match self.running.iter_mut().find(|(running, _)| running == id) {
+            Some((_, buffer)) => {
+                buffer.push_str(output);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stops buffering the output of a task, returning its block of output
+    pub fn finish(&mut self, id: &TaskId) -> Option<String> {
+        let index = self.running.iter().position(|(running, _)| running == id)?;
+        let (id, buffer) = self.running.remove(index);
+        Some(block(&id, &buffer))
+    }
+
+    /// Stops buffering the output of every task, returning their blocks of output in the order
+    /// the tasks started
+    pub fn finish_all(&mut self) -> Vec<String> {
+        self.running
+            .drain(..)
+            .map(|(id, buffer)| block(&id, &buffer))
+            .collect()
+    }
+}
+
+/// The output of a task headed by its id. Always ends with a newline.
+fn block(id: &TaskId, output: &str) -> String {
+    let header = AssembleFormatter::default().task_status(id, "").unwrap();
+    let output = output.trim_end();
+    if output.is_empty() {
+        format!("{}\n", header)
+    } else {
+        format!("{}\n{}\n", header, output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_is_grouped_by_task() {
+        let first = TaskId::new("first").unwrap();
+        let second = TaskId::new("second").unwrap();
+        let mut groups = TaskGroups::new();
+        groups.start(&first);
+        groups.start(&second);
+
+        assert!(groups.buffer(&Origin::Task(first.clone()), "first 1\n"));
+        assert!(groups.buffer(&Origin::Task(second.clone()), "second 1\n"));
+        assert!(groups.buffer(&Origin::Task(first.clone()), "first 2\n"));
+        assert!(!groups.buffer(&Origin::None, "build\n"));
+
+        let block = groups.finish(&first).unwrap();
+        let lines = block.lines().collect::<Vec<_>>();
+        assert!(lines[0].contains(":first"));
+        assert_eq!(&lines[1..], ["first 1", "first 2"]);
+        assert!(!groups.buffer(&Origin::Task(first.clone()), "late\n"));
+
+        let remaining = groups.finish_all();
+        assert_eq!(remaining.len(), 1);
+        assert!(remaining[0].ends_with("second 1\n"));
+        assert!(groups.finish(&second).is_none());
+    }
+}
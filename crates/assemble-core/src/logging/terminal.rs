@@ -0,0 +1,113 @@
+//! Information about the terminal assemble is outputting to.
+//!
+//! Progress is drawn on stderr while log messages are written to stdout, so each stream is checked
+//! separately for whether it's attached to a terminal.
+
+use atty::Stream;
+use console::Term;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Width used when the terminal width can not be determined
+pub const DEFAULT_CONSOLE_WIDTH: usize = 80;
+
+/// Below this width, progress bars are drawn in a compact form
+pub const NARROW_CONSOLE_WIDTH: usize = 60;
+
+/// The string used in place of removed characters when truncating
+const ELLIPSIS: &str = "...";
+
+static CONSOLE_WIDTH_OVERRIDE: AtomicUsize = AtomicUsize::new(0);
+
+/// Overrides the detected console width. Passing `None` removes the override.
+pub fn set_console_width_override(width: impl Into<Option<usize>>) {
+    CONSOLE_WIDTH_OVERRIDE.store(width.into().unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Gets the width of the console progress is drawn on.
+///
+/// The width is queried every time this function is called so that changes to the size of the
+/// terminal are picked up. If the width was overridden with `--console-width`, the override is
+/// always used.
+pub fn console_width() -> usize {
+    match CONSOLE_WIDTH_OVERRIDE.load(Ordering::Relaxed) {
+        0 => Term::stderr()
+            .size_checked()
+            .map(|(_, cols)| cols as usize)
+            .unwrap_or(DEFAULT_CONSOLE_WIDTH),
+        width => width,
+    }
+}
+
+/// Gets whether the console is too narrow to draw full width progress bars
+pub fn is_narrow_console() -> bool {
+    console_width() < NARROW_CONSOLE_WIDTH
+}
+
+/// Whether stdout, where log messages are written, is attached to a terminal
+pub fn stdout_is_terminal() -> bool {
+    atty::is(Stream::Stdout)
+}
+
+/// Whether stderr, where progress is drawn, is attached to a terminal
+pub fn stderr_is_terminal() -> bool {
+    atty::is(Stream::Stderr)
+}
+
+/// Truncates a string so it fits within `width` characters.
+///
+/// The middle of the string is removed, keeping both the start and the end. For task identifiers,
+/// this keeps the root project and the name of the task visible.
+pub fn truncate_middle(string: &str, width: usize) -> String {
+    let len = string.chars().count();
+    if len <= width {
+        return string.to_string();
+    }
+    if width <= ELLIPSIS.len() {
+        return string.chars().take(width).collect();
+    }
+    let remaining = width - ELLIPSIS.len();
+    let tail_len = (remaining + 1) / 2;
+    let head_len = remaining - tail_len;
+    let head: String = string.chars().take(head_len).collect();
+    let tail: String = string.chars().skip(len - tail_len).collect();
+    format!("{head}{ELLIPSIS}{tail}")
+}
+
+/// Truncates a string to fit on a single line of the console, after leaving room for `reserved`
+/// characters used by surrounding decoration.
+pub fn fit_to_console(string: &str, reserved: usize) -> String {
+    truncate_middle(string, console_width().saturating_sub(reserved).max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_strings_unchanged() {
+        assert_eq!(truncate_middle(":root:build", 20), ":root:build");
+        assert_eq!(truncate_middle(":root:build", 11), ":root:build");
+    }
+
+    #[test]
+    fn truncates_middle() {
+        let truncated = truncate_middle(":root:some:deep:project:compileRust", 20);
+        assert_eq!(truncated.chars().count(), 20);
+        assert!(truncated.starts_with(":root"));
+        assert!(truncated.ends_with("compileRust"));
+        assert!(truncated.contains(ELLIPSIS));
+    }
+
+    #[test]
+    fn tiny_widths() {
+        assert_eq!(truncate_middle(":root:build", 2), ":r");
+        assert_eq!(truncate_middle(":root:build", 0), "");
+    }
+
+    #[test]
+    fn override_width() {
+        set_console_width_override(42);
+        assert_eq!(console_width(), 42);
+        set_console_width_override(None);
+    }
+}
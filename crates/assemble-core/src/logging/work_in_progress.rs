@@ -0,0 +1,236 @@
+//! The work in progress region of the rich console.
+//!
+//! While tasks execute, the bottom of the console lists every task that's in flight with how long
+//! it has been running, below the progress of the build. The region grows and collapses as tasks
+//! start and finish, and is redrawn by the central logger every
+//! [`REDRAW_INTERVAL`](REDRAW_INTERVAL) so elapsed times stay current. Progress reported by a task
+//! through its [`TaskProgress`](crate::task::task_progress::TaskProgress) is shown on its line. Log
+//! output and the lines of completed tasks are printed above the region, so they scroll normally.
+
+use crate::humanize;
+use crate::identifier::TaskId;
+use crate::logging::terminal::{console_width, truncate_middle};
+use crate::task::task_progress::ProgressState;
+use crate::unstable::text_factory::AssembleFormatter;
+use colored::Colorize;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::time::{Duration, Instant};
+
+/// How often the work in progress region is redrawn
+pub const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+struct InFlight {
+    id: TaskId,
+    since: Instant,
+    progress: ProgressState,
+    bar: Option<ProgressBar>,
+}
+
+/// The tasks in flight, drawn at the bottom of the console while a progress bar is attached
+#[derive(Debug, Default)]
+pub struct WorkInProgress {
+    progress: Option<MultiProgress>,
+    in_flight: Vec<InFlight>,
+    completed: usize,
+    started: Option<Instant>,
+}
+
+impl WorkInProgress {
+    /// Creates an empty region, which isn't drawn until attached to a progress bar
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draws the region below the given progress bar. Tasks already in flight are added to it.
+    pub fn attach(&mut self, progress: &MultiProgress) {
+        self.detach();
+        self.started = Some(Instant::now());
+        self.completed = 0;
+        for task in &mut self.in_flight {
+            task.bar = Some(progress.add(line_bar()));
+        }
+        self.progress = Some(progress.clone());
+        self.redraw();
+    }
+
+    /// Stops drawing the region, removing its lines from the console
+    pub fn detach(&mut self) {
+        for task in &mut self.in_flight {
+            if let Some(bar) = task.bar.take() {
+                bar.finish_and_clear();
+            }
+        }
+        self.progress = None;
+    }
+
+    /// Whether the region is drawn
+    pub fn is_attached(&self) -> bool {
+        self.progress.is_some()
+    }
+
+    /// Adds a task to the bottom of the region
+    pub fn task_started(&mut self, id: &TaskId) {
+        let bar = self
+            .progress
+            .as_ref()
+            .map(|progress| progress.add(line_bar()));
+        self.in_flight.push(InFlight {
+            id: id.clone(),
+            since: Instant::now(),
+            progress: ProgressState::default(),
+            bar,
+        });
+        self.redraw();
+    }
+
+    /// Sets the progress shown on the line of a task in flight
+    pub fn set_progress(&mut self, id: &TaskId, progress: ProgressState) {
+        if let Some(task) = self.in_flight.iter_mut().find(|task| &task.id == id) {
+            task.progress = progress;
+        }
+    }
+
+    /// Removes a task from the region, collapsing its line. Returns how long the task was in
+    /// flight, if it was.
+    pub fn task_ended(&mut self, id: &TaskId) -> Option<Duration> {
+        let index = self.in_flight.iter().position(|task| &task.id == id)?;
+        let task = self.in_flight.remove(index);
+        if let Some(bar) = task.bar {
+            bar.finish_and_clear();
+            if let Some(progress) = &self.progress {
+                progress.remove(&bar);
+            }
+        }
+        self.completed += 1;
+        Some(task.since.elapsed())
+    }
+
+    /// The tasks in flight, in the order they started
+    pub fn in_flight(&self) -> impl Iterator<Item = &TaskId> {
+        self.in_flight.iter().map(|task| &task.id)
+    }
+
+    /// Updates the elapsed times and progress shown in the region
+    pub fn redraw(&self) {
+        if self.progress.is_none() {
+            return;
+        }
+        let width = console_width();
+        for task in &self.in_flight {
+            if let Some(bar) = &task.bar {
+                bar.set_message(task_line(
+                    &task.id,
+                    &task.progress,
+                    task.since.elapsed(),
+                    width,
+                ));
+            }
+        }
+    }
+
+    /// A summary of the tasks completed since the region was attached, shown in place of the region
+    /// once the build finishes
+    pub fn summary(&self) -> Option<String> {
+        let started = self.started?;
+        let tasks = match self.completed {
+            0 => return None,
+            1 => "1 task".to_string(),
+            n => format!("{} tasks", n),
+        };
+        Some(
+            format!(
+                "{} completed in {}",
+                tasks,
+                humanize::duration(started.elapsed())
+            )
+            .bold()
+            .to_string(),
+        )
+    }
+}
+
+fn line_bar() -> ProgressBar {
+    ProgressBar::new_spinner().with_style(ProgressStyle::with_template("{msg}").unwrap())
+}
+
+/// The line of a task in flight, fit within `width` characters. The progress of the task is
+/// truncated first, then the task id, so the elapsed time stays visible.
+fn task_line(id: &TaskId, progress: &ProgressState, elapsed: Duration, width: usize) -> String {
+    let elapsed = format!(" ({})", humanize::duration(elapsed));
+    let id = id.to_string();
+    let reserved = "> ".len() + elapsed.len();
+    let available = width.saturating_sub(reserved);
+    let progress = progress.to_string();
+    let progress = if progress.is_empty() || available <= id.len() + 1 {
+        String::new()
+    } else {
+        format!(" {}", truncate_middle(&progress, available - id.len() - 1))
+    };
+    let id = truncate_middle(&id, available.max(1));
+    format!("> {}{}{}", id, progress, elapsed)
+}
+
+/// The line printed above the region when a task completes
+pub fn completed_line(id: &TaskId, elapsed: Duration) -> String {
+    AssembleFormatter::default()
+        .task_status(id, humanize::duration(elapsed))
+        .unwrap()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tasks_collapse_when_ended() {
+        let first = TaskId::new("first").unwrap();
+        let second = TaskId::new("second").unwrap();
+        let mut region = WorkInProgress::new();
+        region.task_started(&first);
+        region.task_started(&second);
+        assert_eq!(
+            region.in_flight().collect::<Vec<_>>(),
+            vec![&first, &second]
+        );
+
+        assert!(region.task_ended(&first).is_some());
+        assert!(region.task_ended(&first).is_none());
+        assert_eq!(region.in_flight().collect::<Vec<_>>(), vec![&second]);
+        assert_eq!(region.summary(), None, "never attached");
+    }
+
+    #[test]
+    fn lines_keep_elapsed_time_visible() {
+        let id = TaskId::new(":root:some:deep:project:compileRust").unwrap();
+        let line = task_line(&id, &ProgressState::default(), Duration::from_secs(3), 30);
+        assert!(line.chars().count() <= 30, "{:?}", line);
+        assert!(line.starts_with("> :root"));
+        assert!(line.ends_with(&format!("({})", humanize::duration(Duration::from_secs(3)))));
+    }
+
+    #[test]
+    fn lines_show_task_progress() {
+        let id = TaskId::new("download").unwrap();
+        let progress = ProgressState {
+            position: 3,
+            total: Some(10),
+            message: Some("fetching c.txt".to_string()),
+        };
+        let mut region = WorkInProgress::new();
+        region.task_started(&id);
+        region.set_progress(&id, progress.clone());
+        assert_eq!(region.in_flight[0].progress, progress);
+
+        let line = task_line(&id, &progress, Duration::from_secs(1), 80);
+        assert!(
+            line.starts_with("> :download [3/10] fetching c.txt ("),
+            "{:?}",
+            line
+        );
+        let line = task_line(&id, &progress, Duration::from_secs(1), 24);
+        assert!(line.chars().count() <= 24, "{:?}", line);
+        assert!(line.starts_with("> :download "), "{:?}", line);
+    }
+}
@@ -1,5 +1,6 @@
 //! Provide a "unified" way of adding plugins to an assemble project
 
+use crate::deprecations::DeprecationOrigin;
 use crate::project::error::ProjectResult;
 
 use crate::utilities::Action;
@@ -11,6 +12,9 @@ use std::fmt::{Debug, Formatter};
 
 use std::sync::Arc;
 
+pub mod binary;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 pub mod extensions;
 
 /// A plugin to apply to the project. All plugins must implement default.
@@ -32,6 +36,24 @@ pub trait PluginAware: Sized {
         manager.apply::<P>(self)
     }
 
+    /// Apply a plugin to this only if a condition holds for it. The condition is checked when this
+    /// is called, so it sees this value as configured so far.
+    ///
+    /// Returns whether the plugin was applied.
+    ///
+    /// # Example
+    /// ```ignore
+    /// project.apply_plugin_if::<RustPlugin, _>(|p| p.file_exists("Cargo.toml"))?;
+    /// ```
+    fn apply_plugin_if<P, F>(&mut self, condition: F) -> ProjectResult<bool>
+    where
+        P: Plugin<Self>,
+        F: FnOnce(&Self) -> bool,
+    {
+        let manager = &mut self.plugin_manager().clone();
+        manager.apply_if::<P, F>(self, condition)
+    }
+
     /// Gets a reference to the plugin manager for this value.
     fn plugin_manager(&self) -> &PluginManager<Self>;
     /// Gets a mutable reference to the plugin manager for this value.
@@ -68,6 +90,25 @@ impl<T: PluginAware> PluginManager<T> {
         self.0.apply::<P>(target)
     }
 
+    /// Applies this plugin if the condition holds for the target and the plugin hasn't been
+    /// applied before. Returns whether the condition held.
+    pub fn apply_if<P, F>(&mut self, target: &mut T, condition: F) -> ProjectResult<bool>
+    where
+        P: Plugin<T>,
+        F: FnOnce(&T) -> bool,
+    {
+        if condition(target) {
+            self.0.apply::<P>(target)?;
+            Ok(true)
+        } else {
+            trace!(
+                "condition for plugin of type {} not met",
+                std::any::type_name::<P>()
+            );
+            Ok(false)
+        }
+    }
+
     /// Set an action to perform if a plugin has been applied
     pub fn with_plugin<F: 'static>(&mut self, id: &str, target: &mut T, action: F) -> ProjectResult
     where
@@ -135,7 +176,10 @@ impl<T: PluginAware> PluginManagerInner<T> {
             let plugin = P::default();
             let id = plugin.plugin_id().to_string();
             trace!("applying generated plugin of type {type_name} with id {id}");
-            plugin.apply_to(target)?;
+            {
+                let _origin = DeprecationOrigin::enter(format_args!("plugin {}", id));
+                plugin.apply_to(target)?;
+            }
             trace!("added applied plugin id {id}");
             self.applied.write().insert(id);
 
@@ -185,4 +229,9 @@ impl<T: PluginAware> PluginManagerInner<T> {
 pub enum PluginError {
     #[error("Couldn't create the plugin")]
     CouldNotCreatePlugin,
+    #[error(transparent)]
+    BinaryPlugin(#[from] binary::BinaryPluginError),
+    #[cfg(feature = "wasm")]
+    #[error(transparent)]
+    WasmPlugin(#[from] wasm::WasmPluginError),
 }
@@ -0,0 +1,602 @@
+//! Binary plugins, which are dynamic libraries resolved from plugin repositories.
+//!
+//! A plugin is requested by its id and version, such as `org.example.foo` version `1.2`, and is
+//! resolved from the [plugin repositories](crate::startup::invocation::StartParameter::plugin_repositories)
+//! of the build in order. Within a repository, the library of a plugin is found at
+//! ```text
+//! <repository>/org/example/foo/1.2/org.example.foo-1.2.<so|dylib|dll>
+//! ```
+//! next to a `.sha256` file containing its checksum. When the `wasm` feature is enabled, plugins
+//! can also be published as WebAssembly modules with the `.wasm` extension, which are used if
+//! there's no library for the platform. See [`wasm`](crate::plugins::wasm) for how they're run.
+//!
+//! Libraries are downloaded once into the
+//! [`BINARY_PLUGINS_DIR`](BINARY_PLUGINS_DIR) directory of `ASSEMBLE_HOME`, and their checksum is
+//! verified every time they're loaded.
+//!
+//! A library declares the plugins it contains with [`export_plugins!`](crate::export_plugins):
+//! ```ignore
+//! assemble_core::export_plugins!(|registrar| {
+//!     registrar.register::<FooPlugin>("org.example.foo");
+//! });
+//! ```
+//! or by creating each plugin with the [`#[plugin]`](crate::plugin) attribute, which exports an
+//! entry point for the plugin named by [`plugin_symbol`](plugin_symbol).
+//!
+//! Rust has no stable ABI, so a library must be built against the same version of `assemble-core`,
+//! with the same compiler and features, as the assemble loading it. `export_plugins!` also exports
+//! the [`PluginApi`](PluginApi) fingerprint of the library, which is checked through a C-compatible
+//! symbol before anything else in the library is used.
+
+use crate::cryptography::hash_file_sha256;
+use crate::error::PayloadError;
+use crate::plugins::{Plugin, PluginAware, PluginError};
+use crate::prelude::AssembleAware;
+use crate::project::error::ProjectResult;
+use crate::web::{WebClient, WebError};
+use crate::{Project, ASSEMBLE_HOME};
+use libloading::Library;
+use once_cell::sync::Lazy;
+use reqwest::StatusCode;
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::fmt::{Debug, Display, Formatter};
+use std::io;
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use url::Url;
+
+/// The directory within `ASSEMBLE_HOME` binary plugins are cached in
+pub const BINARY_PLUGINS_DIR: &str = "plugins";
+
+/// The symbol of the [`PluginDeclaration`](PluginDeclaration) exported by a plugin library
+pub const PLUGIN_DECLARATION: &str = "__assemble_plugin_declaration";
+
+/// The symbol of the function returning the [`PluginApi`](PluginApi) fingerprint of a plugin
+/// library
+pub const PLUGIN_API: &str = "__assemble_plugin_api";
+
+/// The version of `assemble-core` that plugin libraries must be built against
+pub const CORE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The version of the compiler that plugin libraries must be built with
+pub const RUSTC_VERSION: &str = env!("ASSEMBLE_RUSTC_VERSION");
+
+/// The features of `assemble-core` that change its api
+const API_FEATURES: &[(&str, bool)] = &[
+    ("compact", cfg!(feature = "compact")),
+    ("derive", cfg!(feature = "derive")),
+    ("log_origin_control", cfg!(feature = "log_origin_control")),
+    ("ron", cfg!(feature = "ron")),
+    ("text_factory", cfg!(feature = "text_factory")),
+    ("unstable", cfg!(feature = "unstable")),
+    ("wasm", cfg!(feature = "wasm")),
+];
+
+/// The fingerprint of the `assemble-core` api a plugin library was built against. A library can
+/// only be loaded by an assemble with the same fingerprint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginApi {
+    core_version: String,
+    rustc_version: String,
+    features: Vec<String>,
+}
+
+impl PluginApi {
+    /// The api of the running assemble
+    pub fn current() -> Self {
+        Self {
+            core_version: CORE_VERSION.to_string(),
+            rustc_version: RUSTC_VERSION.to_string(),
+            features: API_FEATURES
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(feature, _)| feature.to_string())
+                .collect(),
+        }
+    }
+
+    /// Parses a fingerprint created by [`fingerprint`](PluginApi::fingerprint)
+    pub fn parse(fingerprint: &str) -> Option<Self> {
+        let mut parts = fingerprint.split(';');
+        let core_version = parts.next()?.strip_prefix("assemble-core ")?;
+        let rustc_version = parts.next()?;
+        let features = parts.next()?.strip_prefix("features=")?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            core_version: core_version.to_string(),
+            rustc_version: rustc_version.to_string(),
+            features: features
+                .split(',')
+                .filter(|feature| !feature.is_empty())
+                .map(str::to_string)
+                .collect(),
+        })
+    }
+
+    /// The version of `assemble-core`
+    pub fn core_version(&self) -> &str {
+        &self.core_version
+    }
+
+    /// The version of the compiler
+    pub fn rustc_version(&self) -> &str {
+        &self.rustc_version
+    }
+
+    /// The enabled features of `assemble-core`
+    pub fn features(&self) -> &[String] {
+        &self.features
+    }
+
+    /// A nul-terminated fingerprint of the api, as exported by plugin libraries
+    pub fn fingerprint(&self) -> CString {
+        CString::new(format!(
+            "assemble-core {};{};features={}",
+            self.core_version,
+            self.rustc_version,
+            self.features.join(",")
+        ))
+        .expect("fingerprint contains no nul bytes")
+    }
+
+    /// The nul-terminated fingerprint of the running assemble, which lives for the whole program
+    #[doc(hidden)]
+    pub fn current_fingerprint() -> *const c_char {
+        static FINGERPRINT: Lazy<CString> = Lazy::new(|| PluginApi::current().fingerprint());
+        FINGERPRINT.as_ptr()
+    }
+}
+
+impl Display for PluginApi {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "assemble-core {} ({}",
+            self.core_version, self.rustc_version
+        )?;
+        if !self.features.is_empty() {
+            write!(f, ", features: {}", self.features.join(", "))?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Declares the plugins within a library. Created by [`export_plugins!`](crate::export_plugins).
+#[derive(Debug)]
+pub struct PluginDeclaration {
+    /// Registers the plugins of the library
+    pub register: fn(&mut PluginRegistrar),
+}
+
+/// Exports the plugins of a library so they can be loaded as binary plugins. Takes a function
+/// registering the plugins with a [`PluginRegistrar`](crate::plugins::binary::PluginRegistrar).
+#[macro_export]
+macro_rules! export_plugins {
+    ($register:expr) => {
+        #[doc(hidden)]
+        #[no_mangle]
+        pub extern "C" fn __assemble_plugin_api() -> *const ::std::os::raw::c_char {
+            $crate::plugins::binary::PluginApi::current_fingerprint()
+        }
+
+        #[doc(hidden)]
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        pub static __assemble_plugin_declaration: $crate::plugins::binary::PluginDeclaration =
+            $crate::plugins::binary::PluginDeclaration {
+                register: $register,
+            };
+    };
+}
+
+type ApplyPlugin = dyn Fn(&mut Project) -> ProjectResult + Send + Sync;
+
+/// Collects the plugins declared by a library
+#[derive(Default)]
+pub struct PluginRegistrar {
+    plugins: HashMap<String, Arc<ApplyPlugin>>,
+}
+
+impl PluginRegistrar {
+    /// Registers a plugin with an id
+    pub fn register<P: Plugin<Project> + 'static>(&mut self, id: &str) {
+        self.plugins.insert(
+            id.to_string(),
+            Arc::new(|project: &mut Project| project.apply_plugin::<P>()),
+        );
+    }
+
+    /// The ids of the registered plugins
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.plugins.keys().map(|s| s.as_str())
+    }
+}
+
+impl Debug for PluginRegistrar {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.plugins.keys()).finish()
+    }
+}
+
+/// The formats binary plugins are published in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PluginFormat {
+    /// A dynamic library for the current platform
+    Native,
+    /// A WebAssembly module
+    Wasm,
+}
+
+impl PluginFormat {
+    /// The file extension of plugins in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PluginFormat::Native => std::env::consts::DLL_EXTENSION,
+            PluginFormat::Wasm => "wasm",
+        }
+    }
+}
+
+/// A request for a binary plugin
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PluginRequest {
+    id: String,
+    version: String,
+}
+
+impl PluginRequest {
+    /// Creates a request for a version of a plugin
+    pub fn new(id: &str, version: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            version: version.to_string(),
+        }
+    }
+
+    /// The id of the requested plugin
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The requested version
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// The name of the file of the plugin in a format
+    pub fn file_name(&self, format: PluginFormat) -> String {
+        format!("{}-{}.{}", self.id, self.version, format.extension())
+    }
+
+    /// The path of the file of the plugin in a format, relative to a repository
+    pub fn relative_path(&self, format: PluginFormat) -> String {
+        format!(
+            "{}/{}/{}",
+            self.id.replace('.', "/"),
+            self.version,
+            self.file_name(format)
+        )
+    }
+}
+
+impl Display for PluginRequest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} version {}", self.id, self.version)
+    }
+}
+
+/// An error occurred resolving or loading a binary plugin
+#[derive(Debug, thiserror::Error)]
+pub enum BinaryPluginError {
+    #[error("plugin {request} couldn't be found in any plugin repository ({repositories:?})")]
+    NotFound {
+        request: PluginRequest,
+        repositories: Vec<Url>,
+    },
+    #[error("plugin {0} hasn't been downloaded, and the build is offline")]
+    Offline(PluginRequest),
+    #[error("checksum of {path:?} doesn't match (expected sha256 {expected}, found {actual})")]
+    ChecksumMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+    #[error(
+        "plugin {path:?} was built against incompatible assemble version {found}, but {expected} \
+        is running"
+    )]
+    IncompatibleVersion {
+        path: PathBuf,
+        found: String,
+        expected: PluginApi,
+    },
+    #[error("{0:?} isn't a plugin library built with export_plugins!")]
+    NotAPlugin(PathBuf),
+    #[error("{path:?} doesn't declare a plugin with id {id:?}")]
+    NotDeclared { path: PathBuf, id: String },
+    #[error(transparent)]
+    LoadError(#[from] libloading::Error),
+    #[error(transparent)]
+    WebError(#[from] WebError),
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+}
+
+/// Resolves the file of a plugin in a format, downloading it into `ASSEMBLE_HOME` if it isn't
+/// cached yet
+pub fn resolve_plugin(
+    request: &PluginRequest,
+    format: PluginFormat,
+    repositories: &[Url],
+    offline: bool,
+) -> Result<PathBuf, BinaryPluginError> {
+    resolve_plugin_in(
+        request,
+        format,
+        repositories,
+        ASSEMBLE_HOME.path().join(BINARY_PLUGINS_DIR),
+        offline,
+    )
+}
+
+/// Resolves the file of a plugin in a format, caching downloads in the given directory
+pub fn resolve_plugin_in<P: AsRef<Path>>(
+    request: &PluginRequest,
+    format: PluginFormat,
+    repositories: &[Url],
+    cache_dir: P,
+    offline: bool,
+) -> Result<PathBuf, BinaryPluginError> {
+    let library = cache_dir.as_ref().join(request.relative_path(format));
+    let checksum_file = checksum_path(&library);
+    if library.is_file() && checksum_file.is_file() {
+        verify_checksum(&library, &std::fs::read_to_string(&checksum_file)?)?;
+        return Ok(library);
+    }
+    if offline {
+        return Err(BinaryPluginError::Offline(request.clone()));
+    }
+
+    let client = WebClient::new();
+    for repository in repositories {
+        let url = library_url(repository, request, format);
+        let checksum_url = Url::parse(&format!("{}.sha256", url)).expect("valid url");
+        let checksum = match client.get_bytes(&checksum_url) {
+            Ok(checksum) => String::from_utf8_lossy(&checksum).to_string(),
+            Err(WebError::Status { status, .. }) if status == StatusCode::NOT_FOUND => {
+                debug!("plugin {} not found in {}", request, repository);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        info!("downloading plugin {} from {}", request, repository);
+        client.download(&url, &library)?;
+        if let Err(e) = verify_checksum(&library, &checksum) {
+            std::fs::remove_file(&library)?;
+            return Err(e);
+        }
+        std::fs::write(&checksum_file, checksum)?;
+        return Ok(library);
+    }
+    Err(BinaryPluginError::NotFound {
+        request: request.clone(),
+        repositories: repositories.to_vec(),
+    })
+}
+
+fn library_url(repository: &Url, request: &PluginRequest, format: PluginFormat) -> Url {
+    let mut repository = repository.clone();
+    if !repository.path().ends_with('/') {
+        repository.set_path(&format!("{}/", repository.path()));
+    }
+    repository
+        .join(&request.relative_path(format))
+        .expect("valid plugin path")
+}
+
+fn checksum_path(library: &Path) -> PathBuf {
+    let mut file_name = library.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".sha256");
+    library.with_file_name(file_name)
+}
+
+/// Checks that a file has a checksum. Only the first word of the checksum is used, so checksums in
+/// the format of `sha256sum` are accepted.
+pub fn verify_checksum(path: &Path, checksum: &str) -> Result<(), BinaryPluginError> {
+    let expected = checksum
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    let actual = hash_file_sha256(path)?.to_string();
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(BinaryPluginError::ChecksumMismatch {
+            path: path.to_path_buf(),
+            expected,
+            actual,
+        })
+    }
+}
+
+type ReadApi = extern "C" fn() -> *const c_char;
+
+type RegisterPlugin = extern "C" fn(&mut PluginRegistrar);
+
+/// The symbol of the entry point exported for a plugin created with [`#[plugin]`](crate::plugin).
+/// The symbol of its api fingerprint has an extra `_api` suffix.
+pub fn plugin_symbol(id: &str) -> String {
+    let id: String = id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("__assemble_plugin_{}", id)
+}
+
+/// Loads a plugin library, returning the plugin with the given id.
+///
+/// Loaded libraries are never unloaded, as plugins applied from them may still be referenced.
+pub fn load_plugin(library: &Path, id: &str) -> Result<Arc<ApplyPlugin>, BinaryPluginError> {
+    let mut registrar = PluginRegistrar::default();
+    let symbol = plugin_symbol(id);
+    let symbol_api = format!("{}_api", symbol);
+    // SAFETY: the fingerprint is read through a C-compatible function, and the entry points are
+    // only used if the library was built against the same api as the running assemble
+    unsafe {
+        let lib = Library::new(library)?;
+        let (api, by_attribute) = match lib.get::<ReadApi>(symbol_api.as_bytes()) {
+            Ok(api) => (api, true),
+            Err(_) => (
+                lib.get::<ReadApi>(PLUGIN_API.as_bytes())
+                    .map_err(|_| BinaryPluginError::NotAPlugin(library.to_path_buf()))?,
+                false,
+            ),
+        };
+        let found = CStr::from_ptr(api()).to_string_lossy().to_string();
+        check_api(library, &found)?;
+        if by_attribute {
+            let register = lib.get::<RegisterPlugin>(symbol.as_bytes())?;
+            register(&mut registrar);
+        } else {
+            let declaration = lib.get::<*const PluginDeclaration>(PLUGIN_DECLARATION.as_bytes())?;
+            let declaration = &**declaration;
+            (declaration.register)(&mut registrar);
+        }
+        std::mem::forget(lib);
+    }
+    registrar
+        .plugins
+        .remove(id)
+        .ok_or_else(|| BinaryPluginError::NotDeclared {
+            path: library.to_path_buf(),
+            id: id.to_string(),
+        })
+}
+
+/// Checks that the api fingerprint of a library matches the running assemble
+fn check_api(library: &Path, found: &str) -> Result<(), BinaryPluginError> {
+    let expected = PluginApi::current();
+    match PluginApi::parse(found) {
+        Some(api) if api == expected => Ok(()),
+        Some(api) => Err(BinaryPluginError::IncompatibleVersion {
+            path: library.to_path_buf(),
+            found: api.to_string(),
+            expected,
+        }),
+        None => Err(BinaryPluginError::IncompatibleVersion {
+            path: library.to_path_buf(),
+            found: format!("{:?}", found),
+            expected,
+        }),
+    }
+}
+
+/// Resolves, loads and applies a binary plugin to a project, using the plugin repositories of the
+/// build
+pub fn apply_plugin_request(project: &mut Project, request: &PluginRequest) -> ProjectResult {
+    let start_parameter = project.start_parameter();
+    let repositories = start_parameter.plugin_repositories();
+    let offline = start_parameter.is_offline();
+    let native = resolve_plugin(request, PluginFormat::Native, repositories, offline);
+
+    #[cfg(feature = "wasm")]
+    if let Err(BinaryPluginError::NotFound { .. } | BinaryPluginError::Offline(_)) = &native {
+        if let Ok(module) = resolve_plugin(request, PluginFormat::Wasm, repositories, offline) {
+            let plugin = super::wasm::WasmPlugin::load(&module)
+                .map_err(|e| PayloadError::new(PluginError::from(e)))?;
+            info!("applying wasm plugin {} to {}", request, project);
+            return plugin.apply_to(project);
+        }
+    }
+
+    let library = native.map_err(|e| PayloadError::new(PluginError::from(e)))?;
+    let apply =
+        load_plugin(&library, request.id()).map_err(|e| PayloadError::new(PluginError::from(e)))?;
+    info!("applying plugin {} to {}", request, project);
+    apply(project)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn plugin_urls() {
+        let request = PluginRequest::new("org.example.foo", "1.2");
+        let repository = Url::parse("https://plugins.example.com/releases").unwrap();
+        let url = library_url(&repository, &request, PluginFormat::Native);
+        assert_eq!(
+            url.as_str(),
+            format!(
+                "https://plugins.example.com/releases/org/example/foo/1.2/org.example.foo-1.2.{}",
+                std::env::consts::DLL_EXTENSION
+            )
+        );
+    }
+
+    #[test]
+    fn plugin_symbols() {
+        assert_eq!(
+            plugin_symbol("org.example.foo-bar"),
+            "__assemble_plugin_org_example_foo_bar"
+        );
+    }
+
+    #[test]
+    fn api_fingerprints() {
+        let current = PluginApi::current();
+        let fingerprint = current.fingerprint();
+        assert_eq!(
+            PluginApi::parse(fingerprint.to_str().unwrap()),
+            Some(current.clone())
+        );
+        assert!(check_api(Path::new("lib"), fingerprint.to_str().unwrap()).is_ok());
+
+        let older = format!("assemble-core 0.0.1;{};features=", RUSTC_VERSION);
+        let error = check_api(Path::new("lib"), &older).unwrap_err();
+        assert!(matches!(
+            error,
+            BinaryPluginError::IncompatibleVersion { .. }
+        ));
+        assert!(error
+            .to_string()
+            .contains("built against incompatible assemble version assemble-core 0.0.1"));
+        assert!(check_api(Path::new("lib"), "garbage").is_err());
+    }
+
+    #[test]
+    fn cached_plugins_are_verified() {
+        let dir = TempDir::new().unwrap();
+        let request = PluginRequest::new("org.example.foo", "1.2");
+        assert!(matches!(
+            resolve_plugin_in(&request, PluginFormat::Native, &[], dir.path(), true),
+            Err(BinaryPluginError::Offline(_))
+        ));
+
+        let library = dir.path().join(request.relative_path(PluginFormat::Native));
+        std::fs::create_dir_all(library.parent().unwrap()).unwrap();
+        std::fs::write(&library, b"not really a library").unwrap();
+        let checksum = hash_file_sha256(&library).unwrap();
+        std::fs::write(
+            checksum_path(&library),
+            format!("{}  {}", checksum, request.file_name(PluginFormat::Native)),
+        )
+        .unwrap();
+        assert_eq!(
+            resolve_plugin_in(&request, PluginFormat::Native, &[], dir.path(), true).unwrap(),
+            library
+        );
+
+        std::fs::write(&library, b"tampered").unwrap();
+        assert!(matches!(
+            resolve_plugin_in(&request, PluginFormat::Native, &[], dir.path(), true),
+            Err(BinaryPluginError::ChecksumMismatch { .. })
+        ));
+    }
+}
@@ -4,9 +4,11 @@ use std::any;
 use std::any::Any;
 use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
-use std::ops::{Index, IndexMut};
+use std::ops::{Deref, Index, IndexMut};
+use std::sync::Arc;
 
 use crate::prelude::{ProjectError, ProjectResult};
+use crate::project::inheritance::SharedScope;
 use thiserror::Error;
 
 /// A a helper trait that extends the needed traits to add a value as an extension
@@ -32,14 +34,58 @@ pub trait ExtensionAware {
     fn extension_mut<E: Extension>(&mut self) -> ProjectResult<&mut E> {
         self.extensions_mut().get_by_type_mut()
     }
+
+    /// Finds a shared extension by name, searching this container and then the containers of each
+    /// parent in turn.
+    fn shared_extension<E: Extension, S: AsRef<str>>(&self, name: S) -> ProjectResult<Shared<E>> {
+        self.extensions().find_shared(name)
+    }
 }
 
 type AnyExtension = Box<dyn Any + Send + Sync>;
+type AnySharedExtension = Arc<dyn Any + Send + Sync>;
+
+/// A read-only extension that is visible from subprojects. Clones refer to the same value.
+pub struct Shared<E: Extension> {
+    value: Arc<E>,
+}
+
+impl<E: Extension> Shared<E> {
+    /// Wraps a value as a shared extension
+    pub fn new(value: E) -> Self {
+        Self {
+            value: Arc::new(value),
+        }
+    }
+}
+
+impl<E: Extension> Clone for Shared<E> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl<E: Extension> Deref for Shared<E> {
+    type Target = E;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<E: Extension + Debug> Debug for Shared<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Shared").field(&self.value).finish()
+    }
+}
 
 /// Contains extensions
 #[derive(Default)]
 pub struct ExtensionContainer {
     ob_map: HashMap<String, AnyExtension>,
+    shared: SharedScope<AnySharedExtension>,
 }
 
 impl ExtensionContainer {
@@ -61,6 +107,44 @@ impl ExtensionContainer {
         Ok(())
     }
 
+    /// Adds a new extension to this container that is also visible, read-only, from the extension
+    /// containers of subprojects. The extension is registered to this container as a
+    /// [`Shared<E>`](Shared).
+    ///
+    /// # Error
+    /// Will return an error if `name` is already registered to this container
+    pub fn add_shared<E: Extension, S: AsRef<str>>(
+        &mut self,
+        name: S,
+        value: E,
+    ) -> Result<Shared<E>, ExtensionError> {
+        let shared = Shared::new(value);
+        self.add(name.as_ref(), shared.clone())?;
+        self.shared
+            .insert(name, Arc::new(shared.clone()) as AnySharedExtension);
+        Ok(shared)
+    }
+
+    /// Finds a shared extension by name, searching this container and then the containers of each
+    /// parent in turn. Extensions that aren't shared are never found.
+    pub fn find_shared<E: Extension, S: AsRef<str>>(&self, name: S) -> ProjectResult<Shared<E>> {
+        let name = name.as_ref();
+        self.shared
+            .find(name)
+            .and_then(|ext| ext.downcast_ref::<Shared<E>>().cloned())
+            .ok_or_else(|| ProjectError::ExtensionNotRegistered(name.to_string()).into())
+    }
+
+    /// The scope of shared extensions of this container
+    pub(crate) fn shared_scope(&self) -> &SharedScope<AnySharedExtension> {
+        &self.shared
+    }
+
+    /// Makes the shared extensions of a parent container visible from this container
+    pub(crate) fn inherit_from(&mut self, parent: &ExtensionContainer) {
+        self.shared.set_parent(parent.shared_scope().clone());
+    }
+
     /// Gets a reference to an extension, if it exists
     pub fn get<S: AsRef<str>>(&self, name: S) -> ProjectResult<&AnyExtension> {
         self.ob_map
@@ -178,4 +262,22 @@ mod tests {
             Err(ExtensionError::AlreadyRegistered(_))
         ));
     }
+
+    #[test]
+    fn shared_extensions_are_inherited() {
+        let mut parent = ExtensionContainer::default();
+        parent.add("private", 1_i32).unwrap();
+        parent
+            .add_shared("shared", String::from("Hello, World"))
+            .unwrap();
+
+        let mut child = ExtensionContainer::default();
+        child.inherit_from(&parent);
+
+        let shared = child.find_shared::<String, _>("shared").unwrap();
+        assert_eq!(&*shared, "Hello, World");
+        assert!(child.find_shared::<i32, _>("private").is_err());
+        assert!(child.get("shared").is_err());
+        assert!(parent.get_by_type::<Shared<String>>().is_ok());
+    }
 }
@@ -0,0 +1,367 @@
+//! Plugins compiled to WebAssembly, which are portable and run in a sandbox.
+//!
+//! Unlike [native plugins](crate::plugins::binary), a WebAssembly plugin has no access to the
+//! project, the file system or the network. It can only use the host functions imported from the
+//! `assemble` module:
+//!
+//! | Function | Description |
+//! |----------|-------------|
+//! | `log(level: i32, ptr: i32, len: i32)` | Logs a message. Levels go from `0` (error) to `4` (trace) |
+//! | `property(key_ptr: i32, key_len: i32, out_ptr: i32, out_cap: i32) -> i32` | Copies the value of a project property into the buffer, returning its length, or `-1` if it isn't set |
+//! | `register_task(name_ptr: i32, name_len: i32, desc_ptr: i32, desc_len: i32) -> i32` | Registers a task, returning `0` if successful |
+//!
+//! Strings are utf-8, passed as a pointer and length in the exported `memory` of the module. A
+//! module must export:
+//!
+//! | Function | Description |
+//! |----------|-------------|
+//! | `assemble_plugin_apply() -> i32` | Applies the plugin, returning `0` if successful |
+//! | `assemble_alloc(len: i32) -> i32` | Allocates a buffer the host can pass strings in |
+//! | `assemble_run_task(name_ptr: i32, name_len: i32) -> i32` | Runs the action of a registered task, returning `0` if successful |
+//!
+//! Project properties are read from a snapshot taken when the plugin is applied.
+
+use crate::exception::BuildException;
+use crate::project::error::ProjectResult;
+use crate::task::initialize_task::InitializeTask;
+use crate::task::task_io::TaskIO;
+use crate::task::up_to_date::UpToDate;
+use crate::task::HasTaskId;
+use crate::{BuildResult, Executable, Project, Task};
+use log::Level;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::path::Path;
+use std::sync::Arc;
+use wasmtime::{Caller, Engine, Extern, Instance, Linker, Module, Store, Val};
+
+/// The module host functions are imported from
+pub const HOST_MODULE: &str = "assemble";
+
+/// An error occurred loading or running a WebAssembly plugin
+#[derive(Debug, thiserror::Error)]
+pub enum WasmPluginError {
+    #[error("couldn't load wasm plugin: {0}")]
+    Load(String),
+    #[error("wasm plugin doesn't export {0:?}")]
+    MissingExport(&'static str),
+    #[error("wasm plugin trapped: {0}")]
+    Trap(String),
+    #[error("{function} returned {code}")]
+    Failed { function: &'static str, code: i32 },
+    #[error("wasm plugin made an invalid host call: {0}")]
+    InvalidHostCall(String),
+}
+
+#[derive(Debug, Default)]
+struct RegisteredTask {
+    name: String,
+    description: String,
+}
+
+/// The state the host functions can see
+#[derive(Debug, Default)]
+struct Host {
+    properties: HashMap<String, Option<String>>,
+    registered: Vec<RegisteredTask>,
+    error: Option<String>,
+}
+
+struct Instantiated {
+    store: Store<Host>,
+    instance: Instance,
+}
+
+impl Instantiated {
+    /// Calls an exported function taking and returning `i32`s
+    fn call(&mut self, name: &'static str, params: &[i32]) -> Result<i32, WasmPluginError> {
+        let func = self
+            .instance
+            .get_func(&mut self.store, name)
+            .ok_or(WasmPluginError::MissingExport(name))?;
+        let params: Vec<Val> = params.iter().map(|p| Val::I32(*p)).collect();
+        let mut results = [Val::I32(0)];
+        func.call(&mut self.store, &params, &mut results)
+            .map_err(|e| WasmPluginError::Trap(e.to_string()))?;
+        if let Some(error) = self.store.data_mut().error.take() {
+            return Err(WasmPluginError::InvalidHostCall(error));
+        }
+        results[0]
+            .i32()
+            .ok_or_else(|| WasmPluginError::Trap(format!("{} didn't return an i32", name)))
+    }
+
+    /// Copies a string into a buffer allocated by the module
+    fn pass_string(&mut self, string: &str) -> Result<(i32, i32), WasmPluginError> {
+        let len = string.len() as i32;
+        let ptr = self.call("assemble_alloc", &[len])?;
+        let memory = self
+            .instance
+            .get_memory(&mut self.store, "memory")
+            .ok_or(WasmPluginError::MissingExport("memory"))?;
+        memory
+            .write(&mut self.store, ptr as usize, string.as_bytes())
+            .map_err(|e| WasmPluginError::InvalidHostCall(e.to_string()))?;
+        Ok((ptr, len))
+    }
+
+    /// Calls a function that returns `0` when successful
+    fn call_checked(&mut self, name: &'static str, params: &[i32]) -> Result<(), WasmPluginError> {
+        match self.call(name, params)? {
+            0 => Ok(()),
+            code => Err(WasmPluginError::Failed {
+                function: name,
+                code,
+            }),
+        }
+    }
+}
+
+/// A loaded WebAssembly plugin
+#[derive(Clone)]
+pub struct WasmPlugin {
+    instantiated: Arc<Mutex<Instantiated>>,
+}
+
+impl WasmPlugin {
+    /// Loads and instantiates a WebAssembly module
+    pub fn load<P: AsRef<Path>>(module: P) -> Result<Self, WasmPluginError> {
+        let engine = Engine::default();
+        let module = Module::from_file(&engine, module.as_ref())
+            .map_err(|e| WasmPluginError::Load(e.to_string()))?;
+        Self::instantiate(&engine, &module)
+    }
+
+    /// Loads and instantiates a WebAssembly module from its binary or text format
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, WasmPluginError> {
+        let engine = Engine::default();
+        let module =
+            Module::new(&engine, bytes).map_err(|e| WasmPluginError::Load(e.to_string()))?;
+        Self::instantiate(&engine, &module)
+    }
+
+    fn instantiate(engine: &Engine, module: &Module) -> Result<Self, WasmPluginError> {
+        let mut linker = Linker::new(engine);
+        link_host(&mut linker).map_err(|e| WasmPluginError::Load(e.to_string()))?;
+        let mut store = Store::new(engine, Host::default());
+        let instance = linker
+            .instantiate(&mut store, module)
+            .map_err(|e| WasmPluginError::Load(e.to_string()))?;
+        Ok(Self {
+            instantiated: Arc::new(Mutex::new(Instantiated { store, instance })),
+        })
+    }
+
+    /// Applies the plugin to a project, registering the tasks it declares
+    pub fn apply_to(&self, project: &mut Project) -> ProjectResult {
+        let registered = {
+            let mut instantiated = self.instantiated.lock();
+            instantiated.store.data_mut().properties = project.properties().clone();
+            instantiated
+                .call_checked("assemble_plugin_apply", &[])
+                .map_err(|e| crate::error::PayloadError::new(super::PluginError::from(e)))?;
+            std::mem::take(&mut instantiated.store.data_mut().registered)
+        };
+
+        for task in registered {
+            let plugin = self.clone();
+            project
+                .task_container_mut()
+                .register_task_with::<WasmTask, _>(&task.name, move |t, _| {
+                    t.plugin = Some(plugin);
+                    t.set_description(&task.description);
+                    Ok(())
+                })?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for WasmPlugin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmPlugin").finish_non_exhaustive()
+    }
+}
+
+/// Reads a string from the memory of the module calling a host function
+fn read_string(caller: &mut Caller<'_, Host>, ptr: i32, len: i32) -> Result<String, String> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| "module doesn't export memory".to_string())?;
+    let mut buffer = vec![0_u8; len.max(0) as usize];
+    memory
+        .read(&*caller, ptr as usize, &mut buffer)
+        .map_err(|e| e.to_string())?;
+    String::from_utf8(buffer).map_err(|e| e.to_string())
+}
+
+/// Records an error from a host function, which fails the call the module is in
+fn record_error(caller: &mut Caller<'_, Host>, error: String) {
+    caller.data_mut().error.get_or_insert(error);
+}
+
+fn link_host(linker: &mut Linker<Host>) -> wasmtime::Result<()> {
+    linker.func_wrap(
+        HOST_MODULE,
+        "log",
+        |mut caller: Caller<'_, Host>, level: i32, ptr: i32, len: i32| match read_string(
+            &mut caller,
+            ptr,
+            len,
+        ) {
+            Ok(message) => {
+                let level = match level {
+                    0 => Level::Error,
+                    1 => Level::Warn,
+                    2 => Level::Info,
+                    3 => Level::Debug,
+                    _ => Level::Trace,
+                };
+                log!(level, "{}", message);
+            }
+            Err(e) => record_error(&mut caller, e),
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "property",
+        |mut caller: Caller<'_, Host>, key_ptr: i32, key_len: i32, out_ptr: i32, out_cap: i32| {
+            let key = match read_string(&mut caller, key_ptr, key_len) {
+                Ok(key) => key,
+                Err(e) => {
+                    record_error(&mut caller, e);
+                    return -1;
+                }
+            };
+            let value = match caller.data().properties.get(&key) {
+                Some(Some(value)) => value.clone(),
+                _ => return -1,
+            };
+            let copied = value.len().min(out_cap.max(0) as usize);
+            let memory = caller.get_export("memory").and_then(Extern::into_memory);
+            let written = memory.map(|memory| {
+                memory.write(&mut caller, out_ptr as usize, &value.as_bytes()[..copied])
+            });
+            match written {
+                Some(Ok(())) => value.len() as i32,
+                Some(Err(e)) => {
+                    record_error(&mut caller, e.to_string());
+                    -1
+                }
+                None => {
+                    record_error(&mut caller, "module doesn't export memory".to_string());
+                    -1
+                }
+            }
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "register_task",
+        |mut caller: Caller<'_, Host>,
+         name_ptr: i32,
+         name_len: i32,
+         desc_ptr: i32,
+         desc_len: i32| {
+            let task = read_string(&mut caller, name_ptr, name_len).and_then(|name| {
+                let description = read_string(&mut caller, desc_ptr, desc_len)?;
+                Ok(RegisteredTask { name, description })
+            });
+            match task {
+                Ok(task) => {
+                    caller.data_mut().registered.push(task);
+                    0
+                }
+                Err(e) => {
+                    record_error(&mut caller, e);
+                    -1
+                }
+            }
+        },
+    )?;
+    Ok(())
+}
+
+/// A task registered by a WebAssembly plugin, whose action runs in the plugin
+#[derive(Debug, Default)]
+pub struct WasmTask {
+    plugin: Option<WasmPlugin>,
+}
+
+impl UpToDate for WasmTask {}
+
+impl InitializeTask for WasmTask {}
+
+impl TaskIO for WasmTask {}
+
+impl Task for WasmTask {
+    fn task_action(task: &mut Executable<Self>, _project: &Project) -> BuildResult {
+        let plugin = task
+            .plugin
+            .clone()
+            .ok_or_else(|| BuildException::custom("task isn't from a wasm plugin"))?;
+        let name = task.task_id().this().to_string();
+        let mut instantiated = plugin.instantiated.lock();
+        let (ptr, len) = instantiated
+            .pass_string(&name)
+            .map_err(|e| BuildException::custom(&e.to_string()))?;
+        instantiated
+            .call_checked("assemble_run_task", &[ptr, len])
+            .map_err(|e| BuildException::custom(&e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identifier::TaskId;
+    use crate::task::ExecutableTask;
+
+    const PLUGIN: &str = r#"
+    (module
+        (import "assemble" "log" (func $log (param i32 i32 i32)))
+        (import "assemble" "register_task" (func $register (param i32 i32 i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (data (i32.const 0) "greet")
+        (data (i32.const 16) "Says hello")
+        (data (i32.const 32) "hello from wasm")
+        (func (export "assemble_plugin_apply") (result i32)
+            (call $register (i32.const 0) (i32.const 5) (i32.const 16) (i32.const 10)))
+        (func (export "assemble_alloc") (param i32) (result i32)
+            (i32.const 1024))
+        (func (export "assemble_run_task") (param i32 i32) (result i32)
+            (call $log (i32.const 2) (i32.const 32) (i32.const 15))
+            (i32.const 0))
+    )
+    "#;
+
+    #[test]
+    fn wasm_plugins_register_tasks() {
+        let project = Project::temp(None);
+        let plugin = WasmPlugin::from_bytes(PLUGIN.as_bytes()).unwrap();
+        project.with_mut(|p| plugin.apply_to(p)).unwrap();
+
+        let mut greet = project
+            .get_typed_task::<WasmTask>(&TaskId::new(":root:greet").unwrap())
+            .expect("task registered");
+        project.with(|p| greet.execute(p)).unwrap();
+    }
+
+    #[test]
+    fn host_calls_are_checked() {
+        let module = r#"
+        (module
+            (import "assemble" "register_task" (func $register (param i32 i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (func (export "assemble_plugin_apply") (result i32)
+                (call $register (i32.const 70000) (i32.const 5) (i32.const 0) (i32.const 0)))
+        )
+        "#;
+        let project = Project::temp(None);
+        let plugin = WasmPlugin::from_bytes(module.as_bytes()).unwrap();
+        assert!(project.with_mut(|p| plugin.apply_to(p)).is_err());
+    }
+}
@@ -2,6 +2,7 @@ use crate::defaults::plugins::BasePlugin;
 use std::cell::RefCell;
 
 use crate::dependencies::dependency_container::ConfigurationHandler;
+use crate::dependencies::locking::{DependencyLocking, LOCK_FILE_NAME};
 
 use crate::dependencies::RegistryContainer;
 
@@ -38,14 +39,22 @@ pub mod buildable;
 #[cfg(test)]
 pub mod dev;
 pub mod error;
+pub mod evaluation;
 pub mod finder;
+pub mod inheritance;
+pub mod layout;
+pub mod phase;
 pub mod requests;
 pub mod shared;
 pub mod variant;
 
 use crate::error::PayloadError;
 use crate::prelude::{Settings, SettingsAware};
+use crate::project::evaluation::{EvaluationHooks, EvaluationState};
 use crate::project::finder::TaskPath;
+use crate::project::inheritance::SharedScope;
+use crate::project::layout::ProjectLayout;
+use crate::project::phase::{PhaseState, ProjectPhase};
 pub use error::*;
 use shared::{SharedProject, TrueSharableProject, WeakSharedProject};
 
@@ -83,12 +92,15 @@ pub struct Project {
     project_id: ProjectId,
     task_id_factory: TaskIdFactory,
     task_container: TaskContainer,
+    phase: PhaseState,
+    evaluation: EvaluationHooks,
     workspace: Workspace,
     build_dir: Prop<PathBuf>,
     applied_plugins: Vec<String>,
     variants: VariantHandler,
     self_reference: OnceCell<WeakSharedProject>,
     properties: HashMap<String, Option<String>>,
+    shared_properties: SharedScope<Option<String>>,
     default_tasks: Vec<TaskId>,
     registries: Arc<Mutex<RegistryContainer>>,
     configurations: ConfigurationHandler,
@@ -164,6 +176,20 @@ impl Project {
         root: Option<&SharedProject>,
         settings: Option<Weak<RwLock<Settings>>>,
     ) -> error::Result<SharedProject>
+    where
+        ProjectError: From<<Id as TryInto<ProjectId>>::Error>,
+    {
+        Self::in_dir_with_phase(path, id, root, settings, PhaseState::new())
+    }
+
+    /// Creates an assemble project that shares the phase of the build it's part of
+    fn in_dir_with_phase<Id: TryInto<ProjectId>, P: AsRef<Path>>(
+        path: P,
+        id: Id,
+        root: Option<&SharedProject>,
+        settings: Option<Weak<RwLock<Settings>>>,
+        phase: PhaseState,
+    ) -> error::Result<SharedProject>
     where
         ProjectError: From<<Id as TryInto<ProjectId>>::Error>,
     {
@@ -175,20 +201,27 @@ impl Project {
             .set(path.as_ref().join("build"))
             .map_err(PayloadError::new)?;
         let registries = Arc::new(Mutex::new(Default::default()));
-        let dependencies = ConfigurationHandler::new(id.clone(), &registries);
+        let dependencies = ConfigurationHandler::with_locking(
+            id.clone(),
+            &registries,
+            DependencyLocking::new(path.as_ref().join(LOCK_FILE_NAME)),
+        );
 
         let project = SharedProject::new_cyclic(|cycle| {
             let mut project = Self {
                 settings: settings.clone(),
                 project_id: id,
                 task_id_factory: factory.clone(),
-                task_container: TaskContainer::new(factory),
+                task_container: TaskContainer::new(factory, phase.clone()),
+                phase,
+                evaluation: EvaluationHooks::default(),
                 workspace: Workspace::new(path),
                 build_dir,
                 applied_plugins: Default::default(),
                 variants: VariantHandler::new(),
                 self_reference: OnceCell::new(),
                 properties: Default::default(),
+                shared_properties: SharedScope::new(),
                 default_tasks: vec![],
                 registries,
                 configurations: dependencies,
@@ -227,6 +260,11 @@ impl Project {
         self.build_dir.clone()
     }
 
+    /// Gets the file system layout of the project
+    pub fn layout(&self) -> ProjectLayout {
+        ProjectLayout::new(self.project_dir(), self.build_dir.clone())
+    }
+
     /// Always set as relative to the project dir
     pub fn set_build_dir(&mut self, dir: &str) {
         let dir = self.workspace.dir(dir).unwrap();
@@ -271,6 +309,11 @@ impl Project {
         self.workspace.absolute_path()
     }
 
+    /// Checks whether a file or directory exists, relative to the project directory
+    pub fn file_exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.project_dir().join(path).exists()
+    }
+
     /// The project directory for the root directory
     pub fn root_dir(&self) -> PathBuf {
         self.root_project().with(|p| p.project_dir())
@@ -296,6 +339,44 @@ impl Project {
         SharedProject::try_from(self.self_reference.get().unwrap().clone()).unwrap()
     }
 
+    /// The current phase of the build this project is part of
+    pub fn phase(&self) -> ProjectPhase {
+        self.phase.phase()
+    }
+
+    /// The phase state of the build this project is part of, shared by every project in the build
+    pub fn phase_state(&self) -> &PhaseState {
+        &self.phase
+    }
+
+    /// How far along the evaluation of this project is
+    pub fn evaluation_state(&self) -> EvaluationState {
+        self.evaluation.state()
+    }
+
+    /// Runs a function just before the build logic configures this project. Fails if this project
+    /// was already evaluated.
+    pub fn before_evaluate<F>(&mut self, func: F) -> ProjectResult
+    where
+        F: FnOnce(&mut Project) -> ProjectResult + Send + Sync + 'static,
+    {
+        self.evaluation.add_before(&self.project_id, Box::new(func))
+    }
+
+    /// Runs a function once the build logic has configured every project of the build. Runs
+    /// immediately if this project was already evaluated.
+    ///
+    /// Plugins should use this to finalize conventions that depend on user configuration.
+    pub fn after_evaluate<F>(&mut self, func: F) -> ProjectResult
+    where
+        F: FnOnce(&mut Project) -> ProjectResult + Send + Sync + 'static,
+    {
+        match self.evaluation.add_after(Box::new(func)) {
+            Some(hook) => hook(self),
+            None => Ok(()),
+        }
+    }
+
     /// Gets the factory for generating task ids
     pub fn task_id_factory(&self) -> &TaskIdFactory {
         &self.task_id_factory
@@ -317,6 +398,23 @@ impl Project {
         self.properties.contains_key(key)
     }
 
+    /// Sets a property that is also visible, read-only, from subprojects through
+    /// [`find_property`](Project::find_property).
+    pub fn set_shared_property(&mut self, key: String, value: impl Into<Option<String>>) {
+        let value = value.into();
+        self.shared_properties.insert(&key, value.clone());
+        self.properties.insert(key, value);
+    }
+
+    /// Finds a property, searching the properties of this project first, then the shared
+    /// properties of the parent project, and so on until the root project is reached.
+    pub fn find_property(&self, key: &str) -> Option<Option<String>> {
+        self.properties
+            .get(key)
+            .cloned()
+            .or_else(|| self.shared_properties.find_inherited(key))
+    }
+
     /// Gets the subprojects for this project.
     pub fn subprojects(&self) -> Vec<&SharedProject> {
         self.subprojects.values().collect()
@@ -388,15 +486,27 @@ impl Project {
         let self_shared = self.as_shared();
         let id = ProjectId::from(self.project_id.join(name).map_err(PayloadError::new)?);
         let shared = self.subprojects.entry(id.clone()).or_insert_with(|| {
-            Project::in_dir_with_id_and_root(
+            Project::in_dir_with_phase(
                 path,
                 id.clone(),
                 Some(&root_shared),
                 self.settings.clone(),
+                self.phase.clone(),
             )
             .unwrap()
         });
-        shared.with_mut(|p| p.parent_project.set(self_shared.weak()).unwrap());
+        let shared_properties = self.shared_properties.clone();
+        shared.with_mut(|p| -> ProjectResult {
+            if p.parent_project.set(self_shared.weak()).is_ok() {
+                p.shared_properties.set_parent(shared_properties);
+                p.extensions.inherit_from(&self.extensions);
+                if let Some(settings) = self.settings.as_ref().and_then(Weak::upgrade) {
+                    let rules = settings.read_recursive().project_rules().clone();
+                    rules.apply_to(p)?;
+                }
+            }
+            Ok(())
+        })?;
         shared.with_mut(configure)
     }
 
@@ -423,6 +533,29 @@ impl Project {
         &mut self.variants
     }
 
+    /// Gets the root project of a build included in the build of this project by its name, if it
+    /// exists
+    pub fn included_build(&self, name: &str) -> Option<SharedProject> {
+        let settings = self.settings.as_ref().and_then(Weak::upgrade)?;
+        let settings = settings.read_recursive();
+        settings
+            .included_build(name)
+            .map(|build| build.root_project().clone())
+    }
+
+    /// Gets the root projects of every build included in the build of this project
+    pub fn included_builds(&self) -> Vec<SharedProject> {
+        match self.settings.as_ref().and_then(Weak::upgrade) {
+            Some(settings) => settings
+                .read_recursive()
+                .configured_builds()
+                .iter()
+                .map(|build| build.root_project().clone())
+                .collect(),
+            None => vec![],
+        }
+    }
+
     /// Gets the reference to the settings object
     fn settings(&self) -> Arc<RwLock<Settings>> {
         self.settings
@@ -507,7 +640,8 @@ impl GetProjectId for Project {
 mod test {
     use crate::defaults::tasks::Empty;
     use crate::logging::init_root_log;
-    use crate::project::Project;
+    use crate::project::phase::ProjectPhase;
+    use crate::project::{Project, ProjectError};
 
     use crate::project::shared::SharedProject;
     use crate::workspace::WorkspaceDirectory;
@@ -524,6 +658,34 @@ mod test {
         provider.configure_with(|_, _| Ok(())).unwrap();
     }
 
+    #[test]
+    fn no_configuration_after_graph_ready() {
+        let project = Project::temp("late");
+        let mut handle = project.register_task::<Empty>("early").unwrap();
+        project
+            .with_mut(|p| {
+                p.subproject("child", |child| {
+                    assert_eq!(child.phase(), ProjectPhase::Configuration);
+                    Ok(())
+                })
+            })
+            .unwrap();
+
+        project.with(|p| p.phase_state().advance(ProjectPhase::GraphReady));
+        let child = project
+            .with(|p| p.get_subproject("child").cloned())
+            .unwrap();
+        assert_eq!(child.with(|c| c.phase()), ProjectPhase::GraphReady);
+
+        let error = project.register_task::<Empty>("late").unwrap_err();
+        assert!(matches!(error.kind(), ProjectError::LateMutation(_)));
+        let error = handle.configure_with(|_, _| Ok(())).unwrap_err();
+        match error.kind() {
+            ProjectError::LateMutation(late) => assert_eq!(late.caller().file(), file!()),
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
     #[test]
     fn project_name_based_on_directory() {
         let path = PathBuf::from("parent_dir/ProjectName");
@@ -7,8 +7,11 @@
 //! - Any type that implements [`TaskDependency`](TaskDependency)
 //! - Any type that implements [`Buildable`](Buildable)
 //! - [`FileCollection`](crate::file_collection::FileCollection)
+//! - A [`Provider`](crate::lazy_evaluation::Provider) of any of the above, wrapped in a
+//!   [`ProvidedBuildable`](ProvidedBuildable)
 
 use crate::identifier::TaskId;
+use crate::lazy_evaluation::Provider;
 
 use crate::project::ProjectResult;
 
@@ -16,6 +19,7 @@ use crate::project::Project;
 
 use std::collections::HashSet;
 use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
 
@@ -265,3 +269,91 @@ impl From<Box<dyn Buildable>> for BuildableObject {
 }
 
 assert_impl_all!(BuildableObject: Buildable, IntoBuildable, GetBuildable);
+
+/// A buildable produced by a provider, such as a provider of task ids or task handles.
+///
+/// The provider isn't queried until the dependencies of this buildable are requested, which happens
+/// when the task graph is constructed. This allows dependencies to be computed from configuration
+/// that's set after the dependency is declared. A provider without a value has no dependencies.
+///
+/// The dependencies of the provider itself, if any, are also dependencies of this buildable.
+pub struct ProvidedBuildable<B, P>
+where
+    B: IntoBuildable + Clone + Send + Sync,
+    P: Provider<B>,
+{
+    provider: P,
+    _buildable: PhantomData<fn() -> B>,
+}
+
+impl<B, P> ProvidedBuildable<B, P>
+where
+    B: IntoBuildable + Clone + Send + Sync,
+    P: Provider<B>,
+{
+    /// Creates a new buildable from a provider
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider,
+            _buildable: PhantomData,
+        }
+    }
+}
+
+impl<B, P> Debug for ProvidedBuildable<B, P>
+where
+    B: IntoBuildable + Clone + Send + Sync,
+    P: Provider<B>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProvidedBuildable")
+            .field("provider", &self.provider)
+            .finish()
+    }
+}
+
+impl<B, P> Buildable for ProvidedBuildable<B, P>
+where
+    B: IntoBuildable + Clone + Send + Sync,
+    P: Provider<B>,
+{
+    fn get_dependencies(&self, project: &Project) -> ProjectResult<HashSet<TaskId>> {
+        let mut output = self.provider.get_dependencies(project)?;
+        match self.provider.try_get() {
+            Some(buildable) => {
+                output.extend(buildable.into_buildable().get_dependencies(project)?);
+            }
+            None => {
+                trace!("provided buildable has no value, so it has no dependencies");
+            }
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lazy_evaluation::Prop;
+
+    #[test]
+    fn provided_buildables_resolve_late() {
+        let project = Project::temp("provided");
+        let mut targets = Prop::<Vec<TaskId>>::with_name("targets");
+        let buildable = ProvidedBuildable::new(targets.clone());
+        assert!(project
+            .with(|p| buildable.get_dependencies(p))
+            .unwrap()
+            .is_empty());
+
+        let ids = vec![
+            TaskId::new(":provided:compile-x86").unwrap(),
+            TaskId::new(":provided:compile-arm").unwrap(),
+        ];
+        targets.set(ids.clone()).unwrap();
+        assert_eq!(
+            project.with(|p| buildable.get_dependencies(p)).unwrap(),
+            ids.into_iter().collect()
+        );
+    }
+}
@@ -10,8 +10,10 @@ use crate::lazy_evaluation;
 use crate::lazy_evaluation::ProviderError;
 use crate::plugins::extensions::ExtensionError;
 use crate::plugins::PluginError;
-use crate::project::finder::{ProjectPathBuf, TaskPath, TaskPathBuf};
+use crate::project::finder::{ProjectPathBuf, TaskPath, TaskPathBuf, UnknownTask};
+use crate::project::phase::LateMutation;
 use crate::resources::InvalidResourceLocation;
+use crate::startup::initialization::InvalidProjectGraph;
 use crate::task::flags::{OptionsDecoderError, OptionsSlurperError};
 use crate::workspace::WorkspaceError;
 use std::any::Any;
@@ -38,6 +40,8 @@ pub enum ProjectError {
     #[error("no project could be found for {0:?}")]
     ProjectNotFound(ProjectPathBuf),
     #[error(transparent)]
+    UnknownTask(#[from] UnknownTask),
+    #[error(transparent)]
     InvalidIdentifier(#[from] InvalidId),
     #[error(transparent)]
     PluginError(#[from] PluginError),
@@ -77,6 +81,10 @@ pub enum ProjectError {
     ExtensionError(#[from] ExtensionError),
     #[error(transparent)]
     FromUtf8Error(#[from] FromUtf8Error),
+    #[error(transparent)]
+    InvalidProjectGraph(#[from] InvalidProjectGraph),
+    #[error(transparent)]
+    LateMutation(#[from] LateMutation),
 }
 
 impl<G> From<PoisonError<G>> for ProjectError {
@@ -0,0 +1,192 @@
+//! Hooks ran before and after projects are evaluated.
+//!
+//! A project is evaluated when the build logic of the build configures it. Hooks added with
+//! [`before_evaluate`](crate::Project::before_evaluate) run just before that, and hooks added with
+//! [`after_evaluate`](crate::Project::after_evaluate) run once every project has been evaluated.
+//! Plugins use after-evaluate hooks to finalize their conventions once user configuration has run.
+//!
+//! Parent projects run their hooks before their subprojects, and each project runs its hooks in
+//! the order they were added.
+
+use crate::error::PayloadError;
+use crate::identifier::ProjectId;
+use crate::project::error::{ProjectError, ProjectResult};
+use crate::project::shared::SharedProject;
+use crate::Project;
+use std::fmt::{Debug, Formatter};
+
+/// A hook ran on a project before or after it's evaluated
+pub type EvaluationHook = Box<dyn FnOnce(&mut Project) -> ProjectResult + Send + Sync>;
+
+/// How far along the evaluation of a project is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvaluationState {
+    /// The build logic hasn't configured the project yet
+    #[default]
+    NotEvaluated,
+    /// The build logic is configuring the project
+    Evaluating,
+    /// The build logic has configured the project
+    Evaluated,
+}
+
+/// The evaluation hooks of a project
+#[derive(Default)]
+pub struct EvaluationHooks {
+    state: EvaluationState,
+    before: Vec<EvaluationHook>,
+    after: Vec<EvaluationHook>,
+}
+
+impl Debug for EvaluationHooks {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EvaluationHooks")
+            .field("state", &self.state)
+            .field("before", &self.before.len())
+            .field("after", &self.after.len())
+            .finish()
+    }
+}
+
+impl EvaluationHooks {
+    /// How far along the evaluation of the project is
+    pub fn state(&self) -> EvaluationState {
+        self.state
+    }
+
+    /// Adds a hook to run before the project is evaluated. Fails if evaluation already started.
+    pub(crate) fn add_before(
+        &mut self,
+        project: &ProjectId,
+        hook: EvaluationHook,
+    ) -> ProjectResult {
+        if self.state != EvaluationState::NotEvaluated {
+            return Err(ProjectError::custom(format!(
+                "can't add a before-evaluate hook to {}, because it was already evaluated",
+                project
+            ))
+            .into());
+        }
+        self.before.push(hook);
+        Ok(())
+    }
+
+    /// Adds a hook to run after the project is evaluated. Returns the hook if the project was
+    /// already evaluated, in which case it should be ran immediately.
+    pub(crate) fn add_after(&mut self, hook: EvaluationHook) -> Option<EvaluationHook> {
+        if self.state == EvaluationState::Evaluated {
+            Some(hook)
+        } else {
+            self.after.push(hook);
+            None
+        }
+    }
+}
+
+/// Evaluates the projects of a build. Before-evaluate hooks of every project are ran, then the
+/// projects are evaluated by the given function, then the after-evaluate hooks are ran.
+pub fn evaluate_projects<F, E>(root: &SharedProject, evaluate: F) -> Result<(), PayloadError<E>>
+where
+    F: FnOnce() -> Result<(), PayloadError<E>>,
+    ProjectError: Into<E>,
+{
+    let projects = all_projects(root);
+    for project in &projects {
+        let hooks = project.with_mut(|p| {
+            p.evaluation.state = EvaluationState::Evaluating;
+            std::mem::take(&mut p.evaluation.before)
+        });
+        run_hooks(project, hooks).map_err(PayloadError::into)?;
+    }
+
+    evaluate()?;
+
+    for project in &projects {
+        let hooks = project.with_mut(|p| {
+            p.evaluation.state = EvaluationState::Evaluated;
+            std::mem::take(&mut p.evaluation.after)
+        });
+        run_hooks(project, hooks).map_err(PayloadError::into)?;
+    }
+    Ok(())
+}
+
+fn run_hooks(project: &SharedProject, hooks: Vec<EvaluationHook>) -> ProjectResult {
+    for hook in hooks {
+        project.with_mut(hook)?;
+    }
+    Ok(())
+}
+
+/// Every project of a build, with parents before their subprojects
+fn all_projects(root: &SharedProject) -> Vec<SharedProject> {
+    let mut projects = vec![root.clone()];
+    let mut index = 0;
+    while let Some(project) = projects.get(index).cloned() {
+        let mut subprojects =
+            project.with(|p| p.subprojects().into_iter().cloned().collect::<Vec<_>>());
+        subprojects.sort_by_key(|subproject| subproject.with(|p| p.id().to_string()));
+        projects.extend(subprojects);
+        index += 1;
+    }
+    projects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn hooks_run_around_evaluation() {
+        let project = Project::temp("root");
+        project
+            .with_mut(|p| p.subproject("child", |_| Ok(())))
+            .unwrap();
+        let events = Arc::new(Mutex::new(vec![]));
+        let record = |event: &'static str| {
+            let events = events.clone();
+            move |p: &mut Project| -> ProjectResult {
+                events.lock().unwrap().push(format!("{} {}", event, p.id()));
+                Ok(())
+            }
+        };
+
+        let child = project
+            .with(|p| p.get_subproject("child").cloned())
+            .unwrap();
+        child
+            .with_mut(|p| p.after_evaluate(record("after")))
+            .unwrap();
+        child
+            .with_mut(|p| p.before_evaluate(record("before")))
+            .unwrap();
+        project
+            .with_mut(|p| p.after_evaluate(record("after")))
+            .unwrap();
+
+        let evaluated = events.clone();
+        evaluate_projects(&project, move || -> ProjectResult {
+            evaluated.lock().unwrap().push("evaluate".to_string());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                "before :root:child",
+                "evaluate",
+                "after :root",
+                "after :root:child"
+            ]
+        );
+
+        project
+            .with_mut(|p| p.after_evaluate(record("late")))
+            .unwrap();
+        assert_eq!(events.lock().unwrap().last().unwrap(), "late :root");
+        assert!(project
+            .with_mut(|p| p.before_evaluate(record("before")))
+            .is_err());
+    }
+}
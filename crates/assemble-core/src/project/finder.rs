@@ -8,6 +8,7 @@ use crate::project::{GetProjectId, ProjectError, ProjectResult};
 use crate::task::HasTaskId;
 use itertools::Itertools;
 use std::borrow::Borrow;
+use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::fmt::{Display, Formatter};
 use std::iter::FusedIterator;
@@ -22,6 +23,8 @@ use std::ops::Deref;
 /// - a path starting with a `":"` searches starting from the `root` project.
 /// - A path starting with `:<root project name>` is equivalent to just `":"`.
 /// - each component after that, which are seperated by a `":"`, is a child of the current search
+/// - a component directly after a root project that isn't a subproject of it refers to the root
+///   project of the included build with that name, if there is one.
 ///
 /// For example, in this given structure with a search starting pointed marked with `<--`,
 /// ```text
@@ -66,7 +69,11 @@ impl ProjectFinder {
                     {
                         continue;
                     }
+                    let root = project_ptr.clone().filter(|p| p.is_root());
                     project_ptr = project_ptr.and_then(|s| s.get_subproject(normal).ok());
+                    if project_ptr.is_none() {
+                        project_ptr = root.and_then(|root| root.with(|p| p.included_build(normal)));
+                    }
                 }
             }
         }
@@ -398,7 +405,33 @@ impl TaskFinder {
 
         let mut output = vec![];
 
-        if let Ok(task_id) = proj.task_id_factory().create(task) {
+        let task_id = proj
+            .task_id_factory()
+            .create(task)
+            .ok()
+            .filter(|task_id| proj.get_task(task_id).is_ok())
+            .or_else(|| {
+                // fall back to a case insensitive match, as long as it's unambiguous
+                let matches = registered_tasks(&proj)
+                    .into_iter()
+                    .filter(|registered| registered.this().eq_ignore_ascii_case(task))
+                    .collect::<Vec<_>>();
+                match <[TaskId; 1]>::try_from(matches) {
+                    Ok([task_id]) => {
+                        debug!("{:?} matched {} ignoring case", task, task_id);
+                        Some(task_id)
+                    }
+                    Err(_) => None,
+                }
+            });
+        let task_id = match task_id {
+            Some(task_id) => Some(task_id),
+            // finally, give the task rules of the project a chance to create the task
+            None if !task.is_empty() => proj.apply_task_rules(task)?,
+            None => None,
+        };
+
+        if let Some(task_id) = task_id {
             trace!("checking if {} exists", task_id);
             if let Ok(task) = proj.get_task(&task_id) {
                 if project.is_empty() && !task.only_current() {
@@ -410,11 +443,6 @@ impl TaskFinder {
             }
         }
 
-        for registered_task in proj.task_container().get_tasks() {
-            trace!("registered task: {}", registered_task);
-            // todo:
-        }
-
         if project.is_empty() {
             self.project.with(|p| {
                 for subproject in p.subprojects() {
@@ -432,6 +460,121 @@ impl TaskFinder {
             Ok(Some(output))
         }
     }
+
+    /// Creates an error for a task path that couldn't be found, with suggestions of similarly named
+    /// tasks ranked by edit distance, and the tasks available in the targeted project.
+    pub fn unknown_task<T: AsRef<TaskPath>>(&self, task_path: T) -> UnknownTask {
+        let task_path = task_path.as_ref();
+        let (project, task) = task_path.split();
+        let target = ProjectFinder::new(&self.project).find(project);
+
+        let candidates = RefCell::new(vec![]);
+        match &target {
+            Some(target) if project.is_empty() => target.allprojects(|p| {
+                candidates
+                    .borrow_mut()
+                    .extend(p.task_container().get_tasks().into_iter().cloned());
+            }),
+            Some(target) => candidates.borrow_mut().extend(registered_tasks(target)),
+            None => {}
+        }
+
+        let requested = task.to_lowercase();
+        let max_distance = (requested.chars().count() / 3).max(2);
+        let mut suggestions = candidates
+            .into_inner()
+            .into_iter()
+            .filter_map(|candidate| {
+                let name = candidate.this().to_lowercase();
+                let distance = strsim::damerau_levenshtein(&requested, &name);
+                if distance <= max_distance || name.starts_with(&requested) {
+                    Some((distance, candidate))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        suggestions.sort_by(|(d1, id1), (d2, id2)| {
+            d1.cmp(d2)
+                .then_with(|| id1.to_string().cmp(&id2.to_string()))
+        });
+
+        let available = target.as_ref().map(|target| {
+            let mut tasks = registered_tasks(target)
+                .into_iter()
+                .map(|id| id.this().to_string())
+                .collect::<Vec<_>>();
+            tasks.sort();
+            (target.project_id(), tasks)
+        });
+
+        UnknownTask {
+            request: task_path.to_string(),
+            suggestions: suggestions
+                .into_iter()
+                .map(|(_, id)| id)
+                .take(MAX_SUGGESTIONS)
+                .collect(),
+            available,
+        }
+    }
+}
+
+/// The most tasks suggested for a task that couldn't be found
+const MAX_SUGGESTIONS: usize = 5;
+
+fn registered_tasks(project: &SharedProject) -> Vec<TaskId> {
+    project.with(|p| {
+        p.task_container()
+            .get_tasks()
+            .into_iter()
+            .cloned()
+            .collect()
+    })
+}
+
+/// A requested task could not be found
+#[derive(Debug, Clone, thiserror::Error)]
+pub struct UnknownTask {
+    request: String,
+    suggestions: Vec<TaskId>,
+    available: Option<(ProjectId, Vec<String>)>,
+}
+
+impl UnknownTask {
+    /// The task path that was requested
+    pub fn request(&self) -> &str {
+        &self.request
+    }
+
+    /// Tasks with names similar to the request, most similar first
+    pub fn suggestions(&self) -> &[TaskId] {
+        &self.suggestions
+    }
+}
+
+impl Display for UnknownTask {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No task could be found for {:?}", self.request)?;
+        match &self.suggestions[..] {
+            [] => {}
+            [only] => write!(f, "\n  Did you mean {}?", only)?,
+            many => {
+                write!(f, "\n  Did you mean one of these?")?;
+                for suggestion in many {
+                    write!(f, "\n    - {}", suggestion)?;
+                }
+            }
+        }
+        match &self.available {
+            Some((project, tasks)) if !tasks.is_empty() => {
+                write!(f, "\n  Tasks in {}: {}", project, tasks.join(", "))?
+            }
+            Some((project, _)) => write!(f, "\n  {} has no tasks", project)?,
+            None => write!(f, "\n  No project could be found for this path")?,
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -548,4 +691,47 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn case_insensitive_search() -> ProjectResult {
+        let project = init();
+        project.with_mut(|p| {
+            p.task_container_mut()
+                .register_task::<Empty>("compileJava")
+                .map(|_| ())
+        })?;
+
+        let finder = TaskFinder::new(&project);
+        assert_eq!(
+            finder.find(":compilejava")?,
+            Some(vec![TaskId::new(":root:compileJava").unwrap()])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn suggests_similar_tasks() -> ProjectResult {
+        let project = init();
+        project.with_mut(|p| -> ProjectResult {
+            p.task_container_mut().register_task::<Empty>("build")?;
+            p.task_container_mut().register_task::<Empty>("clean")?;
+            Ok(())
+        })?;
+
+        let finder = TaskFinder::new(&project);
+        assert_eq!(finder.find(":biuld")?, None);
+        let unknown = finder.unknown_task(":biuld");
+        assert_eq!(
+            unknown.suggestions(),
+            &[TaskId::new(":root:build").unwrap()]
+        );
+        let message = unknown.to_string();
+        assert!(message.contains("Did you mean :root:build?"), "{}", message);
+        assert!(
+            message.contains("Tasks in :root: build, clean"),
+            "{}",
+            message
+        );
+        Ok(())
+    }
 }
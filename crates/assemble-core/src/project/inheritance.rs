@@ -0,0 +1,154 @@
+//! Values shared by a project with its subprojects.
+//!
+//! Properties and extensions are only visible from subprojects when they are explicitly marked as
+//! shared, with [`Project::set_shared_property`](crate::Project::set_shared_property) and
+//! [`ExtensionContainer::add_shared`](crate::plugins::extensions::ExtensionContainer::add_shared).
+//! Shared values are read-only for subprojects.
+//!
+//! Lookups search the project itself first, then its parent, and so on until the root project is
+//! reached. The first value found is used, so a subproject can hide a value shared by an ancestor
+//! by sharing its own value with the same name.
+
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+/// A scope of shared values, linked to the scope of the parent project
+pub struct SharedScope<V> {
+    parent: Option<Box<SharedScope<V>>>,
+    values: Arc<RwLock<HashMap<String, V>>>,
+}
+
+impl<V> SharedScope<V> {
+    /// Creates a new scope with no parent
+    pub fn new() -> Self {
+        Self {
+            parent: None,
+            values: Default::default(),
+        }
+    }
+
+    /// Sets the scope searched after this one
+    pub(crate) fn set_parent(&mut self, parent: SharedScope<V>) {
+        self.parent = Some(Box::new(parent));
+    }
+
+    /// Shares a value with the given name, replacing any value previously shared in this scope
+    pub fn insert<S: AsRef<str>>(&self, name: S, value: V) -> Option<V> {
+        self.values.write().insert(name.as_ref().to_string(), value)
+    }
+
+    /// Checks whether a value was shared in this scope, ignoring parent scopes
+    pub fn contains<S: AsRef<str>>(&self, name: S) -> bool {
+        self.values.read().contains_key(name.as_ref())
+    }
+
+    /// The depth of the scope, where the scope of the root project has a depth of `0`
+    pub fn depth(&self) -> usize {
+        self.parent
+            .as_ref()
+            .map(|parent| parent.depth() + 1)
+            .unwrap_or(0)
+    }
+}
+
+impl<V: Clone> SharedScope<V> {
+    /// Finds a value, searching this scope first and then each parent scope in turn
+    pub fn find<S: AsRef<str>>(&self, name: S) -> Option<V> {
+        let name = name.as_ref();
+        if let Some(value) = self.values.read().get(name) {
+            return Some(value.clone());
+        }
+        self.parent.as_ref().and_then(|parent| parent.find(name))
+    }
+
+    /// Finds a value only in the parent scopes
+    pub fn find_inherited<S: AsRef<str>>(&self, name: S) -> Option<V> {
+        self.parent.as_ref().and_then(|parent| parent.find(name))
+    }
+}
+
+impl<V> Default for SharedScope<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Clone for SharedScope<V> {
+    fn clone(&self) -> Self {
+        Self {
+            parent: self
+                .parent
+                .as_ref()
+                .map(|parent| Box::new((**parent).clone())),
+            values: self.values.clone(),
+        }
+    }
+}
+
+impl<V> Debug for SharedScope<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedScope")
+            .field("shared", &self.values.read().keys().collect::<Vec<_>>())
+            .field("parent", &self.parent)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::extensions::ExtensionAware;
+    use crate::Project;
+
+    #[test]
+    fn nearest_scope_wins() {
+        let root = SharedScope::new();
+        root.insert("version", "1.0");
+        root.insert("group", "org");
+
+        let mut child = SharedScope::new();
+        child.set_parent(root.clone());
+        child.insert("version", "2.0");
+
+        assert_eq!(child.find("version"), Some("2.0"));
+        assert_eq!(child.find("group"), Some("org"));
+        assert_eq!(child.find_inherited("version"), Some("1.0"));
+        assert_eq!(child.find("missing"), None);
+
+        root.insert("license", "MIT");
+        assert_eq!(child.find("license"), Some("MIT"));
+        assert_eq!(child.depth(), 1);
+    }
+
+    #[test]
+    fn subprojects_see_shared_values() {
+        let project = Project::temp("root");
+        project.with_mut(|root| {
+            root.set_property("private".to_string(), "root".to_string());
+            root.set_shared_property("version".to_string(), "1.0".to_string());
+            root.extensions_mut()
+                .add_shared("conventions", String::from("strict"))
+                .unwrap();
+            root.subproject("child", |child| {
+                assert_eq!(
+                    child.find_property("version"),
+                    Some(Some("1.0".to_string()))
+                );
+                assert_eq!(child.find_property("private"), None);
+                assert_eq!(
+                    &*child.shared_extension::<String, _>("conventions").unwrap(),
+                    "strict"
+                );
+                child.set_property("version".to_string(), "2.0".to_string());
+                assert_eq!(
+                    child.find_property("version"),
+                    Some(Some("2.0".to_string()))
+                );
+                Ok(())
+            })
+            .unwrap();
+        });
+    }
+}
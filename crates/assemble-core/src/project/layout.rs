@@ -0,0 +1,227 @@
+//! File system conventions of a project.
+//!
+//! The [`ProjectLayout`](ProjectLayout) of a project gives lazily evaluated locations for files
+//! and directories, resolved relative to the project directory. The locations are provided as
+//! [`RegularFile`](RegularFile) and [`Directory`](Directory) values, so task outputs can be wired
+//! together without ever handling a raw `PathBuf`, while still carrying the tasks that build them.
+
+use crate::file;
+use crate::lazy_evaluation::anonymous::AnonymousProvider;
+use crate::lazy_evaluation::{IntoProvider, Prop, Provider, ProviderExt};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The location of a regular file. Unlike [`file::RegularFile`](file::RegularFile), the file
+/// isn't opened until requested.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct RegularFile {
+    path: PathBuf,
+}
+
+impl RegularFile {
+    /// Creates a regular file location from a path
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Gets the path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The directory containing the file, if it has one
+    pub fn parent(&self) -> Option<Directory> {
+        self.path.parent().map(Directory::new)
+    }
+
+    /// Attempts to open the file in read-only mode.
+    pub fn open(&self) -> io::Result<file::RegularFile> {
+        file::RegularFile::open(&self.path)
+    }
+
+    /// Opens the file in write-only mode, creating the directories containing it if necessary.
+    ///
+    /// See [`RegularFile::create`](file::RegularFile::create).
+    pub fn create(&self) -> io::Result<file::RegularFile> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        file::RegularFile::create(&self.path)
+    }
+}
+
+impl Display for RegularFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.path)
+    }
+}
+
+impl AsRef<Path> for RegularFile {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl From<RegularFile> for PathBuf {
+    fn from(file: RegularFile) -> Self {
+        file.path
+    }
+}
+
+/// The location of a directory
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Directory {
+    path: PathBuf,
+}
+
+impl Directory {
+    /// Creates a directory location from a path
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Gets the path of the directory
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// A file within this directory
+    pub fn file<P: AsRef<Path>>(&self, path: P) -> RegularFile {
+        RegularFile::new(self.path.join(path))
+    }
+
+    /// A directory within this directory
+    pub fn dir<P: AsRef<Path>>(&self, path: P) -> Directory {
+        Directory::new(self.path.join(path))
+    }
+
+    /// Creates this directory and all of its parents if they are missing
+    pub fn create(&self) -> io::Result<()> {
+        std::fs::create_dir_all(&self.path)
+    }
+}
+
+impl Display for Directory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.path)
+    }
+}
+
+impl AsRef<Path> for Directory {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl From<Directory> for PathBuf {
+    fn from(dir: Directory) -> Self {
+        dir.path
+    }
+}
+
+/// The file system layout of a project. All relative paths are resolved against the project
+/// directory.
+#[derive(Debug, Clone)]
+pub struct ProjectLayout {
+    project_dir: Directory,
+    build_dir: Prop<PathBuf>,
+}
+
+impl ProjectLayout {
+    pub(crate) fn new(project_dir: PathBuf, build_dir: Prop<PathBuf>) -> Self {
+        Self {
+            project_dir: Directory::new(project_dir),
+            build_dir,
+        }
+    }
+
+    /// The directory of the project
+    pub fn project_directory(&self) -> Directory {
+        self.project_dir.clone()
+    }
+
+    /// The directory where created files should be stored. Follows changes to the build directory
+    /// of the project.
+    pub fn build_directory(&self) -> AnonymousProvider<Directory> {
+        self.dir(self.build_dir.clone())
+    }
+
+    /// A file provided by a provider of paths, resolved relative to the project directory. Keeps
+    /// the dependencies of the provider.
+    pub fn file<P>(&self, path: P) -> AnonymousProvider<RegularFile>
+    where
+        P: IntoProvider<PathBuf>,
+        P::Provider: 'static,
+    {
+        let project_dir = self.project_dir.clone();
+        AnonymousProvider::new(path.into_provider().map(move |path| project_dir.file(path)))
+    }
+
+    /// A directory provided by a provider of paths, resolved relative to the project directory.
+    /// Keeps the dependencies of the provider.
+    pub fn dir<P>(&self, path: P) -> AnonymousProvider<Directory>
+    where
+        P: IntoProvider<PathBuf>,
+        P::Provider: 'static,
+    {
+        let project_dir = self.project_dir.clone();
+        AnonymousProvider::new(path.into_provider().map(move |path| project_dir.dir(path)))
+    }
+
+    /// A file within the build directory
+    pub fn build_file<P: AsRef<Path>>(&self, path: P) -> AnonymousProvider<RegularFile> {
+        let path = path.as_ref().to_path_buf();
+        AnonymousProvider::new(self.build_directory().map(move |dir| dir.file(&path)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identifier::TaskId;
+    use crate::project::buildable::Buildable;
+    use crate::Project;
+    use std::collections::HashSet;
+
+    #[test]
+    fn locations_are_relative_to_project() {
+        let project = Project::temp("root");
+        let layout = project.with(|p| p.layout());
+        let project_dir = project.with(|p| p.project_dir());
+
+        assert_eq!(layout.project_directory().path(), project_dir);
+        assert_eq!(
+            layout.build_directory().get().path(),
+            project_dir.join("build")
+        );
+        assert_eq!(
+            layout.build_file("out.txt").get().path(),
+            project_dir.join("build").join("out.txt")
+        );
+
+        project.with_mut(|p| p.set_build_dir("target"));
+        assert_eq!(
+            layout.build_directory().get(),
+            Directory::new(project_dir.join("target"))
+        );
+    }
+
+    #[test]
+    fn locations_keep_dependencies() {
+        let project = Project::temp("root");
+        let layout = project.with(|p| p.layout());
+        let producer = TaskId::new("root:produce").unwrap();
+        let path =
+            AnonymousProvider::with_value(PathBuf::from("produced.txt")).built_by(producer.clone());
+
+        let file = layout.file(path);
+        let dependencies = project.with(|p| file.get_dependencies(p)).unwrap();
+        assert_eq!(dependencies, HashSet::from([producer]));
+    }
+}
@@ -0,0 +1,200 @@
+//! The phases a build moves through, as seen by its projects.
+//!
+//! Projects are configured until the task graph has been constructed. After that, registering or
+//! configuring tasks would have no effect, because the tasks that will run have already been
+//! chosen. Instead of silently ignoring such changes, they fail with a [`LateMutation`](LateMutation)
+//! error naming the code that attempted them.
+//!
+//! Every project in a build shares the same [`PhaseState`](PhaseState).
+
+use std::backtrace::Backtrace;
+use std::fmt::{Display, Formatter};
+use std::panic::Location;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+/// The maximum amount of frames kept in the backtrace of a [`LateMutation`](LateMutation)
+pub const LATE_MUTATION_FRAMES: usize = 6;
+
+/// A phase of a build. Phases only ever advance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum ProjectPhase {
+    /// Projects and tasks are being configured
+    Configuration = 0,
+    /// The task graph has been constructed
+    GraphReady = 1,
+    /// Tasks are being executed
+    Execution = 2,
+}
+
+impl ProjectPhase {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => ProjectPhase::Configuration,
+            1 => ProjectPhase::GraphReady,
+            _ => ProjectPhase::Execution,
+        }
+    }
+
+    /// Whether tasks can still be registered and configured in this phase
+    pub fn is_configurable(&self) -> bool {
+        *self == ProjectPhase::Configuration
+    }
+}
+
+impl Display for ProjectPhase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProjectPhase::Configuration => write!(f, "configuration"),
+            ProjectPhase::GraphReady => write!(f, "graph ready"),
+            ProjectPhase::Execution => write!(f, "execution"),
+        }
+    }
+}
+
+/// The current phase of a build. Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct PhaseState(Arc<AtomicU8>);
+
+impl PhaseState {
+    /// Creates a new state, in the configuration phase
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The current phase
+    pub fn phase(&self) -> ProjectPhase {
+        ProjectPhase::from_u8(self.0.load(Ordering::SeqCst))
+    }
+
+    /// Advances to a phase. Does nothing if a later phase has already been reached.
+    pub fn advance(&self, phase: ProjectPhase) {
+        let previous = ProjectPhase::from_u8(self.0.fetch_max(phase as u8, Ordering::SeqCst));
+        if previous < phase {
+            trace!("advanced from {} phase to {} phase", previous, phase);
+        }
+    }
+
+    /// Checks that the build is still being configured before performing some action.
+    ///
+    /// The caller of the function this is called from is named in the error, so functions calling
+    /// this should be `#[track_caller]`.
+    #[track_caller]
+    pub fn ensure_configurable(&self, action: impl AsRef<str>) -> Result<(), LateMutation> {
+        self.ensure_configurable_at(action, Location::caller())
+    }
+
+    /// Checks that the build is still being configured before performing some action, naming
+    /// the given caller in the error.
+    pub fn ensure_configurable_at(
+        &self,
+        action: impl AsRef<str>,
+        caller: &'static Location<'static>,
+    ) -> Result<(), LateMutation> {
+        let phase = self.phase();
+        if phase.is_configurable() {
+            Ok(())
+        } else {
+            Err(LateMutation {
+                action: action.as_ref().to_string(),
+                phase,
+                caller,
+                backtrace: small_backtrace(),
+            })
+        }
+    }
+}
+
+/// An attempt to change the configuration of a build after the task graph was constructed
+#[derive(Debug, thiserror::Error)]
+#[error("{action} at {caller} during the {phase} phase, but tasks can only be registered or configured before the task graph is ready{}", format_frames(.backtrace))]
+pub struct LateMutation {
+    action: String,
+    phase: ProjectPhase,
+    caller: &'static Location<'static>,
+    backtrace: Vec<String>,
+}
+
+impl LateMutation {
+    /// What was attempted
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    /// The phase the build was in
+    pub fn phase(&self) -> ProjectPhase {
+        self.phase
+    }
+
+    /// The location of the code that attempted the change
+    pub fn caller(&self) -> &'static Location<'static> {
+        self.caller
+    }
+
+    /// The innermost frames of the backtrace of the attempt, if backtraces could be captured
+    pub fn backtrace(&self) -> &[String] {
+        &self.backtrace
+    }
+}
+
+fn format_frames(frames: &[String]) -> String {
+    frames
+        .iter()
+        .map(|frame| format!("\n    {}", frame))
+        .collect()
+}
+
+/// Captures the innermost frames of the current backtrace, skipping frames from the standard
+/// library and from this module
+fn small_backtrace() -> Vec<String> {
+    let backtrace = Backtrace::force_capture().to_string();
+    let mut frames: Vec<String> = vec![];
+    for line in backtrace.lines().map(str::trim) {
+        match line.split_once(": ") {
+            Some((index, symbol)) if index.chars().all(|c| c.is_ascii_digit()) => {
+                frames.push(symbol.to_string());
+            }
+            _ => {
+                if let (Some(location), Some(frame)) = (line.strip_prefix("at "), frames.last_mut())
+                {
+                    frame.push_str(&format!(" ({})", location));
+                }
+            }
+        }
+    }
+    frames
+        .into_iter()
+        .filter(|frame| {
+            !(frame.starts_with("std::")
+                || frame.starts_with("core::")
+                || frame.starts_with("alloc::")
+                || frame.contains("::phase::"))
+        })
+        .take(LATE_MUTATION_FRAMES)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases_only_advance() {
+        let state = PhaseState::new();
+        let shared = state.clone();
+        assert!(state.ensure_configurable("registering task").is_ok());
+
+        state.advance(ProjectPhase::Execution);
+        shared.advance(ProjectPhase::GraphReady);
+        assert_eq!(shared.phase(), ProjectPhase::Execution);
+
+        let error = shared
+            .ensure_configurable("registering task :root:late")
+            .unwrap_err();
+        assert_eq!(error.caller().file(), file!());
+        assert!(error
+            .to_string()
+            .starts_with("registering task :root:late at "));
+    }
+}
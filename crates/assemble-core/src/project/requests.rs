@@ -6,7 +6,7 @@ use crate::project::error::{ProjectError, ProjectResult};
 use crate::project::finder::{ProjectFinder, ProjectPathBuf, TaskFinder, TaskPath, TaskPathBuf};
 use crate::project::shared::SharedProject;
 use crate::task::flags::{OptionsSlurper, WeakOptionsDecoder};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 /// The finalized tasks requests.
 #[derive(Debug)]
@@ -67,7 +67,7 @@ impl TaskRequests {
                     builder.add_tasks(ids);
                 }
             } else {
-                return Err(ProjectError::NoIdentifiersFound(task_req.to_string()).into());
+                return Err(ProjectError::from(task_finder.unknown_task(task_req)).into());
             }
         }
 
@@ -80,10 +80,60 @@ impl TaskRequests {
         Some(self.weak_decoders[*index].clone())
     }
 
+    /// Recreates task requests from requested tasks and the options given to them
+    pub fn restore<I>(tasks: Vec<TaskId>, decoders: I) -> Self
+    where
+        I: IntoIterator<Item = (Vec<TaskId>, WeakOptionsDecoder)>,
+    {
+        let mut builder = TaskRequestsBuilder::new();
+        builder.in_progress.tasks = tasks;
+        for (tasks, decoder) in decoders {
+            let index = builder.in_progress.weak_decoders.len();
+            builder.in_progress.weak_decoders.push(decoder);
+            for task in tasks {
+                builder.in_progress.task_to_weak_decoder.insert(task, index);
+            }
+        }
+        builder.finish()
+    }
+
+    /// The options given to requested tasks, with the tasks each set of options was given to
+    pub fn decoders(&self) -> Vec<(Vec<TaskId>, WeakOptionsDecoder)> {
+        self.weak_decoders
+            .iter()
+            .enumerate()
+            .map(|(index, decoder)| {
+                let tasks = self
+                    .tasks
+                    .iter()
+                    .filter(|task| self.task_to_weak_decoder.get(*task) == Some(&index))
+                    .cloned()
+                    .collect();
+                (tasks, decoder.clone())
+            })
+            .collect()
+    }
+
     /// Tasks requests, in-order
     pub fn requested_tasks(&self) -> &[TaskId] {
         &self.tasks[..]
     }
+
+    /// Removes tasks that were requested more than once, keeping the first request of each task.
+    /// Returns the tasks that were removed.
+    pub fn deduplicate(&mut self) -> Vec<TaskId> {
+        let mut seen = HashSet::new();
+        let mut duplicates = vec![];
+        self.tasks.retain(|task| {
+            if seen.insert(task.clone()) {
+                true
+            } else {
+                duplicates.push(task.clone());
+                false
+            }
+        });
+        duplicates
+    }
 }
 
 struct TaskRequestsBuilder {
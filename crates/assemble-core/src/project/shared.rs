@@ -14,8 +14,13 @@ use crate::{project, Plugin, Project, Task, Workspace};
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::fmt::{Debug, Display, Formatter};
 use std::ops::{Deref, DerefMut};
+use std::panic::Location;
 use std::sync::{Arc, Weak};
 
+pub mod locks;
+
+use locks::{LockKind, TrackedLock};
+
 /// The shared project allows for many projects to share references to the same
 /// [`Project`](Project) instance.
 #[derive(Debug, Clone)]
@@ -44,42 +49,83 @@ impl SharedProject {
         WeakSharedProject(Arc::downgrade(&self.0))
     }
 
+    /// Takes a read lock on the project, waiting as described in [`locks`](locks)
+    #[track_caller]
+    fn read(&self) -> (RwLockReadGuard<Project>, TrackedLock) {
+        locks::acquire(&self.0 .1, LockKind::Read, Location::caller(), |wait| {
+            self.0 .0.try_read_recursive_for(wait)
+        })
+    }
+
+    /// Takes a write lock on the project, waiting as described in [`locks`](locks)
+    #[track_caller]
+    fn write(&self) -> (RwLockWriteGuard<Project>, TrackedLock) {
+        locks::acquire(&self.0 .1, LockKind::Write, Location::caller(), |wait| {
+            self.0 .0.try_write_for(wait)
+        })
+    }
+
+    /// Runs a function with read access to the project, waiting for any thread with write access
+    /// to finish.
+    ///
+    /// # Panics
+    /// If the current thread has write access to the project, or the
+    /// [project lock timeout](locks::set_project_lock_timeout) passes.
+    #[track_caller]
     pub fn with<F, R>(&self, func: F) -> R
     where
         F: FnOnce(&Project) -> R,
     {
-        let guard = self
-            .0
-             .0
-            .try_read()
-            .unwrap_or_else(|| panic!("Couldn't get read access to {}", self));
+        let (guard, _tracked) = self.read();
         let r = (func)(&*guard);
         r
     }
 
+    /// Runs a function with write access to the project, waiting for every other thread accessing
+    /// the project to finish.
+    ///
+    /// # Panics
+    /// If the current thread already has access to the project, or the
+    /// [project lock timeout](locks::set_project_lock_timeout) passes.
+    #[track_caller]
     pub fn with_mut<F, R>(&self, func: F) -> R
     where
         F: FnOnce(&mut Project) -> R,
     {
-        let mut guard = self
-            .0
-             .0
-            .try_write()
-            .unwrap_or_else(|| panic!("Couldn't get read access to {}", self));
+        let (mut guard, _tracked) = self.write();
         let r = (func)(&mut *guard);
         r
     }
 
+    /// Runs a function with read access to the project if no thread has write access to it.
+    /// Returns `None` without waiting otherwise.
+    pub fn try_with<F, R>(&self, func: F) -> Option<R>
+    where
+        F: FnOnce(&Project) -> R,
+    {
+        let guard = self.0 .0.try_read_recursive()?;
+        Some((func)(&*guard))
+    }
+
+    /// Runs a function with write access to the project if no other thread is accessing it.
+    /// Returns `None` without waiting otherwise.
+    pub fn try_with_mut<F, R>(&self, func: F) -> Option<R>
+    where
+        F: FnOnce(&mut Project) -> R,
+    {
+        let mut guard = self.0 .0.try_write()?;
+        Some((func)(&mut *guard))
+    }
+
+    #[track_caller]
     pub fn guard<'g, T, F: Fn(&Project) -> &T + 'g>(&'g self, func: F) -> ProjectResult<Guard<T>> {
-        let guard = match self.0 .0.try_read() {
-            Some(guard) => guard,
-            None => {
-                panic!("Accessing this immutable guard would block for {}", self)
-            }
-        };
-        Ok(Guard::new(guard, func))
+        let (guard, tracked) = self.read();
+        let mut guard = Guard::new(guard, func);
+        guard._tracked = Some(tracked);
+        Ok(guard)
     }
 
+    #[track_caller]
     pub fn guard_mut<'g, T, F1, F2>(
         &'g self,
         ref_getter: F1,
@@ -89,13 +135,10 @@ impl SharedProject {
         F1: Fn(&Project) -> &T + 'g,
         F2: Fn(&mut Project) -> &mut T + 'g,
     {
-        let guard = match self.0 .0.try_write() {
-            Some(guard) => guard,
-            None => {
-                panic!("Accessing this guard would block for {}", self)
-            }
-        };
-        Ok(GuardMut::new(guard, ref_getter, mut_getter))
+        let (guard, tracked) = self.write();
+        let mut guard = GuardMut::new(guard, ref_getter, mut_getter);
+        guard._tracked = Some(tracked);
+        Ok(guard)
     }
 
     pub fn tasks(&self) -> GuardMut<TaskContainer> {
@@ -106,11 +149,14 @@ impl SharedProject {
         .expect("couldn't safely get task container")
     }
 
+    #[track_caller]
     pub fn register_task<T: Task + Send + Sync + Debug + 'static>(
         &self,
         id: &str,
     ) -> ProjectResult<TaskHandle<T>> {
-        self.tasks().with_mut(|t| t.register_task::<T>(id))
+        let caller = Location::caller();
+        self.tasks()
+            .with_mut(|t| t.register_task_at::<T>(id, caller))
     }
 
     /// Gets a task with a given name
@@ -133,6 +179,32 @@ impl SharedProject {
         })
     }
 
+    /// Applies the first [task rule](crate::task::task_rule) of this project matching a task name,
+    /// returning the id of the task if the rule registered it. Rules are only applied while the
+    /// project can still be configured, and never to tasks that are already registered.
+    pub fn apply_task_rules(&self, name: &str) -> ProjectResult<Option<TaskId>> {
+        let id = match self.task_id_factory().create(name) {
+            Ok(id) => id,
+            Err(_) => return Ok(None),
+        };
+        if self.get_task(&id).is_ok() {
+            return Ok(Some(id));
+        }
+        let rule = self.with(|p| {
+            if p.phase_state().phase().is_configurable() {
+                p.task_container().matching_rule(name)
+            } else {
+                None
+            }
+        });
+        let rule = match rule {
+            Some(rule) => rule,
+            None => return Ok(None),
+        };
+        self.with_mut(|p| rule.apply(name, p))?;
+        Ok(self.get_task(&id).ok().map(|_| id))
+    }
+
     /// Finds a task, using this project as the base task
     pub fn find_task<P: AsRef<TaskPath>>(&self, path: P) -> ProjectResult<AnyTaskHandle> {
         let finder = TaskFinder::new(self);
@@ -225,6 +297,16 @@ impl SharedProject {
     pub fn apply_plugin<P: Plugin<Project>>(&self) -> ProjectResult {
         self.with_mut(|p| p.apply_plugin::<P>())
     }
+
+    /// Apply a plugin to this only if a condition holds for it. Returns whether the plugin was
+    /// applied.
+    pub fn apply_plugin_if<P, F>(&self, condition: F) -> ProjectResult<bool>
+    where
+        P: Plugin<Project>,
+        F: FnOnce(&Project) -> bool,
+    {
+        self.with_mut(|p| p.apply_plugin_if::<P, F>(condition))
+    }
 }
 
 impl Display for SharedProject {
@@ -268,6 +350,7 @@ impl From<Arc<(TrueSharableProject, ProjectId)>> for SharedProject {
 pub struct Guard<'g, T> {
     guard: RwLockReadGuard<'g, Project>,
     getter: Box<dyn Fn(&Project) -> &T + 'g>,
+    _tracked: Option<TrackedLock>,
 }
 
 impl<'g, T> Guard<'g, T> {
@@ -278,6 +361,7 @@ impl<'g, T> Guard<'g, T> {
         Self {
             guard,
             getter: Box::new(getter),
+            _tracked: None,
         }
     }
 
@@ -303,6 +387,7 @@ pub struct GuardMut<'g, T> {
     guard: RwLockWriteGuard<'g, Project>,
     ref_getter: Box<dyn Fn(&Project) -> &T + 'g>,
     mut_getter: Box<dyn Fn(&mut Project) -> &mut T + 'g>,
+    _tracked: Option<TrackedLock>,
 }
 
 impl<'g, T> GuardMut<'g, T> {
@@ -315,6 +400,7 @@ impl<'g, T> GuardMut<'g, T> {
             guard,
             ref_getter: Box::new(ref_getter),
             mut_getter: Box::new(mut_getter),
+            _tracked: None,
         }
     }
 
@@ -0,0 +1,293 @@
+//! Tracking of the locks held on [shared projects](super::SharedProject).
+//!
+//! Every access to a shared project takes a read or write lock on it, and the accesses of
+//! different threads can wait on each other forever if they nest. To make this visible, every
+//! thread holding or waiting for a project lock is recorded. A thread that waits longer than
+//! [`LOCK_REPORT_THRESHOLD`](LOCK_REPORT_THRESHOLD) logs which threads hold and wait for which
+//! project locks, and keeps doing so until it gets the lock. If a [timeout](set_project_lock_timeout)
+//! is set, the thread panics with the same report once the timeout passes.
+//!
+//! Accesses that would always wait forever, like a thread taking a write lock on a project it
+//! already has locked, panic immediately.
+
+use crate::identifier::ProjectId;
+use crate::startup::watchdog::{register_state_dump, StateDumpHandle};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::fmt::{Display, Formatter, Write as _};
+use std::panic::Location;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+/// How long a thread waits for a project lock before reporting which threads hold it
+pub const LOCK_REPORT_THRESHOLD: Duration = Duration::from_secs(5);
+
+static LOCK_TIMEOUT: Mutex<Option<Duration>> = parking_lot::const_mutex(None);
+
+static ACQUISITIONS: Lazy<Mutex<Vec<Acquisition>>> = Lazy::new(Default::default);
+static NEXT_ACQUISITION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Includes the project locks in the state dumps emitted when the build times out
+static STATE_DUMP: Lazy<StateDumpHandle> =
+    Lazy::new(|| register_state_dump("project locks", lock_report));
+
+/// Sets how long a thread waits for a project lock before panicking. Threads wait forever when the
+/// timeout is `None`, which is the default.
+pub fn set_project_lock_timeout(timeout: Option<Duration>) {
+    *LOCK_TIMEOUT.lock() = timeout;
+}
+
+/// How long a thread waits for a project lock before panicking
+pub fn project_lock_timeout() -> Option<Duration> {
+    *LOCK_TIMEOUT.lock()
+}
+
+/// The kinds of locks taken on a project
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockKind {
+    /// Shared access, which can be held by many threads at once
+    Read,
+    /// Exclusive access
+    Write,
+}
+
+impl Display for LockKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LockKind::Read => write!(f, "read"),
+            LockKind::Write => write!(f, "write"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Acquisition {
+    id: u64,
+    project: ProjectId,
+    kind: LockKind,
+    thread: ThreadId,
+    thread_name: String,
+    location: &'static Location<'static>,
+    since: Instant,
+    held: bool,
+}
+
+/// A recorded acquisition of a project lock, removed from the record when dropped. Must be
+/// dropped after the lock guard it tracks.
+#[derive(Debug)]
+pub(crate) struct TrackedLock(u64);
+
+impl TrackedLock {
+    fn waiting(project: &ProjectId, kind: LockKind, location: &'static Location<'static>) -> Self {
+        Lazy::force(&STATE_DUMP);
+        let id = NEXT_ACQUISITION_ID.fetch_add(1, Ordering::Relaxed);
+        let current = thread::current();
+        ACQUISITIONS.lock().push(Acquisition {
+            id,
+            project: project.clone(),
+            kind,
+            thread: current.id(),
+            thread_name: current.name().unwrap_or("<unnamed>").to_string(),
+            location,
+            since: Instant::now(),
+            held: false,
+        });
+        Self(id)
+    }
+
+    fn acquired(&self) {
+        if let Some(acquisition) = ACQUISITIONS.lock().iter_mut().find(|a| a.id == self.0) {
+            acquisition.held = true;
+            acquisition.since = Instant::now();
+        }
+    }
+}
+
+impl Drop for TrackedLock {
+    fn drop(&mut self) {
+        ACQUISITIONS.lock().retain(|a| a.id != self.0);
+    }
+}
+
+/// Takes a lock on a project using `try_for`, which tries to take the lock within a duration.
+/// Waits at most the [project lock timeout](project_lock_timeout).
+///
+/// # Panics
+/// If the current thread already holds a lock on the project that stops it from ever taking this
+/// lock, or the timeout passes.
+pub(crate) fn acquire<G>(
+    project: &ProjectId,
+    kind: LockKind,
+    location: &'static Location<'static>,
+    try_for: impl FnMut(Duration) -> Option<G>,
+) -> (G, TrackedLock) {
+    acquire_within(project, kind, location, project_lock_timeout(), try_for)
+}
+
+fn acquire_within<G>(
+    project: &ProjectId,
+    kind: LockKind,
+    location: &'static Location<'static>,
+    timeout: Option<Duration>,
+    mut try_for: impl FnMut(Duration) -> Option<G>,
+) -> (G, TrackedLock) {
+    if let Some(held) = held_by_current_thread(project) {
+        if held == LockKind::Write || kind == LockKind::Write {
+            panic!(
+                "taking a {} lock on project {} at {} would wait forever, because this thread \
+                 already holds a {} lock on it\n{}",
+                kind,
+                project,
+                location,
+                held,
+                lock_report()
+            );
+        }
+    }
+
+    let tracked = TrackedLock::waiting(project, kind, location);
+    let start = Instant::now();
+    loop {
+        let wait = match timeout {
+            Some(timeout) => timeout
+                .saturating_sub(start.elapsed())
+                .min(LOCK_REPORT_THRESHOLD),
+            None => LOCK_REPORT_THRESHOLD,
+        };
+        if let Some(guard) = try_for(wait) {
+            tracked.acquired();
+            return (guard, tracked);
+        }
+        let waited = start.elapsed();
+        if timeout.map_or(false, |timeout| waited >= timeout) {
+            panic!(
+                "timed out after {} waiting for a {} lock on project {} at {}\n{}",
+                crate::humanize::duration(waited),
+                kind,
+                project,
+                location,
+                lock_report()
+            );
+        }
+        warn!(
+            "waited {} for a {} lock on project {} at {}\n{}",
+            crate::humanize::duration(waited),
+            kind,
+            project,
+            location,
+            lock_report()
+        );
+    }
+}
+
+/// The strongest lock the current thread holds on a project
+fn held_by_current_thread(project: &ProjectId) -> Option<LockKind> {
+    let current = thread::current().id();
+    ACQUISITIONS
+        .lock()
+        .iter()
+        .filter(|a| a.held && a.thread == current && &a.project == project)
+        .map(|a| a.kind)
+        .max_by_key(|kind| *kind == LockKind::Write)
+}
+
+/// Reports which threads hold and wait for which project locks
+pub fn lock_report() -> String {
+    let acquisitions = ACQUISITIONS.lock();
+    if acquisitions.is_empty() {
+        return "no project locks are held".to_string();
+    }
+    let mut report = String::new();
+    for acquisition in acquisitions.iter() {
+        let _ = writeln!(
+            report,
+            "thread '{}' {} a {} lock on project {} for {}, taken at {}",
+            acquisition.thread_name,
+            if acquisition.held {
+                "holds"
+            } else {
+                "is waiting for"
+            },
+            acquisition.kind,
+            acquisition.project,
+            crate::humanize::duration(acquisition.since.elapsed()),
+            acquisition.location
+        );
+    }
+    report.truncate(report.trim_end().len());
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Project;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn try_with_does_not_block() {
+        let project = Project::temp("try-with");
+        assert_eq!(project.try_with(|_| 1), Some(1));
+
+        let (locked, wait_locked) = channel();
+        let (release, wait_release) = channel::<()>();
+        let holder = project.clone();
+        let handle = thread::spawn(move || {
+            holder.with_mut(|_| {
+                locked.send(()).unwrap();
+                wait_release.recv().unwrap();
+            })
+        });
+        wait_locked.recv().unwrap();
+        assert_eq!(project.try_with(|_| 1), None);
+        assert!(lock_report().contains("holds a write lock on project"));
+
+        release.send(()).unwrap();
+        handle.join().unwrap();
+        assert_eq!(project.try_with_mut(|_| 2), Some(2));
+    }
+
+    #[test]
+    fn waiting_past_timeout_panics_with_report() {
+        let project = Project::temp("lock-timeout");
+        let id = project.with(|p| p.id().clone());
+        let (locked, wait_locked) = channel();
+        let (release, wait_release) = channel::<()>();
+        let holder = project.clone();
+        let handle = thread::Builder::new()
+            .name("lock-holder".to_string())
+            .spawn(move || {
+                holder.with_mut(|_| {
+                    locked.send(()).unwrap();
+                    wait_release.recv().unwrap();
+                })
+            })
+            .unwrap();
+        wait_locked.recv().unwrap();
+
+        let error = std::panic::catch_unwind(|| {
+            acquire_within(
+                &id,
+                LockKind::Read,
+                Location::caller(),
+                Some(Duration::from_millis(50)),
+                |_| None::<()>,
+            )
+        })
+        .unwrap_err();
+        let message = error.downcast_ref::<String>().unwrap();
+        assert!(message.contains("timed out"));
+        assert!(message.contains("thread 'lock-holder' holds a write lock"));
+
+        release.send(()).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "would wait forever")]
+    fn nested_writes_panic() {
+        let project = Project::temp("nested-writes");
+        project.with(|_| project.with_mut(|_| ()));
+    }
+}
@@ -13,7 +13,16 @@
 //! [assemble_struct]: crate::startup::invocation::Assemble;
 //! [assemble_settings]: crate::startup::initialization::Settings;
 
+pub mod cancellation;
+pub mod control;
 pub mod execution_graph;
+pub mod init_scripts;
 pub mod initialization;
 pub mod invocation;
 pub mod listeners;
+pub mod profile;
+pub mod properties;
+pub mod script_plugins;
+pub mod trust;
+pub mod updates;
+pub mod watchdog;
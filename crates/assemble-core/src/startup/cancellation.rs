@@ -0,0 +1,130 @@
+//! Build-wide cancellation.
+//!
+//! A build can be cancelled at any point, for example when a timeout is exceeded. Once cancelled,
+//! no new tasks are started, and running tasks can poll the [`CancellationToken`](CancellationToken)
+//! to stop early. When run from the command line, the first Ctrl-C cancels the build, and a second
+//! one aborts it. Other processes can also cancel a build through its
+//! [control endpoint](crate::startup::control).
+
+use crate::startup::watchdog::TIMEOUT_EXIT_CODE;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The exit code used when a build was interrupted
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Why a build was cancelled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CancellationReason {
+    /// The user interrupted the build
+    Interrupted,
+    /// Another process requested that the build is cancelled
+    Requested {
+        /// Who requested the cancellation
+        by: String,
+    },
+    /// A timeout was exceeded
+    TimedOut {
+        /// The phase of the build the timeout applied to
+        phase: String,
+        /// The timeout that was exceeded
+        limit: Duration,
+    },
+}
+
+impl CancellationReason {
+    /// The exit code of a build cancelled for this reason
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CancellationReason::Interrupted | CancellationReason::Requested { .. } => {
+                INTERRUPTED_EXIT_CODE
+            }
+            CancellationReason::TimedOut { .. } => TIMEOUT_EXIT_CODE,
+        }
+    }
+}
+
+impl Display for CancellationReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CancellationReason::Interrupted => write!(f, "build was interrupted"),
+            CancellationReason::Requested { by } => {
+                write!(f, "cancellation was requested by {}", by)
+            }
+            CancellationReason::TimedOut { phase, limit } => {
+                write!(f, "{} exceeded timeout of {:?}", phase, limit)
+            }
+        }
+    }
+}
+
+/// A token that can be used to check whether the build was cancelled. Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<CancellationInner>,
+}
+
+#[derive(Debug, Default)]
+struct CancellationInner {
+    cancelled: AtomicBool,
+    reason: RwLock<Option<CancellationReason>>,
+}
+
+impl CancellationToken {
+    /// Creates a new, uncancelled token
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the token. Only the first reason given is kept. Returns whether this call cancelled
+    /// the token.
+    pub fn cancel(&self, reason: CancellationReason) -> bool {
+        let mut guard = self.inner.reason.write();
+        if guard.is_some() {
+            return false;
+        }
+        *guard = Some(reason);
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        true
+    }
+
+    /// Whether this token has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// The reason this token was cancelled, if it was
+    pub fn reason(&self) -> Option<CancellationReason> {
+        self.inner.reason.read().clone()
+    }
+}
+
+static BUILD_CANCELLATION: Lazy<CancellationToken> = Lazy::new(CancellationToken::new);
+
+/// Gets the cancellation token of the running build
+pub fn build_cancellation() -> CancellationToken {
+    BUILD_CANCELLATION.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_reason_is_kept() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!token.is_cancelled());
+        assert!(clone.cancel(CancellationReason::Interrupted));
+        assert!(!token.cancel(CancellationReason::TimedOut {
+            phase: "build".to_string(),
+            limit: Duration::from_secs(1)
+        }));
+        assert!(token.is_cancelled());
+        assert_eq!(token.reason(), Some(CancellationReason::Interrupted));
+    }
+}
@@ -0,0 +1,332 @@
+//! Controlling a running build from another process.
+//!
+//! Every build started from the command line opens a control endpoint on the loopback interface
+//! and registers it under `ASSEMBLE_HOME/builds`, keyed by the id of the build. Another process,
+//! such as an IDE or a second invocation of `assemble --cancel <build-id>`, can then connect to the
+//! endpoint and send requests to the build.
+//!
+//! # Protocol
+//! Each request and response is a single line of json. A connection may send any number of
+//! requests, and each request gets exactly one response.
+//!
+//! Cancellation is cooperative: a cancelled build stops starting new tasks, and running tasks can
+//! poll the [`CancellationToken`](CancellationToken) to stop early.
+
+use crate::startup::cancellation::{CancellationReason, CancellationToken};
+use crate::ASSEMBLE_HOME;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// The directory within `ASSEMBLE_HOME` running builds are registered in
+pub const BUILDS_DIR: &str = "builds";
+
+/// How long clients wait for a response from a build
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Identifies a build
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BuildId(String);
+
+impl BuildId {
+    /// Creates a new, random build id
+    pub fn random() -> Self {
+        Self(uuid::Uuid::new_v4().to_string())
+    }
+
+    /// Creates a build id from a string. The id must only contain alphanumeric characters, `-`,
+    /// and `_` so it can be used as a file name.
+    pub fn new<S: AsRef<str>>(id: S) -> io::Result<Self> {
+        let id = id.as_ref();
+        if id.is_empty()
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid build id {:?}", id),
+            ));
+        }
+        Ok(Self(id.to_string()))
+    }
+}
+
+impl FromStr for BuildId {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl Display for BuildId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A request sent to a running build
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum ControlRequest {
+    /// Cancel the build
+    Cancel {
+        /// Who requested the cancellation
+        requested_by: String,
+    },
+    /// Get the status of the build
+    Status,
+}
+
+/// The response of a running build to a request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "response", rename_all = "snake_case")]
+pub enum ControlResponse {
+    /// The status of the build
+    Status {
+        /// The id of the build
+        build_id: BuildId,
+        /// Why the build was cancelled, if it was
+        cancelled: Option<String>,
+    },
+    /// The request could not be handled
+    Error {
+        /// What went wrong
+        message: String,
+    },
+}
+
+/// How to reach a running build
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildRegistration {
+    /// The id of the build
+    pub build_id: BuildId,
+    /// The id of the process running the build
+    pub pid: u32,
+    /// The address of the control endpoint of the build
+    pub address: SocketAddr,
+}
+
+impl BuildRegistration {
+    fn file(build_id: &BuildId) -> PathBuf {
+        ASSEMBLE_HOME
+            .path()
+            .join(BUILDS_DIR)
+            .join(format!("{}.json", build_id))
+    }
+
+    /// Finds the registration of a running build
+    pub fn find(build_id: &BuildId) -> io::Result<Self> {
+        let file = Self::file(build_id);
+        let contents = std::fs::read_to_string(&file).map_err(|e| {
+            if e.kind() == io::ErrorKind::NotFound {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no running build with id {}", build_id),
+                )
+            } else {
+                e
+            }
+        })?;
+        serde_json::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// All builds that are registered as running
+    pub fn running() -> Vec<Self> {
+        let dir = match std::fs::read_dir(ASSEMBLE_HOME.path().join(BUILDS_DIR)) {
+            Ok(dir) => dir,
+            Err(_) => return vec![],
+        };
+        dir.filter_map(|entry| entry.ok())
+            .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+            .filter_map(|contents| serde_json::from_str(&contents).ok())
+            .collect()
+    }
+
+    /// Sends a request to the build and waits for its response
+    pub fn send(&self, request: &ControlRequest) -> io::Result<ControlResponse> {
+        let stream = TcpStream::connect_timeout(&self.address, RESPONSE_TIMEOUT)?;
+        stream.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+        let mut writer = stream.try_clone()?;
+        writeln!(writer, "{}", serde_json::to_string(request)?)?;
+
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line)?;
+        serde_json::from_str(&line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Requests that a running build is cancelled. Returns the status of the build after the request.
+pub fn request_cancel(build_id: &BuildId) -> io::Result<ControlResponse> {
+    let requested_by = format!("process {}", std::process::id());
+    BuildRegistration::find(build_id)?.send(&ControlRequest::Cancel { requested_by })
+}
+
+/// The control endpoint of a running build. The build is unregistered and the endpoint is closed
+/// when this is dropped.
+#[derive(Debug)]
+pub struct ControlServer {
+    registration: BuildRegistration,
+    stopped: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ControlServer {
+    /// Opens the control endpoint of a build and registers it
+    pub fn start(build_id: BuildId, token: CancellationToken) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let registration = BuildRegistration {
+            build_id,
+            pid: std::process::id(),
+            address: listener.local_addr()?,
+        };
+
+        let file = BuildRegistration::file(&registration.build_id);
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&file, serde_json::to_string(&registration)?)?;
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stopped = stopped.clone();
+            let build_id = registration.build_id.clone();
+            thread::Builder::new()
+                .name("build-control".to_string())
+                .spawn(move || {
+                    for stream in listener.incoming() {
+                        if stopped.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        match stream {
+                            Ok(stream) => {
+                                if let Err(e) = serve(stream, &build_id, &token) {
+                                    debug!("control connection closed with error: {}", e);
+                                }
+                            }
+                            Err(e) => debug!("could not accept control connection: {}", e),
+                        }
+                    }
+                })?
+        };
+        debug!(
+            "build {} accepting control requests on {}",
+            registration.build_id, registration.address
+        );
+
+        Ok(Self {
+            registration,
+            stopped,
+            handle: Some(handle),
+        })
+    }
+
+    /// The registration of the build
+    pub fn registration(&self) -> &BuildRegistration {
+        &self.registration
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        let _ = std::fs::remove_file(BuildRegistration::file(&self.registration.build_id));
+        // wake the listener so it sees that it was stopped
+        let _ = TcpStream::connect(self.registration.address);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn serve(stream: TcpStream, build_id: &BuildId, token: &CancellationToken) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        let response = match serde_json::from_str::<ControlRequest>(&line) {
+            Ok(ControlRequest::Cancel { requested_by }) => {
+                if token.cancel(CancellationReason::Requested {
+                    by: requested_by.clone(),
+                }) {
+                    warn!(
+                        "cancelling build after running tasks finish, as requested by {}",
+                        requested_by
+                    );
+                }
+                status(build_id, token)
+            }
+            Ok(ControlRequest::Status) => status(build_id, token),
+            Err(e) => ControlResponse::Error {
+                message: e.to_string(),
+            },
+        };
+        writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+    }
+    Ok(())
+}
+
+fn status(build_id: &BuildId, token: &CancellationToken) -> ControlResponse {
+    ControlResponse::Status {
+        build_id: build_id.clone(),
+        cancelled: token.reason().map(|reason| reason.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_over_control_endpoint() {
+        let token = CancellationToken::new();
+        let build_id = BuildId::random();
+        let server = ControlServer::start(build_id.clone(), token.clone()).unwrap();
+        let registration = BuildRegistration::find(&build_id).unwrap();
+        assert_eq!(&registration, server.registration());
+
+        assert_eq!(
+            registration.send(&ControlRequest::Status).unwrap(),
+            ControlResponse::Status {
+                build_id: build_id.clone(),
+                cancelled: None
+            }
+        );
+        let response = registration
+            .send(&ControlRequest::Cancel {
+                requested_by: "test".to_string(),
+            })
+            .unwrap();
+        assert!(matches!(
+            response,
+            ControlResponse::Status {
+                cancelled: Some(_),
+                ..
+            }
+        ));
+        assert_eq!(
+            token.reason(),
+            Some(CancellationReason::Requested {
+                by: "test".to_string()
+            })
+        );
+
+        drop(server);
+        assert!(BuildRegistration::find(&build_id).is_err());
+    }
+
+    #[test]
+    fn build_ids_are_file_names() {
+        assert!(BuildId::new("ide-build_1").is_ok());
+        assert!(BuildId::new("../escape").is_err());
+        assert!(BuildId::new("").is_err());
+    }
+}
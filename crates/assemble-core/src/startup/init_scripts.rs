@@ -0,0 +1,231 @@
+//! Init scripts, which configure every build on a machine.
+//!
+//! Init scripts are evaluated before the settings of a build are discovered. They're found in the
+//! [`INIT_SCRIPTS_DIR`](INIT_SCRIPTS_DIR) directory of `ASSEMBLE_HOME`, and evaluated in the order
+//! of their file names, followed by the scripts given with `--init-script`.
+//!
+//! The language of an init script is decided by its extension. YAML init scripts are declarative,
+//! and evaluated here:
+//! ```yaml
+//! properties:
+//!   org.example.mirror: https://mirror.example.com
+//! plugin_repositories:
+//!   - https://plugins.example.com
+//! ```
+//! Other languages are evaluated by the builder of the build, which may support fewer languages.
+//! Every script produces [`InitActions`](InitActions), which are applied to the start parameters of
+//! the build.
+
+use crate::startup::invocation::StartParameter;
+use crate::startup::properties::Properties;
+use crate::ASSEMBLE_HOME;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// The directory within `ASSEMBLE_HOME` init scripts are found in
+pub const INIT_SCRIPTS_DIR: &str = "init.d";
+
+/// The language an init script is written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InitScriptLang {
+    /// A declarative yaml file
+    Yaml,
+    /// A javascript file
+    Js,
+    /// A lua file
+    Lua,
+}
+
+impl InitScriptLang {
+    /// The language of a script, based on its extension
+    pub fn of<P: AsRef<Path>>(path: P) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "yaml" | "yml" => Some(InitScriptLang::Yaml),
+            "js" => Some(InitScriptLang::Js),
+            "lua" => Some(InitScriptLang::Lua),
+            _ => None,
+        }
+    }
+}
+
+impl Display for InitScriptLang {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InitScriptLang::Yaml => write!(f, "yaml"),
+            InitScriptLang::Js => write!(f, "javascript"),
+            InitScriptLang::Lua => write!(f, "lua"),
+        }
+    }
+}
+
+/// An init script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitScript {
+    path: PathBuf,
+    lang: InitScriptLang,
+    explicit: bool,
+}
+
+impl InitScript {
+    /// The path of the script
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The language of the script
+    pub fn lang(&self) -> InitScriptLang {
+        self.lang
+    }
+
+    /// Whether the script was given on the command line, instead of found in `ASSEMBLE_HOME`
+    pub fn is_explicit(&self) -> bool {
+        self.explicit
+    }
+
+    /// Evaluates a yaml init script
+    pub fn evaluate_yaml(&self) -> Result<InitActions, InitScriptError> {
+        let file = std::fs::File::open(&self.path)?;
+        Ok(serde_yaml::from_reader(file)?)
+    }
+}
+
+/// An init script couldn't be evaluated
+#[derive(Debug, thiserror::Error)]
+pub enum InitScriptError {
+    #[error("{0:?} is not an init script. Init scripts must end with .yaml, .yml, .js or .lua")]
+    UnknownLanguage(PathBuf),
+    #[error("{lang} init scripts are not supported by this build ({path:?})")]
+    Unsupported { path: PathBuf, lang: InitScriptLang },
+    #[error("invalid plugin repository: {0}")]
+    InvalidRepository(#[from] url::ParseError),
+    #[error(transparent)]
+    Yaml(#[from] serde_yaml::Error),
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+}
+
+/// Finds the init scripts of a build: the scripts in `ASSEMBLE_HOME`, sorted by file name,
+/// followed by the explicit scripts in the order they were given. Files in `ASSEMBLE_HOME` that
+/// aren't init scripts are ignored, while an explicit script that isn't an init script is an
+/// error.
+pub fn init_scripts(explicit: &[PathBuf]) -> Result<Vec<InitScript>, InitScriptError> {
+    init_scripts_in(&ASSEMBLE_HOME.path().join(INIT_SCRIPTS_DIR), explicit)
+}
+
+fn init_scripts_in(dir: &Path, explicit: &[PathBuf]) -> Result<Vec<InitScript>, InitScriptError> {
+    let mut found = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<_>>>()?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+        Err(e) => return Err(e.into()),
+    };
+    found.sort();
+
+    let mut scripts = found
+        .into_iter()
+        .filter(|path| path.is_file())
+        .filter_map(|path| {
+            InitScriptLang::of(&path).map(|lang| InitScript {
+                path,
+                lang,
+                explicit: false,
+            })
+        })
+        .collect::<Vec<_>>();
+    for path in explicit {
+        let lang = InitScriptLang::of(path)
+            .ok_or_else(|| InitScriptError::UnknownLanguage(path.clone()))?;
+        scripts.push(InitScript {
+            path: path.clone(),
+            lang,
+            explicit: true,
+        });
+    }
+    Ok(scripts)
+}
+
+/// What an init script does to a build
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct InitActions {
+    /// Default values of project properties. Properties set anywhere else take precedence.
+    pub properties: Properties,
+    /// Repositories plugins can be resolved from
+    pub plugin_repositories: Vec<Url>,
+}
+
+impl InitActions {
+    /// Adds a repository plugins can be resolved from
+    pub fn plugin_repository(&mut self, url: &str) -> Result<(), InitScriptError> {
+        self.plugin_repositories.push(Url::parse(url)?);
+        Ok(())
+    }
+
+    /// Applies these actions to the start parameters of a build
+    pub fn apply_to(self, start_parameter: &mut StartParameter) {
+        for (key, value) in self.properties {
+            start_parameter.set_default_property(key, value);
+        }
+        for repository in self.plugin_repositories {
+            start_parameter.add_plugin_repository(repository);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn scripts_are_ordered_by_name_then_explicit() {
+        let dir = TempDir::new().unwrap();
+        for name in ["20-b.js", "10-a.yaml", "notes.txt", "30-c.lua"] {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
+        let scripts = init_scripts_in(dir.path(), &[PathBuf::from("extra.yml")]).unwrap();
+        let names = scripts
+            .iter()
+            .map(|script| script.path().file_name().unwrap().to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["10-a.yaml", "20-b.js", "30-c.lua", "extra.yml"]);
+        assert!(scripts[3].is_explicit());
+        assert_eq!(scripts[2].lang(), InitScriptLang::Lua);
+
+        assert!(init_scripts_in(dir.path(), &[PathBuf::from("notes.txt")]).is_err());
+    }
+
+    #[test]
+    fn yaml_init_script() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("init.yaml");
+        std::fs::write(
+            &path,
+            "properties:\n  mirror: https://mirror.example.com\n  offline:\nplugin_repositories:\n  - https://plugins.example.com\n",
+        )
+        .unwrap();
+        let script = init_scripts_in(dir.path(), &[]).unwrap().remove(0);
+        let actions = script.evaluate_yaml().unwrap();
+        assert_eq!(
+            actions.properties["mirror"].as_deref(),
+            Some("https://mirror.example.com")
+        );
+        assert_eq!(actions.properties["offline"], None);
+        assert_eq!(actions.plugin_repositories.len(), 1);
+
+        let mut start_parameter = StartParameter::new();
+        start_parameter
+            .properties_mut()
+            .insert("mirror".to_string(), Some("cli".to_string()));
+        actions.apply_to(&mut start_parameter);
+        start_parameter.load_properties(dir.path()).unwrap();
+        assert_eq!(
+            start_parameter.properties()["mirror"].as_deref(),
+            Some("cli")
+        );
+        assert!(start_parameter.properties().contains_key("offline"));
+    }
+}
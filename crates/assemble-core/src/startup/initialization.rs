@@ -1,13 +1,19 @@
 //! Handles managing and monitoring build initialization
+pub mod composite;
 mod descriptor;
+mod rules;
 mod settings;
 
 use crate::private::Sealed;
 use crate::project::shared::SharedProject;
 use crate::project::ProjectResult;
+use crate::startup::invocation::AssembleAware;
+use crate::startup::properties::{project_properties, Properties};
 use crate::Project;
+pub use composite::{ConfiguredBuild, IncludedBuild};
 pub use descriptor::*;
 use parking_lot::RwLock;
+pub use rules::*;
 pub use settings::{Settings, SettingsAware};
 use std::sync::{Arc, Weak};
 
@@ -30,22 +36,39 @@ fn create_project(
     settings: &Arc<RwLock<Settings>>,
     descriptor: &ProjectDescriptor,
     parent: &SharedProject,
+    properties: &Properties,
+    overrides: Option<&Properties>,
 ) -> ProjectResult<()> {
     let ref root = parent.with(|p| p.root_project());
+    let mut properties = properties.clone();
+    if let Some(overrides) = overrides {
+        properties.extend(project_properties(descriptor.directory())?);
+        properties.extend(overrides.clone());
+    }
     parent.with_mut(|parent| {
-        parent.subproject_in(descriptor.name(), descriptor.directory(), |p| Ok(()))
+        parent.subproject_in(descriptor.name(), descriptor.directory(), |p| {
+            set_properties(p, &properties);
+            Ok(())
+        })
     })?;
     let output = parent.with(|parent| parent.get_subproject(descriptor.name()).cloned())?;
 
     settings.with_settings(|settings_ref| -> ProjectResult<()> {
         for child in settings_ref.children_projects(descriptor) {
-            create_project(settings, child, &output)?;
+            create_project(settings, child, &output, &properties, overrides)?;
         }
         Ok(())
     })?;
     Ok(())
 }
 
+/// Sets the properties of the build on a project
+fn set_properties(project: &mut Project, properties: &Properties) {
+    for (key, value) in properties {
+        project.set_property(key.clone(), value.clone());
+    }
+}
+
 fn create_root_project(
     settings: &Arc<RwLock<Settings>>,
     descriptor: &ProjectDescriptor,
@@ -56,10 +79,22 @@ fn create_root_project(
         None,
         Some(Arc::downgrade(settings)),
     )?;
+    let rules = settings.with_settings(|s| s.project_rules().clone());
+    let (properties, overrides) = settings.with_assemble(|assemble| {
+        let start_parameter = assemble.start_parameter();
+        (
+            start_parameter.properties().clone(),
+            start_parameter.property_overrides().cloned(),
+        )
+    });
+    output.with_mut(|project| {
+        set_properties(project, &properties);
+        rules.apply_to(project)
+    })?;
 
     settings.with_settings(|settings_ref| -> ProjectResult<()> {
         for child in settings_ref.children_projects(descriptor) {
-            create_project(settings, child, &output)?;
+            create_project(settings, child, &output, &properties, overrides.as_ref())?;
         }
         Ok(())
     })?;
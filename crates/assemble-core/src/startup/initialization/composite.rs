@@ -0,0 +1,239 @@
+//! Composite builds, where a build includes other builds.
+//!
+//! Builds are included with [`Settings::include_build`](super::Settings::include_build). An
+//! included build has its own settings, and is configured in isolation before the projects of the
+//! including build are created. Once configured, its root project can be reached from the
+//! including build by the name of the included build, so tasks of the including build can depend
+//! on tasks such as `:library:jar`, where `library` is the name of the included build. The name of
+//! an included build is the name of its root project, which must differ from the root project of
+//! the including build and from every other included build.
+//!
+//! An included build can also declare the modules it provides. Dependencies on those modules in
+//! any project of the including build are substituted by the project of the included build
+//! providing the module.
+
+use crate::dependencies::project_dependency::{ProjectDependency, ProjectDependencyPlugin};
+use crate::plugins::PluginAware;
+use crate::project::error::ProjectError;
+use crate::project::shared::SharedProject;
+use crate::project::{GetProjectId, ProjectResult};
+use crate::startup::initialization::{ProjectRule, ProjectRuleScope, Settings};
+use crate::Project;
+use parking_lot::RwLock;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A build included in another build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncludedBuild {
+    name: Option<String>,
+    root_dir: PathBuf,
+    substitutions: Vec<(String, String)>,
+}
+
+impl IncludedBuild {
+    /// Creates an included build in a directory
+    pub fn new<P: AsRef<Path>>(root_dir: P) -> Self {
+        Self {
+            name: None,
+            root_dir: root_dir.as_ref().to_path_buf(),
+            substitutions: vec![],
+        }
+    }
+
+    /// The name given to the included build, if any. Otherwise the included build is named by its
+    /// own settings.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Names the included build, overriding the name of its root project
+    pub fn set_name<S: AsRef<str>>(&mut self, name: S) -> &mut Self {
+        self.name = Some(name.as_ref().to_string());
+        self
+    }
+
+    /// The root directory of the included build
+    pub fn root_dir(&self) -> &Path {
+        &self.root_dir
+    }
+
+    /// Declares that a project of the included build provides a module. `project` is a path
+    /// relative to the root project of the included build.
+    ///
+    /// # Example
+    /// ```ignore
+    /// settings
+    ///     .include_build("../library")
+    ///     .substitute("org.example:library", "core");
+    /// ```
+    pub fn substitute<M: AsRef<str>, P: AsRef<str>>(&mut self, module: M, project: P) -> &mut Self {
+        self.substitutions
+            .push((module.as_ref().to_string(), project.as_ref().to_string()));
+        self
+    }
+
+    /// The modules provided by the included build, and the projects providing them
+    pub fn substitutions(&self) -> &[(String, String)] {
+        &self.substitutions
+    }
+}
+
+/// An included build once it has been configured
+#[derive(Debug, Clone)]
+pub struct ConfiguredBuild {
+    settings: Arc<RwLock<Settings>>,
+    root_project: SharedProject,
+}
+
+impl ConfiguredBuild {
+    /// Creates a configured build from its settings and root project
+    pub fn new(settings: &Arc<RwLock<Settings>>, root_project: &SharedProject) -> Self {
+        Self {
+            settings: settings.clone(),
+            root_project: root_project.clone(),
+        }
+    }
+
+    /// The settings of the included build
+    pub fn settings(&self) -> &Arc<RwLock<Settings>> {
+        &self.settings
+    }
+
+    /// The root project of the included build
+    pub fn root_project(&self) -> &SharedProject {
+        &self.root_project
+    }
+
+    /// The name of the included build
+    pub fn name(&self) -> String {
+        self.root_project.project_id().this().to_string()
+    }
+
+    /// Creates the rule that substitutes the projects of this build for the modules they provide
+    pub(super) fn substitution_rule(
+        &self,
+        included: &IncludedBuild,
+    ) -> ProjectResult<Option<ProjectRule>> {
+        if included.substitutions().is_empty() {
+            return Ok(None);
+        }
+        let substitutions = included
+            .substitutions()
+            .iter()
+            .map(
+                |(module, path)| -> ProjectResult<(String, ProjectDependency)> {
+                    let project = ProjectDependency::new(&self.root_project, path, None)
+                        .map_err(ProjectError::custom)?;
+                    Ok((module.clone(), project))
+                },
+            )
+            .collect::<ProjectResult<Vec<_>>>()?;
+
+        Ok(Some(ProjectRule::new(
+            ProjectRuleScope::AllProjects,
+            move |project: &mut Project| {
+                project.apply_plugin::<ProjectDependencyPlugin>()?;
+                for (module, dependency) in &substitutions {
+                    project
+                        .configurations()
+                        .substitutions()
+                        .substitute_project(module, dependency.clone());
+                }
+                Ok(())
+            },
+        )))
+    }
+}
+
+/// Checks that an included build can be added to the build of `settings`
+pub(super) fn check_included_name(settings: &Settings, name: &str) -> ProjectResult {
+    if settings.root_project().name() == name {
+        return Err(ProjectError::custom(format!(
+            "included build {:?} has the same name as the root project of the including build",
+            name
+        ))
+        .into());
+    }
+    if settings.included_build(name).is_some() {
+        return Err(
+            ProjectError::custom(format!("a build named {:?} is already included", name)).into(),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::finder::ProjectFinder;
+    use crate::startup::initialization::CreateProject;
+    use crate::startup::invocation::Assemble;
+    use tempfile::TempDir;
+
+    fn settings(assemble: &Arc<RwLock<Assemble>>, dir: &Path, name: &str) -> Settings {
+        let mut settings = Settings::new(
+            assemble,
+            dir.to_path_buf(),
+            dir.join("settings.assemble.js"),
+        );
+        settings.root_project_mut().set_name(name);
+        settings
+    }
+
+    #[test]
+    fn included_builds_are_found_by_name() {
+        let dir = TempDir::new().unwrap();
+        let assemble = Arc::new(RwLock::new(Assemble::default()));
+
+        let mut library = settings(&assemble, &dir.path().join("library"), "library");
+        library.include("core");
+        let library = Arc::new(RwLock::new(library));
+        let library_root = library.create_project().unwrap();
+
+        let mut app = settings(&assemble, &dir.path().join("app"), "app");
+        let included = app
+            .include_build("../library")
+            .substitute("org.example:library", "core")
+            .clone();
+        assert_eq!(app.include_build("../library"), &included);
+        assert_eq!(app.included_builds().len(), 1);
+        app.add_configured_build(&included, ConfiguredBuild::new(&library, &library_root))
+            .unwrap();
+        assert!(
+            app.add_configured_build(&included, ConfiguredBuild::new(&library, &library_root))
+                .is_err(),
+            "a build can only be included once"
+        );
+        let app = Arc::new(RwLock::new(app));
+        let app_root = app.create_project().unwrap();
+
+        let finder = ProjectFinder::new(&app_root);
+        assert_eq!(finder.find(":library").unwrap().project_id(), ":library");
+        assert_eq!(
+            finder.find(":library:core").unwrap().project_id(),
+            ":library:core"
+        );
+        assert_eq!(app_root.with(|p| p.included_builds()).len(), 1);
+        assert!(!app_root.with(|p| p.configurations().substitutions().is_empty()));
+    }
+
+    #[test]
+    fn included_build_cant_share_root_name() {
+        let dir = TempDir::new().unwrap();
+        let assemble = Arc::new(RwLock::new(Assemble::default()));
+
+        let other = Arc::new(RwLock::new(settings(
+            &assemble,
+            &dir.path().join("other"),
+            "app",
+        )));
+        let other_root = other.create_project().unwrap();
+
+        let mut app = settings(&assemble, &dir.path().join("app"), "app");
+        let included = app.include_build("../other").clone();
+        assert!(app
+            .add_configured_build(&included, ConfiguredBuild::new(&other, &other_root))
+            .is_err());
+    }
+}
@@ -13,11 +13,15 @@ use std::io::Write as _;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+mod validation;
+pub use validation::*;
+
 /// A project descriptor is used to define projects.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct ProjectDescriptor {
     build_file: ProjectDescriptorLocation,
     name: String,
+    declared_in: Option<PathBuf>,
 }
 
 impl Display for ProjectDescriptor {
@@ -30,7 +34,11 @@ impl ProjectDescriptor {
     /// creates a new project descriptor
     fn new<S: AsRef<str>>(name: S, build_file: ProjectDescriptorLocation) -> Self {
         let name = name.as_ref().to_string();
-        Self { build_file, name }
+        Self {
+            build_file,
+            name,
+            declared_in: None,
+        }
     }
 
     /// if this only has the directory known, this sets the file name
@@ -62,6 +70,11 @@ impl ProjectDescriptor {
         }
     }
 
+    /// Gets the settings file that declared this project, if known
+    pub fn declared_in(&self) -> Option<&Path> {
+        self.declared_in.as_deref()
+    }
+
     /// Gets the directory this project is contained in
     pub fn directory(&self) -> &Path {
         match &self.build_file {
@@ -110,6 +123,7 @@ pub struct ProjectGraph {
     project_dir: PathBuf,
     root_project: NodeIndex,
     default_build_script_file: Option<String>,
+    settings_file: Option<PathBuf>,
 }
 
 impl ProjectGraph {
@@ -138,9 +152,21 @@ impl ProjectGraph {
             project_dir: project_dir.as_ref().to_path_buf(),
             root_project: idx,
             default_build_script_file: None,
+            settings_file: None,
         }
     }
 
+    /// Sets the settings file projects are being declared in. Projects added after this is set
+    /// remember that they were declared in this file.
+    pub(crate) fn set_settings_file<P: AsRef<Path>>(&mut self, settings_file: P) {
+        let settings_file = settings_file.as_ref().to_path_buf();
+        let root = &mut self.graph[self.root_project];
+        if root.declared_in.is_none() {
+            root.declared_in = Some(settings_file.clone());
+        }
+        self.settings_file = Some(settings_file);
+    }
+
     /// Gets the root project descriptor
     pub fn root_project(&self) -> &ProjectDescriptor {
         &self.graph[self.root_project]
@@ -190,7 +216,8 @@ impl ProjectGraph {
             None => ProjectDescriptorLocation::KnownDirectory(dir),
             Some(s) => ProjectDescriptorLocation::KnownFile(dir.join(s)),
         };
-        let pd = ProjectDescriptor::new(name, location);
+        let mut pd = ProjectDescriptor::new(name, location);
+        pd.declared_in = self.settings_file.clone();
 
         let node = self.graph.add_node(pd);
         self.graph.add_edge(parent, node, ());
@@ -240,7 +267,11 @@ impl ProjectGraph {
             .node_indices()
             .find(|&idx| &self.graph[idx] == desc)
             .unwrap();
+        self.project_id_of(start)
+    }
 
+    /// Gets the id of the project at an index in the graph
+    fn project_id_of(&self, start: NodeIndex) -> ProjectId {
         let mut queue = VecDeque::new();
         queue.push_front(self.graph[start].name.clone());
         let mut ptr = start;
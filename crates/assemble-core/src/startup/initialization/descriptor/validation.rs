@@ -0,0 +1,275 @@
+//! Validation of the project graph, performed once settings are evaluated and before any project
+//! is configured.
+
+use super::ProjectGraph;
+use crate::prelude::ProjectId;
+use petgraph::prelude::*;
+use std::fmt::{Display, Formatter};
+use std::path::{Component, Path, PathBuf};
+
+/// The name of the build directory of a project, by convention
+const CONVENTIONAL_BUILD_DIR: &str = "build";
+
+/// Where a project in the project graph came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectLocation {
+    /// The id of the project
+    pub id: ProjectId,
+    /// The directory of the project
+    pub directory: PathBuf,
+    /// The settings file the project was declared in, if known
+    pub declared_in: Option<PathBuf>,
+}
+
+impl Display for ProjectLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {:?}", self.id, self.directory)?;
+        if let Some(declared_in) = &self.declared_in {
+            write!(f, " (declared in {:?})", declared_in)?;
+        }
+        Ok(())
+    }
+}
+
+/// A problem with the layout of the project graph
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProjectGraphProblem {
+    /// Several projects use the same directory
+    DuplicateDirectory {
+        /// The shared directory
+        directory: PathBuf,
+        /// The projects using the directory
+        projects: Vec<ProjectLocation>,
+    },
+    /// A project is inside the build directory of another project
+    InsideBuildDir {
+        /// The nested project
+        project: ProjectLocation,
+        /// The project owning the build directory
+        owner: ProjectLocation,
+    },
+    /// A project's directory contains the directory of one of its ancestors, so including the
+    /// project would include the ancestor again
+    IncludeCycle {
+        /// The projects forming the cycle, starting at the ancestor
+        cycle: Vec<ProjectLocation>,
+    },
+}
+
+impl Display for ProjectGraphProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProjectGraphProblem::DuplicateDirectory {
+                directory,
+                projects,
+            } => {
+                writeln!(f, "multiple projects use the directory {:?}:", directory)?;
+                for project in projects {
+                    writeln!(f, "    - {}", project)?;
+                }
+                Ok(())
+            }
+            ProjectGraphProblem::InsideBuildDir { project, owner } => {
+                writeln!(
+                    f,
+                    "project {} is inside the build directory of {}",
+                    project.id, owner.id
+                )?;
+                writeln!(f, "    - {}", project)?;
+                writeln!(f, "    - {}", owner)
+            }
+            ProjectGraphProblem::IncludeCycle { cycle } => {
+                writeln!(f, "project include cycle:")?;
+                for project in cycle {
+                    writeln!(f, "    - {}", project)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The project graph has an invalid layout
+#[derive(Debug, Clone, thiserror::Error)]
+pub struct InvalidProjectGraph {
+    problems: Vec<ProjectGraphProblem>,
+}
+
+impl InvalidProjectGraph {
+    /// The problems found in the project graph
+    pub fn problems(&self) -> &[ProjectGraphProblem] {
+        &self.problems
+    }
+}
+
+impl Display for InvalidProjectGraph {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "invalid project layout ({} problem(s) found):",
+            self.problems.len()
+        )?;
+        for problem in &self.problems {
+            write!(f, "  {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl ProjectGraph {
+    /// Checks the project graph for duplicate project directories, projects nested in the build
+    /// directories of other projects, and include cycles.
+    pub fn validate(&self) -> Result<(), InvalidProjectGraph> {
+        let mut problems = vec![];
+        let nodes = self.graph.node_indices().collect::<Vec<_>>();
+        let location = |idx: NodeIndex| {
+            let descriptor = &self.graph[idx];
+            ProjectLocation {
+                id: self.project_id_of(idx),
+                directory: descriptor.directory().to_path_buf(),
+                declared_in: descriptor.declared_in.clone(),
+            }
+        };
+        let dir = |idx: NodeIndex| normalize(self.graph[idx].directory());
+
+        let mut in_cycle = vec![];
+        for &node in &nodes {
+            let node_dir = dir(node);
+            let mut ancestors = vec![];
+            let mut ptr = node;
+            while let Some(parent) = self.graph.edges_directed(ptr, Direction::Incoming).next() {
+                ptr = parent.source();
+                ancestors.push(ptr);
+                if dir(ptr).starts_with(&node_dir) {
+                    let mut cycle = ancestors.iter().rev().copied().collect::<Vec<_>>();
+                    cycle.push(node);
+                    in_cycle.push(node);
+                    problems.push(ProjectGraphProblem::IncludeCycle {
+                        cycle: cycle.into_iter().map(location).collect(),
+                    });
+                    break;
+                }
+            }
+        }
+
+        let mut reported = vec![];
+        for &node in &nodes {
+            if in_cycle.contains(&node) || reported.contains(&node) {
+                continue;
+            }
+            let node_dir = dir(node);
+            let duplicates = nodes
+                .iter()
+                .copied()
+                .filter(|&other| !in_cycle.contains(&other) && dir(other) == node_dir)
+                .collect::<Vec<_>>();
+            if duplicates.len() > 1 {
+                reported.extend(duplicates.iter().copied());
+                problems.push(ProjectGraphProblem::DuplicateDirectory {
+                    directory: self.graph[node].directory().to_path_buf(),
+                    projects: duplicates.into_iter().map(location).collect(),
+                });
+            }
+        }
+
+        for &node in &nodes {
+            let node_dir = dir(node);
+            for &owner in &nodes {
+                if owner != node && node_dir.starts_with(dir(owner).join(CONVENTIONAL_BUILD_DIR)) {
+                    problems.push(ProjectGraphProblem::InsideBuildDir {
+                        project: location(node),
+                        owner: location(owner),
+                    });
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(InvalidProjectGraph { problems })
+        }
+    }
+}
+
+/// Lexically normalizes a path, removing `.` components and resolving `..` components
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph() -> ProjectGraph {
+        let mut graph = ProjectGraph::new("root");
+        graph.set_settings_file("root/settings.assemble");
+        graph
+    }
+
+    #[test]
+    fn valid_graph() {
+        let mut graph = graph();
+        graph.project("list", |builder| {
+            builder.project("linked", |_| {});
+        });
+        graph.project("map", |_| {});
+        assert!(graph.validate().is_ok());
+    }
+
+    #[test]
+    fn duplicate_directories() {
+        let mut graph = graph();
+        graph.project("first", |builder| builder.set_dir("root/shared"));
+        graph.project("second", |builder| builder.set_dir("root/./shared"));
+
+        let error = graph.validate().unwrap_err();
+        assert!(matches!(
+            &error.problems()[0],
+            ProjectGraphProblem::DuplicateDirectory { projects, .. } if projects.len() == 2
+        ));
+        assert!(error.to_string().contains("settings.assemble"));
+    }
+
+    #[test]
+    fn project_inside_build_dir() {
+        let mut graph = graph();
+        graph.project("app", |_| {});
+        graph.project("generated", |builder| {
+            builder.set_dir("root/app/build/generated")
+        });
+
+        let error = graph.validate().unwrap_err();
+        assert!(matches!(
+            &error.problems()[0],
+            ProjectGraphProblem::InsideBuildDir { project, owner }
+                if project.directory.ends_with("generated") && owner.directory.ends_with("app")
+        ));
+    }
+
+    #[test]
+    fn include_cycle() {
+        let mut graph = graph();
+        graph.project("child", |builder| {
+            builder.project("back", |builder| builder.set_dir("root/child/.."));
+        });
+
+        let error = graph.validate().unwrap_err();
+        assert!(matches!(
+            &error.problems()[0],
+            ProjectGraphProblem::IncludeCycle { cycle } if cycle.len() == 2
+        ));
+    }
+}
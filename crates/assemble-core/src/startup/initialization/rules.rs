@@ -0,0 +1,205 @@
+//! Configuration rules that apply to many projects.
+//!
+//! Rules are registered on the [`Settings`](super::Settings) with
+//! [`allprojects`](super::Settings::allprojects) and [`subprojects`](super::Settings::subprojects).
+//! They are not applied over a snapshot of the projects that exist when they are registered, but
+//! to each project as it's created, including projects created later by build logic.
+//!
+//! # Ordering
+//! - Rules are applied in the order they were registered.
+//! - A project has all rules applied to it before any of its subprojects are created.
+//! - Rules are applied before any project-local configuration, such as the build script of the
+//!   project or the configuration function passed to [`Project::subproject`](Project::subproject).
+//!
+//! Rules are applied while the parent of the project is being configured, so a rule must not
+//! access the parent project.
+
+use crate::project::{GetProjectId, ProjectResult};
+use crate::Project;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+type RuleAction = Arc<dyn Fn(&mut Project) -> ProjectResult + Send + Sync>;
+
+/// The projects a rule applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectRuleScope {
+    /// Applies to every project, including the root project
+    AllProjects,
+    /// Applies to every project except the root project
+    Subprojects,
+}
+
+impl ProjectRuleScope {
+    /// Checks whether a project is in this scope
+    pub fn contains(&self, project: &Project) -> bool {
+        match self {
+            ProjectRuleScope::AllProjects => true,
+            ProjectRuleScope::Subprojects => !project.is_root(),
+        }
+    }
+}
+
+/// A rule that configures every project in its scope
+#[derive(Clone)]
+pub struct ProjectRule {
+    scope: ProjectRuleScope,
+    action: RuleAction,
+}
+
+impl ProjectRule {
+    /// Creates a new rule
+    pub fn new<F>(scope: ProjectRuleScope, action: F) -> Self
+    where
+        F: Fn(&mut Project) -> ProjectResult + Send + Sync + 'static,
+    {
+        Self {
+            scope,
+            action: Arc::new(action),
+        }
+    }
+
+    /// The scope of the rule
+    pub fn scope(&self) -> ProjectRuleScope {
+        self.scope
+    }
+
+    /// Applies this rule to a project if it's in scope
+    pub fn apply(&self, project: &mut Project) -> ProjectResult {
+        if self.scope.contains(project) {
+            (self.action)(project)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Debug for ProjectRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProjectRule")
+            .field("scope", &self.scope)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The rules registered for a build, in the order they were registered
+#[derive(Debug, Clone, Default)]
+pub struct ProjectRules {
+    rules: Vec<ProjectRule>,
+}
+
+impl ProjectRules {
+    /// Adds a rule
+    pub fn add(&mut self, rule: ProjectRule) {
+        self.rules.push(rule);
+    }
+
+    /// Applies every rule in scope to a project, in order
+    pub fn apply_to(&self, project: &mut Project) -> ProjectResult {
+        trace!(
+            "applying {} project rules to {}",
+            self.rules.len(),
+            project.id()
+        );
+        for rule in &self.rules {
+            rule.apply(project)?;
+        }
+        Ok(())
+    }
+
+    /// The number of rules
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Whether there are no rules
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::{Plugin, PluginAware};
+    use crate::startup::initialization::{CreateProject, Settings};
+    use crate::startup::invocation::Assemble;
+    use parking_lot::RwLock;
+    use tempfile::TempDir;
+
+    #[test]
+    fn rules_apply_as_projects_are_created() {
+        let dir = TempDir::new().unwrap();
+        let assemble = Arc::new(RwLock::new(Assemble::default()));
+        let mut settings = Settings::new(
+            &assemble,
+            dir.path().to_path_buf(),
+            dir.path().join("settings.assemble.js"),
+        );
+        settings.root_project_mut().set_name("root");
+        settings.include("child");
+        settings.allprojects(|project| {
+            project.set_property("seen".to_string(), "all".to_string());
+            Ok(())
+        });
+        settings.subprojects(|project| {
+            project.set_property("seen".to_string(), "sub".to_string());
+            Ok(())
+        });
+        let settings = Arc::new(RwLock::new(settings));
+
+        let root = settings.create_project().unwrap();
+        let seen = |project: &Project| project.get_property("seen").cloned().flatten();
+        assert_eq!(root.with(seen), Some("all".to_string()));
+        let child = root.with(|p| p.get_subproject("child").cloned()).unwrap();
+        assert_eq!(child.with(seen), Some("sub".to_string()));
+
+        root.with_mut(|p| {
+            p.subproject("later", |later| {
+                assert_eq!(seen(later), Some("sub".to_string()));
+                Ok(())
+            })
+        })
+        .unwrap();
+    }
+
+    #[derive(Default)]
+    struct MarkerPlugin;
+
+    impl Plugin<Project> for MarkerPlugin {
+        fn apply_to(&self, project: &mut Project) -> ProjectResult {
+            project.set_property("marked".to_string(), "true".to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn auto_apply_only_to_matching_projects() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("with")).unwrap();
+        std::fs::write(dir.path().join("with").join("Marker.toml"), "").unwrap();
+        std::fs::create_dir_all(dir.path().join("without")).unwrap();
+
+        let assemble = Arc::new(RwLock::new(Assemble::default()));
+        let mut settings = Settings::new(
+            &assemble,
+            dir.path().to_path_buf(),
+            dir.path().join("settings.assemble.js"),
+        );
+        settings.root_project_mut().set_name("root");
+        settings.include("with");
+        settings.include("without");
+        settings.auto_apply::<MarkerPlugin, _>(|p| p.file_exists("Marker.toml"));
+        let settings = Arc::new(RwLock::new(settings));
+
+        let root = settings.create_project().unwrap();
+        let has_marker = |name: &str| {
+            root.with(|p| p.get_subproject(name).cloned())
+                .unwrap()
+                .with(|p| p.plugin_manager().has_plugin_ty::<MarkerPlugin>())
+        };
+        assert!(has_marker("with"));
+        assert!(!has_marker("without"));
+        assert!(!root.with(|p| p.plugin_manager().has_plugin_ty::<MarkerPlugin>()));
+    }
+}
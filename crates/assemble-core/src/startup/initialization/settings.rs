@@ -1,8 +1,15 @@
-use crate::plugins::PluginAware;
+use crate::plugins::{Plugin, PluginAware};
 use crate::prelude::PluginManager;
 use crate::project::shared::SharedProject;
-use crate::startup::initialization::{ProjectBuilder, ProjectDescriptor, ProjectGraph};
+use crate::project::ProjectResult;
+use crate::startup::initialization::composite::check_included_name;
+use crate::startup::initialization::{
+    ConfiguredBuild, IncludedBuild, ProjectBuilder, ProjectDescriptor, ProjectGraph, ProjectRule,
+    ProjectRuleScope, ProjectRules,
+};
 use crate::startup::invocation::{Assemble, AssembleAware};
+use crate::startup::listeners::{ProjectsEvaluated, ProjectsLoaded};
+use crate::Project;
 use parking_lot::RwLock;
 use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
@@ -31,6 +38,9 @@ pub struct Settings {
     project_graph: ProjectGraph,
     root_dir: PathBuf,
     settings_file: PathBuf,
+    project_rules: ProjectRules,
+    included_builds: Vec<IncludedBuild>,
+    configured_builds: Vec<ConfiguredBuild>,
 }
 
 impl Settings {
@@ -40,12 +50,17 @@ impl Settings {
         root_dir: PathBuf,
         settings_file: PathBuf,
     ) -> Self {
+        let mut project_graph = ProjectGraph::new(root_dir.clone());
+        project_graph.set_settings_file(&settings_file);
         Self {
             assemble: assemble.clone(),
             plugin_manager: PluginManager::new(),
-            project_graph: ProjectGraph::new(root_dir.clone()),
+            project_graph,
             root_dir,
             settings_file,
+            project_rules: ProjectRules::default(),
+            included_builds: vec![],
+            configured_builds: vec![],
         }
     }
 
@@ -120,6 +135,130 @@ impl Settings {
     pub fn project_graph(&self) -> &ProjectGraph {
         &self.project_graph
     }
+
+    /// Adds a rule that configures every project, including the root project, as it's created.
+    ///
+    /// See [`rules`](super::rules) for the order rules are applied in.
+    pub fn allprojects<F>(&mut self, configure: F)
+    where
+        F: Fn(&mut Project) -> ProjectResult + Send + Sync + 'static,
+    {
+        self.project_rules
+            .add(ProjectRule::new(ProjectRuleScope::AllProjects, configure));
+    }
+
+    /// Adds a rule that configures every project except the root project as it's created.
+    ///
+    /// See [`rules`](super::rules) for the order rules are applied in.
+    pub fn subprojects<F>(&mut self, configure: F)
+    where
+        F: Fn(&mut Project) -> ProjectResult + Send + Sync + 'static,
+    {
+        self.project_rules
+            .add(ProjectRule::new(ProjectRuleScope::Subprojects, configure));
+    }
+
+    /// Adds a rule that applies a plugin to every project, including the root project, that
+    /// the condition holds for. The condition is checked as each project is created, before its
+    /// build script runs, so it's best suited to checking the contents of the project directory.
+    ///
+    /// See [`rules`](super::rules) for the order rules are applied in.
+    ///
+    /// # Example
+    /// ```ignore
+    /// settings.auto_apply::<RustPlugin, _>(|p| p.file_exists("Cargo.toml"));
+    /// ```
+    pub fn auto_apply<P, F>(&mut self, condition: F)
+    where
+        P: Plugin<Project> + 'static,
+        F: Fn(&Project) -> bool + Send + Sync + 'static,
+    {
+        self.project_rules.add(ProjectRule::new(
+            ProjectRuleScope::AllProjects,
+            move |project| project.apply_plugin_if::<P, _>(&condition).map(|_| ()),
+        ));
+    }
+
+    /// Runs a function once every project of the build was created, before any project is
+    /// evaluated. Mostly useful to add [before-evaluate](Project::before_evaluate) hooks.
+    pub fn projects_loaded<F>(&mut self, func: F) -> ProjectResult
+    where
+        F: FnMut(&SharedProject) -> ProjectResult + Send + Sync + 'static,
+    {
+        self.assemble
+            .write()
+            .add_listener(ProjectsLoaded::new(func))
+    }
+
+    /// Runs a function once every project of the build was evaluated, after their
+    /// [after-evaluate](Project::after_evaluate) hooks ran.
+    pub fn projects_evaluated<F>(&mut self, func: F) -> ProjectResult
+    where
+        F: FnMut(&SharedProject) -> ProjectResult + Send + Sync + 'static,
+    {
+        self.assemble
+            .write()
+            .add_listener(ProjectsEvaluated::new(func))
+    }
+
+    /// The rules applied to projects as they are created
+    pub fn project_rules(&self) -> &ProjectRules {
+        &self.project_rules
+    }
+
+    /// Includes the build in a directory in this build, making this a composite build. Relative
+    /// paths are relative to the root directory of this build. Including the same directory more
+    /// than once returns the build that's already included.
+    ///
+    /// See [`composite`](super::composite) for how included builds are used.
+    pub fn include_build<P: AsRef<Path>>(&mut self, path: P) -> &mut IncludedBuild {
+        let root_dir = self.root_dir.join(path);
+        let index = match self
+            .included_builds
+            .iter()
+            .position(|build| build.root_dir() == root_dir)
+        {
+            Some(index) => index,
+            None => {
+                self.included_builds.push(IncludedBuild::new(root_dir));
+                self.included_builds.len() - 1
+            }
+        };
+        &mut self.included_builds[index]
+    }
+
+    /// The builds included in this build
+    pub fn included_builds(&self) -> &[IncludedBuild] {
+        &self.included_builds
+    }
+
+    /// Adds an included build once it's configured. Must be called before the projects of this
+    /// build are created, so dependencies on the modules the included build provides are
+    /// substituted in every project.
+    pub fn add_configured_build(
+        &mut self,
+        included: &IncludedBuild,
+        build: ConfiguredBuild,
+    ) -> ProjectResult {
+        check_included_name(self, &build.name())?;
+        if let Some(rule) = build.substitution_rule(included)? {
+            self.project_rules.add(rule);
+        }
+        self.configured_builds.push(build);
+        Ok(())
+    }
+
+    /// Gets a configured included build by its name
+    pub fn included_build(&self, name: &str) -> Option<&ConfiguredBuild> {
+        self.configured_builds
+            .iter()
+            .find(|build| build.name() == name)
+    }
+
+    /// The configured builds included in this build
+    pub fn configured_builds(&self) -> &[ConfiguredBuild] {
+        &self.configured_builds
+    }
 }
 
 /// A type that's aware of the settings value
@@ -1,14 +1,24 @@
 //! Handles standard invoking and monitoring builds
 
+use crate::deprecations::WarningMode;
 use crate::logging::{ConsoleMode, LoggingArgs};
 use crate::plugins::PluginManager;
 use crate::prelude::listeners::TaskExecutionGraphListener;
 use crate::prelude::{PluginAware, SettingsAware};
 use std::backtrace::Backtrace;
 
+use crate::project::error::ProjectError;
+use crate::project::shared::SharedProject;
 use crate::project::ProjectResult;
+use crate::startup::control::BuildId;
 use crate::startup::execution_graph::ExecutionGraph;
 use crate::startup::listeners::{BuildListener, Listener, TaskExecutionListener};
+use crate::startup::profile::BuildProfile;
+use crate::startup::properties::{project_properties, property_overrides, Properties};
+use crate::startup::trust::TrustPolicy;
+use crate::startup::updates::ReleaseChannel;
+use crate::startup::watchdog::BuildTimeouts;
+use crate::task::undeclared_io::UndeclaredIoPolicy;
 use crate::version::{version, Version};
 
 use itertools::Itertools;
@@ -18,9 +28,11 @@ use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::env::current_dir;
 use std::fmt::Debug;
+use std::io;
 use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use url::Url;
 
 /// Provides a wrapper around the assemble instance that's running this build.
 #[derive(Debug)]
@@ -32,6 +44,7 @@ pub struct Assemble {
     version: Version,
     start_parameter: StartParameter,
     graph: RwLock<OnceCell<ExecutionGraph>>,
+    profile: BuildProfile,
 }
 
 impl Assemble {
@@ -45,9 +58,15 @@ impl Assemble {
             version: version(),
             start_parameter: start,
             graph: Default::default(),
+            profile: BuildProfile::new(),
         }
     }
 
+    /// The profile recording the time spent in each phase of this build
+    pub fn profile(&self) -> &BuildProfile {
+        &self.profile
+    }
+
     /// Makes the execution graph available
     pub fn set_execution_graph(&mut self, graph: &ExecutionGraph) -> ProjectResult {
         self.graph
@@ -93,6 +112,10 @@ impl Assemble {
     pub fn settings_evaluated<S: SettingsAware>(&mut self, settings: S) -> ProjectResult {
         trace!("running settings evaluated method in build listeners");
         settings.with_settings(|settings| {
+            settings
+                .project_graph()
+                .validate()
+                .map_err(ProjectError::from)?;
             self.build_listeners
                 .iter_mut()
                 .map(|b| b.settings_evaluated(&settings))
@@ -100,6 +123,22 @@ impl Assemble {
         })
     }
 
+    /// Notifies build listeners that every project of the build was created
+    pub fn projects_loaded(&mut self, root: &SharedProject) -> ProjectResult {
+        trace!("running projects loaded method in build listeners");
+        self.build_listeners
+            .iter_mut()
+            .try_for_each(|b| b.projects_loaded(root))
+    }
+
+    /// Notifies build listeners that every project of the build was evaluated
+    pub fn projects_evaluated(&mut self, root: &SharedProject) -> ProjectResult {
+        trace!("running projects evaluated method in build listeners");
+        self.build_listeners
+            .iter_mut()
+            .try_for_each(|b| b.projects_evaluated(root))
+    }
+
     /// Gets the current version of assemble
     pub fn assemble_version(&self) -> &Version {
         &self.version
@@ -109,6 +148,11 @@ impl Assemble {
     pub fn start_parameter(&self) -> &StartParameter {
         &self.start_parameter
     }
+
+    /// A mutable reference to the start parameter, for changes made before settings are evaluated
+    pub fn start_parameter_mut(&mut self) -> &mut StartParameter {
+        &mut self.start_parameter
+    }
     pub fn current_dir(&self) -> &Path {
         self.start_parameter().current_dir()
     }
@@ -120,6 +164,12 @@ impl Assemble {
     pub fn properties(&self) -> &HashMap<String, Option<String>> {
         &self.start_parameter.properties
     }
+
+    /// Loads the properties files of the build. See
+    /// [`StartParameter::load_properties`](StartParameter::load_properties).
+    pub fn load_properties(&mut self, root_dir: &Path) -> io::Result<()> {
+        self.start_parameter.load_properties(root_dir)
+    }
 }
 
 impl PluginAware for Assemble {
@@ -198,10 +248,29 @@ pub struct StartParameter {
     mode: ConsoleMode,
     project_dir: Option<PathBuf>,
     properties: HashMap<String, Option<String>>,
+    property_overrides: Option<Properties>,
+    default_properties: Properties,
+    init_scripts: Vec<PathBuf>,
+    plugin_repositories: Vec<Url>,
     task_requests: Vec<String>,
     workers: usize,
     backtrace: BacktraceEmit,
     rerun_tasks: bool,
+    write_locks: bool,
+    strict_outputs: bool,
+    warn_overlapping_outputs: bool,
+    undeclared_io: UndeclaredIoPolicy,
+    warning_mode: WarningMode,
+    timeouts: BuildTimeouts,
+    report_dir: Option<PathBuf>,
+    profile: Option<usize>,
+    results_xml: Option<PathBuf>,
+    export_plan: Option<PathBuf>,
+    from_plan: Option<PathBuf>,
+    build_id: BuildId,
+    trust_policy: TrustPolicy,
+    offline: bool,
+    update_channel: Option<ReleaseChannel>,
 }
 
 /// The mechanism to emit the backtrace at
@@ -284,10 +353,29 @@ impl StartParameter {
             mode: ConsoleMode::Auto,
             project_dir: None,
             properties: HashMap::new(),
+            property_overrides: None,
+            default_properties: Properties::new(),
+            init_scripts: vec![],
+            plugin_repositories: vec![],
             task_requests: vec![],
             workers: 0,
             backtrace: BacktraceEmit::None,
             rerun_tasks: false,
+            write_locks: false,
+            strict_outputs: false,
+            warn_overlapping_outputs: false,
+            undeclared_io: UndeclaredIoPolicy::Off,
+            warning_mode: WarningMode::Summary,
+            timeouts: BuildTimeouts::default(),
+            report_dir: None,
+            profile: None,
+            results_xml: None,
+            export_plan: None,
+            from_plan: None,
+            build_id: BuildId::random(),
+            trust_policy: TrustPolicy::Off,
+            offline: false,
+            update_channel: None,
         }
     }
 
@@ -321,6 +409,64 @@ impl StartParameter {
         &mut self.properties
     }
 
+    /// Sets the default value of a project property. Defaults are only used by projects where the
+    /// property isn't set anywhere else.
+    pub fn set_default_property(&mut self, key: impl AsRef<str>, value: Option<String>) {
+        self.default_properties
+            .insert(key.as_ref().to_string(), value);
+    }
+
+    /// The default values of project properties, set by init scripts
+    pub fn default_properties(&self) -> &Properties {
+        &self.default_properties
+    }
+
+    /// Loads properties from the `assemble.properties` files of `ASSEMBLE_HOME` and the root
+    /// project, and from environment variables starting with `ASSEMBLE_`. Properties that are
+    /// already set, such as those from the command line, take precedence, while
+    /// [defaults](Self::set_default_property) are only used if nothing else sets the property. See
+    /// [`properties`](crate::startup::properties) for the full order.
+    ///
+    /// Should be called once the root project directory is known, before settings are evaluated.
+    pub fn load_properties(&mut self, root_dir: &Path) -> io::Result<()> {
+        let overrides = property_overrides(&self.properties)?;
+        let mut properties = self.default_properties.clone();
+        properties.extend(project_properties(root_dir)?);
+        properties.extend(overrides.clone());
+        self.properties = properties;
+        self.property_overrides = Some(overrides);
+        Ok(())
+    }
+
+    /// The properties that take precedence over the `assemble.properties` files of projects, if
+    /// properties files were [loaded](Self::load_properties). Subprojects use the properties of
+    /// their parent, overwritten by their own properties file, then by these.
+    pub fn property_overrides(&self) -> Option<&Properties> {
+        self.property_overrides.as_ref()
+    }
+
+    /// The init scripts given on the command line, evaluated after those in `ASSEMBLE_HOME`
+    pub fn init_scripts(&self) -> &[PathBuf] {
+        &self.init_scripts
+    }
+
+    /// Adds an init script to evaluate before settings are evaluated
+    pub fn add_init_script<P: AsRef<Path>>(&mut self, path: P) {
+        self.init_scripts.push(path.as_ref().to_path_buf());
+    }
+
+    /// The repositories plugins can be resolved from, registered by init scripts
+    pub fn plugin_repositories(&self) -> &[Url] {
+        &self.plugin_repositories
+    }
+
+    /// Adds a repository plugins can be resolved from
+    pub fn add_plugin_repository(&mut self, url: Url) {
+        if !self.plugin_repositories.contains(&url) {
+            self.plugin_repositories.push(url);
+        }
+    }
+
     /// Gets whether the backtrace should be emitted
     pub fn backtrace(&self) -> BacktraceEmit {
         self.backtrace
@@ -355,6 +501,161 @@ impl StartParameter {
         self.rerun_tasks = true;
     }
 
+    /// Whether dependency lock files should be written instead of checked
+    pub fn is_write_locks(&self) -> bool {
+        self.write_locks
+    }
+
+    /// Write dependency lock files with the versions resolved in this build
+    pub fn write_locks(&mut self) {
+        self.write_locks = true;
+    }
+
+    /// Whether writing into the outputs of other tasks should fail the build
+    pub fn is_strict_outputs(&self) -> bool {
+        self.strict_outputs
+    }
+
+    /// Fail the build when a task writes into the outputs of another task
+    pub fn strict_outputs(&mut self) {
+        self.strict_outputs = true;
+    }
+
+    /// Whether tasks declaring overlapping outputs without an ordering only emit a warning
+    pub fn is_warn_overlapping_outputs(&self) -> bool {
+        self.warn_overlapping_outputs
+    }
+
+    /// Warn when tasks declare overlapping outputs without an ordering, instead of failing the build
+    pub fn warn_overlapping_outputs(&mut self) {
+        self.warn_overlapping_outputs = true;
+    }
+
+    /// How files accessed by tasks without being declared are handled
+    pub fn undeclared_io(&self) -> UndeclaredIoPolicy {
+        self.undeclared_io
+    }
+
+    /// Sets how files accessed by tasks without being declared are handled
+    pub fn set_undeclared_io(&mut self, undeclared_io: UndeclaredIoPolicy) {
+        self.undeclared_io = undeclared_io;
+    }
+
+    /// How deprecations used by the build are reported
+    pub fn warning_mode(&self) -> WarningMode {
+        self.warning_mode
+    }
+
+    /// Sets how deprecations used by the build are reported
+    pub fn set_warning_mode(&mut self, warning_mode: WarningMode) {
+        self.warning_mode = warning_mode;
+    }
+
+    /// The timeouts enforced on the build
+    pub fn timeouts(&self) -> &BuildTimeouts {
+        &self.timeouts
+    }
+
+    /// Set the timeouts enforced on the build
+    pub fn set_timeouts(&mut self, timeouts: BuildTimeouts) {
+        self.timeouts = timeouts;
+    }
+
+    /// The directory a build report is written to, if a report should be generated. Relative paths
+    /// are relative to the root project directory.
+    pub fn report_dir(&self) -> Option<&Path> {
+        self.report_dir.as_deref()
+    }
+
+    /// Generate a build report in the given directory
+    pub fn set_report_dir<P: AsRef<Path>>(&mut self, report_dir: P) {
+        self.report_dir = Some(report_dir.as_ref().to_path_buf());
+    }
+
+    /// The number of slowest tasks to print at the end of the build, if profiling
+    pub fn profile(&self) -> Option<usize> {
+        self.profile
+    }
+
+    /// Print the given number of slowest tasks at the end of the build
+    pub fn set_profile(&mut self, count: usize) {
+        self.profile = Some(count);
+    }
+
+    /// The file a JUnit xml report of task results is written to, if any. Relative paths are
+    /// relative to the root project directory.
+    pub fn results_xml(&self) -> Option<&Path> {
+        self.results_xml.as_deref()
+    }
+
+    /// Write a JUnit xml report of task results to the given file
+    pub fn set_results_xml<P: AsRef<Path>>(&mut self, path: P) {
+        self.results_xml = Some(path.as_ref().to_path_buf());
+    }
+
+    /// The file the resolved execution plan is saved to, if any. Relative paths are relative to the
+    /// root project directory.
+    pub fn export_plan(&self) -> Option<&Path> {
+        self.export_plan.as_deref()
+    }
+
+    /// Save the resolved execution plan to the given file
+    pub fn set_export_plan<P: AsRef<Path>>(&mut self, path: P) {
+        self.export_plan = Some(path.as_ref().to_path_buf());
+    }
+
+    /// The file of a saved execution plan to replay instead of resolving task requests, if any.
+    /// Relative paths are relative to the root project directory.
+    pub fn from_plan(&self) -> Option<&Path> {
+        self.from_plan.as_deref()
+    }
+
+    /// Replay the saved execution plan in the given file instead of resolving task requests
+    pub fn set_from_plan<P: AsRef<Path>>(&mut self, path: P) {
+        self.from_plan = Some(path.as_ref().to_path_buf());
+    }
+
+    /// The id of the build, used by other processes to control it
+    pub fn build_id(&self) -> &BuildId {
+        &self.build_id
+    }
+
+    /// Set the id of the build
+    pub fn set_build_id(&mut self, build_id: BuildId) {
+        self.build_id = build_id;
+    }
+
+    /// How untrusted build logic is handled
+    pub fn trust_policy(&self) -> TrustPolicy {
+        self.trust_policy
+    }
+
+    /// Set how untrusted build logic is handled
+    pub fn set_trust_policy(&mut self, trust_policy: TrustPolicy) {
+        self.trust_policy = trust_policy;
+    }
+
+    /// Whether the build runs without network access
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Run the build without network access
+    pub fn offline(&mut self) {
+        self.offline = true;
+    }
+
+    /// The channel checked for newer releases of assemble at the end of the build, if updates
+    /// should be checked. Updates are never checked when offline.
+    pub fn update_check(&self) -> Option<ReleaseChannel> {
+        self.update_channel.filter(|_| !self.offline)
+    }
+
+    /// Check the given channel for newer releases of assemble at the end of the build
+    pub fn set_update_check(&mut self, channel: ReleaseChannel) {
+        self.update_channel = Some(channel);
+    }
+
     /// Set the current directory
     pub fn set_current_dir<P: AsRef<Path>>(&mut self, current_dir: P) {
         self.current_dir = current_dir.as_ref().to_path_buf();
@@ -400,4 +701,35 @@ mod tests {
         println!("assemble: {:#?}", assemble);
         assert_eq!(assemble.assemble_version(), &version());
     }
+
+    #[test]
+    fn project_listeners_are_notified() {
+        use crate::startup::listeners::{ProjectsEvaluated, ProjectsLoaded};
+        use crate::Project;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let mut assemble = Assemble::default();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let loaded = calls.clone();
+        assemble
+            .add_listener(ProjectsLoaded::new(move |_| {
+                loaded.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }))
+            .unwrap();
+        let evaluated = calls.clone();
+        assemble
+            .add_listener(ProjectsEvaluated::new(move |root| {
+                assert_eq!(root.with(|p| p.id().to_string()), ":root");
+                evaluated.fetch_add(10, Ordering::SeqCst);
+                Ok(())
+            }))
+            .unwrap();
+
+        let project = Project::temp(None);
+        assemble.projects_loaded(&project).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assemble.projects_evaluated(&project).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 11);
+    }
 }
@@ -6,6 +6,7 @@ use crate::task::{ExecutableTask, TaskOutcome};
 use std::fmt::{Debug, Formatter};
 
 use crate::prelude::*;
+use crate::project::shared::SharedProject;
 use crate::startup::execution_graph::ExecutionGraph;
 
 /// A listener than can be added to a type.
@@ -31,7 +32,20 @@ pub trait TaskExecutionGraphListener: Debug + Listener<Listened = Assemble> {
 
 /// Listens for major build lifecycle moments
 pub trait BuildListener: Debug + Listener<Listened = Assemble> {
-    fn settings_evaluated(&mut self, settings: &Settings) -> ProjectResult;
+    fn settings_evaluated(&mut self, _settings: &Settings) -> ProjectResult {
+        Ok(())
+    }
+
+    /// Listens for every project of the build to be created, before any is evaluated
+    fn projects_loaded(&mut self, _root: &SharedProject) -> ProjectResult {
+        Ok(())
+    }
+
+    /// Listens for every project of the build to be evaluated, after their after-evaluate hooks
+    /// ran
+    fn projects_evaluated(&mut self, _root: &SharedProject) -> ProjectResult {
+        Ok(())
+    }
 }
 
 /// A listener for when the graph is ready
@@ -68,3 +82,72 @@ impl TaskExecutionGraphListener for GraphReady {
         (self.function)(graph)
     }
 }
+
+/// The type of functions listening to the projects of a build
+type ProjectsListener = Box<dyn FnMut(&SharedProject) -> ProjectResult + Send + Sync>;
+
+/// A listener for when every project of the build was created
+pub struct ProjectsLoaded {
+    function: ProjectsListener,
+}
+
+impl ProjectsLoaded {
+    pub fn new<F: FnMut(&SharedProject) -> ProjectResult + 'static + Send + Sync>(func: F) -> Self {
+        Self {
+            function: Box::new(func),
+        }
+    }
+}
+
+impl Debug for ProjectsLoaded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProjectsLoaded").finish_non_exhaustive()
+    }
+}
+
+impl Listener for ProjectsLoaded {
+    type Listened = Assemble;
+
+    fn add_listener(self, freight: &mut Self::Listened) -> ProjectResult {
+        freight.add_build_listener(self)
+    }
+}
+
+impl BuildListener for ProjectsLoaded {
+    fn projects_loaded(&mut self, root: &SharedProject) -> ProjectResult {
+        (self.function)(root)
+    }
+}
+
+/// A listener for when every project of the build was evaluated
+pub struct ProjectsEvaluated {
+    function: ProjectsListener,
+}
+
+impl ProjectsEvaluated {
+    pub fn new<F: FnMut(&SharedProject) -> ProjectResult + 'static + Send + Sync>(func: F) -> Self {
+        Self {
+            function: Box::new(func),
+        }
+    }
+}
+
+impl Debug for ProjectsEvaluated {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProjectsEvaluated").finish_non_exhaustive()
+    }
+}
+
+impl Listener for ProjectsEvaluated {
+    type Listened = Assemble;
+
+    fn add_listener(self, freight: &mut Self::Listened) -> ProjectResult {
+        freight.add_build_listener(self)
+    }
+}
+
+impl BuildListener for ProjectsEvaluated {
+    fn projects_evaluated(&mut self, root: &SharedProject) -> ProjectResult {
+        (self.function)(root)
+    }
+}
@@ -0,0 +1,344 @@
+//! Profiling of the phases of a build.
+//!
+//! The time spent in each phase of the build is recorded into the
+//! [`BuildProfile`](BuildProfile) of the [`Assemble`](crate::prelude::Assemble) instance, so that
+//! slow configuration can be told apart from slow task execution.
+//!
+//! Entering and leaving a phase emits a [`BuildEvent`](BuildEvent), which is logged at the info
+//! level and sent to every listener added with [`on_event`](BuildProfile::on_event).
+
+use crate::humanize;
+use crate::identifier::ProjectId;
+use parking_lot::Mutex;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// A phase of a build
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BuildPhase {
+    /// Initializing assemble from the start parameters
+    Initialization,
+    /// Finding the build scripts and settings file
+    ScriptDiscovery,
+    /// Evaluating the settings file
+    SettingsEvaluation,
+    /// Creating projects and configuring them with the build logic
+    BuildLogicConfiguration,
+    /// Resolving task requests into an execution plan
+    TaskResolution,
+    /// Executing tasks
+    TaskExecution,
+    /// Writing reports and summaries once tasks have executed
+    Finish,
+}
+
+impl Display for BuildPhase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildPhase::Initialization => write!(f, "initialization"),
+            BuildPhase::ScriptDiscovery => write!(f, "script discovery"),
+            BuildPhase::SettingsEvaluation => write!(f, "settings evaluation"),
+            BuildPhase::BuildLogicConfiguration => write!(f, "build logic configuration"),
+            BuildPhase::TaskResolution => write!(f, "task resolution"),
+            BuildPhase::TaskExecution => write!(f, "task execution"),
+            BuildPhase::Finish => write!(f, "finish"),
+        }
+    }
+}
+
+/// A marker emitted as a build moves through its phases
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildEvent {
+    /// A phase was entered
+    PhaseStarted(BuildPhase),
+    /// A phase was left
+    PhaseFinished {
+        /// The phase
+        phase: BuildPhase,
+        /// The time spent in the phase
+        duration: Duration,
+    },
+    /// A project was configured by the build logic
+    ProjectConfigured {
+        /// The project
+        project: ProjectId,
+        /// The time spent configuring the project, not including its subprojects
+        duration: Duration,
+    },
+    /// The build finished
+    BuildFinished {
+        /// Whether the build succeeded
+        success: bool,
+        /// The total time of the build
+        duration: Duration,
+    },
+}
+
+impl Display for BuildEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildEvent::PhaseStarted(phase) => write!(f, "> {}", phase),
+            BuildEvent::PhaseFinished { phase, duration } => {
+                write!(f, "< {} ({})", phase, humanize::duration(*duration))
+            }
+            BuildEvent::ProjectConfigured { project, duration } => write!(
+                f,
+                "  configured project {} ({})",
+                **project,
+                humanize::duration(*duration)
+            ),
+            BuildEvent::BuildFinished { success, duration } => write!(
+                f,
+                "build {} ({})",
+                if *success { "succeeded" } else { "failed" },
+                duration.as_secs_f64()
+            ),
+        }
+    }
+}
+
+type EventListener = Box<dyn Fn(&BuildEvent) + Send + Sync>;
+
+/// The time spent in the phases of a build. Clones share the same state.
+#[derive(Clone)]
+pub struct BuildProfile {
+    inner: Arc<ProfileInner>,
+}
+
+struct ProfileInner {
+    started: Instant,
+    started_at: SystemTime,
+    phases: Mutex<Vec<(BuildPhase, Duration)>>,
+    projects: Mutex<Vec<(ProjectId, Duration)>>,
+    listeners: Mutex<Vec<EventListener>>,
+}
+
+impl std::fmt::Debug for BuildProfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BuildProfile")
+            .field("started_at", &self.inner.started_at)
+            .field("phases", &*self.inner.phases.lock())
+            .field("projects", &*self.inner.projects.lock())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A phase that has been entered, but not yet left. Created by [`BuildProfile::start`](BuildProfile::start).
+#[must_use = "the phase is only recorded once finished"]
+#[derive(Debug)]
+pub struct PhaseTimer {
+    profile: BuildProfile,
+    phase: BuildPhase,
+    start: Instant,
+}
+
+impl PhaseTimer {
+    /// Leaves the phase, recording the time spent in it
+    pub fn finish(self) {
+        self.profile.record(self.phase, self.start.elapsed());
+    }
+}
+
+impl Default for BuildProfile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BuildProfile {
+    /// Creates a new profile, starting now
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(ProfileInner {
+                started: Instant::now(),
+                started_at: SystemTime::now(),
+                phases: Default::default(),
+                projects: Default::default(),
+                listeners: Default::default(),
+            }),
+        }
+    }
+
+    /// Adds a listener that's called with every event emitted after it was added
+    pub fn on_event<F: Fn(&BuildEvent) + Send + Sync + 'static>(&self, listener: F) {
+        self.inner.listeners.lock().push(Box::new(listener));
+    }
+
+    /// Emits an event, logging it at the info level and sending it to every listener
+    pub fn emit(&self, event: BuildEvent) {
+        info!("{}", event);
+        for listener in self.inner.listeners.lock().iter() {
+            listener(&event);
+        }
+    }
+
+    /// Enters a phase. The phase is left when the returned timer is finished.
+    pub fn start(&self, phase: BuildPhase) -> PhaseTimer {
+        self.emit(BuildEvent::PhaseStarted(phase));
+        PhaseTimer {
+            profile: self.clone(),
+            phase,
+            start: Instant::now(),
+        }
+    }
+
+    /// Runs a function, recording how long it took as part of a phase
+    pub fn measure<R, F: FnOnce() -> R>(&self, phase: BuildPhase, func: F) -> R {
+        let timer = self.start(phase);
+        let output = func();
+        timer.finish();
+        output
+    }
+
+    /// Runs a function that configures a single project, recording how long it took
+    pub fn measure_project<R, F: FnOnce() -> R>(&self, project: &ProjectId, func: F) -> R {
+        let start = Instant::now();
+        let output = func();
+        let duration = start.elapsed();
+        self.inner.projects.lock().push((project.clone(), duration));
+        self.emit(BuildEvent::ProjectConfigured {
+            project: project.clone(),
+            duration,
+        });
+        output
+    }
+
+    /// Records time spent in a phase, and emits that the phase was left. Time recorded for the
+    /// same phase more than once is added together.
+    pub fn record(&self, phase: BuildPhase, duration: Duration) {
+        {
+            let mut phases = self.inner.phases.lock();
+            match phases.iter_mut().find(|(recorded, _)| *recorded == phase) {
+                Some((_, total)) => *total += duration,
+                None => phases.push((phase, duration)),
+            }
+        }
+        self.emit(BuildEvent::PhaseFinished { phase, duration });
+    }
+
+    /// Emits that the build finished
+    pub fn finish_build(&self, success: bool) {
+        self.emit(BuildEvent::BuildFinished {
+            success,
+            duration: self.elapsed(),
+        });
+    }
+
+    /// The time spent configuring each project, in the order they were configured
+    pub fn projects(&self) -> Vec<(ProjectId, Duration)> {
+        self.inner.projects.lock().clone()
+    }
+
+    /// A plain text summary of the time spent in each phase
+    pub fn summary(&self) -> String {
+        let mut summary = String::from("build phases:");
+        for (phase, duration) in self.phases() {
+            summary.push_str(&format!(
+                "\n  {:<28}{:>10}",
+                phase.to_string(),
+                humanize::duration(duration)
+            ));
+        }
+        summary
+    }
+
+    /// The time spent in each recorded phase, in the order they occur
+    pub fn phases(&self) -> Vec<(BuildPhase, Duration)> {
+        let mut phases = self.inner.phases.lock().clone();
+        phases.sort_by_key(|(phase, _)| *phase);
+        phases
+    }
+
+    /// The time spent in a phase, if it was recorded
+    pub fn phase(&self, phase: BuildPhase) -> Option<Duration> {
+        self.inner
+            .phases
+            .lock()
+            .iter()
+            .find(|(recorded, _)| *recorded == phase)
+            .map(|(_, duration)| *duration)
+    }
+
+    /// The wall clock time the profile was started
+    pub fn started_at(&self) -> SystemTime {
+        self.inner.started_at
+    }
+
+    /// The time elapsed since the profile was started
+    pub fn elapsed(&self) -> Duration {
+        self.inner.started.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases_are_ordered_and_summed() {
+        let profile = BuildProfile::new();
+        profile.record(BuildPhase::TaskExecution, Duration::from_millis(5));
+        profile.record(BuildPhase::ScriptDiscovery, Duration::from_millis(1));
+        profile
+            .clone()
+            .record(BuildPhase::TaskExecution, Duration::from_millis(5));
+        let value = profile.measure(BuildPhase::SettingsEvaluation, || 3);
+
+        assert_eq!(value, 3);
+        assert_eq!(
+            profile
+                .phases()
+                .into_iter()
+                .map(|(phase, _)| phase)
+                .collect::<Vec<_>>(),
+            vec![
+                BuildPhase::ScriptDiscovery,
+                BuildPhase::SettingsEvaluation,
+                BuildPhase::TaskExecution
+            ]
+        );
+        assert_eq!(
+            profile.phase(BuildPhase::TaskExecution),
+            Some(Duration::from_millis(10))
+        );
+        assert_eq!(profile.phase(BuildPhase::TaskResolution), None);
+    }
+
+    #[test]
+    fn phases_emit_events() {
+        let profile = BuildProfile::new();
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = events.clone();
+        profile.on_event(move |event| recorded.lock().push(event.clone()));
+
+        let timer = profile.start(BuildPhase::BuildLogicConfiguration);
+        let project = ProjectId::new("root").unwrap();
+        profile.measure_project(&project, || {});
+        timer.finish();
+        profile.finish_build(true);
+
+        let events = events.lock();
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            events[0],
+            BuildEvent::PhaseStarted(BuildPhase::BuildLogicConfiguration)
+        );
+        assert!(
+            matches!(&events[1], BuildEvent::ProjectConfigured { project: p, .. } if p == &project)
+        );
+        assert!(matches!(
+            events[2],
+            BuildEvent::PhaseFinished {
+                phase: BuildPhase::BuildLogicConfiguration,
+                ..
+            }
+        ));
+        assert!(matches!(
+            events[3],
+            BuildEvent::BuildFinished { success: true, .. }
+        ));
+        assert_eq!(profile.projects().len(), 1);
+        assert!(profile.summary().contains("build logic configuration"));
+    }
+}
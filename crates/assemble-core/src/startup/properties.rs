@@ -0,0 +1,175 @@
+//! Project properties files.
+//!
+//! Project properties can be set in `assemble.properties` files, using the format of java
+//! properties files: one `key=value` (or `key: value`) per line, with `#` or `!` starting a
+//! comment. A key without a value sets the property without a value, and a line ending with `\`
+//! continues on the next line.
+//!
+//! Properties are merged from several places before settings are evaluated. When the same
+//! property is set in more than one of them, the value from the place that comes first in this
+//! list is used:
+//! 1. `-P key=value` on the command line
+//! 2. files given with `--project-prop-file`, with later files taking precedence over earlier ones
+//! 3. environment variables starting with [`ENV_PREFIX`](ENV_PREFIX), such as
+//!    `ASSEMBLE_version=1.0` for the property `version`
+//! 4. `ASSEMBLE_HOME/assemble.properties`
+//! 5. `assemble.properties` in the directory of a subproject, for that subproject and its
+//!    children
+//! 6. `assemble.properties` in the root project directory
+//! 7. defaults set by [init scripts](crate::startup::init_scripts)
+//!
+//! The first four are [overrides](crate::startup::invocation::StartParameter::property_overrides),
+//! which apply to every project.
+
+use crate::ASSEMBLE_HOME;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+/// The name of properties files
+pub const PROPERTIES_FILE_NAME: &str = "assemble.properties";
+
+/// The prefix of environment variables that set project properties
+pub const ENV_PREFIX: &str = "ASSEMBLE_";
+
+/// Project properties. A value of `None` means the property is set without a value.
+pub type Properties = HashMap<String, Option<String>>;
+
+/// Parses the contents of a properties file
+pub fn parse_properties(contents: &str) -> Properties {
+    let mut properties = Properties::new();
+    let mut lines = contents.lines();
+    while let Some(line) = lines.next() {
+        let mut line = line.trim_start().to_string();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+            continue;
+        }
+        while line.ends_with('\\') {
+            line.pop();
+            match lines.next() {
+                Some(next) => line.push_str(next.trim_start()),
+                None => break,
+            }
+        }
+        let (key, value) = match line.find(|c| c == '=' || c == ':') {
+            Some(index) => (&line[..index], Some(line[index + 1..].trim().to_string())),
+            None => (line.as_str(), None),
+        };
+        properties.insert(key.trim().to_string(), value);
+    }
+    properties
+}
+
+/// Reads a properties file
+pub fn read_properties_file<P: AsRef<Path>>(path: P) -> io::Result<Properties> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(parse_properties(&contents))
+}
+
+/// Reads a properties file if it exists
+pub fn read_optional_properties_file<P: AsRef<Path>>(path: P) -> io::Result<Properties> {
+    match read_properties_file(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Properties::new()),
+        result => result,
+    }
+}
+
+/// Reads the `assemble.properties` file in a project directory, if there is one
+pub fn project_properties<P: AsRef<Path>>(project_dir: P) -> io::Result<Properties> {
+    read_optional_properties_file(project_dir.as_ref().join(PROPERTIES_FILE_NAME))
+}
+
+/// Merges the properties set on the command line, directly and through `prop_files`. The
+/// properties set directly take precedence.
+pub fn command_line_properties<I, P>(
+    prop_files: I,
+    properties: &Properties,
+) -> io::Result<Properties>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let mut merged = Properties::new();
+    for file in prop_files {
+        merged.extend(read_properties_file(file)?);
+    }
+    merged.extend(properties.clone());
+    Ok(merged)
+}
+
+/// The properties set by environment variables starting with [`ENV_PREFIX`](ENV_PREFIX)
+pub fn env_properties<I: IntoIterator<Item = (String, String)>>(vars: I) -> Properties {
+    vars.into_iter()
+        .filter_map(|(key, value)| {
+            key.strip_prefix(ENV_PREFIX)
+                .filter(|key| !key.is_empty())
+                .map(|key| (key.to_string(), Some(value)))
+        })
+        .collect()
+}
+
+/// Merges the properties that take precedence over the properties files of projects: those in
+/// `ASSEMBLE_HOME`, then those from the environment, then those from the command line.
+pub fn property_overrides(command_line: &Properties) -> io::Result<Properties> {
+    let mut overrides =
+        read_optional_properties_file(ASSEMBLE_HOME.path().join(PROPERTIES_FILE_NAME))?;
+    overrides.extend(env_properties(std::env::vars()));
+    overrides.extend(command_line.clone());
+    Ok(overrides)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn parse_properties_file() {
+        let properties = parse_properties(
+            r"
+            # a comment
+            ! another comment
+            version = 1.0
+            name: assemble
+            flag
+            long=first, \
+                 second
+            url=http://example.com
+            ",
+        );
+        assert_eq!(properties["version"].as_deref(), Some("1.0"));
+        assert_eq!(properties["name"].as_deref(), Some("assemble"));
+        assert_eq!(properties["flag"], None);
+        assert_eq!(properties["long"].as_deref(), Some("first, second"));
+        assert_eq!(properties["url"].as_deref(), Some("http://example.com"));
+        assert_eq!(properties.len(), 5);
+    }
+
+    #[test]
+    fn command_line_takes_precedence() {
+        let dir = TempDir::new().unwrap();
+        let prop_file = dir.path().join("extra.properties");
+        std::fs::write(&prop_file, "b=file\nc=file").unwrap();
+        let command_line = Properties::from([("c".to_string(), Some("cli".to_string()))]);
+
+        let merged = command_line_properties([&prop_file], &command_line).unwrap();
+        assert_eq!(merged["b"].as_deref(), Some("file"));
+        assert_eq!(merged["c"].as_deref(), Some("cli"));
+
+        assert!(command_line_properties([dir.path().join("missing")], &command_line).is_err());
+        assert!(project_properties(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn properties_from_env() {
+        let properties = env_properties([
+            ("ASSEMBLE_version".to_string(), "1.0".to_string()),
+            ("ASSEMBLE_".to_string(), "empty".to_string()),
+            ("PATH".to_string(), "/bin".to_string()),
+        ]);
+        assert_eq!(
+            properties,
+            Properties::from([("version".to_string(), Some("1.0".to_string()))])
+        );
+    }
+}
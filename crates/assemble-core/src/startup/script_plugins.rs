@@ -0,0 +1,190 @@
+//! Script plugins, which are scripts applied to a project from its build script.
+//!
+//! A script plugin is either a local file, relative to the directory of the project applying it,
+//! or an `http` or `https` url. Remote scripts are downloaded once into the
+//! [`SCRIPT_PLUGINS_DIR`](SCRIPT_PLUGINS_DIR) directory of `ASSEMBLE_HOME` and reused by every
+//! later build, including offline ones.
+//!
+//! Every resolved script plugin has the checksum of its contents, which builders record as an input
+//! of the build logic of the project it's applied to.
+
+use crate::cryptography::{hash_file_sha256, hash_sha256, Sha256};
+use crate::web::{WebClient, WebError};
+use crate::ASSEMBLE_HOME;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// The directory within `ASSEMBLE_HOME` remote script plugins are cached in
+pub const SCRIPT_PLUGINS_DIR: &str = "script-plugins";
+
+/// Where a script plugin is applied from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptLocation {
+    /// A local file
+    File(PathBuf),
+    /// A remote script
+    Url(Url),
+}
+
+impl ScriptLocation {
+    /// Parses the location of a script plugin. Anything that isn't an `http` or `https` url is a
+    /// file, and relative files are relative to the base directory.
+    pub fn parse<P: AsRef<Path>>(location: &str, base_dir: P) -> Self {
+        match Url::parse(location) {
+            Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {
+                ScriptLocation::Url(url)
+            }
+            _ => ScriptLocation::File(base_dir.as_ref().join(location)),
+        }
+    }
+}
+
+impl Display for ScriptLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScriptLocation::File(path) => write!(f, "{:?}", path),
+            ScriptLocation::Url(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+/// A script plugin whose contents are available locally
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptPlugin {
+    location: ScriptLocation,
+    path: PathBuf,
+    checksum: Sha256,
+}
+
+impl ScriptPlugin {
+    /// Where the script was applied from
+    pub fn location(&self) -> &ScriptLocation {
+        &self.location
+    }
+
+    /// The local file containing the script. For remote scripts, this is the cached download.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The checksum of the contents of the script
+    pub fn checksum(&self) -> Sha256 {
+        self.checksum
+    }
+
+    /// Reads the contents of the script
+    pub fn contents(&self) -> io::Result<String> {
+        std::fs::read_to_string(&self.path)
+    }
+}
+
+/// An error occurred resolving a script plugin
+#[derive(Debug, thiserror::Error)]
+pub enum ScriptPluginError {
+    /// A local script doesn't exist
+    #[error("no script plugin at {0:?}")]
+    Missing(PathBuf),
+    /// A remote script isn't cached, and can't be downloaded while offline
+    #[error("script plugin {0} hasn't been downloaded, and the build is offline")]
+    Offline(Url),
+    #[error(transparent)]
+    WebError(#[from] WebError),
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+}
+
+/// Resolves a script plugin, downloading it into `ASSEMBLE_HOME` if it's remote and not cached yet
+pub fn resolve_script_plugin<P: AsRef<Path>>(
+    location: &str,
+    base_dir: P,
+    offline: bool,
+) -> Result<ScriptPlugin, ScriptPluginError> {
+    resolve_script_plugin_in(
+        ScriptLocation::parse(location, base_dir),
+        ASSEMBLE_HOME.path().join(SCRIPT_PLUGINS_DIR),
+        offline,
+    )
+}
+
+/// Resolves a script plugin, caching remote scripts in the given directory
+pub fn resolve_script_plugin_in<P: AsRef<Path>>(
+    location: ScriptLocation,
+    cache_dir: P,
+    offline: bool,
+) -> Result<ScriptPlugin, ScriptPluginError> {
+    let path = match &location {
+        ScriptLocation::File(path) => {
+            if !path.is_file() {
+                return Err(ScriptPluginError::Missing(path.clone()));
+            }
+            path.clone()
+        }
+        ScriptLocation::Url(url) => {
+            let path = cached_path(url, cache_dir.as_ref());
+            if !path.is_file() {
+                if offline {
+                    return Err(ScriptPluginError::Offline(url.clone()));
+                }
+                info!("downloading script plugin {}", url);
+                WebClient::new().download(url, &path)?;
+            }
+            path
+        }
+    };
+    let checksum = hash_file_sha256(&path)?;
+    Ok(ScriptPlugin {
+        location,
+        path,
+        checksum,
+    })
+}
+
+/// Remote scripts are cached under the checksum of their url, keeping the extension of the script
+fn cached_path(url: &Url, cache_dir: &Path) -> PathBuf {
+    let file_name = url
+        .path_segments()
+        .and_then(|segments| segments.last())
+        .unwrap_or_default();
+    let mut path = cache_dir.join(hash_sha256(url.as_str()).to_string());
+    if let Some(extension) = Path::new(file_name).extension() {
+        path.set_extension(extension);
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn parse_locations() {
+        let base = Path::new("/project");
+        assert_eq!(
+            ScriptLocation::parse("gradle/common.assemble.js", base),
+            ScriptLocation::File(base.join("gradle/common.assemble.js"))
+        );
+        assert_eq!(
+            ScriptLocation::parse("https://example.com/common.js", base),
+            ScriptLocation::Url(Url::parse("https://example.com/common.js").unwrap())
+        );
+    }
+
+    #[test]
+    fn remote_scripts_are_cached() {
+        let dir = TempDir::new().unwrap();
+        let url = Url::parse("https://example.com/scripts/common.js").unwrap();
+        let cached = cached_path(&url, dir.path());
+        assert_eq!(cached.extension().unwrap(), "js");
+
+        let missing = resolve_script_plugin_in(ScriptLocation::Url(url.clone()), dir.path(), true);
+        assert!(matches!(missing, Err(ScriptPluginError::Offline(_))));
+
+        std::fs::write(&cached, "project.register('a')").unwrap();
+        let script = resolve_script_plugin_in(ScriptLocation::Url(url), dir.path(), true).unwrap();
+        assert_eq!(script.path(), cached);
+        assert_eq!(script.checksum(), hash_file_sha256(&cached).unwrap());
+    }
+}
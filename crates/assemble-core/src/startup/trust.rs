@@ -0,0 +1,173 @@
+//! Trusting dynamically loaded build logic.
+//!
+//! Build logic such as build scripts and plugin libraries runs with the full permissions of the
+//! user running assemble. In a shared repository, a change to a script could run anything on the
+//! machine of everyone who builds it. To protect against this, a [`TrustPolicy`](TrustPolicy) can
+//! require that build logic matches a checksum recorded when it was last trusted.
+//!
+//! Files are trusted with `assemble --trust <PATH>`, which records the current checksum of the
+//! file in `ASSEMBLE_HOME/trusted.json`. Trusted files must be trusted again after every change.
+
+use crate::cryptography::{hash_file_sha256, Sha256};
+use crate::ASSEMBLE_HOME;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The file within `ASSEMBLE_HOME` trusted checksums are stored in
+pub const TRUST_FILE: &str = "trusted.json";
+
+/// How untrusted build logic is handled
+#[derive(Debug, Default, Copy, Clone, clap::ValueEnum, Eq, PartialEq)]
+pub enum TrustPolicy {
+    /// Build logic isn't checked
+    #[default]
+    Off,
+    /// Untrusted build logic is loaded with a warning
+    Warn,
+    /// Untrusted build logic isn't loaded
+    Enforce,
+}
+
+/// An error that occurs when checking whether build logic is trusted
+#[derive(Debug, thiserror::Error)]
+pub enum TrustError {
+    /// The file has never been trusted
+    #[error("{0:?} is not trusted. Run `assemble --trust {0:?}` after reviewing it")]
+    Untrusted(PathBuf),
+    /// The file changed since it was trusted
+    #[error("{path:?} changed since it was trusted (expected sha256 {expected}, found {actual}). Run `assemble --trust {path:?}` after reviewing it")]
+    Modified {
+        /// The file
+        path: PathBuf,
+        /// The checksum the file had when it was trusted
+        expected: Sha256,
+        /// The current checksum of the file
+        actual: Sha256,
+    },
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+}
+
+/// The checksums of trusted files
+#[derive(Debug)]
+pub struct TrustStore {
+    file: PathBuf,
+    trusted: BTreeMap<PathBuf, Sha256>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TrustedFiles {
+    trusted: BTreeMap<PathBuf, Sha256>,
+}
+
+impl TrustStore {
+    /// Opens the trust store in `ASSEMBLE_HOME`
+    pub fn open() -> io::Result<Self> {
+        Self::open_at(ASSEMBLE_HOME.path().join(TRUST_FILE))
+    }
+
+    /// Opens a trust store stored in a file. The store is empty if the file doesn't exist.
+    pub fn open_at<P: AsRef<Path>>(file: P) -> io::Result<Self> {
+        let file = file.as_ref().to_path_buf();
+        let trusted = match std::fs::read_to_string(&file) {
+            Ok(contents) => {
+                serde_json::from_str::<TrustedFiles>(&contents)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+                    .trusted
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self { file, trusted })
+    }
+
+    /// Trusts the current contents of a file, and saves the store. Returns the trusted checksum.
+    pub fn trust<P: AsRef<Path>>(&mut self, path: P) -> io::Result<Sha256> {
+        let path = path.as_ref().canonicalize()?;
+        let checksum = hash_file_sha256(&path)?;
+        self.trusted.insert(path, checksum);
+        self.save()?;
+        Ok(checksum)
+    }
+
+    /// Checks whether the current contents of a file are trusted
+    pub fn check<P: AsRef<Path>>(&self, path: P) -> Result<(), TrustError> {
+        let path = path.as_ref().canonicalize()?;
+        let expected = *self
+            .trusted
+            .get(&path)
+            .ok_or_else(|| TrustError::Untrusted(path.clone()))?;
+        let actual = hash_file_sha256(&path)?;
+        if actual != expected {
+            return Err(TrustError::Modified {
+                path,
+                expected,
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> io::Result<()> {
+        if let Some(parent) = self.file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let trusted = TrustedFiles {
+            trusted: self.trusted.clone(),
+        };
+        let json = serde_json::to_string_pretty(&trusted)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        std::fs::write(&self.file, json)
+    }
+}
+
+/// Checks build logic against the trust store in `ASSEMBLE_HOME` before it's loaded, following the
+/// given policy. Only returns an error if the policy is [`Enforce`](TrustPolicy::Enforce).
+pub fn verify_build_logic<P: AsRef<Path>>(policy: TrustPolicy, path: P) -> Result<(), TrustError> {
+    if policy == TrustPolicy::Off {
+        return Ok(());
+    }
+    let result = TrustStore::open()
+        .map_err(TrustError::from)
+        .and_then(|store| store.check(path.as_ref()));
+    match (result, policy) {
+        (Err(e), TrustPolicy::Warn) => {
+            warn!("{}", e);
+            Ok(())
+        }
+        (result, _) => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn trusted_files_must_not_change() {
+        let dir = TempDir::new().unwrap();
+        let script = dir.path().join("build.assemble.js");
+        std::fs::write(&script, "project.register('a')").unwrap();
+        let store_file = dir.path().join("home").join(TRUST_FILE);
+
+        let mut store = TrustStore::open_at(&store_file).unwrap();
+        assert!(matches!(
+            store.check(&script),
+            Err(TrustError::Untrusted(_))
+        ));
+        store.trust(&script).unwrap();
+        assert!(store.check(&script).is_ok());
+
+        let reopened = TrustStore::open_at(&store_file).unwrap();
+        assert!(reopened.check(&script).is_ok());
+
+        std::fs::write(&script, "malicious()").unwrap();
+        assert!(matches!(
+            reopened.check(&script),
+            Err(TrustError::Modified { .. })
+        ));
+    }
+}
@@ -0,0 +1,326 @@
+//! Release channels and update notifications.
+//!
+//! When enabled with `--check-updates`, the releases of assemble are checked at the end of a build,
+//! and a notice is shown if a newer version is available on the configured
+//! [`ReleaseChannel`](ReleaseChannel). Releases are fetched at most once per
+//! [`CHECK_INTERVAL`](CHECK_INTERVAL); in between, the result of the last check is read from
+//! `ASSEMBLE_HOME/update-check.json`. Nothing is checked when running with `--offline`.
+//!
+//! The newest release of a channel can be installed with `assemble --self-update`.
+
+use crate::ASSEMBLE_HOME;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The file within `ASSEMBLE_HOME` the result of the last update check is stored in
+pub const UPDATE_CHECK_FILE: &str = "update-check.json";
+
+/// How long the result of an update check is reused before releases are fetched again
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Where releases are fetched from
+pub const RELEASES_URL: &str = "https://api.github.com/repos/joshradin/assemble-rs/releases";
+
+/// Environment variable that overrides [`RELEASES_URL`](RELEASES_URL)
+pub const RELEASES_URL_ENV: &str = "ASSEMBLE_RELEASES_URL";
+
+/// The releases a user is notified about
+#[derive(Debug, Default, Copy, Clone, clap::ValueEnum, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseChannel {
+    /// Only full releases
+    #[default]
+    Stable,
+    /// Pre-releases as well as full releases
+    Nightly,
+}
+
+impl ReleaseChannel {
+    /// Whether a release is published on this channel
+    pub fn includes(&self, release: &Release) -> bool {
+        match self {
+            ReleaseChannel::Stable => !release.prerelease && release.version.pre.is_empty(),
+            ReleaseChannel::Nightly => true,
+        }
+    }
+}
+
+impl Display for ReleaseChannel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReleaseChannel::Stable => write!(f, "stable"),
+            ReleaseChannel::Nightly => write!(f, "nightly"),
+        }
+    }
+}
+
+/// A published release of assemble
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Release {
+    /// The version of the release
+    pub version: Version,
+    /// Whether the release is a pre-release
+    pub prerelease: bool,
+    /// Where the release can be viewed, if known
+    pub url: Option<String>,
+}
+
+/// An error occurred while checking for updates
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateError {
+    /// Releases couldn't be fetched
+    #[error("could not fetch releases: {0}")]
+    Http(#[from] reqwest::Error),
+    /// Releases couldn't be parsed
+    #[error("could not parse releases: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+}
+
+/// The releases published on all channels
+#[derive(Debug, Clone, Default)]
+pub struct ReleaseMetadata {
+    releases: Vec<Release>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    draft: bool,
+    html_url: Option<String>,
+}
+
+impl ReleaseMetadata {
+    /// Creates release metadata from a list of releases
+    pub fn new<I: IntoIterator<Item = Release>>(releases: I) -> Self {
+        Self {
+            releases: releases.into_iter().collect(),
+        }
+    }
+
+    /// Fetches releases from [`RELEASES_URL`](RELEASES_URL), or from the url in
+    /// [`RELEASES_URL_ENV`](RELEASES_URL_ENV) if it's set
+    pub fn fetch() -> Result<Self, UpdateError> {
+        let url = std::env::var(RELEASES_URL_ENV).unwrap_or_else(|_| RELEASES_URL.to_string());
+        debug!("fetching releases from {}", url);
+        let body = reqwest::blocking::Client::builder()
+            .user_agent(concat!("assemble/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(5))
+            .build()?
+            .get(url)
+            .send()?
+            .error_for_status()?
+            .text()?;
+        Self::parse(&body)
+    }
+
+    /// Parses a list of releases in the format of the github releases api. Drafts, and releases
+    /// whose tag isn't a version, are skipped.
+    pub fn parse(json: &str) -> Result<Self, UpdateError> {
+        let releases: Vec<GithubRelease> = serde_json::from_str(json)?;
+        Ok(Self::new(
+            releases
+                .into_iter()
+                .filter(|r| !r.draft)
+                .filter_map(|release| {
+                    let version = release.tag_name.trim_start_matches('v');
+                    Some(Release {
+                        version: Version::parse(version).ok()?,
+                        prerelease: release.prerelease,
+                        url: release.html_url,
+                    })
+                }),
+        ))
+    }
+
+    /// The newest release on a channel
+    pub fn latest(&self, channel: ReleaseChannel) -> Option<&Release> {
+        self.releases
+            .iter()
+            .filter(|release| channel.includes(release))
+            .max_by(|a, b| a.version.cmp(&b.version))
+    }
+}
+
+/// The result of the last update check
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedCheck {
+    /// When releases were fetched, in seconds since the unix epoch
+    checked_at: u64,
+    channel: ReleaseChannel,
+    latest: Option<Release>,
+}
+
+/// Checks for newer releases, fetching them at most once per [`CHECK_INTERVAL`](CHECK_INTERVAL)
+#[derive(Debug, Clone)]
+pub struct UpdateChecker {
+    file: PathBuf,
+    channel: ReleaseChannel,
+    current: Version,
+}
+
+impl UpdateChecker {
+    /// Creates a checker for a channel, comparing releases to the running version of assemble
+    pub fn new(channel: ReleaseChannel) -> Self {
+        Self::with_file(
+            ASSEMBLE_HOME.path().join(UPDATE_CHECK_FILE),
+            channel,
+            Version::parse(env!("CARGO_PKG_VERSION")).expect("package version is valid semver"),
+        )
+    }
+
+    /// Creates a checker that stores its results in the given file
+    pub fn with_file<P: AsRef<Path>>(file: P, channel: ReleaseChannel, current: Version) -> Self {
+        Self {
+            file: file.as_ref().to_path_buf(),
+            channel,
+            current,
+        }
+    }
+
+    /// The channel releases are checked on
+    pub fn channel(&self) -> ReleaseChannel {
+        self.channel
+    }
+
+    /// The version releases are compared to
+    pub fn current(&self) -> &Version {
+        &self.current
+    }
+
+    /// Gets a release newer than the current version, if there is one. Releases are only fetched
+    /// if the last check was for another channel, or is older than
+    /// [`CHECK_INTERVAL`](CHECK_INTERVAL).
+    pub fn check(&self) -> Result<Option<Release>, UpdateError> {
+        self.check_with(ReleaseMetadata::fetch)
+    }
+
+    /// Like [`check`](Self::check), but fetches releases with the given function
+    pub fn check_with<F>(&self, fetch: F) -> Result<Option<Release>, UpdateError>
+    where
+        F: FnOnce() -> Result<ReleaseMetadata, UpdateError>,
+    {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let latest = match self.cached(now) {
+            Some(cached) => {
+                trace!("using update check from {}", cached.checked_at);
+                cached.latest
+            }
+            None => {
+                let latest = fetch()?.latest(self.channel).cloned();
+                self.save(&CachedCheck {
+                    checked_at: now,
+                    channel: self.channel,
+                    latest: latest.clone(),
+                })?;
+                latest
+            }
+        };
+        Ok(latest.filter(|release| release.version > self.current))
+    }
+
+    /// Forgets the result of the last check, so the next check fetches releases
+    pub fn invalidate(&self) -> Result<(), UpdateError> {
+        match std::fs::remove_file(&self.file) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn cached(&self, now: u64) -> Option<CachedCheck> {
+        let file = std::fs::File::open(&self.file).ok()?;
+        let cached: CachedCheck = serde_json::from_reader(file).ok()?;
+        let fresh = now.saturating_sub(cached.checked_at) < CHECK_INTERVAL.as_secs();
+        (fresh && cached.channel == self.channel).then_some(cached)
+    }
+
+    fn save(&self, check: &CachedCheck) -> Result<(), UpdateError> {
+        if let Some(parent) = self.file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = std::fs::File::create(&self.file)?;
+        serde_json::to_writer_pretty(file, check)?;
+        Ok(())
+    }
+}
+
+/// The notice shown when a newer release is available
+pub fn update_notice(current: &Version, release: &Release, channel: ReleaseChannel) -> String {
+    let mut notice = format!(
+        "assemble {} is available on the {} channel (currently running {}). Run `assemble --self-update` to update.",
+        release.version, channel, current
+    );
+    if let Some(url) = &release.url {
+        notice.push_str(&format!("\nrelease notes: {}", url));
+    }
+    notice
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use tempfile::TempDir;
+
+    const RELEASES: &str = r#"[
+        {"tag_name": "v0.3.0-nightly.2", "prerelease": true, "html_url": "https://example.com/n2"},
+        {"tag_name": "v0.2.1", "prerelease": false, "html_url": "https://example.com/0.2.1"},
+        {"tag_name": "v0.4.0", "draft": true},
+        {"tag_name": "latest", "prerelease": false}
+    ]"#;
+
+    #[test]
+    fn latest_release_per_channel() {
+        let metadata = ReleaseMetadata::parse(RELEASES).unwrap();
+        assert_eq!(
+            metadata.latest(ReleaseChannel::Stable).unwrap().version,
+            Version::new(0, 2, 1)
+        );
+        assert_eq!(
+            metadata
+                .latest(ReleaseChannel::Nightly)
+                .unwrap()
+                .version
+                .to_string(),
+            "0.3.0-nightly.2"
+        );
+    }
+
+    #[test]
+    fn releases_fetched_once_per_interval() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join(UPDATE_CHECK_FILE);
+        let fetches = Cell::new(0);
+        let fetch = || {
+            fetches.set(fetches.get() + 1);
+            ReleaseMetadata::parse(RELEASES)
+        };
+
+        let stable = UpdateChecker::with_file(&file, ReleaseChannel::Stable, Version::new(0, 2, 0));
+        let newer = stable.check_with(fetch).unwrap().unwrap();
+        assert_eq!(newer.version, Version::new(0, 2, 1));
+        assert!(stable.check_with(fetch).unwrap().is_some());
+        assert_eq!(fetches.get(), 1);
+
+        let up_to_date =
+            UpdateChecker::with_file(&file, ReleaseChannel::Stable, Version::new(0, 2, 1));
+        assert!(up_to_date.check_with(fetch).unwrap().is_none());
+        assert_eq!(fetches.get(), 1);
+
+        let nightly =
+            UpdateChecker::with_file(&file, ReleaseChannel::Nightly, Version::new(0, 2, 1));
+        assert!(nightly.check_with(fetch).unwrap().is_some());
+        assert_eq!(fetches.get(), 2);
+    }
+}
@@ -0,0 +1,337 @@
+//! A watchdog that enforces build timeouts.
+//!
+//! The watchdog runs on its own thread. When the build, or the phase the build is currently in,
+//! exceeds its timeout, the watchdog logs a dump of the state of the build and cancels it through
+//! its [`CancellationToken`](CancellationToken). If the build still hasn't stopped after a grace
+//! period, the watchdog exits the process so that a build never hangs forever.
+
+use crate::project::shared::locks::set_project_lock_timeout;
+use crate::startup::cancellation::{CancellationReason, CancellationToken};
+use once_cell::sync::Lazy;
+use parking_lot::{Condvar, Mutex};
+use std::fmt::{Display, Formatter, Write as _};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How long the watchdog waits for a cancelled build to stop before exiting the process
+pub const CANCELLATION_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+/// The exit code used when the watchdog exits the process
+pub const TIMEOUT_EXIT_CODE: i32 = 124;
+
+/// How often the watchdog checks for exceeded timeouts
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The timeouts of a build. No timeout is enforced when a value is `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildTimeouts {
+    /// The timeout for the entire build
+    pub build: Option<Duration>,
+    /// The timeout for configuring projects
+    pub configuration: Option<Duration>,
+    /// The timeout for executing tasks
+    pub execution: Option<Duration>,
+    /// The longest a thread waits for a lock on a project. Enforced by the threads waiting rather
+    /// than the watchdog, see [`locks`](crate::project::shared::locks).
+    pub project_lock: Option<Duration>,
+}
+
+impl BuildTimeouts {
+    /// Whether no timeouts are set
+    pub fn is_empty(&self) -> bool {
+        !self.watched() && self.project_lock.is_none()
+    }
+
+    /// Whether any timeouts enforced by the watchdog thread are set
+    fn watched(&self) -> bool {
+        self.build.is_some() || self.configuration.is_some() || self.execution.is_some()
+    }
+
+    fn phase_timeout(&self, phase: WatchedPhase) -> Option<Duration> {
+        match phase {
+            WatchedPhase::Configuration => self.configuration,
+            WatchedPhase::Execution => self.execution,
+        }
+    }
+}
+
+/// The phases of a build that can have their own timeouts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchedPhase {
+    /// Settings and projects are being configured
+    Configuration,
+    /// Tasks are being executed
+    Execution,
+}
+
+impl Display for WatchedPhase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WatchedPhase::Configuration => write!(f, "configuration"),
+            WatchedPhase::Execution => write!(f, "execution"),
+        }
+    }
+}
+
+/// Provides part of the state dump emitted when a timeout is exceeded
+pub type StateDumpProvider = Box<dyn Fn() -> String + Send + Sync>;
+
+static STATE_DUMPS: Lazy<Mutex<Vec<(usize, String, StateDumpProvider)>>> =
+    Lazy::new(Default::default);
+static NEXT_STATE_DUMP_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Registers a provider of part of the state dump emitted by watchdogs. The provider is removed
+/// when the returned handle is dropped.
+pub fn register_state_dump<F>(name: &str, provider: F) -> StateDumpHandle
+where
+    F: Fn() -> String + Send + Sync + 'static,
+{
+    let id = NEXT_STATE_DUMP_ID.fetch_add(1, Ordering::Relaxed);
+    STATE_DUMPS
+        .lock()
+        .push((id, name.to_string(), Box::new(provider)));
+    StateDumpHandle(id)
+}
+
+/// Keeps a state dump provider registered until dropped
+#[derive(Debug)]
+pub struct StateDumpHandle(usize);
+
+impl Drop for StateDumpHandle {
+    fn drop(&mut self) {
+        STATE_DUMPS.lock().retain(|(id, _, _)| *id != self.0);
+    }
+}
+
+/// Enforces build timeouts. The watchdog stops when dropped.
+pub struct Watchdog {
+    shared: Arc<WatchdogShared>,
+    handle: Option<JoinHandle<()>>,
+}
+
+struct WatchdogShared {
+    timeouts: BuildTimeouts,
+    token: CancellationToken,
+    state: Mutex<WatchdogState>,
+    condvar: Condvar,
+}
+
+struct WatchdogState {
+    started: Instant,
+    phase: Option<(WatchedPhase, Instant)>,
+    stopped: bool,
+    cancelled_at: Option<Instant>,
+}
+
+impl Watchdog {
+    /// Starts a watchdog enforcing the given timeouts, cancelling the given token once a timeout is
+    /// exceeded.
+    pub fn start(timeouts: BuildTimeouts, token: CancellationToken) -> Self {
+        set_project_lock_timeout(timeouts.project_lock);
+        let shared = Arc::new(WatchdogShared {
+            timeouts,
+            token,
+            state: Mutex::new(WatchdogState {
+                started: Instant::now(),
+                phase: None,
+                stopped: false,
+                cancelled_at: None,
+            }),
+            condvar: Condvar::new(),
+        });
+        let handle = if !shared.timeouts.watched() {
+            None
+        } else {
+            let shared = shared.clone();
+            Some(
+                thread::Builder::new()
+                    .name("watchdog".to_string())
+                    .spawn(move || shared.run())
+                    .expect("could not start watchdog thread"),
+            )
+        };
+        Self { shared, handle }
+    }
+
+    /// Marks the start of a phase of the build. The timeout of the phase starts now.
+    pub fn enter_phase(&self, phase: WatchedPhase) {
+        debug!("watchdog entering {} phase", phase);
+        self.shared.state.lock().phase = Some((phase, Instant::now()));
+    }
+
+    /// Creates a dump of the state of the build
+    pub fn state_dump(&self) -> String {
+        let state = self.shared.state.lock();
+        self.shared.state_dump(&state)
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.shared.state.lock().stopped = true;
+        self.shared.condvar.notify_all();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl WatchdogShared {
+    fn run(&self) {
+        let mut state = self.state.lock();
+        while !state.stopped {
+            match state.cancelled_at {
+                None => {
+                    if let Some(reason) = self.exceeded(&state) {
+                        error!("{}, cancelling build", reason);
+                        error!("{}", self.state_dump(&state));
+                        self.token.cancel(reason);
+                        state.cancelled_at = Some(Instant::now());
+                    }
+                }
+                Some(cancelled_at) => {
+                    if cancelled_at.elapsed() >= CANCELLATION_GRACE_PERIOD {
+                        eprintln!(
+                            "build did not stop within {:?} of being cancelled, exiting",
+                            CANCELLATION_GRACE_PERIOD
+                        );
+                        eprintln!("{}", self.state_dump(&state));
+                        std::process::exit(TIMEOUT_EXIT_CODE);
+                    }
+                }
+            }
+            self.condvar.wait_for(&mut state, POLL_INTERVAL);
+        }
+    }
+
+    /// Gets the reason to cancel the build if any timeout has been exceeded
+    fn exceeded(&self, state: &WatchdogState) -> Option<CancellationReason> {
+        if let Some(limit) = self.timeouts.build {
+            if state.started.elapsed() >= limit {
+                return Some(CancellationReason::TimedOut {
+                    phase: "build".to_string(),
+                    limit,
+                });
+            }
+        }
+        let (phase, entered) = state.phase?;
+        let limit = self.timeouts.phase_timeout(phase)?;
+        if entered.elapsed() >= limit {
+            Some(CancellationReason::TimedOut {
+                phase: phase.to_string(),
+                limit,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn state_dump(&self, state: &WatchdogState) -> String {
+        let mut dump = String::from("build state:\n");
+        let _ = writeln!(dump, "  elapsed: {:.3?}", state.started.elapsed());
+        match state.phase {
+            Some((phase, entered)) => {
+                let _ = writeln!(dump, "  phase: {} (for {:.3?})", phase, entered.elapsed());
+            }
+            None => {
+                let _ = writeln!(dump, "  phase: none");
+            }
+        }
+        for (_, name, provider) in STATE_DUMPS.lock().iter() {
+            let _ = writeln!(dump, "  {}:", name);
+            for line in provider().lines() {
+                let _ = writeln!(dump, "    {}", line);
+            }
+        }
+        dump
+    }
+}
+
+/// An error parsing a duration
+#[derive(Debug, thiserror::Error)]
+#[error("invalid duration {0:?} (expected a value like 30s, 10m, or 1h30m)")]
+pub struct ParseDurationError(String);
+
+/// Parses a duration such as `90`, `30s`, `500ms`, `10m` or `1h30m`. A number without a unit is
+/// interpreted as seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, ParseDurationError> {
+    let error = || ParseDurationError(s.to_string());
+    let trimmed = s.trim();
+    if trimmed.is_empty() {
+        return Err(error());
+    }
+    if let Ok(seconds) = trimmed.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+
+    let mut total = Duration::ZERO;
+    let mut rest = trimmed;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(error)?;
+        if digits == 0 {
+            return Err(error());
+        }
+        let value: u64 = rest[..digits].parse().map_err(|_| error())?;
+        rest = &rest[digits..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let unit = match &rest[..unit_len] {
+            "ms" => Duration::from_millis(value),
+            "s" => Duration::from_secs(value),
+            "m" => Duration::from_secs(value * 60),
+            "h" => Duration::from_secs(value * 60 * 60),
+            _ => return Err(error()),
+        };
+        total += unit;
+        rest = &rest[unit_len..];
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("m").is_err());
+    }
+
+    #[test]
+    fn cancels_on_phase_timeout() {
+        let token = CancellationToken::new();
+        let watchdog = Watchdog::start(
+            BuildTimeouts {
+                execution: Some(Duration::from_millis(50)),
+                ..Default::default()
+            },
+            token.clone(),
+        );
+        let _dump = register_state_dump("running tasks", || ":root:build".to_string());
+        watchdog.enter_phase(WatchedPhase::Configuration);
+        thread::sleep(Duration::from_millis(150));
+        assert!(!token.is_cancelled(), "configuration has no timeout");
+
+        watchdog.enter_phase(WatchedPhase::Execution);
+        let start = Instant::now();
+        while !token.is_cancelled() && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(matches!(
+            token.reason(),
+            Some(CancellationReason::TimedOut { ref phase, .. }) if phase == "execution"
+        ));
+        assert!(watchdog.state_dump().contains(":root:build"));
+    }
+}
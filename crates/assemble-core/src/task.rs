@@ -2,8 +2,10 @@ use crate::exception::BuildResult;
 use crate::project::Project;
 
 use parking_lot::RwLock;
+use std::collections::HashSet;
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::identifier::TaskId;
@@ -11,21 +13,30 @@ use crate::identifier::TaskId;
 use crate::project::buildable::BuiltByContainer;
 
 pub mod action;
+#[cfg(feature = "async")]
+pub mod async_action;
 mod any_task;
 pub mod create_task;
 mod executable;
 pub mod flags;
 pub mod initialize_task;
 mod lazy_task;
+pub mod output_ownership;
 pub mod task_container;
 pub mod task_executor;
 pub mod task_io;
 mod task_ordering;
+pub mod task_progress;
+pub mod task_rule;
+pub mod task_weight;
+pub mod test_results;
+pub mod undeclared_io;
 pub mod up_to_date;
 pub mod work_handler;
 
 use crate::project::error::ProjectResult;
 use crate::task::flags::{OptionDeclarations, OptionsDecoder};
+use crate::task::task_weight::TaskWeight;
 use crate::task::up_to_date::UpToDate;
 pub use any_task::AnyTaskHandle;
 use create_task::CreateTask;
@@ -37,7 +48,8 @@ use task_io::TaskIO;
 pub use task_ordering::*;
 
 /// The outcome of task.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TaskOutcome {
     /// the task executed successfully
     Executed,
@@ -111,11 +123,24 @@ pub trait ExecutableTask: HasTaskId + Send + Sync {
     /// Check if this task marked itself as up to date
     fn task_up_to_date(&self) -> bool;
 
+    /// Why this task was skipped by an only-if predicate, if it was
+    fn skip_reason(&self) -> Option<String>;
+
     /// Gets the group of the task
     fn group(&self) -> String;
 
     /// Gets the description of the task
     fn description(&self) -> String;
+
+    /// Gets how many workers the task occupies while it executes
+    fn weight(&self) -> TaskWeight;
+
+    /// Gets the names of the actions of the task, in the order they run. Unnamed actions are
+    /// `None`.
+    fn action_names(&self) -> Vec<Option<String>>;
+
+    /// Gets the output files declared by the task
+    fn declared_outputs(&self) -> HashSet<PathBuf>;
 }
 
 assert_obj_safe!(ExecutableTask);
@@ -162,6 +187,10 @@ impl ExecutableTask for Box<dyn FullTask> {
         (**self).task_up_to_date()
     }
 
+    fn skip_reason(&self) -> Option<String> {
+        (**self).skip_reason()
+    }
+
     fn group(&self) -> String {
         (**self).group()
     }
@@ -169,6 +198,18 @@ impl ExecutableTask for Box<dyn FullTask> {
     fn description(&self) -> String {
         (**self).description()
     }
+
+    fn weight(&self) -> TaskWeight {
+        (**self).weight()
+    }
+
+    fn action_names(&self) -> Vec<Option<String>> {
+        (**self).action_names()
+    }
+
+    fn declared_outputs(&self) -> HashSet<PathBuf> {
+        (**self).declared_outputs()
+    }
 }
 
 impl<E: ExecutableTask> HasTaskId for Arc<RwLock<E>> {
@@ -198,6 +239,10 @@ impl<E: ExecutableTask + Send + Sync> ExecutableTask for Arc<RwLock<E>> {
         self.read().task_up_to_date()
     }
 
+    fn skip_reason(&self) -> Option<String> {
+        self.read().skip_reason()
+    }
+
     fn group(&self) -> String {
         self.read().group()
     }
@@ -205,6 +250,18 @@ impl<E: ExecutableTask + Send + Sync> ExecutableTask for Arc<RwLock<E>> {
     fn description(&self) -> String {
         self.read().description()
     }
+
+    fn weight(&self) -> TaskWeight {
+        self.read().weight()
+    }
+
+    fn action_names(&self) -> Vec<Option<String>> {
+        self.read().action_names()
+    }
+
+    fn declared_outputs(&self) -> HashSet<PathBuf> {
+        self.read().declared_outputs()
+    }
 }
 
 impl Debug for Box<dyn FullTask + Send + Sync> {
@@ -31,12 +31,16 @@ assert_obj_safe!(TaskAction<crate::defaults::tasks::Empty>);
 pub type DynamicTaskAction<T> = dyn Fn(&mut Executable<T>, &Project) -> BuildResult + Send + Sync;
 /// A structure to generically own a task action over `'static` lifetime
 pub struct Action<T: Task> {
+    name: Option<String>,
     func: Box<DynamicTaskAction<T>>,
 }
 
 impl<T: Task> Debug for Action<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Action<{}>", type_name::<T>())
+        match &self.name {
+            Some(name) => write!(f, "Action<{}>({:?})", type_name::<T>(), name),
+            None => write!(f, "Action<{}>", type_name::<T>()),
+        }
     }
 }
 
@@ -48,7 +52,43 @@ impl<T: Task> Action<T> {
         F: Send + Sync,
     {
         Self {
+            name: None,
             func: Box::new(func),
         }
     }
+
+    /// Creates a new named action from a function. Named actions can be found and removed by
+    /// other plugins.
+    pub fn named<S, F>(name: S, func: F) -> Self
+    where
+        S: AsRef<str>,
+        F: Fn(&mut Executable<T>, &Project) -> BuildResult + 'static,
+        F: Send + Sync,
+    {
+        Self {
+            name: Some(name.as_ref().to_string()),
+            func: Box::new(func),
+        }
+    }
+
+    /// The name of the action, if it has one
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+/// The name of the action that runs [`Task::task_action`](Task::task_action)
+pub const TASK_ACTION: &str = "task_action";
+
+/// Where an action is added within the actions of a task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActionPosition {
+    /// Before every other action
+    First,
+    /// After every other action
+    Last,
+    /// Immediately before the action with the given name
+    Before(String),
+    /// Immediately after the action with the given name
+    After(String),
 }
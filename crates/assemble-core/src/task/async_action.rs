@@ -0,0 +1,170 @@
+//! Task actions that run asynchronously. Requires the `async` feature.
+//!
+//! An async action is created from a function that reads what it needs from the task, then
+//! returns a future doing the work. The future runs on a [tokio](tokio) runtime shared by the
+//! whole build, next to the worker threads running tasks. This lets a single action wait on many
+//! operations at once, like downloading many files, without using a worker thread per operation.
+//!
+//! The worker running the task waits for the future to finish, and its result becomes the result
+//! of the action.
+//!
+//! ```no_run
+//! # use assemble_core::defaults::tasks::Empty;
+//! # use assemble_core::Executable;
+//! # fn configure(task: &mut Executable<Empty>) -> assemble_core::project::ProjectResult {
+//! task.do_last_async(|_task, _project| async move {
+//!     let doubled = (0..3).map(|i| tokio::spawn(async move { i * 2 }));
+//!     for value in doubled {
+//!         println!("{}", value.await.unwrap());
+//!     }
+//!     Ok(())
+//! })
+//! # }
+//! ```
+
+use crate::project::error::ProjectResult;
+use crate::task::action::{Action, ActionPosition};
+use crate::{BuildResult, Executable, Project, Task};
+use once_cell::sync::Lazy;
+use std::fmt::Debug;
+use std::future::Future;
+use std::num::NonZeroUsize;
+use tokio::runtime::{Builder, Runtime};
+
+static RUNTIME: Lazy<Runtime> = Lazy::new(|| {
+    let threads = std::thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1);
+    Builder::new_multi_thread()
+        .worker_threads(threads)
+        .thread_name("assemble-async")
+        .enable_all()
+        .build()
+        .expect("could not start async runtime")
+});
+
+/// The runtime async actions run on, started the first time it's used
+pub fn runtime() -> &'static Runtime {
+    &RUNTIME
+}
+
+/// Runs a future on the async runtime, blocking the current thread until it finishes. Panics
+/// within the future are resumed on the current thread.
+///
+/// # Panics
+/// If called from within the async runtime.
+pub fn block_on<F>(future: F) -> F::Output
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match runtime().block_on(runtime().spawn(future)) {
+        Ok(output) => output,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => panic!("async action was cancelled: {}", e),
+    }
+}
+
+impl<T: Task> Action<T> {
+    /// Creates a new action from a function returning a future
+    pub fn from_async<F, Fut>(func: F) -> Self
+    where
+        F: Fn(&mut Executable<T>, &Project) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = BuildResult> + Send + 'static,
+    {
+        Action::new(move |task, project| block_on(func(task, project)))
+    }
+
+    /// Creates a new named action from a function returning a future
+    pub fn named_async<S, F, Fut>(name: S, func: F) -> Self
+    where
+        S: AsRef<str>,
+        F: Fn(&mut Executable<T>, &Project) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = BuildResult> + Send + 'static,
+    {
+        Action::named(name, move |task, project| block_on(func(task, project)))
+    }
+}
+
+impl<T: 'static + Task + Send + Debug> Executable<T> {
+    /// Adds an async action that runs before every other action
+    #[track_caller]
+    pub fn do_first_async<F, Fut>(&mut self, a: F) -> ProjectResult
+    where
+        F: Fn(&mut Executable<T>, &Project) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = BuildResult> + Send + 'static,
+    {
+        self.add_action(ActionPosition::First, Action::from_async(a))
+    }
+
+    /// Adds an async action that runs after every other action
+    #[track_caller]
+    pub fn do_last_async<F, Fut>(&mut self, a: F) -> ProjectResult
+    where
+        F: Fn(&mut Executable<T>, &Project) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = BuildResult> + Send + 'static,
+    {
+        self.add_action(ActionPosition::Last, Action::from_async(a))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::defaults::tasks::Empty;
+    use crate::exception::BuildException;
+    use crate::task::ExecutableTask;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn async_actions_run_on_runtime() {
+        let project = Project::temp("async-actions");
+        let total = Arc::new(AtomicUsize::new(0));
+        let mut handle = project.register_task::<Empty>("task").unwrap();
+        let total_clone = total.clone();
+        handle
+            .configure_with(move |task, _| {
+                let total = total_clone.clone();
+                task.do_last_async(move |_, _| {
+                    let total = total.clone();
+                    async move {
+                        let handles = (1..=4)
+                            .map(|i| {
+                                let total = total.clone();
+                                tokio::spawn(async move {
+                                    total.fetch_add(i, Ordering::SeqCst);
+                                })
+                            })
+                            .collect::<Vec<_>>();
+                        for handle in handles {
+                            handle
+                                .await
+                                .map_err(|e| BuildException::custom(&e.to_string()))?;
+                        }
+                        Ok(())
+                    }
+                })
+            })
+            .unwrap();
+
+        project.with(|p| handle.execute(p)).unwrap();
+        assert_eq!(total.load(Ordering::SeqCst), 10);
+    }
+
+    #[test]
+    fn async_errors_fail_the_task() {
+        let project = Project::temp("async-errors");
+        let mut handle = project.register_task::<Empty>("task").unwrap();
+        handle
+            .configure_with(|task, _| {
+                task.do_first_async(|_, _| async {
+                    Err(BuildException::custom("download failed").into())
+                })
+            })
+            .unwrap();
+
+        let error = project.with(|p| handle.execute(p)).unwrap_err();
+        assert!(error.to_string().contains("download failed"));
+    }
+}
@@ -2,43 +2,62 @@ use super::Task;
 use crate::defaults::tasks::Empty;
 use crate::exception::BuildException;
 use crate::identifier::TaskId;
-use crate::project::buildable::{BuiltByContainer, IntoBuildable};
+use crate::lazy_evaluation::Provider;
+use crate::project::buildable::{BuiltByContainer, IntoBuildable, ProvidedBuildable};
 use crate::project::error::{ProjectError, ProjectResult};
+use crate::project::phase::{PhaseState, ProjectPhase};
 use crate::project::shared::WeakSharedProject;
-use crate::task::action::{Action, TaskAction};
+use crate::startup::cancellation::{build_cancellation, CancellationToken};
+use crate::task::action::{Action, ActionPosition, TaskAction, TASK_ACTION};
 use crate::task::flags::{OptionDeclarations, OptionsDecoder};
+use crate::task::output_ownership::{register_outputs, ExecutingTaskGuard};
 use crate::task::task_io::TaskIO;
+use crate::task::task_progress::TaskProgress;
+use crate::task::task_weight::TaskWeight;
+use crate::task::undeclared_io::{undeclared_io_policy, DeclaredIoGuard, UndeclaredIoPolicy};
 use crate::task::up_to_date::{UpToDate, UpToDateContainer};
 
 use crate::task::work_handler::WorkHandler;
 use crate::task::{BuildableTask, ExecutableTask, HasTaskId, TaskOrdering, TaskOrderingKind};
+use crate::workspace::{Workspace, WorkspaceDirectory};
 use crate::{BuildResult, Project};
 
-use log::{debug, error, trace};
+use log::{debug, error, info, trace, warn};
 
+use std::collections::HashSet;
 use std::fmt::{Debug, Formatter};
 
 use std::ops::{Deref, DerefMut};
+use std::panic::Location;
+use std::path::PathBuf;
 
 use crate::error::PayloadError;
 use crate::project::shared::SharedProject;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
+use std::time::Instant;
+
+type OnlyIf<T> = Box<dyn Fn(&Executable<T>, &Project) -> bool + Send + Sync>;
 
 /// The wrapped task itself
 pub struct Executable<T: Task> {
     pub task: T,
     project: WeakSharedProject,
     task_id: TaskId,
-    first: Mutex<Vec<Action<T>>>,
-    last: Mutex<Vec<Action<T>>>,
+    actions: Mutex<Vec<Action<T>>>,
     task_ordering: Vec<TaskOrdering>,
     queried: AtomicBool,
     up_to_date: UpToDateContainer<T>,
+    only_if: Vec<(String, OnlyIf<T>)>,
+    skip_reason: Option<String>,
+    phase: PhaseState,
     work: WorkHandler,
+    temp_dir: Option<PathBuf>,
+    progress: TaskProgress,
 
     description: String,
     group: String,
+    weight: TaskWeight,
 }
 
 assert_impl_all!(Executable<Empty> : Send);
@@ -59,14 +78,19 @@ impl<T: 'static + Task + Send + Debug> Executable<T> {
             task,
             project: shared.weak(),
             task_id: id.clone(),
-            first: Default::default(),
-            last: Default::default(),
+            actions: Mutex::new(vec![Action::named(TASK_ACTION, T::task_action)]),
             task_ordering: Default::default(),
             queried: AtomicBool::new(false),
             up_to_date: UpToDateContainer::default(),
+            only_if: vec![],
+            skip_reason: None,
+            phase: PhaseState::new(),
             work: WorkHandler::new(&id, cache_location),
+            temp_dir: None,
+            progress: TaskProgress::new(&id),
             description: T::description(),
             group: "".to_string(),
+            weight: TaskWeight::default(),
         }
     }
 
@@ -80,76 +104,136 @@ impl<T: 'static + Task + Send + Debug> Executable<T> {
         T::configure_io(self)
     }
 
+    #[track_caller]
     pub fn depends_on<B: IntoBuildable>(&mut self, buildable: B)
     where
         B::Buildable: 'static,
     {
+        if !self.configurable_or_warn("adding a dependency to", Location::caller()) {
+            return;
+        }
         trace!("adding depends ordering for {:?}", self);
         let buildable = TaskOrdering::depends_on(buildable);
         self.task_ordering.push(buildable);
     }
 
+    /// Adds a dependency on the task ids or task handles produced by a provider.
+    ///
+    /// Unlike [`depends_on`](Self::depends_on), which would use the tasks that produce the value
+    /// of a provider, the value itself is used as the dependency. The provider isn't queried until
+    /// the task graph is constructed, so it can depend on configuration that's set later.
+    #[track_caller]
+    pub fn depends_on_provider<B, P>(&mut self, provider: P)
+    where
+        B: IntoBuildable + Clone + Send + Sync + 'static,
+        P: Provider<B> + 'static,
+    {
+        self.depends_on(ProvidedBuildable::new(provider));
+    }
+
+    /// Adds an action that runs before every other action
+    #[track_caller]
     pub fn do_first<F>(&mut self, a: F) -> ProjectResult
     where
         F: Fn(&mut Executable<T>, &Project) -> BuildResult + 'static,
         F: Send + Sync,
     {
-        let action = Action::new(a);
-        self.first.lock().map_err(PayloadError::new)?.push(action);
-        Ok(())
+        self.add_action(ActionPosition::First, Action::new(a))
     }
 
+    /// Adds an action that runs after every other action
+    #[track_caller]
     pub fn do_last<F>(&mut self, a: F) -> ProjectResult
     where
         F: Fn(&mut Executable<T>, &Project) -> BuildResult + 'static,
         F: Send + Sync,
     {
-        let action = Action::new(a);
-        self.last.lock().map_err(PayloadError::new)?.push(action);
+        self.add_action(ActionPosition::Last, Action::new(a))
+    }
+
+    /// Adds a named action that runs before every other action
+    #[track_caller]
+    pub fn do_first_named<F>(&mut self, name: &str, a: F) -> ProjectResult
+    where
+        F: Fn(&mut Executable<T>, &Project) -> BuildResult + 'static,
+        F: Send + Sync,
+    {
+        self.add_action(ActionPosition::First, Action::named(name, a))
+    }
+
+    /// Adds a named action that runs after every other action
+    #[track_caller]
+    pub fn do_last_named<F>(&mut self, name: &str, a: F) -> ProjectResult
+    where
+        F: Fn(&mut Executable<T>, &Project) -> BuildResult + 'static,
+        F: Send + Sync,
+    {
+        self.add_action(ActionPosition::Last, Action::named(name, a))
+    }
+
+    /// Adds an action at a position. Fails if the action is named and an action with the same
+    /// name already exists, or if the position refers to an action that doesn't exist.
+    #[track_caller]
+    pub fn add_action(&mut self, position: ActionPosition, action: Action<T>) -> ProjectResult {
+        self.ensure_configurable("adding an action to", Location::caller())?;
+        let actions = self.actions.get_mut().map_err(PayloadError::new)?;
+        if let Some(name) = action.name() {
+            if actions.iter().any(|other| other.name() == Some(name)) {
+                return Err(PayloadError::new(ProjectError::custom(format!(
+                    "{} already has an action named {:?}",
+                    self.task_id, name
+                ))));
+            }
+        }
+        let index = match position {
+            ActionPosition::First => 0,
+            ActionPosition::Last => actions.len(),
+            ActionPosition::Before(name) => position_of(actions, &name, &self.task_id)?,
+            ActionPosition::After(name) => position_of(actions, &name, &self.task_id)? + 1,
+        };
+        actions.insert(index, action);
         Ok(())
     }
 
-    fn query_actions(&self) -> ProjectResult<(Vec<Action<T>>, Vec<Action<T>>)> {
+    /// Removes the action with the given name. Returns whether an action was removed.
+    #[track_caller]
+    pub fn remove_action(&mut self, name: &str) -> ProjectResult<bool> {
+        self.ensure_configurable("removing an action from", Location::caller())?;
+        let actions = self.actions.get_mut().map_err(PayloadError::new)?;
+        let before = actions.len();
+        actions.retain(|action| action.name() != Some(name));
+        Ok(actions.len() != before)
+    }
+
+    /// The names of the actions of this task, in the order they run. Unnamed actions are `None`.
+    pub fn action_names(&self) -> Vec<Option<String>> {
+        self.actions
+            .lock()
+            .map(|actions| {
+                actions
+                    .iter()
+                    .map(|action| action.name().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn actions(&self) -> ProjectResult<Vec<Box<dyn TaskAction<T>>>> {
         match self
             .queried
             .compare_exchange(false, true, Ordering::Release, Ordering::Relaxed)
         {
-            Ok(false) => {
-                let first: Vec<_> = self
-                    .first
-                    .lock()
-                    .map_err(PayloadError::new)?
-                    .drain(..)
-                    .rev()
-                    .collect();
-                let last: Vec<_> = self
-                    .last
-                    .lock()
-                    .map_err(PayloadError::new)?
-                    .drain(..)
-                    .collect();
-                Ok((first, last))
-            }
+            Ok(false) => Ok(self
+                .actions
+                .lock()
+                .map_err(PayloadError::new)?
+                .drain(..)
+                .map(|a| Box::new(a) as Box<dyn TaskAction<T>>)
+                .collect()),
             Ok(true) => unreachable!(),
             Err(_) => Err(ProjectError::ActionsAlreadyQueried.into()),
         }
     }
-
-    fn actions(&self) -> ProjectResult<Vec<Box<dyn TaskAction<T>>>> {
-        let mut output: Vec<Box<dyn TaskAction<T>>> = vec![];
-        let (first, last) = self.query_actions()?;
-        output.extend(
-            first
-                .into_iter()
-                .map(|a| Box::new(a) as Box<dyn TaskAction<T>>),
-        );
-        output.push(Box::new(T::task_action));
-        output.extend(
-            last.into_iter()
-                .map(|a| Box::new(a) as Box<dyn TaskAction<T>>),
-        );
-        Ok(output)
-    }
     pub fn project(&self) -> SharedProject {
         SharedProject::try_from(self.project.clone()).unwrap()
     }
@@ -158,6 +242,37 @@ impl<T: 'static + Task + Send + Debug> Executable<T> {
         &mut self.work
     }
 
+    /// Gets the progress of this task, which task actions can use to report how far along they
+    /// are. In the rich console, the progress is shown on the line of the task while it executes.
+    pub fn progress(&self) -> &TaskProgress {
+        &self.progress
+    }
+
+    /// Gets the temporary directory of this task, at `tmp/<task name>` within the build directory,
+    /// creating it if it doesn't exist. Tasks should keep their intermediate files here instead
+    /// of in the temporary directory of the system.
+    ///
+    /// The directory is registered as an output of this task, so it's tracked when checking if the
+    /// task is up-to-date and deleted when the task's outputs are cleaned.
+    pub fn temp_dir(&mut self, project: &Project) -> ProjectResult<PathBuf> {
+        if let Some(temp_dir) = &self.temp_dir {
+            return Ok(temp_dir.clone());
+        }
+        let temp_dir = Workspace::new(project.build_dir().get())
+            .temp_dir(&self.task_id)
+            .map_err(PayloadError::new)?
+            .absolute_path();
+        self.work.add_output(temp_dir.clone());
+        self.temp_dir = Some(temp_dir.clone());
+        Ok(temp_dir)
+    }
+
+    /// Gets the cancellation token of the build. Long running actions should poll it and stop
+    /// early once the build is cancelled.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        build_cancellation()
+    }
+
     fn handler_up_to_date(&self) -> bool {
         if !self.task.up_to_date() {
             return false;
@@ -173,6 +288,15 @@ impl<T: 'static + Task + Send + Debug> Executable<T> {
         self.group = group.to_string();
     }
 
+    /// Sets how many workers this task occupies while it executes. Tasks that use many threads or
+    /// a lot of memory, like linking, can use this to limit what runs alongside them.
+    #[track_caller]
+    pub fn set_weight(&mut self, weight: TaskWeight) {
+        if self.configurable_or_warn("setting the weight of", Location::caller()) {
+            self.weight = weight;
+        }
+    }
+
     /// Check to see if this task is already up-to-date before execution begins. Up-to-date handlers
     /// are ran first. If all up-to-date handlers return true, then shortcuts to returning true. If none declared, this task is always
     /// not up-to-date.
@@ -198,6 +322,12 @@ impl<T: 'static + Task + Send + Debug> Executable<T> {
                     return Ok(false);
                 }
 
+                // Check if values read from the environment have changed
+                if let Some(source) = self.work.environment_changed() {
+                    debug!("{} not up-to-date because {} changed", self.task_id, source);
+                    return Ok(false);
+                }
+
                 // Check if output is not up to date
                 Ok(if prev_o.up_to_date() {
                     true
@@ -210,14 +340,89 @@ impl<T: 'static + Task + Send + Debug> Executable<T> {
     }
 
     /// Add an up-to-date check
+    #[track_caller]
     pub fn up_to_date<F: Fn(&Executable<T>) -> bool + Send + Sync + 'static>(
         &mut self,
         configure: F,
     ) {
-        self.up_to_date.up_to_date_if(configure)
+        if self.configurable_or_warn("adding an up-to-date check to", Location::caller()) {
+            self.up_to_date.up_to_date_if(configure)
+        }
+    }
+
+    /// Only execute this task if the predicate holds. Predicates are checked in the order they're
+    /// added just before the task would execute, and the task is skipped if any doesn't hold.
+    #[track_caller]
+    pub fn only_if<F>(&mut self, predicate: F)
+    where
+        F: Fn(&Executable<T>, &Project) -> bool + Send + Sync + 'static,
+    {
+        self.only_if_because("an only-if condition wasn't met", predicate)
+    }
+
+    /// Only execute this task if the predicate holds, reporting the reason if it's skipped because
+    /// of it.
+    #[track_caller]
+    pub fn only_if_because<F>(&mut self, reason: impl AsRef<str>, predicate: F)
+    where
+        F: Fn(&Executable<T>, &Project) -> bool + Send + Sync + 'static,
+    {
+        if !self.configurable_or_warn("adding an only-if condition to", Location::caller()) {
+            return;
+        }
+        self.only_if
+            .push((reason.as_ref().to_string(), Box::new(predicate)));
+    }
+
+    /// Fails once this task has started executing, because changes to its configuration would be
+    /// ignored.
+    fn ensure_configurable(
+        &self,
+        action: &str,
+        caller: &'static Location<'static>,
+    ) -> ProjectResult {
+        self.phase
+            .ensure_configurable_at(format!("{} {}", action, self.task_id), caller)
+            .map_err(PayloadError::new)
+    }
+
+    /// Like [`ensure_configurable`](Self::ensure_configurable), for changes that can't fail.
+    /// Instead, the late change is logged and should be dropped.
+    fn configurable_or_warn(&self, action: &str, caller: &'static Location<'static>) -> bool {
+        match self.ensure_configurable(action, caller) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("{}", e);
+                false
+            }
+        }
+    }
+
+    /// The reason of the first only-if predicate that doesn't hold
+    fn unmet_only_if(&self, project: &Project) -> Option<String> {
+        self.only_if
+            .iter()
+            .find(|(_, predicate)| !predicate(self, project))
+            .map(|(reason, _)| reason.clone())
     }
 }
 
+fn position_of<T: Task>(
+    actions: &[Action<T>],
+    name: &str,
+    task_id: &TaskId,
+) -> ProjectResult<usize> {
+    actions
+        .iter()
+        .position(|action| action.name() == Some(name))
+        .ok_or_else(|| {
+            PayloadError::new(ProjectError::custom(format!(
+                "{} has no action named {:?}",
+                task_id, name
+            )))
+        })
+}
+
 impl<T: Task + Debug> Debug for Executable<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Executable")
@@ -293,6 +498,16 @@ impl<T: 'static + Task + Send + Sync + Debug> ExecutableTask for Executable<T> {
     }
 
     fn execute(&mut self, project: &Project) -> BuildResult {
+        self.phase.advance(ProjectPhase::Execution);
+        self.work.finalize_inputs()?;
+        self.skip_reason = self.unmet_only_if(project);
+        if let Some(reason) = &self.skip_reason {
+            debug!("skipping {} because {}", self.task_id, reason);
+            self.work.set_up_to_date(false);
+            self.work.set_did_work(false);
+            return Ok(());
+        }
+
         let up_to_date = if FORCE_RERUN.load(Ordering::Relaxed) {
             false
         } else {
@@ -301,10 +516,39 @@ impl<T: 'static + Task + Send + Sync + Debug> ExecutableTask for Executable<T> {
 
         let work = if !up_to_date {
             self.work().set_up_to_date(false);
-            (|| -> BuildResult {
+            match self.work.remove_stale_outputs() {
+                Ok(removed) if !removed.is_empty() => {
+                    info!(
+                        "removed {} stale outputs of {}",
+                        removed.len(),
+                        self.task_id
+                    )
+                }
+                Ok(_) => {}
+                Err(e) => warn!("could not remove stale outputs of {}: {}", self.task_id, e),
+            }
+            let _executing = ExecutingTaskGuard::enter(&self.task_id);
+            let _declared_io = (undeclared_io_policy() != UndeclaredIoPolicy::Off).then(|| {
+                let temp_dir =
+                    Workspace::new(project.build_dir().get()).temp_dir_path(&self.task_id);
+                DeclaredIoGuard::enter(
+                    &self.task_id,
+                    self.work.declared_inputs(),
+                    self.work.declared_outputs().into_iter().chain([temp_dir]),
+                )
+            });
+            let started = Instant::now();
+            let result = (|| -> BuildResult {
                 let actions = self.actions()?;
 
                 for action in actions {
+                    if let Some(reason) = self.cancellation_token().reason() {
+                        debug!(
+                            "not running remaining actions of {}: {}",
+                            self.task_id, reason
+                        );
+                        return Err(BuildException::new(reason).into());
+                    }
                     let result: BuildResult = action.execute(self, project);
                     match result {
                         Ok(()) => {}
@@ -317,7 +561,9 @@ impl<T: 'static + Task + Send + Sync + Debug> ExecutableTask for Executable<T> {
                 }
 
                 Ok(())
-            })()
+            })();
+            self.work.set_execution_time(started.elapsed());
+            result
         } else {
             self.work().set_up_to_date(true);
             self.work().set_did_work(false);
@@ -332,7 +578,13 @@ impl<T: 'static + Task + Send + Sync + Debug> ExecutableTask for Executable<T> {
             Ok(())
         };
 
-        if self.work.get_input()?.any_inputs() {
+        if work.is_ok() {
+            if let Ok(Some(output)) = self.work.get_output() {
+                register_outputs(&self.task_id, output.files());
+            }
+        }
+
+        if self.work.get_input()?.any_inputs() || self.work.read_environment() {
             if work.is_ok() {
                 if let Err(e) = self.work.store_execution_history() {
                     error!("encountered error while caching input: {}", e);
@@ -349,6 +601,10 @@ impl<T: 'static + Task + Send + Sync + Debug> ExecutableTask for Executable<T> {
         self.work.did_work()
     }
 
+    fn skip_reason(&self) -> Option<String> {
+        self.skip_reason.clone()
+    }
+
     fn task_up_to_date(&self) -> bool {
         *self.work.up_to_date()
     }
@@ -360,4 +616,146 @@ impl<T: 'static + Task + Send + Sync + Debug> ExecutableTask for Executable<T> {
     fn description(&self) -> String {
         self.description.clone()
     }
+
+    fn weight(&self) -> TaskWeight {
+        self.weight
+    }
+
+    fn action_names(&self) -> Vec<Option<String>> {
+        Executable::action_names(self)
+    }
+
+    fn declared_outputs(&self) -> HashSet<PathBuf> {
+        self.work.declared_outputs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::defaults::tasks::Empty;
+    use crate::task::action::{Action, ActionPosition, TASK_ACTION};
+    use crate::task::ExecutableTask;
+    use crate::{BuildResult, Executable, Project};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn named_actions_can_be_inserted_and_removed() {
+        let project = Project::temp("actions");
+        let ran = Arc::new(Mutex::new(vec![]));
+        let mut handle = project.register_task::<Empty>("task").unwrap();
+
+        let ran_clone = ran.clone();
+        handle
+            .configure_with(move |task, _| {
+                let record = |name: &'static str| {
+                    let ran = ran_clone.clone();
+                    move |_: &mut Executable<Empty>, _: &Project| -> BuildResult {
+                        ran.lock().unwrap().push(name);
+                        Ok(())
+                    }
+                };
+                task.do_first_named("first", record("first"))?;
+                task.do_last_named("last", record("last"))?;
+                task.do_last_named("removed", record("removed"))?;
+                task.add_action(
+                    ActionPosition::After(TASK_ACTION.to_string()),
+                    Action::named("after", record("after")),
+                )?;
+                task.add_action(
+                    ActionPosition::Before("first".to_string()),
+                    Action::new(record("unnamed")),
+                )?;
+                assert!(task.do_first_named("first", record("first")).is_err());
+                assert!(task
+                    .add_action(
+                        ActionPosition::Before("missing".to_string()),
+                        Action::new(record("missing")),
+                    )
+                    .is_err());
+                assert!(task.remove_action("removed")?);
+                assert!(!task.remove_action("removed")?);
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(
+            handle.action_names(),
+            [
+                None,
+                Some("first".to_string()),
+                Some(TASK_ACTION.to_string()),
+                Some("after".to_string()),
+                Some("last".to_string()),
+            ]
+        );
+        project.with(|p| handle.execute(p)).unwrap();
+        assert_eq!(*ran.lock().unwrap(), ["unnamed", "first", "after", "last"]);
+    }
+    #[test]
+    fn temp_dir_is_an_output() {
+        let project = Project::temp("temp-dir");
+        let mut handle = project.register_task::<Empty>("task").unwrap();
+        handle
+            .configure_with(|task, _| {
+                task.do_first(|task, project| {
+                    let temp_dir = task.temp_dir(project)?;
+                    assert!(temp_dir.ends_with("build/tmp/task"));
+                    assert!(temp_dir.is_dir());
+                    assert_eq!(task.temp_dir(project)?, temp_dir);
+                    assert!(task.declared_outputs().contains(&temp_dir));
+                    Ok(())
+                })
+            })
+            .unwrap();
+
+        project.with(|p| handle.execute(p)).unwrap();
+    }
+
+    #[test]
+    fn actions_cant_be_added_while_executing() {
+        let project = Project::temp("late-actions");
+        let mut handle = project.register_task::<Empty>("task").unwrap();
+        handle
+            .configure_with(|task, _| {
+                task.do_first(|task, _| {
+                    let error = task.do_last(|_, _| Ok(())).unwrap_err();
+                    assert!(error
+                        .to_string()
+                        .starts_with("adding an action to :late-actions:task at "));
+                    Ok(())
+                })
+            })
+            .unwrap();
+
+        project.with(|p| handle.execute(p)).unwrap();
+    }
+
+    #[test]
+    fn unmet_only_if_skips_task() {
+        let project = Project::temp("only-if");
+        let ran = Arc::new(Mutex::new(false));
+        let mut handle = project.register_task::<Empty>("task").unwrap();
+
+        let ran_clone = ran.clone();
+        handle
+            .configure_with(move |task, _| {
+                task.do_first(move |_, _| {
+                    *ran_clone.lock().unwrap() = true;
+                    Ok(())
+                })?;
+                task.only_if(|_, _| true);
+                task.only_if_because("the task is disabled", |_, _| false);
+                Ok(())
+            })
+            .unwrap();
+
+        project.with(|p| handle.execute(p)).unwrap();
+        assert!(!*ran.lock().unwrap());
+        assert!(!handle.did_work());
+        assert!(!handle.task_up_to_date());
+        assert_eq!(
+            handle.skip_reason().as_deref(),
+            Some("the task is disabled")
+        );
+    }
 }
@@ -1,6 +1,7 @@
 //! Add flags for tasks
 
 use log::error;
+use serde::{Deserialize, Serialize};
 use std::any::{type_name, Any, TypeId};
 use std::collections::HashMap;
 use std::error::Error;
@@ -295,7 +296,7 @@ pub enum OptionsSlurperError {
 }
 
 /// Provides the struct to decode options
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct WeakOptionsDecoder {
     option_dec_string: String,
     fed_options: HashMap<String, Vec<String>>,
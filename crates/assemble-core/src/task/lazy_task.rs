@@ -2,6 +2,8 @@ use std::any::type_name;
 use std::collections::HashSet;
 use std::fmt::{Debug, Formatter};
 use std::marker::PhantomData;
+use std::panic::Location;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
 use crate::defaults::tasks::Empty;
@@ -12,9 +14,11 @@ use crate::immutable::Immutable;
 use crate::lazy_evaluation::{Provider, ProviderError};
 use crate::project::buildable::{Buildable, IntoBuildable};
 use crate::project::error::{ProjectError, ProjectResult};
+use crate::project::phase::PhaseState;
 use crate::project::shared::SharedProject;
 use crate::project::shared::WeakSharedProject;
 use crate::task::flags::{OptionDeclarations, OptionsDecoder};
+use crate::task::task_weight::TaskWeight;
 use crate::task::up_to_date::UpToDate;
 use crate::task::{BuildableTask, FullTask, HasTaskId, TaskOrdering};
 use crate::{BuildResult, Executable, Project};
@@ -184,6 +188,7 @@ impl<T: Task + Send + Sync + Debug + 'static> ResolveInnerTask for TaskHandleInn
 pub struct TaskHandle<T: Task + Send + Sync + Debug + 'static> {
     id: TaskId,
     connection: Arc<Mutex<TaskHandleInner<T>>>,
+    phase: PhaseState,
 }
 
 impl<T: Task + Send + Sync + Debug + 'static> UpToDate for TaskHandle<T> {
@@ -209,10 +214,29 @@ impl<T: Task + Send + Sync + Debug + 'static> TaskHandle<T> {
         &self.id
     }
 
+    /// Adds a configuration to the task. Fails once the task graph is ready, because the
+    /// configuration could never take effect.
+    #[track_caller]
     pub fn configure_with<F>(&mut self, config: F) -> ProjectResult
     where
         F: FnOnce(&mut Executable<T>, &Project) -> ProjectResult + Send + 'static,
     {
+        self.configure_with_at(config, Location::caller())
+    }
+
+    /// Adds a configuration to the task, naming the given caller if the build is no longer being
+    /// configured
+    pub(crate) fn configure_with_at<F>(
+        &mut self,
+        config: F,
+        caller: &'static Location<'static>,
+    ) -> ProjectResult
+    where
+        F: FnOnce(&mut Executable<T>, &Project) -> ProjectResult + Send + 'static,
+    {
+        self.phase
+            .ensure_configurable_at(format!("configuring task {}", self.id), caller)
+            .map_err(PayloadError::new)?;
         let mut guard = self.connection.lock().map_err(PayloadError::new)?;
         match &mut *guard {
             TaskHandleInner::Lazy(lazy) => {
@@ -290,6 +314,7 @@ impl<T: Task + Send + Sync + Debug + 'static> Clone for TaskHandle<T> {
         Self {
             id: self.id.clone(),
             connection: self.connection.clone(),
+            phase: self.phase.clone(),
         }
     }
 }
@@ -336,6 +361,15 @@ impl<T: Task + Send + Sync + Debug + 'static> ExecutableTask for TaskHandle<T> {
         guard.bare_configured().unwrap().did_work()
     }
 
+    fn skip_reason(&self) -> Option<String> {
+        let mut guard = self
+            .connection
+            .lock()
+            .map_err(|_| BuildException::new("Could not get access to provider"))
+            .unwrap();
+        guard.bare_configured().unwrap().skip_reason()
+    }
+
     fn task_up_to_date(&self) -> bool {
         let mut guard = self
             .connection
@@ -352,6 +386,18 @@ impl<T: Task + Send + Sync + Debug + 'static> ExecutableTask for TaskHandle<T> {
     fn description(&self) -> String {
         self.configured(|e| e.description()).unwrap()
     }
+
+    fn weight(&self) -> TaskWeight {
+        self.configured(|e| e.weight()).unwrap()
+    }
+
+    fn action_names(&self) -> Vec<Option<String>> {
+        self.configured(|e| e.action_names()).unwrap()
+    }
+
+    fn declared_outputs(&self) -> HashSet<PathBuf> {
+        self.configured(|e| e.declared_outputs()).unwrap()
+    }
 }
 
 pub trait ResolveExecutable: ResolveInnerTask {
@@ -456,11 +502,12 @@ where
 #[derive(Debug)]
 pub struct TaskHandleFactory {
     project: WeakSharedProject,
+    phase: PhaseState,
 }
 
 impl TaskHandleFactory {
-    pub(crate) fn new(project: WeakSharedProject) -> Self {
-        Self { project }
+    pub(crate) fn new(project: WeakSharedProject, phase: PhaseState) -> Self {
+        Self { project, phase }
     }
 
     /// Creates a task handle that's not configured
@@ -473,6 +520,7 @@ impl TaskHandleFactory {
         Ok(TaskHandle {
             id,
             connection: Arc::new(Mutex::new(inner)),
+            phase: self.phase.clone(),
         })
     }
 }
@@ -0,0 +1,164 @@
+//! Tracks which task owns each output, and checks writes made through managed file APIs against
+//! those owners.
+//!
+//! Once a task has executed, its outputs are registered as being owned by that task. Afterwards,
+//! when another task creates a file through [`RegularFile::create`](crate::file::RegularFile::create)
+//! or a [`Workspace`](crate::workspace::Workspace) within one of those outputs, a warning is emitted.
+//! If strict output ownership is enabled, the write fails instead.
+//!
+//! Before any task executes, tasks that declare overlapping outputs without an ordering between
+//! them fail the build, unless [overlapping outputs](warn_overlapping_outputs) are only warned of.
+
+use crate::identifier::TaskId;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// If set to true, writing into the output of another task is an error instead of a warning
+pub static STRICT_OUTPUT_OWNERSHIP: AtomicBool = AtomicBool::new(false);
+pub fn strict_output_ownership(value: bool) {
+    STRICT_OUTPUT_OWNERSHIP.store(value, Ordering::Relaxed)
+}
+
+/// If set to true, tasks declaring overlapping outputs without an ordering between them only emit
+/// a warning, instead of failing the build
+pub static WARN_OVERLAPPING_OUTPUTS: AtomicBool = AtomicBool::new(false);
+pub fn warn_overlapping_outputs(value: bool) {
+    WARN_OVERLAPPING_OUTPUTS.store(value, Ordering::Relaxed)
+}
+
+static OUTPUT_OWNERS: Lazy<RwLock<HashMap<PathBuf, TaskId>>> = Lazy::new(Default::default);
+
+thread_local! {
+    static EXECUTING_TASK: RefCell<Option<TaskId>> = RefCell::new(None);
+}
+
+/// A task wrote into a path owned by a different task
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{writer} wrote to {path:?}, which is an output of {owner}")]
+pub struct OutputConflict {
+    /// The task that performed the write
+    pub writer: TaskId,
+    /// The task that owns the output
+    pub owner: TaskId,
+    /// The path that was written to
+    pub path: PathBuf,
+}
+
+/// Marks the current thread as executing a task until dropped
+#[derive(Debug)]
+pub struct ExecutingTaskGuard {
+    previous: Option<TaskId>,
+}
+
+impl ExecutingTaskGuard {
+    /// Marks the current thread as executing the given task
+    pub fn enter(task: &TaskId) -> Self {
+        let previous = EXECUTING_TASK.with(|cell| cell.replace(Some(task.clone())));
+        Self { previous }
+    }
+}
+
+impl Drop for ExecutingTaskGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        EXECUTING_TASK.with(|cell| *cell.borrow_mut() = previous);
+    }
+}
+
+/// Gets the task executing on the current thread, if any
+pub fn executing_task() -> Option<TaskId> {
+    EXECUTING_TASK.with(|cell| cell.borrow().clone())
+}
+
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map(|dir| dir.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+/// Registers paths as outputs owned by a task. Paths already owned by another task keep their
+/// original owner.
+pub fn register_outputs<I: IntoIterator<Item = P>, P: AsRef<Path>>(task: &TaskId, outputs: I) {
+    let mut owners = OUTPUT_OWNERS.write();
+    for output in outputs {
+        owners
+            .entry(normalize(output.as_ref()))
+            .or_insert_with(|| task.clone());
+    }
+}
+
+/// Gets the task owning a path. A path is owned by the task that owns it, or the nearest of its
+/// ancestors.
+pub fn output_owner(path: &Path) -> Option<TaskId> {
+    let path = normalize(path);
+    let owners = OUTPUT_OWNERS.read();
+    path.ancestors().find_map(|p| owners.get(p).cloned())
+}
+
+/// Checks whether the executing task can write to a path.
+///
+/// Writes performed outside of a task, or by the task that owns the path, are always allowed.
+/// Otherwise a warning is emitted, or an error is returned when strict output ownership is enabled.
+pub fn check_write(path: &Path) -> Result<(), OutputConflict> {
+    let writer = match executing_task() {
+        Some(writer) => writer,
+        None => return Ok(()),
+    };
+    match output_owner(path) {
+        Some(owner) if owner != writer => {
+            let conflict = OutputConflict {
+                writer,
+                owner,
+                path: path.to_path_buf(),
+            };
+            if STRICT_OUTPUT_OWNERSHIP.load(Ordering::Relaxed) {
+                Err(conflict)
+            } else {
+                warn!("{}", conflict);
+                Ok(())
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn detects_writes_into_other_outputs() {
+        let temp_dir = TempDir::new().unwrap();
+        let owner = TaskId::new("owner").unwrap();
+        let writer = TaskId::new("writer").unwrap();
+        let output_dir = temp_dir.path().join("owned");
+        register_outputs(&owner, [&output_dir]);
+
+        let inner = output_dir.join("nested").join("file.txt");
+        assert_eq!(output_owner(&inner), Some(owner.clone()));
+        assert!(check_write(&inner).is_ok(), "not executing a task");
+
+        {
+            let _guard = ExecutingTaskGuard::enter(&owner);
+            assert!(check_write(&inner).is_ok());
+        }
+        strict_output_ownership(true);
+        {
+            let _guard = ExecutingTaskGuard::enter(&writer);
+            let conflict = check_write(&inner).unwrap_err();
+            assert_eq!(conflict.owner, owner);
+            assert!(check_write(&temp_dir.path().join("other.txt")).is_ok());
+        }
+        strict_output_ownership(false);
+        assert_eq!(executing_task(), None);
+    }
+}
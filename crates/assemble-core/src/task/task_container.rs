@@ -5,16 +5,20 @@ use crate::project::error::{ProjectError, ProjectResult};
 use crate::project::shared::WeakSharedProject;
 use crate::task::any_task::AnyTaskHandle;
 use crate::task::lazy_task::TaskHandle;
+use crate::task::task_rule::TaskRule;
 use crate::task::TaskHandleFactory;
 use crate::{Executable, Project, Task};
 use once_cell::sync::OnceCell;
 
 use crate::error::PayloadError;
 use crate::project::finder::TaskPath;
+use crate::project::phase::PhaseState;
 use crate::project::shared::SharedProject;
 use itertools::Itertools;
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::panic::Location;
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct TaskContainer {
@@ -22,17 +26,21 @@ pub struct TaskContainer {
     task_id_factory: TaskIdFactory,
     handle_factory: OnceCell<TaskHandleFactory>,
     mapping: HashMap<TaskId, AnyTaskHandle>,
+    rules: Vec<Arc<TaskRule>>,
+    phase: PhaseState,
 }
 
 impl TaskContainer {
     /// Creates a new task container. Tasks can not be registered until a project has been shared with
-    /// the task container.
-    pub fn new(id_factory: TaskIdFactory) -> Self {
+    /// the task container, or once the build has left the configuration phase.
+    pub fn new(id_factory: TaskIdFactory, phase: PhaseState) -> Self {
         Self {
             shared: OnceCell::new(),
             task_id_factory: id_factory,
             handle_factory: OnceCell::new(),
             mapping: HashMap::new(),
+            rules: vec![],
+            phase,
         }
     }
 
@@ -42,7 +50,7 @@ impl TaskContainer {
             .set(project.clone())
             .expect("shared already set");
         self.handle_factory
-            .set(TaskHandleFactory::new(project.clone()))
+            .set(TaskHandleFactory::new(project.clone(), self.phase.clone()))
             .expect("factory already set");
     }
 
@@ -59,11 +67,24 @@ impl TaskContainer {
         weak.clone().upgrade().expect("should be not weak")
     }
 
+    #[track_caller]
     pub fn register_task<T: Task + Send + Sync + Debug + 'static>(
         &mut self,
         id: &str,
+    ) -> ProjectResult<TaskHandle<T>> {
+        self.register_task_at(id, Location::caller())
+    }
+
+    /// Registers a task, naming the given caller if the build is no longer being configured
+    pub(crate) fn register_task_at<T: Task + Send + Sync + Debug + 'static>(
+        &mut self,
+        id: &str,
+        caller: &'static Location<'static>,
     ) -> ProjectResult<TaskHandle<T>> {
         let id = self.task_id_factory.create(id).map_err(PayloadError::new)?;
+        self.phase
+            .ensure_configurable_at(format!("registering task {}", id), caller)
+            .map_err(PayloadError::new)?;
 
         if self.mapping.contains_key(&id) {
             panic!("Task with id {} already registered", id);
@@ -77,6 +98,7 @@ impl TaskContainer {
         self.mapping.insert(id, any_task_handle);
         Ok(handle)
     }
+    #[track_caller]
     pub fn register_task_with<
         T: Task + Send + Sync + Debug + 'static,
         F: 'static + Send + FnOnce(&mut Executable<T>, &Project) -> ProjectResult,
@@ -85,8 +107,9 @@ impl TaskContainer {
         id: &str,
         config: F,
     ) -> ProjectResult<TaskHandle<T>> {
-        let mut handle = self.register_task::<T>(id)?;
-        handle.configure_with(config)?;
+        let caller = Location::caller();
+        let mut handle = self.register_task_at::<T>(id, caller)?;
+        handle.configure_with_at(config, caller)?;
         Ok(handle)
     }
 
@@ -99,4 +122,22 @@ impl TaskContainer {
     pub fn get_task(&self, id: &TaskId) -> Option<&AnyTaskHandle> {
         self.mapping.get(id)
     }
+
+    /// Adds a rule creating tasks on demand. See [`task_rule`](crate::task::task_rule).
+    pub fn add_rule(&mut self, rule: TaskRule) {
+        self.rules.push(Arc::new(rule));
+    }
+
+    /// The task rules of this container, in the order they were added
+    pub fn rules(&self) -> &[Arc<TaskRule>] {
+        &self.rules
+    }
+
+    /// The first rule matching a task name
+    pub(crate) fn matching_rule(&self, name: &str) -> Option<Arc<TaskRule>> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(name).is_some())
+            .cloned()
+    }
 }
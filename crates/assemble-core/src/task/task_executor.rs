@@ -6,21 +6,40 @@ use crate::task::ExecutableTask;
 
 use crate::work_queue::{TypedWorkerQueue, WorkerExecutor};
 use crate::BuildResult;
+use crossbeam::channel::{unbounded, Receiver, Sender};
 use std::any::Any;
 use std::sync::Arc;
 
 use crate::project::finder::{ProjectFinder, ProjectPath, ProjectPathBuf};
 use crate::project::shared::SharedProject;
 use parking_lot::RwLock;
+use std::collections::HashMap;
 use std::io;
+use std::time::Instant;
 
 /// The task executor. Implemented on top of a thread pool to maximize parallelism.
 pub struct TaskExecutor<'exec> {
     task_queue: TypedWorkerQueue<'exec, TaskWork>,
     project: SharedProject,
     task_returns: Arc<RwLock<Vec<(TaskId, BuildResult<(bool, bool)>)>>>,
+    start_times: TaskStartTimes,
+    skip_reasons: TaskSkipReasons,
+    /// Sent to when a queued task completes, including if it panicked
+    completed: (Sender<()>, Receiver<()>),
+    /// The number of queued tasks whose results haven't been returned yet
+    running: usize,
+    /// The number of workers tasks can occupy
+    capacity: usize,
+    /// The number of workers occupied by each running task
+    occupied: HashMap<TaskId, usize>,
 }
 
+/// The instants tasks started executing on a worker
+pub type TaskStartTimes = Arc<RwLock<HashMap<TaskId, Instant>>>;
+
+/// Why tasks were skipped by their only-if predicates
+pub type TaskSkipReasons = Arc<RwLock<HashMap<TaskId, String>>>;
+
 impl<'exec> TaskExecutor<'exec> {
     /// Create a new task executor
     pub fn new(project: SharedProject, executor: &'exec WorkerExecutor) -> Self {
@@ -29,11 +48,44 @@ impl<'exec> TaskExecutor<'exec> {
             task_queue: typed_queue,
             project,
             task_returns: Default::default(),
+            start_times: Default::default(),
+            skip_reasons: Default::default(),
+            completed: unbounded(),
+            running: 0,
+            capacity: executor.max_jobs(),
+            occupied: HashMap::new(),
         }
     }
 
-    /// Queue a task to be executed
+    /// Gets the instants queued tasks started executing. Tasks are added once a worker picks them
+    /// up, so the time between queueing a task and its start time is the time spent waiting for a
+    /// worker.
+    pub fn start_times(&self) -> TaskStartTimes {
+        self.start_times.clone()
+    }
+
+    /// Gets why finished tasks were skipped. Only tasks skipped by an only-if predicate are
+    /// present.
+    pub fn skip_reasons(&self) -> TaskSkipReasons {
+        self.skip_reasons.clone()
+    }
+
+    /// The number of workers not occupied by running tasks
+    pub fn free_workers(&self) -> usize {
+        self.capacity
+            .saturating_sub(self.occupied.values().sum::<usize>())
+    }
+
+    /// Whether enough workers are free to run a task, given its [weight](ExecutableTask::weight)
+    pub fn can_queue<E: ExecutableTask + ?Sized>(&self, task: &E) -> bool {
+        task.weight().workers(self.capacity) <= self.free_workers()
+    }
+
+    /// Queue a task to be executed. The task occupies workers according to its
+    /// [weight](ExecutableTask::weight) until it finishes, even if not enough workers are free.
     pub fn queue_task<E: ExecutableTask + 'static>(&mut self, task: E) -> io::Result<()> {
+        let id = task.task_id();
+        let weight = task.weight().workers(self.capacity);
         let project = task
             .task_id()
             .project_id()
@@ -49,8 +101,17 @@ impl<'exec> TaskExecutor<'exec> {
             .find(&ProjectPathBuf::from(project))
             .expect("should exist");
 
-        let token = TaskWork::new(Box::new(task), &project, &self.task_returns);
+        let token = TaskWork::new(
+            Box::new(task),
+            &project,
+            &self.task_returns,
+            &self.start_times,
+            &self.skip_reasons,
+            &self.completed.0,
+        );
         let _ = self.task_queue.submit(token)?;
+        self.occupied.insert(id, weight);
+        self.running += 1;
         Ok(())
     }
 
@@ -58,8 +119,25 @@ impl<'exec> TaskExecutor<'exec> {
     /// vector must be used
     #[must_use]
     pub fn finished_tasks(&mut self) -> Vec<(TaskId, BuildResult<(bool, bool)>)> {
-        let mut guard = self.task_returns.write();
-        guard.drain(..).collect()
+        let finished = self.task_returns.write().drain(..).collect::<Vec<_>>();
+        self.running -= finished.len();
+        for (id, _) in &finished {
+            self.occupied.remove(id);
+        }
+        finished
+    }
+
+    /// Gets finished tasks along with their build result, blocking until a queued task completes
+    /// if none have finished yet. Returns immediately if no tasks are running. May return no tasks
+    /// if a task panicked.
+    #[must_use]
+    pub fn wait_for_finished_tasks(&mut self) -> Vec<(TaskId, BuildResult<(bool, bool)>)> {
+        let finished = self.finished_tasks();
+        if !finished.is_empty() || self.running == 0 {
+            return finished;
+        }
+        let _ = self.completed.1.recv();
+        self.finished_tasks()
     }
 
     /// Wait for all running and queued tasks to finish.
@@ -96,6 +174,9 @@ mod hidden {
         exec: Box<dyn ExecutableTask>,
         project: WeakSharedProject,
         return_vec: Arc<RwLock<Vec<(TaskId, BuildResult<(bool, bool)>)>>>,
+        start_times: TaskStartTimes,
+        skip_reasons: TaskSkipReasons,
+        completed: Sender<()>,
     }
 
     impl TaskWork {
@@ -103,11 +184,17 @@ mod hidden {
             exec: Box<dyn ExecutableTask>,
             project: &SharedProject,
             return_vec: &Arc<RwLock<Vec<(TaskId, BuildResult<(bool, bool)>)>>>,
+            start_times: &TaskStartTimes,
+            skip_reasons: &TaskSkipReasons,
+            completed: &Sender<()>,
         ) -> Self {
             Self {
                 exec,
                 project: project.weak(),
                 return_vec: return_vec.clone(),
+                start_times: start_times.clone(),
+                skip_reasons: skip_reasons.clone(),
+                completed: completed.clone(),
             }
         }
     }
@@ -115,7 +202,9 @@ mod hidden {
     impl ToWorkToken for TaskWork {
         fn on_start(&self) -> Box<dyn Fn() + Send + Sync> {
             let id = self.exec.task_id();
+            let start_times = self.start_times.clone();
             Box::new(move || {
+                start_times.write().insert(id.clone(), Instant::now());
                 LOGGING_CONTROL.start_task(&id);
                 LOGGING_CONTROL.in_task(id.clone());
                 trace!("{} starting task {}", thread::current().name().unwrap(), id);
@@ -124,10 +213,12 @@ mod hidden {
 
         fn on_complete(&self) -> Box<dyn Fn() + Send + Sync> {
             let id = self.exec.task_id();
+            let completed = self.completed.clone();
             Box::new(move || {
                 trace!("{} finished task {}", thread::current().name().unwrap(), id);
                 LOGGING_CONTROL.end_task(&id);
                 LOGGING_CONTROL.reset();
+                let _ = completed.send(());
             })
         }
 
@@ -140,6 +231,11 @@ mod hidden {
                 let output = { self.exec.execute(&*project) };
                 let up_to_date = self.exec.task_up_to_date();
                 let did_work = self.exec.did_work();
+                if let Some(reason) = self.exec.skip_reason() {
+                    self.skip_reasons
+                        .write()
+                        .insert(self.exec.task_id(), reason);
+                }
                 let mut write_guard = self.return_vec.write();
 
                 let status = (self.exec.task_id(), output.map(|_| (up_to_date, did_work)));
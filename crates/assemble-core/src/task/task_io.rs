@@ -1,5 +1,6 @@
 use crate::__export::ProjectResult;
 use crate::task::work_handler::output::Output;
+use crate::task::work_handler::WorkHandler;
 use crate::{Executable, Task};
 
 pub mod work;
@@ -16,3 +17,16 @@ pub trait TaskIO<T: Task = Self> {
         Ok(())
     }
 }
+
+/// Configures the inputs and outputs of a part of a task, such as a spec nested within it. Tasks
+/// deriving `TaskIO` include it with an `#[output(nested)]` field.
+pub trait NestedTaskIO {
+    /// Configures the inputs and outputs of this part of a task. Their ids are prefixed by the name
+    /// of the field this is nested in.
+    fn configure_nested_io(&self, name: &str, work: &mut WorkHandler) -> ProjectResult;
+
+    /// Recovers outputs from previous run if up-to-date
+    fn recover_nested_outputs(&mut self, _name: &str, _output: &Output) -> ProjectResult {
+        Ok(())
+    }
+}
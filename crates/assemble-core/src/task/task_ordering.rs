@@ -3,6 +3,7 @@ use crate::prelude::ProjectResult;
 use crate::project::buildable::{Buildable, IntoBuildable};
 
 use crate::Project;
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
@@ -56,7 +57,7 @@ impl TaskOrdering {
 }
 
 /// The kind of task ordering to establish temporal dependencies between tasks and buildables
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum TaskOrderingKind {
     DependsOn,
     FinalizedBy,
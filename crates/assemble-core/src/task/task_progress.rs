@@ -0,0 +1,142 @@
+//! Progress reported by a task while it executes.
+//!
+//! Task actions can report how far along they are through [`Executable::progress`], such as the
+//! number of files downloaded so far. In the rich console, the progress is shown on the line of the
+//! task in the work in progress region.
+//!
+//! ```no_run
+//! # use assemble_core::defaults::tasks::Empty;
+//! # use assemble_core::Executable;
+//! # fn configure(task: &mut Executable<Empty>) -> assemble_core::project::error::ProjectResult {
+//! task.do_last(|task, _project| {
+//!     let files = ["a.txt", "b.txt", "c.txt"];
+//!     task.progress().set_total(files.len() as u64);
+//!     for file in files {
+//!         task.progress().set_message(format!("downloading {}", file));
+//!         // ...
+//!         task.progress().advance(1);
+//!     }
+//!     Ok(())
+//! })
+//! # }
+//! ```
+//!
+//! [`Executable::progress`]: crate::Executable::progress
+
+use crate::identifier::TaskId;
+use crate::logging::LOGGING_CONTROL;
+use parking_lot::Mutex;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+/// How far along a task is
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProgressState {
+    /// The amount of work done so far
+    pub position: u64,
+    /// The total amount of work, if known
+    pub total: Option<u64>,
+    /// What the task is currently doing
+    pub message: Option<String>,
+}
+
+impl ProgressState {
+    /// Whether no progress has been reported
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+impl Display for ProgressState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let counter = match self.total {
+            Some(total) => Some(format!("[{}/{}]", self.position.min(total), total)),
+            None if self.position > 0 => Some(format!("[{}]", self.position)),
+            None => None,
+        };
+        match (counter, &self.message) {
+            (Some(counter), Some(message)) => write!(f, "{} {}", counter, message),
+            (Some(counter), None) => write!(f, "{}", counter),
+            (None, Some(message)) => write!(f, "{}", message),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Reports the progress of a task. Every change is sent to the console.
+#[derive(Debug, Clone)]
+pub struct TaskProgress {
+    task: TaskId,
+    state: Arc<Mutex<ProgressState>>,
+}
+
+impl TaskProgress {
+    /// Creates the progress of a task, with no progress reported
+    pub fn new(task: &TaskId) -> Self {
+        Self {
+            task: task.clone(),
+            state: Default::default(),
+        }
+    }
+
+    /// Sets the total amount of work
+    pub fn set_total(&self, total: u64) {
+        self.update(|state| state.total = Some(total));
+    }
+
+    /// Adds to the amount of work done
+    pub fn advance(&self, delta: u64) {
+        self.update(|state| state.position = state.position.saturating_add(delta));
+    }
+
+    /// Sets the amount of work done
+    pub fn set_position(&self, position: u64) {
+        self.update(|state| state.position = position);
+    }
+
+    /// Sets what the task is currently doing
+    pub fn set_message(&self, message: impl Display) {
+        let message = message.to_string();
+        self.update(|state| state.message = Some(message));
+    }
+
+    /// Clears the progress reported so far
+    pub fn reset(&self) {
+        self.update(|state| *state = ProgressState::default());
+    }
+
+    /// The progress reported so far
+    pub fn state(&self) -> ProgressState {
+        self.state.lock().clone()
+    }
+
+    fn update(&self, func: impl FnOnce(&mut ProgressState)) {
+        let mut state = self.state.lock();
+        func(&mut state);
+        LOGGING_CONTROL.task_status(&self.task, state.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_is_displayed_compactly() {
+        let progress = TaskProgress::new(&TaskId::new("download").unwrap());
+        assert!(progress.state().is_empty());
+        assert_eq!(progress.state().to_string(), "");
+
+        progress.advance(2);
+        assert_eq!(progress.state().to_string(), "[2]");
+        progress.set_total(10);
+        progress.advance(1);
+        progress.set_message("fetching c.txt");
+        assert_eq!(progress.state().to_string(), "[3/10] fetching c.txt");
+
+        progress.set_position(20);
+        assert_eq!(progress.state().to_string(), "[10/10] fetching c.txt");
+        progress.reset();
+        assert!(progress.state().is_empty());
+    }
+}
@@ -0,0 +1,145 @@
+//! Task rules, which create tasks on demand from their names.
+//!
+//! A rule is registered on a [`TaskContainer`](crate::task::task_container::TaskContainer) with a
+//! pattern such as `clean<TaskName>`. When a task that isn't registered is requested, and its name
+//! matches the pattern of a rule, the rule is given the chance to register it before the task is
+//! reported as missing. Rules are only evaluated while the project can still be configured.
+//!
+//! # Example
+//! ```
+//! # use assemble_core::Project;
+//! use assemble_core::defaults::tasks::Empty;
+//! use assemble_core::task::task_rule::TaskRule;
+//! # let project = Project::temp(None);
+//! project.with_mut(|p| {
+//!     p.task_container_mut().add_rule(TaskRule::new(
+//!         "ping<Host>",
+//!         "Pings a host",
+//!         |name, _host, project| {
+//!             project.task_container_mut().register_task::<Empty>(name)?;
+//!             Ok(())
+//!         },
+//!     ));
+//! });
+//! assert!(project.apply_task_rules("pingLocalhost").unwrap().is_some());
+//! ```
+
+use crate::project::error::ProjectResult;
+use crate::Project;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+type RuleAction = dyn Fn(&str, &str, &mut Project) -> ProjectResult + Send + Sync;
+
+/// A rule creating tasks whose names match a pattern
+#[derive(Clone)]
+pub struct TaskRule {
+    pattern: String,
+    prefix: String,
+    suffix: String,
+    description: String,
+    action: Arc<RuleAction>,
+}
+
+impl TaskRule {
+    /// Creates a task rule. The pattern contains a single placeholder in angle brackets, such as
+    /// `clean<TaskName>`, which matches any name starting with an uppercase letter.
+    ///
+    /// When applied, the action is given the name of the requested task, the matched part of the
+    /// name with its first letter lowercased (`buildDocs` for `cleanBuildDocs`), and the project the
+    /// task is requested in. The action may decide not to register anything, in which case the task
+    /// is still missing.
+    ///
+    /// # Panics
+    /// Panics if the pattern doesn't contain exactly one placeholder.
+    pub fn new<F>(pattern: &str, description: &str, action: F) -> Self
+    where
+        F: Fn(&str, &str, &mut Project) -> ProjectResult + Send + Sync + 'static,
+    {
+        let (prefix, rest) = pattern
+            .split_once('<')
+            .unwrap_or_else(|| panic!("task rule pattern {:?} has no placeholder", pattern));
+        let (_, suffix) = rest
+            .split_once('>')
+            .unwrap_or_else(|| panic!("task rule pattern {:?} is missing a '>'", pattern));
+        if suffix.contains('<') {
+            panic!(
+                "task rule pattern {:?} has more than one placeholder",
+                pattern
+            );
+        }
+        Self {
+            pattern: pattern.to_string(),
+            prefix: prefix.to_string(),
+            suffix: suffix.to_string(),
+            description: description.to_string(),
+            action: Arc::new(action),
+        }
+    }
+
+    /// The pattern of task names this rule matches
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// The description of this rule
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Matches a task name against the pattern of this rule, returning the matched part of the name
+    /// with its first letter lowercased
+    pub fn matches(&self, name: &str) -> Option<String> {
+        let matched = name
+            .strip_prefix(&self.prefix)?
+            .strip_suffix(&self.suffix)?;
+        let mut chars = matched.chars();
+        let first = chars.next().filter(|c| c.is_uppercase())?;
+        Some(first.to_lowercase().chain(chars).collect())
+    }
+
+    /// Applies this rule to a task name, if it matches
+    pub(crate) fn apply(&self, name: &str, project: &mut Project) -> ProjectResult<bool> {
+        match self.matches(name) {
+            Some(matched) => {
+                debug!("applying task rule {} to {}", self.pattern, name);
+                (self.action)(name, &matched, project)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+impl Debug for TaskRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TaskRule")
+            .field("pattern", &self.pattern)
+            .field("description", &self.description)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_patterns() {
+        let rule = TaskRule::new("clean<TaskName>", "", |_, _, _| Ok(()));
+        assert_eq!(rule.matches("cleanBuildDocs").as_deref(), Some("buildDocs"));
+        assert_eq!(rule.matches("cleanbuildDocs"), None);
+        assert_eq!(rule.matches("clean"), None);
+        assert_eq!(rule.matches("buildDocs"), None);
+
+        let rule = TaskRule::new("run<Example>Debug", "", |_, _, _| Ok(()));
+        assert_eq!(rule.matches("runHelloDebug").as_deref(), Some("hello"));
+        assert_eq!(rule.matches("runHello"), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn pattern_needs_placeholder() {
+        TaskRule::new("clean", "", |_, _, _| Ok(()));
+    }
+}
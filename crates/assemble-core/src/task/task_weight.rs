@@ -0,0 +1,62 @@
+//! How many workers a task occupies while it executes.
+//!
+//! Most tasks only need the worker running them, but some tasks use many threads or a lot of
+//! memory themselves. Running many of them at once, one per worker, can slow down or exhaust the
+//! machine. Giving such a task a larger weight stops other tasks from starting while it runs, so
+//! the build never runs more work at once than it has workers for.
+
+use std::fmt::{Display, Formatter};
+
+/// The number of workers a task occupies while it executes. By default, a task occupies only the
+/// worker running it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TaskWeight {
+    /// The task occupies this many workers. A task can't occupy more workers than there are, so
+    /// weights larger than the number of workers are treated as [`Exclusive`](Self::Exclusive).
+    Workers(usize),
+    /// The task occupies every worker, so no other task runs at the same time
+    Exclusive,
+}
+
+impl TaskWeight {
+    /// The number of workers occupied when there are `capacity` workers in total. Always at least
+    /// one and at most `capacity`.
+    pub fn workers(&self, capacity: usize) -> usize {
+        let capacity = capacity.max(1);
+        match *self {
+            TaskWeight::Workers(workers) => workers.clamp(1, capacity),
+            TaskWeight::Exclusive => capacity,
+        }
+    }
+}
+
+impl Default for TaskWeight {
+    fn default() -> Self {
+        TaskWeight::Workers(1)
+    }
+}
+
+impl Display for TaskWeight {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskWeight::Workers(1) => write!(f, "1 worker"),
+            TaskWeight::Workers(workers) => write!(f, "{} workers", workers),
+            TaskWeight::Exclusive => write!(f, "all workers"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weights_are_bounded_by_capacity() {
+        assert_eq!(TaskWeight::default().workers(8), 1);
+        assert_eq!(TaskWeight::Workers(0).workers(8), 1);
+        assert_eq!(TaskWeight::Workers(3).workers(8), 3);
+        assert_eq!(TaskWeight::Workers(16).workers(8), 8);
+        assert_eq!(TaskWeight::Exclusive.workers(8), 8);
+        assert_eq!(TaskWeight::Exclusive.workers(0), 1);
+    }
+}
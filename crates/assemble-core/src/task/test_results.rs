@@ -0,0 +1,130 @@
+//! Results of tests run by tasks.
+//!
+//! Tasks that run tests, such as `cargo test`, record the result of every test they ran with
+//! [`record_test_results`](record_test_results). Recorded results are included in build reports,
+//! where every test is shown as a test case of the task that ran it.
+
+use crate::identifier::TaskId;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+/// The outcome of a single test
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestOutcome {
+    /// The test passed
+    Passed,
+    /// The test failed
+    Failed,
+    /// The test was ignored
+    Ignored,
+}
+
+/// The result of a single test
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TestCaseResult {
+    /// The full name of the test
+    pub name: String,
+    /// The outcome of the test
+    pub outcome: TestOutcome,
+    /// How long the test took, in milliseconds, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// The output captured from the test, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+/// The results of all tests run by a task
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TestResults {
+    /// The results of every test, in the order they finished
+    pub tests: Vec<TestCaseResult>,
+}
+
+impl TestResults {
+    /// The number of tests with an outcome
+    pub fn count(&self, outcome: TestOutcome) -> usize {
+        self.tests
+            .iter()
+            .filter(|test| test.outcome == outcome)
+            .count()
+    }
+
+    /// The tests that failed
+    pub fn failures(&self) -> impl Iterator<Item = &TestCaseResult> {
+        self.tests
+            .iter()
+            .filter(|test| test.outcome == TestOutcome::Failed)
+    }
+
+    /// Reads results from a json file
+    pub fn read_from<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        serde_json::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Writes the results as json to a file, creating its parent directories
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        std::fs::write(path, json)
+    }
+}
+
+static RECORDED: Lazy<Mutex<HashMap<TaskId, TestResults>>> = Lazy::new(Default::default);
+
+/// Records the test results of a task, replacing any results recorded before
+pub fn record_test_results(task: &TaskId, results: TestResults) {
+    RECORDED.lock().insert(task.clone(), results);
+}
+
+/// Gets the test results recorded by a task, if any
+pub fn test_results(task: &TaskId) -> Option<TestResults> {
+    RECORDED.lock().get(task).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn results_round_trip() {
+        let results = TestResults {
+            tests: vec![
+                TestCaseResult {
+                    name: "tests::passes".to_string(),
+                    outcome: TestOutcome::Passed,
+                    duration_ms: Some(3),
+                    output: None,
+                },
+                TestCaseResult {
+                    name: "tests::fails".to_string(),
+                    outcome: TestOutcome::Failed,
+                    duration_ms: None,
+                    output: Some("assertion failed".to_string()),
+                },
+            ],
+        };
+        assert_eq!(results.count(TestOutcome::Passed), 1);
+        assert_eq!(results.failures().count(), 1);
+
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("results").join("test-results.json");
+        results.write_to(&file).unwrap();
+        assert_eq!(TestResults::read_from(&file).unwrap(), results);
+
+        let id = TaskId::new(":results:test").unwrap();
+        record_test_results(&id, results.clone());
+        assert_eq!(test_results(&id), Some(results));
+    }
+}
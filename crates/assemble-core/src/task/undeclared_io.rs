@@ -0,0 +1,218 @@
+//! Checks files accessed by an executing task against the inputs and outputs it declared.
+//!
+//! Tasks that read files they didn't declare as inputs, or write files they didn't declare as
+//! outputs, can't be correctly checked for being up-to-date. When enabled, reads through
+//! [`RegularFile::open`](crate::file::RegularFile::open), writes through
+//! [`RegularFile::create`](crate::file::RegularFile::create) and
+//! [`Workspace`](crate::workspace::Workspace)s, and the working directories of processes started by
+//! a task are checked against what the task declared. Undeclared accesses are either warned of or
+//! fail, depending on the [`UndeclaredIoPolicy`](UndeclaredIoPolicy).
+//!
+//! Accesses made outside of a task action are never checked.
+
+use crate::identifier::TaskId;
+use crate::task::output_ownership::normalize;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How files accessed by a task without being declared are handled
+#[derive(Debug, Default, Copy, Clone, clap::ValueEnum, Eq, PartialEq)]
+pub enum UndeclaredIoPolicy {
+    /// Accesses aren't checked
+    #[default]
+    Off,
+    /// Undeclared accesses emit a warning
+    Warn,
+    /// Undeclared accesses fail
+    Fail,
+}
+
+static UNDECLARED_IO_POLICY: AtomicU8 = AtomicU8::new(0);
+
+/// Sets how files accessed by a task without being declared are handled
+pub fn set_undeclared_io_policy(policy: UndeclaredIoPolicy) {
+    let value = match policy {
+        UndeclaredIoPolicy::Off => 0,
+        UndeclaredIoPolicy::Warn => 1,
+        UndeclaredIoPolicy::Fail => 2,
+    };
+    UNDECLARED_IO_POLICY.store(value, Ordering::Relaxed)
+}
+
+/// Gets how files accessed by a task without being declared are handled
+pub fn undeclared_io_policy() -> UndeclaredIoPolicy {
+    match UNDECLARED_IO_POLICY.load(Ordering::Relaxed) {
+        1 => UndeclaredIoPolicy::Warn,
+        2 => UndeclaredIoPolicy::Fail,
+        _ => UndeclaredIoPolicy::Off,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DeclaredIo {
+    task: TaskId,
+    inputs: HashSet<PathBuf>,
+    outputs: HashSet<PathBuf>,
+}
+
+thread_local! {
+    static DECLARED_IO: RefCell<Option<DeclaredIo>> = RefCell::new(None);
+}
+
+/// How a file was accessed
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Access {
+    /// The file was read
+    Read,
+    /// The file was written to
+    Write,
+    /// A process was started in the directory
+    WorkingDirectory,
+}
+
+impl Display for Access {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Access::Read => write!(f, "read"),
+            Access::Write => write!(f, "wrote to"),
+            Access::WorkingDirectory => write!(f, "started a process in"),
+        }
+    }
+}
+
+/// A task accessed a file it didn't declare
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{task} {access} {path:?}, which it didn't declare as {}", match access { Access::Write => "an output", _ => "an input or output" })]
+pub struct UndeclaredAccess {
+    /// The task that accessed the file
+    pub task: TaskId,
+    /// How the file was accessed
+    pub access: Access,
+    /// The file that was accessed
+    pub path: PathBuf,
+}
+
+/// Declares the inputs and outputs of the task executing on the current thread until dropped
+#[derive(Debug)]
+pub struct DeclaredIoGuard {
+    previous: Option<DeclaredIo>,
+}
+
+impl DeclaredIoGuard {
+    /// Declares the inputs and outputs of a task executing on the current thread
+    pub fn enter<I, O>(task: &TaskId, inputs: I, outputs: O) -> Self
+    where
+        I: IntoIterator<Item = PathBuf>,
+        O: IntoIterator<Item = PathBuf>,
+    {
+        let declared = DeclaredIo {
+            task: task.clone(),
+            inputs: inputs.into_iter().map(|p| normalize(&p)).collect(),
+            outputs: outputs.into_iter().map(|p| normalize(&p)).collect(),
+        };
+        let previous = DECLARED_IO.with(|cell| cell.replace(Some(declared)));
+        Self { previous }
+    }
+}
+
+impl Drop for DeclaredIoGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        DECLARED_IO.with(|cell| *cell.borrow_mut() = previous);
+    }
+}
+
+/// Checks whether the executing task can read a file. Files within declared inputs and outputs can
+/// be read.
+pub fn check_read(path: &Path) -> Result<(), UndeclaredAccess> {
+    check(path, Access::Read, |declared, path| {
+        within(path, &declared.inputs) || within(path, &declared.outputs)
+    })
+}
+
+/// Checks whether the executing task can write to a file. Only files within declared outputs can
+/// be written to.
+pub fn check_write(path: &Path) -> Result<(), UndeclaredAccess> {
+    check(path, Access::Write, |declared, path| {
+        within(path, &declared.outputs)
+    })
+}
+
+/// Checks whether the executing task can start a process in a directory. The directory must be
+/// within a declared input or output, or contain one.
+pub fn check_working_dir(path: &Path) -> Result<(), UndeclaredAccess> {
+    check(path, Access::WorkingDirectory, |declared, path| {
+        declared
+            .inputs
+            .iter()
+            .chain(&declared.outputs)
+            .any(|declared| path.starts_with(declared) || declared.starts_with(path))
+    })
+}
+
+fn within(path: &Path, declared: &HashSet<PathBuf>) -> bool {
+    path.ancestors().any(|ancestor| declared.contains(ancestor))
+}
+
+fn check<F>(path: &Path, access: Access, allowed: F) -> Result<(), UndeclaredAccess>
+where
+    F: FnOnce(&DeclaredIo, &Path) -> bool,
+{
+    let policy = undeclared_io_policy();
+    if policy == UndeclaredIoPolicy::Off {
+        return Ok(());
+    }
+    let path = normalize(path);
+    let undeclared = DECLARED_IO.with(|cell| {
+        cell.borrow()
+            .as_ref()
+            .filter(|declared| !allowed(declared, &path))
+            .map(|declared| UndeclaredAccess {
+                task: declared.task.clone(),
+                access,
+                path: path.clone(),
+            })
+    });
+    match undeclared {
+        Some(undeclared) if policy == UndeclaredIoPolicy::Fail => Err(undeclared),
+        Some(undeclared) => {
+            warn!("{}", undeclared);
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn checks_declared_io() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("input.txt");
+        let output_dir = temp_dir.path().join("out");
+        let other = temp_dir.path().join("other.txt");
+        let task = TaskId::new("task").unwrap();
+
+        set_undeclared_io_policy(UndeclaredIoPolicy::Fail);
+        assert!(check_read(&other).is_ok(), "not executing a task");
+        {
+            let _guard = DeclaredIoGuard::enter(&task, [input.clone()], [output_dir.clone()]);
+            assert!(check_read(&input).is_ok());
+            assert!(check_read(&output_dir.join("file.txt")).is_ok());
+            assert!(check_write(&output_dir.join("file.txt")).is_ok());
+            assert!(check_working_dir(temp_dir.path()).is_ok());
+
+            let undeclared = check_read(&other).unwrap_err();
+            assert_eq!(undeclared.access, Access::Read);
+            assert!(check_write(&input).is_err());
+            assert!(check_working_dir(&temp_dir.path().join("elsewhere")).is_err());
+        }
+        set_undeclared_io_policy(UndeclaredIoPolicy::Off);
+    }
+}
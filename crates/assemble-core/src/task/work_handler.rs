@@ -1,14 +1,21 @@
 use crate::__export::from_str;
-use crate::cryptography::{hash_file_sha256, Sha256};
+use crate::cryptography::Sha256;
 use crate::exception::BuildError;
 use crate::file_collection::{FileCollection, FileSet};
+use crate::fingerprint::files::{fingerprint_file, fingerprint_files};
 use crate::identifier::TaskId;
 use crate::lazy_evaluation::anonymous::AnonymousProvider;
+use crate::lazy_evaluation::environment::{
+    changed_source, read_environment, take_environment_reads, EnvironmentSource,
+    EnvironmentValues,
+};
 use crate::lazy_evaluation::{IntoProvider, Prop, Provider, ProviderExt, VecProp};
 use crate::project::buildable::IntoBuildable;
 use crate::project::error::ProjectResult;
 
 use crate::provider;
+use crate::task::output_ownership::output_owner;
+use crate::task::work_handler::history::{history_key, open_store, HistoryStore};
 use crate::task::work_handler::output::Output;
 use crate::task::work_handler::serializer::Serializable;
 use input::Input;
@@ -19,16 +26,16 @@ use serde::ser::Error as _;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use std::fs::{create_dir_all, File};
 use std::io;
-use std::io::Read;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
-use std::time::SystemTime;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use time::OffsetDateTime;
 use crate::error::PayloadError;
 
+pub mod history;
 pub mod input;
 pub mod output;
 pub mod serializer;
@@ -37,6 +44,7 @@ pub struct WorkHandler {
     task_id: TaskId,
     cache_location: PathBuf,
     inputs: VecProp<Serializable>,
+    input_files: FileSet,
     outputs: Option<FileSet>,
     serialized_output: HashMap<String, AnonymousProvider<Serializable>>,
     final_input: OnceCell<Input>,
@@ -44,12 +52,20 @@ pub struct WorkHandler {
     execution_history: OnceCell<TaskExecutionHistory>,
     up_to_date_status: OnceCell<bool>,
     did_work: bool,
+    execution_time: Option<Duration>,
+    finalizers: Vec<Box<dyn FnMut() -> ProjectResult + Send + Sync>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct TaskExecutionHistory {
     input: Input,
     output: Output,
+    /// Values read from the environment through providers while the task executed
+    #[serde(default)]
+    environment: EnvironmentValues,
+    /// How long the actions of the task took to execute
+    #[serde(default)]
+    execution_time: Option<Duration>,
 }
 
 impl WorkHandler {
@@ -58,6 +74,7 @@ impl WorkHandler {
             task_id: id.clone(),
             cache_location: cache_loc,
             inputs: VecProp::new(id.join("inputs").unwrap()),
+            input_files: FileSet::new(),
             outputs: None,
             serialized_output: Default::default(),
             final_input: OnceCell::new(),
@@ -65,6 +82,8 @@ impl WorkHandler {
             execution_history: OnceCell::new(),
             up_to_date_status: OnceCell::new(),
             did_work: true,
+            execution_time: None,
+            finalizers: vec![],
         }
     }
 
@@ -72,20 +91,39 @@ impl WorkHandler {
         !self.inputs.get().is_empty() && self.outputs.is_some()
     }
 
+    /// The store holding the execution history of the task
+    fn history_store(&self) -> io::Result<Arc<dyn HistoryStore>> {
+        open_store(&self.cache_location)
+    }
+
     /// Removes execution history, if it exists.
     pub fn remove_execution_history(&self) -> io::Result<()> {
-        let path = self.task_id.as_path();
-        let file_location = self.cache_location.join(path);
-        if file_location.exists() {
-            std::fs::remove_file(file_location)?;
-        }
-        Ok(())
+        self.history_store()?.remove(&history_key(&self.task_id))
+    }
+
+    /// Whether the task read any values from the environment while executing
+    pub fn read_environment(&self) -> bool {
+        read_environment(&self.task_id)
     }
 
-    /// Store execution data. Will only perform a store if there's both an input and an output
+    /// The first value read from the environment during the previous run that has changed since
+    pub fn environment_changed(&self) -> Option<&EnvironmentSource> {
+        self.try_get_execution_history()
+            .and_then(|history| changed_source(&history.environment))
+    }
+
+    /// Store execution data. Will only perform a store if there's both an input, or a value read
+    /// from the environment, and an output
     pub fn store_execution_history(&self) -> ProjectResult<()> {
         let input = self.get_input()?.clone();
-        if !input.any_inputs() {
+        let mut environment = take_environment_reads(&self.task_id);
+        if self.up_to_date_status.get() == Some(&true) {
+            // the task didn't run, so it read nothing this time
+            if let Some(history) = self.try_get_execution_history() {
+                environment = history.environment.clone();
+            }
+        }
+        if !input.any_inputs() && environment.is_empty() {
             return Ok(());
         }
         let output = if let Some(output) = self.get_output()? {
@@ -93,53 +131,33 @@ impl WorkHandler {
         } else {
             return Ok(());
         };
-        let history = TaskExecutionHistory { input, output };
-        let path = self.task_id.as_path();
-        let file_location = self.cache_location.join(path);
-        if let Some(parent) = file_location.parent() {
-            create_dir_all(parent).map_err(PayloadError::new)?;
-        }
-
-        let mut file = File::options()
-            .write(true)
-            .truncate(true)
-            .create(true)
-            .open(file_location).map_err(PayloadError::new)?;
+        let execution_time = self.execution_time.or_else(|| self.prev_execution_time());
+        let history = TaskExecutionHistory {
+            input,
+            output,
+            environment,
+            execution_time,
+        };
 
-        serializer::to_writer(&mut file, &history)?;
+        let serialized = serializer::to_string(&history)?;
+        self.history_store()
+            .and_then(|store| store.put(&history_key(&self.task_id), serialized.as_bytes()))
+            .map_err(PayloadError::new)?;
         Ok(())
     }
 
     pub fn cache_input(&self, input: Input) -> io::Result<()> {
-        let path = self.task_id.as_path();
-        let file_location = self.cache_location.join(path);
-        if let Some(parent) = file_location.parent() {
-            create_dir_all(parent)?;
-        }
-
-        let mut file = File::options()
-            .write(true)
-            .truncate(true)
-            .create(true)
-            .open(file_location)?;
-
-        serializer::to_writer(&mut file, &input).unwrap();
-        Ok(())
+        let serialized = serializer::to_string(&input).unwrap();
+        self.history_store()?
+            .put(&history_key(&self.task_id), serialized.as_bytes())
     }
 
     fn try_get_execution_history(&self) -> Option<&TaskExecutionHistory> {
         self.execution_history
             .get_or_try_init(|| -> Result<TaskExecutionHistory, Box<dyn Error>> {
-                let path = self.task_id.as_path();
-                let file_location = self.cache_location.join(path);
-                if file_location.exists() {
-                    let mut read = File::open(&file_location)?;
-                    let mut buffer = String::new();
-                    read.read_to_string(&mut buffer)
-                        .unwrap_or_else(|_| panic!("Could not read to end of {:?}", file_location));
-                    Ok(from_str(&buffer)?)
-                } else {
-                    Err(Box::new(BuildError::new("no file found for cache")))
+                match self.history_store()?.get(&history_key(&self.task_id))? {
+                    Some(history) => Ok(from_str(String::from_utf8(history)?)?),
+                    None => Err(Box::new(BuildError::new("no history found for task"))),
                 }
             })
             .ok()
@@ -175,6 +193,8 @@ impl WorkHandler {
     {
         let mut prop: Prop<Serializable> = self.task_id.prop(id).map_err(PayloadError::new)?;
         let provider = value.into_provider();
+        self.input_files +=
+            FileSet::with_provider(provider.clone().map(|p: Pa| p.as_ref().to_path_buf()));
         let path_provider = provider.flat_map(|p| Serializable::new(InputFile::new(p.as_ref())));
         prop.set_with(path_provider).map_err(PayloadError::new)?;
         self.inputs.push_with(prop);
@@ -189,6 +209,7 @@ impl WorkHandler {
     {
         let mut prop: Prop<Serializable> = self.task_id.prop(id).map_err(PayloadError::new)?;
         let provider = value.into_provider();
+        self.input_files += FileSet::with_provider(provider.clone());
         let path_provider = provider.flat_map(|p: Pa| Serializable::new(InputFiles::new(p)));
         prop.set_with(path_provider).map_err(PayloadError::new)?;
         self.inputs.push_with(prop);
@@ -209,6 +230,23 @@ impl WorkHandler {
         Ok(())
     }
 
+    /// Finalizes the value of a property once the task starts executing, so it can't be changed
+    /// while the task uses it
+    pub fn finalize_on_execute<T: Send + Sync + Clone + 'static>(&mut self, prop: &Prop<T>) {
+        let mut prop = prop.clone();
+        self.finalizers
+            .push(Box::new(move || prop.finalize_value().map_err(PayloadError::new)));
+    }
+
+    /// Finalizes the properties registered with
+    /// [`finalize_on_execute`](WorkHandler::finalize_on_execute)
+    pub fn finalize_inputs(&mut self) -> ProjectResult {
+        for finalize in &mut self.finalizers {
+            finalize()?;
+        }
+        Ok(())
+    }
+
     pub fn get_input(&self) -> ProjectResult<&Input> {
         self.final_input.get_or_try_init(|| {
             let inputs = self.inputs.fallible_get().map_err(PayloadError::new)?;
@@ -231,6 +269,19 @@ impl WorkHandler {
         *self.outputs.get_or_insert(FileSet::new()) += FileSet::with_provider(fc_provider);
     }
 
+    /// The input files declared for this task. Inputs that can't be resolved are ignored.
+    pub fn declared_inputs(&self) -> HashSet<PathBuf> {
+        self.input_files.try_files().unwrap_or_default()
+    }
+
+    /// The output files declared for this task, without finalizing the output
+    pub fn declared_outputs(&self) -> HashSet<PathBuf> {
+        self.outputs
+            .as_ref()
+            .map(|outputs| outputs.files())
+            .unwrap_or_default()
+    }
+
     /// Add data that can be serialized, then deserialized later for reuse
     pub fn add_serialized_data<P, T: Serialize + DeserializeOwned + 'static + Send + Sync + Clone>(
         &mut self,
@@ -285,11 +336,66 @@ impl WorkHandler {
         self.try_get_prev_input().zip(self.try_get_prev_output())
     }
 
+    /// How long the actions of the task took to execute during the previous run
+    pub fn prev_execution_time(&self) -> Option<Duration> {
+        self.try_get_execution_history()
+            .and_then(|h| h.execution_time)
+    }
+
+    /// Sets how long the actions of the task took to execute
+    pub fn set_execution_time(&mut self, execution_time: Duration) {
+        self.execution_time = Some(execution_time);
+    }
+
     /// Try to get the output of the previous run
     pub fn try_get_prev_output(&self) -> Option<&Output> {
         self.try_get_execution_history().map(|h| &h.output)
     }
 
+    /// Removes the outputs produced by the previous run of this task that are no longer declared,
+    /// such as files left behind after an output was renamed. Outputs of the previous run that
+    /// another task has since produced are kept. Directories are only removed once they're empty.
+    ///
+    /// Returns the paths that were removed.
+    pub fn remove_stale_outputs(&self) -> io::Result<Vec<PathBuf>> {
+        let previous = match self.try_get_prev_output() {
+            Some(previous) => previous,
+            None => return Ok(vec![]),
+        };
+        let declared = self.declared_outputs();
+        let mut stale = previous
+            .files()
+            .iter()
+            .filter(|path| !path.ancestors().any(|ancestor| declared.contains(ancestor)))
+            .filter(|path| {
+                output_owner(path)
+                    .map(|owner| owner == self.task_id)
+                    .unwrap_or(true)
+            })
+            .collect::<Vec<_>>();
+        // children are removed before their parents
+        stale.sort_by(|l, r| r.cmp(l));
+
+        let mut removed = vec![];
+        for path in stale {
+            let metadata = match std::fs::symlink_metadata(path) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            if metadata.is_dir() {
+                if std::fs::read_dir(path)?.next().is_some() {
+                    continue;
+                }
+                std::fs::remove_dir(path)?;
+            } else {
+                std::fs::remove_file(path)?;
+            }
+            debug!("removed stale output {:?} of {}", path, self.task_id);
+            removed.push(path.clone());
+        }
+        Ok(removed)
+    }
+
     pub fn did_work(&self) -> bool {
         self.did_work
     }
@@ -368,7 +474,7 @@ impl Serialize for InputFile {
         if self.0.exists() {
             InputFileData {
                 path: self.0.clone(),
-                data: hash_file_sha256(&self.0).map_err(S::Error::custom)?,
+                data: fingerprint_file(&self.0).map_err(S::Error::custom)?,
             }
             .serialize(serializer)
         } else {
@@ -432,6 +538,8 @@ struct InputFilesData {
 impl InputFilesData {
     pub fn new(set: FileSet) -> Self {
         let files = set.files();
+        // hashes the files in parallel, so serializing each file only looks up its hash
+        fingerprint_files(&files);
         Self {
             all_files: files.clone(),
             data: files
@@ -441,3 +549,55 @@ impl InputFilesData {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn removes_outputs_no_longer_declared() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = temp_dir.path().join("cache");
+        let old = temp_dir.path().join("old");
+        let new = temp_dir.path().join("new");
+        std::fs::create_dir_all(&old).unwrap();
+        std::fs::write(old.join("file.txt"), "old").unwrap();
+        let id = TaskId::new("task").unwrap();
+
+        let mut previous = WorkHandler::new(&id, cache.clone());
+        previous.add_input("version", provider!(|| 1)).unwrap();
+        previous.add_output(old.clone());
+        previous.store_execution_history().unwrap();
+
+        let mut current = WorkHandler::new(&id, cache);
+        current.add_output(new);
+        let removed = current.remove_stale_outputs().unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(!old.exists());
+    }
+
+    #[test]
+    fn history_is_kept_in_store() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = temp_dir.path().join("cache");
+        let id = TaskId::new("timed").unwrap();
+
+        let mut previous = WorkHandler::new(&id, cache.clone());
+        previous.add_input("version", provider!(|| 1)).unwrap();
+        previous.add_output(temp_dir.path().join("out"));
+        previous.set_execution_time(Duration::from_millis(1500));
+        previous.store_execution_history().unwrap();
+        assert!(cache.join(history::STORE_FILE_NAME).exists());
+
+        let current = WorkHandler::new(&id, cache);
+        assert_eq!(
+            current.prev_execution_time(),
+            Some(Duration::from_millis(1500))
+        );
+        current.remove_execution_history().unwrap();
+        assert!(WorkHandler::new(&id, current.cache_location.clone())
+            .try_get_prev_input()
+            .is_none());
+    }
+}
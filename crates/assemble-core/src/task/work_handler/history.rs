@@ -0,0 +1,401 @@
+//! Storage for the execution history of tasks.
+//!
+//! Histories are kept in a [`HistoryStore`](HistoryStore), a key-value store keyed by task. The
+//! default store is a [`LogStore`](LogStore), which keeps every task of a build in a single
+//! append-only file instead of a file per task, so builds with thousands of tasks don't have to
+//! open thousands of files.
+//!
+//! Older versions of assemble stored the history of each task in its own file within the cache
+//! directory. These files are moved into the store the first time it's opened.
+
+use crate::file_lock::FileLock;
+use crate::identifier::TaskId;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use walkdir::WalkDir;
+
+/// The name of the file of the store within the cache directory
+pub const STORE_FILE_NAME: &str = "history.db";
+
+const MAGIC: &[u8; 8] = b"ASMHIST1";
+const HEADER_LEN: u64 = 16;
+const RECORD_HEADER_LEN: u64 = 9;
+const PUT: u8 = 1;
+const REMOVE: u8 = 0;
+
+/// Logs smaller than this are never compacted
+const COMPACTION_THRESHOLD: u64 = 1024 * 1024;
+
+static STORES: Lazy<Mutex<HashMap<PathBuf, Arc<LogStore>>>> = Lazy::new(Default::default);
+
+/// A key-value store of task execution histories
+pub trait HistoryStore: Send + Sync {
+    /// Gets the value of a key
+    fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>>;
+
+    /// Sets the value of a key
+    fn put(&self, key: &str, value: &[u8]) -> io::Result<()>;
+
+    /// Removes a key, if it's present
+    fn remove(&self, key: &str) -> io::Result<()>;
+
+    /// All keys in the store
+    fn keys(&self) -> io::Result<Vec<String>>;
+}
+
+/// The key of the history of a task
+pub fn history_key(task_id: &TaskId) -> String {
+    task_id.iter().collect::<Vec<_>>().join("/")
+}
+
+/// Opens the store within a cache directory, shared by every task using the directory
+pub fn open_store(cache_dir: impl AsRef<Path>) -> io::Result<Arc<dyn HistoryStore>> {
+    let cache_dir = cache_dir.as_ref();
+    let mut stores = STORES.lock();
+    if let Some(store) = stores.get(cache_dir) {
+        return Ok(store.clone());
+    }
+    let store = Arc::new(LogStore::open(cache_dir)?);
+    stores.insert(cache_dir.to_path_buf(), store.clone());
+    Ok(store)
+}
+
+/// Where the value of each key is in the log
+#[derive(Debug, Default)]
+struct Index {
+    generation: u64,
+    /// The end of the last record read
+    end: u64,
+    /// The offset and length of the value of each key
+    values: HashMap<String, (u64, u32)>,
+    /// The number of bytes used by records that were overwritten or removed
+    garbage: u64,
+}
+
+/// A store kept in a single append-only log file.
+///
+/// Every change appends a record to the log, and the location of the latest value of each key is
+/// indexed in memory. Other processes may append to the same log, which is read up to its end
+/// before every access. Writes are serialized between processes with a [`FileLock`](FileLock).
+///
+/// When most of the log is overwritten records, it's compacted the next time it's opened.
+#[derive(Debug)]
+pub struct LogStore {
+    path: PathBuf,
+    lock_path: PathBuf,
+    index: Mutex<Index>,
+}
+
+impl LogStore {
+    /// Opens the store in a directory, creating it if it doesn't exist and importing histories
+    /// stored in the old per-task file layout
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        let store = Self {
+            path: dir.join(STORE_FILE_NAME),
+            lock_path: dir.join(format!("{}.lock", STORE_FILE_NAME)),
+            index: Mutex::new(Index::default()),
+        };
+
+        let _lock = FileLock::acquire(&store.lock_path, "open task history")?;
+        let mut index = store.index.lock();
+        let len = std::fs::metadata(&store.path).map_or(0, |metadata| metadata.len());
+        if len < HEADER_LEN {
+            store.create_log(&store.path)?;
+        }
+        let mut file = store.open_log()?;
+        store.catch_up(&mut index, &mut file, true)?;
+        drop(file);
+
+        store.migrate(dir, &mut index)?;
+        let size = index.end;
+        if size > COMPACTION_THRESHOLD && index.garbage > size / 2 {
+            store.compact(&mut index)?;
+        }
+        drop(index);
+        Ok(store)
+    }
+
+    fn create_log(&self, path: &Path) -> io::Result<File> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.write_all(MAGIC)?;
+        file.write_all(&rand::random::<u64>().to_le_bytes())?;
+        Ok(file)
+    }
+
+    fn open_log(&self) -> io::Result<File> {
+        OpenOptions::new().read(true).write(true).open(&self.path)
+    }
+
+    /// Reads records appended to the log since it was last read. If the log was replaced, it's
+    /// read from the start. An incomplete record at the end of the log was left by a process that
+    /// didn't finish writing it, and is removed if `repair` is set.
+    fn catch_up(&self, index: &mut Index, file: &mut File, repair: bool) -> io::Result<()> {
+        let len = file.metadata()?.len();
+        let mut header = [0; HEADER_LEN as usize];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut header)?;
+        if &header[..8] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{:?} is not a task history store", self.path),
+            ));
+        }
+        let generation = u64::from_le_bytes(header[8..].try_into().unwrap());
+        if generation != index.generation || len < index.end {
+            *index = Index {
+                generation,
+                end: HEADER_LEN,
+                ..Default::default()
+            };
+        }
+
+        file.seek(SeekFrom::Start(index.end))?;
+        let mut remaining = Vec::with_capacity((len - index.end) as usize);
+        file.read_to_end(&mut remaining)?;
+        let mut position = 0;
+        while let Some((op, key, value_len)) = parse_record(&remaining[position..]) {
+            let record_len = RECORD_HEADER_LEN + key.len() as u64 + value_len as u64;
+            let value_offset = index.end + record_len - value_len as u64;
+            let replaced = match op {
+                PUT => index.values.insert(key, (value_offset, value_len)),
+                _ => {
+                    index.garbage += record_len;
+                    index.values.remove(&key)
+                }
+            };
+            if let Some((_, old_len)) = replaced {
+                index.garbage += old_len as u64;
+            }
+            index.end += record_len;
+            position += record_len as usize;
+        }
+        if repair && index.end < len {
+            warn!("removing incomplete record from end of {:?}", self.path);
+            file.set_len(index.end)?;
+        }
+        Ok(())
+    }
+
+    fn append(&self, op: u8, key: &str, value: &[u8]) -> io::Result<()> {
+        let _lock = FileLock::acquire(&self.lock_path, "write task history")?;
+        let mut index = self.index.lock();
+        let mut file = self.open_log()?;
+        self.catch_up(&mut index, &mut file, true)?;
+        if op == REMOVE && !index.values.contains_key(key) {
+            return Ok(());
+        }
+
+        file.seek(SeekFrom::Start(index.end))?;
+        file.write_all(&encode_record(op, key, value))?;
+        self.catch_up(&mut index, &mut file, false)
+    }
+
+    /// Moves histories stored in their own files into the log
+    fn migrate(&self, dir: &Path, index: &mut Index) -> io::Result<()> {
+        let old_files = WalkDir::new(dir)
+            .min_depth(1)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.into_path())
+            .filter(|path| {
+                path.parent() != Some(dir)
+                    || path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .map_or(true, |name| !name.starts_with(STORE_FILE_NAME))
+            })
+            .collect::<Vec<_>>();
+        if old_files.is_empty() {
+            return Ok(());
+        }
+
+        info!(
+            "moving {} task histories in {:?} into {}",
+            old_files.len(),
+            dir,
+            STORE_FILE_NAME
+        );
+        let mut file = self.open_log()?;
+        file.seek(SeekFrom::Start(index.end))?;
+        for path in &old_files {
+            if path.extension().map_or(false, |ext| ext == "lock") {
+                std::fs::remove_file(path)?;
+                continue;
+            }
+            let key = path
+                .strip_prefix(dir)
+                .unwrap()
+                .iter()
+                .map(|part| part.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if !index.values.contains_key(&key) {
+                let value = std::fs::read(path)?;
+                file.write_all(&encode_record(PUT, &key, &value))?;
+            }
+            std::fs::remove_file(path)?;
+        }
+        self.catch_up(index, &mut file, false)?;
+
+        // remove the directories of projects that are now empty
+        for entry in WalkDir::new(dir)
+            .min_depth(1)
+            .contents_first(true)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_dir())
+        {
+            let _ = std::fs::remove_dir(entry.path());
+        }
+        Ok(())
+    }
+
+    /// Rewrites the log with only the latest value of each key
+    fn compact(&self, index: &mut Index) -> io::Result<()> {
+        debug!("compacting {:?}", self.path);
+        let mut old = self.open_log()?;
+        let compacted_path = self.path.with_extension("db.compacting");
+        let mut compacted = self.create_log(&compacted_path)?;
+        for (key, (offset, len)) in &index.values {
+            let mut value = vec![0; *len as usize];
+            old.seek(SeekFrom::Start(*offset))?;
+            old.read_exact(&mut value)?;
+            compacted.write_all(&encode_record(PUT, key, &value))?;
+        }
+        compacted.sync_all()?;
+        drop(compacted);
+        std::fs::rename(&compacted_path, &self.path)?;
+
+        *index = Index::default();
+        let mut file = self.open_log()?;
+        self.catch_up(index, &mut file, false)
+    }
+}
+
+fn encode_record(op: u8, key: &str, value: &[u8]) -> Vec<u8> {
+    let mut record = Vec::with_capacity(RECORD_HEADER_LEN as usize + key.len() + value.len());
+    record.push(op);
+    record.extend((key.len() as u32).to_le_bytes());
+    record.extend((value.len() as u32).to_le_bytes());
+    record.extend(key.as_bytes());
+    record.extend(value);
+    record
+}
+
+/// Parses the record at the start of a buffer, if it's complete
+fn parse_record(buffer: &[u8]) -> Option<(u8, String, u32)> {
+    if buffer.len() < RECORD_HEADER_LEN as usize {
+        return None;
+    }
+    let op = buffer[0];
+    let key_len = u32::from_le_bytes(buffer[1..5].try_into().unwrap()) as usize;
+    let value_len = u32::from_le_bytes(buffer[5..9].try_into().unwrap());
+    let key_end = RECORD_HEADER_LEN as usize + key_len;
+    if (op != PUT && op != REMOVE) || buffer.len() < key_end + value_len as usize {
+        return None;
+    }
+    let key = String::from_utf8(buffer[RECORD_HEADER_LEN as usize..key_end].to_vec()).ok()?;
+    Some((op, key, value_len))
+}
+
+impl HistoryStore for LogStore {
+    fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        let mut index = self.index.lock();
+        let mut file = File::open(&self.path)?;
+        self.catch_up(&mut index, &mut file, false)?;
+        match index.values.get(key) {
+            Some((offset, len)) => {
+                let mut value = vec![0; *len as usize];
+                file.seek(SeekFrom::Start(*offset))?;
+                file.read_exact(&mut value)?;
+                Ok(Some(value))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn put(&self, key: &str, value: &[u8]) -> io::Result<()> {
+        self.append(PUT, key, value)
+    }
+
+    fn remove(&self, key: &str) -> io::Result<()> {
+        self.append(REMOVE, key, &[])
+    }
+
+    fn keys(&self) -> io::Result<Vec<String>> {
+        let mut index = self.index.lock();
+        let mut file = File::open(&self.path)?;
+        self.catch_up(&mut index, &mut file, false)?;
+        Ok(index.values.keys().cloned().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn values_are_shared_between_stores() {
+        let dir = TempDir::new().unwrap();
+        let first = LogStore::open(dir.path()).unwrap();
+        let second = LogStore::open(dir.path()).unwrap();
+        first.put("root/build", b"first").unwrap();
+        assert_eq!(second.get("root/build").unwrap().unwrap(), b"first");
+        second.put("root/build", b"second").unwrap();
+        second.put("root/test", b"test").unwrap();
+        assert_eq!(first.get("root/build").unwrap().unwrap(), b"second");
+        first.remove("root/test").unwrap();
+        assert_eq!(second.get("root/test").unwrap(), None);
+
+        let reopened = LogStore::open(dir.path()).unwrap();
+        assert_eq!(reopened.keys().unwrap(), ["root/build"]);
+    }
+
+    #[test]
+    fn incomplete_records_are_ignored() {
+        let dir = TempDir::new().unwrap();
+        let store = LogStore::open(dir.path()).unwrap();
+        store.put("root/build", b"value").unwrap();
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(dir.path().join(STORE_FILE_NAME))
+            .unwrap();
+        file.write_all(&[PUT, 100, 0, 0]).unwrap();
+
+        let store = LogStore::open(dir.path()).unwrap();
+        store.put("root/test", b"test").unwrap();
+        assert_eq!(store.get("root/build").unwrap().unwrap(), b"value");
+        assert_eq!(store.get("root/test").unwrap().unwrap(), b"test");
+    }
+
+    #[test]
+    fn old_layout_is_migrated() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("root").join("sub")).unwrap();
+        std::fs::write(dir.path().join("root").join("build"), "build").unwrap();
+        std::fs::write(dir.path().join("root").join("sub").join("test"), "test").unwrap();
+        std::fs::write(dir.path().join("root").join("build.lock"), "").unwrap();
+
+        let store = LogStore::open(dir.path()).unwrap();
+        let mut keys = store.keys().unwrap();
+        keys.sort();
+        assert_eq!(keys, ["root/build", "root/sub/test"]);
+        assert_eq!(store.get("root/sub/test").unwrap().unwrap(), b"test");
+        assert!(!dir.path().join("root").exists());
+    }
+}
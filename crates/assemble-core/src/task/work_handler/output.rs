@@ -33,6 +33,11 @@ impl Output {
         }
     }
 
+    /// Gets the files of this output
+    pub fn files(&self) -> &HashSet<PathBuf> {
+        &self.files
+    }
+
     /// Gets previously serialized data, if any set
     pub fn serialized_data(&self) -> Option<&HashMap<String, Serializable>> {
         self.serialized_data.as_ref()
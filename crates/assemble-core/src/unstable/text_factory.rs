@@ -1,5 +1,6 @@
 //! Text factory adds some useful traits and factories for producing text.
 
+use crate::humanize;
 use crate::identifier::{ProjectId, TaskId};
 use colored::Colorize;
 use std::fmt;
@@ -112,23 +113,39 @@ impl<'f, W: Write> Write for LessImportant<'f, W> {
 #[derive(Debug)]
 pub struct BuildResultString {
     result_good: bool,
+    cancelled: bool,
     time: Duration,
 }
 
 impl BuildResultString {
     /// Construct a new build result
     pub fn new(result_good: bool, time: Duration) -> Self {
-        Self { result_good, time }
+        Self {
+            result_good,
+            cancelled: false,
+            time,
+        }
+    }
+
+    /// Construct the result of a cancelled build
+    pub fn cancelled(time: Duration) -> Self {
+        Self {
+            result_good: false,
+            cancelled: true,
+            time,
+        }
     }
 }
 
 impl Display for BuildResultString {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        let status = if self.result_good {
+        let status = if self.cancelled {
+            "BUILD CANCELLED".bright_yellow().bold()
+        } else if self.result_good {
             "BUILD SUCCEEDED".bright_green().bold()
         } else {
             "BUILD FAILED".bright_red().bold()
         };
-        write!(f, "{} in {:.2} sec", status, self.time.as_secs_f64())
+        write!(f, "{} in {}", status, humanize::duration(self.time))
     }
 }
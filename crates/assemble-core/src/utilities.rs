@@ -1,3 +1,4 @@
+use crate::humanize;
 use parking_lot::{RwLockReadGuard, RwLockWriteGuard};
 use std::any::{Any, TypeId};
 use std::fmt::Debug;
@@ -248,9 +249,9 @@ pub fn measure_time<R, F: FnOnce() -> R>(name: &str, level: log::Level, func: F)
     let out = func();
     log!(
         level,
-        "{} completion time: {:.3} sec.",
+        "{} completion time: {}",
         name,
-        instant.elapsed().as_secs_f32()
+        humanize::duration(instant.elapsed())
     );
     out
 }
@@ -1,6 +1,518 @@
 //! Control web requests
 
-use reqwest::Client;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+    RANGE,
+};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use url::Url;
+
+mod credentials;
+mod repository;
+
+pub use credentials::Credentials;
+pub use repository::AuthenticatedRepository;
+
+/// An error occurred while making a web request
+#[derive(Debug, thiserror::Error)]
+pub enum WebError {
+    /// The server responded with an unsuccessful status
+    #[error("{url} responded with {status}")]
+    Status {
+        /// The url requested
+        url: Url,
+        /// The status of the response
+        status: StatusCode,
+    },
+    /// A header is invalid
+    #[error("invalid header {0:?}")]
+    InvalidHeader(String),
+    /// Error occurred while making a request
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    /// Io error occurred
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl WebError {
+    /// Whether retrying the request could succeed
+    fn is_transient(&self) -> bool {
+        match self {
+            WebError::Status { status, .. } => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            WebError::Http(e) => e.is_timeout() || e.is_connect() || e.is_request(),
+            WebError::Io(e) => matches!(
+                e.kind(),
+                io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::TimedOut
+                    | io::ErrorKind::UnexpectedEof
+                    | io::ErrorKind::Interrupted
+            ),
+            WebError::InvalidHeader(_) => false,
+        }
+    }
+}
+
+/// How failed requests are retried. The delay between attempts grows exponentially, starting at
+/// the initial backoff and never exceeding the max backoff.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// The maximum amount of attempts made for a request, including the first
+    pub max_attempts: usize,
+    /// The delay before the first retry
+    pub initial_backoff: Duration,
+    /// The maximum delay between attempts
+    pub max_backoff: Duration,
+    /// The factor the delay is multiplied by after every attempt
+    pub multiplier: u32,
+}
+
+/// The `ETag` and `Last-Modified` headers of a response, which are sent back to the server to only
+/// download a resource if it changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheValidators {
+    /// The entity tag of the resource
+    pub etag: Option<String>,
+    /// When the resource was last modified, as an http date
+    pub last_modified: Option<String>,
+}
+
+impl CacheValidators {
+    /// Gets the validators of a response
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+
+    /// Whether there are no validators, in which case a conditional request can't be made
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    fn apply(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if let Some(etag) = &self.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+}
+
+/// The result of a conditional download
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConditionalDownload {
+    /// The resource hasn't changed, so nothing was downloaded
+    NotModified,
+    /// The resource was downloaded
+    Downloaded {
+        /// The size of the download in bytes
+        size: u64,
+        /// The validators of the downloaded resource
+        validators: CacheValidators,
+    },
+}
+
+impl RetryPolicy {
+    /// Never retry requests
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// The delay before a retry. `attempt` is the amount of attempts already made.
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        let factor = self
+            .multiplier
+            .saturating_pow(attempt.saturating_sub(1) as u32);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2,
+        }
+    }
+}
+
+/// A client for making blocking web requests, with support for credentials, custom headers,
+/// retries, and resumable downloads.
+#[derive(Debug, Clone)]
 pub struct WebClient {
     client: Client,
+    credentials: Credentials,
+    headers: HeaderMap,
+    retry: RetryPolicy,
+}
+
+impl Default for WebClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebClient {
+    /// Creates a new web client with no credentials and the default retry policy
+    pub fn new() -> Self {
+        Self {
+            client: Client::new(),
+            credentials: Credentials::None,
+            headers: HeaderMap::new(),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Sets the credentials used for requests
+    pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Adds a header sent with every request
+    pub fn with_header(mut self, name: &str, value: &str) -> Result<Self, WebError> {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| WebError::InvalidHeader(name.to_string()))?;
+        let value =
+            HeaderValue::from_str(value).map_err(|_| WebError::InvalidHeader(name.to_string()))?;
+        self.headers.insert(name, value);
+        Ok(self)
+    }
+
+    /// Sets the retry policy of the client
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Gets the credentials used by this client
+    pub fn credentials(&self) -> &Credentials {
+        &self.credentials
+    }
+
+    fn request(&self, url: &Url) -> RequestBuilder {
+        let request = self.client.get(url.clone()).headers(self.headers.clone());
+        self.credentials.apply(request)
+    }
+
+    /// Runs an operation until it succeeds, fails with an error that isn't transient, or the max
+    /// amount of attempts is reached.
+    fn with_retries<T, F: FnMut() -> Result<T, WebError>>(
+        &self,
+        url: &Url,
+        mut operation: F,
+    ) -> Result<T, WebError> {
+        let mut attempt = 1;
+        loop {
+            match operation() {
+                Err(e) if e.is_transient() && attempt < self.retry.max_attempts => {
+                    let backoff = self.retry.backoff(attempt);
+                    warn!(
+                        "request to {} failed ({}), retrying in {:?}",
+                        url, e, backoff
+                    );
+                    thread::sleep(backoff);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Makes a `GET` request, returning the response if it was successful.
+    pub fn get(&self, url: &Url) -> Result<Response, WebError> {
+        self.with_retries(url, || {
+            let response = self.request(url).send()?;
+            check_status(url, response)
+        })
+    }
+
+    /// Gets the bytes of a url
+    pub fn get_bytes(&self, url: &Url) -> Result<Vec<u8>, WebError> {
+        self.with_retries(url, || {
+            let response = check_status(url, self.request(url).send()?)?;
+            Ok(response.bytes()?.to_vec())
+        })
+    }
+
+    /// Downloads a url to a file.
+    ///
+    /// The download is written to a `.partial` file next to the destination, which is renamed once
+    /// the download completes. If a download fails partway through, the next attempt resumes from
+    /// the end of the partial file, as long as the server supports range requests.
+    pub fn download(&self, url: &Url, destination: &Path) -> Result<u64, WebError> {
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let partial = partial_path(destination);
+        let size = self.with_retries(url, || self.download_attempt(url, &partial))?;
+        std::fs::rename(&partial, destination)?;
+        Ok(size)
+    }
+
+    /// Checks whether a resource changed since it had the given validators, without downloading it.
+    pub fn is_modified(&self, url: &Url, validators: &CacheValidators) -> Result<bool, WebError> {
+        if validators.is_empty() {
+            return Ok(true);
+        }
+        self.with_retries(url, || {
+            let request = self.client.head(url.clone()).headers(self.headers.clone());
+            let response = validators.apply(self.credentials.apply(request)).send()?;
+            if response.status() == StatusCode::NOT_MODIFIED {
+                return Ok(false);
+            }
+            check_status(url, response).map(|_| true)
+        })
+    }
+
+    /// Downloads a url to a file, unless the resource hasn't changed since it had the given
+    /// validators. `progress` is called with the amount of bytes downloaded so far and, if known,
+    /// the total size of the download.
+    ///
+    /// Like [`download`](WebClient::download), the download is written to a `.partial` file that
+    /// is renamed once the download completes.
+    pub fn download_if_modified<F>(
+        &self,
+        url: &Url,
+        destination: &Path,
+        validators: Option<&CacheValidators>,
+        mut progress: F,
+    ) -> Result<ConditionalDownload, WebError>
+    where
+        F: FnMut(u64, Option<u64>),
+    {
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let partial = partial_path(destination);
+        let result = self.with_retries(url, || {
+            let mut request = self.request(url);
+            if let Some(validators) = validators {
+                request = validators.apply(request);
+            }
+            let response = request.send()?;
+            if response.status() == StatusCode::NOT_MODIFIED {
+                return Ok(ConditionalDownload::NotModified);
+            }
+            let mut response = check_status(url, response)?;
+            let validators = CacheValidators::from_headers(response.headers());
+            let total = response.content_length();
+
+            let mut file = File::create(&partial)?;
+            let mut buffer = [0_u8; 64 * 1024];
+            let mut size = 0;
+            loop {
+                let read = response.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                file.write_all(&buffer[..read])?;
+                size += read as u64;
+                progress(size, total);
+            }
+            Ok(ConditionalDownload::Downloaded { size, validators })
+        })?;
+        if let ConditionalDownload::Downloaded { .. } = result {
+            std::fs::rename(&partial, destination)?;
+        }
+        Ok(result)
+    }
+
+    fn download_attempt(&self, url: &Url, partial: &Path) -> Result<u64, WebError> {
+        let existing = partial.metadata().map(|m| m.len()).unwrap_or(0);
+        let mut request = self.request(url);
+        if existing > 0 {
+            debug!("resuming download of {} from byte {}", url, existing);
+            request = request.header(RANGE, format!("bytes={}-", existing));
+        }
+        let response = request.send()?;
+        if existing > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            // the partial file is either complete or invalid, start over
+            std::fs::remove_file(partial)?;
+            return self.download_attempt(url, partial);
+        }
+        let mut response = check_status(url, response)?;
+
+        let resumed = response.status() == StatusCode::PARTIAL_CONTENT;
+        let mut file = if resumed {
+            OpenOptions::new().append(true).open(partial)?
+        } else {
+            File::create(partial)?
+        };
+        let copied = response.copy_to(&mut file)?;
+        Ok(if resumed { existing + copied } else { copied })
+    }
+}
+
+fn check_status(url: &Url, response: Response) -> Result<Response, WebError> {
+    if response.status().is_success() {
+        Ok(response)
+    } else {
+        Err(WebError::Status {
+            url: url.clone(),
+            status: response.status(),
+        })
+    }
+}
+
+fn partial_path(destination: &Path) -> PathBuf {
+    let mut file_name = destination
+        .file_name()
+        .map(|s| s.to_os_string())
+        .unwrap_or_default();
+    file_name.push(".partial");
+    destination.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exponential_backoff() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            multiplier: 2,
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(8), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn transient_errors() {
+        let url = Url::parse("https://example.com").unwrap();
+        let status = |status| WebError::Status {
+            url: url.clone(),
+            status,
+        };
+        assert!(status(StatusCode::SERVICE_UNAVAILABLE).is_transient());
+        assert!(status(StatusCode::TOO_MANY_REQUESTS).is_transient());
+        assert!(!status(StatusCode::NOT_FOUND).is_transient());
+        assert!(!status(StatusCode::UNAUTHORIZED).is_transient());
+    }
+
+    /// Serves a resource with an etag, responding with `304 Not Modified` when the etag is sent back
+    fn serve_with_etag(requests: usize) -> (Url, thread::JoinHandle<Vec<StatusCode>>) {
+        use std::io::{BufRead, BufReader};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!(
+            "http://{}/file.txt",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+        let handle = thread::spawn(move || {
+            let mut statuses = vec![];
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut not_modified = false;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    not_modified |= line.to_lowercase().starts_with("if-none-match: \"v1\"");
+                }
+                if not_modified {
+                    statuses.push(StatusCode::NOT_MODIFIED);
+                    write!(
+                        stream,
+                        "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n"
+                    )
+                    .unwrap();
+                } else {
+                    statuses.push(StatusCode::OK);
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello"
+                    )
+                    .unwrap();
+                }
+            }
+            statuses
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn conditional_download() {
+        let (url, server) = serve_with_etag(3);
+        let dir = tempfile::TempDir::new().unwrap();
+        let destination = dir.path().join("file.txt");
+        let client = WebClient::new().with_retry(RetryPolicy::none());
+
+        let mut downloaded = 0;
+        let validators = match client
+            .download_if_modified(&url, &destination, None, |size, _| downloaded = size)
+            .unwrap()
+        {
+            ConditionalDownload::Downloaded { size, validators } => {
+                assert_eq!(size, 5);
+                validators
+            }
+            ConditionalDownload::NotModified => panic!("nothing was downloaded"),
+        };
+        assert_eq!(downloaded, 5);
+        assert_eq!(validators.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(std::fs::read_to_string(&destination).unwrap(), "hello");
+
+        assert!(!client.is_modified(&url, &validators).unwrap());
+        assert_eq!(
+            client
+                .download_if_modified(&url, &destination, Some(&validators), |_, _| {})
+                .unwrap(),
+            ConditionalDownload::NotModified
+        );
+        assert_eq!(
+            server.join().unwrap(),
+            [
+                StatusCode::OK,
+                StatusCode::NOT_MODIFIED,
+                StatusCode::NOT_MODIFIED
+            ]
+        );
+    }
+
+    #[test]
+    fn partial_paths() {
+        assert_eq!(
+            partial_path(Path::new("/cache/file.jar")),
+            Path::new("/cache/file.jar.partial")
+        );
+    }
 }
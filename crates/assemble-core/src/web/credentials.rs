@@ -0,0 +1,136 @@
+use crate::Project;
+use reqwest::blocking::RequestBuilder;
+use std::fmt::{Debug, Formatter};
+
+/// Credentials used to authenticate with a web server
+#[derive(Clone, Default, PartialEq, Eq)]
+pub enum Credentials {
+    /// No authentication
+    #[default]
+    None,
+    /// Http basic authentication
+    Basic {
+        /// The username
+        username: String,
+        /// The password, if any
+        password: Option<String>,
+    },
+    /// A bearer token
+    Bearer(String),
+}
+
+impl Debug for Credentials {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // never print secrets
+        match self {
+            Credentials::None => write!(f, "None"),
+            Credentials::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .finish_non_exhaustive(),
+            Credentials::Bearer(_) => write!(f, "Bearer(..)"),
+        }
+    }
+}
+
+impl Credentials {
+    /// Creates credentials from a lookup function. A token takes precedence over a username and
+    /// password.
+    fn from_lookup<F: Fn(&str) -> Option<String>>(
+        username: &str,
+        password: &str,
+        token: &str,
+        lookup: F,
+    ) -> Self {
+        if let Some(token) = lookup(token) {
+            Credentials::Bearer(token)
+        } else if let Some(username) = lookup(username) {
+            Credentials::Basic {
+                username,
+                password: lookup(password),
+            }
+        } else {
+            Credentials::None
+        }
+    }
+
+    /// Reads credentials from the environment variables `{PREFIX}_TOKEN`, or `{PREFIX}_USERNAME`
+    /// and `{PREFIX}_PASSWORD`.
+    pub fn from_env(prefix: &str) -> Self {
+        let prefix = prefix.to_uppercase();
+        Self::from_lookup(
+            &format!("{prefix}_USERNAME"),
+            &format!("{prefix}_PASSWORD"),
+            &format!("{prefix}_TOKEN"),
+            |var| std::env::var(var).ok().filter(|s| !s.is_empty()),
+        )
+    }
+
+    /// Reads credentials from the project properties `{name}Token`, or `{name}Username` and
+    /// `{name}Password`.
+    pub fn from_properties(project: &Project, name: &str) -> Self {
+        Self::from_lookup(
+            &format!("{name}Username"),
+            &format!("{name}Password"),
+            &format!("{name}Token"),
+            |key| project.get_property(key).cloned().flatten(),
+        )
+    }
+
+    /// Reads credentials from project properties, falling back to environment variables if no
+    /// credentials are set as properties.
+    pub fn from_properties_or_env(project: &Project, name: &str) -> Self {
+        match Self::from_properties(project, name) {
+            Credentials::None => Self::from_env(name),
+            credentials => credentials,
+        }
+    }
+
+    /// Adds these credentials to a request
+    pub(super) fn apply(&self, request: RequestBuilder) -> RequestBuilder {
+        match self {
+            Credentials::None => request,
+            Credentials::Basic { username, password } => {
+                request.basic_auth(username, password.as_ref())
+            }
+            Credentials::Bearer(token) => request.bearer_auth(token),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn token_takes_precedence() {
+        let values = HashMap::from([
+            ("REPO_USERNAME", "user"),
+            ("REPO_PASSWORD", "pass"),
+            ("REPO_TOKEN", "token"),
+        ]);
+        let lookup = |key: &str| values.get(key).map(|s| s.to_string());
+        assert_eq!(
+            Credentials::from_lookup("REPO_USERNAME", "REPO_PASSWORD", "REPO_TOKEN", lookup),
+            Credentials::Bearer("token".to_string())
+        );
+        assert_eq!(
+            Credentials::from_lookup("REPO_USERNAME", "REPO_PASSWORD", "MISSING", lookup),
+            Credentials::Basic {
+                username: "user".to_string(),
+                password: Some("pass".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn debug_hides_secrets() {
+        let credentials = Credentials::Basic {
+            username: "user".to_string(),
+            password: Some("hunter2".to_string()),
+        };
+        assert!(!format!("{:?}", credentials).contains("hunter2"));
+        assert!(!format!("{:?}", Credentials::Bearer("secret".to_string())).contains("secret"));
+    }
+}
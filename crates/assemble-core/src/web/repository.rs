@@ -0,0 +1,89 @@
+use crate::dependencies::{DependencyType, Registry};
+use crate::web::{Credentials, RetryPolicy, WebClient, WebError};
+use url::Url;
+
+/// A repository accessed over http(s) that may require authentication.
+///
+/// By default, the repository supports a remote file system dependency type with the same name as
+/// the repository. Other dependency types, such as maven dependencies, can be added with
+/// [`supporting`](AuthenticatedRepository::supporting). Dependencies resolved from this repository
+/// use its [`web_client`](Registry::web_client), so they are sent with the configured credentials
+/// and headers.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedRepository {
+    name: String,
+    url: Url,
+    client: WebClient,
+    supported: Vec<DependencyType>,
+}
+
+impl AuthenticatedRepository {
+    /// Creates a new repository at a url, with no credentials
+    pub fn new(name: &str, url: &str) -> Result<Self, url::ParseError> {
+        let mut url = url.to_string();
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+        Ok(Self {
+            name: name.to_string(),
+            url: Url::parse(&url)?,
+            client: WebClient::new(),
+            supported: vec![DependencyType::new(name, name, ["*"])],
+        })
+    }
+
+    /// The name of the repository
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Sets the credentials of this repository
+    pub fn credentials(&mut self, credentials: Credentials) -> &mut Self {
+        self.client = self.client.clone().with_credentials(credentials);
+        self
+    }
+
+    /// Adds a header sent with every request to this repository
+    pub fn header(&mut self, name: &str, value: &str) -> Result<&mut Self, WebError> {
+        self.client = self.client.clone().with_header(name, value)?;
+        Ok(self)
+    }
+
+    /// Adds a header whose value is read from an environment variable. If the variable isn't set,
+    /// no header is added.
+    pub fn header_from_env(&mut self, name: &str, var: &str) -> Result<&mut Self, WebError> {
+        match std::env::var(var) {
+            Ok(value) => self.header(name, &value),
+            Err(_) => {
+                debug!("{} not set, not adding header {}", var, name);
+                Ok(self)
+            }
+        }
+    }
+
+    /// Sets how failed requests to this repository are retried
+    pub fn retry(&mut self, retry: RetryPolicy) -> &mut Self {
+        self.client = self.client.clone().with_retry(retry);
+        self
+    }
+
+    /// Adds a dependency type that can be resolved from this repository
+    pub fn supporting(mut self, dependency_type: DependencyType) -> Self {
+        self.supported.push(dependency_type);
+        self
+    }
+}
+
+impl Registry for AuthenticatedRepository {
+    fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    fn supported(&self) -> Vec<DependencyType> {
+        self.supported.clone()
+    }
+
+    fn web_client(&self) -> WebClient {
+        self.client.clone()
+    }
+}
@@ -7,21 +7,24 @@
 use crate::error::PayloadError;
 
 use crate::project::error::ProjectError;
-use crossbeam::channel::{bounded, unbounded, Receiver, SendError, Sender, TryRecvError};
-use crossbeam::deque::{Injector, Steal, Stealer, Worker};
+use crossbeam::channel::{bounded, Receiver, Sender};
+use parking_lot::{Condvar, Mutex};
 
 use std::any::Any;
-use std::collections::HashMap;
+use std::collections::VecDeque;
 
 use std::marker::PhantomData;
 
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread::JoinHandle;
 
 use std::{io, panic, thread};
-use uuid::Uuid;
 
 /// A Work Token is a single unit of work done within the Work Queue. Can be built using a [WorkTokenBuilder](WorkTokenBuilder)
+///
+/// `on_complete` is called once the work finishes, even if it panicked.
 pub struct WorkToken {
     pub on_start: Box<dyn Fn() + Send + 'static>,
     pub on_complete: Box<dyn Fn() + Send + 'static>,
@@ -150,46 +153,38 @@ where
     }
 }
 
-enum WorkerQueueRequest {
-    GetStatus,
-}
-
-enum WorkerQueueResponse {
-    Status(HashMap<Uuid, WorkerStatus>),
+/// State shared between an executor and its workers
+#[derive(Default)]
+struct Shared {
+    state: Mutex<QueueState>,
+    /// Notified when work is submitted, or when the workers should stop
+    work_available: Condvar,
+    /// Notified when a token finishes
+    work_finished: Condvar,
+    panicked: AtomicBool,
 }
 
-#[derive(Debug, Eq, PartialEq)]
-enum WorkerMessage {
-    Stop,
+#[derive(Default)]
+struct QueueState {
+    queue: VecDeque<WorkerTuple>,
+    /// The number of tokens submitted that haven't finished yet
+    outstanding: usize,
+    stopping: bool,
 }
 
-type WorkTokenId = u64;
-
 /// A worker queue allows for the submission of work to be done in parallel.
+///
+/// Idle workers block until work is submitted, and callers waiting on work block until it
+/// finishes, so nothing spins while waiting.
 pub struct WorkerExecutor {
     max_jobs: usize,
-    injector: Arc<Injector<WorkerTuple>>,
-    connection: Option<Connection>,
-}
-
-struct Connection {
-    join_send: Sender<()>,
-    inner_handle: JoinHandle<()>,
-
-    request_sender: Sender<WorkerQueueRequest>,
-    response_receiver: Receiver<WorkerQueueResponse>,
-}
-
-impl Connection {
-    fn handle_request(&self, request: WorkerQueueRequest) -> WorkerQueueResponse {
-        self.request_sender.send(request).unwrap();
-        self.response_receiver.recv().unwrap()
-    }
+    shared: Arc<Shared>,
+    handles: Vec<JoinHandle<()>>,
 }
 
 impl Drop for WorkerExecutor {
     fn drop(&mut self) {
-        self.join_inner();
+        let _ = self.join_inner();
     }
 }
 
@@ -197,8 +192,8 @@ impl WorkerExecutor {
     pub fn new(pool_size: usize) -> io::Result<Self> {
         let mut out = Self {
             max_jobs: pool_size,
-            injector: Arc::new(Injector::new()),
-            connection: None,
+            shared: Default::default(),
+            handles: vec![],
         };
         out.start()?;
         Ok(out)
@@ -206,7 +201,14 @@ impl WorkerExecutor {
 
     /// Can be used to restart a joined worker queue
     fn start(&mut self) -> io::Result<()> {
-        self.connection = Some(Inner::start(&self.injector, self.max_jobs)?);
+        self.shared.state.lock().stopping = false;
+        for index in 0..self.max_jobs {
+            let shared = self.shared.clone();
+            let handle = thread::Builder::new()
+                .name(format!("Assemble Worker (id = {})", index))
+                .spawn(move || run_worker(&shared))?;
+            self.handles.push(handle);
+        }
         Ok(())
     }
 
@@ -218,62 +220,52 @@ impl WorkerExecutor {
     }
 
     fn join_inner(&mut self) -> thread::Result<()> {
-        if let Some(connection) = std::mem::replace(&mut self.connection, None) {
-            let _ = connection.join_send.send(());
-            connection.inner_handle.join()?;
-        };
-        Ok(())
+        self.shared.state.lock().stopping = true;
+        self.shared.work_available.notify_all();
+        let mut result = Ok(());
+        for handle in self.handles.drain(..) {
+            if let Err(e) = handle.join() {
+                result = Err(e);
+            }
+        }
+        result
     }
 
-    /// Forces all running tokens to end.
     /// Submit some work to the Worker Queue.
-    ///
     pub fn submit<I: Into<WorkToken>>(&self, token: I) -> io::Result<WorkHandle> {
         let work_token = token.into();
 
         let (handle, channel) = work_channel(self);
-        let id = rand::random();
-        let work_tuple = WorkerTuple(id, work_token, channel);
-        self.injector.push(work_tuple);
+        {
+            let mut state = self.shared.state.lock();
+            if state.stopping {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "worker executor has been stopped",
+                ));
+            }
+            state.queue.push_back(WorkerTuple(work_token, channel));
+            state.outstanding += 1;
+        }
+        self.shared.work_available.notify_one();
         Ok(handle)
     }
 
+    /// The number of workers
+    pub fn max_jobs(&self) -> usize {
+        self.max_jobs
+    }
+
+    /// Whether any submitted work has panicked
     pub fn any_panicked(&self) -> bool {
-        let status = self
-            .connection
-            .as_ref()
-            .map(|s| s.handle_request(WorkerQueueRequest::GetStatus));
-        match status {
-            Some(WorkerQueueResponse::Status(status)) => {
-                status.values().any(|s| s == &WorkerStatus::Panic)
-            }
-            None => false,
-        }
+        self.shared.panicked.load(Ordering::SeqCst)
     }
 
     /// Wait for all current jobs to finish.
     pub fn finish_jobs(&mut self) -> io::Result<()> {
-        if self.connection.is_none() {
-            panic!("Shouldn't be possible")
-        }
-
-        loop {
-            if self.injector.is_empty() {
-                break;
-            }
-        }
-
-        while let Some(connection) = &self.connection {
-            // thread::sleep(Duration::from_millis(100));
-            let status = connection.handle_request(WorkerQueueRequest::GetStatus);
-            let finished = match status {
-                WorkerQueueResponse::Status(s) => s
-                    .values()
-                    .all(|status| status == &WorkerStatus::Idle || status == &WorkerStatus::Panic),
-            };
-            if finished {
-                break;
-            }
+        let mut state = self.shared.state.lock();
+        while state.outstanding > 0 {
+            self.shared.work_finished.wait(&mut state);
         }
         Ok(())
     }
@@ -284,20 +276,6 @@ impl WorkerExecutor {
     }
 }
 
-struct Inner {
-    max_jobs: usize,
-    injector: Arc<Injector<WorkerTuple>>,
-    worker: Worker<WorkerTuple>,
-    message_sender: Sender<WorkerMessage>,
-    status_receiver: Receiver<WorkStatusUpdate>,
-    stop_receiver: Receiver<()>,
-    handles: Vec<JoinHandle<()>>,
-    id_to_status: HashMap<Uuid, WorkerStatus>,
-
-    request_recv: Receiver<WorkerQueueRequest>,
-    response_sndr: Sender<WorkerQueueResponse>,
-}
-
 #[derive(Clone)]
 pub struct WorkHandle<'exec> {
     recv: Receiver<()>,
@@ -317,7 +295,7 @@ fn work_channel(exec: &WorkerExecutor) -> (WorkHandle, Sender<()>) {
 }
 
 impl WorkHandle<'_> {
-    /// Joins the work handle
+    /// Joins the work handle. Fails if the work panicked.
     pub fn join(self) -> thread::Result<()> {
         self.recv
             .recv()
@@ -325,203 +303,52 @@ impl WorkHandle<'_> {
     }
 }
 
-mod inner_impl {
-    use super::*;
-    impl Inner {
-        /// Create a Worker queue with a set maximum amount of workers.
-        fn new(
-            injector: &Arc<Injector<WorkerTuple>>,
-            pool_size: usize,
-            stop_recv: Receiver<()>,
-        ) -> io::Result<(
-            Self,
-            Sender<WorkerQueueRequest>,
-            Receiver<WorkerQueueResponse>,
-        )> {
-            let (s, r) = unbounded();
-            let (s2, r2) = unbounded();
-
-            let requests = unbounded();
-            let responses = unbounded();
-
-            let mut output = Self {
-                max_jobs: pool_size,
-                injector: injector.clone(),
-                worker: Worker::new_fifo(),
-                message_sender: s,
-                status_receiver: r2,
-                stop_receiver: stop_recv,
-                handles: vec![],
-                id_to_status: HashMap::new(),
-
-                request_recv: requests.1,
-                response_sndr: responses.0,
-            };
-            for _ in 0..pool_size {
-                let stealer = output.worker.stealer();
-                let (id, handle) = AssembleWorker::new(stealer, r.clone(), s2.clone()).start()?;
-                output.id_to_status.insert(id, WorkerStatus::Unknown);
-                output.handles.push(handle);
-            }
-
-            Ok((output, requests.0, responses.1))
-        }
-
-        pub fn start(
-            injector: &Arc<Injector<WorkerTuple>>,
-            pool_size: usize,
-        ) -> io::Result<Connection> {
-            let (stop_s, stop_r) = unbounded();
-            let (inner, sender, recv) = Self::new(injector, pool_size, stop_r)?;
-
-            let handle = thread::spawn(move || inner.run());
-
-            Ok(Connection {
-                join_send: stop_s,
-                inner_handle: handle,
-                request_sender: sender,
-                response_receiver: recv,
-            })
-        }
-
-        fn run(mut self) {
+/// Runs work on a worker thread until the executor stops and there's no work left
+fn run_worker(shared: &Shared) {
+    loop {
+        let WorkerTuple(token, finished) = {
+            let mut state = shared.state.lock();
             loop {
-                match self.stop_receiver.try_recv() {
-                    Ok(()) => break,
-                    Err(TryRecvError::Empty) => {}
-                    Err(_) => break,
+                if let Some(tuple) = state.queue.pop_front() {
+                    break tuple;
                 }
-
-                let _ = self.injector.steal_batch(&self.worker);
-
-                self.update_worker_status();
-                self.handle_requests();
-            }
-            for _ in &self.handles {
-                self.message_sender.send(WorkerMessage::Stop);
-            }
-            for handle in self.handles {
-                handle.join();
-            }
-        }
-
-        fn update_worker_status(&mut self) {
-            while let Ok(status) = self.status_receiver.try_recv() {
-                self.id_to_status.insert(status.worker_id, status.status);
-            }
-        }
-
-        fn handle_requests(&mut self) {
-            while let Ok(req) = self.request_recv.try_recv() {
-                let response = self.on_request(req);
-                self.response_sndr
-                    .send(response)
-                    .expect("Inner still exists while Outer gone")
-            }
-        }
-
-        fn on_request(&mut self, request: WorkerQueueRequest) -> WorkerQueueResponse {
-            match request {
-                WorkerQueueRequest::GetStatus => {
-                    let map = self.id_to_status.clone();
-                    WorkerQueueResponse::Status(map)
+                if state.stopping {
+                    return;
                 }
+                shared.work_available.wait(&mut state);
             }
-        }
-    }
-}
-
-#[derive(Debug, Clone, Eq, PartialEq)]
-enum WorkerStatus {
-    Unknown,
-    TaskRunning(WorkTokenId),
-    Idle,
-    Panic,
-}
-
-struct WorkStatusUpdate {
-    worker_id: Uuid,
-    status: WorkerStatus,
-}
-
-struct AssembleWorker {
-    id: Uuid,
-    stealer: Stealer<WorkerTuple>,
-    message_recv: Receiver<WorkerMessage>,
-    status_send: Sender<WorkStatusUpdate>,
-}
+        };
 
-impl Drop for AssembleWorker {
-    fn drop(&mut self) {
-        if thread::panicking() {
-            self.report_status(WorkerStatus::Panic).unwrap()
+        let WorkToken {
+            on_start,
+            on_complete,
+            work,
+        } = token;
+        let worked = panic::catch_unwind(AssertUnwindSafe(|| {
+            on_start();
+            work();
+        }))
+        .is_ok();
+        if !worked {
+            shared.panicked.store(true, Ordering::SeqCst);
         }
-    }
-}
-
-impl AssembleWorker {
-    pub fn new(
-        stealer: Stealer<WorkerTuple>,
-        message_recv: Receiver<WorkerMessage>,
-        status_send: Sender<WorkStatusUpdate>,
-    ) -> Self {
-        let id = Uuid::new_v4();
-        Self {
-            id,
-            stealer,
-            message_recv,
-            status_send,
+        // ran even if the work panicked, so anything waiting on the work is notified
+        let completed = panic::catch_unwind(AssertUnwindSafe(|| on_complete())).is_ok();
+        if !completed {
+            shared.panicked.store(true, Ordering::SeqCst);
         }
-    }
-
-    fn start(mut self) -> io::Result<(Uuid, JoinHandle<()>)> {
-        let id = self.id;
-        self.report_status(WorkerStatus::Idle).unwrap();
-        let handle = thread::Builder::new()
-            .name(format!("Assemble Worker (id = {})", id))
-            .spawn(move || self.run())?;
-        Ok((id, handle))
-    }
-
-    fn run(&mut self) {
-        'outer: loop {
-            match self.message_recv.try_recv() {
-                Ok(msg) => match msg {
-                    WorkerMessage::Stop => break 'outer,
-                },
-                Err(TryRecvError::Empty) => {}
-                Err(_) => break 'outer,
-            }
-
-            if let Steal::Success(tuple) = self.stealer.steal() {
-                let WorkerTuple(id, work, vc) = tuple;
-                self.report_status(WorkerStatus::TaskRunning(id)).unwrap();
-
-                (work.on_start)();
-                (work.work)();
-                (work.on_complete)();
-
-                self.report_status(WorkerStatus::Idle).unwrap();
-
-                match vc.send(()) {
-                    Ok(()) => {}
-                    Err(_e) => {
-                        // occurs only if request handle went out of scope
-                    }
-                }
-            }
+        if worked && completed {
+            // only fails if the work handle went out of scope
+            let _ = finished.send(());
         }
-    }
+        drop(finished);
 
-    fn report_status(&mut self, status: WorkerStatus) -> Result<(), SendError<WorkStatusUpdate>> {
-        self.status_send.send(WorkStatusUpdate {
-            worker_id: self.id,
-            status,
-        })
+        shared.state.lock().outstanding -= 1;
+        shared.work_finished.notify_all();
     }
 }
 
-struct WorkerTuple(WorkTokenId, WorkToken, Sender<()>);
+struct WorkerTuple(WorkToken, Sender<()>);
 
 /// A worker queue is a way of submitting work to a [`WorkerExecutor`](WorkerExecutor).
 ///
@@ -609,7 +436,6 @@ mod tests {
     use std::time::Duration;
     const WORK_SIZE: usize = 6;
     #[test]
-    #[ignore]
     fn parallelism_works() {
         let mut worker_queue = WorkerExecutor::new(WORK_SIZE).unwrap();
 
@@ -725,7 +551,6 @@ mod tests {
     }
 
     #[test]
-    #[ignore]
     fn can_stop_after_panic() {
         let executor = WorkerExecutor::new(1).unwrap();
         let job = executor.submit(|| panic!("WOOH I PANICKED")).unwrap();
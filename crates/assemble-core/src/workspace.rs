@@ -1,6 +1,9 @@
 //! Workspaces help provide limited access to files
 
 use crate::file::RegularFile;
+use crate::identifier::TaskId;
+use crate::task::output_ownership::{check_write, OutputConflict};
+use crate::task::undeclared_io::{self, UndeclaredAccess};
 
 use log::debug;
 
@@ -24,6 +27,10 @@ pub enum WorkspaceError {
     IoError(#[from] io::Error),
     #[error("Protected paths were poisoned")]
     PoisonError,
+    #[error(transparent)]
+    OutputConflict(#[from] OutputConflict),
+    #[error(transparent)]
+    UndeclaredAccess(#[from] UndeclaredAccess),
 }
 
 impl<T> From<PoisonError<T>> for WorkspaceError {
@@ -34,6 +41,9 @@ impl<T> From<PoisonError<T>> for WorkspaceError {
 
 pub type WorkspaceResult<T> = Result<T, WorkspaceError>;
 
+/// The directory within a workspace containing the temporary directories of tasks
+pub const TEMP_DIR_NAME: &str = "tmp";
+
 pub trait WorkspaceEntry {
     fn into_absolute_path(self) -> WorkspaceResult<PathBuf>;
 }
@@ -177,6 +187,8 @@ impl Workspace {
             debug!("resolved path to {:?}", path);
             let true_path = self.root_dir.join(path);
             debug!("creating path at {:?}", true_path);
+            check_write(&true_path)?;
+            undeclared_io::check_write(&true_path)?;
             if let Some(parent) = true_path.parent() {
                 create_dir_all(parent)?;
             }
@@ -195,6 +207,18 @@ impl Workspace {
     pub fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf {
         self.root_dir.join(path)
     }
+
+    /// Gets the path of the temporary directory of a task within this workspace, without creating
+    /// it. The directory is at `tmp/<task name>`.
+    pub fn temp_dir_path(&self, for_task: &TaskId) -> PathBuf {
+        self.root_dir.join(TEMP_DIR_NAME).join(for_task.this())
+    }
+
+    /// Gets the temporary directory of a task within this workspace, creating it if it doesn't
+    /// exist. Files within it are kept between builds.
+    pub fn temp_dir(&self, for_task: &TaskId) -> WorkspaceResult<Dir> {
+        self.dir(&format!("{}/{}", TEMP_DIR_NAME, for_task.this()))
+    }
 }
 
 impl WorkspaceEntry for Workspace {
@@ -383,6 +407,7 @@ pub mod default_workspaces {
 
 #[cfg(test)]
 mod tests {
+    use crate::identifier::TaskId;
     use crate::workspace::{Workspace, WorkspaceDirectory};
 
     #[test]
@@ -401,4 +426,14 @@ mod tests {
         let file = dir.file("tests.txt").unwrap();
         assert!(file.metadata().unwrap().is_file());
     }
+
+    #[test]
+    fn temp_dir_of_task() {
+        let workspace = Workspace::new_temp();
+        let task = TaskId::new("root:compile").unwrap();
+        let dir = workspace.temp_dir(&task).unwrap();
+        assert_eq!(dir.absolute_path(), workspace.temp_dir_path(&task));
+        assert!(workspace.temp_dir_path(&task).ends_with("tmp/compile"));
+        assert!(dir.absolute_path().is_dir());
+    }
 }
@@ -0,0 +1,160 @@
+//! Track the headers a source file includes, so that only sources affected by a change are
+//! compiled again.
+//!
+//! gcc and clang write make-style dependency files with `-MMD`. msvc instead prints every
+//! included header with `/showIncludes`; those lines are collected from its output with
+//! [`parse_show_includes`](parse_show_includes) and written to the same format.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The prefix msvc prints before every included file with `/showIncludes`
+pub const SHOW_INCLUDES_PREFIX: &str = "Note: including file:";
+
+/// Parses a make-style dependency file, returning every prerequisite of its rule. The first
+/// prerequisite is normally the source file itself.
+pub fn parse_depfile(contents: &str) -> Vec<PathBuf> {
+    let joined = contents.replace("\\\r\n", " ").replace("\\\n", " ");
+    let prerequisites = match find_rule_separator(&joined) {
+        Some(index) => &joined[index + 1..],
+        None => return vec![],
+    };
+
+    let mut paths = vec![];
+    let mut current = String::new();
+    let mut chars = prerequisites.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&' ') => {
+                current.push(' ');
+                chars.next();
+            }
+            '$' if chars.peek() == Some(&'$') => {
+                current.push('$');
+                chars.next();
+            }
+            c if c.is_whitespace() => {
+                if !current.is_empty() {
+                    paths.push(PathBuf::from(std::mem::take(&mut current)));
+                }
+                // only the first rule is read, phony targets for headers follow it
+                if c == '\n' {
+                    break;
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        paths.push(PathBuf::from(current));
+    }
+    paths
+}
+
+/// Finds the `:` separating the target from its prerequisites, skipping drive letters like `C:\`
+fn find_rule_separator(rule: &str) -> Option<usize> {
+    let bytes = rule.as_bytes();
+    bytes.iter().enumerate().position(|(index, &b)| {
+        b == b':'
+            && !(index > 0
+                && bytes[index - 1].is_ascii_alphabetic()
+                && matches!(bytes.get(index + 1), Some(b'\\') | Some(b'/')))
+    })
+}
+
+/// Splits the output of msvc into the headers reported by `/showIncludes` and all other lines
+pub fn parse_show_includes(output: &str) -> (Vec<PathBuf>, Vec<&str>) {
+    let mut headers = vec![];
+    let mut other = vec![];
+    for line in output.lines() {
+        match line.strip_prefix(SHOW_INCLUDES_PREFIX) {
+            Some(header) => headers.push(PathBuf::from(header.trim())),
+            None => other.push(line),
+        }
+    }
+    (headers, other)
+}
+
+/// Writes a make-style dependency file
+pub fn write_depfile(
+    depfile: &Path,
+    object: &Path,
+    source: &Path,
+    headers: &[PathBuf],
+) -> io::Result<()> {
+    let escape = |path: &Path| path.to_string_lossy().replace(' ', "\\ ");
+    let mut contents = format!("{}: {}", escape(object), escape(source));
+    for header in headers {
+        contents.push_str(" \\\n  ");
+        contents.push_str(&escape(header));
+    }
+    contents.push('\n');
+    fs::write(depfile, contents)
+}
+
+/// Whether an object file needs to be compiled again. This is the case when the object or its
+/// dependency file is missing, or when the source or any header it included has changed since
+/// the object was written.
+pub fn needs_compile(source: &Path, object: &Path, depfile: &Path) -> bool {
+    let object_modified = match modified(object) {
+        Some(modified) => modified,
+        None => return true,
+    };
+    let dependencies = match fs::read_to_string(depfile) {
+        Ok(contents) => parse_depfile(&contents),
+        Err(_) => return true,
+    };
+    std::iter::once(source)
+        .chain(dependencies.iter().map(PathBuf::as_path))
+        .any(|dependency| match modified(dependency) {
+            Some(modified) => modified > object_modified,
+            None => true,
+        })
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_gcc_depfile() {
+        let depfile = "build/obj/main.o: src/main.cpp include/my\\ header.h \\\n  /usr/include/stdio.h\n\ninclude/my\\ header.h:\n";
+        assert_eq!(
+            parse_depfile(depfile),
+            [
+                PathBuf::from("src/main.cpp"),
+                PathBuf::from("include/my header.h"),
+                PathBuf::from("/usr/include/stdio.h"),
+            ]
+        );
+    }
+
+    #[test]
+    fn msvc_includes_round_trip() {
+        let output = "main.cpp\nNote: including file: C:\\src\\util.h\nNote: including file:  C:\\VC\\include\\vector\nmain.cpp(3): warning C4101";
+        let (headers, other) = parse_show_includes(output);
+        assert_eq!(headers.len(), 2);
+        assert_eq!(other, ["main.cpp", "main.cpp(3): warning C4101"]);
+
+        let dir = std::env::temp_dir().join(format!("assemble-cpp-depfile-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let depfile = dir.join("main.d");
+        write_depfile(
+            &depfile,
+            Path::new("C:\\obj\\main.obj"),
+            Path::new("C:\\src\\main.cpp"),
+            &headers,
+        )
+        .unwrap();
+        let parsed = parse_depfile(&fs::read_to_string(&depfile).unwrap());
+        assert_eq!(parsed[0], PathBuf::from("C:\\src\\main.cpp"));
+        assert_eq!(&parsed[1..], &headers[..]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
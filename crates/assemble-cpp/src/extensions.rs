@@ -0,0 +1,90 @@
+//! Contains c++ related extensions
+
+use crate::toolchain::CppToolchain;
+use assemble_core::identifier::Id;
+use assemble_core::lazy_evaluation::{Prop, VecProp};
+use std::path::{Path, PathBuf};
+
+/// The file extensions of sources compiled by default
+pub const SOURCE_EXTENSIONS: [&str; 5] = ["cpp", "cc", "cxx", "c++", "c"];
+
+/// The c++ plugin extension
+#[derive(Debug)]
+pub struct CppExtension {
+    /// The toolchain used to build the project. Set to the toolchain found by
+    /// [`CppToolchain::discover`](CppToolchain::discover) if one is found.
+    pub toolchain: Prop<CppToolchain>,
+    /// The name of the produced executable or library. Defaults to the name of the project.
+    pub base_name: Prop<String>,
+    /// The directories containing sources. Defaults to `src/main/cpp`.
+    pub source_dirs: VecProp<PathBuf>,
+    /// The directories searched for headers. Defaults to `src/main/headers`, and also
+    /// `src/main/public` for libraries.
+    pub include_dirs: VecProp<PathBuf>,
+    /// Preprocessor definitions used for every variant
+    pub defines: VecProp<String>,
+    /// Additional arguments passed to the compiler
+    pub compiler_args: VecProp<String>,
+    /// Libraries linked into the executable or shared library
+    pub libraries: VecProp<String>,
+    /// Directories searched for libraries
+    pub library_dirs: VecProp<PathBuf>,
+    /// Additional arguments passed to the linker
+    pub linker_args: VecProp<String>,
+}
+
+impl CppExtension {
+    /// Creates a new c++ extension for a project in a directory
+    pub fn new(project_dir: &Path, base_name: &str) -> Self {
+        let mut extension = Self {
+            toolchain: Prop::with_name("toolchain"),
+            base_name: Prop::with_name("base_name"),
+            source_dirs: VecProp::new(Id::new("source_dirs").unwrap()),
+            include_dirs: VecProp::new(Id::new("include_dirs").unwrap()),
+            defines: VecProp::new(Id::new("defines").unwrap()),
+            compiler_args: VecProp::new(Id::new("compiler_args").unwrap()),
+            libraries: VecProp::new(Id::new("libraries").unwrap()),
+            library_dirs: VecProp::new(Id::new("library_dirs").unwrap()),
+            linker_args: VecProp::new(Id::new("linker_args").unwrap()),
+        };
+        match CppToolchain::discover() {
+            Ok(toolchain) => extension.toolchain.set(toolchain).unwrap(),
+            Err(e) => debug!("{}", e),
+        }
+        extension.base_name.set(base_name).unwrap();
+        extension
+            .source_dirs
+            .push(project_dir.join("src").join("main").join("cpp"));
+        extension
+            .include_dirs
+            .push(project_dir.join("src").join("main").join("headers"));
+        extension
+    }
+}
+
+/// Finds the sources in some directories, sorted so that objects are always linked in the same
+/// order. Directories that don't exist are skipped.
+pub fn find_sources(dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let mut sources = vec![];
+    let mut stack = dirs.to_vec();
+    while let Some(dir) = stack.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                stack.push(path);
+            } else if path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map_or(false, |ext| SOURCE_EXTENSIONS.contains(&ext))
+            {
+                sources.push(path);
+            }
+        }
+    }
+    sources.sort();
+    sources
+}
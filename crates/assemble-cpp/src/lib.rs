@@ -0,0 +1,34 @@
+//! Provides c and c++ tasks for assemble-projects
+
+#[macro_use]
+extern crate serde;
+
+#[macro_use]
+extern crate log;
+
+use crate::plugin::CppApplicationPlugin;
+use assemble_core::plugins::PluginAware;
+use assemble_core::project::ProjectResult;
+use assemble_core::Project;
+
+pub mod depfile;
+pub mod extensions;
+pub mod plugin;
+pub mod tasks;
+pub mod toolchain;
+pub mod variant;
+
+mod prelude {
+    pub use assemble_core::*;
+    pub use assemble_std::*;
+}
+
+/// The default plugin for c++, which builds an application
+#[derive(Debug, Default)]
+pub struct Plugin;
+impl assemble_core::Plugin<Project> for Plugin {
+    fn apply_to(&self, project: &mut Project) -> ProjectResult {
+        project.apply_plugin::<CppApplicationPlugin>()?;
+        Ok(())
+    }
+}
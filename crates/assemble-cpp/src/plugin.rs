@@ -0,0 +1,244 @@
+//! Contains the c++ plugins
+
+use crate::extensions::{find_sources, CppExtension};
+use crate::tasks::archive::StaticLibrary;
+use crate::tasks::compile::CompileCpp;
+use crate::tasks::link::{LinkExecutable, LinkSharedLibrary};
+use crate::toolchain::CppToolchain;
+use crate::variant::Variant;
+use assemble_core::defaults::plugins::LifecycleBasePlugin;
+use assemble_core::defaults::tasks::Empty;
+use assemble_core::identifier::TaskId;
+use assemble_core::lazy_evaluation::{Provider, ProviderExt};
+use assemble_core::plugins::extensions::ExtensionAware;
+use assemble_core::plugins::{Plugin, PluginAware};
+use assemble_core::project::error::ProjectResult;
+use assemble_core::task::TaskHandle;
+use assemble_core::Project;
+use std::path::PathBuf;
+
+/// The name of the extension added by the c++ plugins
+pub const CPP_EXTENSION: &str = "cpp";
+
+/// The group of the tasks registered by the c++ plugins
+pub const BUILD_GROUP: &str = "build";
+
+/// Builds a c++ executable.
+///
+/// # Provided Tasks
+/// For each [`Variant`](Variant), where `<variant>` is `debug` or `release`:
+/// - `compile-<variant>`: compiles the sources of the project
+/// - `link-<variant>`: links the compiled objects into an executable
+/// - `assemble-<variant>`: builds the executable
+///
+/// Applies the [`LifecycleBasePlugin`](LifecycleBasePlugin), whose `assemble` task builds the debug
+/// variant.
+#[derive(Debug, Default)]
+pub struct CppApplicationPlugin;
+
+impl Plugin<Project> for CppApplicationPlugin {
+    fn apply_to(&self, project: &mut Project) -> ProjectResult {
+        add_extension(project)?;
+        let mut lifecycle = vec![];
+        for variant in Variant::ALL {
+            let compile = register_compile(project, variant, false)?;
+            let link = project
+                .task_container_mut()
+                .register_task_with::<LinkExecutable, _>(
+                    &format!("link-{}", variant),
+                    move |t, project| {
+                        let ext = project.extension::<CppExtension>()?;
+                        t.set_group(BUILD_GROUP);
+                        t.toolchain.set_with(ext.toolchain.clone())?;
+                        t.variant.set(variant)?;
+                        t.objects
+                            .push_all_with(compile.provides(|c| c.objects.clone()).flatten());
+                        t.libraries.push_all_with(ext.libraries.clone());
+                        t.library_dirs.push_all_with(ext.library_dirs.clone());
+                        t.linker_args.push_all_with(ext.linker_args.clone());
+                        t.output.set_with(output_file(
+                            project,
+                            ["exe", "main", variant.name()],
+                            CppToolchain::executable_name,
+                        )?)?;
+                        Ok(())
+                    },
+                )?;
+            lifecycle.push(register_variant_lifecycle(
+                project,
+                variant,
+                "Builds the executable",
+                vec![link.id().clone()],
+            )?);
+        }
+        register_assemble(project, lifecycle)
+    }
+}
+
+/// Builds a c++ library, as both a shared and a static library. Headers in `src/main/public` are
+/// the public headers of the library, and are also used to compile it.
+///
+/// # Provided Tasks
+/// For each [`Variant`](Variant), where `<variant>` is `debug` or `release`:
+/// - `compile-<variant>`: compiles the sources of the project as position independent code
+/// - `link-<variant>`: links the compiled objects into a shared library
+/// - `create-<variant>-static`: archives the compiled objects into a static library
+/// - `assemble-<variant>`: builds both libraries
+///
+/// Applies the [`LifecycleBasePlugin`](LifecycleBasePlugin), whose `assemble` task builds the debug
+/// variant.
+#[derive(Debug, Default)]
+pub struct CppLibraryPlugin;
+
+impl Plugin<Project> for CppLibraryPlugin {
+    fn apply_to(&self, project: &mut Project) -> ProjectResult {
+        add_extension(project)?;
+        let public_headers = project
+            .project_dir()
+            .join("src")
+            .join("main")
+            .join("public");
+        project
+            .extension_mut::<CppExtension>()?
+            .include_dirs
+            .push(public_headers);
+
+        let mut lifecycle = vec![];
+        for variant in Variant::ALL {
+            let compile = register_compile(project, variant, true)?;
+            let objects = compile.provides(|c| c.objects.clone()).flatten();
+            let shared_objects = objects.clone();
+            let container = project.task_container_mut();
+            let link = container.register_task_with::<LinkSharedLibrary, _>(
+                &format!("link-{}", variant),
+                move |t, project| {
+                    let ext = project.extension::<CppExtension>()?;
+                    t.set_group(BUILD_GROUP);
+                    t.toolchain.set_with(ext.toolchain.clone())?;
+                    t.variant.set(variant)?;
+                    t.objects.push_all_with(shared_objects);
+                    t.libraries.push_all_with(ext.libraries.clone());
+                    t.library_dirs.push_all_with(ext.library_dirs.clone());
+                    t.linker_args.push_all_with(ext.linker_args.clone());
+                    t.output.set_with(output_file(
+                        project,
+                        ["lib", "main", variant.name(), "shared"],
+                        CppToolchain::shared_library_name,
+                    )?)?;
+                    Ok(())
+                },
+            )?;
+            let archive = container.register_task_with::<StaticLibrary, _>(
+                &format!("create-{}-static", variant),
+                move |t, project| {
+                    let ext = project.extension::<CppExtension>()?;
+                    t.set_group(BUILD_GROUP);
+                    t.toolchain.set_with(ext.toolchain.clone())?;
+                    t.objects.push_all_with(objects);
+                    t.output.set_with(output_file(
+                        project,
+                        ["lib", "main", variant.name(), "static"],
+                        CppToolchain::static_library_name,
+                    )?)?;
+                    Ok(())
+                },
+            )?;
+            lifecycle.push(register_variant_lifecycle(
+                project,
+                variant,
+                "Builds the shared and static libraries",
+                vec![link.id().clone(), archive.id().clone()],
+            )?);
+        }
+        register_assemble(project, lifecycle)
+    }
+}
+
+fn add_extension(project: &mut Project) -> ProjectResult {
+    project.apply_plugin::<LifecycleBasePlugin>()?;
+    let extension = CppExtension::new(&project.project_dir(), project.id().this());
+    project.extensions_mut().add(CPP_EXTENSION, extension)?;
+    Ok(())
+}
+
+/// Registers the task compiling a variant
+fn register_compile(
+    project: &mut Project,
+    variant: Variant,
+    position_independent: bool,
+) -> ProjectResult<TaskHandle<CompileCpp>> {
+    project
+        .task_container_mut()
+        .register_task_with::<CompileCpp, _>(&format!("compile-{}", variant), move |t, project| {
+            let ext = project.extension::<CppExtension>()?;
+            t.set_group(BUILD_GROUP);
+            t.toolchain.set_with(ext.toolchain.clone())?;
+            t.variant.set(variant)?;
+            t.sources.push_all_with(
+                ext.source_dirs
+                    .clone()
+                    .map(|dirs: Vec<PathBuf>| find_sources(&dirs)),
+            );
+            t.include_dirs.push_all_with(ext.include_dirs.clone());
+            t.defines.push_all_with(ext.defines.clone());
+            t.compiler_args.push_all_with(ext.compiler_args.clone());
+            t.position_independent.set(position_independent)?;
+            t.object_dir.set_with(
+                project
+                    .build_dir()
+                    .map(move |dir| dir.join("obj").join("main").join(variant.name())),
+            )?;
+            Ok(())
+        })
+}
+
+/// Creates a provider of a file in the build directory, named after the base name of the project
+fn output_file<const N: usize>(
+    project: &Project,
+    dirs: [&'static str; N],
+    file_name: fn(&CppToolchain, &str) -> String,
+) -> ProjectResult<impl Provider<PathBuf> + Clone> {
+    let ext = project.extension::<CppExtension>()?;
+    Ok(project.build_dir().zip3(
+        ext.toolchain.clone(),
+        ext.base_name.clone(),
+        move |build_dir, toolchain, base_name| {
+            dirs.iter()
+                .fold(build_dir, |path, dir| path.join(dir))
+                .join(file_name(&toolchain, &base_name))
+        },
+    ))
+}
+
+/// Registers the `assemble-<variant>` task
+fn register_variant_lifecycle(
+    project: &mut Project,
+    variant: Variant,
+    description: &'static str,
+    outputs: Vec<TaskId>,
+) -> ProjectResult<TaskHandle<Empty>> {
+    project.task_container_mut().register_task_with::<Empty, _>(
+        &format!("assemble-{}", variant),
+        move |t, _| {
+            t.set_description(&format!("{} for the {} variant", description, variant));
+            t.set_group(BUILD_GROUP);
+            for output in outputs {
+                t.depends_on(output);
+            }
+            Ok(())
+        },
+    )
+}
+
+/// Attaches the first variant to the `assemble` lifecycle task
+fn register_assemble(project: &mut Project, variants: Vec<TaskHandle<Empty>>) -> ProjectResult {
+    let debug = variants.into_iter().next();
+    LifecycleBasePlugin::task(project, LifecycleBasePlugin::ASSEMBLE)?.configure_with(
+        move |t, _| {
+            if let Some(debug) = debug {
+                t.depends_on(debug);
+            }
+            Ok(())
+        },
+    )
+}
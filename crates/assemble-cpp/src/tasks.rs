@@ -0,0 +1,66 @@
+//! Tasks that compile, link and archive c++ code
+
+use crate::prelude::*;
+use assemble_core::error::PayloadError;
+use assemble_core::exception::BuildException;
+use assemble_std::specs::exec_spec::Output;
+use assemble_std::ProjectExec;
+use std::ffi::OsString;
+use std::path::Path;
+
+pub mod archive;
+pub mod compile;
+pub mod link;
+
+/// The output of a tool that ran successfully
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ToolOutput {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Runs a tool of a toolchain. Anything it prints is logged as a warning if it fails, and as info
+/// otherwise, except for the lines `filter` returns false for.
+pub(crate) fn run_tool<F>(
+    project: &Project,
+    program: &Path,
+    args: &[OsString],
+    filter: F,
+) -> BuildResult<ToolOutput>
+where
+    F: Fn(&str) -> bool,
+{
+    trace!("running {:?} with {:?}", program, args);
+    let result = project.exec_with(|exec| {
+        exec.exec(program)
+            .args(args)
+            .stdout(Output::Bytes)
+            .stderr(Output::Bytes);
+    })?;
+    let output = ToolOutput {
+        stdout: result
+            .utf8_string()
+            .unwrap_or_else(|| Ok(String::new()))
+            .map_err(PayloadError::<BuildException>::new)?,
+        stderr: result
+            .utf8_string_err()
+            .unwrap_or_else(|| Ok(String::new()))
+            .map_err(PayloadError::<BuildException>::new)?,
+    };
+    let lines = output
+        .stdout
+        .lines()
+        .chain(output.stderr.lines())
+        .filter(|line| filter(line));
+    if result.success() {
+        lines.for_each(|line| info!("{}", line));
+        Ok(output)
+    } else {
+        lines.for_each(|line| warn!("{}", line));
+        Err(BuildException::custom(&format!(
+            "{:?} failed",
+            program.file_name().unwrap_or(program.as_os_str())
+        ))
+        .into())
+    }
+}
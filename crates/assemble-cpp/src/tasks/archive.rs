@@ -0,0 +1,74 @@
+//! Archive object files into static libraries
+
+use crate::prelude::*;
+use crate::tasks::run_tool;
+use crate::toolchain::CppToolchain;
+use assemble_core::error::PayloadError;
+use assemble_core::exception::BuildException;
+use assemble_core::identifier::TaskId;
+use assemble_core::lazy_evaluation::{Prop, Provider, VecProp};
+use assemble_core::project::error::ProjectResult;
+use assemble_core::task::create_task::CreateTask;
+use assemble_core::task::initialize_task::InitializeTask;
+use assemble_core::task::task_io::TaskIO;
+use assemble_core::task::up_to_date::UpToDate;
+use std::fs;
+use std::path::PathBuf;
+
+/// Archives object files into a static library
+#[derive(Debug)]
+pub struct StaticLibrary {
+    /// The toolchain to archive with
+    pub toolchain: Prop<CppToolchain>,
+    /// The object files to archive
+    pub objects: VecProp<PathBuf>,
+    /// The static library to write
+    pub output: Prop<PathBuf>,
+}
+
+impl CreateTask for StaticLibrary {
+    fn new(using_id: &TaskId, _project: &Project) -> ProjectResult<Self> {
+        Ok(Self {
+            toolchain: using_id.prop("toolchain").map_err(PayloadError::new)?,
+            objects: using_id.vec_prop("objects").map_err(PayloadError::new)?,
+            output: using_id.prop("output").map_err(PayloadError::new)?,
+        })
+    }
+
+    fn description() -> String {
+        "Archives object files into a static library".to_string()
+    }
+}
+
+impl TaskIO for StaticLibrary {
+    fn configure_io(task: &mut Executable<Self>) -> ProjectResult {
+        let objects = task.objects.clone();
+        let output = task.output.clone();
+        task.work().add_input_files("objects", objects)?;
+        task.work().add_output_provider(output);
+        Ok(())
+    }
+}
+
+impl InitializeTask for StaticLibrary {}
+
+impl UpToDate for StaticLibrary {}
+
+impl Task for StaticLibrary {
+    fn task_action(task: &mut Executable<Self>, project: &Project) -> BuildResult {
+        let toolchain = task.toolchain.fallible_get()?;
+        let objects = task.objects.fallible_get()?;
+        let output = task.output.fallible_get()?;
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent).map_err(PayloadError::<BuildException>::new)?;
+        }
+        // ar adds to existing archives, which would keep objects that were removed
+        if output.exists() {
+            fs::remove_file(&output).map_err(PayloadError::<BuildException>::new)?;
+        }
+        let args = toolchain.archive_args(&output, &objects);
+        run_tool(project, &toolchain.archiver, &args, |_| true)?;
+        info!("archived {:?}", output);
+        Ok(())
+    }
+}
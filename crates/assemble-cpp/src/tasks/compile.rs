@@ -0,0 +1,226 @@
+//! Compile c++ sources into object files
+
+use crate::depfile::{needs_compile, parse_show_includes, write_depfile};
+use crate::prelude::*;
+use crate::tasks::run_tool;
+use crate::toolchain::{CompileSpec, CppToolchain, ToolchainKind};
+use crate::variant::Variant;
+use assemble_core::cryptography::hash_sha256;
+use assemble_core::error::PayloadError;
+use assemble_core::exception::BuildException;
+use assemble_core::identifier::TaskId;
+use assemble_core::lazy_evaluation::{Prop, Provider, ProviderExt, VecProp};
+use assemble_core::project::error::ProjectResult;
+use assemble_core::task::create_task::CreateTask;
+use assemble_core::task::initialize_task::InitializeTask;
+use assemble_core::task::task_io::TaskIO;
+use assemble_core::task::up_to_date::UpToDate;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Compiles c++ sources into object files, one object per source.
+///
+/// Compilation is incremental: the headers every source includes are recorded in a dependency
+/// file next to its object, and a source is only compiled again when it, or one of those
+/// headers, changed since its object was written. Objects of sources that were removed are
+/// deleted.
+#[derive(Debug)]
+pub struct CompileCpp {
+    /// The toolchain to compile with
+    pub toolchain: Prop<CppToolchain>,
+    /// The variant to compile
+    pub variant: Prop<Variant>,
+    /// The source files to compile
+    pub sources: VecProp<PathBuf>,
+    /// Directories searched for headers
+    pub include_dirs: VecProp<PathBuf>,
+    /// Preprocessor definitions, as `NAME` or `NAME=value`
+    pub defines: VecProp<String>,
+    /// Additional arguments passed to the compiler
+    pub compiler_args: VecProp<String>,
+    /// Whether position independent code is compiled, which is needed for shared libraries.
+    /// Defaults to false.
+    pub position_independent: Prop<bool>,
+    /// The directory objects are written to
+    pub object_dir: Prop<PathBuf>,
+    /// The object files compiled from the sources, in the same order as the sources
+    pub objects: VecProp<PathBuf>,
+}
+
+impl CreateTask for CompileCpp {
+    fn new(using_id: &TaskId, _project: &Project) -> ProjectResult<Self> {
+        Ok(Self {
+            toolchain: using_id.prop("toolchain").map_err(PayloadError::new)?,
+            variant: using_id.prop("variant").map_err(PayloadError::new)?,
+            sources: using_id.vec_prop("sources").map_err(PayloadError::new)?,
+            include_dirs: using_id
+                .vec_prop("include_dirs")
+                .map_err(PayloadError::new)?,
+            defines: using_id.vec_prop("defines").map_err(PayloadError::new)?,
+            compiler_args: using_id
+                .vec_prop("compiler_args")
+                .map_err(PayloadError::new)?,
+            position_independent: using_id
+                .prop("position_independent")
+                .map_err(PayloadError::new)?,
+            object_dir: using_id.prop("object_dir").map_err(PayloadError::new)?,
+            objects: using_id.vec_prop("objects").map_err(PayloadError::new)?,
+        })
+    }
+
+    fn description() -> String {
+        "Compiles c++ sources into object files".to_string()
+    }
+}
+
+impl TaskIO for CompileCpp {
+    fn configure_io(task: &mut Executable<Self>) -> ProjectResult {
+        let sources = task.sources.clone();
+        let objects = task.objects.clone();
+        task.work().add_input_files("sources", sources)?;
+        task.work().add_output_provider(objects);
+        Ok(())
+    }
+}
+
+impl InitializeTask for CompileCpp {
+    fn initialize(task: &mut Executable<Self>, _project: &Project) -> ProjectResult {
+        task.position_independent.set(false)?;
+        let objects = task.sources.clone().zip3(
+            task.object_dir.clone(),
+            task.toolchain.clone(),
+            |sources, object_dir, toolchain| {
+                sources
+                    .iter()
+                    .map(|source| object_file(&toolchain, &object_dir, source))
+                    .collect::<Vec<_>>()
+            },
+        );
+        task.objects.push_all_with(objects);
+        Ok(())
+    }
+}
+
+impl UpToDate for CompileCpp {
+    /// Always run, whether each source needs to be compiled is decided with its dependency file
+    fn up_to_date(&self) -> bool {
+        false
+    }
+}
+
+impl Task for CompileCpp {
+    fn task_action(task: &mut Executable<Self>, project: &Project) -> BuildResult {
+        let toolchain = task.toolchain.fallible_get()?;
+        let variant = task.variant.fallible_get()?;
+        let sources = task.sources.fallible_get()?;
+        let include_dirs = task.include_dirs.fallible_get()?;
+        let defines = task.defines.fallible_get()?;
+        let compiler_args = task.compiler_args.fallible_get()?;
+        let position_independent = task.position_independent.fallible_get()?;
+        let object_dir = task.object_dir.fallible_get()?;
+        let objects = task.objects.fallible_get()?;
+
+        fs::create_dir_all(&object_dir).map_err(PayloadError::<BuildException>::new)?;
+        remove_stale_objects(&object_dir, &objects)?;
+
+        let mut compiled = 0;
+        for (source, object) in sources.iter().zip(&objects) {
+            let depfile = object.with_extension("d");
+            if !needs_compile(source, object, &depfile) {
+                trace!("{:?} is up to date", object);
+                continue;
+            }
+            debug!("compiling {:?}", source);
+            let args = toolchain.compile_args(&CompileSpec {
+                source,
+                object,
+                depfile: &depfile,
+                variant,
+                include_dirs: &include_dirs,
+                defines: &defines,
+                position_independent,
+                args: &compiler_args,
+            });
+            let output = run_tool(project, &toolchain.compiler, &args, |line| {
+                !is_msvc_noise(toolchain.kind, source, line)
+            })?;
+            if toolchain.kind == ToolchainKind::Msvc {
+                let (headers, _) = parse_show_includes(&output.stdout);
+                write_depfile(&depfile, object, source, &headers)
+                    .map_err(PayloadError::<BuildException>::new)?;
+            }
+            compiled += 1;
+        }
+        info!(
+            "compiled {} of {} source(s) for {}",
+            compiled,
+            sources.len(),
+            variant
+        );
+        Ok(())
+    }
+}
+
+/// The object file a source is compiled to. Sources with the same file name in different
+/// directories get different objects, because the name includes a hash of the source's path.
+pub fn object_file(toolchain: &CppToolchain, object_dir: &Path, source: &Path) -> PathBuf {
+    let stem = source
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let hash = hash_sha256(source.to_string_lossy().as_bytes()).to_string();
+    object_dir.join(toolchain.object_file_name(&format!("{}-{}", stem, &hash[..8])))
+}
+
+/// Removes objects, and their dependency files, that no source compiles to anymore
+fn remove_stale_objects(object_dir: &Path, objects: &[PathBuf]) -> BuildResult {
+    let entries = fs::read_dir(object_dir).map_err(PayloadError::<BuildException>::new)?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_object = matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("o") | Some("obj")
+        );
+        if is_object && !objects.contains(&path) {
+            debug!("removing stale object {:?}", path);
+            fs::remove_file(&path).map_err(PayloadError::<BuildException>::new)?;
+            let _ = fs::remove_file(path.with_extension("d"));
+        }
+    }
+    Ok(())
+}
+
+/// msvc prints the name of the source it compiles and every included header, which aren't
+/// worth logging
+fn is_msvc_noise(kind: ToolchainKind, source: &Path, line: &str) -> bool {
+    kind == ToolchainKind::Msvc
+        && (line.starts_with(crate::depfile::SHOW_INCLUDES_PREFIX)
+            || source
+                .file_name()
+                .map_or(false, |name| name.to_string_lossy() == line.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn objects_of_sources_with_same_name_differ() {
+        let toolchain = CppToolchain {
+            kind: ToolchainKind::Gcc,
+            compiler: PathBuf::from("g++"),
+            archiver: PathBuf::from("ar"),
+        };
+        let dir = Path::new("build/obj");
+        let first = object_file(&toolchain, dir, Path::new("src/a/util.cpp"));
+        let second = object_file(&toolchain, dir, Path::new("src/b/util.cpp"));
+        assert_ne!(first, second);
+        assert_eq!(first.parent(), Some(dir));
+        assert!(first
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("util-"));
+        assert_eq!(first.extension().unwrap(), "o");
+    }
+}
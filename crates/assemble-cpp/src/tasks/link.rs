@@ -0,0 +1,137 @@
+//! Link object files into executables and shared libraries
+
+use crate::prelude::*;
+use crate::tasks::run_tool;
+use crate::toolchain::{CppToolchain, LinkSpec};
+use crate::variant::Variant;
+use assemble_core::error::PayloadError;
+use assemble_core::exception::BuildException;
+use assemble_core::identifier::TaskId;
+use assemble_core::lazy_evaluation::{Prop, Provider, VecProp};
+use assemble_core::project::error::ProjectResult;
+use assemble_core::task::create_task::CreateTask;
+use assemble_core::task::initialize_task::InitializeTask;
+use assemble_core::task::task_io::TaskIO;
+use assemble_core::task::up_to_date::UpToDate;
+use std::fs;
+use std::path::PathBuf;
+
+/// Links object files into an executable
+#[derive(Debug)]
+pub struct LinkExecutable {
+    /// The toolchain to link with
+    pub toolchain: Prop<CppToolchain>,
+    /// The variant being linked
+    pub variant: Prop<Variant>,
+    /// The object files to link
+    pub objects: VecProp<PathBuf>,
+    /// Libraries to link against, without prefix or extension
+    pub libraries: VecProp<String>,
+    /// Directories searched for libraries
+    pub library_dirs: VecProp<PathBuf>,
+    /// Additional arguments passed to the linker
+    pub linker_args: VecProp<String>,
+    /// The executable to write
+    pub output: Prop<PathBuf>,
+}
+
+/// Links object files into a shared library. The objects should be compiled as position
+/// independent code.
+#[derive(Debug)]
+pub struct LinkSharedLibrary {
+    /// The toolchain to link with
+    pub toolchain: Prop<CppToolchain>,
+    /// The variant being linked
+    pub variant: Prop<Variant>,
+    /// The object files to link
+    pub objects: VecProp<PathBuf>,
+    /// Libraries to link against, without prefix or extension
+    pub libraries: VecProp<String>,
+    /// Directories searched for libraries
+    pub library_dirs: VecProp<PathBuf>,
+    /// Additional arguments passed to the linker
+    pub linker_args: VecProp<String>,
+    /// The shared library to write
+    pub output: Prop<PathBuf>,
+}
+
+macro_rules! link_task {
+    ($ty:ident, $shared:expr, $description:literal) => {
+        impl CreateTask for $ty {
+            fn new(using_id: &TaskId, _project: &Project) -> ProjectResult<Self> {
+                Ok(Self {
+                    toolchain: using_id.prop("toolchain").map_err(PayloadError::new)?,
+                    variant: using_id.prop("variant").map_err(PayloadError::new)?,
+                    objects: using_id.vec_prop("objects").map_err(PayloadError::new)?,
+                    libraries: using_id.vec_prop("libraries").map_err(PayloadError::new)?,
+                    library_dirs: using_id
+                        .vec_prop("library_dirs")
+                        .map_err(PayloadError::new)?,
+                    linker_args: using_id
+                        .vec_prop("linker_args")
+                        .map_err(PayloadError::new)?,
+                    output: using_id.prop("output").map_err(PayloadError::new)?,
+                })
+            }
+
+            fn description() -> String {
+                $description.to_string()
+            }
+        }
+
+        impl TaskIO for $ty {
+            fn configure_io(task: &mut Executable<Self>) -> ProjectResult {
+                let objects = task.objects.clone();
+                let libraries = task.libraries.clone();
+                let linker_args = task.linker_args.clone();
+                let output = task.output.clone();
+                task.work().add_input_files("objects", objects)?;
+                task.work().add_input("libraries", libraries)?;
+                task.work().add_input("linker_args", linker_args)?;
+                task.work().add_output_provider(output);
+                Ok(())
+            }
+        }
+
+        impl InitializeTask for $ty {}
+
+        impl UpToDate for $ty {}
+
+        impl Task for $ty {
+            fn task_action(task: &mut Executable<Self>, project: &Project) -> BuildResult {
+                let toolchain = task.toolchain.fallible_get()?;
+                let output = task.output.fallible_get()?;
+                let objects = task.objects.fallible_get()?;
+                let libraries = task.libraries.fallible_get()?;
+                let library_dirs = task.library_dirs.fallible_get()?;
+                let linker_args = task.linker_args.fallible_get()?;
+                if let Some(parent) = output.parent() {
+                    fs::create_dir_all(parent).map_err(PayloadError::<BuildException>::new)?;
+                }
+                let args = toolchain.link_args(&LinkSpec {
+                    output: &output,
+                    objects: &objects,
+                    variant: task.variant.fallible_get()?,
+                    shared: $shared,
+                    libraries: &libraries,
+                    library_dirs: &library_dirs,
+                    args: &linker_args,
+                });
+                run_tool(project, &toolchain.compiler, &args, |_| true)?;
+                info!("linked {:?}", output);
+                Ok(())
+            }
+        }
+    };
+}
+
+link_task!(
+    LinkExecutable,
+    false,
+    "Links object files into an executable"
+);
+link_task!(
+    LinkSharedLibrary,
+    true,
+    "Links object files into a shared library"
+);
@@ -0,0 +1,370 @@
+//! Find the compiler, linker and archiver used to build c++ projects
+
+use crate::variant::Variant;
+use std::ffi::OsString;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+/// The compilers, in the order they are searched for when `CXX` isn't set
+pub const COMPILER_CANDIDATES: [&str; 4] = ["clang++", "g++", "c++", "cl"];
+
+/// The family of a toolchain, which decides the command line syntax used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ToolchainKind {
+    /// The gnu compiler collection, or a compatible compiler
+    Gcc,
+    /// clang
+    Clang,
+    /// The microsoft visual c++ compiler
+    Msvc,
+}
+
+impl ToolchainKind {
+    /// Guesses the kind of a compiler from its file name
+    pub fn of_compiler<P: AsRef<Path>>(compiler: P) -> Self {
+        let name = compiler
+            .as_ref()
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if name == "cl" || name == "clang-cl" {
+            ToolchainKind::Msvc
+        } else if name.contains("clang") {
+            ToolchainKind::Clang
+        } else {
+            ToolchainKind::Gcc
+        }
+    }
+}
+
+impl Display for ToolchainKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToolchainKind::Gcc => write!(f, "gcc"),
+            ToolchainKind::Clang => write!(f, "clang"),
+            ToolchainKind::Msvc => write!(f, "msvc"),
+        }
+    }
+}
+
+/// No usable toolchain could be found
+#[derive(Debug, thiserror::Error)]
+#[error("no c++ compiler found. Install gcc, clang or msvc, or set CXX to the compiler to use")]
+pub struct ToolchainNotFound;
+
+/// A c++ toolchain. The compiler is also used as the linker.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CppToolchain {
+    /// The family of the toolchain
+    pub kind: ToolchainKind,
+    /// The compiler executable
+    pub compiler: PathBuf,
+    /// The executable used to create static libraries
+    pub archiver: PathBuf,
+}
+
+impl CppToolchain {
+    /// Creates a toolchain from a compiler, using the default archiver of its kind
+    pub fn new<P: AsRef<Path>>(compiler: P) -> Self {
+        let compiler = compiler.as_ref().to_path_buf();
+        let kind = ToolchainKind::of_compiler(&compiler);
+        let archiver = match kind {
+            ToolchainKind::Msvc => which::which("lib").unwrap_or_else(|_| PathBuf::from("lib")),
+            ToolchainKind::Clang => which::which("llvm-ar")
+                .or_else(|_| which::which("ar"))
+                .unwrap_or_else(|_| PathBuf::from("ar")),
+            ToolchainKind::Gcc => which::which("ar").unwrap_or_else(|_| PathBuf::from("ar")),
+        };
+        Self {
+            kind,
+            compiler,
+            archiver,
+        }
+    }
+
+    /// Finds a toolchain. The `CXX` and `AR` environment variables are used if set, otherwise
+    /// the first compiler of [`COMPILER_CANDIDATES`](COMPILER_CANDIDATES) on the `PATH` is used.
+    pub fn discover() -> Result<Self, ToolchainNotFound> {
+        let compiler = match std::env::var_os("CXX") {
+            Some(cxx) => which::which(&cxx).unwrap_or_else(|_| PathBuf::from(cxx)),
+            None => COMPILER_CANDIDATES
+                .iter()
+                .find_map(|candidate| which::which(candidate).ok())
+                .ok_or(ToolchainNotFound)?,
+        };
+        let mut toolchain = Self::new(compiler);
+        if let Some(ar) = std::env::var_os("AR") {
+            toolchain.archiver = PathBuf::from(ar);
+        }
+        debug!(
+            "using {} toolchain {:?}",
+            toolchain.kind, toolchain.compiler
+        );
+        Ok(toolchain)
+    }
+
+    /// The file name of an object file compiled from a source with the given stem
+    pub fn object_file_name(&self, stem: &str) -> String {
+        match self.kind {
+            ToolchainKind::Msvc => format!("{}.obj", stem),
+            _ => format!("{}.o", stem),
+        }
+    }
+
+    /// The file name of an executable
+    pub fn executable_name(&self, base_name: &str) -> String {
+        if cfg!(windows) {
+            format!("{}.exe", base_name)
+        } else {
+            base_name.to_string()
+        }
+    }
+
+    /// The file name of a shared library
+    pub fn shared_library_name(&self, base_name: &str) -> String {
+        if cfg!(windows) {
+            format!("{}.dll", base_name)
+        } else if cfg!(target_os = "macos") {
+            format!("lib{}.dylib", base_name)
+        } else {
+            format!("lib{}.so", base_name)
+        }
+    }
+
+    /// The file name of a static library
+    pub fn static_library_name(&self, base_name: &str) -> String {
+        match self.kind {
+            ToolchainKind::Msvc => format!("{}.lib", base_name),
+            _ => format!("lib{}.a", base_name),
+        }
+    }
+
+    /// The arguments to compile a single source file into an object file.
+    ///
+    /// gcc and clang write the headers the source includes to `depfile`. msvc can't write a
+    /// depfile, and instead prints the headers with `/showIncludes`.
+    pub fn compile_args(&self, spec: &CompileSpec) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec![];
+        match self.kind {
+            ToolchainKind::Msvc => {
+                args.extend(["/nologo", "/c", "/EHsc", "/showIncludes"].map(OsString::from));
+                args.extend(
+                    spec.variant
+                        .compiler_flags(self.kind)
+                        .iter()
+                        .map(OsString::from),
+                );
+                args.extend(spec.include_dirs.iter().map(|dir| prefixed("/I", dir)));
+                args.extend(
+                    spec.defines
+                        .iter()
+                        .map(|define| format!("/D{}", define).into()),
+                );
+                args.extend(spec.args.iter().map(OsString::from));
+                args.push(prefixed("/Fo", spec.object));
+                args.push(spec.source.into());
+            }
+            ToolchainKind::Gcc | ToolchainKind::Clang => {
+                args.push("-c".into());
+                args.extend(
+                    spec.variant
+                        .compiler_flags(self.kind)
+                        .iter()
+                        .map(OsString::from),
+                );
+                if spec.position_independent {
+                    args.push("-fPIC".into());
+                }
+                args.extend(spec.include_dirs.iter().map(|dir| prefixed("-I", dir)));
+                args.extend(
+                    spec.defines
+                        .iter()
+                        .map(|define| format!("-D{}", define).into()),
+                );
+                args.extend(spec.args.iter().map(OsString::from));
+                args.extend(["-MMD", "-MF"].map(OsString::from));
+                args.push(spec.depfile.into());
+                args.push("-o".into());
+                args.push(spec.object.into());
+                args.push(spec.source.into());
+            }
+        }
+        args
+    }
+
+    /// The arguments to link object files into an executable, or into a shared library if
+    /// `shared` is set
+    pub fn link_args(&self, spec: &LinkSpec) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec![];
+        match self.kind {
+            ToolchainKind::Msvc => {
+                args.push("/nologo".into());
+                if spec.shared {
+                    args.push("/LD".into());
+                }
+                args.extend(
+                    spec.variant
+                        .linker_flags(self.kind)
+                        .iter()
+                        .map(OsString::from),
+                );
+                args.push(prefixed("/Fe", spec.output));
+                args.extend(spec.objects.iter().map(OsString::from));
+                args.extend(
+                    spec.libraries
+                        .iter()
+                        .map(|lib| format!("{}.lib", lib).into()),
+                );
+                if !spec.library_dirs.is_empty() || !spec.args.is_empty() {
+                    args.push("/link".into());
+                    args.extend(
+                        spec.library_dirs
+                            .iter()
+                            .map(|dir| prefixed("/LIBPATH:", dir)),
+                    );
+                    args.extend(spec.args.iter().map(OsString::from));
+                }
+            }
+            ToolchainKind::Gcc | ToolchainKind::Clang => {
+                if spec.shared {
+                    args.push("-shared".into());
+                }
+                args.extend(
+                    spec.variant
+                        .linker_flags(self.kind)
+                        .iter()
+                        .map(OsString::from),
+                );
+                args.push("-o".into());
+                args.push(spec.output.into());
+                args.extend(spec.objects.iter().map(OsString::from));
+                args.extend(spec.library_dirs.iter().map(|dir| prefixed("-L", dir)));
+                args.extend(spec.libraries.iter().map(|lib| format!("-l{}", lib).into()));
+                args.extend(spec.args.iter().map(OsString::from));
+            }
+        }
+        args
+    }
+
+    /// The arguments to archive object files into a static library
+    pub fn archive_args(&self, output: &Path, objects: &[PathBuf]) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec![];
+        match self.kind {
+            ToolchainKind::Msvc => {
+                args.push("/nologo".into());
+                args.push(prefixed("/OUT:", output));
+            }
+            ToolchainKind::Gcc | ToolchainKind::Clang => {
+                args.push("rcs".into());
+                args.push(output.into());
+            }
+        }
+        args.extend(objects.iter().map(OsString::from));
+        args
+    }
+}
+
+/// How a single source file is compiled
+#[derive(Debug, Clone, Copy)]
+pub struct CompileSpec<'a> {
+    /// The source file
+    pub source: &'a Path,
+    /// The object file to write
+    pub object: &'a Path,
+    /// The file headers included by the source are written to
+    pub depfile: &'a Path,
+    /// The variant being compiled
+    pub variant: Variant,
+    /// Directories searched for headers
+    pub include_dirs: &'a [PathBuf],
+    /// Preprocessor definitions, as `NAME` or `NAME=value`
+    pub defines: &'a [String],
+    /// Whether to compile position independent code
+    pub position_independent: bool,
+    /// Additional arguments passed to the compiler
+    pub args: &'a [String],
+}
+
+/// How object files are linked
+#[derive(Debug, Clone, Copy)]
+pub struct LinkSpec<'a> {
+    /// The file to write
+    pub output: &'a Path,
+    /// The object files to link
+    pub objects: &'a [PathBuf],
+    /// The variant being linked
+    pub variant: Variant,
+    /// Whether to link a shared library instead of an executable
+    pub shared: bool,
+    /// Libraries to link against, without prefix or extension
+    pub libraries: &'a [String],
+    /// Directories searched for libraries
+    pub library_dirs: &'a [PathBuf],
+    /// Additional arguments passed to the linker
+    pub args: &'a [String],
+}
+
+fn prefixed(prefix: &str, path: &Path) -> OsString {
+    let mut arg = OsString::from(prefix);
+    arg.push(path);
+    arg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kind_from_compiler_name() {
+        assert_eq!(
+            ToolchainKind::of_compiler("/usr/bin/g++"),
+            ToolchainKind::Gcc
+        );
+        assert_eq!(
+            ToolchainKind::of_compiler("/usr/bin/clang++-14"),
+            ToolchainKind::Clang
+        );
+        assert_eq!(
+            ToolchainKind::of_compiler("C:\\VC\\cl.exe"),
+            ToolchainKind::Msvc
+        );
+    }
+
+    #[test]
+    fn gcc_compile_args() {
+        let toolchain = CppToolchain {
+            kind: ToolchainKind::Gcc,
+            compiler: PathBuf::from("g++"),
+            archiver: PathBuf::from("ar"),
+        };
+        let args = toolchain.compile_args(&CompileSpec {
+            source: Path::new("src/main.cpp"),
+            object: Path::new("obj/main.o"),
+            depfile: Path::new("obj/main.d"),
+            variant: Variant::Release,
+            include_dirs: &[PathBuf::from("include")],
+            defines: &["VERSION=2".to_string()],
+            position_independent: true,
+            args: &[],
+        });
+        assert_eq!(
+            args,
+            [
+                "-c",
+                "-O2",
+                "-DNDEBUG",
+                "-fPIC",
+                "-Iinclude",
+                "-DVERSION=2",
+                "-MMD",
+                "-MF",
+                "obj/main.d",
+                "-o",
+                "obj/main.o",
+                "src/main.cpp"
+            ]
+            .map(OsString::from)
+        );
+        assert_eq!(toolchain.static_library_name("util"), "libutil.a");
+    }
+}
@@ -0,0 +1,51 @@
+//! The variants a c++ project is built in
+
+use crate::toolchain::ToolchainKind;
+use std::fmt::{Display, Formatter};
+
+/// A build variant. Every variant is compiled and linked by its own tasks, into its own directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Variant {
+    /// Unoptimized, with debug information
+    Debug,
+    /// Optimized, with assertions disabled
+    Release,
+}
+
+impl Variant {
+    /// All variants, in the order their tasks are registered
+    pub const ALL: [Variant; 2] = [Variant::Debug, Variant::Release];
+
+    /// The name of the variant, used in task names and output directories
+    pub fn name(&self) -> &'static str {
+        match self {
+            Variant::Debug => "debug",
+            Variant::Release => "release",
+        }
+    }
+
+    /// The compiler flags of this variant for a kind of toolchain
+    pub fn compiler_flags(&self, kind: ToolchainKind) -> &'static [&'static str] {
+        match (self, kind) {
+            (Variant::Debug, ToolchainKind::Msvc) => &["/Zi", "/Od"],
+            (Variant::Release, ToolchainKind::Msvc) => &["/O2", "/DNDEBUG"],
+            (Variant::Debug, _) => &["-g", "-O0"],
+            (Variant::Release, _) => &["-O2", "-DNDEBUG"],
+        }
+    }
+
+    /// The linker flags of this variant for a kind of toolchain
+    pub fn linker_flags(&self, kind: ToolchainKind) -> &'static [&'static str] {
+        match (self, kind) {
+            (Variant::Debug, ToolchainKind::Msvc) => &["/Zi"],
+            (Variant::Debug, _) => &["-g"],
+            (Variant::Release, _) => &[],
+        }
+    }
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
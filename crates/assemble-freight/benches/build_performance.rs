@@ -0,0 +1,88 @@
+//! Benchmarks configuration time, plan creation, and no-op build time on generated multi-project
+//! builds.
+
+mod fixture;
+
+use assemble_core::prelude::{Assemble, StartParameter};
+use assemble_core::project::requests::TaskRequests;
+use assemble_freight::core::TaskResolver;
+use assemble_freight::ops::{execute_tasks2, try_creating_plan};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use fixture::{generate, FixtureSpec, AGGREGATE_TASK};
+
+/// The builds every benchmark runs against
+fn fixtures() -> Vec<FixtureSpec> {
+    vec![
+        FixtureSpec::new(1, 10),
+        FixtureSpec::new(10, 10),
+        FixtureSpec::new(50, 20),
+        FixtureSpec::new(10, 10).with_density(0.25),
+    ]
+}
+
+fn configuration(c: &mut Criterion) {
+    let mut group = c.benchmark_group("configuration");
+    for spec in fixtures() {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(spec.label()),
+            &spec,
+            |b, spec| b.iter(|| generate(spec).expect("could not generate fixture")),
+        );
+    }
+    group.finish();
+}
+
+fn plan_creation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("plan creation");
+    for spec in fixtures() {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(spec.label()),
+            &spec,
+            |b, spec| {
+                b.iter_batched(
+                    || generate(spec).expect("could not generate fixture"),
+                    |project| {
+                        let requests = TaskRequests::build(&project, [AGGREGATE_TASK])
+                            .expect("could not build task requests");
+                        let graph = TaskResolver::new(&project)
+                            .to_execution_graph(requests)
+                            .expect("could not create execution graph");
+                        let plan = try_creating_plan(graph).expect("could not create plan");
+                        assert_eq!(plan.len(), spec.total_tasks());
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+fn no_op_build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("no-op build");
+    group.sample_size(10);
+    for spec in fixtures() {
+        let mut start_parameter = StartParameter::new().with_task_requests([AGGREGATE_TASK]);
+        start_parameter.set_workers(num_cpus::get());
+        let assemble = Assemble::new(start_parameter);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(spec.label()),
+            &spec,
+            |b, spec| {
+                b.iter_batched(
+                    || generate(spec).expect("could not generate fixture"),
+                    |project| {
+                        let results =
+                            execute_tasks2(&project, &project, &assemble).expect("build failed");
+                        assert_eq!(results.len(), spec.total_tasks());
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, configuration, plan_creation, no_op_build);
+criterion_main!(benches);
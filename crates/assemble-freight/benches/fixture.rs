@@ -0,0 +1,110 @@
+//! Generates canned multi-project builds for benchmarks.
+//!
+//! A fixture is a root project with a number of subprojects, each with a number of empty tasks.
+//! Every task may depend on any task that was registered before it, in any project, so the task
+//! graph is always acyclic. The root project has an `assemble` task that depends on every other
+//! task. Fixtures are generated from a seed, so the same spec always produces the same build.
+
+use assemble_core::defaults::tasks::Empty;
+use assemble_core::identifier::TaskId;
+use assemble_core::project::error::ProjectResult;
+use assemble_core::project::shared::SharedProject;
+use assemble_core::task::HasTaskId;
+use assemble_core::Project;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// The name of the task in the root project that depends on every other task
+pub const AGGREGATE_TASK: &str = "assemble";
+
+/// Describes the shape of a generated build
+#[derive(Debug, Clone, Copy)]
+pub struct FixtureSpec {
+    /// The number of subprojects
+    pub projects: usize,
+    /// The number of tasks in each subproject
+    pub tasks_per_project: usize,
+    /// The chance, from `0.0` to `1.0`, that a task depends on any given earlier task
+    pub dependency_density: f64,
+    /// The seed used to pick dependencies
+    pub seed: u64,
+}
+
+impl FixtureSpec {
+    /// Creates a spec with the given size and a sparse dependency density
+    pub fn new(projects: usize, tasks_per_project: usize) -> Self {
+        Self {
+            projects,
+            tasks_per_project,
+            dependency_density: 0.01,
+            seed: 0x5eed,
+        }
+    }
+
+    /// Sets the dependency density of the spec
+    pub fn with_density(mut self, dependency_density: f64) -> Self {
+        self.dependency_density = dependency_density;
+        self
+    }
+
+    /// The total number of tasks in the generated build, including the aggregate task
+    pub fn total_tasks(&self) -> usize {
+        self.projects * self.tasks_per_project + 1
+    }
+
+    /// A short label for the spec, used as a benchmark parameter
+    pub fn label(&self) -> String {
+        format!(
+            "{}x{}@{}",
+            self.projects, self.tasks_per_project, self.dependency_density
+        )
+    }
+}
+
+/// Generates a build matching the spec
+pub fn generate(spec: &FixtureSpec) -> ProjectResult<SharedProject> {
+    let root = Project::temp("root");
+    let mut rng = StdRng::seed_from_u64(spec.seed);
+    let mut registered: Vec<TaskId> = vec![];
+
+    root.with_mut(|root| -> ProjectResult {
+        for project in 0..spec.projects {
+            root.subproject(&format!("project{}", project), |project| {
+                for task in 0..spec.tasks_per_project {
+                    let dependencies = registered
+                        .iter()
+                        .filter(|_| rng.gen_bool(spec.dependency_density))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    let handle = project
+                        .task_container_mut()
+                        .register_task_with::<Empty, _>(
+                            &format!("task{}", task),
+                            move |task, _| {
+                                for dependency in dependencies {
+                                    task.depends_on(dependency);
+                                }
+                                Ok(())
+                            },
+                        )?;
+                    registered.push(handle.task_id());
+                }
+                Ok(())
+            })?;
+        }
+
+        let all = registered.clone();
+        root.task_container_mut().register_task_with::<Empty, _>(
+            AGGREGATE_TASK,
+            move |task, _| {
+                for dependency in all {
+                    task.depends_on(dependency);
+                }
+                Ok(())
+            },
+        )?;
+        Ok(())
+    })?;
+
+    Ok(root)
+}
@@ -1,20 +1,31 @@
 use std::ffi::OsString;
+use std::num::NonZeroUsize;
 
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use clap::error::ErrorKind;
 use clap::{Args, Command, CommandFactory, Error, FromArgMatches, Parser};
 
-use indicatif::ProgressStyle;
+use indicatif::{ProgressState, ProgressStyle};
 use itertools::Itertools;
 use merge::Merge;
 
+use assemble_core::deprecations::WarningMode;
+use assemble_core::humanize;
+use assemble_core::logging::terminal::is_narrow_console;
 use assemble_core::logging::LoggingArgs;
 use assemble_core::prelude::BacktraceEmit;
 use assemble_core::project::error::ProjectResult;
 use assemble_core::project::requests::TaskRequests;
 use assemble_core::project::shared::SharedProject;
+use assemble_core::startup::control::BuildId;
+use assemble_core::startup::trust::TrustPolicy;
+use assemble_core::startup::updates::ReleaseChannel;
+use assemble_core::startup::watchdog::{parse_duration, BuildTimeouts};
+use assemble_core::task::undeclared_io::UndeclaredIoPolicy;
 
+use crate::report::{DEFAULT_PROFILE_TASKS, DEFAULT_REPORT_DIR};
 use crate::ProjectProperties;
 
 /// Command line options for running assemble based projects.
@@ -43,7 +54,8 @@ pub struct FreightArgs {
 
     /// The number of workers to use.
     ///
-    /// Defaults to the number of cpus on the host.
+    /// Defaults to the number of cpus available to assemble, which may be less than the number of
+    /// cpus on the host.
     #[clap(long, short = 'J')]
     #[clap(help_heading = None)]
     #[clap(value_parser = clap::value_parser!(u32).range(1..))]
@@ -74,6 +86,163 @@ pub struct FreightArgs {
     #[merge(strategy = merge::bool::overwrite_false)]
     rerun_tasks: bool,
 
+    /// Writes the resolved versions of dependencies into lock files instead of checking them.
+    #[clap(long)]
+    #[clap(alias = "update-locks")]
+    #[clap(help_heading = None)]
+    #[merge(strategy = merge::bool::overwrite_false)]
+    write_locks: bool,
+
+    /// Fails the build when a task writes into the outputs of another task, instead of warning.
+    #[clap(long)]
+    #[clap(help_heading = None)]
+    #[merge(strategy = merge::bool::overwrite_false)]
+    strict_outputs: bool,
+
+    /// Warns when tasks without an ordering between them declare overlapping outputs, instead of
+    /// failing the build.
+    #[clap(long)]
+    #[clap(help_heading = None)]
+    #[merge(strategy = merge::bool::overwrite_false)]
+    warn_overlapping_outputs: bool,
+
+    /// How files accessed by a task without being declared as inputs or outputs are handled.
+    #[clap(long, value_enum, value_name = "POLICY")]
+    #[clap(help_heading = None)]
+    undeclared_io: Option<UndeclaredIoPolicy>,
+
+    /// How deprecated features used by the build are reported. Defaults to `summary`.
+    #[clap(long, value_enum, value_name = "MODE")]
+    #[clap(help_heading = None)]
+    warning_mode: Option<WarningMode>,
+
+    /// Cancels the build if it takes longer than the given duration, such as `30m` or `1h30m`.
+    #[clap(long, value_name = "DURATION")]
+    #[clap(value_parser = parse_duration)]
+    #[clap(help_heading = "Timeouts")]
+    build_timeout: Option<Duration>,
+
+    /// Cancels the build if configuring projects takes longer than the given duration.
+    #[clap(long, value_name = "DURATION")]
+    #[clap(value_parser = parse_duration)]
+    #[clap(help_heading = "Timeouts")]
+    configuration_timeout: Option<Duration>,
+
+    /// Cancels the build if executing tasks takes longer than the given duration.
+    #[clap(long, value_name = "DURATION")]
+    #[clap(value_parser = parse_duration)]
+    #[clap(help_heading = "Timeouts")]
+    execution_timeout: Option<Duration>,
+
+    /// Fails if a thread waits longer than the given duration for access to a project. Threads
+    /// waiting for a while report which threads are accessing the project, even without a timeout.
+    #[clap(long, value_name = "DURATION")]
+    #[clap(value_parser = parse_duration)]
+    #[clap(help_heading = "Timeouts")]
+    project_lock_timeout: Option<Duration>,
+
+    /// Generates a build report in `build/reports/assemble`.
+    #[clap(long)]
+    #[clap(help_heading = "Reports")]
+    #[merge(strategy = merge::bool::overwrite_false)]
+    scan: bool,
+
+    /// Generates a build report in the given directory.
+    #[clap(long, value_name = "DIR")]
+    #[clap(help_heading = "Reports")]
+    report: Option<PathBuf>,
+
+    /// Profiles the build, writing a report of the time spent in each phase of the build and in
+    /// the slowest tasks into `build/reports/profile`.
+    #[clap(long)]
+    #[clap(help_heading = "Reports")]
+    #[merge(strategy = merge::bool::overwrite_false)]
+    profile: bool,
+
+    /// The number of slowest tasks printed by `--profile`.
+    #[clap(long, value_name = "N")]
+    #[clap(requires = "profile")]
+    #[clap(help_heading = "Reports")]
+    profile_tasks: Option<usize>,
+
+    /// Writes a JUnit xml report of task results to the given file.
+    #[clap(long, value_name = "PATH")]
+    #[clap(help_heading = "Reports")]
+    results_xml: Option<PathBuf>,
+
+    /// Saves the resolved execution plan to the given file, so it can be replayed with
+    /// `--from-plan`.
+    #[clap(long, value_name = "PATH")]
+    #[clap(help_heading = "Execution Plans")]
+    export_plan: Option<PathBuf>,
+
+    /// Runs the execution plan saved in the given file instead of resolving task requests.
+    #[clap(long, value_name = "PATH")]
+    #[clap(help_heading = "Execution Plans")]
+    from_plan: Option<PathBuf>,
+
+    /// The id of this build, used to control it from other processes. Defaults to a random id.
+    #[clap(long, value_name = "ID")]
+    #[clap(help_heading = "Build Control")]
+    build_id: Option<BuildId>,
+
+    /// Cancels the running build with the given id, then exits without running a build.
+    #[clap(long, value_name = "BUILD_ID")]
+    #[clap(help_heading = "Build Control")]
+    #[clap(conflicts_with = "build_id")]
+    cancel: Option<BuildId>,
+
+    /// How build logic that hasn't been trusted, or changed since it was trusted, is handled.
+    #[clap(long, value_enum, value_name = "POLICY")]
+    #[clap(help_heading = "Build Logic Trust")]
+    trust_policy: Option<TrustPolicy>,
+
+    /// Trusts the current contents of build logic files, then exits without running a build.
+    #[clap(long, value_name = "PATH")]
+    #[clap(help_heading = "Build Logic Trust")]
+    #[merge(strategy = merge::vec::append)]
+    trust: Vec<PathBuf>,
+
+    /// Evaluates the given init script before settings, after the init scripts in
+    /// `ASSEMBLE_HOME/init.d`. Can be given more than once.
+    #[clap(long, value_name = "FILE")]
+    #[clap(help_heading = None)]
+    #[merge(strategy = merge::vec::append)]
+    init_script: Vec<PathBuf>,
+
+    /// Runs the build without network access. Updates aren't checked.
+    #[clap(long)]
+    #[clap(help_heading = None)]
+    #[merge(strategy = merge::bool::overwrite_false)]
+    offline: bool,
+
+    /// Shows a notice at the end of the build when a newer version of assemble is available.
+    /// Releases are checked at most once per day.
+    #[clap(long)]
+    #[clap(help_heading = "Updates")]
+    #[merge(strategy = merge::bool::overwrite_false)]
+    check_updates: bool,
+
+    /// The release channel checked for newer versions. Defaults to `stable`.
+    #[clap(long, value_enum, value_name = "CHANNEL")]
+    #[clap(help_heading = "Updates")]
+    update_channel: Option<ReleaseChannel>,
+
+    /// Installs the newest version of assemble on the release channel, then exits without running
+    /// a build.
+    #[clap(long)]
+    #[clap(help_heading = "Updates")]
+    #[clap(conflicts_with = "offline")]
+    #[merge(strategy = merge::bool::overwrite_false)]
+    self_update: bool,
+
+    /// Deletes old and unused entries from the caches in the assemble home, then exits without
+    /// running a build.
+    #[clap(long)]
+    #[clap(help_heading = "Caches")]
+    #[merge(strategy = merge::bool::overwrite_false)]
+    clean_caches: bool,
+
     #[clap(flatten)]
     bare_task_requests: TaskRequestsArgs,
 }
@@ -225,7 +394,7 @@ impl FreightArgs {
         } else {
             self.workers
                 .map(|w| w as usize)
-                .unwrap_or_else(num_cpus::get)
+                .unwrap_or_else(available_parallelism)
         }
     }
 
@@ -242,29 +411,170 @@ impl FreightArgs {
     pub fn rerun_tasks(&self) -> bool {
         self.rerun_tasks
     }
+
+    /// Get whether to write dependency lock files.
+    pub fn write_locks(&self) -> bool {
+        self.write_locks
+    }
+
+    /// Get whether writing into the outputs of other tasks is an error.
+    pub fn strict_outputs(&self) -> bool {
+        self.strict_outputs
+    }
+
+    /// Get whether overlapping outputs are only warned of.
+    pub fn warn_overlapping_outputs(&self) -> bool {
+        self.warn_overlapping_outputs
+    }
+
+    /// Get how files accessed by a task without being declared are handled.
+    pub fn undeclared_io(&self) -> Option<UndeclaredIoPolicy> {
+        self.undeclared_io
+    }
+
+    /// Get how deprecated features used by the build are reported.
+    pub fn warning_mode(&self) -> Option<WarningMode> {
+        self.warning_mode
+    }
+
+    /// Get the timeouts enforced on the build.
+    pub fn timeouts(&self) -> BuildTimeouts {
+        BuildTimeouts {
+            build: self.build_timeout,
+            configuration: self.configuration_timeout,
+            execution: self.execution_timeout,
+            project_lock: self.project_lock_timeout,
+        }
+    }
+
+    /// Get the directory a build report should be generated in, if any.
+    pub fn report_dir(&self) -> Option<PathBuf> {
+        self.report
+            .clone()
+            .or_else(|| self.scan.then(|| PathBuf::from(DEFAULT_REPORT_DIR)))
+    }
+
+    /// Get the number of slowest tasks to print at the end of the build, if any.
+    pub fn profile(&self) -> Option<usize> {
+        self.profile
+            .then(|| self.profile_tasks.unwrap_or(DEFAULT_PROFILE_TASKS))
+    }
+
+    /// Get the file a JUnit xml report of task results should be written to, if any.
+    pub fn results_xml(&self) -> Option<&Path> {
+        self.results_xml.as_deref()
+    }
+
+    /// Get the file the resolved execution plan should be saved to, if any.
+    pub fn export_plan(&self) -> Option<&Path> {
+        self.export_plan.as_deref()
+    }
+
+    /// Get the file of a saved execution plan to replay, if any.
+    pub fn from_plan(&self) -> Option<&Path> {
+        self.from_plan.as_deref()
+    }
+
+    /// Get the id given to this build, if any.
+    pub fn build_id(&self) -> Option<&BuildId> {
+        self.build_id.as_ref()
+    }
+
+    /// Get the id of a running build that should be cancelled instead of running a build, if any.
+    pub fn cancel(&self) -> Option<&BuildId> {
+        self.cancel.as_ref()
+    }
+
+    /// Get how untrusted build logic is handled, if set.
+    pub fn trust_policy(&self) -> Option<TrustPolicy> {
+        self.trust_policy
+    }
+
+    /// Get build logic files that should be trusted instead of running a build.
+    pub fn trust(&self) -> &[PathBuf] {
+        &self.trust
+    }
+
+    /// Get the init scripts given on the command line.
+    pub fn init_scripts(&self) -> &[PathBuf] {
+        &self.init_script
+    }
+
+    /// Get whether the build runs without network access.
+    pub fn offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Get the release channel to check for newer versions at the end of the build, if updates
+    /// should be checked.
+    pub fn update_check(&self) -> Option<ReleaseChannel> {
+        self.check_updates.then(|| self.update_channel())
+    }
+
+    /// Get the release channel used for update checks and self updates.
+    pub fn update_channel(&self) -> ReleaseChannel {
+        self.update_channel.unwrap_or_default()
+    }
+
+    /// Get whether assemble should update itself instead of running a build.
+    pub fn self_update(&self) -> bool {
+        self.self_update
+    }
+
+    /// Get whether assemble should clean its caches instead of running a build.
+    pub fn clean_caches(&self) -> bool {
+        self.clean_caches
+    }
+
     pub fn properties(&self) -> &ProjectProperties {
         &self.properties
     }
 }
 
+/// The number of cpus available to this process, which respects cpu affinity and container cpu
+/// quotas. Falls back to the number of cpus on the host if it can't be determined.
+pub fn available_parallelism() -> usize {
+    std::thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or_else(|_| num_cpus::get())
+}
+
+/// The style of the main progress bar. A compact style is used when the console is narrow.
 pub fn main_progress_bar_style(failing: bool) -> ProgressStyle {
-    let template = if failing {
-        "{msg:>12.cyan.bold} [{bar:25.red.bright} {percent:>3}% ({pos}/{len})]  elapsed: {elapsed}"
-    } else {
-        "{msg:>12.cyan.bold} [{bar:25.green.bright} {percent:>3}% ({pos}/{len})]  elapsed: {elapsed}"
+    let template = match (failing, is_narrow_console()) {
+        (true, false) => {
+            "{msg:>12.cyan.bold} [{bar:25.red.bright} {percent:>3}% ({pos}/{len})]  elapsed: {elapsed_time}"
+        }
+        (false, false) => {
+            "{msg:>12.cyan.bold} [{bar:25.green.bright} {percent:>3}% ({pos}/{len})]  elapsed: {elapsed_time}"
+        }
+        (true, true) => "[{wide_bar:.red.bright}] {pos}/{len}",
+        (false, true) => "[{wide_bar:.green.bright}] {pos}/{len}",
     };
     ProgressStyle::with_template(template)
         .unwrap()
+        .with_key(
+            "elapsed_time",
+            |state: &ProgressState, w: &mut dyn std::fmt::Write| {
+                let _ = write!(w, "{}", humanize::duration(state.elapsed()));
+            },
+        )
         .progress_chars("=> ")
 }
 
 #[cfg(test)]
 mod test {
     use assemble_core::logging::ConsoleMode;
+    use assemble_core::startup::control::BuildId;
+    use assemble_core::startup::trust::TrustPolicy;
+    use assemble_core::startup::updates::ReleaseChannel;
+    use assemble_core::startup::watchdog::BuildTimeouts;
     use clap::{Command, CommandFactory};
     use log::LevelFilter;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
 
-    use crate::cli::FreightArgs;
+    use crate::cli::{available_parallelism, FreightArgs};
 
     #[test]
     fn can_render_help() {
@@ -289,9 +599,9 @@ mod test {
     }
 
     #[test]
-    fn default_workers_is_num_cpus() {
+    fn default_workers_is_available_parallelism() {
         let args: FreightArgs = FreightArgs::command_line("");
-        assert_eq!(args.workers(), num_cpus::get());
+        assert_eq!(args.workers(), available_parallelism());
     }
 
     #[test]
@@ -315,6 +625,26 @@ mod test {
         assert_eq!(args.property("key2"), Some(""));
     }
 
+    #[test]
+    fn project_prop_files_must_exist() {
+        let args = FreightArgs::command_line("--project-prop-file Cargo.toml");
+        assert_eq!(
+            args.properties().prop_files(),
+            [PathBuf::from("Cargo.toml")]
+        );
+        assert!(FreightArgs::try_command_line("--project-prop-file missing.properties").is_err());
+    }
+
+    #[test]
+    fn init_scripts_can_be_repeated() {
+        let args = FreightArgs::command_line("--init-script a.js --init-script b.yaml build");
+        assert_eq!(
+            args.init_scripts(),
+            [PathBuf::from("a.js"), PathBuf::from("b.yaml")]
+        );
+        assert_eq!(args.task_requests_raw(), ["build"]);
+    }
+
     #[test]
     fn arbitrary_task_positions() {
         let args = FreightArgs::try_command_line(":tasks --all --debug --workers 6 help");
@@ -379,8 +709,187 @@ mod test {
         assert_eq!(args.logging().console, ConsoleMode::Plain);
     }
 
+    #[test]
+    fn grouped_console() {
+        let args = FreightArgs::command_line("--console grouped");
+        assert_eq!(args.logging().console, ConsoleMode::Grouped);
+        assert_eq!(args.logging().console.resolve(), ConsoleMode::Grouped);
+    }
+
+    #[test]
+    fn write_locks() {
+        assert!(!FreightArgs::command_line("").write_locks());
+        assert!(FreightArgs::command_line("--write-locks").write_locks());
+        assert!(FreightArgs::command_line("--update-locks").write_locks());
+    }
+
+    #[test]
+    fn console_width_override() {
+        let args = FreightArgs::command_line("--console-width 40");
+        assert_eq!(args.logging().console_width, Some(40));
+        assert!(FreightArgs::try_command_line("--console-width 0").is_err());
+    }
+
+    #[test]
+    fn log_file_has_independent_level() {
+        let args = FreightArgs::command_line("--warn --log-file build.log");
+        assert_eq!(args.logging().log_level_filter(), LevelFilter::Warn);
+        assert_eq!(args.logging().log_file, Some(PathBuf::from("build.log")));
+        assert_eq!(args.logging().log_file_level(), Some(LevelFilter::Debug));
+
+        let args = FreightArgs::command_line("--log-file build.log --log-file-level trace");
+        assert_eq!(args.logging().log_file_level(), Some(LevelFilter::Trace));
+        assert_eq!(
+            FreightArgs::command_line("").logging().log_file_level(),
+            None
+        );
+        assert!(FreightArgs::try_command_line("--log-file-level trace").is_err());
+    }
+
+    #[test]
+    fn strict_outputs() {
+        assert!(!FreightArgs::command_line("").strict_outputs());
+        assert!(FreightArgs::command_line("--strict-outputs").strict_outputs());
+        assert!(!FreightArgs::command_line("").warn_overlapping_outputs());
+        assert!(FreightArgs::command_line("--warn-overlapping-outputs").warn_overlapping_outputs());
+    }
+
+    #[test]
+    fn undeclared_io() {
+        assert_eq!(FreightArgs::command_line("").undeclared_io(), None);
+        assert_eq!(
+            FreightArgs::command_line("--undeclared-io fail").undeclared_io(),
+            Some(UndeclaredIoPolicy::Fail)
+        );
+    }
+
+    #[test]
+    fn warning_mode() {
+        assert_eq!(FreightArgs::command_line("").warning_mode(), None);
+        assert_eq!(
+            FreightArgs::command_line("--warning-mode all").warning_mode(),
+            Some(WarningMode::All)
+        );
+        assert!(FreightArgs::try_command_line("--warning-mode none").is_err());
+    }
+
+    #[test]
+    fn timeouts() {
+        assert!(FreightArgs::command_line("").timeouts().is_empty());
+        let args = FreightArgs::command_line("--build-timeout 1h --execution-timeout 90s");
+        assert_eq!(
+            args.timeouts(),
+            BuildTimeouts {
+                build: Some(Duration::from_secs(3600)),
+                configuration: None,
+                execution: Some(Duration::from_secs(90)),
+                project_lock: None,
+            }
+        );
+        assert!(FreightArgs::try_command_line("--build-timeout soon").is_err());
+    }
+
     #[test]
     fn disallow_multiple_logging() {
         assert!(FreightArgs::try_command_line("--trace --debug").is_err());
     }
+
+    #[test]
+    fn report_dir() {
+        assert_eq!(FreightArgs::command_line("").report_dir(), None);
+        assert_eq!(
+            FreightArgs::command_line("--scan").report_dir(),
+            Some(PathBuf::from("build/reports/assemble"))
+        );
+        assert_eq!(
+            FreightArgs::command_line("--scan --report out").report_dir(),
+            Some(PathBuf::from("out"))
+        );
+    }
+
+    #[test]
+    fn profile() {
+        assert_eq!(FreightArgs::command_line("").profile(), None);
+        assert_eq!(FreightArgs::command_line("--profile").profile(), Some(10));
+        assert_eq!(
+            FreightArgs::command_line("--profile --profile-tasks 3").profile(),
+            Some(3)
+        );
+        assert!(FreightArgs::try_command_line("--profile-tasks 3").is_err());
+    }
+
+    #[test]
+    fn results_xml() {
+        assert_eq!(FreightArgs::command_line("").results_xml(), None);
+        assert_eq!(
+            FreightArgs::command_line("--results-xml build/results.xml").results_xml(),
+            Some(Path::new("build/results.xml"))
+        );
+    }
+
+    #[test]
+    fn build_control() {
+        let args = FreightArgs::command_line("--build-id ide-1 build");
+        assert_eq!(args.build_id(), Some(&BuildId::new("ide-1").unwrap()));
+        assert_eq!(args.cancel(), None);
+        assert_eq!(
+            FreightArgs::command_line("--cancel ide-1").cancel(),
+            Some(&BuildId::new("ide-1").unwrap())
+        );
+        assert!(FreightArgs::try_command_line("--cancel ../ide").is_err());
+    }
+
+    #[test]
+    fn build_logic_trust() {
+        let args = FreightArgs::command_line("build");
+        assert_eq!(args.trust_policy(), None);
+        assert!(args.trust().is_empty());
+        assert_eq!(
+            FreightArgs::command_line("--trust-policy enforce build").trust_policy(),
+            Some(TrustPolicy::Enforce)
+        );
+        assert_eq!(
+            FreightArgs::command_line("--trust build.assemble.js --trust settings.assemble.js")
+                .trust(),
+            [
+                PathBuf::from("build.assemble.js"),
+                PathBuf::from("settings.assemble.js")
+            ]
+        );
+    }
+
+    #[test]
+    fn execution_plans() {
+        let args = FreightArgs::command_line("");
+        assert_eq!(args.export_plan(), None);
+        assert_eq!(args.from_plan(), None);
+        assert_eq!(
+            FreightArgs::command_line("--export-plan plan.json build").export_plan(),
+            Some(Path::new("plan.json"))
+        );
+        assert_eq!(
+            FreightArgs::command_line("--from-plan plan.json").from_plan(),
+            Some(Path::new("plan.json"))
+        );
+    }
+
+    #[test]
+    fn update_checks() {
+        assert_eq!(FreightArgs::command_line("build").update_check(), None);
+        assert_eq!(
+            FreightArgs::command_line("--check-updates build").update_check(),
+            Some(ReleaseChannel::Stable)
+        );
+        assert_eq!(
+            FreightArgs::command_line("--check-updates --update-channel nightly").update_check(),
+            Some(ReleaseChannel::Nightly)
+        );
+        assert!(FreightArgs::try_command_line("--self-update --offline").is_err());
+    }
+
+    #[test]
+    fn clean_caches() {
+        assert!(!FreightArgs::command_line("build").clean_caches());
+        assert!(FreightArgs::command_line("--clean-caches").clean_caches());
+    }
 }
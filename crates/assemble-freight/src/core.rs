@@ -1,9 +1,12 @@
 //! Core parts of freight
 
+use assemble_core::error::category::{Categorize, ErrorCategory, ErrorCode};
+use assemble_core::error::hints::Hint;
 use assemble_core::error::PayloadError;
 use assemble_core::identifier::TaskId;
 
 use assemble_core::project::error::ProjectError;
+use std::path::PathBuf;
 
 mod task_resolver;
 pub use task_resolver::*;
@@ -12,12 +15,54 @@ mod execution_plan;
 
 pub use execution_plan::*;
 
+mod saved_plan;
+pub use saved_plan::*;
+
 #[derive(Debug, thiserror::Error)]
 pub enum ConstructionError {
     #[error("No task named {0} found in project")]
     IdentifierNotFound(TaskId),
     #[error("Cycle found in between tasks {}", cycle.iter().map(ToString::to_string).collect::<Vec<_>>().join(","))]
     CycleFound { cycle: Vec<TaskId> },
+    #[error(
+        "{first} and {second} both declare {path:?} as an output, but neither runs after the other"
+    )]
+    OverlappingOutputs {
+        first: TaskId,
+        second: TaskId,
+        path: PathBuf,
+    },
     #[error(transparent)]
     ProjectError(#[from] ProjectError),
 }
+
+impl Categorize for ConstructionError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            ConstructionError::IdentifierNotFound(_) => {
+                ErrorCode::new(ErrorCategory::Configuration, 30)
+            }
+            ConstructionError::CycleFound { .. } => {
+                ErrorCode::new(ErrorCategory::Configuration, 31)
+            }
+            ConstructionError::OverlappingOutputs { .. } => {
+                ErrorCode::new(ErrorCategory::Configuration, 32)
+            }
+            ConstructionError::ProjectError(e) => e.code(),
+        }
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        match self {
+            ConstructionError::CycleFound { .. } => vec![Hint::new(
+                "remove a dependency between two of the tasks in the cycle",
+            )],
+            ConstructionError::OverlappingOutputs { .. } => vec![Hint::new(
+                "make one of the tasks depend on the other, give them separate outputs, or run \
+                 with --warn-overlapping-outputs",
+            )],
+            ConstructionError::ProjectError(e) => e.hints(),
+            ConstructionError::IdentifierNotFound(_) => vec![],
+        }
+    }
+}
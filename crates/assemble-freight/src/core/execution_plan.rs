@@ -40,10 +40,14 @@ pub enum Type {
 
 /// An execution plan is guaranteed to have no cycles, and each task is run in the correct order.
 /// The execution graph can only be created from an [`ExecutionGraph`](crate::core::ExecutionGraph)
+///
+/// Of the tasks ready to run, the task with the longest chain of tasks waiting on it runs first, so
+/// the tasks unblocking the most work finish sooner.
 #[derive(Debug)]
 pub struct ExecutionPlan {
     graph: DiGraph<TaskId, Type>,
     id_to_task: HashMap<TaskId, SharedAnyTask>,
+    critical_paths: HashMap<TaskId, usize>,
     task_queue: BinaryHeap<Reverse<WorkRequest>>,
     task_requests: Arc<TaskRequests>,
     waiting_on: HashSet<TaskId>,
@@ -62,11 +66,13 @@ impl ExecutionPlan {
         let mut plan = Self {
             graph: fixed,
             id_to_task,
+            critical_paths: Default::default(),
             task_queue: Default::default(),
             task_requests: requests,
             waiting_on: Default::default(),
         };
         plan.remove_redundant_edges();
+        plan.critical_paths = critical_path_lengths(&plan.graph);
         plan.discover_available_tasks();
         plan
     }
@@ -76,6 +82,20 @@ impl ExecutionPlan {
         self.graph.node_count()
     }
 
+    /// The orderings between the tasks remaining in the plan, as `(task, waits on, type)`
+    pub fn dependencies(&self) -> Vec<(TaskId, TaskId, Type)> {
+        self.graph
+            .edge_references()
+            .map(|edge| {
+                (
+                    self.graph[edge.source()].clone(),
+                    self.graph[edge.target()].clone(),
+                    *edge.weight(),
+                )
+            })
+            .collect()
+    }
+
     /// Removes redundant edges from a graph. Should only really do anything once. Redundant edges
     /// are defined as edges such given three points A, B, C. if A depends on C and B, and B depends on C,
     /// then the edge from A to C is redundant because it's already covered by the transitive property.
@@ -103,6 +123,12 @@ impl ExecutionPlan {
         );
     }
 
+    /// The number of tasks in the longest chain of tasks starting with a task, including the task
+    /// itself. Tasks with longer chains are run first.
+    pub fn critical_path_length(&self, id: &TaskId) -> Option<usize> {
+        self.critical_paths.get(id).copied()
+    }
+
     /// Check whether the execution plan actually has anything to do
     pub fn is_empty(&self) -> bool {
         self.task_requests.requested_tasks().is_empty()
@@ -115,11 +141,14 @@ impl ExecutionPlan {
 
     /// Get the next task that can be run.
     pub fn pop_task(&mut self) -> Option<(SharedAnyTask, Option<WeakOptionsDecoder>)> {
-        let out = self
-            .task_queue
-            .pop()
-            .map(|req| req.0.identifier)
-            .and_then(|id| self.id_to_task.remove(&id));
+        // tasks that were discovered more than once are only handed out once
+        let mut out = None;
+        while let Some(Reverse(request)) = self.task_queue.pop() {
+            if let Some(task) = self.id_to_task.remove(&request.identifier) {
+                out = Some(task);
+                break;
+            }
+        }
         if let Some(out) = out {
             let id = out.read().task_id();
             self.waiting_on.insert(id.clone());
@@ -179,8 +208,10 @@ impl ExecutionPlan {
                 None => Priority::OnPath,
                 Some(pos) => Priority::Requested(pos),
             };
+            let critical_path = self.critical_paths.get(&id).copied().unwrap_or(1);
             Reverse(WorkRequest {
                 identifier: id,
+                critical_path,
                 priority: prio,
             })
         });
@@ -221,17 +252,47 @@ pub enum Priority {
     OnPath,
 }
 
+/// Gets the number of tasks in the longest chain starting at each task of a plan. A chain follows
+/// the tasks waiting on a task, so tasks that nothing waits on have a length of 1.
+fn critical_path_lengths(graph: &DiGraph<TaskId, Type>) -> HashMap<TaskId, usize> {
+    let order = petgraph::algo::toposort(graph, None).expect("execution plan has a cycle");
+    let mut lengths = HashMap::<NodeIndex, usize>::new();
+    // tasks come before the tasks they wait on
+    for index in order {
+        let length = graph
+            .neighbors_directed(index, Direction::Incoming)
+            .map(|waiting| lengths[&waiting])
+            .max()
+            .unwrap_or(0)
+            + 1;
+        lengths.insert(index, length);
+    }
+    lengths
+        .into_iter()
+        .map(|(index, length)| (graph[index].clone(), length))
+        .collect()
+}
+
 #[derive(Debug)]
 struct WorkRequest {
     identifier: TaskId,
+    /// The length of the longest chain of tasks starting with this task
+    critical_path: usize,
     priority: Priority,
 }
 
+impl WorkRequest {
+    /// Requests that sort first are ran first
+    fn key(&self) -> (Reverse<usize>, &Priority) {
+        (Reverse(self.critical_path), &self.priority)
+    }
+}
+
 impl Eq for WorkRequest {}
 
 impl PartialEq<Self> for WorkRequest {
     fn eq(&self, other: &Self) -> bool {
-        self.priority == other.priority
+        self.key() == other.key()
     }
 }
 
@@ -243,9 +304,54 @@ impl PartialOrd<Self> for WorkRequest {
 
 impl Ord for WorkRequest {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.priority.cmp(&other.priority)
+        self.key().cmp(&other.key())
     }
 }
 
 #[cfg(test)]
-mod test {}
+mod test {
+    use super::*;
+
+    #[test]
+    fn longest_chains_are_critical() {
+        let mut graph = DiGraph::new();
+        let ids = ["compile", "test", "package", "publish", "docs"]
+            .map(|id| graph.add_node(TaskId::new(id).unwrap()));
+        let [compile, test, package, publish, _docs] = ids;
+        graph.add_edge(test, compile, Type::RunAfter);
+        graph.add_edge(package, compile, Type::RunAfter);
+        graph.add_edge(publish, package, Type::RunAfter);
+        graph.add_edge(publish, test, Type::RunAfter);
+
+        let lengths = critical_path_lengths(&graph);
+        let length = |id: &str| lengths[&TaskId::new(id).unwrap()];
+        assert_eq!(length("compile"), 3);
+        assert_eq!(length("package"), 2);
+        assert_eq!(length("test"), 2);
+        assert_eq!(length("publish"), 1);
+        assert_eq!(length("docs"), 1);
+    }
+
+    #[test]
+    fn longer_chains_run_first() {
+        let mut queue = BinaryHeap::new();
+        queue.push(Reverse(WorkRequest {
+            identifier: TaskId::new("requested").unwrap(),
+            critical_path: 1,
+            priority: Priority::Requested(0),
+        }));
+        queue.push(Reverse(WorkRequest {
+            identifier: TaskId::new("deep").unwrap(),
+            critical_path: 4,
+            priority: Priority::OnPath,
+        }));
+        queue.push(Reverse(WorkRequest {
+            identifier: TaskId::new("shallow").unwrap(),
+            critical_path: 2,
+            priority: Priority::OnPath,
+        }));
+        let order = std::iter::from_fn(|| queue.pop().map(|request| request.0.identifier))
+            .collect::<Vec<_>>();
+        assert_eq!(order, ["deep", "shallow", "requested"]);
+    }
+}
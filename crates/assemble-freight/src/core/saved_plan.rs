@@ -0,0 +1,202 @@
+//! Saving resolved execution graphs so they can be replayed later.
+//!
+//! A saved plan records the requested tasks, the options given to them, and every task and
+//! ordering of the resolved [`ExecutionGraph`](ExecutionGraph). When replayed with `--from-plan`,
+//! task requests are not parsed and tasks are not resolved. Instead, the tasks in the plan are
+//! looked up by id and ordered exactly as they were when the plan was saved.
+
+use crate::core::{ConstructionError, TaskResolver};
+use assemble_core::error::PayloadError;
+use assemble_core::identifier::TaskId;
+use assemble_core::project::requests::TaskRequests;
+use assemble_core::project::shared::SharedProject;
+use assemble_core::startup::execution_graph::ExecutionGraph;
+use assemble_core::task::flags::WeakOptionsDecoder;
+use assemble_core::task::TaskOrderingKind;
+use parking_lot::RwLock;
+use petgraph::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::sync::Arc;
+
+/// The version of the saved plan format. Plans saved with a different version can't be replayed.
+pub const SAVED_PLAN_VERSION: u32 = 1;
+
+/// A resolved execution graph that can be written to and read from a file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedPlan {
+    /// The version of the format the plan was saved with
+    pub version: u32,
+    /// The requested tasks, in order
+    pub requested: Vec<TaskId>,
+    /// The options given to requested tasks
+    pub options: Vec<SavedOptions>,
+    /// Every task in the execution graph
+    pub tasks: Vec<TaskId>,
+    /// Every ordering between tasks in the execution graph
+    pub orderings: Vec<SavedOrdering>,
+}
+
+/// Options given to one or more requested tasks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedOptions {
+    /// The tasks the options were given to
+    pub tasks: Vec<TaskId>,
+    /// The options
+    pub options: WeakOptionsDecoder,
+}
+
+/// An ordering between two tasks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedOrdering {
+    /// The task the ordering is declared on
+    pub task: TaskId,
+    /// The task the ordering refers to
+    pub other: TaskId,
+    /// The kind of ordering
+    pub kind: TaskOrderingKind,
+}
+
+impl SavedPlan {
+    /// Saves an execution graph
+    pub fn new(graph: &ExecutionGraph) -> Self {
+        let requests = graph.requested_tasks();
+        let graph = graph.graph().read();
+        let tasks = graph
+            .node_indices()
+            .map(|index| graph[index].read().task_id())
+            .collect();
+        let orderings = graph
+            .edge_references()
+            .map(|edge| SavedOrdering {
+                task: graph[edge.source()].read().task_id(),
+                other: graph[edge.target()].read().task_id(),
+                kind: *edge.weight(),
+            })
+            .collect();
+
+        Self {
+            version: SAVED_PLAN_VERSION,
+            requested: requests.requested_tasks().to_vec(),
+            options: requests
+                .decoders()
+                .into_iter()
+                .map(|(tasks, options)| SavedOptions { tasks, options })
+                .collect(),
+            tasks,
+            orderings,
+        }
+    }
+
+    /// Writes this plan to a file as json
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+    }
+
+    /// Reads a plan from a json file. Fails if the plan was saved with a different version.
+    pub fn read_from<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let plan: Self = serde_json::from_reader(reader)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if plan.version != SAVED_PLAN_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "saved plan has version {} but only version {} is supported",
+                    plan.version, SAVED_PLAN_VERSION
+                ),
+            ));
+        }
+        Ok(plan)
+    }
+
+    /// Recreates the execution graph of this plan, using the tasks registered in the project.
+    pub fn into_execution_graph(
+        self,
+        project: &SharedProject,
+    ) -> Result<ExecutionGraph, PayloadError<ConstructionError>> {
+        let resolver = TaskResolver::new(project);
+        let mut graph = DiGraph::with_capacity(self.tasks.len(), self.orderings.len());
+        let mut indices = HashMap::new();
+
+        for id in self.tasks {
+            let task = resolver.find_task(&id)?;
+            let index = graph.add_node(Arc::new(RwLock::new(task)));
+            indices.insert(id, index);
+        }
+
+        for ordering in self.orderings {
+            let from = *indices
+                .get(&ordering.task)
+                .ok_or_else(|| ConstructionError::IdentifierNotFound(ordering.task.clone()))?;
+            let to = *indices
+                .get(&ordering.other)
+                .ok_or_else(|| ConstructionError::IdentifierNotFound(ordering.other.clone()))?;
+            graph.add_edge(from, to, ordering.kind);
+        }
+
+        let requests = TaskRequests::restore(
+            self.requested,
+            self.options
+                .into_iter()
+                .map(|options| (options.tasks, options.options)),
+        );
+        Ok(ExecutionGraph::new(graph, requests))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assemble_core::defaults::tasks::Empty;
+    use assemble_core::Project;
+    use tempfile::TempDir;
+
+    #[test]
+    fn replayed_plan_matches_resolved_plan() {
+        let project = Project::temp("root");
+        project.register_task::<Empty>("compile").unwrap();
+        project
+            .register_task::<Empty>("build")
+            .unwrap()
+            .configure_with(|task, _| {
+                task.depends_on("compile");
+                Ok(())
+            })
+            .unwrap();
+
+        let requests = TaskRequests::build(&project, ["build"]).unwrap();
+        let graph = TaskResolver::new(&project)
+            .to_execution_graph(requests)
+            .unwrap();
+        let plan = SavedPlan::new(&graph);
+        assert_eq!(plan.tasks.len(), 2);
+        assert_eq!(
+            plan.orderings,
+            [SavedOrdering {
+                task: TaskId::new(":root:build").unwrap(),
+                other: TaskId::new(":root:compile").unwrap(),
+                kind: TaskOrderingKind::DependsOn,
+            }]
+        );
+
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("plan.json");
+        plan.write_to(&file).unwrap();
+        let read = SavedPlan::read_from(&file).unwrap();
+        assert_eq!(read, plan);
+
+        let replayed = read.into_execution_graph(&project).unwrap();
+        assert_eq!(SavedPlan::new(&replayed), plan);
+    }
+}
@@ -19,10 +19,11 @@ use assemble_core::project::finder::{
 };
 use assemble_core::project::shared::SharedProject;
 use assemble_core::startup::execution_graph::{ExecutionGraph, SharedAnyTask};
+use indexmap::IndexMap;
 use itertools::Itertools;
 use parking_lot::RwLock;
 use std::collections::{HashMap, HashSet, VecDeque};
-use std::fmt::Debug;
+use std::fmt::{Debug, Display, Formatter};
 use std::sync::Arc;
 
 /// Resolves tasks
@@ -52,18 +53,24 @@ impl TaskResolver {
                 let mut iter = project.iter();
                 let first = iter.next().unwrap();
                 if ptr.project_id() != first {
-                    return Err(
-                        ConstructionError::ProjectError(ProjectError::NoSharedProjectSet).into(),
-                    );
+                    // tasks of included builds are found from the root of the included build
+                    ptr = ptr.with(|p| p.included_build(first)).ok_or_else(|| {
+                        ConstructionError::ProjectError(ProjectError::NoSharedProjectSet)
+                    })?;
                 }
                 for id in iter {
                     ptr = ptr.get_subproject(id).map_err(PayloadError::into)?;
                 }
 
+                if ptr.get_task(task_id).is_err() {
+                    // the task may be created by a task rule of its project
+                    ptr.apply_task_rules(task_id.this())
+                        .map_err(PayloadError::into)?;
+                }
                 let config_info = ptr
                     .get_task(task_id)
                     .map_err(PayloadError::into)?
-                    .resolve_shared(&self.project)
+                    .resolve_shared(&ptr)
                     .map_err(PayloadError::into)?;
 
                 Ok(config_info)
@@ -93,27 +100,68 @@ impl TaskResolver {
         self,
         tasks: TaskRequests,
     ) -> Result<ExecutionGraph, PayloadError<ConstructionError>> {
+        let (graph, contributions) = self.to_execution_graph_with_contributions(tasks)?;
+        debug!("{}", contributions);
+        Ok(graph)
+    }
+
+    /// Creates an execution graph like [`to_execution_graph`](TaskResolver::to_execution_graph),
+    /// also reporting which tasks each request contributed to the graph.
+    ///
+    /// Tasks requested more than once are only resolved once, and tasks required by more than one
+    /// request are only added to the graph by the first request that requires them.
+    pub fn to_execution_graph_with_contributions(
+        self,
+        mut tasks: TaskRequests,
+    ) -> Result<(ExecutionGraph, RequestContributions), PayloadError<ConstructionError>> {
         let mut task_id_graph = TaskIdentifierGraph::new();
+        let mut contributions = RequestContributions {
+            duplicates: tasks.deduplicate(),
+            ..Default::default()
+        };
 
         let mut task_queue: VecDeque<TaskId> = VecDeque::new();
         let requested = tasks.requested_tasks().to_vec();
-        task_queue.extend(requested);
-        log!(
-            EXEC_GRAPH_LOG_LEVEL,
-            "task queue at start: {:?}",
-            task_queue
-        );
+        log!(EXEC_GRAPH_LOG_LEVEL, "requested tasks: {:?}", requested);
 
         let mut visited = HashSet::new();
 
+        for request in requested {
+            contributions.contributed.insert(request.clone(), vec![]);
+            task_queue.push_back(request.clone());
+            self.resolve_request(
+                &request,
+                &mut task_queue,
+                &mut visited,
+                &mut task_id_graph,
+                &mut contributions,
+            )?;
+        }
+        debug!("Attempting to create execution graph.");
+        let execution_graph = task_id_graph.map_with(self.project.clone())?;
+        Ok((ExecutionGraph::new(execution_graph, tasks), contributions))
+    }
+
+    /// Adds every task reachable from the task queue that hasn't been visited yet to the graph,
+    /// attributing them to the request.
+    fn resolve_request(
+        &self,
+        request: &TaskId,
+        task_queue: &mut VecDeque<TaskId>,
+        visited: &mut HashSet<TaskId>,
+        task_id_graph: &mut TaskIdentifierGraph,
+        contributions: &mut RequestContributions,
+    ) -> Result<(), PayloadError<ConstructionError>> {
         while let Some(task_id) = task_queue.pop_front() {
             if visited.contains(&task_id) {
                 log!(
                     EXEC_GRAPH_LOG_LEVEL,
                     "task {task_id} already visited, skipping..."
                 );
+                contributions.record_overlap(request, &task_id);
                 continue;
             }
+            contributions.record_contribution(request, &task_id);
 
             if !task_id_graph.contains_id(&task_id) {
                 log!(EXEC_GRAPH_LOG_LEVEL, "adding {} to task graph", task_id);
@@ -168,9 +216,91 @@ impl TaskResolver {
                 }
             }
         }
-        debug!("Attempting to create execution graph.");
-        let execution_graph = task_id_graph.map_with(self.project.clone())?;
-        Ok(ExecutionGraph::new(execution_graph, tasks))
+        Ok(())
+    }
+}
+
+/// Which tasks each task request contributed to an execution graph.
+#[derive(Debug, Default, Clone)]
+pub struct RequestContributions {
+    contributed: IndexMap<TaskId, Vec<TaskId>>,
+    owners: HashMap<TaskId, TaskId>,
+    overlaps: IndexMap<TaskId, Vec<TaskId>>,
+    duplicates: Vec<TaskId>,
+}
+
+impl RequestContributions {
+    fn record_contribution(&mut self, request: &TaskId, task: &TaskId) {
+        self.contributed
+            .entry(request.clone())
+            .or_default()
+            .push(task.clone());
+        self.owners.insert(task.clone(), request.clone());
+    }
+
+    fn record_overlap(&mut self, request: &TaskId, task: &TaskId) {
+        if let Some(owner) = self.owners.get(task) {
+            if owner != request {
+                let overlaps = self.overlaps.entry(request.clone()).or_default();
+                if !overlaps.contains(owner) {
+                    overlaps.push(owner.clone());
+                }
+            }
+        }
+    }
+
+    /// The deduplicated requests, in order
+    pub fn requests(&self) -> impl Iterator<Item = &TaskId> {
+        self.contributed.keys()
+    }
+
+    /// The tasks that were added to the execution graph because of a request
+    pub fn contributed_by(&self, request: &TaskId) -> &[TaskId] {
+        self.contributed
+            .get(request)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// The request that added a task to the execution graph
+    pub fn contributor_of(&self, task: &TaskId) -> Option<&TaskId> {
+        self.owners.get(task)
+    }
+
+    /// The earlier requests whose tasks were also required by a request
+    pub fn overlapping(&self, request: &TaskId) -> &[TaskId] {
+        self.overlaps
+            .get(request)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Tasks that were requested more than once. Only their first request is resolved.
+    pub fn duplicates(&self) -> &[TaskId] {
+        &self.duplicates
+    }
+}
+
+impl Display for RequestContributions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "task request contributions:")?;
+        for (request, tasks) in &self.contributed {
+            write!(f, "\n  {} -> {} task(s)", request, tasks.len())?;
+            if !tasks.is_empty() {
+                write!(f, ": {}", tasks.iter().join(", "))?;
+            }
+            if let Some(overlaps) = self.overlaps.get(request) {
+                write!(f, " (shares tasks with {})", overlaps.iter().join(", "))?;
+            }
+        }
+        if !self.duplicates.is_empty() {
+            write!(
+                f,
+                "\n  ignored duplicate requests: {}",
+                self.duplicates.iter().join(", ")
+            )?;
+        }
+        Ok(())
     }
 }
 
@@ -259,3 +389,41 @@ impl TaskIdentifierGraph {
         Ok(output)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assemble_core::defaults::tasks::Empty;
+
+    #[test]
+    fn overlapping_requests_are_resolved_once() {
+        let project = Project::temp("root");
+        project.register_task::<Empty>("compile").unwrap();
+        project
+            .register_task::<Empty>("build")
+            .unwrap()
+            .configure_with(|task, _| {
+                task.depends_on("compile");
+                Ok(())
+            })
+            .unwrap();
+
+        let requests = TaskRequests::build(&project, ["compile", "build", "compile"]).unwrap();
+        let (graph, contributions) = TaskResolver::new(&project)
+            .to_execution_graph_with_contributions(requests)
+            .unwrap();
+
+        let compile = TaskId::new(":root:compile").unwrap();
+        let build = TaskId::new(":root:build").unwrap();
+        assert_eq!(graph.graph().read().node_count(), 2);
+        assert_eq!(
+            graph.requested_tasks().requested_tasks(),
+            [compile.clone(), build.clone()]
+        );
+        assert_eq!(contributions.duplicates(), [compile.clone()]);
+        assert_eq!(contributions.contributed_by(&compile), [compile.clone()]);
+        assert_eq!(contributions.contributed_by(&build), [build.clone()]);
+        assert_eq!(contributions.overlapping(&build), [compile.clone()]);
+        assert_eq!(contributions.contributor_of(&compile), Some(&compile));
+    }
+}
@@ -17,7 +17,9 @@ extern crate log;
 pub mod cli;
 pub mod core;
 pub mod ops;
+pub mod profile;
 pub mod project_properties;
+pub mod report;
 pub mod utils;
 pub mod consts;
 pub mod startup;
@@ -3,36 +3,51 @@
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::identity;
 use std::num::NonZeroUsize;
-
-use std::time::{Duration, Instant};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use std::{io, panic};
 
 use assemble_core::error::PayloadError;
 use colored::Colorize;
-use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget};
 use itertools::Itertools;
 use log::Level;
-use petgraph::algo::tarjan_scc;
+use parking_lot::Mutex;
+use petgraph::algo::{has_path_connecting, tarjan_scc};
 use petgraph::graph::{DiGraph, NodeIndex};
 use petgraph::prelude::EdgeRef;
 use petgraph::Outgoing;
-use rayon::iter::{ParallelBridge, ParallelIterator};
 
+use assemble_core::dependencies::locking::write_dependency_locks;
 use assemble_core::identifier::TaskId;
 use assemble_core::logging::{ConsoleMode, LOGGING_CONTROL};
 use assemble_core::prelude::AssembleAware;
 use assemble_core::project::requests::TaskRequests;
+use assemble_core::task::output_ownership::{
+    strict_output_ownership, warn_overlapping_outputs, WARN_OVERLAPPING_OUTPUTS,
+};
 
+use assemble_core::humanize;
+use assemble_core::project::phase::ProjectPhase;
 use assemble_core::project::shared::SharedProject;
+use assemble_core::startup::cancellation::build_cancellation;
 use assemble_core::startup::execution_graph::{ExecutionGraph, SharedAnyTask};
+use assemble_core::startup::profile::BuildPhase;
+use assemble_core::startup::watchdog::register_state_dump;
 
 use assemble_core::task::task_executor::TaskExecutor;
+use assemble_core::task::undeclared_io::set_undeclared_io_policy;
 use assemble_core::task::{force_rerun, ExecutableTask, HasTaskId, TaskOrderingKind, TaskOutcome};
 use assemble_core::utilities::measure_time;
 use assemble_core::work_queue::WorkerExecutor;
 
 use crate::cli::{main_progress_bar_style, FreightArgs};
-use crate::core::{ConstructionError, ExecutionPlan, Type};
+use crate::core::{ConstructionError, ExecutionPlan, SavedPlan, Type};
+use crate::profile::{ProfileReport, PROFILE_REPORT_DIR};
+use crate::report::junit::write_junit_xml;
+use crate::report::{slowest_tasks_summary, BuildReport};
 use crate::utils::FreightError;
 use crate::{FreightResult, TaskResolver, TaskResult, TaskResultBuilder};
 
@@ -72,6 +87,11 @@ pub fn init_executor(num_workers: NonZeroUsize) -> io::Result<WorkerExecutor> {
 /// > This is still a task cycle, but it's not as obvious since it relies on the before/after operations
 /// > instead of direct edges.
 ///
+/// ## Overlapping Outputs
+/// If two tasks declare the same output, or an output within the output of the other, one of them
+/// must run after the other. Otherwise which task produced the output depends on the order they
+/// happen to run in. If [overlapping outputs](warn_overlapping_outputs) are only warned of, the
+/// plan is still created.
 #[cold]
 pub fn try_creating_plan(exec_g: ExecutionGraph) -> Result<ExecutionPlan, ConstructionError> {
     trace!("creating plan from {:#?}", exec_g);
@@ -178,12 +198,65 @@ pub fn try_creating_plan(exec_g: ExecutionGraph) -> Result<ExecutionPlan, Constr
         return Err(ConstructionError::CycleFound { cycle });
     }
 
+    for overlap in overlapping_outputs(&new_graph) {
+        if WARN_OVERLAPPING_OUTPUTS.load(Ordering::Relaxed) {
+            warn!("{}", overlap);
+        } else {
+            return Err(overlap);
+        }
+    }
+
     Ok(ExecutionPlan::new(
         new_graph,
         exec_g.requested_tasks().clone(),
     ))
 }
 
+/// Finds tasks declaring overlapping outputs without an ordering between them
+fn overlapping_outputs(graph: &DiGraph<SharedAnyTask, Type>) -> Vec<ConstructionError> {
+    let mut owners: HashMap<PathBuf, Vec<NodeIndex>> = HashMap::new();
+    for idx in graph.node_indices() {
+        for output in graph[idx].read().declared_outputs() {
+            owners.entry(output).or_default().push(idx);
+        }
+    }
+
+    let mut overlaps = HashMap::new();
+    for (path, tasks) in &owners {
+        for ancestor in path.ancestors() {
+            let others = match owners.get(ancestor) {
+                Some(others) => others,
+                None => continue,
+            };
+            for (&task, &other) in tasks.iter().cartesian_product(others) {
+                if task == other {
+                    continue;
+                }
+                let pair = (task.min(other), task.max(other));
+                overlaps
+                    .entry(pair)
+                    .or_insert_with(|| ancestor.to_path_buf());
+            }
+        }
+    }
+
+    overlaps
+        .into_iter()
+        .filter(|((first, second), _)| {
+            !has_path_connecting(graph, *first, *second, None)
+                && !has_path_connecting(graph, *second, *first, None)
+        })
+        .map(
+            |((first, second), path)| ConstructionError::OverlappingOutputs {
+                first: graph[first].read().task_id(),
+                second: graph[second].read().task_id(),
+                path,
+            },
+        )
+        .sorted_by_key(|overlap| overlap.to_string())
+        .collect()
+}
+
 fn find_node<W>(graph: &DiGraph<SharedAnyTask, W>, id: &TaskId) -> Option<NodeIndex> {
     graph
         .node_indices()
@@ -198,12 +271,38 @@ pub fn execute_tasks2<A: AssembleAware + ?Sized>(
 ) -> FreightResult<Vec<TaskResult>> {
     let start_instant = Instant::now();
     let start_parameter = assemble.start_parameter();
+    let profile = assemble.with_assemble(|assemble| assemble.profile().clone());
+    let resolution = profile.start(BuildPhase::TaskResolution);
 
     if start_parameter.is_rerun_tasks() {
         force_rerun(true);
     }
 
-    let exec_graph = {
+    if start_parameter.is_write_locks() {
+        write_dependency_locks(true);
+    }
+
+    if start_parameter.is_strict_outputs() {
+        strict_output_ownership(true);
+    }
+
+    if start_parameter.is_warn_overlapping_outputs() {
+        warn_overlapping_outputs(true);
+    }
+
+    set_undeclared_io_policy(start_parameter.undeclared_io());
+
+    let exec_graph = if let Some(from_plan) = start_parameter.from_plan() {
+        let from_plan = project.with(|p| p.root_dir()).join(from_plan);
+        if !start_parameter.task_requests().is_empty() {
+            warn!("task requests are ignored when replaying a saved execution plan");
+        }
+        info!("replaying execution plan from {}", from_plan.display());
+        SavedPlan::read_from(&from_plan)
+            .map_err(PayloadError::new)?
+            .into_execution_graph(project)
+            .map_err(PayloadError::into)?
+    } else {
         let resolver = TaskResolver::new(project);
         let task_requests = TaskRequests::build(current, start_parameter.task_requests())
             .map_err(PayloadError::into)?;
@@ -218,16 +317,33 @@ pub fn execute_tasks2<A: AssembleAware + ?Sized>(
         "created exec graph: {:#?}",
         exec_graph
     );
+    let phases = std::iter::once(project.clone())
+        .chain(project.with(|p| p.included_builds()))
+        .map(|project| project.with(|p| p.phase_state().clone()))
+        .collect::<Vec<_>>();
+    phases
+        .iter()
+        .for_each(|phase| phase.advance(ProjectPhase::GraphReady));
+    if let Some(export_plan) = start_parameter.export_plan() {
+        let export_plan = project.with(|p| p.root_dir()).join(export_plan);
+        match SavedPlan::new(&exec_graph).write_to(&export_plan) {
+            Ok(()) => info!("execution plan saved to {}", export_plan.display()),
+            Err(e) => warn!("could not save execution plan to {:?}: {}", export_plan, e),
+        }
+    }
     let mut exec_plan = try_creating_plan(exec_graph).map_err(PayloadError::new)?;
     exec_plan.print_plan(Level::Trace);
 
+    resolution.finish();
+
     if exec_plan.is_empty() {
         return Ok(vec![]);
     }
+    let dependencies = exec_plan.dependencies();
 
     debug!(
-        "{project} plan creation time: {:.3} sec",
-        start_instant.elapsed().as_secs_f32()
+        "{project} plan creation time: {}",
+        humanize::duration(start_instant.elapsed())
     );
 
     let max_workers = start_parameter.workers();
@@ -236,11 +352,16 @@ pub fn execute_tasks2<A: AssembleAware + ?Sized>(
 
     let mut results = vec![];
 
+    let execution = profile.start(BuildPhase::TaskExecution);
+    phases
+        .iter()
+        .for_each(|phase| phase.advance(ProjectPhase::Execution));
     let mut work_queue = TaskExecutor::new(project.clone(), &executor);
+    let start_times = work_queue.start_times();
+    let skip_reasons = work_queue.skip_reasons();
 
-    let progress = MultiProgress::with_draw_target(ProgressDrawTarget::stdout_with_hz(u8::MAX));
+    let progress = MultiProgress::with_draw_target(ProgressDrawTarget::stderr_with_hz(u8::MAX));
 
-    let mut worker_bars = vec![];
     let mut available_workers = VecDeque::from_iter(0..max_workers);
     let mut in_use_workers: HashMap<TaskId, usize> = HashMap::new();
 
@@ -253,13 +374,6 @@ pub fn execute_tasks2<A: AssembleAware + ?Sized>(
     let main_bar = progress.add(bar);
     main_bar.tick();
 
-    for _ in 0..max_workers {
-        let bar = progress.add(
-            ProgressBar::new_spinner().with_style(ProgressStyle::with_template("> {msg}").unwrap()),
-        );
-        worker_bars.push(bar.clone());
-    }
-
     progress.set_move_cursor(false);
 
     if let ConsoleMode::Rich = start_parameter.logging().console.resolve() {
@@ -268,38 +382,71 @@ pub fn execute_tasks2<A: AssembleAware + ?Sized>(
 
     let mut results_builders = HashMap::new();
 
-    let _task_execution_start_time = Instant::now();
+    let task_execution_start_time = Instant::now();
+    let task_execution_started_at = SystemTime::now();
+
+    let cancellation = build_cancellation();
+    let mut next_task = None;
+    let running_tasks: Arc<Mutex<HashSet<TaskId>>> = Default::default();
+    let _running_tasks_dump = {
+        let running_tasks = running_tasks.clone();
+        register_state_dump("running tasks", move || {
+            running_tasks
+                .lock()
+                .iter()
+                .map(|id| id.to_string())
+                .sorted()
+                .join("\n")
+        })
+    };
 
     while !(exec_plan.finished() || executor.any_panicked()) {
+        if cancellation.is_cancelled() && results_builders.is_empty() {
+            break;
+        }
+        let mut queued = false;
         if let Some(worker_index) = available_workers.pop_front() {
-            if let Some((task, decs)) = exec_plan.pop_task() {
-                trace!("loading task {} into task queue", task.read().task_id());
-                let task_id = task.read().task_id().clone();
-                let result_builder = TaskResultBuilder::new(task_id.clone());
-                results_builders.insert(task_id.clone(), result_builder);
-
-                let task_bar = { worker_bars[worker_index].clone() };
-
-                if let Some(weak_decoder) = decs {
-                    let task_options = task.read().options_declarations().unwrap();
-                    let upgraded_decoder = weak_decoder
-                        .upgrade(&task_options)
-                        .map_err(PayloadError::new)?;
-                    task.write()
-                        .try_set_from_decoder(&upgraded_decoder)
-                        .map_err(PayloadError::into)?;
-                }
+            if cancellation.is_cancelled() {
+                // don't start any new tasks once the build is cancelled
+                available_workers.push_front(worker_index);
+            } else if let Some((task, decs)) = next_task.take().or_else(|| exec_plan.pop_task()) {
+                if !work_queue.can_queue(&task) {
+                    // wait for running tasks to free enough workers, without letting other
+                    // tasks start in the meantime
+                    available_workers.push_front(worker_index);
+                    next_task = Some((task, decs));
+                } else {
+                    trace!("loading task {} into task queue", task.read().task_id());
+                    let task_id = task.read().task_id().clone();
+                    let result_builder = TaskResultBuilder::new(task_id.clone());
+                    results_builders.insert(task_id.clone(), result_builder);
+
+                    if let Some(weak_decoder) = decs {
+                        let task_options = task.read().options_declarations().unwrap();
+                        let upgraded_decoder = weak_decoder
+                            .upgrade(&task_options)
+                            .map_err(PayloadError::new)?;
+                        task.write()
+                            .try_set_from_decoder(&upgraded_decoder)
+                            .map_err(PayloadError::into)?;
+                    }
 
-                task_bar.set_message(format!("{}", task.read().task_id()));
-                task_bar.tick();
-                in_use_workers.insert(task_id, worker_index);
-                work_queue.queue_task(task).map_err(PayloadError::new)?;
+                    in_use_workers.insert(task_id.clone(), worker_index);
+                    running_tasks.lock().insert(task_id);
+                    work_queue.queue_task(task).map_err(PayloadError::new)?;
+                    queued = true;
+                }
             } else {
                 available_workers.push_front(worker_index);
             }
         }
-        // sleep(Duration::from_millis(100));
-        for (task_id, output) in work_queue.finished_tasks() {
+        let finished_tasks = if queued {
+            work_queue.finished_tasks()
+        } else {
+            // no more tasks can start until a running task finishes
+            work_queue.wait_for_finished_tasks()
+        };
+        for (task_id, output) in finished_tasks {
             trace!("received task {} from task queue", task_id);
             let outcome: Option<TaskOutcome> = if let &Ok((up_to_date, did_work)) = &output {
                 match (up_to_date, did_work) {
@@ -333,12 +480,8 @@ pub fn execute_tasks2<A: AssembleAware + ?Sized>(
                 None
             };
 
-            let task_bar_index = in_use_workers[&task_id];
-            let task_bar = &worker_bars[task_bar_index];
-            available_workers.push_front(task_bar_index);
-
-            task_bar.set_message("");
-            task_bar.tick();
+            running_tasks.lock().remove(&task_id);
+            available_workers.push_front(in_use_workers[&task_id]);
 
             if output.is_err() {
                 error!("Task {} FAILED", task_id);
@@ -349,9 +492,16 @@ pub fn execute_tasks2<A: AssembleAware + ?Sized>(
             main_bar.inc(1);
 
             exec_plan.report_task_status(&task_id, output.is_ok());
-            let result_builder = results_builders.remove(&task_id).unwrap();
+            let mut result_builder = results_builders.remove(&task_id).unwrap();
+            if let Some(started) = start_times.read().get(&task_id) {
+                result_builder.started_at(*started);
+            }
+            if let Some(reason) = skip_reasons.read().get(&task_id) {
+                result_builder.skipped_because(reason);
+            }
             let work_result =
                 result_builder.finish(output.map(|_| outcome.expect("should be set")));
+            LOGGING_CONTROL.emit_event(work_result.json_event());
             results.push(work_result);
         }
     }
@@ -397,12 +547,8 @@ pub fn execute_tasks2<A: AssembleAware + ?Sized>(
             None
         };
 
-        let task_bar_index = in_use_workers[&task_id];
-        let task_bar = &worker_bars[task_bar_index];
-        available_workers.push_front(task_bar_index);
-
-        task_bar.set_message("");
-        task_bar.tick();
+        running_tasks.lock().remove(&task_id);
+        available_workers.push_front(in_use_workers[&task_id]);
 
         if output.is_err() {
             error!("Task {} FAILED", task_id);
@@ -412,25 +558,25 @@ pub fn execute_tasks2<A: AssembleAware + ?Sized>(
         main_bar.inc(1);
 
         exec_plan.report_task_status(&task_id, output.is_ok());
-        let result_builder = results_builders.remove(&task_id).unwrap();
+        let mut result_builder = results_builders.remove(&task_id).unwrap();
+        if let Some(started) = start_times.read().get(&task_id) {
+            result_builder.started_at(*started);
+        }
+        if let Some(reason) = skip_reasons.read().get(&task_id) {
+            result_builder.skipped_because(reason);
+        }
         let work_result = result_builder.finish(output.map(|_| outcome.unwrap()));
+        LOGGING_CONTROL.emit_event(work_result.json_event());
         results.push(work_result);
     }
 
     let panicked = matches!(&error, Some(_));
 
     trace!(
-        "freight task completion time: {:.3} sec",
-        start_instant.elapsed().as_secs_f32()
+        "freight task completion time: {}",
+        humanize::duration(start_instant.elapsed())
     );
 
-    measure_time("finish and clear bars", Level::Trace, || {
-        worker_bars
-            .into_iter()
-            .par_bridge()
-            .for_each(|bar| bar.finish_and_clear());
-    });
-
     if !panicked {
         measure_time("join executor", Level::Trace, || {
             executor.join() // force the executor to terminate safely.
@@ -444,14 +590,56 @@ pub fn execute_tasks2<A: AssembleAware + ?Sized>(
         panic::resume_unwind(error.unwrap());
     }
 
-    if let ConsoleMode::Rich = start_parameter.logging().console {
+    if let ConsoleMode::Rich = start_parameter.logging().console.resolve() {
         LOGGING_CONTROL.end_progress_bar();
     }
 
     trace!(
-        "freight execution time: {:.3} sec",
-        start_instant.elapsed().as_secs_f32()
+        "freight execution time: {}",
+        humanize::duration(start_instant.elapsed())
     );
+    execution.finish();
+    let finish = profile.start(BuildPhase::Finish);
+
+    if let Some(report_dir) = start_parameter.report_dir() {
+        let report = BuildReport::new(
+            start_parameter.task_requests(),
+            task_execution_start_time,
+            task_execution_started_at,
+            &results,
+            &dependencies,
+        );
+        let report_dir = project.with(|p| p.root_dir()).join(report_dir);
+        match report.write_to(&report_dir) {
+            Ok(path) => info!("build report written to {}", path.display()),
+            Err(e) => warn!("could not write build report to {:?}: {}", report_dir, e),
+        }
+    }
+
+    if let Some(results_xml) = start_parameter.results_xml() {
+        let results_xml = project.with(|p| p.root_dir()).join(results_xml);
+        match write_junit_xml(&results_xml, &results) {
+            Ok(()) => info!("task results written to {}", results_xml.display()),
+            Err(e) => warn!("could not write task results to {:?}: {}", results_xml, e),
+        }
+    }
+
+    finish.finish();
+
+    if let Some(count) = start_parameter.profile() {
+        info!("{}", slowest_tasks_summary(&results, count));
+        let report = ProfileReport::new(&profile, &results, count);
+        info!("{}", report.summary());
+        let profile_dir = project.with(|p| p.root_dir()).join(PROFILE_REPORT_DIR);
+        match report.write_to(&profile_dir) {
+            Ok(path) => info!("profile report written to {}", path.display()),
+            Err(e) => warn!("could not write profile report to {:?}: {}", profile_dir, e),
+        }
+    }
+
+    if let Some(reason) = cancellation.reason() {
+        return Err(FreightError::Cancelled(reason).into());
+    }
 
     Ok(results)
 }
@@ -480,6 +668,7 @@ pub fn execute_tasks(
     };
 
     trace!("created exec graph: {:#?}", exec_graph);
+    project.with(|p| p.phase_state().advance(ProjectPhase::GraphReady));
     let mut exec_plan = try_creating_plan(exec_graph)?;
     exec_plan.print_plan(Level::Trace);
 
@@ -488,8 +677,8 @@ pub fn execute_tasks(
     }
 
     debug!(
-        "{project} plan creation time: {:.3} sec",
-        start_instant.elapsed().as_secs_f32()
+        "{project} plan creation time: {}",
+        humanize::duration(start_instant.elapsed())
     );
 
     let executor = init_executor(NonZeroUsize::new(args.workers()).unwrap())?;
@@ -497,10 +686,10 @@ pub fn execute_tasks(
     let mut results = vec![];
 
     let mut work_queue = TaskExecutor::new(project.clone(), &executor);
+    let skip_reasons = work_queue.skip_reasons();
 
-    let progress = MultiProgress::with_draw_target(ProgressDrawTarget::stdout_with_hz(u8::MAX));
+    let progress = MultiProgress::with_draw_target(ProgressDrawTarget::stderr_with_hz(u8::MAX));
 
-    let mut worker_bars = vec![];
     let mut available_workers = VecDeque::from_iter(0..args.workers());
     let mut in_use_workers: HashMap<TaskId, usize> = HashMap::new();
 
@@ -513,13 +702,6 @@ pub fn execute_tasks(
     let main_bar = progress.add(bar);
     main_bar.tick();
 
-    for _ in 0..args.workers() {
-        let bar = progress.add(
-            ProgressBar::new_spinner().with_style(ProgressStyle::with_template("> {msg}").unwrap()),
-        );
-        worker_bars.push(bar.clone());
-    }
-
     progress.set_move_cursor(false);
 
     if let ConsoleMode::Rich = args.logging().console.resolve() {
@@ -529,35 +711,46 @@ pub fn execute_tasks(
     let mut results_builders = HashMap::new();
 
     let _task_execution_start_time = Instant::now();
+    let mut next_task = None;
 
     while !(exec_plan.finished() || executor.any_panicked()) {
+        let mut queued = false;
         if let Some(worker_index) = available_workers.pop_front() {
-            if let Some((task, decs)) = exec_plan.pop_task() {
-                trace!("loading task {} into task queue", task.read().task_id());
-                let task_id = task.read().task_id().clone();
-                let result_builder = TaskResultBuilder::new(task_id.clone());
-                results_builders.insert(task_id.clone(), result_builder);
-
-                let task_bar = { worker_bars[worker_index].clone() };
-
-                if let Some(weak_decoder) = decs {
-                    let task_options = task.read().options_declarations().unwrap();
-                    let upgraded_decoder = weak_decoder.upgrade(&task_options)?;
-                    task.write()
-                        .try_set_from_decoder(&upgraded_decoder)
-                        .map_err(PayloadError::into_inner)?;
-                }
+            if let Some((task, decs)) = next_task.take().or_else(|| exec_plan.pop_task()) {
+                if !work_queue.can_queue(&task) {
+                    // wait for running tasks to free enough workers, without letting other
+                    // tasks start in the meantime
+                    available_workers.push_front(worker_index);
+                    next_task = Some((task, decs));
+                } else {
+                    trace!("loading task {} into task queue", task.read().task_id());
+                    let task_id = task.read().task_id().clone();
+                    let result_builder = TaskResultBuilder::new(task_id.clone());
+                    results_builders.insert(task_id.clone(), result_builder);
+
+                    if let Some(weak_decoder) = decs {
+                        let task_options = task.read().options_declarations().unwrap();
+                        let upgraded_decoder = weak_decoder.upgrade(&task_options)?;
+                        task.write()
+                            .try_set_from_decoder(&upgraded_decoder)
+                            .map_err(PayloadError::into_inner)?;
+                    }
 
-                task_bar.set_message(format!("{}", task.read().task_id()));
-                task_bar.tick();
-                in_use_workers.insert(task_id, worker_index);
-                work_queue.queue_task(task)?;
+                    in_use_workers.insert(task_id, worker_index);
+                    work_queue.queue_task(task)?;
+                    queued = true;
+                }
             } else {
                 available_workers.push_front(worker_index);
             }
         }
-        // sleep(Duration::from_millis(100));
-        for (task_id, output) in work_queue.finished_tasks() {
+        let finished_tasks = if queued {
+            work_queue.finished_tasks()
+        } else {
+            // no more tasks can start until a running task finishes
+            work_queue.wait_for_finished_tasks()
+        };
+        for (task_id, output) in finished_tasks {
             trace!("received task {} from task queue", task_id);
             let outcome: Option<TaskOutcome> = if let &Ok((up_to_date, did_work)) = &output {
                 match (up_to_date, did_work) {
@@ -591,12 +784,7 @@ pub fn execute_tasks(
                 None
             };
 
-            let task_bar_index = in_use_workers[&task_id];
-            let task_bar = &worker_bars[task_bar_index];
-            available_workers.push_front(task_bar_index);
-
-            task_bar.set_message("");
-            task_bar.tick();
+            available_workers.push_front(in_use_workers[&task_id]);
 
             if output.is_err() {
                 error!("Task {} FAILED", task_id);
@@ -607,9 +795,13 @@ pub fn execute_tasks(
             main_bar.inc(1);
 
             exec_plan.report_task_status(&task_id, output.is_ok());
-            let result_builder = results_builders.remove(&task_id).unwrap();
+            let mut result_builder = results_builders.remove(&task_id).unwrap();
+            if let Some(reason) = skip_reasons.read().get(&task_id) {
+                result_builder.skipped_because(reason);
+            }
             let work_result =
                 result_builder.finish(output.map(|_| outcome.expect("should be set")));
+            LOGGING_CONTROL.emit_event(work_result.json_event());
             results.push(work_result);
         }
     }
@@ -655,12 +847,7 @@ pub fn execute_tasks(
             None
         };
 
-        let task_bar_index = in_use_workers[&task_id];
-        let task_bar = &worker_bars[task_bar_index];
-        available_workers.push_front(task_bar_index);
-
-        task_bar.set_message("");
-        task_bar.tick();
+        available_workers.push_front(in_use_workers[&task_id]);
 
         if output.is_err() {
             error!("Task {} FAILED", task_id);
@@ -670,25 +857,22 @@ pub fn execute_tasks(
         main_bar.inc(1);
 
         exec_plan.report_task_status(&task_id, output.is_ok());
-        let result_builder = results_builders.remove(&task_id).unwrap();
+        let mut result_builder = results_builders.remove(&task_id).unwrap();
+        if let Some(reason) = skip_reasons.read().get(&task_id) {
+            result_builder.skipped_because(reason);
+        }
         let work_result = result_builder.finish(output.map(|_| outcome.unwrap()));
+        LOGGING_CONTROL.emit_event(work_result.json_event());
         results.push(work_result);
     }
 
     let panicked = matches!(&error, Some(_));
 
     trace!(
-        "freight task completion time: {:.3} sec",
-        start_instant.elapsed().as_secs_f32()
+        "freight task completion time: {}",
+        humanize::duration(start_instant.elapsed())
     );
 
-    measure_time("finish and clear bars", Level::Trace, || {
-        worker_bars
-            .into_iter()
-            .par_bridge()
-            .for_each(|bar| bar.finish_and_clear());
-    });
-
     if !panicked {
         measure_time("join executor", Level::Trace, || {
             executor.join() // force the executor to terminate safely.
@@ -702,13 +886,13 @@ pub fn execute_tasks(
         panic::resume_unwind(error.unwrap());
     }
 
-    if let ConsoleMode::Rich = args.logging().console {
+    if let ConsoleMode::Rich = args.logging().console.resolve() {
         LOGGING_CONTROL.end_progress_bar();
     }
 
     trace!(
-        "freight execution time: {:.3} sec",
-        start_instant.elapsed().as_secs_f32()
+        "freight execution time: {}",
+        humanize::duration(start_instant.elapsed())
     );
 
     if let Some(handle) = handle {
@@ -718,3 +902,52 @@ pub fn execute_tasks(
 
     Ok(results)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assemble_core::defaults::tasks::Empty;
+    use assemble_core::Project;
+    use tempfile::TempDir;
+
+    #[test]
+    fn overlapping_outputs_need_an_ordering() {
+        let dir = TempDir::new().unwrap();
+        let output = dir.path().join("out");
+        let project = Project::temp("root");
+        for (name, output, depends_on) in [
+            ("first", output.clone(), None),
+            ("second", output.clone(), None),
+            ("third", output.join("nested"), Some("first")),
+        ] {
+            project
+                .register_task::<Empty>(name)
+                .unwrap()
+                .configure_with(move |task, _| {
+                    task.work().add_output(output);
+                    if let Some(depends_on) = depends_on {
+                        task.depends_on(depends_on);
+                    }
+                    Ok(())
+                })
+                .unwrap();
+        }
+
+        let plan = |requests: &[&str]| {
+            let requests = TaskRequests::build(&project, requests.iter().copied()).unwrap();
+            let graph = TaskResolver::new(&project)
+                .to_execution_graph(requests)
+                .unwrap();
+            try_creating_plan(graph)
+        };
+        assert!(matches!(
+            plan(&["first", "second"]),
+            Err(ConstructionError::OverlappingOutputs { .. })
+        ));
+        assert!(matches!(
+            plan(&["second", "third"]),
+            Err(ConstructionError::OverlappingOutputs { .. })
+        ));
+        assert!(plan(&["first", "third"]).is_ok());
+    }
+}
@@ -0,0 +1,160 @@
+//! Profile reports.
+//!
+//! When `--profile` is used, a report showing where the wall clock time of the build went is
+//! written into `build/reports/profile`, breaking the build down into its phases and listing the
+//! slowest tasks.
+
+use crate::report::escape;
+use crate::utils::TaskResult;
+use assemble_core::humanize;
+use assemble_core::startup::profile::{BuildPhase, BuildProfile};
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// The directory profile reports are written to, relative to the root project
+pub const PROFILE_REPORT_DIR: &str = "build/reports/profile";
+
+/// A report of where the time of a build went
+#[derive(Debug)]
+pub struct ProfileReport {
+    started_at: u128,
+    total: Duration,
+    phases: Vec<(BuildPhase, Duration)>,
+    slowest: Vec<(String, Duration, Duration)>,
+}
+
+impl ProfileReport {
+    /// Creates a report from a build profile and the `count` slowest task results
+    pub fn new(profile: &BuildProfile, results: &[TaskResult], count: usize) -> Self {
+        let mut slowest = results.iter().collect::<Vec<_>>();
+        slowest.sort_by(|a, b| b.execution_time.cmp(&a.execution_time));
+        slowest.truncate(count);
+        Self {
+            started_at: profile
+                .started_at()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis(),
+            total: profile.elapsed(),
+            phases: profile.phases(),
+            slowest: slowest
+                .into_iter()
+                .map(|result| {
+                    (
+                        result.id.to_string(),
+                        result.execution_time,
+                        result.queue_wait,
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// A plain text breakdown of the time spent in each phase
+    pub fn summary(&self) -> String {
+        let mut summary = format!("profile ({} total):", humanize::duration(self.total));
+        for (phase, duration) in &self.phases {
+            let _ = write!(
+                summary,
+                "\n  {:>10}  {:>5.1}%  {}",
+                humanize::duration(*duration),
+                self.percent(*duration),
+                phase
+            );
+        }
+        summary
+    }
+
+    /// Writes the report into a directory, returning the path of the report
+    pub fn write_to<P: AsRef<Path>>(&self, dir: P) -> io::Result<PathBuf> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("profile-{}.html", self.started_at));
+        std::fs::write(&path, self.to_html())?;
+        Ok(path)
+    }
+
+    /// Renders this report as a standalone html document
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Build profile</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+table {{ border-collapse: collapse; }}
+th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; }}
+td.numeric {{ text-align: right; }}
+</style>
+</head>
+<body>
+<h1>Build profile</h1>
+<p>Total build time: {total:.3} sec</p>
+<h2>Phases</h2>
+<table>
+<tr><th>Phase</th><th>Duration</th><th>Share</th></tr>
+"#,
+            total = self.total.as_secs_f64()
+        );
+        for (phase, duration) in &self.phases {
+            let _ = writeln!(
+                html,
+                r#"<tr><td>{}</td><td class="numeric">{:.3} sec</td><td class="numeric">{:.1}%</td></tr>"#,
+                phase,
+                duration.as_secs_f64(),
+                self.percent(*duration)
+            );
+        }
+        html.push_str("</table>\n<h2>Slowest tasks</h2>\n<table>\n");
+        html.push_str("<tr><th>Task</th><th>Duration</th><th>Waited for worker</th></tr>\n");
+        for (id, execution_time, queue_wait) in &self.slowest {
+            let _ = writeln!(
+                html,
+                r#"<tr><td><code>{}</code></td><td class="numeric">{:.3} sec</td><td class="numeric">{:.3} sec</td></tr>"#,
+                escape(id),
+                execution_time.as_secs_f64(),
+                queue_wait.as_secs_f64()
+            );
+        }
+        html.push_str("</table>\n</body>\n</html>\n");
+        html
+    }
+
+    fn percent(&self, duration: Duration) -> f64 {
+        duration.as_secs_f64() / self.total.as_secs_f64().max(f64::EPSILON) * 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TaskResultBuilder;
+    use assemble_core::identifier::TaskId;
+    use assemble_core::task::TaskOutcome;
+
+    #[test]
+    fn report_lists_phases_and_tasks() {
+        let profile = BuildProfile::new();
+        profile.record(BuildPhase::SettingsEvaluation, Duration::from_millis(20));
+        profile.record(BuildPhase::TaskExecution, Duration::from_millis(80));
+        let results = vec![TaskResultBuilder::new(TaskId::new(":compile").unwrap())
+            .finish(Ok(TaskOutcome::Executed))];
+
+        let report = ProfileReport::new(&profile, &results, 10);
+        let summary = report.summary();
+        assert!(summary.contains("settings evaluation"));
+        assert!(summary.contains("task execution"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = report.write_to(dir.path()).unwrap();
+        let html = std::fs::read_to_string(path).unwrap();
+        assert!(html.contains("<code>:compile</code>"));
+        assert!(html.contains("task execution"));
+    }
+}
@@ -1,4 +1,7 @@
+use assemble_core::startup::properties::command_line_properties;
 use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
 
 #[derive(Debug, clap::Args, Clone, merge::Merge)]
 pub struct ProjectProperties {
@@ -8,15 +11,35 @@ pub struct ProjectProperties {
     #[clap(value_name = "KEY[=VALUE]")]
     #[merge(strategy = merge::vec::append)]
     properties: Vec<(String, Option<String>)>,
+    /// Read project properties from a file. Can be given more than once, with later files taking
+    /// precedence.
+    #[clap(long = "project-prop-file")]
+    #[clap(value_parser(try_parse_prop_file))]
+    #[clap(value_name = "FILE")]
+    #[merge(strategy = merge::vec::append)]
+    prop_files: Vec<PathBuf>,
 }
 
 const MISSING_VALUE: &str = "";
 
 impl ProjectProperties {
+    /// The properties set with `-P`
     pub fn properties(&self) -> HashMap<String, Option<String>> {
         self.properties.clone().into_iter().collect()
     }
 
+    /// The files given with `--project-prop-file`
+    pub fn prop_files(&self) -> &[PathBuf] {
+        &self.prop_files
+    }
+
+    /// Merges the properties set with `-P` and those in the files given with `--project-prop-file`.
+    /// The properties files of projects and `ASSEMBLE_HOME` are loaded once the root project is
+    /// found. See [`properties`](assemble_core::startup::properties) for the order of precedence.
+    pub fn resolve(&self) -> io::Result<HashMap<String, Option<String>>> {
+        command_line_properties(&self.prop_files, &self.properties())
+    }
+
     /// Get a property
     pub fn property<S: AsRef<str>>(&self, prop: S) -> Option<&str> {
         let prop = prop.as_ref();
@@ -29,6 +52,15 @@ impl ProjectProperties {
     }
 }
 
+fn try_parse_prop_file(path: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(path);
+    if path.is_file() {
+        Ok(path)
+    } else {
+        Err(format!("{:?} is not a file", path))
+    }
+}
+
 fn try_parse_property(prop: &str) -> Result<(String, Option<String>), String> {
     if prop.contains('=') {
         let (prop, value) = prop.split_once('=').unwrap();
@@ -0,0 +1,378 @@
+//! Build reports.
+//!
+//! When requested with `--scan` or `--report <dir>`, a report of the build is written after tasks
+//! are executed. The report is written both as `build-report.json`, for tools, and as
+//! `build-report.html`, for people.
+
+use crate::core::Type;
+use crate::utils::TaskResult;
+use assemble_core::humanize;
+use assemble_core::identifier::TaskId;
+use assemble_core::logging::excerpts::task_log_excerpt;
+use assemble_core::task::TaskOutcome;
+use serde::Serialize;
+use std::backtrace::BacktraceStatus;
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+pub mod junit;
+
+/// The directory reports are written to when `--scan` is used, relative to the root project
+pub const DEFAULT_REPORT_DIR: &str = "build/reports/assemble";
+
+/// The number of slowest tasks printed by `--profile` by default
+pub const DEFAULT_PROFILE_TASKS: usize = 10;
+
+/// The name of the json report file
+pub const JSON_REPORT_FILE: &str = "build-report.json";
+
+/// The name of the html report file
+pub const HTML_REPORT_FILE: &str = "build-report.html";
+
+/// A report of a build
+#[derive(Debug, Serialize)]
+pub struct BuildReport {
+    /// Milliseconds since the unix epoch when task execution started
+    pub started_at: u128,
+    /// How long task execution took, in milliseconds
+    pub duration_ms: u128,
+    /// Whether every task succeeded
+    pub success: bool,
+    /// The task requests of the build
+    pub requested: Vec<String>,
+    /// The tasks that were executed, in the order they finished
+    pub tasks: Vec<TaskReport>,
+    /// The orderings between tasks
+    pub dependencies: Vec<DependencyReport>,
+}
+
+/// A report of a single task
+#[derive(Debug, Serialize)]
+pub struct TaskReport {
+    /// The id of the task
+    pub id: String,
+    /// The outcome of the task
+    pub outcome: String,
+    /// Why the task did no work, if it didn't
+    pub reason: Option<String>,
+    /// Milliseconds between the start of task execution and the start of the task
+    pub start_offset_ms: u128,
+    /// How long the task waited for a worker, in milliseconds
+    pub queue_wait_ms: u128,
+    /// How long the task took, in milliseconds
+    pub duration_ms: u128,
+    /// The last lines logged by the task
+    pub log: Vec<String>,
+    /// The error the task failed with
+    pub failure: Option<String>,
+    /// The stack trace of the failure, if one was captured
+    pub stack_trace: Option<String>,
+}
+
+/// An ordering between two tasks
+#[derive(Debug, Serialize)]
+pub struct DependencyReport {
+    /// The task that waits
+    pub task: String,
+    /// The task that is waited on
+    pub depends_on: String,
+    /// Whether this is a regular dependency or a finalizer
+    pub kind: String,
+}
+
+impl BuildReport {
+    /// Creates a report from the results of executing tasks
+    pub fn new(
+        requested: &[String],
+        start: Instant,
+        started_at: SystemTime,
+        results: &[TaskResult],
+        dependencies: &[(TaskId, TaskId, Type)],
+    ) -> Self {
+        let tasks = results
+            .iter()
+            .map(|result| TaskReport::new(start, result))
+            .collect::<Vec<_>>();
+        Self {
+            started_at: started_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis(),
+            duration_ms: start.elapsed().as_millis(),
+            success: results.iter().all(|result| result.result.is_ok()),
+            requested: requested.to_vec(),
+            tasks,
+            dependencies: dependencies
+                .iter()
+                .map(|(task, depends_on, kind)| DependencyReport {
+                    task: task.to_string(),
+                    depends_on: depends_on.to_string(),
+                    kind: match kind {
+                        Type::RunAfter => "run-after",
+                        Type::Finalizer => "finalizer",
+                    }
+                    .to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Writes the json and html reports into a directory, returning the path of the html report
+    pub fn write_to<P: AsRef<Path>>(&self, dir: P) -> io::Result<PathBuf> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(dir.join(JSON_REPORT_FILE), json)?;
+        let html_path = dir.join(HTML_REPORT_FILE);
+        std::fs::write(&html_path, self.to_html())?;
+        Ok(html_path)
+    }
+
+    /// Renders this report as a standalone html document
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Build report</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+table {{ border-collapse: collapse; width: 100%; }}
+th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top; }}
+.bar {{ background: #4a90d9; height: 10px; }}
+.failed {{ color: #c0392b; }}
+pre {{ margin: 0; white-space: pre-wrap; }}
+</style>
+</head>
+<body>
+<h1>Build report</h1>
+<p class="{status_class}">{status} in {duration:.2} sec</p>
+<p>Requested: <code>{requested}</code></p>
+<h2>Tasks</h2>
+<table>
+<tr><th>Task</th><th>Outcome</th><th>Duration</th><th>Timeline</th><th>Details</th></tr>
+"#,
+            status_class = if self.success { "" } else { "failed" },
+            status = if self.success {
+                "BUILD SUCCEEDED"
+            } else {
+                "BUILD FAILED"
+            },
+            duration = self.duration_ms as f64 / 1000.0,
+            requested = escape(&self.requested.join(" ")),
+        );
+
+        let total = self.duration_ms.max(1) as f64;
+        for task in &self.tasks {
+            let offset = task.start_offset_ms as f64 / total * 100.0;
+            let width = (task.duration_ms as f64 / total * 100.0).max(0.5);
+            let _ = write!(
+                html,
+                r#"<tr><td><code>{id}</code></td><td class="{class}">{outcome}</td><td>{duration:.3} sec</td><td><div class="bar" style="margin-left: {offset:.1}%; width: {width:.1}%"></div></td><td>"#,
+                id = escape(&task.id),
+                class = if task.failure.is_some() { "failed" } else { "" },
+                outcome = escape(&task.outcome),
+                duration = task.duration_ms as f64 / 1000.0,
+            );
+            if let Some(reason) = &task.reason {
+                let _ = write!(html, "<p>{}</p>", escape(reason));
+            }
+            if let Some(failure) = &task.failure {
+                let _ = write!(html, r#"<pre class="failed">{}</pre>"#, escape(failure));
+            }
+            if let Some(stack_trace) = &task.stack_trace {
+                let _ = write!(
+                    html,
+                    "<details><summary>Stack trace</summary><pre>{}</pre></details>",
+                    escape(stack_trace)
+                );
+            }
+            if !task.log.is_empty() {
+                let _ = write!(
+                    html,
+                    "<details><summary>Log</summary><pre>{}</pre></details>",
+                    escape(&task.log.join("\n"))
+                );
+            }
+            html.push_str("</td></tr>\n");
+        }
+        html.push_str("</table>\n<h2>Task graph</h2>\n<table>\n");
+        html.push_str("<tr><th>Task</th><th>Ordering</th><th>Task</th></tr>\n");
+        for dependency in &self.dependencies {
+            let _ = writeln!(
+                html,
+                "<tr><td><code>{}</code></td><td>{}</td><td><code>{}</code></td></tr>",
+                escape(&dependency.task),
+                escape(&dependency.kind),
+                escape(&dependency.depends_on)
+            );
+        }
+        html.push_str("</table>\n</body>\n</html>\n");
+        html
+    }
+}
+
+impl TaskReport {
+    fn new(start: Instant, result: &TaskResult) -> Self {
+        let (failure, stack_trace) = match &result.result {
+            Ok(()) => (None, None),
+            Err(e) => {
+                let stack_trace = match e.backtrace().status() {
+                    BacktraceStatus::Captured => Some(e.backtrace().to_string()),
+                    _ => None,
+                };
+                (Some(e.kind().to_string()), stack_trace)
+            }
+        };
+        Self {
+            id: result.id.to_string(),
+            outcome: outcome_name(&result.outcome).to_string(),
+            reason: no_work_reason(result).map(str::to_string),
+            start_offset_ms: (result.load_time.saturating_duration_since(start)
+                + result.queue_wait)
+                .as_millis(),
+            queue_wait_ms: result.queue_wait.as_millis(),
+            duration_ms: result.execution_time.as_millis(),
+            log: task_log_excerpt(&result.id),
+            failure,
+            stack_trace,
+        }
+    }
+}
+
+/// Lists the `count` tasks that took the longest to execute, slowest first
+pub fn slowest_tasks_summary(results: &[TaskResult], count: usize) -> String {
+    let mut slowest = results.iter().collect::<Vec<_>>();
+    slowest.sort_by(|a, b| b.execution_time.cmp(&a.execution_time));
+    slowest.truncate(count);
+
+    let mut summary = format!("{}:", humanize::count(slowest.len() as u64, "slowest task"));
+    for result in slowest {
+        let _ = write!(
+            summary,
+            "\n  {:>10}  {} ({}, waited {})",
+            humanize::duration(result.execution_time),
+            result.id,
+            outcome_name(&result.outcome),
+            humanize::duration(result.queue_wait)
+        );
+    }
+    summary
+}
+
+fn outcome_name(outcome: &TaskOutcome) -> &'static str {
+    match outcome {
+        TaskOutcome::Executed => "EXECUTED",
+        TaskOutcome::Skipped => "SKIPPED",
+        TaskOutcome::UpToDate => "UP-TO-DATE",
+        TaskOutcome::NoSource => "NO-SOURCE",
+        TaskOutcome::Failed => "FAILED",
+    }
+}
+
+/// Why a task did no work, preferring the reason it was skipped for if it has one
+fn no_work_reason(result: &TaskResult) -> Option<&str> {
+    if let Some(reason) = &result.skip_reason {
+        return Some(reason);
+    }
+    match result.outcome {
+        TaskOutcome::UpToDate => Some("inputs and outputs are unchanged since the last execution"),
+        TaskOutcome::NoSource => Some("the task had no inputs to process"),
+        TaskOutcome::Skipped => Some("the task did no work"),
+        TaskOutcome::Executed | TaskOutcome::Failed => None,
+    }
+}
+
+/// Escapes text for use in html
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TaskResultBuilder;
+    use std::time::Duration;
+
+    #[test]
+    fn html_is_escaped() {
+        assert_eq!(
+            escape("<a href=\"x\">&</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn writes_json_and_html() {
+        let start = Instant::now();
+        let compile = TaskId::new(":compile").unwrap();
+        let build = TaskId::new(":build").unwrap();
+        let results = vec![
+            TaskResultBuilder::new(compile.clone()).finish(Ok(TaskOutcome::UpToDate)),
+            TaskResultBuilder::new(build.clone()).finish(Ok(TaskOutcome::Executed)),
+        ];
+        let report = BuildReport::new(
+            &[":build".to_string()],
+            start,
+            SystemTime::now(),
+            &results,
+            &[(build, compile, Type::RunAfter)],
+        );
+        assert!(report.success);
+        assert_eq!(report.tasks[0].outcome, "UP-TO-DATE");
+        assert!(report.tasks[0].reason.is_some());
+
+        let dir = tempfile::tempdir().unwrap();
+        let html = report.write_to(dir.path()).unwrap();
+        assert!(std::fs::read_to_string(html)
+            .unwrap()
+            .contains("<code>:compile</code>"));
+        let json: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(dir.path().join(JSON_REPORT_FILE)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json["dependencies"][0]["depends_on"], ":compile");
+    }
+
+    #[test]
+    fn skip_reason_is_reported() {
+        let mut builder = TaskResultBuilder::new(TaskId::new(":docs").unwrap());
+        builder.skipped_because("docs are disabled");
+        let result = builder.finish(Ok(TaskOutcome::Skipped));
+        let report = TaskReport::new(Instant::now(), &result);
+        assert_eq!(report.outcome, "SKIPPED");
+        assert_eq!(report.reason.as_deref(), Some("docs are disabled"));
+    }
+
+    #[test]
+    fn slowest_tasks_first() {
+        let load = Instant::now();
+        let task = |id: &str, millis: u64| {
+            let mut builder = TaskResultBuilder::new(TaskId::new(id).unwrap());
+            builder.started_at(load - Duration::from_millis(millis));
+            builder.finish(Ok(TaskOutcome::Executed))
+        };
+        let results = vec![task(":fast", 0), task(":slow", 200), task(":medium", 100)];
+        let summary = slowest_tasks_summary(&results, 2);
+        let lines = summary.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].contains(":slow"));
+        assert!(lines[2].contains(":medium"));
+    }
+}
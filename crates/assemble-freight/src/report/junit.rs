@@ -0,0 +1,285 @@
+//! JUnit style xml reports.
+//!
+//! With `--results-xml <path>`, the results of the build are written in the JUnit xml format, so
+//! that CI servers can display them natively. Every project is a test suite and every task a
+//! test case. Tasks that did no work are reported as skipped.
+//!
+//! Tasks that recorded [test results](assemble_core::task::test_results) also get a test suite of
+//! their own, named after the task, with a test case for every test they ran.
+
+use super::{no_work_reason, outcome_name};
+use crate::utils::TaskResult;
+use assemble_core::identifier::ProjectId;
+use assemble_core::logging::excerpts::task_log_excerpt;
+use assemble_core::task::test_results::{test_results, TestOutcome, TestResults};
+use assemble_core::task::TaskOutcome;
+use indexmap::IndexMap;
+use std::backtrace::BacktraceStatus;
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+
+/// Renders task results as a JUnit xml document
+pub fn to_junit_xml(results: &[TaskResult]) -> String {
+    let mut suites: IndexMap<String, Vec<&TaskResult>> = IndexMap::new();
+    for result in results {
+        let project = result
+            .id
+            .project_id()
+            .as_ref()
+            .map(ProjectId::to_string)
+            .unwrap_or_default();
+        suites.entry(project).or_default().push(result);
+    }
+    let test_suites: Vec<(&TaskResult, TestResults)> = results
+        .iter()
+        .filter_map(|result| test_results(&result.id).map(|tests| (result, tests)))
+        .collect();
+    let tests = test_suites
+        .iter()
+        .map(|(_, tests)| tests.tests.len())
+        .sum::<usize>();
+    let test_failures = test_suites
+        .iter()
+        .map(|(_, tests)| tests.count(TestOutcome::Failed))
+        .sum::<usize>();
+    let test_skipped = test_suites
+        .iter()
+        .map(|(_, tests)| tests.count(TestOutcome::Ignored))
+        .sum::<usize>();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        r#"<testsuites name="assemble" tests="{}" failures="{}" skipped="{}" time="{:.3}">"#,
+        results.len() + tests,
+        count_failures(results.iter()) + test_failures,
+        count_skipped(results.iter()) + test_skipped,
+        total_time(results.iter())
+    );
+    for (project, results) in &suites {
+        let _ = writeln!(
+            xml,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" skipped="{}" time="{:.3}">"#,
+            escape_xml(project),
+            results.len(),
+            count_failures(results.iter().copied()),
+            count_skipped(results.iter().copied()),
+            total_time(results.iter().copied())
+        );
+        for result in results {
+            write_test_case(&mut xml, project, result);
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    for (result, tests) in &test_suites {
+        write_test_suite(&mut xml, result, tests);
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// Writes the JUnit xml report of task results to a file
+pub fn write_junit_xml<P: AsRef<Path>>(path: P, results: &[TaskResult]) -> io::Result<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, to_junit_xml(results))
+}
+
+fn write_test_case(xml: &mut String, project: &str, result: &TaskResult) {
+    let _ = write!(
+        xml,
+        r#"    <testcase name="{}" classname="{}" time="{:.3}">"#,
+        escape_xml(&result.id.to_string()),
+        escape_xml(project),
+        result.execution_time.as_secs_f64()
+    );
+    xml.push('\n');
+    match &result.result {
+        Err(error) => {
+            let mut body = error.kind().to_string();
+            if let BacktraceStatus::Captured = error.backtrace().status() {
+                let _ = write!(body, "\n{}", error.backtrace());
+            }
+            let _ = writeln!(
+                xml,
+                r#"      <failure message="{}" type="{}">{}</failure>"#,
+                escape_xml(&error.kind().to_string()),
+                outcome_name(&result.outcome),
+                escape_xml(&body)
+            );
+        }
+        Ok(()) => {
+            if let Some(reason) = no_work_reason(result) {
+                let _ = writeln!(
+                    xml,
+                    r#"      <skipped message="{}: {}"/>"#,
+                    outcome_name(&result.outcome),
+                    escape_xml(reason)
+                );
+            }
+        }
+    }
+    let log = task_log_excerpt(&result.id);
+    if !log.is_empty() {
+        let _ = writeln!(
+            xml,
+            "      <system-out>{}</system-out>",
+            escape_xml(&log.join("\n"))
+        );
+    }
+    xml.push_str("    </testcase>\n");
+}
+
+fn write_test_suite(xml: &mut String, result: &TaskResult, tests: &TestResults) {
+    let task = result.id.to_string();
+    let _ = writeln!(
+        xml,
+        r#"  <testsuite name="{}" tests="{}" failures="{}" skipped="{}" time="{:.3}">"#,
+        escape_xml(&task),
+        tests.tests.len(),
+        tests.count(TestOutcome::Failed),
+        tests.count(TestOutcome::Ignored),
+        result.execution_time.as_secs_f64()
+    );
+    for test in &tests.tests {
+        let _ = write!(
+            xml,
+            r#"    <testcase name="{}" classname="{}" time="{:.3}">"#,
+            escape_xml(&test.name),
+            escape_xml(&task),
+            test.duration_ms.unwrap_or_default() as f64 / 1000.0
+        );
+        xml.push('\n');
+        match test.outcome {
+            TestOutcome::Passed => {}
+            TestOutcome::Failed => {
+                let _ = writeln!(
+                    xml,
+                    r#"      <failure message="{} failed">{}</failure>"#,
+                    escape_xml(&test.name),
+                    escape_xml(test.output.as_deref().unwrap_or_default())
+                );
+            }
+            TestOutcome::Ignored => xml.push_str("      <skipped/>\n"),
+        }
+        if let Some(output) = test
+            .output
+            .as_ref()
+            .filter(|_| test.outcome != TestOutcome::Failed)
+        {
+            let _ = writeln!(xml, "      <system-out>{}</system-out>", escape_xml(output));
+        }
+        xml.push_str("    </testcase>\n");
+    }
+    xml.push_str("  </testsuite>\n");
+}
+
+fn count_failures<'a>(results: impl Iterator<Item = &'a TaskResult>) -> usize {
+    results.filter(|result| result.result.is_err()).count()
+}
+
+fn count_skipped<'a>(results: impl Iterator<Item = &'a TaskResult>) -> usize {
+    results
+        .filter(|result| {
+            matches!(
+                result.outcome,
+                TaskOutcome::Skipped | TaskOutcome::UpToDate | TaskOutcome::NoSource
+            )
+        })
+        .count()
+}
+
+fn total_time<'a>(results: impl Iterator<Item = &'a TaskResult>) -> f64 {
+    results
+        .map(|result| result.execution_time.as_secs_f64())
+        .sum()
+}
+
+/// Escapes text for use in xml, dropping characters xml can't represent
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TaskResultBuilder;
+    use assemble_core::exception::BuildException;
+    use assemble_core::identifier::TaskId;
+    use assemble_core::task::test_results::{record_test_results, TestCaseResult};
+
+    #[test]
+    fn tasks_are_grouped_by_project() {
+        let results = vec![
+            TaskResultBuilder::new(TaskId::new(":root:app:compile").unwrap())
+                .finish(Ok(TaskOutcome::Executed)),
+            TaskResultBuilder::new(TaskId::new(":root:app:test").unwrap())
+                .finish(Err(BuildException::new("2 tests <failed>").into())),
+            TaskResultBuilder::new(TaskId::new(":root:clean").unwrap())
+                .finish(Ok(TaskOutcome::UpToDate)),
+        ];
+        let xml = to_junit_xml(&results);
+
+        assert!(xml.contains(r#"<testsuites name="assemble" tests="3" failures="1" skipped="1""#));
+        assert!(xml.contains(r#"<testsuite name=":root:app" tests="2" failures="1" skipped="0""#));
+        assert!(xml.contains(r#"<testsuite name=":root" tests="1" failures="0" skipped="1""#));
+        assert!(xml.contains(r#"<failure message="2 tests &lt;failed&gt;" type="FAILED">"#));
+        assert!(xml.contains(r#"<skipped message="UP-TO-DATE: "#));
+    }
+
+    #[test]
+    fn recorded_tests_get_a_suite() {
+        let id = TaskId::new(":tests:app:test").unwrap();
+        record_test_results(
+            &id,
+            TestResults {
+                tests: vec![
+                    TestCaseResult {
+                        name: "tests::works".to_string(),
+                        outcome: TestOutcome::Passed,
+                        duration_ms: Some(1500),
+                        output: None,
+                    },
+                    TestCaseResult {
+                        name: "tests::broken".to_string(),
+                        outcome: TestOutcome::Failed,
+                        duration_ms: None,
+                        output: Some("left != right".to_string()),
+                    },
+                ],
+            },
+        );
+        let results = vec![
+            TaskResultBuilder::new(id).finish(Err(BuildException::new("1 test failed").into()))
+        ];
+        let xml = to_junit_xml(&results);
+
+        assert!(xml.contains(r#"<testsuites name="assemble" tests="3" failures="2" skipped="0""#));
+        assert!(xml.contains(r#"<testsuite name=":tests:app:test" tests="2" failures="1""#));
+        assert!(xml.contains(
+            r#"<testcase name="tests::works" classname=":tests:app:test" time="1.500">"#
+        ));
+        assert!(xml.contains(r#"<failure message="tests::broken failed">left != right</failure>"#));
+    }
+
+    #[test]
+    fn control_characters_are_dropped() {
+        assert_eq!(escape_xml("a\u{1b}[0m\tb"), "a[0m\tb");
+    }
+}
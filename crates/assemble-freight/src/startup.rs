@@ -15,12 +15,76 @@ impl From<FreightArgs> for StartParameter {
 
         start_parameter.set_logging(args.logging().clone());
         start_parameter.set_mode(args.logging().console);
-        start_parameter
-            .properties_mut()
-            .extend(args.properties().properties());
+        let properties = args.properties().resolve().unwrap_or_else(|e| {
+            error!("could not read project properties files: {}", e);
+            args.properties().properties()
+        });
+        start_parameter.properties_mut().extend(properties);
 
         start_parameter.set_workers(args.workers());
 
+        if args.write_locks() {
+            start_parameter.write_locks();
+        }
+
+        if args.strict_outputs() {
+            start_parameter.strict_outputs();
+        }
+
+        if args.warn_overlapping_outputs() {
+            start_parameter.warn_overlapping_outputs();
+        }
+
+        if let Some(undeclared_io) = args.undeclared_io() {
+            start_parameter.set_undeclared_io(undeclared_io);
+        }
+
+        if let Some(warning_mode) = args.warning_mode() {
+            start_parameter.set_warning_mode(warning_mode);
+        }
+
+        start_parameter.set_timeouts(args.timeouts());
+
+        if let Some(report_dir) = args.report_dir() {
+            start_parameter.set_report_dir(report_dir);
+        }
+
+        if let Some(results_xml) = args.results_xml() {
+            start_parameter.set_results_xml(results_xml);
+        }
+
+        if let Some(build_id) = args.build_id() {
+            start_parameter.set_build_id(build_id.clone());
+        }
+
+        if let Some(trust_policy) = args.trust_policy() {
+            start_parameter.set_trust_policy(trust_policy);
+        }
+
+        if let Some(export_plan) = args.export_plan() {
+            start_parameter.set_export_plan(export_plan);
+        }
+
+        if let Some(from_plan) = args.from_plan() {
+            start_parameter.set_from_plan(from_plan);
+        }
+
+        for init_script in args.init_scripts() {
+            start_parameter.add_init_script(init_script);
+        }
+
+        if args.offline() {
+            start_parameter.offline();
+        }
+
+        if let Some(channel) = args.update_check() {
+            start_parameter.set_update_check(channel);
+        }
+
+        if let Some(count) = args.profile() {
+            start_parameter.set_profile(count);
+        }
+
         start_parameter
     }
 }
@@ -1,9 +1,13 @@
 //! Utilities for fright to use
 
 use crate::core::ConstructionError;
+use assemble_core::error::category::{Categorize, ErrorCategory, ErrorCode};
+use assemble_core::error::hints::Hint;
 use assemble_core::error::PayloadError;
 use assemble_core::identifier::{InvalidId, TaskId};
+use assemble_core::logging::json_stream::JsonEvent;
 use assemble_core::project::error::ProjectError;
+use assemble_core::startup::cancellation::CancellationReason;
 use assemble_core::task::flags::OptionsDecoderError;
 use assemble_core::task::TaskOutcome;
 use assemble_core::{BuildResult, payload_from, Project};
@@ -14,7 +18,7 @@ use std::fmt::{Debug, Formatter};
 use std::io;
 use std::marker::PhantomData;
 
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 use thiserror::Error;
 
 /// Represents the result of a task
@@ -24,10 +28,20 @@ pub struct TaskResult {
     /// The result of the task
     pub result: BuildResult,
     pub outcome: TaskOutcome,
+    /// Why the task was skipped, if it was skipped by an only-if predicate
+    pub skip_reason: Option<String>,
     /// The time the task was loaded into the executor
     pub load_time: Instant,
+    /// The wall clock time the task started executing
+    pub start_time: SystemTime,
+    /// The wall clock time a result was received for the task
+    pub end_time: SystemTime,
     /// The duration between the load time and when a result was received
     pub duration: Duration,
+    /// How long the task waited in the executor before a worker started it
+    pub queue_wait: Duration,
+    /// The duration between the task starting and when a result was received
+    pub execution_time: Duration,
     /// The stdout of the task
     pub stdout: Vec<u8>,
     /// The stderr of the task
@@ -36,6 +50,18 @@ pub struct TaskResult {
     _data: PhantomData<()>,
 }
 
+impl TaskResult {
+    /// The event reporting this result in the json log stream
+    pub fn json_event(&self) -> JsonEvent {
+        JsonEvent::TaskFinished {
+            task: self.id.clone(),
+            outcome: self.outcome.clone(),
+            duration_ms: self.execution_time.as_millis() as u64,
+            error: self.result.as_ref().err().map(|e| e.to_string()),
+        }
+    }
+}
+
 impl Debug for TaskResult {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} -> {:?}", self.id, self.result)
@@ -45,6 +71,9 @@ impl Debug for TaskResult {
 pub struct TaskResultBuilder {
     id: TaskId,
     load_time: Instant,
+    load_system_time: SystemTime,
+    started: Option<Instant>,
+    skip_reason: Option<String>,
     pub stdout: Vec<u8>,
     pub stderr: Vec<u8>,
 }
@@ -54,13 +83,30 @@ impl TaskResultBuilder {
         Self {
             id: task,
             load_time: Instant::now(),
+            load_system_time: SystemTime::now(),
+            started: None,
+            skip_reason: None,
             stdout: vec![],
             stderr: vec![],
         }
     }
 
+    /// Sets the instant a worker started executing the task. If never set, the task is assumed to
+    /// have started as soon as it was loaded.
+    pub fn started_at(&mut self, instant: Instant) {
+        self.started = Some(instant);
+    }
+
+    /// Sets why the task was skipped
+    pub fn skipped_because(&mut self, reason: impl AsRef<str>) {
+        self.skip_reason = Some(reason.as_ref().to_string());
+    }
+
     pub fn finish(self, result: BuildResult<TaskOutcome>) -> TaskResult {
         let duration = self.load_time.elapsed();
+        let started = self.started.unwrap_or(self.load_time);
+        let queue_wait = started.saturating_duration_since(self.load_time);
+        let execution_time = started.elapsed();
         let outcome = match &result {
             Ok(outcome) => outcome.clone(),
             Err(_) => TaskOutcome::Failed,
@@ -69,8 +115,13 @@ impl TaskResultBuilder {
             id: self.id,
             result: result.map(|_| ()),
             outcome,
+            skip_reason: self.skip_reason,
             load_time: self.load_time,
+            start_time: self.load_system_time + queue_wait,
+            end_time: self.load_system_time + duration,
             duration,
+            queue_wait,
+            execution_time,
             stdout: self.stdout,
             stderr: self.stderr,
             _data: Default::default(),
@@ -95,6 +146,41 @@ pub enum FreightError {
     SetLoggerError(#[from] SetLoggerError),
     #[error(transparent)]
     ClapError(#[from] clap::Error),
+    #[error("Build cancelled: {0}")]
+    Cancelled(CancellationReason),
+    #[error("{} failed", .0.iter().map(|id| format!("Task {}", id)).collect::<Vec<_>>().join(", "))]
+    TasksFailed(Vec<TaskId>),
+    #[error("Build used {0} deprecated feature(s) with --warning-mode fail")]
+    DeprecationsUsed(usize),
+}
+
+impl Categorize for FreightError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            FreightError::ProjectError(e) => e.code(),
+            FreightError::DecoderError(_) => ErrorCode::new(ErrorCategory::Configuration, 12),
+            FreightError::IoError(_) => ErrorCode::new(ErrorCategory::Environment, 1),
+            FreightError::ConstructError(e) => e.code(),
+            FreightError::InvalidId(_) => ErrorCode::new(ErrorCategory::Configuration, 9),
+            FreightError::SetLoggerError(_) => ErrorCode::new(ErrorCategory::Internal, 8),
+            FreightError::ClapError(_) => ErrorCode::new(ErrorCategory::Configuration, 33),
+            FreightError::Cancelled(_) => ErrorCode::new(ErrorCategory::Cancelled, 1),
+            FreightError::TasksFailed(_) => ErrorCode::new(ErrorCategory::TaskExecution, 1),
+            FreightError::DeprecationsUsed(_) => ErrorCode::new(ErrorCategory::Configuration, 41),
+        }
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        match self {
+            FreightError::ProjectError(e) => e.hints(),
+            FreightError::ConstructError(e) => e.hints(),
+            FreightError::DeprecationsUsed(_) => vec![Hint::new(
+                "replace the deprecated features listed above, or run with --warning-mode all to \
+                 only warn of them",
+            )],
+            _ => vec![],
+        }
+    }
 }
 
 
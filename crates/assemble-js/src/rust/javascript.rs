@@ -12,6 +12,7 @@ static TRANSPILED_JAVASCRIPT: Dir<'_> = include_dir::include_dir!("$OUT_DIR/js")
 
 pub mod listeners;
 pub mod logger;
+pub mod plugins;
 pub mod project;
 pub mod task;
 pub use logger::Logging;
@@ -0,0 +1,99 @@
+//! Provides the `plugins` block for requesting binary plugins
+
+use assemble_core::plugins::binary::PluginRequest;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+#[derive(Debug, Default, Clone)]
+struct Requested {
+    id: String,
+    version: Option<String>,
+}
+
+/// The plugins requested within a `plugins` block
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Requests(Arc<Mutex<Vec<Requested>>>);
+
+impl Requests {
+    /// The requested plugins. Fails if a plugin was requested without a version.
+    pub(crate) fn requests(&self) -> Result<Vec<PluginRequest>, String> {
+        self.0
+            .lock()
+            .iter()
+            .map(|requested| match &requested.version {
+                Some(version) => Ok(PluginRequest::new(&requested.id, version)),
+                None => Err(format!("no version given for plugin {:?}", requested.id)),
+            })
+            .collect()
+    }
+}
+
+#[rquickjs::bind(public, object)]
+#[quickjs(bare)]
+mod plugins {
+    use super::{Requested, Requests};
+
+    /// Requests plugins with `spec.id("org.example.foo").version("1.2")`
+    #[derive(Debug, Clone)]
+    #[quickjs(cloneable)]
+    pub struct PluginsSpec {
+        #[quickjs(skip)]
+        pub(crate) requests: Requests,
+    }
+
+    impl PluginsSpec {
+        #[quickjs(skip)]
+        pub(crate) fn new(requests: Requests) -> Self {
+            Self { requests }
+        }
+
+        /// Requests a plugin
+        pub fn id(&self, id: String) -> PluginRequestSpec {
+            let mut requests = self.requests.0.lock();
+            requests.push(Requested { id, version: None });
+            PluginRequestSpec {
+                requests: self.requests.clone(),
+                index: requests.len() - 1,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    #[quickjs(cloneable)]
+    pub struct PluginRequestSpec {
+        #[quickjs(skip)]
+        requests: Requests,
+        #[quickjs(skip)]
+        index: usize,
+    }
+
+    impl PluginRequestSpec {
+        /// Sets the version of the requested plugin
+        pub fn version(&self, version: String) -> PluginRequestSpec {
+            self.requests.0.lock()[self.index].version = Some(version);
+            self.clone()
+        }
+    }
+}
+
+pub use plugins::{PluginRequestSpec, Plugins, PluginsSpec};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_need_versions() {
+        let requests = Requests::default();
+        let spec = PluginsSpec::new(requests.clone());
+        spec.id("org.example.foo".to_string())
+            .version("1.2".to_string());
+        assert_eq!(
+            requests.requests().unwrap(),
+            vec![PluginRequest::new("org.example.foo", "1.2")]
+        );
+
+        spec.id("org.example.bar".to_string());
+        assert!(requests.requests().is_err());
+    }
+}
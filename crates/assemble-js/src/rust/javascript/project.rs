@@ -9,13 +9,21 @@ use std::ops::{Deref, DerefMut};
 #[bind(public, object)]
 #[quickjs(bare)]
 mod project {
+    use crate::javascript::plugins::{PluginsSpec, Requests};
+    use crate::javascript::task::typed::TypedTaskProvider;
     use crate::javascript::task::{JSTask, TaskProvider};
-    use crate::{JsPluginExtension, PhantomIntoJs};
+    use crate::{JsPlugin, JsPluginExtension, PhantomIntoJs};
+    use assemble_core::plugins::binary::apply_plugin_request;
     use assemble_core::plugins::extensions::ExtensionAware;
+    use assemble_core::prelude::AssembleAware;
+    use assemble_core::project::finder::ProjectFinder;
     use assemble_core::project::shared::SharedProject;
+    use assemble_core::startup::script_plugins::resolve_script_plugin;
+    use assemble_core::startup::trust::verify_build_logic;
     use assemble_std::prelude::ProjectId;
     use log::{info, trace};
     use rquickjs::{Constructor, Ctx, Function, Persistent, Value};
+    use std::collections::HashMap;
 
     #[derive(Debug)]
     pub struct ProjectObj {
@@ -60,7 +68,145 @@ mod project {
                 inner: handle,
             }
         }
+
+        /// Registers a task of a rust type, such as `Script`, whose properties are set by assigning
+        /// fields in [`TypedTaskProvider::configure`](TypedTaskProvider)
+        pub fn registerTyped(
+            &self,
+            name: String,
+            task_type: String,
+        ) -> rquickjs::Result<TypedTaskProvider> {
+            let registry = self.shared.with(|pr| {
+                pr.extension::<JsPluginExtension>()
+                    .expect("js plugin not added")
+                    .typed_tasks()
+                    .clone()
+            });
+            let handle = registry
+                .register(&task_type, &self.shared, &name)
+                .map_err(|e| {
+                    rquickjs::Error::new_from_js_message("string", "task type", e.to_string())
+                })?
+                .expect("invalid handle");
+            Ok(TypedTaskProvider::new(handle))
+        }
+
+        /// Requests binary plugins, which are resolved from the plugin repositories of the build
+        /// and applied to this project
+        pub fn plugins<'js>(&self, configure: Function<'js>) -> rquickjs::Result<()> {
+            let requests = Requests::default();
+            configure.call::<_, ()>((PluginsSpec::new(requests.clone()),))?;
+            let requests = requests
+                .requests()
+                .map_err(|e| rquickjs::Error::new_from_js_message("object", "plugin request", e))?;
+            for request in requests {
+                self.shared
+                    .with_mut(|pr| apply_plugin_request(pr, &request))
+                    .map_err(|e| {
+                        rquickjs::Error::new_from_js_message("object", "plugin", e.to_string())
+                    })?;
+            }
+            Ok(())
+        }
+
+        /// Applies a script plugin to this project, such as `{ from: "common.assemble.js" }`. The
+        /// script is either a file relative to this project, or an http url that's downloaded once.
+        /// It's evaluated in its own scope, with `project` bound to this project.
+        pub fn apply<'js>(
+            &self,
+            ctx: Ctx<'js>,
+            options: HashMap<String, String>,
+        ) -> rquickjs::Result<()> {
+            let location = options.get("from").ok_or_else(|| {
+                rquickjs::Error::new_from_js_message(
+                    "object",
+                    "apply options",
+                    "only `from` is supported",
+                )
+            })?;
+            let (project_dir, start_parameter) =
+                self.shared.with(|p| (p.project_dir(), p.start_parameter()));
+            let to_js_error = |e: &dyn std::fmt::Display| {
+                rquickjs::Error::new_from_js_message("string", "script plugin", e.to_string())
+            };
+            let script = resolve_script_plugin(location, project_dir, start_parameter.is_offline())
+                .map_err(|e| to_js_error(&e))?;
+            verify_build_logic(start_parameter.trust_policy(), script.path())
+                .map_err(|e| to_js_error(&e))?;
+            let contents = script.contents().map_err(|e| to_js_error(&e))?;
+            info!(
+                "applying script plugin {} to {}",
+                script.location(),
+                self.shared
+            );
+
+            self.shared.with_mut(|pr| {
+                pr.extension_mut::<JsPluginExtension>()
+                    .expect("js plugin not added")
+                    .add_script_plugin(script)
+            });
+            let apply: Function = ctx.eval(format!("(function (project) {{\n{}\n}})", contents))?;
+            apply.call((ProjectObj::new(self.shared.clone()),))
+        }
+
+        /// Configures a project relative to this one, such as `":a:b"`, returning it
+        pub fn project<'js>(
+            &self,
+            path: String,
+            configure: Function<'js>,
+        ) -> rquickjs::Result<ProjectObj> {
+            let found = ProjectFinder::new(&self.shared)
+                .find(&path)
+                .ok_or_else(|| {
+                    rquickjs::Error::new_from_js_message(
+                        "string",
+                        "Project",
+                        format!("no project found at {:?} from {}", path, self.shared),
+                    )
+                })?;
+            configure_with(&found, &configure)?;
+            Ok(ProjectObj::new(found))
+        }
+
+        /// Configures every project below this one
+        pub fn subprojects<'js>(&self, configure: Function<'js>) -> rquickjs::Result<()> {
+            for subproject in super::descendants(&self.shared) {
+                configure_with(&subproject, &configure)?;
+            }
+            Ok(())
+        }
+
+        /// Configures this project and every project below it
+        pub fn allprojects<'js>(&self, configure: Function<'js>) -> rquickjs::Result<()> {
+            configure_with(&self.shared, &configure)?;
+            self.subprojects(configure)
+        }
     }
+
+    /// Calls a configuration function with a project. Projects configured before their own build
+    /// script runs get the js plugin applied first, so tasks can be registered in them.
+    #[quickjs(skip)]
+    fn configure_with(project: &SharedProject, configure: &Function) -> rquickjs::Result<()> {
+        project
+            .apply_plugin::<JsPlugin>()
+            .expect("couldn't add js plugin");
+        configure.call::<_, ()>((ProjectObj::new(project.clone()),))
+    }
+}
+
+/// Every project below a project, parents before their children. No project is locked when this
+/// returns.
+fn descendants(project: &SharedProject) -> Vec<SharedProject> {
+    let children: Vec<SharedProject> =
+        project.with(|p| p.subprojects().into_iter().cloned().collect());
+    children
+        .into_iter()
+        .flat_map(|child| {
+            let mut projects = descendants(&child);
+            projects.insert(0, child);
+            projects
+        })
+        .collect()
 }
 
 pub use project::ProjectObj;
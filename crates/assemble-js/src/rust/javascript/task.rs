@@ -14,6 +14,8 @@ use rquickjs::{bind, Context, Ctx, Function, Object, Persistent, This, Value};
 use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 
+pub mod typed;
+
 #[bind(public, object)]
 #[quickjs(bare)]
 mod tasks {
@@ -0,0 +1,452 @@
+//! Tasks of rust types configured from javascript through their properties.
+//!
+//! Instead of constructing a javascript task, a script can register a task of a rust type known to
+//! the [`TypedTaskRegistry`](TypedTaskRegistry) and assign its properties as fields:
+//! ```js
+//! project.registerTyped("greet", "Script").configure(task => {
+//!     task.script = "echo hello";
+//!     task.env = { "GREETING": "hi" };
+//! });
+//! ```
+//! Assignments aren't applied immediately. Each is added as a configuration of the task, which sets
+//! the matching rust property when the task is configured.
+
+use assemble_core::__export::{ProjectResult, TaskId};
+use assemble_core::error::PayloadError;
+use assemble_core::project::error::ProjectError;
+use assemble_core::project::shared::SharedProject;
+use assemble_core::task::{HasTaskId, TaskHandle};
+use assemble_core::{Executable, Task};
+use assemble_std::tasks::script::{Script, Shell};
+use rquickjs::{Ctx, FromJs, Type, Value};
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// A value assigned to a property of a typed task from javascript
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    /// A boolean
+    Bool(bool),
+    /// A number
+    Number(f64),
+    /// A string
+    String(String),
+    /// An array
+    List(Vec<PropertyValue>),
+    /// A plain object
+    Map(HashMap<String, PropertyValue>),
+}
+
+impl PropertyValue {
+    /// The javascript name of the type of this value
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            PropertyValue::Bool(_) => "boolean",
+            PropertyValue::Number(_) => "number",
+            PropertyValue::String(_) => "string",
+            PropertyValue::List(_) => "array",
+            PropertyValue::Map(_) => "object",
+        }
+    }
+
+    /// Gets this value as a boolean
+    pub fn as_bool(&self) -> Result<bool, PropertyError> {
+        match self {
+            PropertyValue::Bool(b) => Ok(*b),
+            other => Err(other.wrong_type("boolean")),
+        }
+    }
+
+    /// Gets this value as a string
+    pub fn as_string(&self) -> Result<String, PropertyError> {
+        match self {
+            PropertyValue::String(s) => Ok(s.clone()),
+            other => Err(other.wrong_type("string")),
+        }
+    }
+
+    /// Gets this value as a list of strings. A single string is a list of one string.
+    pub fn as_string_list(&self) -> Result<Vec<String>, PropertyError> {
+        match self {
+            PropertyValue::String(s) => Ok(vec![s.clone()]),
+            PropertyValue::List(list) => list.iter().map(|v| v.as_string()).collect(),
+            other => Err(other.wrong_type("array of strings")),
+        }
+    }
+
+    /// Gets this value as a map of strings
+    pub fn as_string_map(&self) -> Result<HashMap<String, String>, PropertyError> {
+        match self {
+            PropertyValue::Map(map) => map
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), v.as_string()?)))
+                .collect(),
+            other => Err(other.wrong_type("object of strings")),
+        }
+    }
+
+    fn wrong_type(&self, expected: &'static str) -> PropertyError {
+        PropertyError::WrongType {
+            expected,
+            found: self.type_name(),
+        }
+    }
+}
+
+impl<'js> FromJs<'js> for PropertyValue {
+    fn from_js(ctx: Ctx<'js>, value: Value<'js>) -> rquickjs::Result<Self> {
+        match value.type_of() {
+            Type::Bool => Ok(PropertyValue::Bool(bool::from_js(ctx, value)?)),
+            Type::Int | Type::Float => Ok(PropertyValue::Number(f64::from_js(ctx, value)?)),
+            Type::String => Ok(PropertyValue::String(String::from_js(ctx, value)?)),
+            Type::Array => Ok(PropertyValue::List(Vec::from_js(ctx, value)?)),
+            Type::Object => Ok(PropertyValue::Map(HashMap::from_js(ctx, value)?)),
+            other => Err(rquickjs::Error::new_from_js(
+                other.as_str(),
+                "property value",
+            )),
+        }
+    }
+}
+
+/// An error occurred setting the property of a typed task
+#[derive(Debug, thiserror::Error)]
+pub enum PropertyError {
+    #[error("no task type named {0:?}")]
+    UnknownTaskType(String),
+    #[error("tasks of type {task_type} have no property {property:?}")]
+    UnknownProperty { task_type: String, property: String },
+    #[error("expected {expected}, found {found}")]
+    WrongType {
+        expected: &'static str,
+        found: &'static str,
+    },
+    #[error("invalid value {value:?}, expected one of {expected:?}")]
+    InvalidValue {
+        value: String,
+        expected: &'static [&'static str],
+    },
+    #[error(transparent)]
+    PropError(#[from] assemble_core::lazy_evaluation::Error),
+}
+
+/// Sets a property of a task from a javascript value
+pub type PropertySetter<T> = fn(&mut Executable<T>, PropertyValue) -> Result<(), PropertyError>;
+
+/// The properties of a rust task type that can be set from javascript
+pub struct TypedTask<T: Task + Send + Sync + Debug + 'static> {
+    properties: Arc<HashMap<String, PropertySetter<T>>>,
+}
+
+impl<T: Task + Send + Sync + Debug + 'static> TypedTask<T> {
+    /// Creates a typed task with no properties
+    pub fn new() -> Self {
+        Self {
+            properties: Arc::new(HashMap::new()),
+        }
+    }
+
+    /// Adds a property that javascript can assign
+    pub fn property(mut self, name: &str, setter: PropertySetter<T>) -> Self {
+        Arc::make_mut(&mut self.properties).insert(name.to_string(), setter);
+        self
+    }
+
+    /// The names of the properties that can be assigned
+    pub fn properties(&self) -> impl Iterator<Item = &str> {
+        self.properties.keys().map(|s| s.as_str())
+    }
+
+    fn register_handle(
+        &self,
+        task_type: &str,
+        project: &SharedProject,
+        name: &str,
+    ) -> ProjectResult<TypedTaskHandle<T>> {
+        let handle = project.tasks().with_mut(|tc| tc.register_task::<T>(name))?;
+        Ok(TypedTaskHandle {
+            task_type: task_type.to_string(),
+            handle,
+            properties: self.properties.clone(),
+        })
+    }
+}
+
+impl<T: Task + Send + Sync + Debug + 'static> Default for TypedTask<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A registered task of a rust type whose properties can be set from javascript
+pub(crate) trait TypedHandle: Send + Sync {
+    /// The id of the task
+    fn id(&self) -> &TaskId;
+
+    /// Sets a property of the task once it's configured
+    fn set(&mut self, property: &str, value: PropertyValue) -> Result<(), PropertyError>;
+}
+
+struct TypedTaskHandle<T: Task + Send + Sync + Debug + 'static> {
+    task_type: String,
+    handle: TaskHandle<T>,
+    properties: Arc<HashMap<String, PropertySetter<T>>>,
+}
+
+impl<T: Task + Send + Sync + Debug + 'static> TypedHandle for TypedTaskHandle<T> {
+    fn id(&self) -> &TaskId {
+        self.handle.id()
+    }
+
+    fn set(&mut self, property: &str, value: PropertyValue) -> Result<(), PropertyError> {
+        let setter =
+            *self
+                .properties
+                .get(property)
+                .ok_or_else(|| PropertyError::UnknownProperty {
+                    task_type: self.task_type.clone(),
+                    property: property.to_string(),
+                })?;
+        let property = property.to_string();
+        self.handle
+            .configure_with(move |task, _| {
+                setter(task, value).map_err(|e| {
+                    PayloadError::new(ProjectError::custom(format!(
+                        "couldn't set {} of {}: {}",
+                        property,
+                        task.task_id(),
+                        e
+                    )))
+                })
+            })
+            .expect("task can no longer be configured");
+        Ok(())
+    }
+}
+
+trait TypedTaskFactory: Send + Sync {
+    fn register(
+        &self,
+        task_type: &str,
+        project: &SharedProject,
+        name: &str,
+    ) -> ProjectResult<Box<dyn TypedHandle>>;
+}
+
+impl<T: Task + Send + Sync + Debug + 'static> TypedTaskFactory for TypedTask<T> {
+    fn register(
+        &self,
+        task_type: &str,
+        project: &SharedProject,
+        name: &str,
+    ) -> ProjectResult<Box<dyn TypedHandle>> {
+        Ok(Box::new(self.register_handle(task_type, project, name)?))
+    }
+}
+
+/// The rust task types that javascript can register by name. By default, contains `Script`.
+#[derive(Clone)]
+pub struct TypedTaskRegistry {
+    types: HashMap<String, Arc<dyn TypedTaskFactory>>,
+}
+
+impl TypedTaskRegistry {
+    /// Creates a registry without any task types
+    pub fn empty() -> Self {
+        Self {
+            types: HashMap::new(),
+        }
+    }
+
+    /// Adds a task type that javascript can register by name
+    pub fn insert<T: Task + Send + Sync + Debug + 'static>(
+        &mut self,
+        task_type: &str,
+        typed: TypedTask<T>,
+    ) {
+        self.types.insert(task_type.to_string(), Arc::new(typed));
+    }
+
+    /// Checks whether there's a task type with a given name
+    pub fn contains(&self, task_type: &str) -> bool {
+        self.types.contains_key(task_type)
+    }
+
+    /// Registers a task of a type in a project
+    pub(crate) fn register(
+        &self,
+        task_type: &str,
+        project: &SharedProject,
+        name: &str,
+    ) -> Result<ProjectResult<Box<dyn TypedHandle>>, PropertyError> {
+        let factory = self
+            .types
+            .get(task_type)
+            .ok_or_else(|| PropertyError::UnknownTaskType(task_type.to_string()))?;
+        Ok(factory.register(task_type, project, name))
+    }
+}
+
+impl Default for TypedTaskRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.insert("Script", script());
+        registry
+    }
+}
+
+impl Debug for TypedTaskRegistry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.types.keys()).finish()
+    }
+}
+
+/// The properties of the [`Script`](Script) task
+fn script() -> TypedTask<Script> {
+    TypedTask::new()
+        .property("script", |task, value| {
+            Ok(task.script.set(value.as_string()?)?)
+        })
+        .property("shell", |task, value| {
+            let shell = match value.as_string()?.as_str() {
+                "bash" => Shell::Bash,
+                "sh" => Shell::Sh,
+                "powershell" => Shell::PowerShell,
+                other => {
+                    return Err(PropertyError::InvalidValue {
+                        value: other.to_string(),
+                        expected: &["bash", "sh", "powershell"],
+                    })
+                }
+            };
+            Ok(task.shell.set(shell)?)
+        })
+        .property("env", |task, value| {
+            for (key, value) in value.as_string_map()? {
+                task.env.insert(key, value);
+            }
+            Ok(())
+        })
+        .property("workingDir", |task, value| {
+            Ok(task.working_dir.set(PathBuf::from(value.as_string()?))?)
+        })
+        .property("echo", |task, value| Ok(task.echo.set(value.as_bool()?)?))
+        .property("ignoreExitValue", |task, value| {
+            Ok(task.ignore_exit_value.set(value.as_bool()?)?)
+        })
+}
+
+#[rquickjs::bind(public, object)]
+#[quickjs(bare)]
+mod typed_tasks {
+    use super::{PropertyValue, TypedHandle};
+    use parking_lot::Mutex;
+    use rquickjs::{Ctx, Function, Value};
+    use std::sync::Arc;
+
+    /// Assigning a field of the proxy sets the property of the task with the same name
+    const PROXY: &str = "(provider) => new Proxy(provider, {
+        set(target, property, value) {
+            target.set(property, value);
+            return true;
+        }
+    })";
+
+    #[derive(Clone)]
+    #[quickjs(cloneable)]
+    pub struct TypedTaskProvider {
+        #[quickjs(skip)]
+        pub(crate) inner: Arc<Mutex<Box<dyn TypedHandle>>>,
+    }
+
+    impl TypedTaskProvider {
+        #[quickjs(skip)]
+        pub(crate) fn new(handle: Box<dyn TypedHandle>) -> Self {
+            Self {
+                inner: Arc::new(Mutex::new(handle)),
+            }
+        }
+
+        pub fn id(&self) -> String {
+            self.inner.lock().id().to_string()
+        }
+
+        /// Sets a property of the task
+        pub fn set(&self, property: String, value: PropertyValue) -> rquickjs::Result<()> {
+            self.inner.lock().set(&property, value).map_err(|e| {
+                rquickjs::Error::new_from_js_message("value", "property", e.to_string())
+            })
+        }
+
+        /// Calls a function with an object whose assigned fields set properties of the task
+        pub fn configure<'js>(&self, ctx: Ctx<'js>, config: Function<'js>) -> rquickjs::Result<()> {
+            let proxy: Function = ctx.eval(PROXY)?;
+            let proxied: Value = proxy.call((self.clone(),))?;
+            config.call((proxied,))
+        }
+    }
+}
+
+pub use typed_tasks::{TypedTaskProvider, TypedTasks};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assemble_core::lazy_evaluation::Provider;
+    use assemble_core::Project;
+    use rquickjs::{Context, Runtime};
+
+    #[test]
+    fn values_from_js() -> rquickjs::Result<()> {
+        let runtime = Runtime::new()?;
+        let context = Context::full(&runtime)?;
+        context.with(|ctx| -> rquickjs::Result<()> {
+            let value: PropertyValue = ctx.eval(r#"({ "list": ["a", 1], "flag": true })"#)?;
+            assert_eq!(
+                value,
+                PropertyValue::Map(HashMap::from([
+                    (
+                        "list".to_string(),
+                        PropertyValue::List(vec![
+                            PropertyValue::String("a".to_string()),
+                            PropertyValue::Number(1.0)
+                        ])
+                    ),
+                    ("flag".to_string(), PropertyValue::Bool(true)),
+                ]))
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn set_script_properties() {
+        let project = Project::temp(None);
+        let registry = TypedTaskRegistry::default();
+        let mut handle = registry
+            .register("Script", &project, "greet")
+            .unwrap()
+            .unwrap();
+
+        handle
+            .set("script", PropertyValue::String("echo hello".to_string()))
+            .unwrap();
+        handle.set("echo", PropertyValue::Bool(true)).unwrap();
+        assert!(matches!(
+            handle.set("executable", PropertyValue::Bool(true)),
+            Err(PropertyError::UnknownProperty { .. })
+        ));
+
+        let script = project
+            .task_container()
+            .get_task(handle.id())
+            .cloned()
+            .unwrap()
+            .as_type::<Script>()
+            .unwrap();
+        assert_eq!(script.provides(|t| t.script.get()).get(), "echo hello");
+        assert!(script.provides(|t| t.echo.get()).get());
+    }
+}
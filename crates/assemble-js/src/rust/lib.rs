@@ -11,8 +11,10 @@ use parking_lot::{Mutex, RwLock};
 use assemble_core::__export::ProjectResult;
 use assemble_core::{Plugin, Project};
 use assemble_core::plugins::extensions::ExtensionAware;
+use assemble_core::startup::script_plugins::ScriptPlugin;
 use crate::javascript::file_contents;
 use crate::javascript::task::JsTaskContainer;
+use crate::javascript::task::typed::TypedTaskRegistry;
 
 pub mod javascript;
 
@@ -22,7 +24,21 @@ pub struct JsPlugin;
 
 impl Plugin<Project> for JsPlugin {
     fn apply_to(&self, target: &mut Project) -> ProjectResult {
-        let engine = Engine::new();
+        // projects share the runtime of the root project, so that functions saved while
+        // configuring one project from another can be restored in either
+        let shared_runtime = if target.is_root() {
+            None
+        } else {
+            target.root_project().with(|root| {
+                root.extension::<JsPluginExtension>()
+                    .ok()
+                    .map(|ext| ext.engine().lock().runtime().clone())
+            })
+        };
+        let engine = match shared_runtime {
+            Some(runtime) => Engine::with_runtime(&runtime),
+            None => Engine::new(),
+        };
         target.extensions_mut().add("javascript", JsPluginExtension::new(engine))?;
         Ok(())
     }
@@ -31,13 +47,20 @@ impl Plugin<Project> for JsPlugin {
 #[derive(Debug)]
 pub struct JsPluginExtension {
     engine: Mutex<Engine>,
-    container: JsTaskContainer
+    container: JsTaskContainer,
+    typed_tasks: TypedTaskRegistry,
+    script_plugins: Vec<ScriptPlugin>,
 }
 
 impl JsPluginExtension {
     /// Creates a js plugin extension
     pub fn new(engine: Engine) -> Self {
-        Self { engine: Mutex::new(engine), container: JsTaskContainer::new() }
+        Self {
+            engine: Mutex::new(engine),
+            container: JsTaskContainer::new(),
+            typed_tasks: TypedTaskRegistry::default(),
+            script_plugins: vec![],
+        }
     }
 
     pub(crate) fn container(&self) -> &JsTaskContainer {
@@ -50,6 +73,26 @@ impl JsPluginExtension {
     pub fn engine(&self) -> &Mutex<Engine> {
         &self.engine
     }
+
+    /// The rust task types scripts can register by name
+    pub fn typed_tasks(&self) -> &TypedTaskRegistry {
+        &self.typed_tasks
+    }
+
+    /// The rust task types scripts can register by name, for adding more
+    pub fn typed_tasks_mut(&mut self) -> &mut TypedTaskRegistry {
+        &mut self.typed_tasks
+    }
+
+    /// The script plugins applied to the project, along with their checksums. These are inputs of
+    /// the build logic of the project.
+    pub fn script_plugins(&self) -> &[ScriptPlugin] {
+        &self.script_plugins
+    }
+
+    pub(crate) fn add_script_plugin(&mut self, script: ScriptPlugin) {
+        self.script_plugins.push(script);
+    }
 }
 
 /// Provides an engine for executing scripts in
@@ -78,6 +121,8 @@ impl Engine {
         .with_bindings::<javascript::Bindings>()
         .with_bindings::<javascript::Logging>()
         .with_bindings::<javascript::task::Tasks>()
+        .with_bindings::<javascript::task::typed::TypedTasks>()
+        .with_bindings::<javascript::plugins::Plugins>()
         .with_declaration("logger", javascript::logger::Logger::default())
     }
 
@@ -86,6 +131,11 @@ impl Engine {
         Self::with_runtime(&Runtime::new().expect("a js runtime"))
     }
 
+    /// The runtime contexts of this engine are created in
+    pub fn runtime(&self) -> &Runtime {
+        &self.runtime
+    }
+
     /// Adds libraries
     pub fn with_libs<S: AsRef<str>, I: IntoIterator<Item = S>>(mut self, iter: I) -> Self {
         self.using_libs(iter);
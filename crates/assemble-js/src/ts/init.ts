@@ -0,0 +1,58 @@
+require("assemble")
+
+/**
+ * The actions of an init script, which configures every build on a machine. Init scripts are
+ * evaluated before the settings script of a build.
+ */
+class Init {
+    public properties: InitProperty[];
+    public plugin_repositories: string[];
+    public project_rules: { scope: string, source: string }[];
+
+    constructor() {
+        this.properties = [];
+        this.plugin_repositories = [];
+        this.project_rules = [];
+    }
+
+    /**
+     * Sets the default value of a project property. The property is only set to this value in
+     * projects where it isn't set anywhere else.
+     */
+    property(key: string, value: string | null = null): void {
+        this.properties.push(new InitProperty(key, value));
+    }
+
+    /**
+     * Adds a repository plugins can be resolved from.
+     */
+    plugin_repository(url: string): void {
+        this.plugin_repositories.push(url);
+    }
+
+    /**
+     * Configures every project, including the root project, before its build script is evaluated.
+     */
+    allprojects(action: (project: any) => void): void {
+        this.project_rules.push({scope: "allprojects", source: action.toString()});
+    }
+
+    /**
+     * Configures every project except the root project before its build script is evaluated.
+     */
+    subprojects(action: (project: any) => void): void {
+        this.project_rules.push({scope: "subprojects", source: action.toString()});
+    }
+}
+
+class InitProperty {
+    public key: string;
+    public value: string | null;
+
+    constructor(key: string, value: string | null) {
+        this.key = key;
+        this.value = value;
+    }
+}
+
+const init = new Init();
@@ -4,9 +4,53 @@ require("identifier")
 declare class Project {
     id(): Id;
     register<T extends Task>(name: string, cons: () => T): TaskProvider<T>;
+    /**
+     * Registers a task of a rust type, such as `"Script"`, configured by assigning its properties
+     */
+    registerTyped(name: string, type: string): TypedTaskProvider;
+    /**
+     * Requests binary plugins, which are resolved from the plugin repositories of the build
+     */
+    plugins(configure: (spec: PluginsSpec) => void): void;
+    /**
+     * Applies a script plugin, either a file relative to this project or an http url
+     */
+    apply(options: { from: string }): void;
+    /**
+     * Configures a project relative to this one, such as `":a:b"`
+     */
+    project(path: string, configure: (project: Project) => void): Project;
+    /**
+     * Configures every project below this one
+     */
+    subprojects(configure: (project: Project) => void): void;
+    /**
+     * Configures this project and every project below it
+     */
+    allprojects(configure: (project: Project) => void): void;
 }
 
 declare class TaskProvider<T extends Task> {
     id() : Id;
     configure<R extends T>(fun: (task: R) => void): void;
-}
\ No newline at end of file
+}
+
+declare class TypedTaskProvider {
+    id(): string;
+    /**
+     * Sets a property of the task
+     */
+    set(property: string, value: any): void;
+    /**
+     * Calls the function with an object whose assigned fields set the properties of the task
+     */
+    configure(fun: (task: any) => void): void;
+}
+
+declare class PluginsSpec {
+    id(id: string): PluginRequestSpec;
+}
+
+declare class PluginRequestSpec {
+    version(version: string): PluginRequestSpec;
+}
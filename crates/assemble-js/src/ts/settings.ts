@@ -8,9 +8,40 @@ function get_name(path: string, sep = path_separator()): string {
 
 class Settings {
     public root_project: ProjectDescriptor;
+    public project_rules: ProjectRule[];
+    public included_builds: IncludedBuild[];
 
     constructor(root_project: string) {
         this.root_project = new ProjectDescriptor(get_name(root_project), root_project);
+        this.project_rules = [];
+        this.included_builds = [];
+    }
+
+    /**
+     * Configures every project, including the root project, before its build script is evaluated.
+     */
+    allprojects(action: (project: any) => void): void {
+        this.project_rules.push(new ProjectRule("allprojects", action.toString()));
+    }
+
+    /**
+     * Configures every project except the root project before its build script is evaluated.
+     */
+    subprojects(action: (project: any) => void): void {
+        this.project_rules.push(new ProjectRule("subprojects", action.toString()));
+    }
+
+    /**
+     * Includes the build in a directory in this build. Relative paths are relative to the root
+     * directory of this build.
+     */
+    include_build(path: string): IncludedBuild {
+        for (const build of this.included_builds) {
+            if (build.path == path) return build;
+        }
+        const ret = new IncludedBuild(path);
+        this.included_builds.push(ret);
+        return ret;
     }
 
     include(...path: [string] & string[]): ProjectDescriptor | ProjectDescriptor[] {
@@ -27,6 +58,54 @@ class Settings {
     }
 }
 
+/**
+ * A rule configuring many projects. Rules are evaluated within the scope of each project, so
+ * only the source of the action is kept.
+ */
+class ProjectRule {
+    public scope: string;
+    public source: string;
+
+    constructor(scope: string, source: string) {
+        this.scope = scope;
+        this.source = source;
+    }
+}
+
+/**
+ * A build included in this build.
+ */
+class IncludedBuild {
+    public path: string;
+    public name: string | null;
+    public substitutions: Substitution[];
+
+    constructor(path: string) {
+        this.path = path;
+        this.name = null;
+        this.substitutions = [];
+    }
+
+    /**
+     * Declares that a project of the included build provides a module, so dependencies on the
+     * module are substituted by the project.
+     */
+    substitute(module: string, project: string): IncludedBuild {
+        this.substitutions.push(new Substitution(module, project));
+        return this;
+    }
+}
+
+class Substitution {
+    public module: string;
+    public project: string;
+
+    constructor(module: string, project: string) {
+        this.module = module;
+        this.project = project;
+    }
+}
+
 class ProjectDescriptor {
     public name: string;
     public path: string;
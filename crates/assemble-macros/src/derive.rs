@@ -7,11 +7,14 @@ use syn::{
 
 pub mod create_task;
 pub mod io_task;
+pub mod task_option;
+pub mod up_to_date;
 
 #[derive(Debug)]
 pub struct Property {
     kind: PropertyKind,
     field: Field,
+    option: Option<Attribute>,
 }
 
 #[derive(Debug)]
@@ -28,8 +31,21 @@ impl Property {
     pub fn field(&self) -> &Field {
         &self.field
     }
+    /// The `#[option]` attribute of the field, if it's a task option
+    pub fn option(&self) -> Option<&Attribute> {
+        self.option.as_ref()
+    }
     pub fn new(kind: PropertyKind, field: Field) -> Self {
-        Self { kind, field }
+        let option = field
+            .attrs
+            .iter()
+            .find(|att| att.path.is_ident("option"))
+            .cloned();
+        Self {
+            kind,
+            field,
+            option,
+        }
     }
 }
 
@@ -1,4 +1,5 @@
 /// Allow for easy generation of the `CreateTask` trait
+use crate::derive::task_option::TaskOption;
 use crate::TaskVisitor;
 use proc_macro2::TokenStream;
 use quote::quote;
@@ -44,6 +45,7 @@ impl CreateTask {
             };
         }
 
+        let options = Self::options(visitor);
         let (impl_gen, ty_generics, where_clause) = visitor.struct_generics().split_for_impl();
 
         quote! {
@@ -54,6 +56,42 @@ impl CreateTask {
                         #inner
                     })
                 }
+
+                #options
+            }
+        }
+    }
+
+    /// Declares the options of fields marked with `#[option]`, if there are any
+    fn options(visitor: &TaskVisitor) -> TokenStream {
+        let options = visitor
+            .properties()
+            .iter()
+            .filter_map(|prop| {
+                prop.option()
+                    .map(|attr| TaskOption::new(prop.field(), attr))
+            })
+            .collect::<syn::Result<Vec<_>>>();
+        let options = match options {
+            Ok(options) => options,
+            Err(e) => return e.to_compile_error(),
+        };
+        if options.is_empty() {
+            return quote!();
+        }
+
+        let declarations = options.iter().map(TaskOption::declaration);
+        let setters = options.iter().map(TaskOption::set_from_decoder);
+        quote! {
+            fn options_declarations() -> Option<assemble_core::task::flags::OptionDeclarations> {
+                Some(assemble_core::task::flags::OptionDeclarations::new::<Self, _>([
+                    #(#declarations),*
+                ]))
+            }
+
+            fn try_set_from_decoder(&mut self, decoder: &assemble_core::task::flags::OptionsDecoder) -> assemble_core::project::ProjectResult<()> {
+                #(#setters)*
+                Ok(())
             }
         }
     }
@@ -1,12 +1,13 @@
 use crate::derive::{is_prop, Property, PropertyKind};
-use crate::strum::{IntoEnumIterator, VariantNames};
+use crate::strum::VariantNames;
 use crate::TaskVisitor;
 use proc_macro2::Ident;
 
 use proc_macro2::TokenStream;
 
+use std::str::FromStr;
 use syn::spanned::Spanned;
-use syn::{Field, Generics, Meta, NestedMeta, PathArguments, Type};
+use syn::{Expr, Field, Generics, Lit, Meta, NestedMeta, PathArguments, Type};
 
 #[derive(Debug)]
 pub struct TaskIO<'a> {
@@ -51,11 +52,25 @@ struct Input<'a> {
 struct Output<'a> {
     field: &'a Field,
     kind: OutputKind,
+    /// An expression computing the output from the field
+    computed: Option<Expr>,
 }
 
 impl<'a> Output<'a> {
     pub fn new(field: &'a Field, kind: OutputKind) -> Self {
-        Self { field, kind }
+        Self {
+            field,
+            kind,
+            computed: None,
+        }
+    }
+
+    pub fn computed(field: &'a Field, kind: OutputKind, expr: Expr) -> Self {
+        Self {
+            field,
+            kind,
+            computed: Some(expr),
+        }
     }
 }
 
@@ -66,11 +81,99 @@ pub enum OutputKind {
     Serializable,
     File,
     Files,
+    #[strum(serialize = "directory", serialize = "dir")]
     Directory,
+    /// A struct implementing `NestedTaskIO`
+    Nested,
+}
+
+/// Whether the io is configured for a task, or for a part of a task nested within it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IoTarget {
+    Task,
+    Nested,
+}
+
+impl IoTarget {
+    /// The value fields are accessed from
+    fn access(self) -> TokenStream {
+        match self {
+            IoTarget::Task => quote!(task),
+            IoTarget::Nested => quote!(self),
+        }
+    }
+
+    /// The work handler
+    fn work(self) -> TokenStream {
+        match self {
+            IoTarget::Task => quote!(task.work()),
+            IoTarget::Nested => quote!(work),
+        }
+    }
+
+    /// The id of a field. Nested ids are prefixed by the name of the field they're nested in.
+    fn id(self, field: &Ident) -> TokenStream {
+        match self {
+            IoTarget::Task => quote!(stringify!(#field)),
+            IoTarget::Nested => quote!(&format!("{}.{}", name, stringify!(#field))),
+        }
+    }
 }
 
 impl<'a> TaskIO<'a> {
     pub fn derive_task_io(visitor: &TaskVisitor) -> syn::Result<TokenStream> {
+        let task_io = Self::from_visitor(visitor)?;
+        let (configure_io, restore_output) = task_io.generate(IoTarget::Task);
+        let ident = task_io.ty;
+        let (impl_gen, ty_generics, where_clause) = task_io.generics.split_for_impl();
+
+        Ok(quote! {
+            #[automatically_derived]
+            impl #impl_gen assemble_core::__export::TaskIO for #ident #ty_generics #where_clause {
+                fn configure_io(task: &mut assemble_core::__export::Executable<Self>) -> assemble_core::__export::ProjectResult {
+                    #configure_io
+                    Ok(())
+                }
+
+                fn recover_outputs(&mut self, output: &assemble_core::task::work_handler::output::Output) -> assemble_core::__export::ProjectResult {
+                    use assemble_core::task::work_handler::output::Output;
+                    if let Some(map) = output.serialized_data() {
+                        #restore_output
+                    }
+
+                    Ok(())
+                }
+            }
+        })
+    }
+
+    pub fn derive_nested_task_io(visitor: &TaskVisitor) -> syn::Result<TokenStream> {
+        let task_io = Self::from_visitor(visitor)?;
+        let (configure_io, restore_output) = task_io.generate(IoTarget::Nested);
+        let ident = task_io.ty;
+        let (impl_gen, ty_generics, where_clause) = task_io.generics.split_for_impl();
+
+        Ok(quote! {
+            #[automatically_derived]
+            impl #impl_gen assemble_core::__export::NestedTaskIO for #ident #ty_generics #where_clause {
+                fn configure_nested_io(&self, name: &str, work: &mut assemble_core::task::work_handler::WorkHandler) -> assemble_core::__export::ProjectResult {
+                    #configure_io
+                    Ok(())
+                }
+
+                fn recover_nested_outputs(&mut self, name: &str, output: &assemble_core::task::work_handler::output::Output) -> assemble_core::__export::ProjectResult {
+                    use assemble_core::task::work_handler::output::Output;
+                    if let Some(map) = output.serialized_data() {
+                        #restore_output
+                    }
+
+                    Ok(())
+                }
+            }
+        })
+    }
+
+    fn from_visitor(visitor: &'a TaskVisitor) -> syn::Result<Self> {
         let mut task_io = TaskIO::new(visitor.struct_name(), visitor.struct_generics());
 
         for property in visitor.properties() {
@@ -85,7 +188,7 @@ impl<'a> TaskIO<'a> {
             }
         }
 
-        task_io.finish()
+        Ok(task_io)
     }
 
     pub fn add_output(&mut self, property: &'a Property) -> syn::Result<()> {
@@ -112,21 +215,34 @@ impl<'a> TaskIO<'a> {
                     OutputKind::Serializable
                 } else if metas.len() == 1 {
                     let meta = metas.remove(0);
-                    let path = if let NestedMeta::Meta(meta) = &meta {
-                        meta.path()
-                    } else {
-                        abort!(meta.span(), "Only path expected")
+                    let (path, computed) = match &meta {
+                        NestedMeta::Meta(Meta::NameValue(assign)) => match &assign.lit {
+                            Lit::Str(expr) => (&assign.path, Some(expr.parse::<Expr>()?)),
+                            _ => abort!(assign.lit.span(), "Expected an expression in a string"),
+                        },
+                        NestedMeta::Meta(meta) => (meta.path(), None),
+                        _ => abort!(meta.span(), "Only path expected"),
                     };
 
-                    let mut out: Option<OutputKind> = None;
-                    for kind in OutputKind::iter() {
-                        if path.is_ident(kind.as_ref()) {
-                            out.replace(kind);
-                            break;
-                        }
-                    }
+                    let out = path
+                        .get_ident()
+                        .and_then(|ident| OutputKind::from_str(&ident.to_string()).ok());
 
                     if let Some(out) = out {
+                        if let Some(computed) = computed {
+                            if !matches!(
+                                out,
+                                OutputKind::File | OutputKind::Files | OutputKind::Directory
+                            ) {
+                                abort!(
+                                    meta.span(),
+                                    "Only file, files and directory outputs can be computed"
+                                )
+                            }
+                            self.outputs
+                                .push(Output::computed(&property.field, out, computed));
+                            return Ok(());
+                        }
                         out
                     } else {
                         abort!(
@@ -213,42 +329,54 @@ impl<'a> TaskIO<'a> {
         Ok(())
     }
 
-    pub fn finish(self) -> syn::Result<TokenStream> {
-        let inputs = self.inputs;
+    /// Generates the code configuring the io, and the code restoring outputs from a `map` of
+    /// serialized data
+    fn generate(&self, target: IoTarget) -> (TokenStream, TokenStream) {
+        let access = target.access();
+        let work = target.work();
         let mut inputs_quoted = quote!();
 
-        for input in inputs {
+        for input in &self.inputs {
             match input.kind {
                 InputKind::Transparent | InputKind::Directory => {
                     let field = input.field.ident.as_ref().unwrap();
+                    let id = target.id(field);
                     if is_prop(&input.field.ty) {
+                        let finalize = if is_finalizable(&input.field.ty) {
+                            quote!(#work.finalize_on_execute(&#field);)
+                        } else {
+                            quote!()
+                        };
                         inputs_quoted = quote! {
-                            let #field = task.#field.clone();
+                            let #field = #access.#field.clone();
                             #inputs_quoted
-                            task.work().add_input_prop(&#field)?;
+                            #work.add_input_prop(&#field)?;
+                            #finalize
                         }
                     } else {
                         inputs_quoted = quote! {
-                            let #field = task.#field.clone();
+                            let #field = #access.#field.clone();
                             #inputs_quoted
-                            task.work().add_input(stringify!(#field), provider!(|| #field.clone()))?;
+                            #work.add_input(#id, provider!(|| #field.clone()))?;
                         }
                     }
                 }
                 InputKind::Files => {
                     let field = input.field.ident.as_ref().unwrap();
+                    let id = target.id(field);
                     inputs_quoted = quote! {
-                        let #field = task.#field.clone();
+                        let #field = #access.#field.clone();
                         #inputs_quoted
-                        task.work().add_input_files(stringify!(#field), #field)?;
+                        #work.add_input_files(#id, #field)?;
                     };
                 }
                 InputKind::File => {
                     let field = input.field.ident.as_ref().unwrap();
+                    let id = target.id(field);
                     inputs_quoted = quote! {
-                        let #field = task.#field.clone();
+                        let #field = #access.#field.clone();
                         #inputs_quoted
-                        task.work().add_input_file(stringify!(#field), #field)?;
+                        #work.add_input_file(#id, #field)?;
                     };
                 }
             }
@@ -258,22 +386,53 @@ impl<'a> TaskIO<'a> {
 
         let mut restore_output = quote! {};
 
-        for output in self.outputs {
+        for output in &self.outputs {
             let field = &output.field;
+            let ident = field.ident.as_ref().unwrap();
+            let id = target.id(ident);
+
+            if let Some(computed) = &output.computed {
+                let value = match output.kind {
+                    OutputKind::Files => quote!(assemble_core::file_collection::FileSet),
+                    _ => quote!(std::path::PathBuf),
+                };
+                outputs_quoted = quote! {
+                    let #ident = #access.#ident.clone();
+                    #outputs_quoted
+                    #work.add_output_provider(assemble_core::provider!(move || -> #value {
+                        let #ident = #ident.clone();
+                        (#computed).into()
+                    }));
+                };
+                continue;
+            }
+
+            if let OutputKind::Nested = output.kind {
+                outputs_quoted = quote! {
+                    let #ident = #access.#ident.clone();
+                    #outputs_quoted
+                    assemble_core::__export::NestedTaskIO::configure_nested_io(&#ident, #id, #work)?;
+                };
+                restore_output = quote! {
+                    #restore_output
+                    assemble_core::__export::NestedTaskIO::recover_nested_outputs(&mut self.#ident, #id, output)?;
+                };
+                continue;
+            }
+
             if !is_prop(&field.ty) {
                 abort!(
                     field.ty.span(),
                     "Only Prop types are supported currently for outputs"
                 )
             }
-            let ident = field.ident.as_ref().unwrap();
 
             match output.kind {
                 OutputKind::Serializable => {
                     outputs_quoted = quote! {
-                        let #ident = task.#ident.clone();
+                        let #ident = #access.#ident.clone();
                         #outputs_quoted
-                        task.work().add_serialized_data(stringify!(#ident), #ident);
+                        #work.add_serialized_data(#id, #ident);
                     };
                     if let Type::Path(type_path) = &field.ty {
                         let last_segment = type_path.path.segments.last().unwrap();
@@ -290,7 +449,7 @@ impl<'a> TaskIO<'a> {
 
                             restore_output = quote! {
                                 #restore_output
-                                if let Some(value) = map.get(stringify!(#ident)) {
+                                if let Some(value) = map.get(#id) {
                                     let value: #inner = value.deserialize()?;
                                     self.#ident.set(value)?;
                                 }
@@ -299,7 +458,7 @@ impl<'a> TaskIO<'a> {
                         } else if final_value == "VecProp" {
                             restore_output = quote! {
                                 #restore_output
-                                if let Some(value) = map.get(stringify!(#ident)) {
+                                if let Some(value) = map.get(#id) {
                                     let value: Vec #prop_ty = value.deserialize()?;
                                     self.#ident.push_all(value);
                                 }
@@ -312,35 +471,28 @@ impl<'a> TaskIO<'a> {
                 }
                 OutputKind::File | OutputKind::Files | OutputKind::Directory => {
                     outputs_quoted = quote! {
-                        let #ident = task.#ident.clone();
+                        let #ident = #access.#ident.clone();
                         #outputs_quoted
-                        task.work().add_output_provider(#ident);
+                        #work.add_output_provider(#ident);
                     };
                 }
+                OutputKind::Nested => unreachable!(),
             }
         }
 
-        let ident = self.ty;
-        let (impl_gen, ty_generics, where_clause) = self.generics.split_for_impl();
-
-        Ok(quote! {
-            #[automatically_derived]
-            impl #impl_gen assemble_core::__export::TaskIO for #ident #ty_generics #where_clause {
-                fn configure_io(task: &mut assemble_core::__export::Executable<Self>) -> assemble_core::__export::ProjectResult {
-                    #inputs_quoted
-                    #outputs_quoted
-                    Ok(())
-                }
-
-                fn recover_outputs(&mut self, output: &assemble_core::task::work_handler::output::Output) -> assemble_core::__export::ProjectResult {
-                    use assemble_core::task::work_handler::output::Output;
-                    if let Some(map) = output.serialized_data() {
-                        #restore_output
-                    }
+        (quote!(#inputs_quoted #outputs_quoted), restore_output)
+    }
+}
 
-                    Ok(())
-                }
-            }
-        })
+/// Whether the type is a `Prop`, which is finalized once the task executes
+fn is_finalizable(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident == "Prop")
+            .unwrap_or(false),
+        _ => false,
     }
 }
@@ -0,0 +1,203 @@
+//! Generates task options from fields marked with `#[option]`
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{
+    Attribute, Field, GenericArgument, Ident, Lit, LitStr, Meta, Path, PathArguments, Token, Type,
+};
+
+/// How the value of an option is stored in its field
+#[derive(Debug)]
+enum OptionKind {
+    /// A `bool`, set if the flag is present
+    Flag,
+    /// An `Option<T>`, which may be missing
+    Optional(Type),
+    /// A `Vec<T>`, which accepts many values
+    Multiple(Type),
+    /// A `Prop<T>`, only set if a value is given
+    Prop(Type),
+    /// Any other type, which requires a value
+    Required(Type),
+}
+
+impl OptionKind {
+    fn of(ty: &Type) -> Self {
+        if let Type::Path(path) = ty {
+            let segment = path.path.segments.last().unwrap();
+            if segment.ident == "bool" && segment.arguments.is_empty() {
+                return OptionKind::Flag;
+            }
+            if let Some(inner) = single_generic(&segment.arguments) {
+                if segment.ident == "Option" {
+                    return OptionKind::Optional(inner.clone());
+                } else if segment.ident == "Vec" {
+                    return OptionKind::Multiple(inner.clone());
+                } else if segment.ident == "Prop" {
+                    return OptionKind::Prop(inner.clone());
+                }
+            }
+        }
+        OptionKind::Required(ty.clone())
+    }
+}
+
+fn single_generic(arguments: &PathArguments) -> Option<&Type> {
+    match arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match args.args.first() {
+            Some(GenericArgument::Type(ty)) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+enum OptionArg {
+    Name(LitStr),
+    Help(LitStr),
+    Parse(Path),
+}
+
+impl Parse for OptionArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let id: Ident = input.parse()?;
+        input.parse::<Token![=]>()?;
+        match &*id.to_string() {
+            "name" => Ok(OptionArg::Name(input.parse()?)),
+            "help" => Ok(OptionArg::Help(input.parse()?)),
+            "parse" => Ok(OptionArg::Parse(input.parse()?)),
+            _ => Err(syn::Error::new(
+                id.span(),
+                "not a valid setting for options. expected name, help or parse",
+            )),
+        }
+    }
+}
+
+/// A task option created from a field
+#[derive(Debug)]
+pub struct TaskOption {
+    field: Ident,
+    name: String,
+    help: String,
+    parse: Option<Path>,
+    kind: OptionKind,
+}
+
+impl TaskOption {
+    /// Creates an option from a field and its `#[option]` attribute. By default, the name of the
+    /// option is the name of the field with dashes instead of underscores, and its help is the
+    /// documentation of the field.
+    pub fn new(field: &Field, attribute: &Attribute) -> syn::Result<Self> {
+        let ident = field
+            .ident
+            .clone()
+            .ok_or_else(|| syn::Error::new_spanned(field, "options must be named fields"))?;
+        let mut option = Self {
+            name: ident.to_string().trim_start_matches("r#").replace('_', "-"),
+            help: field_docs(&field.attrs),
+            field: ident,
+            parse: None,
+            kind: OptionKind::of(&field.ty),
+        };
+
+        if !attribute.tokens.is_empty() {
+            let args =
+                attribute.parse_args_with(Punctuated::<OptionArg, Token![,]>::parse_terminated)?;
+            for arg in args {
+                match arg {
+                    OptionArg::Name(name) => option.name = name.value(),
+                    OptionArg::Help(help) => option.help = help.value(),
+                    OptionArg::Parse(parse) => option.parse = Some(parse),
+                }
+            }
+        }
+        if option.parse.is_some() && matches!(option.kind, OptionKind::Flag) {
+            return Err(syn::Error::new_spanned(
+                attribute,
+                "flags don't take a value to parse",
+            ));
+        }
+        Ok(option)
+    }
+
+    /// The declaration of the option
+    pub fn declaration(&self) -> TokenStream {
+        let name = &self.name;
+        let help = &self.help;
+        let builder = quote!(assemble_core::task::flags::OptionDeclarationBuilder);
+        let parser = match &self.parse {
+            Some(parse) => quote!(.value_parser(#parse)),
+            None => quote!(.use_from_str()),
+        };
+
+        match &self.kind {
+            OptionKind::Flag => quote! {
+                #builder::flag(#name).help(#help).build()
+            },
+            OptionKind::Optional(ty) | OptionKind::Prop(ty) => quote! {
+                #builder::<#ty>::new(#name).optional(true).help(#help) #parser .build()
+            },
+            OptionKind::Multiple(ty) => quote! {
+                #builder::<#ty>::new(#name)
+                    .allow_multiple_values(true)
+                    .optional(true)
+                    .help(#help)
+                    #parser
+                    .build()
+            },
+            OptionKind::Required(ty) => quote! {
+                #builder::<#ty>::new(#name).help(#help) #parser .build()
+            },
+        }
+    }
+
+    /// Sets the field from an `OptionsDecoder` named `decoder`
+    pub fn set_from_decoder(&self) -> TokenStream {
+        let field = &self.field;
+        let name = &self.name;
+        let error = quote!(assemble_core::error::PayloadError::new);
+
+        match &self.kind {
+            OptionKind::Flag => quote! {
+                self.#field = decoder.flag_present(#name).map_err(#error)?;
+            },
+            OptionKind::Optional(ty) => quote! {
+                self.#field = decoder.get_value::<#ty>(#name).map_err(#error)?;
+            },
+            OptionKind::Multiple(ty) => quote! {
+                if let Some(values) = decoder.get_values::<#ty>(#name).map_err(#error)? {
+                    self.#field = values;
+                }
+            },
+            OptionKind::Prop(ty) => quote! {
+                if let Some(value) = decoder.get_value::<#ty>(#name).map_err(#error)? {
+                    self.#field.set(value).map_err(#error)?;
+                }
+            },
+            OptionKind::Required(ty) => quote! {
+                if let Some(value) = decoder.get_value::<#ty>(#name).map_err(#error)? {
+                    self.#field = value;
+                }
+            },
+        }
+    }
+}
+
+/// Joins the doc comments of a field
+fn field_docs(attrs: &[Attribute]) -> String {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::NameValue(meta)) => match meta.lit {
+                Lit::Str(doc) => Some(doc.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,43 @@
+//! Generates `UpToDate` implementations from `#[up_to_date(...)]` predicates
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::{DeriveInput, Path, Token};
+
+pub struct UpToDate;
+
+impl UpToDate {
+    /// Implements `UpToDate` using the predicates of every `#[up_to_date(path, ...)]` attribute on
+    /// the type. The value is only up-to-date if all predicates hold, and is always up-to-date if
+    /// there are none.
+    pub fn derive_up_to_date(input: &DeriveInput) -> syn::Result<TokenStream> {
+        let mut predicates: Vec<Path> = vec![];
+        for attr in input
+            .attrs
+            .iter()
+            .filter(|attr| attr.path.is_ident("up_to_date"))
+        {
+            let paths = attr.parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated)?;
+            if paths.is_empty() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "expected at least one predicate, like #[up_to_date(path::to::fn)]",
+                ));
+            }
+            predicates.extend(paths);
+        }
+
+        let ident = &input.ident;
+        let (impl_gen, ty_generics, where_clause) = input.generics.split_for_impl();
+
+        Ok(quote! {
+            #[automatically_derived]
+            impl #impl_gen assemble_core::task::up_to_date::UpToDate for #ident #ty_generics #where_clause {
+                fn up_to_date(&self) -> bool {
+                    true #(&& #predicates(self))*
+                }
+            }
+        })
+    }
+}
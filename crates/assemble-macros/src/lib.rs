@@ -5,19 +5,32 @@ extern crate strum;
 
 use crate::derive::create_task::CreateTask;
 use crate::derive::io_task::TaskIO;
+use crate::plugin::{PluginArgs, PluginTokenizer};
 
 use derive::TaskVisitor;
 use proc_macro::TokenStream;
 use quote::ToTokens;
 
 use syn::visit::Visit;
-use syn::{parse_macro_input, DeriveInput, ItemFn, Lit};
+use syn::{parse_macro_input, AttributeArgs, DeriveInput, ItemFn, Lit};
 
 mod actions;
 mod derive;
+mod plugin;
 
 /// Creates tasks using default values. Also creates lazy_evaluation using the name of the field
-#[proc_macro_derive(CreateTask)]
+///
+/// Fields marked with `#[option(name = "...", help = "...", parse = path::to::fn)]` become
+/// options of the task, which can be set from the command line. All settings are optional. The
+/// kind of option depends on the type of the field:
+/// - `bool`: a flag
+/// - `Option<T>`: an optional value
+/// - `Vec<T>`: any number of values
+/// - `Prop<T>`: an optional value, which sets the property if given
+/// - `T`: a required value
+///
+/// Values are parsed with `FromStr` unless a `parse` function is given.
+#[proc_macro_derive(CreateTask, attributes(option))]
 #[proc_macro_error]
 pub fn derive_create_task(item: TokenStream) -> TokenStream {
     let parsed = parse_macro_input!(item as DeriveInput);
@@ -28,7 +41,15 @@ pub fn derive_create_task(item: TokenStream) -> TokenStream {
 }
 
 /// Enables shortcuts for adding inputs and outputs for tasks
-#[proc_macro_derive(TaskIO, attributes(input, output, description))]
+///
+/// Outputs are declared with `#[output]`, which serializes the value of a `Prop`, or:
+/// - `#[output(file)]`, `#[output(files)]` and `#[output(dir)]` for file outputs
+/// - `#[output(file = "expr")]`, `#[output(files = "expr")]` and `#[output(dir = "expr")]` for
+///   outputs computed from the field. The expression can use a clone of the field, bound to its
+///   name, and is evaluated when the outputs are needed.
+/// - `#[output(nested)]` for a field implementing `NestedTaskIO`, which declares its own inputs and
+///   outputs
+#[proc_macro_derive(TaskIO, attributes(input, output, description, option))]
 #[proc_macro_error]
 pub fn derive_io_task(item: TokenStream) -> TokenStream {
     let parsed = parse_macro_input!(item as DeriveInput);
@@ -52,7 +73,77 @@ pub fn derive_io_task(item: TokenStream) -> TokenStream {
     TokenStream::from(TaskIO::derive_task_io(&visitor).unwrap())
 }
 
+/// Implements `UpToDate` with the predicates given by `#[up_to_date(path::to::fn, ...)]`
+/// attributes, which take a reference to the value and return whether it's up-to-date. All
+/// predicates must hold for the value to be up-to-date, and the task must still pass the standard
+/// checks of its inputs and outputs.
+///
+/// # Example
+/// ```ignore
+/// #[derive(Debug, Default, UpToDate)]
+/// #[up_to_date(output_exists)]
+/// struct Download {
+///     destination: Prop<PathBuf>,
+/// }
+///
+/// fn output_exists(task: &Download) -> bool {
+///     task.destination.fallible_get().map_or(false, |path| path.exists())
+/// }
+/// ```
+#[proc_macro_derive(UpToDate, attributes(up_to_date))]
+#[proc_macro_error]
+pub fn derive_up_to_date(item: TokenStream) -> TokenStream {
+    let parsed = parse_macro_input!(item as DeriveInput);
+
+    match derive::up_to_date::UpToDate::derive_up_to_date(&parsed) {
+        Ok(tokens) => TokenStream::from(tokens),
+        Err(e) => TokenStream::from(e.to_compile_error()),
+    }
+}
+
+/// Declares the inputs and outputs of a part of a task, such as a spec nested within it, with the
+/// same attributes as [`TaskIO`](macro@TaskIO). Must be `Clone`, and is included by the task with an
+/// `#[output(nested)]` field.
+#[proc_macro_derive(NestedTaskIO, attributes(input, output))]
+#[proc_macro_error]
+pub fn derive_nested_task_io(item: TokenStream) -> TokenStream {
+    let parsed = parse_macro_input!(item as DeriveInput);
+    let mut visitor = TaskVisitor::new(&parsed.ident, &parsed.generics, None);
+    visitor.visit_derive_input(&parsed);
+
+    match TaskIO::derive_nested_task_io(&visitor) {
+        Ok(tokens) => TokenStream::from(tokens),
+        Err(e) => TokenStream::from(e.to_compile_error()),
+    }
+}
+
+/// Turns a function applying itself to a project into a plugin with the given id.
+///
+/// Creates a plugin type named after the function in upper camel case, and exports the entry point
+/// binary plugins are loaded from. The id is also found by `assemble-build` to create the plugin
+/// descriptor of the crate.
+///
+/// # Example
+/// ```ignore
+/// #[plugin(id = "org.example.greetings")]
+/// pub fn greetings_plugin(project: &mut Project) -> ProjectResult {
+///     project.task_container_mut().register_task::<Empty>("greet")?;
+///     Ok(())
+/// }
+///
+/// project.apply_plugin::<GreetingsPlugin>()?;
+/// ```
 #[proc_macro_attribute]
-pub fn plug(_attr: TokenStream, item: TokenStream) -> TokenStream {
-    item
+#[proc_macro_error]
+pub fn plugin(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as AttributeArgs);
+    let function = parse_macro_input!(item as ItemFn);
+    let args = match PluginArgs::parse(args) {
+        Ok(args) => args,
+        Err(e) => return TokenStream::from(e.to_compile_error()),
+    };
+
+    PluginTokenizer::new(args, function)
+        .into_token_stream()
+        .into()
 }
@@ -0,0 +1,129 @@
+//! Creates plugins from functions
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, ToTokens};
+use syn::{AttributeArgs, Ident, ItemFn, Lit, Meta, NestedMeta};
+
+/// The settings of a `#[plugin]` attribute
+#[derive(Debug)]
+pub struct PluginArgs {
+    id: String,
+}
+
+impl PluginArgs {
+    pub fn parse(args: AttributeArgs) -> syn::Result<Self> {
+        let mut id = None;
+        for arg in args {
+            match arg {
+                NestedMeta::Meta(Meta::NameValue(assign))
+                    if assign.path.is_ident("id") || assign.path.is_ident("plugin_id") =>
+                {
+                    match assign.lit {
+                        Lit::Str(s) => id = Some(s.value()),
+                        other => return Err(syn::Error::new_spanned(other, "id must be a string")),
+                    }
+                }
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "not a valid setting for plugins",
+                    ))
+                }
+            }
+        }
+        let id = id.ok_or_else(|| {
+            syn::Error::new(
+                Span::call_site(),
+                "plugins need an id, like #[plugin(id = \"my.plugin\")]",
+            )
+        })?;
+        Ok(Self { id })
+    }
+}
+
+/// Turns a function applying itself to a project into a plugin
+pub struct PluginTokenizer {
+    id: String,
+    function: ItemFn,
+}
+
+impl PluginTokenizer {
+    pub fn new(args: PluginArgs, function: ItemFn) -> Self {
+        Self {
+            id: args.id,
+            function,
+        }
+    }
+
+    /// The type created for the plugin, which is the name of the function in upper camel case
+    fn plugin_type(&self) -> Ident {
+        let name = self.function.sig.ident.to_string();
+        let camel: String = name
+            .split('_')
+            .filter(|part| !part.is_empty())
+            .map(|part| {
+                let mut chars = part.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                    .unwrap_or_default()
+            })
+            .collect();
+        Ident::new(&camel, self.function.sig.ident.span())
+    }
+
+    /// Must match `assemble_core::plugins::binary::plugin_symbol`
+    fn symbol(&self) -> String {
+        let id: String = self
+            .id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        format!("__assemble_plugin_{}", id)
+    }
+}
+
+impl ToTokens for PluginTokenizer {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let function = &self.function;
+        let function_name = &function.sig.ident;
+        let vis = &function.vis;
+        let id = &self.id;
+        let plugin_type = self.plugin_type();
+        let symbol = self.symbol();
+        let register = format_ident!("{}", symbol);
+        let api = format_ident!("{}_api", symbol);
+        let doc = format!("The `{}` plugin, applied by [`{}`]", id, function_name);
+
+        tokens.extend(quote! {
+            #function
+
+            #[doc = #doc]
+            #[derive(Debug, Default, Clone, Copy)]
+            #vis struct #plugin_type;
+
+            impl assemble_core::plugins::Plugin<assemble_core::Project> for #plugin_type {
+                fn apply_to(&self, project: &mut assemble_core::Project) -> assemble_core::project::error::ProjectResult {
+                    #function_name(project)
+                }
+
+                fn plugin_id(&self) -> &str {
+                    #id
+                }
+            }
+
+            #[doc(hidden)]
+            #[no_mangle]
+            pub extern "C" fn #api() -> *const ::std::os::raw::c_char {
+                assemble_core::plugins::binary::PluginApi::current_fingerprint()
+            }
+
+            #[doc(hidden)]
+            #[no_mangle]
+            #[allow(improper_ctypes_definitions)]
+            pub extern "C" fn #register(registrar: &mut assemble_core::plugins::binary::PluginRegistrar) {
+                registrar.register::<#plugin_type>(#id);
+            }
+        });
+    }
+}
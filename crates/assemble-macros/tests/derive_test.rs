@@ -1,5 +1,6 @@
 use assemble_core::__export::Serializable;
 use assemble_core::file_collection::FileSet;
+use assemble_core::identifier::TaskId;
 use assemble_core::lazy_evaluation::{Prop, Provider};
 
 use assemble_core::task::initialize_task::InitializeTask;
@@ -7,7 +8,7 @@ use assemble_core::task::task_io::TaskIO;
 use assemble_core::task::up_to_date::UpToDate;
 use assemble_core::task::work_handler::output::Output;
 use assemble_core::{BuildResult, Executable, Project, Task};
-use assemble_macros::TaskIO;
+use assemble_macros::{NestedTaskIO, TaskIO, UpToDate};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
@@ -44,3 +45,96 @@ fn can_reuse_basic_output() {
         "Should be set to 15 after output recovered"
     );
 }
+
+#[test]
+fn can_recover_nested_outputs() {
+    #[derive(Debug, Default, Clone, NestedTaskIO)]
+    struct LineSpec {
+        #[input]
+        pattern: Prop<String>,
+        #[output]
+        lines: Prop<usize>,
+    }
+
+    #[derive(Debug, Default, TaskIO)]
+    struct CountMatchingLines {
+        #[output(nested)]
+        spec: LineSpec,
+        #[output(file = "report_dir.get().join(\"report.txt\")")]
+        report_dir: Prop<PathBuf>,
+    }
+
+    impl UpToDate for CountMatchingLines {}
+    impl InitializeTask for CountMatchingLines {}
+
+    impl Task for CountMatchingLines {
+        fn task_action(_task: &mut Executable<Self>, _project: &Project) -> BuildResult {
+            todo!()
+        }
+    }
+
+    let mut mapping = HashMap::new();
+    mapping.insert("spec.lines".to_string(), Serializable::new(3).unwrap());
+    let output = &Output::new(FileSet::new(), mapping);
+
+    let mut task = CountMatchingLines::default();
+    task.recover_outputs(output).unwrap();
+    assert_eq!(task.spec.lines.get(), 3);
+}
+
+#[test]
+fn custom_up_to_date_predicates() {
+    #[derive(Debug, Default, UpToDate)]
+    #[up_to_date(has_lines, not_too_many)]
+    struct CountLines {
+        lines: usize,
+    }
+
+    fn has_lines(task: &CountLines) -> bool {
+        task.lines > 0
+    }
+
+    fn not_too_many(task: &CountLines) -> bool {
+        task.lines < 10
+    }
+
+    let mut count_lines = CountLines::default();
+    assert!(!count_lines.up_to_date());
+    count_lines.lines = 5;
+    assert!(count_lines.up_to_date());
+    count_lines.lines = 15;
+    assert!(!count_lines.up_to_date());
+}
+
+#[test]
+fn input_props_are_finalized_on_execute() {
+    #[derive(Debug, Default, TaskIO)]
+    struct Greet {
+        #[input]
+        name: Prop<String>,
+    }
+
+    impl UpToDate for Greet {}
+    impl InitializeTask for Greet {}
+
+    impl Task for Greet {
+        fn task_action(_task: &mut Executable<Self>, _project: &Project) -> BuildResult {
+            Ok(())
+        }
+    }
+
+    let project = Project::temp(None);
+    let mut task = Executable::new(
+        project,
+        Greet::default(),
+        TaskId::new(":root:greet").unwrap(),
+    );
+    task.name.set("world").unwrap();
+    task.configure_io().unwrap();
+    assert!(!task.name.is_final());
+
+    task.work().finalize_inputs().unwrap();
+    assert!(task.name.is_final());
+    assert!(task.name.set("everyone").is_err());
+    assert_eq!(task.name.get(), "world");
+}
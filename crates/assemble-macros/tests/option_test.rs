@@ -0,0 +1,73 @@
+use assemble_core::identifier::TaskId;
+use assemble_core::lazy_evaluation::{Prop, Provider};
+use assemble_core::task::create_task::CreateTask;
+use assemble_core::task::initialize_task::InitializeTask;
+use assemble_core::task::task_io::TaskIO;
+use assemble_core::task::up_to_date::UpToDate;
+use assemble_core::{BuildResult, Executable, Project, Task};
+use assemble_macros::CreateTask;
+use std::num::ParseIntError;
+
+#[derive(Debug, CreateTask)]
+struct Greet {
+    /// Greets loudly
+    #[option]
+    loud: bool,
+    #[option(name = "times", help = "How many times to greet")]
+    repeat: Option<u32>,
+    /// Who to greet
+    #[option]
+    names: Vec<String>,
+    #[option(parse = parse_hex)]
+    color: Prop<u32>,
+    unused: usize,
+}
+
+fn parse_hex(value: &str) -> Result<u32, ParseIntError> {
+    u32::from_str_radix(value.trim_start_matches('#'), 16)
+}
+
+impl UpToDate for Greet {}
+impl InitializeTask for Greet {}
+impl TaskIO for Greet {}
+
+impl Task for Greet {
+    fn task_action(_task: &mut Executable<Self>, _project: &Project) -> BuildResult {
+        Ok(())
+    }
+}
+
+#[test]
+fn options_declared_from_fields() {
+    let declarations = Greet::options_declarations().expect("options declared");
+    assert!(declarations["loud"].is_flag());
+    assert_eq!(declarations["loud"].help(), "Greets loudly");
+    assert!(declarations["times"].optional());
+    assert_eq!(declarations["times"].help(), "How many times to greet");
+    assert!(declarations["names"].allow_multiple_values());
+    assert!(declarations["color"].takes_value());
+    assert!(!declarations.contains_key("unused"));
+}
+
+#[test]
+fn options_set_from_decoder() {
+    let project = Project::temp(None);
+    let id = TaskId::new(":root:greet").unwrap();
+    let mut greet = project.with(|p| Greet::new(&id, p)).unwrap();
+
+    let declarations = Greet::options_declarations().unwrap();
+    let (weak, _) = declarations
+        .slurper()
+        .slurp(&[
+            "--loud", "--times", "3", "--names", "a", "--names", "b", "--color", "#ff",
+        ])
+        .unwrap();
+    let decoder = weak.upgrade(&declarations).unwrap();
+    greet.try_set_from_decoder(&decoder).unwrap();
+
+    assert!(greet.loud);
+    assert_eq!(greet.repeat, Some(3));
+    assert_eq!(greet.names, ["a", "b"]);
+    assert_eq!(greet.color.get(), 255);
+    assert_eq!(greet.unused, 0);
+}
@@ -0,0 +1,49 @@
+use assemble_core::defaults::tasks::Empty;
+use assemble_core::identifier::TaskId;
+use assemble_core::plugins::binary::{plugin_symbol, PluginApi, PluginRegistrar};
+use assemble_core::plugins::PluginAware;
+use assemble_core::project::error::ProjectResult;
+use assemble_core::Project;
+use assemble_macros::plugin;
+use std::ffi::CStr;
+
+#[plugin(id = "org.example.greetings")]
+fn greetings_plugin(project: &mut Project) -> ProjectResult {
+    project
+        .task_container_mut()
+        .register_task::<Empty>("greet")?;
+    Ok(())
+}
+
+#[test]
+fn plugin_is_applied_by_id() {
+    let project = Project::temp(None);
+    project
+        .with_mut(|p| p.apply_plugin::<GreetingsPlugin>())
+        .unwrap();
+    assert!(project.with(|p| p.plugin_manager().has_plugin("org.example.greetings")));
+    assert!(project
+        .get_task(&TaskId::new(":root:greet").unwrap())
+        .is_ok());
+}
+
+#[test]
+fn plugin_exports_entry_points() {
+    assert_eq!(
+        plugin_symbol("org.example.greetings"),
+        "__assemble_plugin_org_example_greetings"
+    );
+
+    let api = unsafe { CStr::from_ptr(__assemble_plugin_org_example_greetings_api()) };
+    assert_eq!(
+        PluginApi::parse(api.to_str().unwrap()),
+        Some(PluginApi::current())
+    );
+
+    let mut registrar = PluginRegistrar::default();
+    __assemble_plugin_org_example_greetings(&mut registrar);
+    assert_eq!(
+        registrar.ids().collect::<Vec<_>>(),
+        ["org.example.greetings"]
+    );
+}
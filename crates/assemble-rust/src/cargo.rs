@@ -1,7 +1,11 @@
 //! Run cargo commands
 
 pub mod build;
+pub mod clippy;
+pub mod fmt;
+pub mod metadata;
 pub mod publish;
+pub mod test;
 
 /// The target for a cargo command. This can either be packages, the whole workspace, the lib, tests, bins,
 /// or examples
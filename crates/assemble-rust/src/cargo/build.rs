@@ -23,6 +23,7 @@ impl InitializeTask for CargoFmt {
     fn initialize(task: &mut Executable<Self>, project: &Project) -> ProjectResult {
         let ext = project.extension::<RustPluginExtension>().unwrap();
         task.toolchain.set_with(ext.toolchain.clone())?;
+        task.depends_on(ext.sysroot.clone());
         Ok(())
     }
 }
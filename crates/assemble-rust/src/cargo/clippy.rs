@@ -0,0 +1,251 @@
+//! Lint a rust project with clippy
+
+use crate::extensions::RustPluginExtension;
+use crate::prelude::*;
+use crate::toolchain::Toolchain;
+use assemble_core::error::PayloadError;
+use assemble_core::exception::BuildException;
+use assemble_core::identifier::TaskId;
+use assemble_core::lazy_evaluation::{Prop, Provider};
+use assemble_core::plugins::extensions::ExtensionAware;
+use assemble_core::project::error::ProjectResult;
+use assemble_core::task::create_task::CreateTask;
+use assemble_core::task::flags::{OptionDeclarationBuilder, OptionDeclarations, OptionsDecoder};
+use assemble_core::task::initialize_task::InitializeTask;
+use assemble_core::task::task_io::TaskIO;
+use assemble_core::task::up_to_date::UpToDate;
+use assemble_std::specs::exec_spec::Output;
+use assemble_std::ProjectExec;
+use log::Level;
+use std::fmt::{Display, Formatter};
+
+/// Runs `cargo clippy` on a rust project.
+///
+/// Diagnostics are read from cargo's json messages, and logged with the file and line they point
+/// to. The task fails if clippy reports any errors, or any warnings when
+/// [`deny_warnings`](Clippy::deny_warnings) is set.
+///
+/// # Options
+/// - `--fix`: automatically apply lint suggestions
+#[derive(Debug)]
+pub struct Clippy {
+    /// The toolchain to run clippy with
+    pub toolchain: Prop<Toolchain>,
+    /// Whether lint suggestions should be applied. Defaults to false.
+    pub fix: Prop<bool>,
+    /// Whether warnings should fail the task. Defaults to false.
+    pub deny_warnings: Prop<bool>,
+}
+
+impl CreateTask for Clippy {
+    fn new(using_id: &TaskId, _project: &Project) -> ProjectResult<Self> {
+        Ok(Self {
+            toolchain: using_id.prop("toolchain").map_err(PayloadError::new)?,
+            fix: using_id.prop("fix").map_err(PayloadError::new)?,
+            deny_warnings: using_id.prop("deny_warnings").map_err(PayloadError::new)?,
+        })
+    }
+
+    fn description() -> String {
+        "Checks a rust project for common mistakes with clippy".to_string()
+    }
+
+    fn options_declarations() -> Option<OptionDeclarations> {
+        Some(OptionDeclarations::new::<Self, _>([
+            OptionDeclarationBuilder::flag("fix")
+                .help("Automatically apply lint suggestions")
+                .build(),
+        ]))
+    }
+
+    fn try_set_from_decoder(&mut self, decoder: &OptionsDecoder) -> ProjectResult<()> {
+        if decoder.flag_present("fix").map_err(PayloadError::new)? {
+            self.fix.set(true)?;
+        }
+        Ok(())
+    }
+}
+
+impl TaskIO for Clippy {}
+
+impl InitializeTask for Clippy {
+    fn initialize(task: &mut Executable<Self>, project: &Project) -> ProjectResult {
+        let ext = project.extension::<RustPluginExtension>()?;
+        task.toolchain.set_with(ext.toolchain.clone())?;
+        task.depends_on(ext.sysroot.clone());
+        task.fix.set(false)?;
+        task.deny_warnings.set(false)?;
+        Ok(())
+    }
+}
+
+impl UpToDate for Clippy {
+    /// Clippy is always run, cargo decides whether anything needs to be checked again
+    fn up_to_date(&self) -> bool {
+        false
+    }
+}
+
+impl Task for Clippy {
+    fn task_action(task: &mut Executable<Self>, project: &Project) -> BuildResult {
+        let toolchain = task.toolchain.fallible_get()?;
+        let fix = task.fix.fallible_get()?;
+        let deny_warnings = task.deny_warnings.fallible_get()?;
+
+        let result = project.exec_with(|exec| {
+            exec.exec("cargo")
+                .arg(format!("+{}", toolchain))
+                .args(["clippy", "--message-format=json"]);
+            if fix {
+                exec.args(["--fix", "--allow-dirty", "--allow-staged"]);
+            }
+            exec.stdout(Output::Bytes).stderr(Level::Debug);
+        })?;
+
+        let stdout = result
+            .utf8_string()
+            .unwrap_or_else(|| Ok(String::new()))
+            .map_err(PayloadError::<BuildException>::new)?;
+        let diagnostics = parse_diagnostics(&stdout);
+        let mut errors = 0;
+        let mut warnings = 0;
+        for diagnostic in &diagnostics {
+            match diagnostic.level.as_str() {
+                "error" | "error: internal compiler error" => {
+                    errors += 1;
+                    error!("{}", diagnostic);
+                }
+                "warning" => {
+                    warnings += 1;
+                    warn!("{}", diagnostic);
+                }
+                _ => info!("{}", diagnostic),
+            }
+        }
+        info!("clippy found {} error(s), {} warning(s)", errors, warnings);
+
+        if errors > 0 || !result.success() {
+            return Err(
+                BuildException::custom(&format!("clippy failed with {} error(s)", errors)).into(),
+            );
+        }
+        if deny_warnings && warnings > 0 {
+            return Err(BuildException::custom(&format!(
+                "clippy found {} warning(s), and warnings are denied",
+                warnings
+            ))
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// A diagnostic emitted by the compiler
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Diagnostic {
+    /// The level of the diagnostic, such as `warning` or `error`
+    pub level: String,
+    /// The message of the diagnostic
+    pub message: String,
+    /// The lint or error code of the diagnostic, if any
+    pub code: Option<String>,
+    /// The file the diagnostic points to, if any
+    pub file: Option<String>,
+    /// The line the diagnostic points to, if any
+    pub line: Option<usize>,
+    /// The column the diagnostic points to, if any
+    pub column: Option<usize>,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}", file)?;
+            if let Some(line) = self.line {
+                write!(f, ":{}", line)?;
+                if let Some(column) = self.column {
+                    write!(f, ":{}", column)?;
+                }
+            }
+            write!(f, ": ")?;
+        }
+        write!(f, "{}: {}", self.level, self.message)?;
+        if let Some(code) = &self.code {
+            write!(f, " [{}]", code)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoMessage {
+    reason: String,
+    message: Option<CompilerMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompilerMessage {
+    level: String,
+    message: String,
+    code: Option<CompilerCode>,
+    #[serde(default)]
+    spans: Vec<CompilerSpan>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompilerCode {
+    code: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompilerSpan {
+    file_name: String,
+    line_start: usize,
+    column_start: usize,
+    is_primary: bool,
+}
+
+/// Parses the compiler diagnostics out of cargo's json messages. Summary diagnostics, like the
+/// number of warnings emitted, are skipped.
+pub fn parse_diagnostics(output: &str) -> Vec<Diagnostic> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<CargoMessage>(line).ok())
+        .filter(|message| message.reason == "compiler-message")
+        .filter_map(|message| message.message)
+        .filter(|message| !message.spans.is_empty() || message.code.is_some())
+        .map(|message| {
+            let span = message.spans.iter().find(|span| span.is_primary);
+            Diagnostic {
+                level: message.level,
+                message: message.message,
+                code: message.code.map(|code| code.code),
+                file: span.map(|span| span.file_name.clone()),
+                line: span.map(|span| span.line_start),
+                column: span.map(|span| span.column_start),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_clippy_messages() {
+        let output = r#"{"reason":"compiler-artifact","package_id":"app 0.1.0"}
+{"reason":"compiler-message","message":{"level":"warning","message":"redundant clone","code":{"code":"clippy::redundant_clone"},"spans":[{"file_name":"src/lib.rs","line_start":4,"column_start":13,"is_primary":true}]}}
+{"reason":"compiler-message","message":{"level":"error","message":"mismatched types","code":{"code":"E0308"},"spans":[{"file_name":"src/main.rs","line_start":2,"column_start":5,"is_primary":false},{"file_name":"src/main.rs","line_start":3,"column_start":9,"is_primary":true}]}}
+{"reason":"compiler-message","message":{"level":"warning","message":"1 warning emitted","code":null,"spans":[]}}
+{"reason":"build-finished","success":false}"#;
+        let diagnostics = parse_diagnostics(output);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "src/lib.rs:4:13: warning: redundant clone [clippy::redundant_clone]"
+        );
+        assert_eq!(diagnostics[1].level, "error");
+        assert_eq!(diagnostics[1].line, Some(3));
+    }
+}
@@ -0,0 +1,196 @@
+//! Format a rust project with rustfmt
+
+use crate::extensions::RustPluginExtension;
+use crate::prelude::*;
+use crate::toolchain::Toolchain;
+use assemble_core::error::PayloadError;
+use assemble_core::exception::BuildException;
+use assemble_core::identifier::TaskId;
+use assemble_core::lazy_evaluation::{Prop, Provider};
+use assemble_core::plugins::extensions::ExtensionAware;
+use assemble_core::project::error::ProjectResult;
+use assemble_core::task::create_task::CreateTask;
+use assemble_core::task::flags::{OptionDeclarationBuilder, OptionDeclarations, OptionsDecoder};
+use assemble_core::task::initialize_task::InitializeTask;
+use assemble_core::task::task_io::TaskIO;
+use assemble_core::task::up_to_date::UpToDate;
+use assemble_std::specs::exec_spec::Output;
+use assemble_std::ProjectExec;
+use log::Level;
+
+/// Runs `cargo fmt` on a rust project.
+///
+/// By default files are formatted in place. When [`check`](RustFmt::check) is set, files are only
+/// checked, every unformatted line is logged with its file and line, and the task fails if any file
+/// isn't formatted.
+///
+/// # Options
+/// - `--check`: only check whether files are formatted
+#[derive(Debug)]
+pub struct RustFmt {
+    /// The toolchain to run rustfmt with
+    pub toolchain: Prop<Toolchain>,
+    /// Whether files should only be checked. Defaults to false.
+    pub check: Prop<bool>,
+}
+
+impl CreateTask for RustFmt {
+    fn new(using_id: &TaskId, _project: &Project) -> ProjectResult<Self> {
+        Ok(Self {
+            toolchain: using_id.prop("toolchain").map_err(PayloadError::new)?,
+            check: using_id.prop("check").map_err(PayloadError::new)?,
+        })
+    }
+
+    fn description() -> String {
+        "Formats a rust project with rustfmt".to_string()
+    }
+
+    fn options_declarations() -> Option<OptionDeclarations> {
+        Some(OptionDeclarations::new::<Self, _>([
+            OptionDeclarationBuilder::flag("check")
+                .help("Only check whether files are formatted")
+                .build(),
+        ]))
+    }
+
+    fn try_set_from_decoder(&mut self, decoder: &OptionsDecoder) -> ProjectResult<()> {
+        if decoder.flag_present("check").map_err(PayloadError::new)? {
+            self.check.set(true)?;
+        }
+        Ok(())
+    }
+}
+
+impl TaskIO for RustFmt {}
+
+impl InitializeTask for RustFmt {
+    fn initialize(task: &mut Executable<Self>, project: &Project) -> ProjectResult {
+        let ext = project.extension::<RustPluginExtension>()?;
+        task.toolchain.set_with(ext.toolchain.clone())?;
+        task.depends_on(ext.sysroot.clone());
+        task.check.set(false)?;
+        Ok(())
+    }
+}
+
+impl UpToDate for RustFmt {
+    /// Sources can change at any time, so rustfmt is always run
+    fn up_to_date(&self) -> bool {
+        false
+    }
+}
+
+impl Task for RustFmt {
+    fn task_action(task: &mut Executable<Self>, project: &Project) -> BuildResult {
+        let toolchain = task.toolchain.fallible_get()?;
+        let check = task.check.fallible_get()?;
+
+        let result = project.exec_with(|exec| {
+            exec.exec("cargo")
+                .arg(format!("+{}", toolchain))
+                .args(["fmt", "--all"]);
+            if check {
+                exec.args(["--check", "--message-format", "json"]);
+            }
+            exec.stdout(Output::Bytes).stderr(Level::Info);
+        })?;
+
+        if !check {
+            if !result.success() {
+                return Err(BuildException::custom("rustfmt failed to format files").into());
+            }
+            return Ok(());
+        }
+
+        let stdout = result
+            .utf8_string()
+            .unwrap_or_else(|| Ok(String::new()))
+            .map_err(PayloadError::<BuildException>::new)?;
+        let unformatted = parse_mismatches(&stdout);
+        for mismatch in &unformatted {
+            warn!(
+                "{}:{}: not formatted, expected:\n{}",
+                mismatch.file, mismatch.line, mismatch.expected
+            );
+        }
+
+        if !unformatted.is_empty() {
+            let mut files = unformatted
+                .iter()
+                .map(|mismatch| mismatch.file.as_str())
+                .collect::<Vec<_>>();
+            files.dedup();
+            return Err(BuildException::custom(&format!(
+                "{} file(s) are not formatted. Run rustfmt without --check to format them",
+                files.len()
+            ))
+            .into());
+        }
+        if !result.success() {
+            return Err(BuildException::custom("rustfmt failed to check files").into());
+        }
+        Ok(())
+    }
+}
+
+/// A region of a file that isn't formatted
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FormatMismatch {
+    /// The file
+    pub file: String,
+    /// The first line of the region in the original file
+    pub line: usize,
+    /// The region as rustfmt would format it
+    pub expected: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FileMismatches {
+    name: String,
+    mismatches: Vec<Mismatch>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Mismatch {
+    original_begin_line: usize,
+    expected: String,
+}
+
+/// Parses rustfmt's json output. rustfmt prints one json array for every crate it checks.
+pub fn parse_mismatches(output: &str) -> Vec<FormatMismatch> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Vec<FileMismatches>>(line).ok())
+        .flatten()
+        .flat_map(|file| {
+            let name = file.name;
+            file.mismatches
+                .into_iter()
+                .map(move |mismatch| FormatMismatch {
+                    file: name.clone(),
+                    line: mismatch.original_begin_line,
+                    expected: mismatch.expected,
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rustfmt_json() {
+        let output = r#"[{"name":"/app/src/lib.rs","mismatches":[{"original_begin_line":3,"original_end_line":4,"expected_begin_line":3,"expected_end_line":3,"original":"fn a( ) {\n}","expected":"fn a() {}"}]}]
+[]"#;
+        assert_eq!(
+            parse_mismatches(output),
+            vec![FormatMismatch {
+                file: "/app/src/lib.rs".to_string(),
+                line: 3,
+                expected: "fn a() {}".to_string(),
+            }]
+        );
+    }
+}
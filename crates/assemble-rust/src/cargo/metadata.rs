@@ -0,0 +1,152 @@
+//! Read the layout of a cargo workspace with `cargo metadata`
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The layout of a cargo workspace, as reported by `cargo metadata --no-deps`
+#[derive(Debug, Clone, Deserialize)]
+pub struct CargoMetadata {
+    /// The packages of the workspace
+    pub packages: Vec<Package>,
+    /// The ids of the packages that are members of the workspace
+    pub workspace_members: Vec<String>,
+    /// The root directory of the workspace
+    pub workspace_root: PathBuf,
+    /// The directory build artifacts are written to
+    pub target_directory: PathBuf,
+}
+
+/// A package in a cargo workspace
+#[derive(Debug, Clone, Deserialize)]
+pub struct Package {
+    /// The name of the package
+    pub name: String,
+    /// The version of the package
+    pub version: String,
+    /// The unique id of the package
+    pub id: String,
+    /// The path to the `Cargo.toml` of the package
+    pub manifest_path: PathBuf,
+    /// The targets of the package
+    #[serde(default)]
+    pub targets: Vec<PackageTarget>,
+}
+
+impl Package {
+    /// The directory containing the package
+    pub fn directory(&self) -> &Path {
+        self.manifest_path
+            .parent()
+            .expect("manifest path always has a parent")
+    }
+
+    /// Whether any target of this package has a kind, such as `lib` or `bin`
+    pub fn has_target_kind(&self, kind: &str) -> bool {
+        self.targets
+            .iter()
+            .any(|target| target.kind.iter().any(|k| k == kind))
+    }
+}
+
+/// A target of a package, such as a library or a binary
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackageTarget {
+    /// The name of the target
+    pub name: String,
+    /// The kinds of the target, such as `lib`, `bin`, `test` or `example`
+    pub kind: Vec<String>,
+}
+
+/// An error occurred while reading cargo metadata
+#[derive(Debug, thiserror::Error)]
+pub enum MetadataError {
+    /// cargo couldn't be run
+    #[error("could not run cargo metadata: {0}")]
+    Io(#[from] std::io::Error),
+    /// cargo ran, but failed
+    #[error("cargo metadata failed: {0}")]
+    Cargo(String),
+    /// The output of cargo couldn't be parsed
+    #[error("could not parse cargo metadata: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl CargoMetadata {
+    /// Runs `cargo metadata` in a directory, without resolving dependencies
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self, MetadataError> {
+        let output = Command::new("cargo")
+            .args(["metadata", "--format-version", "1", "--no-deps"])
+            .current_dir(dir)
+            .output()?;
+        if !output.status.success() {
+            return Err(MetadataError::Cargo(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        Self::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Parses the json output of `cargo metadata`
+    pub fn parse(json: &str) -> Result<Self, MetadataError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// The packages that are members of the workspace
+    pub fn workspace_packages(&self) -> impl Iterator<Item = &Package> {
+        self.packages
+            .iter()
+            .filter(|package| self.workspace_members.contains(&package.id))
+    }
+
+    /// The workspace member in a directory, if any
+    pub fn package_in<P: AsRef<Path>>(&self, dir: P) -> Option<&Package> {
+        let dir = dir.as_ref();
+        self.workspace_packages()
+            .find(|package| package.directory() == dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METADATA: &str = r#"{
+  "packages": [
+    {
+      "name": "app",
+      "version": "0.1.0",
+      "id": "app 0.1.0 (path+file:///ws/app)",
+      "manifest_path": "/ws/app/Cargo.toml",
+      "targets": [{ "name": "app", "kind": ["bin"], "src_path": "/ws/app/src/main.rs" }]
+    },
+    {
+      "name": "core-lib",
+      "version": "0.2.0",
+      "id": "core-lib 0.2.0 (path+file:///ws/crates/core-lib)",
+      "manifest_path": "/ws/crates/core-lib/Cargo.toml",
+      "targets": [{ "name": "core_lib", "kind": ["lib"], "src_path": "/ws/crates/core-lib/src/lib.rs" }]
+    }
+  ],
+  "workspace_members": [
+    "app 0.1.0 (path+file:///ws/app)",
+    "core-lib 0.2.0 (path+file:///ws/crates/core-lib)"
+  ],
+  "resolve": null,
+  "target_directory": "/ws/target",
+  "version": 1,
+  "workspace_root": "/ws"
+}"#;
+
+    #[test]
+    fn parse_workspace() {
+        let metadata = CargoMetadata::parse(METADATA).unwrap();
+        assert_eq!(metadata.workspace_root, PathBuf::from("/ws"));
+        assert_eq!(metadata.workspace_packages().count(), 2);
+
+        let core = metadata.package_in("/ws/crates/core-lib").unwrap();
+        assert_eq!(core.name, "core-lib");
+        assert!(core.has_target_kind("lib"));
+        assert!(!core.has_target_kind("bin"));
+        assert!(metadata.package_in("/ws").is_none());
+    }
+}
@@ -0,0 +1,267 @@
+//! Test a rust project
+
+use crate::extensions::RustPluginExtension;
+use crate::prelude::*;
+use crate::toolchain::Toolchain;
+use assemble_core::error::PayloadError;
+use assemble_core::exception::BuildException;
+use assemble_core::identifier::TaskId;
+use assemble_core::lazy_evaluation::{Prop, Provider, ProviderExt, VecProp};
+use assemble_core::plugins::extensions::ExtensionAware;
+use assemble_core::project::error::ProjectResult;
+use assemble_core::task::create_task::CreateTask;
+use assemble_core::task::flags::{OptionDeclarationBuilder, OptionDeclarations, OptionsDecoder};
+use assemble_core::task::initialize_task::InitializeTask;
+use assemble_core::task::task_io::TaskIO;
+use assemble_core::task::test_results::{
+    record_test_results, TestCaseResult, TestOutcome, TestResults,
+};
+use assemble_core::task::up_to_date::UpToDate;
+use assemble_core::task::HasTaskId;
+use assemble_std::specs::exec_spec::Output;
+use assemble_std::ProjectExec;
+use log::Level;
+use std::path::PathBuf;
+
+/// The directory test results are written to, relative to the build directory
+pub const TEST_RESULTS_DIR: &str = "test-results";
+
+/// The name of the results file written by a [`CargoTest`](CargoTest) task
+pub const TEST_RESULTS_FILE: &str = "test-results.json";
+
+/// Runs the tests of a rust project with `cargo test`, or with `cargo nextest` if it's installed.
+///
+/// Tests are run with libtest's json output, and the result of every test is recorded with
+/// [`record_test_results`](record_test_results) and written to
+/// [`results_file`](CargoTest::results_file). Failed tests are logged, and fail the task.
+///
+/// Json output is unstable in libtest, so `cargo test` is run with `RUSTC_BOOTSTRAP=1`.
+///
+/// # Options
+/// - `--tests <filter>`: only run tests whose name contains the filter. Can be given more than once.
+#[derive(Debug)]
+pub struct CargoTest {
+    /// The toolchain to run the tests with
+    pub toolchain: Prop<Toolchain>,
+    /// Only tests whose names contain one of these filters are run. All tests are run if empty.
+    pub filters: VecProp<String>,
+    /// Whether to run tests with `cargo nextest`. Defaults to whether `cargo-nextest` is installed.
+    pub use_nextest: Prop<bool>,
+    /// The file the results of the tests are written to. Defaults to
+    /// `build/test-results/<task name>/test-results.json`.
+    pub results_file: Prop<PathBuf>,
+}
+
+impl CreateTask for CargoTest {
+    fn new(using_id: &TaskId, _project: &Project) -> ProjectResult<Self> {
+        Ok(Self {
+            toolchain: using_id.prop("toolchain").map_err(PayloadError::new)?,
+            filters: using_id.vec_prop("filters").map_err(PayloadError::new)?,
+            use_nextest: using_id.prop("use_nextest").map_err(PayloadError::new)?,
+            results_file: using_id.prop("results_file").map_err(PayloadError::new)?,
+        })
+    }
+
+    fn description() -> String {
+        "Runs the tests of a rust project".to_string()
+    }
+
+    fn options_declarations() -> Option<OptionDeclarations> {
+        Some(OptionDeclarations::new::<Self, _>([
+            OptionDeclarationBuilder::<String>::new("tests")
+                .help("Only run tests whose name contains this filter")
+                .allow_multiple_values(true)
+                .optional(true)
+                .use_from_str()
+                .build(),
+        ]))
+    }
+
+    fn try_set_from_decoder(&mut self, decoder: &OptionsDecoder) -> ProjectResult<()> {
+        if let Some(filters) = decoder
+            .get_values::<String>("tests")
+            .map_err(PayloadError::new)?
+        {
+            self.filters.push_all(filters);
+        }
+        Ok(())
+    }
+}
+
+impl TaskIO for CargoTest {
+    fn configure_io(task: &mut Executable<Self>) -> ProjectResult {
+        let results_file = task.results_file.clone();
+        task.work().add_output_provider(results_file);
+        Ok(())
+    }
+}
+
+impl InitializeTask for CargoTest {
+    fn initialize(task: &mut Executable<Self>, project: &Project) -> ProjectResult {
+        let ext = project.extension::<RustPluginExtension>()?;
+        task.toolchain.set_with(ext.toolchain.clone())?;
+        task.depends_on(ext.sysroot.clone());
+        task.use_nextest
+            .set(which::which("cargo-nextest").is_ok())?;
+        let task_name = task.task_id().this().to_string();
+        task.results_file
+            .set_with(project.build_dir().map(move |build_dir| {
+                build_dir
+                    .join(TEST_RESULTS_DIR)
+                    .join(&task_name)
+                    .join(TEST_RESULTS_FILE)
+            }))?;
+        Ok(())
+    }
+}
+
+impl UpToDate for CargoTest {
+    /// Tests are always run
+    fn up_to_date(&self) -> bool {
+        false
+    }
+}
+
+impl Task for CargoTest {
+    fn task_action(task: &mut Executable<Self>, project: &Project) -> BuildResult {
+        let toolchain = task.toolchain.fallible_get()?;
+        let filters = task.filters.fallible_get()?;
+        let use_nextest = task.use_nextest.fallible_get()?;
+        let results_file = task.results_file.fallible_get()?;
+
+        let result = project.exec_with(|exec| {
+            exec.exec("cargo").arg(format!("+{}", toolchain));
+            if use_nextest {
+                exec.args(["nextest", "run", "--no-fail-fast"])
+                    .args(["--message-format", "libtest-json"])
+                    .add_env("NEXTEST_EXPERIMENTAL_LIBTEST_JSON", "1")
+                    .args(&filters);
+            } else {
+                exec.args(["test", "--no-fail-fast", "--"])
+                    .args([
+                        "-Z",
+                        "unstable-options",
+                        "--format",
+                        "json",
+                        "--report-time",
+                    ])
+                    .add_env("RUSTC_BOOTSTRAP", "1")
+                    .args(&filters);
+            }
+            exec.stdout(Output::Bytes).stderr(Level::Info);
+        })?;
+
+        let stdout = result
+            .utf8_string()
+            .unwrap_or_else(|| Ok(String::new()))
+            .map_err(PayloadError::<BuildException>::new)?;
+        let results = parse_libtest_json(&stdout);
+        results
+            .write_to(&results_file)
+            .map_err(PayloadError::<BuildException>::new)?;
+
+        let failures = results.failures().collect::<Vec<_>>();
+        for failure in &failures {
+            error!("test {} failed", failure.name);
+            if let Some(output) = &failure.output {
+                for line in output.lines() {
+                    error!("  {}", line);
+                }
+            }
+        }
+        info!(
+            "{} passed, {} failed, {} ignored",
+            results.count(TestOutcome::Passed),
+            failures.len(),
+            results.count(TestOutcome::Ignored)
+        );
+        let failed = failures.len();
+        record_test_results(&task.task_id(), results);
+
+        if failed > 0 {
+            return Err(BuildException::custom(&format!(
+                "{} test(s) failed. Results written to {:?}",
+                failed, results_file
+            ))
+            .into());
+        }
+        if !result.success() {
+            return Err(BuildException::custom("cargo failed to run the tests").into());
+        }
+        Ok(())
+    }
+}
+
+/// An event from libtest's json output
+#[derive(Debug, Deserialize)]
+struct LibtestEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    event: String,
+    name: Option<String>,
+    exec_time: Option<serde_json::Value>,
+    stdout: Option<String>,
+}
+
+/// Parses the results of finished tests from libtest's json output. Lines that aren't json test
+/// events are ignored.
+fn parse_libtest_json(output: &str) -> TestResults {
+    let tests = output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<LibtestEvent>(line).ok())
+        .filter(|event| event.kind == "test")
+        .filter_map(|event| {
+            let outcome = match event.event.as_str() {
+                "ok" => TestOutcome::Passed,
+                "failed" | "timeout" => TestOutcome::Failed,
+                "ignored" => TestOutcome::Ignored,
+                _ => return None,
+            };
+            Some(TestCaseResult {
+                name: event.name?,
+                outcome,
+                duration_ms: event.exec_time.as_ref().and_then(exec_time_ms),
+                output: event.stdout.filter(|stdout| !stdout.is_empty()),
+            })
+        })
+        .collect();
+    TestResults { tests }
+}
+
+/// libtest reports execution times in seconds, either as a number or as a string like `"0.12s"`
+fn exec_time_ms(exec_time: &serde_json::Value) -> Option<u64> {
+    let seconds = match exec_time {
+        serde_json::Value::Number(number) => number.as_f64()?,
+        serde_json::Value::String(string) => string.trim_end_matches('s').parse().ok()?,
+        _ => return None,
+    };
+    Some((seconds * 1000.0).round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test_events() {
+        let output = r#"
+{ "type": "suite", "event": "started", "test_count": 3 }
+{ "type": "test", "event": "started", "name": "tests::works" }
+{ "type": "test", "name": "tests::works", "event": "ok", "exec_time": 0.25 }
+{ "type": "test", "name": "tests::broken", "event": "failed", "exec_time": "0.001s", "stdout": "assertion failed\n" }
+{ "type": "test", "name": "tests::slow", "event": "ignored" }
+not json
+{ "type": "suite", "event": "failed", "passed": 1, "failed": 1, "ignored": 1 }
+"#;
+        let results = parse_libtest_json(output);
+        assert_eq!(results.tests.len(), 3);
+        assert_eq!(results.tests[0].duration_ms, Some(250));
+        assert_eq!(results.tests[1].outcome, TestOutcome::Failed);
+        assert_eq!(results.tests[1].duration_ms, Some(1));
+        assert_eq!(
+            results.tests[1].output.as_deref(),
+            Some("assertion failed\n")
+        );
+        assert_eq!(results.count(TestOutcome::Ignored), 1);
+    }
+}
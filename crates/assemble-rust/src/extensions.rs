@@ -1,14 +1,23 @@
 //! Contains rust related extensions
 
 use crate::toolchain::Toolchain;
+use std::path::PathBuf;
 
-use assemble_core::lazy_evaluation::Prop;
+use assemble_core::identifier::Id;
+use assemble_core::lazy_evaluation::{Prop, VecProp};
 
 /// The rust plugin extension
 #[derive(Debug)]
 pub struct RustPluginExtension {
     /// The default toolchain to use with the rust executables
     pub toolchain: Prop<Toolchain>,
+    /// The components required in the toolchain. Defaults to `clippy` and `rustfmt`.
+    pub components: VecProp<String>,
+    /// The sysroot of the toolchain, set once it's been installed.
+    ///
+    /// Tasks that need the toolchain can depend on this provider to make sure the toolchain is
+    /// installed before they run.
+    pub sysroot: Prop<PathBuf>,
 }
 
 impl RustPluginExtension {
@@ -16,8 +25,11 @@ impl RustPluginExtension {
     pub fn new() -> Self {
         let mut extension = Self {
             toolchain: Prop::with_name("toolchain"),
+            components: VecProp::new(Id::new("components").unwrap()),
+            sysroot: Prop::with_name("sysroot"),
         };
         extension.toolchain.set(Toolchain::stable()).unwrap();
+        extension.components.push_all(["clippy", "rustfmt"]);
         extension
     }
 }
@@ -17,9 +17,11 @@ use assemble_core::Project;
 pub mod cargo;
 pub mod extensions;
 pub mod plugin;
+pub mod registry;
 pub mod rustc;
 pub mod rustup;
 pub mod toolchain;
+pub mod workspace;
 
 mod prelude {
     pub use assemble_core::*;
@@ -1,25 +1,69 @@
 //! Contains the rust plugin
 
+use crate::cargo::clippy::Clippy;
+use crate::cargo::fmt::RustFmt;
 use crate::extensions::RustPluginExtension;
 use crate::rustup::configure_rustup_tasks;
+use assemble_core::defaults::plugins::LifecycleBasePlugin;
 use assemble_core::plugins::extensions::ExtensionAware;
-use assemble_core::plugins::Plugin;
+use assemble_core::plugins::{Plugin, PluginAware};
 use assemble_core::project::error::ProjectResult;
 use assemble_core::Project;
 
 /// The rust plugin
+///
+/// # Provided Tasks
+/// - `install-default-toolchain`: installs the default toolchain and its components. Tasks that
+///   need the toolchain depend on it through the extension's `sysroot`
+/// - `clippy`: lints the project with clippy
+/// - `fmt`: formats the project with rustfmt
+/// - `fmt-check`: checks that the project is formatted
+///
+/// Applies the [`LifecycleBasePlugin`](LifecycleBasePlugin), and attaches `clippy` and `fmt-check`
+/// to its `check` task.
 #[derive(Debug, Default)]
 pub struct RustBasePlugin;
 
 impl RustBasePlugin {
     pub const INSTALL_DEFAULT_TOOLCHAIN: &'static str = "install-default-toolchain";
+    pub const CLIPPY: &'static str = "clippy";
+    pub const FMT: &'static str = "fmt";
+    pub const FMT_CHECK: &'static str = "fmt-check";
+    pub const VERIFICATION_GROUP: &'static str = "verification";
 }
 
 impl Plugin<Project> for RustBasePlugin {
     fn apply_to(&self, project: &mut Project) -> ProjectResult {
+        project.apply_plugin::<LifecycleBasePlugin>()?;
         project
             .extensions_mut()
             .add("rust", RustPluginExtension::new())?;
-        configure_rustup_tasks(project)
+        configure_rustup_tasks(project)?;
+        configure_verification_tasks(project)
     }
 }
+
+/// Registers clippy and rustfmt tasks, and attaches them to the `check` lifecycle task
+fn configure_verification_tasks(project: &mut Project) -> ProjectResult {
+    let container = project.task_container_mut();
+    let clippy = container.register_task_with::<Clippy, _>(RustBasePlugin::CLIPPY, |t, _| {
+        t.set_group(RustBasePlugin::VERIFICATION_GROUP);
+        Ok(())
+    })?;
+    container.register_task_with::<RustFmt, _>(RustBasePlugin::FMT, |t, _| {
+        t.set_group("formatting");
+        Ok(())
+    })?;
+    let fmt_check =
+        container.register_task_with::<RustFmt, _>(RustBasePlugin::FMT_CHECK, |t, _| {
+            t.set_description("Checks that a rust project is formatted");
+            t.set_group(RustBasePlugin::VERIFICATION_GROUP);
+            t.check.set(true)?;
+            Ok(())
+        })?;
+    LifecycleBasePlugin::task(project, LifecycleBasePlugin::CHECK)?.configure_with(move |t, _| {
+        t.depends_on(clippy);
+        t.depends_on(fmt_check);
+        Ok(())
+    })
+}
@@ -0,0 +1,484 @@
+//! Resolve crates from cargo registries.
+//!
+//! Crates are resolved using the [sparse index protocol][sparse]. The newest non-yanked version
+//! matching the requested version requirement is selected, and its `.crate` file is downloaded and
+//! verified against the checksum in the index. Configurations containing crate dependencies can
+//! then be used as file collections by tasks, such as build logic plugins that need the sources of
+//! a crate.
+//!
+//! [sparse]: https://doc.rust-lang.org/cargo/reference/registry-index.html#sparse-protocol
+
+use assemble_core::cryptography::{hash_sha256, Sha256};
+use assemble_core::dependencies::{
+    AcquisitionError, Dependency, DependencyType, Registry, ResolvedDependency,
+    ResolvedDependencyBuilder,
+};
+use assemble_core::project::buildable::{BuildableObject, GetBuildable};
+use assemble_core::web::{WebClient, WebError};
+use reqwest::StatusCode;
+use semver::{Version, VersionReq};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use url::Url;
+
+/// The url of the sparse index of crates.io
+pub const CRATES_IO_INDEX: &str = "https://index.crates.io/";
+
+/// The download url template used when a registry doesn't have a `config.json` file
+const DEFAULT_DOWNLOAD_TEMPLATE: &str =
+    "https://static.crates.io/crates/{crate}/{version}/download";
+
+/// Creates the dependency type resolved by cargo registries
+pub fn crate_dependency_type() -> DependencyType {
+    DependencyType::new("crate", "cargo_registry", ["crate"])
+}
+
+/// A cargo registry that uses the sparse index protocol
+#[derive(Debug, Clone)]
+pub struct CargoRegistry {
+    name: String,
+    index: Url,
+}
+
+impl CargoRegistry {
+    /// Creates a new cargo registry from the url of its sparse index. A `sparse+` prefix on the url
+    /// is allowed.
+    pub fn new(name: &str, index: &str) -> Result<Self, url::ParseError> {
+        let mut index = index.trim_start_matches("sparse+").to_string();
+        if !index.ends_with('/') {
+            index.push('/');
+        }
+        Ok(Self {
+            name: name.to_string(),
+            index: Url::parse(&index)?,
+        })
+    }
+
+    /// The crates.io registry
+    pub fn crates_io() -> Self {
+        Self::new("crates-io", CRATES_IO_INDEX).expect("crates.io index url is valid")
+    }
+
+    /// The name of the registry
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Registry for CargoRegistry {
+    fn url(&self) -> Url {
+        self.index.clone()
+    }
+
+    fn supported(&self) -> Vec<DependencyType> {
+        vec![crate_dependency_type()]
+    }
+}
+
+/// An error occurred resolving a crate
+#[derive(Debug, thiserror::Error)]
+pub enum CrateResolutionError {
+    /// The crate isn't in the registry
+    #[error("crate {0:?} not found in registry")]
+    CrateNotFound(String),
+    /// No version of the crate matches the requirement
+    #[error("no version of {name} matches {requirement}")]
+    NoMatchingVersion {
+        /// The name of the crate
+        name: String,
+        /// The version requirement
+        requirement: VersionReq,
+    },
+    /// The downloaded crate file doesn't match the checksum in the index
+    #[error("checksum mismatch for {name} {version}: expected {expected}, found {found}")]
+    ChecksumMismatch {
+        /// The name of the crate
+        name: String,
+        /// The version of the crate
+        version: Version,
+        /// The checksum in the index
+        expected: String,
+        /// The checksum of the downloaded file
+        found: Sha256,
+    },
+    /// A version requirement couldn't be parsed
+    #[error(transparent)]
+    Semver(#[from] semver::Error),
+    /// Error occurred while making a request
+    #[error(transparent)]
+    Web(#[from] WebError),
+    /// Error occurred parsing the index
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// Io error occurred
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// A url couldn't be created
+    #[error(transparent)]
+    Url(#[from] url::ParseError),
+}
+
+impl From<CrateResolutionError> for AcquisitionError {
+    fn from(e: CrateResolutionError) -> Self {
+        AcquisitionError::custom(e)
+    }
+}
+
+/// A dependency on a crate from a cargo registry
+#[derive(Debug, Clone)]
+pub struct CrateDependency {
+    name: String,
+    requirement: VersionReq,
+    transitive: bool,
+}
+
+impl CrateDependency {
+    /// Creates a dependency on a crate matching a version requirement, such as `"1.0"` or `"=0.4.17"`.
+    ///
+    /// Only the crate itself is resolved by default.
+    pub fn new(name: &str, requirement: &str) -> Result<Self, CrateResolutionError> {
+        Ok(Self {
+            name: name.to_string(),
+            requirement: VersionReq::parse(requirement)?,
+            transitive: false,
+        })
+    }
+
+    /// Also resolve the normal, non-optional dependencies of the crate
+    pub fn transitive(mut self) -> Self {
+        self.transitive = true;
+        self
+    }
+}
+
+impl FromStr for CrateDependency {
+    type Err = CrateResolutionError;
+
+    /// Parses dependencies in the form `name` or `name@requirement`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('@') {
+            Some((name, requirement)) => Self::new(name, requirement),
+            None => Self::new(s, "*"),
+        }
+    }
+}
+
+impl GetBuildable for CrateDependency {
+    fn as_buildable(&self) -> BuildableObject {
+        BuildableObject::None
+    }
+}
+
+impl Dependency for CrateDependency {
+    fn id(&self) -> String {
+        self.name.clone()
+    }
+
+    fn dep_type(&self) -> DependencyType {
+        crate_dependency_type()
+    }
+
+    fn try_resolve(
+        &self,
+        registry: &dyn Registry,
+        cache_path: &Path,
+    ) -> Result<ResolvedDependency, AcquisitionError> {
+        let mut resolver = IndexResolver::new(
+            registry.url(),
+            registry.web_client(),
+            cache_path.join("crates"),
+        );
+        let root = resolver.select(&self.name, &self.requirement)?;
+
+        let mut files = vec![resolver.download(&root)?];
+        if self.transitive {
+            let mut selected: HashMap<String, Vec<Version>> = HashMap::new();
+            selected.insert(root.name.clone(), vec![root.vers.clone()]);
+            let mut queue = VecDeque::from([root.clone()]);
+            while let Some(entry) = queue.pop_front() {
+                for dep in entry.deps.iter().filter(|d| d.is_required()) {
+                    let name = dep.package.as_ref().unwrap_or(&dep.name);
+                    let versions = selected.entry(name.clone()).or_default();
+                    if versions.iter().any(|v| dep.req.matches(v)) {
+                        continue;
+                    }
+                    let next = resolver.select(name, &dep.req)?;
+                    versions.push(next.vers.clone());
+                    files.push(resolver.download(&next)?);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let mut files = files.into_iter();
+        let mut builder = ResolvedDependencyBuilder::new(files.next().unwrap());
+        builder.add_many(files);
+        Ok(builder.version(root.vers.to_string()).finish())
+    }
+
+    fn with_version(&self, version: &str) -> Option<Box<dyn Dependency + Send + Sync>> {
+        let requirement = VersionReq::parse(&format!("={}", version)).ok()?;
+        Some(Box::new(Self {
+            requirement,
+            ..self.clone()
+        }))
+    }
+}
+
+/// A single version of a crate within the index
+#[derive(Debug, Clone, Deserialize)]
+struct IndexEntry {
+    name: String,
+    vers: Version,
+    #[serde(default)]
+    deps: Vec<IndexDependency>,
+    cksum: String,
+    #[serde(default)]
+    yanked: bool,
+}
+
+/// A dependency of a crate version within the index
+#[derive(Debug, Clone, Deserialize)]
+struct IndexDependency {
+    name: String,
+    req: VersionReq,
+    #[serde(default)]
+    optional: bool,
+    #[serde(default)]
+    kind: Option<String>,
+    #[serde(default)]
+    package: Option<String>,
+}
+
+impl IndexDependency {
+    fn is_required(&self) -> bool {
+        !self.optional && matches!(self.kind.as_deref(), None | Some("normal"))
+    }
+}
+
+/// The `config.json` of a registry index
+#[derive(Debug, Deserialize)]
+struct IndexConfig {
+    dl: String,
+}
+
+/// Gets the path of a crate within the index
+fn index_path(name: &str) -> String {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => format!("1/{name}"),
+        2 => format!("2/{name}"),
+        3 => format!("3/{}/{name}", &name[..1]),
+        _ => format!("{}/{}/{name}", &name[..2], &name[2..4]),
+    }
+}
+
+/// Creates the download url of a crate from the `dl` template of a registry
+fn download_url(template: &str, entry: &IndexEntry) -> String {
+    let markers = [
+        "{crate}",
+        "{version}",
+        "{prefix}",
+        "{lowerprefix}",
+        "{sha256-checksum}",
+    ];
+    if !markers.iter().any(|m| template.contains(m)) {
+        return format!(
+            "{}/{}/{}/download",
+            template.trim_end_matches('/'),
+            entry.name,
+            entry.vers
+        );
+    }
+    let prefix = index_path(&entry.name);
+    let prefix = prefix.rsplit_once('/').map(|(p, _)| p).unwrap_or_default();
+    template
+        .replace("{crate}", &entry.name)
+        .replace("{version}", &entry.vers.to_string())
+        .replace("{prefix}", prefix)
+        .replace("{lowerprefix}", &prefix.to_lowercase())
+        .replace("{sha256-checksum}", &entry.cksum)
+}
+
+/// Selects the newest non-yanked version matching a requirement
+fn select_version<'a>(
+    entries: &'a [IndexEntry],
+    requirement: &VersionReq,
+) -> Option<&'a IndexEntry> {
+    entries
+        .iter()
+        .filter(|e| !e.yanked && requirement.matches(&e.vers))
+        .max_by(|a, b| a.vers.cmp(&b.vers))
+}
+
+/// Queries a single index, caching the index files that were already fetched
+struct IndexResolver {
+    index: Url,
+    client: WebClient,
+    cache_root: PathBuf,
+    download_template: Option<String>,
+    entries: HashMap<String, Arc<Vec<IndexEntry>>>,
+}
+
+impl IndexResolver {
+    fn new(index: Url, client: WebClient, cache_root: PathBuf) -> Self {
+        Self {
+            index,
+            client,
+            cache_root,
+            download_template: None,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn entries(&mut self, name: &str) -> Result<Arc<Vec<IndexEntry>>, CrateResolutionError> {
+        if let Some(entries) = self.entries.get(name) {
+            return Ok(entries.clone());
+        }
+        let url = self.index.join(&index_path(name))?;
+        debug!("fetching index file {}", url);
+        let body = match self.client.get_bytes(&url) {
+            Ok(body) => String::from_utf8_lossy(&body).to_string(),
+            Err(WebError::Status { status, .. }) if status == StatusCode::NOT_FOUND => {
+                return Err(CrateResolutionError::CrateNotFound(name.to_string()));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let entries = body
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str::<IndexEntry>)
+            .collect::<Result<Vec<_>, _>>()?;
+        let entries = Arc::new(entries);
+        self.entries.insert(name.to_string(), entries.clone());
+        Ok(entries)
+    }
+
+    fn select(
+        &mut self,
+        name: &str,
+        requirement: &VersionReq,
+    ) -> Result<IndexEntry, CrateResolutionError> {
+        let entries = self.entries(name)?;
+        select_version(&entries, requirement)
+            .cloned()
+            .ok_or_else(|| CrateResolutionError::NoMatchingVersion {
+                name: name.to_string(),
+                requirement: requirement.clone(),
+            })
+    }
+
+    fn download_template(&mut self) -> Result<String, CrateResolutionError> {
+        if let Some(template) = &self.download_template {
+            return Ok(template.clone());
+        }
+        let url = self.index.join("config.json")?;
+        let template = match self.client.get_bytes(&url) {
+            Ok(body) => serde_json::from_slice::<IndexConfig>(&body)?.dl,
+            Err(WebError::Status { .. }) => DEFAULT_DOWNLOAD_TEMPLATE.to_string(),
+            Err(e) => return Err(e.into()),
+        };
+        self.download_template = Some(template.clone());
+        Ok(template)
+    }
+
+    /// Downloads the `.crate` file of an index entry. Crate files are immutable, so a file that
+    /// was already downloaded and matches its checksum is never downloaded again.
+    fn download(&mut self, entry: &IndexEntry) -> Result<PathBuf, CrateResolutionError> {
+        let destination = self
+            .cache_root
+            .join(&entry.name)
+            .join(format!("{}-{}.crate", entry.name, entry.vers));
+        if destination.exists() {
+            let found = hash_sha256(&fs::read(&destination)?);
+            if found.to_string() == entry.cksum {
+                trace!("using cached {:?}", destination);
+                return Ok(destination);
+            }
+        }
+
+        let url = download_url(&self.download_template()?, entry);
+        debug!("downloading {}", url);
+        let bytes = self.client.get_bytes(&Url::parse(&url)?)?;
+        let found = hash_sha256(&bytes);
+        if found.to_string() != entry.cksum {
+            return Err(CrateResolutionError::ChecksumMismatch {
+                name: entry.name.clone(),
+                version: entry.vers.clone(),
+                expected: entry.cksum.clone(),
+                found,
+            });
+        }
+
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let partial = destination.with_extension("partial");
+        fs::write(&partial, &bytes)?;
+        fs::rename(&partial, &destination)?;
+        Ok(destination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(vers: &str, yanked: bool) -> IndexEntry {
+        IndexEntry {
+            name: "serde".to_string(),
+            vers: Version::parse(vers).unwrap(),
+            deps: vec![],
+            cksum: String::new(),
+            yanked,
+        }
+    }
+
+    #[test]
+    fn index_paths() {
+        assert_eq!(index_path("a"), "1/a");
+        assert_eq!(index_path("cc"), "2/cc");
+        assert_eq!(index_path("log"), "3/l/log");
+        assert_eq!(index_path("Serde"), "se/rd/serde");
+    }
+
+    #[test]
+    fn selects_newest_matching_version() {
+        let entries = vec![
+            entry("1.0.0", false),
+            entry("1.2.0", false),
+            entry("1.3.0", true),
+            entry("2.0.0", false),
+        ];
+        let selected = select_version(&entries, &VersionReq::parse("1").unwrap()).unwrap();
+        assert_eq!(selected.vers, Version::new(1, 2, 0));
+        assert!(select_version(&entries, &VersionReq::parse("3").unwrap()).is_none());
+    }
+
+    #[test]
+    fn download_urls() {
+        let entry = entry("1.0.0", false);
+        assert_eq!(
+            download_url("https://crates.example.com/api/v1/crates", &entry),
+            "https://crates.example.com/api/v1/crates/serde/1.0.0/download"
+        );
+        assert_eq!(
+            download_url(
+                "https://crates.example.com/{prefix}/{crate}-{version}.crate",
+                &entry
+            ),
+            "https://crates.example.com/se/rd/serde-1.0.0.crate"
+        );
+    }
+
+    #[test]
+    fn parse_crate_dependency() {
+        let dependency: CrateDependency = "serde@1.0".parse().unwrap();
+        assert_eq!(dependency.id(), "serde");
+        assert!(dependency.requirement.matches(&Version::new(1, 0, 150)));
+        assert!("serde@not a version".parse::<CrateDependency>().is_err());
+    }
+}
@@ -1,13 +1,12 @@
 //! Rustup related tasks
 //!
 
-use log::info;
+use log::{debug, info};
 
-use assemble_core::dependencies::configurations::Configuration;
 use assemble_core::error::PayloadError;
 use assemble_core::exception::BuildException;
 
-use assemble_core::file_collection::FileCollection;
+use assemble_core::lazy_evaluation::{Provider, ProviderExt};
 
 use assemble_core::plugins::extensions::ExtensionAware;
 
@@ -16,8 +15,8 @@ use assemble_core::task::create_task::CreateTask;
 
 use assemble_core::task::up_to_date::UpToDate;
 
-use assemble_std::dependencies::web::{WebDependency, WebRegistry};
 use assemble_std::specs::exec_spec::Output;
+use assemble_std::tools::{Platform, Tool, ToolArtifact, ToolProvisioner};
 
 use assemble_std::ProjectExec;
 
@@ -43,29 +42,22 @@ pub fn configure_rustup_tasks(project: &mut Project) -> ProjectResult<()> {
         Ok(())
     })?;
 
-    let rustup_install_config = if cfg!(windows) {
-        configure_windows_install(project)?
-    } else if cfg!(unix) {
-        configure_unix_install(project)?
-    } else {
-        return Err(ProjectError::custom("unsupported os for rustup").into());
-    };
+    let rustup_init = ToolProvisioner::default().executable(rustup_init_tool());
 
     install.configure_with(move |task, _project| -> ProjectResult<()> {
-        task.depends_on(rustup_install_config.clone());
         task.do_first(move |_task, project| {
             if which::which("rustup").is_ok() {
                 return Err(BuildException::StopTask.into());
             }
 
-            let configuration = rustup_install_config
-                .resolved()
-                .map_err(|e| PayloadError::<ProjectError>::new(e))?;
-            let rustup_init_file = configuration.files().into_iter().next().unwrap();
-            println!("rustup file = {:?}", rustup_init_file);
+            let rustup_init_file = rustup_init
+                .fallible_get()
+                .map_err(PayloadError::<ProjectError>::new)?;
+            debug!("rustup-init = {:?}", rustup_init_file);
 
             match project.exec_with(move |exec| {
                 exec.exec(rustup_init_file)
+                    .arg("-y")
                     .args(["--default-toolchain", "none"])
                     .args(["--profile", "minimal"])
                     .arg("-v")
@@ -97,7 +89,7 @@ pub fn configure_rustup_tasks(project: &mut Project) -> ProjectResult<()> {
 
         Ok(())
     })?;
-    project
+    let install_toolchain = project
         .task_container_mut()
         .register_task_with::<InstallToolchain, _>(
             RustBasePlugin::INSTALL_DEFAULT_TOOLCHAIN,
@@ -108,52 +100,37 @@ pub fn configure_rustup_tasks(project: &mut Project) -> ProjectResult<()> {
                 let extension = p.extension::<RustPluginExtension>().unwrap();
                 t.depends_on(install);
                 t.toolchain.set_with(extension.toolchain.clone())?;
+                t.components.push_all_with(extension.components.clone());
                 Ok(())
             },
         )?;
 
+    let mut sysroot = project.extension::<RustPluginExtension>()?.sysroot.clone();
+    sysroot.set_with(install_toolchain.provides(|t| t.sysroot.clone()).flatten())?;
+
     Ok(())
 }
 
-fn configure_unix_install(project: &mut Project) -> ProjectResult<Configuration> {
-    project.registries_mut(|reg| {
-        let registry = WebRegistry::new("rust-site", "https://sh.rustup.rs/").unwrap();
-        reg.add_registry(registry);
-        Ok(())
-    })?;
-    let rustup_install_config = project
-        .configurations_mut()
-        .create_with("rustupInstall", |config| {
-            config.add_dependency(
-                WebDependency::new("", "rust-site").with_file_name("rustup-startup.sh"),
+/// The rustup installer, for every platform it's distributed for
+fn rustup_init_tool() -> Tool {
+    const DIST: &str = "https://static.rust-lang.org/rustup/dist";
+    [
+        ("linux", "x86_64", "x86_64-unknown-linux-gnu", ""),
+        ("linux", "aarch64", "aarch64-unknown-linux-gnu", ""),
+        ("macos", "x86_64", "x86_64-apple-darwin", ""),
+        ("macos", "aarch64", "aarch64-apple-darwin", ""),
+        ("windows", "x86_64", "x86_64-pc-windows-msvc", ".exe"),
+        ("windows", "x86", "i686-pc-windows-msvc", ".exe"),
+    ]
+    .into_iter()
+    .fold(
+        Tool::new("rustup-init", "latest"),
+        |tool, (os, arch, target, extension)| {
+            tool.artifact(
+                Platform::new(os, arch),
+                ToolArtifact::executable(&format!("{DIST}/{target}/rustup-init{extension}"))
+                    .expect("rustup-init url is valid"),
             )
-        })
-        .clone();
-
-    Ok(rustup_install_config)
-}
-
-fn configure_windows_install(project: &mut Project) -> ProjectResult<Configuration> {
-    project.registries_mut(|reg| {
-        let registry = WebRegistry::new("rust-site", "https://static.rust-lang.org/").unwrap();
-        reg.add_registry(registry);
-        Ok(())
-    })?;
-    let rustup_install_config = project
-        .configurations_mut()
-        .create_with("rustup-install", |config| {
-            #[cfg(target_pointer_width = "64")]
-            config.add_dependency(WebDependency::new(
-                "/rustup/dist/x86_64-pc-windows-msvc/rustup-startup.exe",
-                "rust-site",
-            ));
-            #[cfg(target_pointer_width = "32")]
-            config.add_dependency(WebDependency::new(
-                "/rustup/dist/i686-pc-windows-msvc/rustup-startup.exe",
-                "rust-site",
-            ));
-        })
-        .clone();
-
-    Ok(rustup_install_config)
+        },
+    )
 }
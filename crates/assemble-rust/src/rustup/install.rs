@@ -1,50 +1,218 @@
 //! Install component or toolchain with rustup
 
 use log::Level;
+use std::path::PathBuf;
 
+use assemble_core::error::PayloadError;
 use assemble_core::exception::BuildException;
-use assemble_core::lazy_evaluation::{Prop, Provider};
+use assemble_core::lazy_evaluation::{Prop, Provider, VecProp};
 
 use assemble_core::task::initialize_task::InitializeTask;
 use assemble_core::task::up_to_date::UpToDate;
 use assemble_core::{BuildResult, Executable, Project, Task};
 use assemble_core::{CreateTask, TaskIO};
+use assemble_std::specs::exec_spec::Output;
 use assemble_std::ProjectExec;
 
 use crate::toolchain::Toolchain;
 
-/// The toolchain to install
+/// Installs a toolchain and its components with rustup, if they're not already installed.
+///
+/// Once the task has run, [`sysroot`](InstallToolchain::sysroot) is set to the sysroot of the
+/// installed toolchain.
 #[derive(Debug, CreateTask, TaskIO)]
 pub struct InstallToolchain {
     /// The toolchain to install
     #[input]
     pub toolchain: Prop<Toolchain>,
+    /// The components to install with the toolchain, like `clippy` or `rustfmt`
+    #[input]
+    pub components: VecProp<String>,
+    /// The sysroot of the installed toolchain. Only available after the task has run.
+    pub sysroot: Prop<PathBuf>,
+}
+
+impl InstallToolchain {
+    /// Whether the toolchain and all of its components are already installed
+    fn installed(&self) -> bool {
+        let toolchain = match self.toolchain.try_get() {
+            Some(toolchain) => toolchain.to_string(),
+            None => return false,
+        };
+        let installed = rustup_output(["toolchain", "list"])
+            .map(|output| parse_toolchain_list(&output))
+            .unwrap_or_default();
+        if !installed.iter().any(|name| is_toolchain(name, &toolchain)) {
+            return false;
+        }
+
+        let components = self.components.try_get().unwrap_or_default();
+        if components.is_empty() {
+            return true;
+        }
+        let installed_components = match rustup_output([
+            "component",
+            "list",
+            "--installed",
+            "--toolchain",
+            &toolchain,
+        ]) {
+            Some(output) => parse_component_list(&output),
+            None => return false,
+        };
+        components.iter().all(|component| {
+            installed_components
+                .iter()
+                .any(|installed| is_component(installed, component))
+        })
+    }
 }
 
-impl UpToDate for InstallToolchain {}
+impl UpToDate for InstallToolchain {
+    /// The toolchain is up to date if it's installed with all of its components
+    fn up_to_date(&self) -> bool {
+        self.installed()
+    }
+}
 
 impl InitializeTask for InstallToolchain {}
 
 impl Task for InstallToolchain {
     fn task_action(task: &mut Executable<Self>, project: &Project) -> BuildResult {
-        let toolchain = task.toolchain.fallible_get()?;
-
-        debug!("attempting to install toolchain {}", toolchain);
-
-        if !project
-            .exec_with(|exec| {
-                exec.exec("rustup")
-                    .arg("install")
-                    .arg("--no-self-update")
-                    .arg(toolchain.to_string())
-                    .stdout(Level::Debug);
-            })?
-            .success()
-        {
-            warn!("bad result gotten");
-            return Err(BuildException::custom("rustup install failed").into());
+        let toolchain = task.toolchain.fallible_get()?.to_string();
+        let components = task.components.fallible_get()?;
+
+        if task.installed() {
+            debug!("toolchain {} is already installed", toolchain);
+        } else {
+            debug!(
+                "attempting to install toolchain {} with components {:?}",
+                toolchain, components
+            );
+            if !project
+                .exec_with(|exec| {
+                    exec.exec("rustup")
+                        .args(["toolchain", "install", &toolchain])
+                        .arg("--no-self-update")
+                        .args(["--profile", "minimal"]);
+                    for component in &components {
+                        exec.args(["--component", component]);
+                    }
+                    exec.stdout(Level::Debug);
+                })?
+                .success()
+            {
+                warn!("bad result gotten");
+                return Err(BuildException::custom("rustup install failed").into());
+            }
         }
 
+        let result = project.exec_with(|exec| {
+            exec.exec("rustup")
+                .args(["run", &toolchain, "rustc", "--print", "sysroot"])
+                .stdout(Output::Bytes);
+        })?;
+        let sysroot = result
+            .utf8_string()
+            .unwrap_or_else(|| Ok(String::new()))
+            .map_err(PayloadError::<BuildException>::new)?;
+        let sysroot = sysroot.trim();
+        if !result.success() || sysroot.is_empty() {
+            return Err(BuildException::custom(&format!(
+                "could not find the sysroot of toolchain {}",
+                toolchain
+            ))
+            .into());
+        }
+        info!("using toolchain {} at {}", toolchain, sysroot);
+        task.sysroot.set(PathBuf::from(sysroot))?;
         Ok(())
     }
 }
+
+/// Runs rustup, returning its output if it succeeded
+fn rustup_output<'a, I: IntoIterator<Item = &'a str>>(args: I) -> Option<String> {
+    let output = std::process::Command::new("rustup")
+        .args(args)
+        .output()
+        .ok()?;
+    if output.status.success() {
+        String::from_utf8(output.stdout).ok()
+    } else {
+        None
+    }
+}
+
+/// Parses the output of `rustup toolchain list`, which lists one toolchain per line, with an
+/// optional marker like `(default)`
+fn parse_toolchain_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|name| *name != "no")
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parses the output of `rustup component list --installed`
+fn parse_component_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The architectures that start the target triples rustup appends to toolchain and component names
+const TRIPLE_ARCHS: &[&str] = &[
+    "x86_64", "i586", "i686", "aarch64", "arm", "thumb", "wasm32", "riscv", "mips", "powerpc",
+    "s390x", "sparc",
+];
+
+/// Whether a name is followed by a target triple, such as `stable-x86_64-unknown-linux-gnu`
+fn has_triple_suffix(installed: &str, name: &str) -> bool {
+    match installed.strip_prefix(name) {
+        Some("") => true,
+        Some(rest) => rest.strip_prefix('-').map_or(false, |triple| {
+            TRIPLE_ARCHS.iter().any(|arch| triple.starts_with(arch))
+        }),
+        None => false,
+    }
+}
+
+/// Installed toolchains are listed with their host triple, such as `stable-x86_64-unknown-linux-gnu`
+fn is_toolchain(installed: &str, toolchain: &str) -> bool {
+    has_triple_suffix(installed, toolchain)
+}
+
+/// Installed components are listed with their target, such as `clippy-x86_64-unknown-linux-gnu`
+fn is_component(installed: &str, component: &str) -> bool {
+    has_triple_suffix(installed, component)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_installed_toolchains() {
+        let output = "stable-x86_64-unknown-linux-gnu (default)\nnightly-2022-10-01-x86_64-unknown-linux-gnu\n1.64-x86_64-unknown-linux-gnu\n";
+        let installed = parse_toolchain_list(output);
+        assert_eq!(installed.len(), 3);
+        assert!(installed.iter().any(|t| is_toolchain(t, "stable")));
+        assert!(installed
+            .iter()
+            .any(|t| is_toolchain(t, "nightly-2022-10-01")));
+        assert!(installed.iter().any(|t| is_toolchain(t, "1.64")));
+        assert!(!installed.iter().any(|t| is_toolchain(t, "nightly")));
+        assert!(!installed.iter().any(|t| is_toolchain(t, "beta")));
+
+        assert!(parse_toolchain_list("no installed toolchains\n").is_empty());
+
+        let components = parse_component_list("cargo-x86_64-unknown-linux-gnu\nclippy-x86_64-unknown-linux-gnu\nrust-std-x86_64-unknown-linux-gnu\n");
+        assert!(components.iter().any(|c| is_component(c, "clippy")));
+        assert!(!components.iter().any(|c| is_component(c, "rustfmt")));
+        assert!(!components.iter().any(|c| is_component(c, "rust")));
+    }
+}
@@ -0,0 +1,154 @@
+//! Import an existing cargo workspace into an assemble build.
+//!
+//! The [`CargoWorkspacePlugin`](CargoWorkspacePlugin) is applied to the settings of a build. It
+//! runs `cargo metadata` in the root directory of the build, and includes a project for every
+//! member of the workspace. The rust plugin is applied to every imported project, and a `test`
+//! task is registered for it, so a cargo workspace can be built without any build scripts.
+
+use crate::cargo::metadata::{CargoMetadata, Package};
+use crate::cargo::test::CargoTest;
+use crate::plugin::RustBasePlugin;
+use assemble_core::plugins::extensions::ExtensionAware;
+use assemble_core::plugins::{Plugin, PluginAware};
+use assemble_core::prelude::Settings;
+use assemble_core::project::error::{ProjectError, ProjectResult};
+use assemble_core::Project;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The name of the extension added to imported projects
+pub const CARGO_PACKAGE_EXTENSION: &str = "cargo";
+
+/// Imports the members of the cargo workspace in the root directory of the build as projects
+#[derive(Debug, Default)]
+pub struct CargoWorkspacePlugin;
+
+impl CargoWorkspacePlugin {
+    /// The name of the test task registered for imported projects
+    pub const TEST: &'static str = "test";
+}
+
+impl Plugin<Settings> for CargoWorkspacePlugin {
+    fn apply_to(&self, settings: &mut Settings) -> ProjectResult {
+        let metadata = CargoMetadata::load(settings.root_dir()).map_err(ProjectError::custom)?;
+        import_workspace(settings, &metadata);
+        Ok(())
+    }
+}
+
+/// The cargo package an imported project was created from
+#[derive(Debug, Clone)]
+pub struct CargoPackageExtension {
+    package: Package,
+}
+
+impl CargoPackageExtension {
+    /// The package
+    pub fn package(&self) -> &Package {
+        &self.package
+    }
+}
+
+/// Includes a project for every member of a workspace, and adds a rule configuring the projects
+/// created for them. A package in the root directory of the workspace is imported as the root
+/// project.
+pub fn import_workspace(settings: &mut Settings, metadata: &CargoMetadata) {
+    let workspace_root = normalize(&metadata.workspace_root);
+    let mut packages = HashMap::new();
+    for package in metadata.workspace_packages() {
+        let directory = normalize(package.directory());
+        if directory != workspace_root {
+            debug!(
+                "importing cargo package {} from {:?}",
+                package.name, directory
+            );
+            let dir = directory.clone();
+            settings.add_project(&package.name, move |builder| builder.set_dir(dir));
+        }
+        packages.insert(directory, package.clone());
+    }
+
+    settings.allprojects(package_rule(packages));
+}
+
+/// Creates a rule that configures projects whose directory contains one of the packages
+fn package_rule(
+    packages: HashMap<PathBuf, Package>,
+) -> impl Fn(&mut Project) -> ProjectResult + Send + Sync + 'static {
+    let packages = Arc::new(packages);
+    move |project| match packages.get(&normalize(&project.project_dir())) {
+        Some(package) => configure_package_project(project, package),
+        None => Ok(()),
+    }
+}
+
+/// Configures a project created for a cargo package
+fn configure_package_project(project: &mut Project, package: &Package) -> ProjectResult {
+    trace!("configuring {} as cargo package {}", project, package.name);
+    project.extensions_mut().add(
+        CARGO_PACKAGE_EXTENSION,
+        CargoPackageExtension {
+            package: package.clone(),
+        },
+    )?;
+    project.apply_plugin::<RustBasePlugin>()?;
+    project
+        .task_container_mut()
+        .register_task_with::<CargoTest, _>(CargoWorkspacePlugin::TEST, |t, _| {
+            t.set_group(RustBasePlugin::VERIFICATION_GROUP);
+            Ok(())
+        })?;
+    Ok(())
+}
+
+/// Paths reported by cargo are canonical, so project directories are canonicalized before being
+/// compared with them
+fn normalize(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imported_packages_get_rust_tasks() {
+        let project = Project::temp("member");
+        let dir = project.with(|p| p.project_dir());
+        let metadata = CargoMetadata::parse(&format!(
+            r#"{{
+                "packages": [{{
+                    "name": "member",
+                    "version": "0.1.0",
+                    "id": "member 0.1.0",
+                    "manifest_path": {:?},
+                    "targets": []
+                }}],
+                "workspace_members": ["member 0.1.0"],
+                "workspace_root": {:?},
+                "target_directory": {:?}
+            }}"#,
+            dir.join("Cargo.toml"),
+            dir,
+            dir.join("target")
+        ))
+        .unwrap();
+        let packages = metadata
+            .workspace_packages()
+            .map(|package| (normalize(package.directory()), package.clone()))
+            .collect();
+
+        let rule = package_rule(packages);
+        project.with_mut(|p| rule(p)).unwrap();
+        project.with(|p| {
+            let extension = p.extension::<CargoPackageExtension>().unwrap();
+            assert_eq!(extension.package().name, "member");
+            assert!(p
+                .task_container()
+                .get_tasks()
+                .into_iter()
+                .any(|id| id.this() == CargoWorkspacePlugin::TEST));
+        });
+    }
+}
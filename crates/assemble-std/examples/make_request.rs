@@ -6,7 +6,7 @@ use assemble_core::prelude::Provider;
 use assemble_core::project::error::ProjectError;
 use assemble_core::project::shared::SharedProject;
 use assemble_core::Project;
-use assemble_std::tasks::web::DownloadFile;
+use assemble_std::tasks::web::Download;
 use reqwest::Url;
 
 use std::str::FromStr;
@@ -23,7 +23,7 @@ fn main() {
         .task_container()
         .get_task(&id)
         .unwrap()
-        .as_type::<DownloadFile>()
+        .as_type::<Download>()
         .unwrap();
     let url = p.provides(|url| url.url.clone()).get();
 
@@ -36,7 +36,7 @@ fn main() {
 fn create_project() -> Result<SharedProject, PayloadError<ProjectError>> {
     let project = Project::with_id("root")?;
 
-    let mut provider = project.register_task::<DownloadFile>("downloadRustSh")?;
+    let mut provider = project.register_task::<Download>("downloadRustSh")?;
     provider
         .configure_with(|task, _| {
             task.url.set(
@@ -0,0 +1,267 @@
+//! Reading archives.
+//!
+//! An [`ArchiveTree`](ArchiveTree) is a [`FileCollection`](FileCollection) of the files within an
+//! archive, so the contents of an archive can be used as task inputs without extracting it
+//! manually. Archive trees are created with
+//! [`ProjectArchives::zip_tree`](crate::extensions::project_extensions::ProjectArchives::zip_tree)
+//! and [`tar_tree`](crate::extensions::project_extensions::ProjectArchives::tar_tree).
+//!
+//! The archive is only extracted when the files of the tree are first queried, into a directory
+//! within `build/tmp/expanded`. It is extracted again if the archive changes.
+
+use assemble_core::error::PayloadError;
+use assemble_core::exception::{BuildException, BuildResult};
+use assemble_core::file_collection::{FileCollection, FileSet};
+use assemble_core::identifier::TaskId;
+use assemble_core::lazy_evaluation::anonymous::AnonymousProvider;
+use assemble_core::lazy_evaluation::Provider;
+use assemble_core::project::buildable::Buildable;
+use assemble_core::project::error::ProjectResult;
+use assemble_core::Project;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// The directory archive trees are extracted into, relative to the build directory
+pub const EXPANDED_DIR: &str = "tmp/expanded";
+
+/// The format of an archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ArchiveFormat {
+    /// A zip archive
+    Zip,
+    /// An uncompressed tar archive
+    Tar,
+    /// A gzip compressed tar archive
+    TarGz,
+    /// A zstd compressed tar archive
+    TarZst,
+}
+
+impl ArchiveFormat {
+    /// Detects the format of an archive from its file name
+    pub fn detect<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let name = path.as_ref().file_name()?.to_str()?.to_lowercase();
+        if name.ends_with(".zip") || name.ends_with(".jar") {
+            Some(ArchiveFormat::Zip)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            Some(ArchiveFormat::TarZst)
+        } else {
+            None
+        }
+    }
+
+    /// Whether this is a tar format
+    pub fn is_tar(&self) -> bool {
+        !matches!(self, ArchiveFormat::Zip)
+    }
+
+    /// Extracts an archive of this format into a directory
+    pub fn extract<P: AsRef<Path>, Q: AsRef<Path>>(&self, archive: P, into: Q) -> io::Result<()> {
+        let file = File::open(archive)?;
+        let into = into.as_ref();
+        std::fs::create_dir_all(into)?;
+        match self {
+            ArchiveFormat::Zip => zip::ZipArchive::new(file)
+                .and_then(|mut zip| zip.extract(into))
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            ArchiveFormat::Tar => tar::Archive::new(file).unpack(into),
+            ArchiveFormat::TarGz => {
+                tar::Archive::new(flate2::read::GzDecoder::new(file)).unpack(into)
+            }
+            ArchiveFormat::TarZst => tar::Archive::new(zstd::Decoder::new(file)?).unpack(into),
+        }
+    }
+}
+
+/// The files within an archive, extracted when first needed. Clones share the extracted files.
+#[derive(Clone)]
+pub struct ArchiveTree {
+    archive: AnonymousProvider<PathBuf>,
+    format: Option<ArchiveFormat>,
+    expand_root: AnonymousProvider<PathBuf>,
+    expanded: Arc<Mutex<Option<Expanded>>>,
+}
+
+#[derive(Debug)]
+struct Expanded {
+    archive: PathBuf,
+    modified: Option<SystemTime>,
+    dir: PathBuf,
+}
+
+impl ArchiveTree {
+    /// Creates a tree over an archive, which is extracted into a directory within `expand_root`.
+    /// If no format is given, the format is detected from the name of the archive.
+    pub fn new<A, R>(archive: A, format: Option<ArchiveFormat>, expand_root: R) -> Self
+    where
+        A: Provider<PathBuf> + 'static,
+        R: Provider<PathBuf> + 'static,
+    {
+        Self {
+            archive: AnonymousProvider::new(archive),
+            format,
+            expand_root: AnonymousProvider::new(expand_root),
+            expanded: Default::default(),
+        }
+    }
+
+    /// The format of the archive
+    pub fn format(&self) -> Option<ArchiveFormat> {
+        self.format
+            .or_else(|| self.archive.try_get().and_then(ArchiveFormat::detect))
+    }
+
+    /// Extracts the archive if it hasn't been extracted since it last changed, and returns the
+    /// directory it was extracted into.
+    pub fn root(&self) -> BuildResult<PathBuf> {
+        let archive = self.archive.try_get().ok_or_else(|| {
+            BuildException::custom(&format!("no archive set for {:?}", self.archive))
+        })?;
+        let format = self.format().ok_or_else(|| {
+            BuildException::custom(&format!("could not detect archive format of {:?}", archive))
+        })?;
+        let modified = std::fs::metadata(&archive)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        let mut expanded = self.expanded.lock();
+        if let Some(expanded) = &*expanded {
+            if expanded.archive == archive && expanded.modified == modified {
+                return Ok(expanded.dir.clone());
+            }
+        }
+
+        let dir = self.expand_root.get().join(expand_dir_name(&archive));
+        trace!("extracting {:?} into {:?}", archive, dir);
+        let extract = || -> io::Result<()> {
+            if dir.exists() {
+                std::fs::remove_dir_all(&dir)?;
+            }
+            format.extract(&archive, &dir)
+        };
+        extract().map_err(PayloadError::<BuildException>::new)?;
+
+        *expanded = Some(Expanded {
+            archive,
+            modified,
+            dir: dir.clone(),
+        });
+        Ok(dir)
+    }
+}
+
+/// A directory name unique to an archive
+fn expand_dir_name(archive: &Path) -> String {
+    let mut hasher = DefaultHasher::new();
+    archive.hash(&mut hasher);
+    let name = archive
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!("{}-{:016x}", name, hasher.finish())
+}
+
+impl FileCollection for ArchiveTree {
+    fn files(&self) -> HashSet<PathBuf> {
+        match self.try_files() {
+            Ok(files) => files,
+            Err(e) => {
+                warn!("could not read archive: {}", e.kind());
+                HashSet::new()
+            }
+        }
+    }
+
+    fn try_files(&self) -> BuildResult<HashSet<PathBuf>> {
+        let root = self.root()?;
+        FileSet::with_path(root).try_files()
+    }
+}
+
+impl Buildable for ArchiveTree {
+    fn get_dependencies(&self, project: &Project) -> ProjectResult<HashSet<TaskId>> {
+        self.archive.get_dependencies(project)
+    }
+}
+
+impl Provider<ArchiveTree> for ArchiveTree {
+    fn try_get(&self) -> Option<ArchiveTree> {
+        Some(self.clone())
+    }
+}
+
+impl From<ArchiveTree> for FileSet {
+    fn from(tree: ArchiveTree) -> Self {
+        let mut set = FileSet::with_provider(tree.clone());
+        set.built_by(tree);
+        set
+    }
+}
+
+impl Debug for ArchiveTree {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArchiveTree")
+            .field("archive", &self.archive)
+            .field("format", &self.format)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn detect_formats() {
+        assert_eq!(ArchiveFormat::detect("a.zip"), Some(ArchiveFormat::Zip));
+        assert_eq!(ArchiveFormat::detect("a.tar"), Some(ArchiveFormat::Tar));
+        assert_eq!(ArchiveFormat::detect("a.tgz"), Some(ArchiveFormat::TarGz));
+        assert_eq!(
+            ArchiveFormat::detect("dir/a.tar.zst"),
+            Some(ArchiveFormat::TarZst)
+        );
+        assert_eq!(ArchiveFormat::detect("a.txt"), None);
+    }
+
+    #[test]
+    fn tree_is_extracted_lazily() {
+        let dir = TempDir::new().unwrap();
+        let archive = dir.path().join("files.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        zip.start_file("nested/a.txt", Default::default()).unwrap();
+        zip.write_all(b"a").unwrap();
+        zip.finish().unwrap();
+
+        let expand_root = dir.path().join("expanded");
+        let tree = ArchiveTree::new(
+            AnonymousProvider::with_value(archive),
+            None,
+            AnonymousProvider::with_value(expand_root.clone()),
+        );
+        assert!(!expand_root.exists());
+
+        let files = tree.files();
+        let root = tree.root().unwrap();
+        assert!(root.starts_with(&expand_root));
+        assert!(files.contains(&root.join("nested").join("a.txt")));
+        assert_eq!(
+            std::fs::read_to_string(root.join("nested").join("a.txt")).unwrap(),
+            "a"
+        );
+    }
+}
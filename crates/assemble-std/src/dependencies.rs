@@ -1,3 +1,4 @@
 //! Additional dependency types
 
+pub mod maven;
 pub mod web;
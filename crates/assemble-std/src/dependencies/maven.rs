@@ -0,0 +1,875 @@
+//! Dependencies resolved from maven compatible repositories.
+//!
+//! Maven dependencies are declared using `group:artifact:version` coordinates, optionally followed by
+//! a classifier (`group:artifact:version:classifier`) and an extension (`group:artifact:version@zip`).
+//! When resolved, the POM of the dependency is downloaded and its compile and runtime dependencies
+//! are resolved transitively. If two versions of the same module are found, the one nearest to the
+//! requested dependency is used.
+//!
+//! Release artifacts are cached forever. Snapshot artifacts are refreshed every time they are
+//! resolved.
+
+use assemble_core::dependencies::{
+    AcquisitionError, Dependency, DependencyType, Registry, RegistryContainer, ResolvedDependency,
+    ResolvedDependencyBuilder,
+};
+use assemble_core::project::buildable::{BuildableObject, GetBuildable};
+use assemble_core::web::{WebClient, WebError};
+use once_cell::sync::Lazy;
+use reqwest::StatusCode;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use url::Url;
+
+/// The url of maven central
+pub const MAVEN_CENTRAL_URL: &str = "https://repo.maven.apache.org/maven2/";
+
+/// The dependency type of maven dependencies. Supported by every [`MavenRegistry`](MavenRegistry).
+pub static MAVEN_TYPE: Lazy<DependencyType> =
+    Lazy::new(|| DependencyType::new("maven", "maven_repository", ["jar", "pom", "*"]));
+
+/// The maximum depth of parent POMs followed
+const MAX_PARENT_DEPTH: usize = 16;
+
+/// A maven compatible repository
+#[derive(Debug, Clone)]
+pub struct MavenRegistry {
+    name: String,
+    base_url: Url,
+}
+
+impl MavenRegistry {
+    /// Create a new maven registry from a url
+    pub fn new(name: &str, url: &str) -> Result<MavenRegistry, url::ParseError> {
+        let mut url = url.to_string();
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+        Url::parse(&url).map(|base_url| Self {
+            name: name.to_string(),
+            base_url,
+        })
+    }
+
+    /// The maven central repository
+    pub fn maven_central() -> MavenRegistry {
+        Self::new("mavenCentral", MAVEN_CENTRAL_URL).expect("maven central url is valid")
+    }
+
+    /// The name of the registry
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Registry for MavenRegistry {
+    fn url(&self) -> Url {
+        self.base_url.clone()
+    }
+
+    fn supported(&self) -> Vec<DependencyType> {
+        vec![MAVEN_TYPE.clone()]
+    }
+}
+
+/// Adds maven registries to a [`RegistryContainer`](RegistryContainer)
+pub trait MavenRegistries {
+    /// Adds maven central to the registries
+    fn maven_central(&mut self);
+
+    /// Adds a maven repository at the given url to the registries
+    fn maven(&mut self, name: &str, url: &str) -> Result<(), url::ParseError>;
+}
+
+impl MavenRegistries for RegistryContainer {
+    fn maven_central(&mut self) {
+        self.add_registry(MavenRegistry::maven_central());
+    }
+
+    fn maven(&mut self, name: &str, url: &str) -> Result<(), url::ParseError> {
+        self.add_registry(MavenRegistry::new(name, url)?);
+        Ok(())
+    }
+}
+
+/// An error occurred while resolving a maven dependency
+#[derive(Debug, thiserror::Error)]
+pub enum MavenError {
+    /// The coordinate string couldn't be parsed
+    #[error(
+        "invalid maven coordinate {0:?} (expected group:artifact:version[:classifier][@extension])"
+    )]
+    InvalidCoordinate(String),
+    /// A dependency in a POM has no version, and none is managed
+    #[error("no version could be determined for {0}")]
+    MissingVersion(String),
+    /// A file could not be found in the repository
+    #[error("{0} not found in repository")]
+    NotFound(Url),
+    /// Error occurred while making a request
+    #[error(transparent)]
+    Web(#[from] WebError),
+    /// Error occurred while parsing xml
+    #[error(transparent)]
+    Xml(#[from] roxmltree::Error),
+    /// Io error occurred
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// A url couldn't be created
+    #[error(transparent)]
+    Url(#[from] url::ParseError),
+}
+
+impl From<MavenError> for AcquisitionError {
+    fn from(e: MavenError) -> Self {
+        AcquisitionError::custom(e)
+    }
+}
+
+/// The coordinates of a maven artifact
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MavenCoordinate {
+    /// The group of the artifact
+    pub group: String,
+    /// The id of the artifact
+    pub artifact: String,
+    /// The version of the artifact
+    pub version: String,
+    /// An optional classifier for the artifact
+    pub classifier: Option<String>,
+    /// The extension of the artifact file
+    pub extension: String,
+}
+
+impl MavenCoordinate {
+    /// Create a new coordinate for a jar artifact
+    pub fn new(group: &str, artifact: &str, version: &str) -> Self {
+        Self {
+            group: group.to_string(),
+            artifact: artifact.to_string(),
+            version: version.to_string(),
+            classifier: None,
+            extension: "jar".to_string(),
+        }
+    }
+
+    /// Whether this coordinate refers to a snapshot version
+    pub fn is_snapshot(&self) -> bool {
+        self.version.ends_with("-SNAPSHOT")
+    }
+
+    /// The group and artifact of this coordinate, without a version
+    pub fn module(&self) -> String {
+        format!("{}:{}", self.group, self.artifact)
+    }
+
+    /// The directory of this coordinate relative to the root of the repository
+    pub fn directory(&self) -> String {
+        format!(
+            "{}/{}/{}",
+            self.group.replace('.', "/"),
+            self.artifact,
+            self.version
+        )
+    }
+
+    /// The name of a file of this coordinate, using the given file version and extension
+    fn file_name(&self, file_version: &str, extension: &str, classifier: bool) -> String {
+        match self.classifier.as_ref().filter(|_| classifier) {
+            None => format!("{}-{}.{}", self.artifact, file_version, extension),
+            Some(classifier) => format!(
+                "{}-{}-{}.{}",
+                self.artifact, file_version, classifier, extension
+            ),
+        }
+    }
+
+    fn with_version(&self, version: &str) -> Self {
+        let mut clone = self.clone();
+        clone.version = version.to_string();
+        clone
+    }
+
+    fn pom_coordinate(&self) -> Self {
+        Self::new(&self.group, &self.artifact, &self.version)
+    }
+}
+
+impl FromStr for MavenCoordinate {
+    type Err = MavenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (coords, extension) = match s.split_once('@') {
+            Some((coords, extension)) => (coords, extension.to_string()),
+            None => (s, "jar".to_string()),
+        };
+        let parts = coords.split(':').collect::<Vec<_>>();
+        if parts.iter().any(|p| p.is_empty()) || extension.is_empty() {
+            return Err(MavenError::InvalidCoordinate(s.to_string()));
+        }
+        match parts[..] {
+            [group, artifact, version] => Ok(Self {
+                extension,
+                ..Self::new(group, artifact, version)
+            }),
+            [group, artifact, version, classifier] => Ok(Self {
+                classifier: Some(classifier.to_string()),
+                extension,
+                ..Self::new(group, artifact, version)
+            }),
+            _ => Err(MavenError::InvalidCoordinate(s.to_string())),
+        }
+    }
+}
+
+impl Display for MavenCoordinate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.group, self.artifact, self.version)?;
+        if let Some(classifier) = &self.classifier {
+            write!(f, ":{}", classifier)?;
+        }
+        if self.extension != "jar" {
+            write!(f, "@{}", self.extension)?;
+        }
+        Ok(())
+    }
+}
+
+/// A dependency on an artifact within a maven repository
+#[derive(Debug, Clone)]
+pub struct MavenDependency {
+    coordinate: MavenCoordinate,
+    transitive: bool,
+}
+
+impl MavenDependency {
+    /// Create a new maven dependency from a coordinate string
+    pub fn new(coordinate: &str) -> Result<Self, MavenError> {
+        Ok(Self {
+            coordinate: coordinate.parse()?,
+            transitive: true,
+        })
+    }
+
+    /// Only resolve the artifact itself, without any of its dependencies
+    pub fn intransitive(mut self) -> Self {
+        self.transitive = false;
+        self
+    }
+
+    /// The coordinate of this dependency
+    pub fn coordinate(&self) -> &MavenCoordinate {
+        &self.coordinate
+    }
+}
+
+impl GetBuildable for MavenDependency {
+    fn as_buildable(&self) -> BuildableObject {
+        BuildableObject::None
+    }
+}
+
+impl Dependency for MavenDependency {
+    fn id(&self) -> String {
+        self.coordinate.module()
+    }
+
+    fn dep_type(&self) -> DependencyType {
+        MAVEN_TYPE.clone()
+    }
+
+    fn try_resolve(
+        &self,
+        registry: &dyn Registry,
+        cache_path: &Path,
+    ) -> Result<ResolvedDependency, AcquisitionError> {
+        let mut resolver = MavenResolver::new(
+            registry.url(),
+            registry.web_client(),
+            cache_path.join("maven"),
+        );
+        let files = resolver.resolve(&self.coordinate, self.transitive)?;
+        let mut files = files.into_iter();
+        let first = files.next().ok_or(AcquisitionError::MissingFile)?;
+        let mut builder = ResolvedDependencyBuilder::new(first);
+        builder.add_many(files);
+        Ok(builder.version(&self.coordinate.version).finish())
+    }
+
+    fn with_version(&self, version: &str) -> Option<Box<dyn Dependency + Send + Sync>> {
+        let mut forced = self.clone();
+        forced.coordinate.version = version.to_string();
+        Some(Box::new(forced))
+    }
+}
+
+/// A dependency declared within a POM
+#[derive(Debug, Clone, Default)]
+struct PomDependency {
+    group: String,
+    artifact: String,
+    version: Option<String>,
+    scope: Option<String>,
+    classifier: Option<String>,
+    ty: Option<String>,
+    optional: bool,
+}
+
+impl PomDependency {
+    fn module(&self) -> String {
+        format!("{}:{}", self.group, self.artifact)
+    }
+
+    /// Whether this dependency is required at runtime by consumers
+    fn is_transitive(&self) -> bool {
+        !self.optional
+            && matches!(
+                self.scope.as_deref(),
+                None | Some("compile") | Some("runtime")
+            )
+    }
+}
+
+/// The parts of a POM used for resolution
+#[derive(Debug, Clone, Default)]
+struct Pom {
+    group: Option<String>,
+    artifact: String,
+    version: Option<String>,
+    packaging: Option<String>,
+    parent: Option<MavenCoordinate>,
+    properties: HashMap<String, String>,
+    dependencies: Vec<PomDependency>,
+    managed: Vec<PomDependency>,
+}
+
+impl Pom {
+    fn parse(xml: &str) -> Result<Self, MavenError> {
+        let document = roxmltree::Document::parse(xml)?;
+        let project = document.root_element();
+
+        let mut pom = Pom {
+            group: child_text(project, "groupId"),
+            artifact: child_text(project, "artifactId").unwrap_or_default(),
+            version: child_text(project, "version"),
+            packaging: child_text(project, "packaging"),
+            ..Default::default()
+        };
+
+        if let Some(parent) = child(project, "parent") {
+            let group = child_text(parent, "groupId").unwrap_or_default();
+            let artifact = child_text(parent, "artifactId").unwrap_or_default();
+            let version = child_text(parent, "version").unwrap_or_default();
+            pom.parent = Some(MavenCoordinate::new(&group, &artifact, &version));
+        }
+
+        if let Some(properties) = child(project, "properties") {
+            for property in properties.children().filter(|n| n.is_element()) {
+                pom.properties.insert(
+                    property.tag_name().name().to_string(),
+                    property.text().unwrap_or_default().trim().to_string(),
+                );
+            }
+        }
+
+        if let Some(dependencies) = child(project, "dependencies") {
+            pom.dependencies = parse_dependencies(dependencies);
+        }
+        if let Some(dependencies) =
+            child(project, "dependencyManagement").and_then(|n| child(n, "dependencies"))
+        {
+            pom.managed = parse_dependencies(dependencies);
+        }
+
+        Ok(pom)
+    }
+
+    /// Merges the values of a parent POM into this POM. Values already within this POM take
+    /// precedence.
+    fn inherit(&mut self, parent: &Pom) {
+        if self.group.is_none() {
+            self.group = parent.group.clone();
+        }
+        if self.version.is_none() {
+            self.version = parent.version.clone();
+        }
+        for (key, value) in &parent.properties {
+            self.properties
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+        self.managed.extend(parent.managed.iter().cloned());
+        let declared = self
+            .dependencies
+            .iter()
+            .map(PomDependency::module)
+            .collect::<HashSet<_>>();
+        self.dependencies.extend(
+            parent
+                .dependencies
+                .iter()
+                .filter(|d| !declared.contains(&d.module()))
+                .cloned(),
+        );
+    }
+
+    /// Replaces `${...}` expressions with the values of properties
+    fn interpolate(&self, value: &str) -> String {
+        let mut output = String::new();
+        let mut rest = value;
+        while let Some(start) = rest.find("${") {
+            output.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            match after.find('}') {
+                Some(end) => {
+                    let key = &after[..end];
+                    match self.property(key) {
+                        Some(value) => output.push_str(&value),
+                        None => output.push_str(&rest[start..start + end + 3]),
+                    }
+                    rest = &after[end + 1..];
+                }
+                None => {
+                    output.push_str(&rest[start..]);
+                    rest = "";
+                }
+            }
+        }
+        output.push_str(rest);
+        output
+    }
+
+    fn property(&self, key: &str) -> Option<String> {
+        match key {
+            "project.version" | "pom.version" | "version" => self.version.clone(),
+            "project.groupId" | "pom.groupId" | "groupId" => self.group.clone(),
+            "project.artifactId" | "pom.artifactId" | "artifactId" => Some(self.artifact.clone()),
+            "project.parent.version" => self.parent.as_ref().map(|p| p.version.clone()),
+            "project.parent.groupId" => self.parent.as_ref().map(|p| p.group.clone()),
+            key => self.properties.get(key).cloned(),
+        }
+    }
+
+    /// Gets the managed version of a module
+    fn managed_version(&self, module: &str) -> Option<String> {
+        self.managed
+            .iter()
+            .find(|d| d.module() == module)
+            .and_then(|d| d.version.clone())
+    }
+
+    /// Interpolates every value within the dependencies of this POM
+    fn interpolate_dependencies(&mut self) {
+        let interpolate = |pom: &Pom, deps: &mut Vec<PomDependency>| {
+            for dep in deps {
+                dep.group = pom.interpolate(&dep.group);
+                dep.artifact = pom.interpolate(&dep.artifact);
+                dep.version = dep.version.as_ref().map(|v| pom.interpolate(v));
+                dep.classifier = dep.classifier.as_ref().map(|v| pom.interpolate(v));
+            }
+        };
+        let mut dependencies = std::mem::take(&mut self.dependencies);
+        let mut managed = std::mem::take(&mut self.managed);
+        interpolate(self, &mut dependencies);
+        interpolate(self, &mut managed);
+        self.dependencies = dependencies;
+        self.managed = managed;
+    }
+}
+
+fn child<'a, 'i>(node: roxmltree::Node<'a, 'i>, name: &str) -> Option<roxmltree::Node<'a, 'i>> {
+    node.children()
+        .find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn child_text(node: roxmltree::Node, name: &str) -> Option<String> {
+    child(node, name)
+        .and_then(|n| n.text())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn parse_dependencies(node: roxmltree::Node) -> Vec<PomDependency> {
+    node.children()
+        .filter(|n| n.is_element() && n.tag_name().name() == "dependency")
+        .map(|dep| PomDependency {
+            group: child_text(dep, "groupId").unwrap_or_default(),
+            artifact: child_text(dep, "artifactId").unwrap_or_default(),
+            version: child_text(dep, "version"),
+            scope: child_text(dep, "scope"),
+            classifier: child_text(dep, "classifier"),
+            ty: child_text(dep, "type"),
+            optional: child_text(dep, "optional").as_deref() == Some("true"),
+        })
+        .collect()
+}
+
+/// Parses the timestamped version of a snapshot from a `maven-metadata.xml` file. Returns `None`
+/// if the snapshot isn't timestamped.
+fn parse_snapshot_version(xml: &str, version: &str) -> Result<Option<String>, MavenError> {
+    let document = roxmltree::Document::parse(xml)?;
+    let snapshot = child(document.root_element(), "versioning").and_then(|v| child(v, "snapshot"));
+    let snapshot = match snapshot {
+        None => return Ok(None),
+        Some(snapshot) => snapshot,
+    };
+    if child_text(snapshot, "localCopy").as_deref() == Some("true") {
+        return Ok(None);
+    }
+    Ok(child_text(snapshot, "timestamp")
+        .zip(child_text(snapshot, "buildNumber"))
+        .map(|(timestamp, build)| version.replace("SNAPSHOT", &format!("{}-{}", timestamp, build))))
+}
+
+/// Resolves maven coordinates against a single repository
+struct MavenResolver {
+    base_url: Url,
+    client: WebClient,
+    cache_root: PathBuf,
+    poms: HashMap<MavenCoordinate, Pom>,
+    snapshot_versions: HashMap<MavenCoordinate, String>,
+}
+
+impl MavenResolver {
+    fn new(base_url: Url, client: WebClient, cache_root: PathBuf) -> Self {
+        Self {
+            base_url,
+            client,
+            cache_root,
+            poms: HashMap::new(),
+            snapshot_versions: HashMap::new(),
+        }
+    }
+
+    /// Downloads a file from the repository into the cache. If `cacheable`, an existing file in the
+    /// cache is used instead of downloading it again.
+    fn fetch(&self, relative: &str, cacheable: bool) -> Result<PathBuf, MavenError> {
+        let destination = self.cache_root.join(relative);
+        if cacheable && destination.exists() {
+            trace!("using cached {:?}", destination);
+            return Ok(destination);
+        }
+        let url = self.base_url.join(relative)?;
+        debug!("downloading {}", url);
+        match self.client.download(&url, &destination) {
+            Ok(_) => Ok(destination),
+            Err(WebError::Status { status, .. }) if status == StatusCode::NOT_FOUND => {
+                Err(MavenError::NotFound(url))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Gets the version used in file names for a coordinate. For releases, this is just the version.
+    fn file_version(&mut self, coordinate: &MavenCoordinate) -> Result<String, MavenError> {
+        if !coordinate.is_snapshot() {
+            return Ok(coordinate.version.clone());
+        }
+        let key = coordinate.pom_coordinate();
+        if let Some(version) = self.snapshot_versions.get(&key) {
+            return Ok(version.clone());
+        }
+        let metadata_path = format!("{}/maven-metadata.xml", coordinate.directory());
+        let version = match self.fetch(&metadata_path, false) {
+            Ok(path) => {
+                let xml = fs::read_to_string(path)?;
+                parse_snapshot_version(&xml, &coordinate.version)?
+                    .unwrap_or_else(|| coordinate.version.clone())
+            }
+            Err(MavenError::NotFound(_)) => coordinate.version.clone(),
+            Err(e) => return Err(e),
+        };
+        self.snapshot_versions.insert(key, version.clone());
+        Ok(version)
+    }
+
+    /// Downloads a file of a coordinate
+    fn fetch_file(
+        &mut self,
+        coordinate: &MavenCoordinate,
+        extension: &str,
+        classifier: bool,
+    ) -> Result<PathBuf, MavenError> {
+        let file_version = self.file_version(coordinate)?;
+        let path = format!(
+            "{}/{}",
+            coordinate.directory(),
+            coordinate.file_name(&file_version, extension, classifier)
+        );
+        self.fetch(&path, !coordinate.is_snapshot())
+    }
+
+    /// Gets the effective POM of a coordinate, with parents merged in and properties interpolated
+    fn pom(&mut self, coordinate: &MavenCoordinate) -> Result<Pom, MavenError> {
+        self.pom_at_depth(coordinate, 0)
+    }
+
+    fn pom_at_depth(
+        &mut self,
+        coordinate: &MavenCoordinate,
+        depth: usize,
+    ) -> Result<Pom, MavenError> {
+        let key = coordinate.pom_coordinate();
+        if let Some(pom) = self.poms.get(&key) {
+            return Ok(pom.clone());
+        }
+        let path = self.fetch_file(&key, "pom", false)?;
+        let mut pom = Pom::parse(&fs::read_to_string(path)?)?;
+
+        if let Some(parent) = pom.parent.clone() {
+            if depth < MAX_PARENT_DEPTH {
+                let parent = self.pom_at_depth(&parent, depth + 1)?;
+                pom.inherit(&parent);
+            } else {
+                warn!(
+                    "parent depth of {} exceeds {}",
+                    coordinate, MAX_PARENT_DEPTH
+                );
+            }
+        }
+        pom.interpolate_dependencies();
+
+        // import bill of materials
+        let imports = pom
+            .managed
+            .iter()
+            .filter(|d| d.scope.as_deref() == Some("import") && d.ty.as_deref() == Some("pom"))
+            .filter_map(|d| {
+                d.version
+                    .as_ref()
+                    .map(|v| MavenCoordinate::new(&d.group, &d.artifact, v))
+            })
+            .collect::<Vec<_>>();
+        for import in imports {
+            if depth < MAX_PARENT_DEPTH {
+                let bom = self.pom_at_depth(&import, depth + 1)?;
+                pom.managed.extend(bom.managed);
+            }
+        }
+
+        self.poms.insert(key, pom.clone());
+        Ok(pom)
+    }
+
+    /// Resolves a coordinate, returning the files of the coordinate and all of its transitive
+    /// dependencies.
+    fn resolve(
+        &mut self,
+        root: &MavenCoordinate,
+        transitive: bool,
+    ) -> Result<Vec<PathBuf>, MavenError> {
+        let mut files = vec![];
+        let mut selected: HashMap<String, String> = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(root.clone());
+        selected.insert(root.module(), root.version.clone());
+
+        let root_pom = if transitive {
+            Some(self.pom(root)?)
+        } else {
+            None
+        };
+
+        while let Some(coordinate) = queue.pop_front() {
+            let pom = match self.pom(&coordinate) {
+                Ok(pom) => Some(pom),
+                Err(MavenError::NotFound(url)) if !transitive => {
+                    trace!("no pom found at {}", url);
+                    None
+                }
+                Err(e) => return Err(e),
+            };
+
+            let is_pom_only = coordinate.extension == "jar"
+                && pom
+                    .as_ref()
+                    .and_then(|p| p.packaging.as_deref())
+                    .map_or(false, |p| p == "pom");
+            if !is_pom_only {
+                files.push(self.fetch_file(&coordinate, &coordinate.extension.clone(), true)?);
+            }
+
+            if !transitive {
+                continue;
+            }
+            let pom = pom.expect("pom always present for transitive resolution");
+
+            for dependency in pom.dependencies.iter().filter(|d| d.is_transitive()) {
+                let module = dependency.module();
+                if selected.contains_key(&module) {
+                    // nearest version wins
+                    continue;
+                }
+                let version = root_pom
+                    .as_ref()
+                    .and_then(|root| root.managed_version(&module))
+                    .or_else(|| dependency.version.clone())
+                    .or_else(|| pom.managed_version(&module))
+                    .ok_or_else(|| MavenError::MissingVersion(module.clone()))?;
+                let version = strip_version_range(&version);
+
+                selected.insert(module, version.clone());
+                let mut next =
+                    MavenCoordinate::new(&dependency.group, &dependency.artifact, &version);
+                next.classifier = dependency.classifier.clone();
+                if let Some(ty) = &dependency.ty {
+                    if ty != "jar" && ty != "bundle" {
+                        next.extension = ty.clone();
+                    }
+                }
+                queue.push_back(next.with_version(&version));
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+/// Maven allows version ranges such as `[1.0,2.0)`. Without access to the list of versions, the
+/// lower bound of a range is used, and a single pinned version `[1.0]` is used as is.
+fn strip_version_range(version: &str) -> String {
+    let trimmed = version.trim();
+    if trimmed.starts_with('[') || trimmed.starts_with('(') {
+        trimmed
+            .trim_matches(|c| c == '[' || c == ']' || c == '(' || c == ')')
+            .split(',')
+            .map(str::trim)
+            .find(|s| !s.is_empty())
+            .unwrap_or(trimmed)
+            .to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_coordinates() {
+        let coordinate: MavenCoordinate = "org.example:library:1.0.0".parse().unwrap();
+        assert_eq!(
+            coordinate,
+            MavenCoordinate::new("org.example", "library", "1.0.0")
+        );
+        assert_eq!(coordinate.directory(), "org/example/library/1.0.0");
+
+        let coordinate: MavenCoordinate = "org.example:library:1.0.0:sources@zip".parse().unwrap();
+        assert_eq!(coordinate.classifier.as_deref(), Some("sources"));
+        assert_eq!(coordinate.extension, "zip");
+        assert_eq!(
+            coordinate.to_string(),
+            "org.example:library:1.0.0:sources@zip"
+        );
+
+        assert!("org.example:library".parse::<MavenCoordinate>().is_err());
+        assert!("org.example::1.0".parse::<MavenCoordinate>().is_err());
+    }
+
+    #[test]
+    fn parse_pom_with_parent_and_properties() {
+        let parent = Pom::parse(
+            r#"
+            <project>
+                <groupId>org.example</groupId>
+                <artifactId>parent</artifactId>
+                <version>2.0</version>
+                <properties><lib.version>3.1</lib.version></properties>
+                <dependencyManagement>
+                    <dependencies>
+                        <dependency>
+                            <groupId>org.other</groupId>
+                            <artifactId>managed</artifactId>
+                            <version>${lib.version}</version>
+                        </dependency>
+                    </dependencies>
+                </dependencyManagement>
+            </project>
+            "#,
+        )
+        .unwrap();
+        let mut pom = Pom::parse(
+            r#"
+            <project xmlns="http://maven.apache.org/POM/4.0.0">
+                <parent>
+                    <groupId>org.example</groupId>
+                    <artifactId>parent</artifactId>
+                    <version>2.0</version>
+                </parent>
+                <artifactId>child</artifactId>
+                <dependencies>
+                    <dependency>
+                        <groupId>${project.groupId}</groupId>
+                        <artifactId>sibling</artifactId>
+                        <version>${project.version}</version>
+                    </dependency>
+                    <dependency>
+                        <groupId>org.other</groupId>
+                        <artifactId>managed</artifactId>
+                    </dependency>
+                    <dependency>
+                        <groupId>junit</groupId>
+                        <artifactId>junit</artifactId>
+                        <version>4.13</version>
+                        <scope>test</scope>
+                    </dependency>
+                </dependencies>
+            </project>
+            "#,
+        )
+        .unwrap();
+        pom.inherit(&parent);
+        pom.interpolate_dependencies();
+
+        assert_eq!(pom.group.as_deref(), Some("org.example"));
+        let sibling = &pom.dependencies[0];
+        assert_eq!(sibling.group, "org.example");
+        assert_eq!(sibling.version.as_deref(), Some("2.0"));
+        assert!(pom.dependencies[0].is_transitive());
+        assert!(!pom.dependencies[2].is_transitive());
+        assert_eq!(
+            pom.managed_version("org.other:managed").as_deref(),
+            Some("3.1")
+        );
+    }
+
+    #[test]
+    fn snapshot_versions() {
+        let xml = r#"
+        <metadata>
+            <versioning>
+                <snapshot>
+                    <timestamp>20221015.120000</timestamp>
+                    <buildNumber>7</buildNumber>
+                </snapshot>
+            </versioning>
+        </metadata>
+        "#;
+        assert_eq!(
+            parse_snapshot_version(xml, "1.0-SNAPSHOT")
+                .unwrap()
+                .as_deref(),
+            Some("1.0-20221015.120000-7")
+        );
+    }
+
+    #[test]
+    fn force_version() {
+        let dependency = MavenDependency::new("org.example:library:1.0.0").unwrap();
+        let forced = dependency.with_version("2.0.0").unwrap();
+        assert_eq!(forced.id(), "org.example:library");
+        assert!(format!("{:?}", forced).contains("\"2.0.0\""));
+    }
+
+    #[test]
+    fn version_ranges() {
+        assert_eq!(strip_version_range("[1.0,2.0)"), "1.0");
+        assert_eq!(strip_version_range("[1.5]"), "1.5");
+        assert_eq!(strip_version_range("1.2.3"), "1.2.3");
+    }
+}
@@ -5,14 +5,13 @@ use assemble_core::dependencies::{
     AcquisitionError, Dependency, DependencyType, Registry, ResolvedDependency,
     ResolvedDependencyBuilder,
 };
+use assemble_core::file_lock::FileLock;
 use assemble_core::project::buildable::{BuildableObject, GetBuildable};
 
 use std::ffi::{OsStr, OsString};
 use std::fmt::Debug;
-use std::fs::File;
-use std::io::Read;
+use std::fs;
 use std::path::{Path, PathBuf};
-use std::{fs, io};
 use url::Url;
 
 /// A web registry
@@ -113,19 +112,20 @@ impl Dependency for WebDependency {
             .join(file_name_sha.to_string())
             .join(file_name);
 
-        fs::create_dir_all(download_location.parent().unwrap())
-            .map_err(AcquisitionError::custom)?;
-
-        let response = reqwest::blocking::get(joined).map_err(AcquisitionError::custom)?;
+        let download_dir = download_location.parent().unwrap();
+        fs::create_dir_all(download_dir).map_err(AcquisitionError::custom)?;
 
-        let body = response.bytes().map_err(AcquisitionError::custom)?;
+        // other builds may be downloading the same file
+        let _lock = FileLock::acquire(
+            download_dir.join(".download.lock"),
+            &format!("download {}", joined),
+        )
+        .map_err(AcquisitionError::custom)?;
 
-        let mut file = File::options()
-            .write(true)
-            .create(true)
-            .open(&download_location)
+        registry
+            .web_client()
+            .download(&joined, &download_location)
             .map_err(AcquisitionError::custom)?;
-        io::copy(&mut body.as_ref(), &mut file).map_err(AcquisitionError::custom)?;
 
         Ok(ResolvedDependencyBuilder::new(download_location).finish())
     }
@@ -1,10 +1,13 @@
 //! Extensions to the [`Project`](assemble_core::Project)
 
+use crate::archives::{ArchiveFormat, ArchiveTree, EXPANDED_DIR};
 use crate::private::ProjectSealed;
 use crate::specs::exec_spec::{ExecHandle, ExecResult, ExecSpec, ExecSpecBuilder};
+use assemble_core::lazy_evaluation::{IntoProvider, ProviderExt};
 use assemble_core::prelude::ProjectResult;
 use assemble_core::project::ProjectError;
 use assemble_core::Project;
+use std::path::PathBuf;
 
 /// Adds [`ExecSpec`](crate::specs::exec_spec::ExecSpec) related methods to projects.
 pub trait ProjectExec: ProjectSealed {
@@ -42,6 +45,54 @@ impl ProjectExec for Project {
     }
 }
 
+/// Adds methods for reading archives to projects.
+pub trait ProjectArchives: ProjectSealed {
+    /// The files within a zip archive. Relative paths are resolved against the project directory.
+    fn zip_tree<P>(&self, path: P) -> ArchiveTree
+    where
+        P: IntoProvider<PathBuf>,
+        P::Provider: 'static;
+
+    /// The files within a tar archive, which may be compressed with gzip or zstd. The compression
+    /// is detected from the name of the archive. Relative paths are resolved against the project
+    /// directory.
+    fn tar_tree<P>(&self, path: P) -> ArchiveTree
+    where
+        P: IntoProvider<PathBuf>,
+        P::Provider: 'static;
+}
+
+impl ProjectArchives for Project {
+    fn zip_tree<P>(&self, path: P) -> ArchiveTree
+    where
+        P: IntoProvider<PathBuf>,
+        P::Provider: 'static,
+    {
+        archive_tree(self, path, Some(ArchiveFormat::Zip))
+    }
+
+    fn tar_tree<P>(&self, path: P) -> ArchiveTree
+    where
+        P: IntoProvider<PathBuf>,
+        P::Provider: 'static,
+    {
+        archive_tree(self, path, None)
+    }
+}
+
+fn archive_tree<P>(project: &Project, path: P, format: Option<ArchiveFormat>) -> ArchiveTree
+where
+    P: IntoProvider<PathBuf>,
+    P::Provider: 'static,
+{
+    let project_dir = project.project_dir();
+    let archive = path.into_provider().map(move |path| project_dir.join(path));
+    let expand_root = project
+        .build_dir()
+        .map(|build_dir| build_dir.join(EXPANDED_DIR));
+    ArchiveTree::new(archive, format, expand_root)
+}
+
 #[cfg(test)]
 mod test {
     use crate::ProjectExec;
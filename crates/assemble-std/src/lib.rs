@@ -9,14 +9,19 @@
 #[macro_use]
 extern crate log;
 
+pub mod archives;
 pub mod dependencies;
 pub mod extensions;
 pub mod specs;
 pub mod tasks;
+pub mod tools;
 
-pub use crate::extensions::project_extensions::ProjectExec;
+pub use crate::extensions::project_extensions::{ProjectArchives, ProjectExec};
 pub use crate::tasks::exec::Exec;
-pub use crate::tasks::files::{Delete, Dupe};
+pub use crate::tasks::files::{Clean, Delete, Dupe};
+pub use crate::tasks::script::Script;
+use assemble_core::defaults::plugins::LifecycleBasePlugin;
+use assemble_core::plugins::PluginAware;
 use assemble_core::Project;
 use assemble_core::__export::ProjectResult;
 
@@ -28,12 +33,35 @@ pub use assemble_core::*;
 #[macro_use]
 extern crate assemble_core;
 
-/// The default plugin for the std library. Is a no-op.
+/// The default plugin for the std library. Applies the
+/// [`LifecycleBasePlugin`](LifecycleBasePlugin), and attaches a [`Clean`](Clean) task to its `clean`
+/// task.
+///
+/// # Provided Tasks
+/// - `clean-outputs`: deletes the build directory and the outputs of every task in the project
 #[derive(Debug, Default)]
 pub struct Plugin;
+
+impl Plugin {
+    /// The name of the task deleting the outputs of the project
+    pub const CLEAN_OUTPUTS: &'static str = "clean-outputs";
+}
+
 impl assemble_core::Plugin<Project> for Plugin {
-    fn apply_to(&self, _project: &mut Project) -> ProjectResult {
-        Ok(())
+    fn apply_to(&self, project: &mut Project) -> ProjectResult {
+        project.apply_plugin::<LifecycleBasePlugin>()?;
+        let clean = project
+            .task_container_mut()
+            .register_task_with::<Clean, _>(Self::CLEAN_OUTPUTS, |t, _| {
+                t.set_group(LifecycleBasePlugin::BUILD_GROUP);
+                Ok(())
+            })?;
+        LifecycleBasePlugin::task(project, LifecycleBasePlugin::CLEAN)?.configure_with(
+            move |t, _| {
+                t.depends_on(clean);
+                Ok(())
+            },
+        )
     }
 }
 
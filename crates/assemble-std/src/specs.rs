@@ -1,4 +1,5 @@
 //! The standard specs that are used by the standard library
 
+pub mod copy_spec;
 pub mod dupe_spec;
 pub mod exec_spec;
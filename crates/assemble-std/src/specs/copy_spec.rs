@@ -0,0 +1,182 @@
+//! Define the CopySpec, which selects files to copy or archive
+
+use assemble_core::error::PayloadError;
+use assemble_core::exception::{BuildException, BuildResult};
+use assemble_core::file_collection::patterns::PatternSet;
+use assemble_core::file_collection::FileSet;
+use assemble_core::identifier::TaskId;
+use assemble_core::lazy_evaluation::{MapProp, Provider, ProviderExt, VecProp};
+use assemble_core::project::buildable::Buildable;
+use assemble_core::project::error::ProjectResult;
+use assemble_core::task::work_handler::WorkHandler;
+use assemble_core::Project;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+type RenameFn = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// Selects files to copy and how they are copied.
+///
+/// Directories in [`from`](CopySpec::from) are copied recursively, keeping the structure relative
+/// to the directory. Files can be selected with ant-style [`include`](CopySpec::include) and
+/// [`exclude`](CopySpec::exclude) patterns, which are matched against these relative paths. Text
+/// files can have `@token@` placeholders replaced with the values in [`tokens`](CopySpec::tokens).
+#[derive(Debug, Clone)]
+pub struct CopySpec {
+    /// The files and directories to copy from
+    pub from: VecProp<PathBuf>,
+    /// Glob patterns of files to copy. If empty, all files are included.
+    pub include: VecProp<String>,
+    /// Glob patterns of files not to copy. Takes precedence over `include`.
+    pub exclude: VecProp<String>,
+    /// Tokens replaced in copied text files, where `@key@` is replaced by its value.
+    pub tokens: MapProp<String, String>,
+    renames: Vec<Renamer>,
+}
+
+/// A file selected by a copy spec
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CopyEntry {
+    /// The file to copy
+    pub source: PathBuf,
+    /// Where the file is copied to, relative to the destination
+    pub destination: PathBuf,
+}
+
+impl CopySpec {
+    /// Creates a new copy spec, with properties owned by the given id
+    pub fn new(id: &TaskId) -> ProjectResult<Self> {
+        Ok(Self {
+            from: id.vec_prop("from").map_err(PayloadError::new)?,
+            include: id.vec_prop("include").map_err(PayloadError::new)?,
+            exclude: id.vec_prop("exclude").map_err(PayloadError::new)?,
+            tokens: id.map_prop("tokens").map_err(PayloadError::new)?,
+            renames: vec![],
+        })
+    }
+
+    /// Renames copied files. The function is given the file name and returns the new file name,
+    /// or `None` to leave the name unchanged. Renames are applied in the order they were added.
+    pub fn rename<F>(&mut self, rename: F)
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.renames.push(Renamer(Arc::new(rename)));
+    }
+
+    /// Registers the selected files and the configuration of this spec as inputs
+    pub fn configure_io(&self, work: &mut WorkHandler) -> ProjectResult {
+        let tokens = self
+            .tokens
+            .clone()
+            .map(|tokens| tokens.into_iter().collect::<BTreeMap<String, String>>());
+
+        work.add_input_files("from", self.from.clone().map(FileSet::from_iter))?;
+        work.add_input("include", self.include.clone())?;
+        work.add_input("exclude", self.exclude.clone())?;
+        work.add_input("tokens", tokens)?;
+        Ok(())
+    }
+
+    /// The files selected by this spec, ordered by their destination so that anything created
+    /// from them is reproducible.
+    pub fn entries(&self) -> BuildResult<Vec<CopyEntry>> {
+        let patterns = PatternSet::with_patterns(self.include.get(), self.exclude.get())
+            .map_err(PayloadError::<BuildException>::new)?;
+        let accepted = |relative: &Path| patterns.matches(relative);
+
+        let mut entries = vec![];
+        for root in self.from.get() {
+            if root.is_dir() {
+                for file in walk_dir(&root).map_err(PayloadError::<BuildException>::new)? {
+                    let relative = file
+                        .strip_prefix(&root)
+                        .expect("walked files are in root")
+                        .to_path_buf();
+                    if accepted(&relative) {
+                        entries.push(self.entry(file, relative));
+                    }
+                }
+            } else if let Some(file_name) = root.file_name() {
+                let relative = PathBuf::from(file_name);
+                if accepted(&relative) {
+                    entries.push(self.entry(root.clone(), relative));
+                }
+            }
+        }
+        entries.sort_by(|left, right| left.destination.cmp(&right.destination));
+        Ok(entries)
+    }
+
+    /// Reads the contents of an entry, with tokens replaced
+    pub fn read(&self, entry: &CopyEntry, tokens: &HashMap<String, String>) -> io::Result<Vec<u8>> {
+        let content = std::fs::read(&entry.source)?;
+        if tokens.is_empty() {
+            return Ok(content);
+        }
+        Ok(match String::from_utf8(content) {
+            Ok(mut text) => {
+                for (key, value) in tokens {
+                    text = text.replace(&format!("@{key}@"), value);
+                }
+                text.into_bytes()
+            }
+            Err(e) => e.into_bytes(),
+        })
+    }
+
+    fn entry(&self, source: PathBuf, relative: PathBuf) -> CopyEntry {
+        let renamed = relative
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| {
+                self.renames.iter().fold(name.to_string(), |name, renamer| {
+                    (renamer.0)(&name).unwrap_or(name)
+                })
+            });
+        let destination = match renamed {
+            Some(file_name) => relative.with_file_name(file_name),
+            None => relative,
+        };
+        CopyEntry {
+            source,
+            destination,
+        }
+    }
+}
+
+impl Buildable for CopySpec {
+    fn get_dependencies(&self, project: &Project) -> ProjectResult<HashSet<TaskId>> {
+        self.from.get_dependencies(project)
+    }
+}
+
+#[derive(Clone)]
+struct Renamer(RenameFn);
+
+impl Debug for Renamer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Renamer")
+    }
+}
+
+/// Gets all files within a directory, recursively, in a stable order
+fn walk_dir(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    let mut files = vec![];
+    for entry in entries {
+        if entry.is_dir() {
+            files.extend(walk_dir(&entry)?);
+        } else {
+            files.push(entry);
+        }
+    }
+    Ok(files)
+}
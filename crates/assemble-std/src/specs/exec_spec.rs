@@ -1,13 +1,17 @@
 //! The exec spec helps with defining executables
 
+use assemble_core::error::PayloadError;
 use assemble_core::exception::BuildException;
 use assemble_core::logging::{Origin, LOGGING_CONTROL};
 use assemble_core::prelude::{ProjectError, ProjectResult};
 use assemble_core::project::VisitProject;
+use assemble_core::startup::cancellation::{build_cancellation, CancellationToken};
+use assemble_core::task::undeclared_io::check_working_dir;
 use assemble_core::{BuildResult, Project};
 use log::Level;
 use std::collections::HashMap;
 use std::ffi::{OsStr, OsString};
+use std::fmt::{Debug, Formatter};
 use std::fs::File;
 use std::io::{BufWriter, ErrorKind, Read, Write};
 use std::path::{Path, PathBuf};
@@ -16,8 +20,8 @@ use std::str::Bytes;
 use std::string::FromUtf8Error;
 use std::sync::{Arc, RwLock};
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 use std::{io, thread};
-use assemble_core::error::PayloadError;
 
 /// Input for exec
 #[derive(Debug, Default, Clone)]
@@ -29,6 +33,13 @@ pub enum Input {
     File(PathBuf),
     /// Get input bytes from a byte vector
     Bytes(Vec<u8>),
+    /// Forward the standard input of assemble to the program, for programs that interact with the
+    /// user (such as `cargo login`).
+    ///
+    /// The terminal is acquired from the logger while the program runs, so progress rendering is
+    /// paused and the program's output is written directly to the terminal. The configured outputs
+    /// are ignored.
+    Terminal,
 }
 
 impl From<&[u8]> for Input {
@@ -93,6 +104,10 @@ pub enum Output {
     Log(#[doc("The log level to emit output to")] Level),
     /// Stream the output into a byte vector
     Bytes,
+    /// Call a function with every line of the output as the program runs, without the line
+    /// terminator. Tasks can use this to parse the output of a tool incrementally, such as to
+    /// update a progress bar.
+    LineCallback(LineCallback),
 }
 
 impl Output {
@@ -103,6 +118,31 @@ impl Output {
             append,
         }
     }
+
+    /// Create a new output that calls a function with every line of output
+    pub fn line_callback<F>(callback: F) -> Self
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        Self::LineCallback(LineCallback(Arc::new(callback)))
+    }
+}
+
+/// A function called with every line of output
+#[derive(Clone)]
+pub struct LineCallback(Arc<dyn Fn(&str) + Send + Sync>);
+
+impl LineCallback {
+    /// Call the function with a line
+    pub fn call(&self, line: &str) {
+        (self.0)(line)
+    }
+}
+
+impl Debug for LineCallback {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "LineCallback")
+    }
 }
 
 impl From<Level> for Output {
@@ -187,13 +227,16 @@ impl ExecSpec {
     ///
     /// # Error
     /// This method will return an error if the given path can not be canonicalized into an
-    /// absolute path, or the executable specified by this spec does not exist.
+    /// absolute path, or the executable specified by this spec does not exist. Also errors if the
+    /// working directory isn't declared by the executing task, and undeclared accesses
+    /// [fail](assemble_core::task::undeclared_io::UndeclaredIoPolicy::Fail).
     pub fn execute_spec<P>(self, path: P) -> ProjectResult<ExecHandle>
     where
         P: AsRef<Path>,
     {
         let path = path.as_ref();
         let working_dir = self.resolve_working_dir(path);
+        check_working_dir(&working_dir).map_err(ProjectError::custom)?;
         let origin = LOGGING_CONTROL.get_origin();
         ExecHandle::create(self, &working_dir, origin)
     }
@@ -430,11 +473,20 @@ impl ExecSpecBuilder {
     }
 }
 
+/// How often a waiting exec handle checks for timeouts and cancellation
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
 /// A handle into an exec spec. Can be queried to get output.
+///
+/// While waiting for the program to finish, the handle polls the cancellation token of the build.
+/// If the build is cancelled, the program and every process it started are killed, so that a hung
+/// tool can't keep a cancelled build running.
 pub struct ExecHandle {
     spec: ExecSpec,
     output: Arc<RwLock<ExecSpecOutputHandle>>,
     handle: JoinHandle<io::Result<ExitStatus>>,
+    process: ProcessTree,
+    cancellation: CancellationToken,
 }
 
 impl ExecHandle {
@@ -444,6 +496,23 @@ impl ExecHandle {
         command.args(spec.args());
 
         let input = match &spec.input {
+            Input::Terminal => {
+                let (process, join_handle) = execute_in_terminal(command)?;
+                let output_handle = Arc::new(RwLock::new(ExecSpecOutputHandle {
+                    origin,
+                    realized_output: Arc::new(RwLock::new(BufWriter::new(RealizedOutput::Null))),
+                    realized_output_err: Arc::new(RwLock::new(BufWriter::new(
+                        RealizedOutput::Null,
+                    ))),
+                }));
+                return Ok(Self {
+                    spec,
+                    output: output_handle,
+                    handle: join_handle,
+                    process,
+                    cancellation: build_cancellation(),
+                });
+            }
             Input::Null => Stdio::null(),
             Input::File(file) => {
                 let file = File::open(file)?;
@@ -460,7 +529,7 @@ impl ExecHandle {
         command.stderr(Stdio::piped());
 
         let realized_output = RealizedOutput::try_from(spec.output.clone())?;
-        let realized_output_err = RealizedOutput::try_from(spec.output.clone())?;
+        let realized_output_err = RealizedOutput::try_from(spec.output_err.clone())?;
 
         let output_handle = Arc::new(RwLock::new(ExecSpecOutputHandle {
             origin,
@@ -468,21 +537,92 @@ impl ExecHandle {
             realized_output_err: Arc::new(RwLock::new(BufWriter::new(realized_output_err))),
         }));
 
-        let join_handle = execute(command, &output_handle)?;
+        let (process, join_handle) = execute(command, &output_handle)?;
 
         Ok(Self {
             spec,
             output: output_handle,
             handle: join_handle,
+            process,
+            cancellation: build_cancellation(),
         })
     }
 
+    /// Uses a different cancellation token than the build's. The program is killed when the token
+    /// is cancelled while waiting for it.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// The id of the process running the program
+    pub fn id(&self) -> u32 {
+        self.process.pid
+    }
+
+    /// Whether the program has finished
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Kills the program, along with every process it started. Does nothing if the program has
+    /// already finished.
+    pub fn kill(&self) -> io::Result<()> {
+        if self.is_finished() {
+            return Ok(());
+        }
+        debug!(
+            "killing {:?} (pid {})",
+            self.spec.executable, self.process.pid
+        );
+        self.process.kill()
+    }
+
     /// Wait for the exec spec handle to finish
+    ///
+    /// # Error
+    /// Returns an error if the build is cancelled before the program finishes, in which case the
+    /// program is killed.
     pub fn wait(self) -> ProjectResult<ExecResult> {
+        self.wait_until(None)
+    }
+
+    /// Wait for the exec spec handle to finish, for at most `timeout`.
+    ///
+    /// # Error
+    /// Returns an error if the program doesn't finish in time, or if the build is cancelled before
+    /// it finishes. In both cases the program is killed.
+    pub fn wait_timeout(self, timeout: Duration) -> ProjectResult<ExecResult> {
+        self.wait_until(Some(timeout))
+    }
+
+    fn wait_until(self, timeout: Option<Duration>) -> ProjectResult<ExecResult> {
+        let start = Instant::now();
+        let mut killed_because = None;
+        while killed_because.is_none() && !self.is_finished() {
+            if let Some(reason) = self.cancellation.reason() {
+                killed_because = Some(reason.to_string());
+            } else if let Some(timeout) = timeout.filter(|&timeout| start.elapsed() >= timeout) {
+                killed_because = Some(format!("timed out after {:?}", timeout));
+            } else {
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+        if killed_because.is_some() {
+            self.kill()?;
+        }
+
         let result = self
             .handle
             .join()
             .map_err(|_| ProjectError::custom("Couldn't join thread"))??;
+        if let Some(reason) = killed_because {
+            return Err(ProjectError::custom(format!(
+                "{:?} was killed: {}",
+                self.spec.executable, reason
+            ))
+            .into());
+        }
         let output = self.output.read().map_err(PayloadError::new)?;
         let bytes = output.bytes();
         let bytes_err = output.bytes_err();
@@ -497,7 +637,7 @@ impl ExecHandle {
 fn execute(
     mut command: Command,
     output: &Arc<RwLock<ExecSpecOutputHandle>>,
-) -> ProjectResult<JoinHandle<io::Result<ExitStatus>>> {
+) -> ProjectResult<(ProcessTree, JoinHandle<io::Result<ExitStatus>>)> {
     trace!("attempting to execute command: {:?}", command);
     trace!("working_dir: {:?}", command.get_current_dir());
     trace!(
@@ -513,9 +653,17 @@ fn execute(
             .collect::<HashMap<_, _>>()
     );
 
+    // the program gets its own process group, so that it can be killed along with its children
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
     let spawned = command.spawn()?;
+    let process = ProcessTree {
+        pid: spawned.id(),
+        own_group: cfg!(unix),
+    };
     let output = output.clone();
-    Ok(thread::spawn(move || {
+    let handle = thread::spawn(move || {
         let mut spawned = spawned;
         let output = output;
         let origin = output.read().unwrap().origin.clone();
@@ -533,13 +681,19 @@ fn execute(
             let out_join = scope.spawn(move || -> io::Result<u64> {
                 LOGGING_CONTROL.with_origin(origin1, || {
                     let mut output = output.write().expect("couldnt get output");
-                    io::copy(&mut stdout, &mut *output)
+                    let copied = io::copy(&mut stdout, &mut *output)?;
+                    output.flush()?;
+                    output.get_mut().finish();
+                    Ok(copied)
                 })
             });
             let err_join = scope.spawn(move || -> io::Result<u64> {
                 LOGGING_CONTROL.with_origin(origin, || {
                     let mut output = output_err.write().expect("couldnt get output");
-                    io::copy(&mut stderr, &mut *output)
+                    let copied = io::copy(&mut stderr, &mut *output)?;
+                    output.flush()?;
+                    output.get_mut().finish();
+                    Ok(copied)
                 })
             });
 
@@ -552,7 +706,78 @@ fn execute(
             })??;
             Ok(out)
         })
-    }))
+    });
+    Ok((process, handle))
+}
+
+/// Executes a command with the terminal acquired, inheriting stdin, stdout and stderr.
+fn execute_in_terminal(
+    mut command: Command,
+) -> ProjectResult<(ProcessTree, JoinHandle<io::Result<ExitStatus>>)> {
+    trace!("attempting to execute command in terminal: {:?}", command);
+    command.stdin(Stdio::inherit());
+    command.stdout(Stdio::inherit());
+    command.stderr(Stdio::inherit());
+
+    let guard = LOGGING_CONTROL.acquire_terminal();
+    let mut spawned = command.spawn()?;
+    // interactive programs stay in the process group of assemble, which owns the terminal
+    let process = ProcessTree {
+        pid: spawned.id(),
+        own_group: false,
+    };
+    let handle = thread::spawn(move || {
+        let status = spawned.wait();
+        drop(guard);
+        status
+    });
+    Ok((process, handle))
+}
+
+/// A running program, and the processes it started
+#[derive(Debug, Clone, Copy)]
+struct ProcessTree {
+    pid: u32,
+    /// Whether the program leads its own process group
+    #[cfg_attr(windows, allow(dead_code))]
+    own_group: bool,
+}
+
+impl ProcessTree {
+    /// Kills every process in the tree.
+    ///
+    /// On unix, the process group of the program is killed, which contains every process it
+    /// started unless they moved to another group. On windows, `taskkill` walks the tree.
+    #[cfg(unix)]
+    fn kill(&self) -> io::Result<()> {
+        let result = if self.own_group {
+            unsafe { libc::killpg(self.pid as libc::pid_t, libc::SIGKILL) }
+        } else {
+            unsafe { libc::kill(self.pid as libc::pid_t, libc::SIGKILL) }
+        };
+        match result {
+            0 => Ok(()),
+            _ => match io::Error::last_os_error() {
+                // the processes already exited
+                e if e.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+                e => Err(e),
+            },
+        }
+    }
+
+    /// Kills every process in the tree.
+    ///
+    /// On unix, the process group of the program is killed, which contains every process it
+    /// started unless they moved to another group. On windows, `taskkill` walks the tree.
+    #[cfg(windows)]
+    fn kill(&self) -> io::Result<()> {
+        Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &self.pid.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|_| ())
+    }
 }
 
 struct ExecSpecOutputHandle {
@@ -615,6 +840,10 @@ impl TryFrom<Output> for RealizedOutput {
                 buffer: vec![],
             }),
             Output::Bytes => Ok(Self::Bytes(vec![])),
+            Output::LineCallback(callback) => Ok(Self::LineCallback {
+                callback,
+                buffer: vec![],
+            }),
         }
     }
 }
@@ -622,8 +851,63 @@ impl TryFrom<Output> for RealizedOutput {
 enum RealizedOutput {
     Null,
     File(File),
-    Log { lvl: Level, buffer: Vec<u8> },
+    Log {
+        lvl: Level,
+        buffer: Vec<u8>,
+    },
     Bytes(Vec<u8>),
+    LineCallback {
+        callback: LineCallback,
+        buffer: Vec<u8>,
+    },
+}
+
+impl RealizedOutput {
+    /// Emits every complete line in the buffer of a line based output
+    fn emit_lines(&mut self) {
+        match self {
+            RealizedOutput::Log { lvl, buffer } => {
+                for line in drain_lines(buffer) {
+                    log!(*lvl, "{}", line);
+                }
+            }
+            RealizedOutput::LineCallback { callback, buffer } => {
+                for line in drain_lines(buffer) {
+                    callback.call(&line);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Called once the program has exited. The last line of output is emitted even if it isn't
+    /// terminated.
+    fn finish(&mut self) {
+        self.emit_lines();
+        match self {
+            RealizedOutput::Log { lvl, buffer } if !buffer.is_empty() => {
+                log!(*lvl, "{}", String::from_utf8_lossy(buffer));
+                buffer.clear();
+            }
+            RealizedOutput::LineCallback { callback, buffer } if !buffer.is_empty() => {
+                callback.call(&String::from_utf8_lossy(buffer));
+                buffer.clear();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Removes the complete lines from a buffer, without their line terminators
+fn drain_lines(buffer: &mut Vec<u8>) -> Vec<String> {
+    let mut lines = vec![];
+    while let Some(pos) = buffer.iter().position(|&l| l == b'\n' || l == 0) {
+        let line = &buffer[..pos];
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        lines.push(String::from_utf8_lossy(line).to_string());
+        buffer.drain(..=pos);
+    }
+    lines
 }
 
 impl Write for RealizedOutput {
@@ -631,14 +915,9 @@ impl Write for RealizedOutput {
         match self {
             RealizedOutput::Null => Ok(buf.len()),
             RealizedOutput::File(f) => f.write(buf),
-            RealizedOutput::Log { lvl: l, buffer } => {
+            RealizedOutput::Log { buffer, .. } | RealizedOutput::LineCallback { buffer, .. } => {
                 buffer.extend(IntoIterator::into_iter(buf));
-                while let Some(pos) = buffer.iter().position(|&l| l == b'\n' || l == 0) {
-                    let line = &buffer[..pos];
-                    let string = String::from_utf8_lossy(line);
-                    log!(*l, "{}", string);
-                    buffer.drain(..=pos);
-                }
+                self.emit_lines();
                 Ok(buf.len())
             }
             RealizedOutput::Bytes(b) => {
@@ -651,13 +930,8 @@ impl Write for RealizedOutput {
     fn flush(&mut self) -> io::Result<()> {
         match self {
             RealizedOutput::File(file) => file.flush(),
-            RealizedOutput::Log { lvl, buffer } => {
-                while let Some(pos) = buffer.iter().position(|&l| l == b'\n' || l == 0) {
-                    let line = &buffer[..pos];
-                    let string = String::from_utf8_lossy(line);
-                    log!(*lvl, "{}", string);
-                    buffer.drain(..=pos);
-                }
+            RealizedOutput::Log { .. } | RealizedOutput::LineCallback { .. } => {
+                self.emit_lines();
                 Ok(())
             }
             _ => Ok(()),
@@ -722,6 +996,7 @@ impl ExecResult {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use assemble_core::startup::cancellation::CancellationReason;
 
     #[test]
     fn create_exec_spec() {
@@ -759,6 +1034,75 @@ mod tests {
         assert!(matches!(spawn, Err(_)), "Should return an error");
     }
 
+    #[test]
+    fn can_execute_in_terminal() {
+        let spec = ExecSpecBuilder::new()
+            .with_exec("echo")
+            .with_args(["hello", "terminal"])
+            .with_stdin(Input::Terminal)
+            .build()
+            .expect("Couldn't build exec spec");
+
+        let result = spec.execute_spec("/").expect("Couldn't create handle");
+        let wait = result.wait().expect("couldn't finish exec spec");
+        assert!(wait.success());
+        assert!(wait.bytes().is_none());
+    }
+
     #[test]
     fn emit_to_log() {}
+
+    #[cfg(unix)]
+    #[test]
+    fn timeout_kills_process_tree() {
+        let spec = ExecSpecBuilder::new()
+            .with_exec("sh")
+            .with_args(["-c", "sleep 30 & sleep 30"])
+            .with_stdout(Output::Null)
+            .build()
+            .expect("Couldn't build exec spec");
+
+        let start = Instant::now();
+        let handle = spec.execute_spec("/").expect("Couldn't create handle");
+        assert!(handle.wait_timeout(Duration::from_millis(200)).is_err());
+        // the backgrounded sleep holds the output pipe open, so this only finishes quickly if it
+        // was killed too
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn cancellation_kills_program() {
+        let spec = ExecSpecBuilder::new()
+            .with_exec("sleep")
+            .with_arg("30")
+            .build()
+            .expect("Couldn't build exec spec");
+
+        let token = CancellationToken::new();
+        let handle = spec
+            .execute_spec("/")
+            .expect("Couldn't create handle")
+            .with_cancellation(token.clone());
+        token.cancel(CancellationReason::Interrupted);
+        let error = handle.wait().expect_err("program should be killed");
+        assert!(error.to_string().contains("interrupted"), "{}", error);
+    }
+
+    #[test]
+    fn line_callback_receives_every_line() {
+        let lines = Arc::new(RwLock::new(vec![]));
+        let received = lines.clone();
+        let spec = ExecSpecBuilder::new()
+            .with_exec("printf")
+            .with_arg("10%\\n50%\\r\\n100%")
+            .with_stdout(Output::line_callback(move |line| {
+                received.write().unwrap().push(line.to_string())
+            }))
+            .build()
+            .expect("Couldn't build exec spec");
+
+        let result = spec.execute_spec("/").expect("Couldn't create handle");
+        assert!(result.wait().expect("couldn't finish exec spec").success());
+        assert_eq!(*lines.read().unwrap(), ["10%", "50%", "100%"]);
+    }
 }
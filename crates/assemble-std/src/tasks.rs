@@ -1,6 +1,8 @@
 //! The standard library tasks. Defines important tasks like `Exec` and `Dupe`
 
+pub mod archive;
 pub mod exec;
+pub mod script;
 pub mod files;
 pub mod web;
 pub mod wrapper;
@@ -0,0 +1,543 @@
+//! Tasks that create and extract archives.
+//!
+//! Created archives are reproducible: entries are always written in order of their path within the
+//! archive, and file permissions and owners are fixed. Unless file timestamps are preserved, every
+//! entry also has the same fixed timestamp, so archives created from the same files are identical
+//! on every machine.
+
+use crate::archives::ArchiveFormat;
+use crate::specs::copy_spec::{CopyEntry, CopySpec};
+use assemble_core::error::PayloadError;
+use assemble_core::exception::{BuildException, BuildResult};
+use assemble_core::identifier::TaskId;
+use assemble_core::lazy_evaluation::{Prop, Provider, ProviderExt};
+use assemble_core::project::error::ProjectResult;
+use assemble_core::project::Project;
+use assemble_core::task::create_task::CreateTask;
+use assemble_core::task::initialize_task::InitializeTask;
+use assemble_core::task::task_io::TaskIO;
+use assemble_core::task::up_to_date::UpToDate;
+use assemble_core::{Executable, Task};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// The directory archives are created in by default, relative to the build directory
+pub const DISTRIBUTIONS_DIR: &str = "distributions";
+
+/// The directory archives are extracted into by default, relative to the build directory
+pub const EXTRACTED_DIR: &str = "extracted";
+
+/// Unix permissions given to every file in an archive
+const ENTRY_MODE: u32 = 0o644;
+
+/// Creates a zip archive. The files in the archive are selected by a [`CopySpec`](CopySpec).
+#[derive(Debug)]
+pub struct Zip {
+    /// The files to archive
+    pub spec: CopySpec,
+    /// The archive to create. Defaults to `build/distributions/<task name>.zip`.
+    pub archive_file: Prop<PathBuf>,
+    /// Whether entries keep the last modified time of their files. Defaults to `false`, where
+    /// every entry has the earliest time a zip archive can store.
+    pub preserve_file_timestamps: Prop<bool>,
+}
+
+impl CreateTask for Zip {
+    fn new(using_id: &TaskId, _project: &Project) -> ProjectResult<Self> {
+        let mut preserve_file_timestamps = using_id
+            .prop("preserve_file_timestamps")
+            .map_err(PayloadError::new)?;
+        preserve_file_timestamps.set(false)?;
+        Ok(Self {
+            spec: CopySpec::new(using_id)?,
+            archive_file: using_id.prop("archive_file").map_err(PayloadError::new)?,
+            preserve_file_timestamps,
+        })
+    }
+
+    fn description() -> String {
+        "Creates a zip archive".to_string()
+    }
+}
+
+impl TaskIO for Zip {
+    fn configure_io(task: &mut Executable<Self>) -> ProjectResult {
+        let spec = task.spec.clone();
+        let preserve_file_timestamps = task.preserve_file_timestamps.clone();
+        let archive_file = task.archive_file.clone();
+
+        spec.configure_io(task.work())?;
+        task.work()
+            .add_input("preserve_file_timestamps", preserve_file_timestamps)?;
+        task.work().add_output_provider(archive_file);
+        Ok(())
+    }
+}
+
+impl InitializeTask for Zip {
+    fn initialize(task: &mut Executable<Self>, project: &Project) -> ProjectResult {
+        let file_name = format!("{}.zip", task.task_id().this());
+        task.archive_file.set_with(
+            project
+                .build_dir()
+                .map(move |dir| dir.join(DISTRIBUTIONS_DIR).join(&file_name)),
+        )?;
+        let spec = task.spec.clone();
+        task.depends_on(spec);
+        Ok(())
+    }
+}
+
+impl UpToDate for Zip {}
+
+impl Task for Zip {
+    fn task_action(task: &mut Executable<Self>, _project: &Project) -> BuildResult {
+        let archive_file = task.archive_file.get();
+        let preserve_file_timestamps = task.preserve_file_timestamps.get();
+        let tokens = task.spec.tokens.get();
+        let entries = task.spec.entries()?;
+        debug!(
+            "creating zip {:?} with {} entries",
+            archive_file,
+            entries.len()
+        );
+
+        let write = || -> zip::result::ZipResult<()> {
+            let mut zip = zip::ZipWriter::new(create_archive_file(&archive_file)?);
+            for entry in &entries {
+                let last_modified = if preserve_file_timestamps {
+                    zip_date_time(modified_secs(&entry.source)?)
+                } else {
+                    zip::DateTime::default()
+                };
+                let options = zip::write::FileOptions::default()
+                    .compression_method(zip::CompressionMethod::Deflated)
+                    .last_modified_time(last_modified)
+                    .unix_permissions(ENTRY_MODE);
+                zip.start_file(entry_name(entry), options)?;
+                zip.write_all(&task.spec.read(entry, &tokens)?)?;
+            }
+            zip.finish()?.flush()?;
+            Ok(())
+        };
+        write().map_err(PayloadError::<BuildException>::new)?;
+        Ok(())
+    }
+}
+
+/// The compression used for a tar archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum TarCompression {
+    /// No compression
+    #[default]
+    None,
+    /// gzip compression
+    Gzip,
+    /// zstd compression
+    Zstd,
+}
+
+impl TarCompression {
+    /// The extension of archives with this compression
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TarCompression::None => "tar",
+            TarCompression::Gzip => "tar.gz",
+            TarCompression::Zstd => "tar.zst",
+        }
+    }
+}
+
+/// Creates a tar archive, optionally compressed. The files in the archive are selected by a
+/// [`CopySpec`](CopySpec).
+#[derive(Debug)]
+pub struct Tar {
+    /// The files to archive
+    pub spec: CopySpec,
+    /// The compression of the archive. Defaults to no compression.
+    pub compression: Prop<TarCompression>,
+    /// The archive to create. Defaults to `build/distributions/<task name>.<extension>`, where the
+    /// extension depends on the compression.
+    pub archive_file: Prop<PathBuf>,
+    /// Whether entries keep the last modified time of their files. Defaults to `false`, where
+    /// every entry has the unix epoch as its last modified time.
+    pub preserve_file_timestamps: Prop<bool>,
+}
+
+impl CreateTask for Tar {
+    fn new(using_id: &TaskId, _project: &Project) -> ProjectResult<Self> {
+        let mut compression = using_id.prop("compression").map_err(PayloadError::new)?;
+        compression.set(TarCompression::None)?;
+        let mut preserve_file_timestamps = using_id
+            .prop("preserve_file_timestamps")
+            .map_err(PayloadError::new)?;
+        preserve_file_timestamps.set(false)?;
+        Ok(Self {
+            spec: CopySpec::new(using_id)?,
+            compression,
+            archive_file: using_id.prop("archive_file").map_err(PayloadError::new)?,
+            preserve_file_timestamps,
+        })
+    }
+
+    fn description() -> String {
+        "Creates a tar archive".to_string()
+    }
+}
+
+impl TaskIO for Tar {
+    fn configure_io(task: &mut Executable<Self>) -> ProjectResult {
+        let spec = task.spec.clone();
+        let compression = task.compression.clone();
+        let preserve_file_timestamps = task.preserve_file_timestamps.clone();
+        let archive_file = task.archive_file.clone();
+
+        spec.configure_io(task.work())?;
+        task.work().add_input("compression", compression)?;
+        task.work()
+            .add_input("preserve_file_timestamps", preserve_file_timestamps)?;
+        task.work().add_output_provider(archive_file);
+        Ok(())
+    }
+}
+
+impl InitializeTask for Tar {
+    fn initialize(task: &mut Executable<Self>, project: &Project) -> ProjectResult {
+        let name = task.task_id().this().to_string();
+        task.archive_file.set_with(project.build_dir().zip(
+            task.compression.clone(),
+            move |dir: PathBuf, compression: TarCompression| {
+                dir.join(DISTRIBUTIONS_DIR)
+                    .join(format!("{}.{}", name, compression.extension()))
+            },
+        ))?;
+        let spec = task.spec.clone();
+        task.depends_on(spec);
+        Ok(())
+    }
+}
+
+impl UpToDate for Tar {}
+
+impl Task for Tar {
+    fn task_action(task: &mut Executable<Self>, _project: &Project) -> BuildResult {
+        let archive_file = task.archive_file.get();
+        let compression = task.compression.get();
+        let preserve_file_timestamps = task.preserve_file_timestamps.get();
+        let tokens = task.spec.tokens.get();
+        let entries = task.spec.entries()?;
+        debug!(
+            "creating {:?} tar {:?} with {} entries",
+            compression,
+            archive_file,
+            entries.len()
+        );
+
+        let write_entries = |writer: &mut dyn Write| -> io::Result<()> {
+            let mut tar = tar::Builder::new(writer);
+            for entry in &entries {
+                let content = task.spec.read(entry, &tokens)?;
+                let mut header = tar::Header::new_gnu();
+                header.set_size(content.len() as u64);
+                header.set_mode(ENTRY_MODE);
+                header.set_uid(0);
+                header.set_gid(0);
+                header.set_mtime(if preserve_file_timestamps {
+                    modified_secs(&entry.source)?
+                } else {
+                    0
+                });
+                tar.append_data(&mut header, entry_name(entry), content.as_slice())?;
+            }
+            tar.finish()
+        };
+
+        let write = || -> io::Result<()> {
+            let mut file = create_archive_file(&archive_file)?;
+            match compression {
+                TarCompression::None => {
+                    write_entries(&mut file)?;
+                }
+                TarCompression::Gzip => {
+                    let mut encoder =
+                        flate2::write::GzEncoder::new(file, flate2::Compression::default());
+                    write_entries(&mut encoder)?;
+                    file = encoder.finish()?;
+                }
+                TarCompression::Zstd => {
+                    let mut encoder = zstd::Encoder::new(file, 0)?;
+                    write_entries(&mut encoder)?;
+                    file = encoder.finish()?;
+                }
+            }
+            file.flush()
+        };
+        write().map_err(PayloadError::<BuildException>::new)?;
+        Ok(())
+    }
+}
+
+/// Extracts an archive into a directory. Files in the directory that aren't in the archive are
+/// removed.
+#[derive(Debug)]
+pub struct Extract {
+    /// The archive to extract
+    pub archive: Prop<PathBuf>,
+    /// The format of the archive. If not set, it is detected from the name of the archive.
+    pub format: Prop<ArchiveFormat>,
+    /// The directory to extract into. Defaults to `build/extracted/<task name>`.
+    pub into: Prop<PathBuf>,
+}
+
+impl CreateTask for Extract {
+    fn new(using_id: &TaskId, _project: &Project) -> ProjectResult<Self> {
+        Ok(Self {
+            archive: using_id.prop("archive").map_err(PayloadError::new)?,
+            format: using_id.prop("format").map_err(PayloadError::new)?,
+            into: using_id.prop("into").map_err(PayloadError::new)?,
+        })
+    }
+
+    fn description() -> String {
+        "Extracts an archive".to_string()
+    }
+}
+
+impl TaskIO for Extract {
+    fn configure_io(task: &mut Executable<Self>) -> ProjectResult {
+        let archive = task.archive.clone();
+        let into = task.into.clone();
+
+        task.work().add_input_file("archive", archive)?;
+        task.work().add_output_provider(into);
+        Ok(())
+    }
+}
+
+impl InitializeTask for Extract {
+    fn initialize(task: &mut Executable<Self>, project: &Project) -> ProjectResult {
+        let name = task.task_id().this().to_string();
+        task.into.set_with(
+            project
+                .build_dir()
+                .map(move |dir| dir.join(EXTRACTED_DIR).join(&name)),
+        )?;
+        let archive = task.archive.clone();
+        task.depends_on(archive);
+        Ok(())
+    }
+}
+
+impl UpToDate for Extract {}
+
+impl Task for Extract {
+    fn task_action(task: &mut Executable<Self>, _project: &Project) -> BuildResult {
+        let archive = task.archive.get();
+        let into = task.into.get();
+        let format = task
+            .format
+            .try_get()
+            .or_else(|| ArchiveFormat::detect(&archive))
+            .ok_or_else(|| {
+                BuildException::custom(&format!("could not detect archive format of {:?}", archive))
+            })?;
+        debug!(
+            "extracting {:?} archive {:?} into {:?}",
+            format, archive, into
+        );
+
+        let extract = || -> io::Result<()> {
+            if into.exists() {
+                std::fs::remove_dir_all(&into)?;
+            }
+            format.extract(&archive, &into)
+        };
+        extract().map_err(PayloadError::<BuildException>::new)?;
+        Ok(())
+    }
+}
+
+fn create_archive_file(path: &Path) -> io::Result<BufWriter<File>> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(BufWriter::new(File::create(path)?))
+}
+
+/// The name of an entry within an archive, which always uses `/` as a separator
+fn entry_name(entry: &CopyEntry) -> String {
+    entry
+        .destination
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The last modified time of a file, in seconds since the unix epoch
+fn modified_secs(path: &Path) -> io::Result<u64> {
+    let modified = std::fs::metadata(path)?.modified()?;
+    Ok(modified
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0))
+}
+
+/// Converts seconds since the unix epoch into a zip date time. Times that can't be stored in a
+/// zip archive are clamped to the earliest time that can be.
+fn zip_date_time(secs: u64) -> zip::DateTime {
+    let days = (secs / 86_400) as i64;
+    let time = secs % 86_400;
+
+    // civil date from days since the unix epoch
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    u16::try_from(year)
+        .ok()
+        .and_then(|year| {
+            zip::DateTime::from_date_and_time(
+                year,
+                month as u8,
+                day as u8,
+                (time / 3600) as u8,
+                (time % 3600 / 60) as u8,
+                (time % 60) as u8,
+            )
+            .ok()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assemble_core::task::ExecutableTask;
+    use std::io::Read;
+    use tempfile::TempDir;
+
+    fn sources(dir: &Path) -> PathBuf {
+        let src = dir.join("src");
+        std::fs::create_dir_all(src.join("nested")).unwrap();
+        std::fs::write(src.join("b.txt"), "b").unwrap();
+        std::fs::write(src.join("a.txt"), "version = @version@").unwrap();
+        std::fs::write(src.join("nested").join("c.txt"), "c").unwrap();
+        src
+    }
+
+    #[test]
+    fn zip_is_reproducible() {
+        let dir = TempDir::new().unwrap();
+        let src = sources(dir.path());
+        let project = Project::temp("archives");
+
+        let mut archives = vec![];
+        for name in ["zip1", "zip2"] {
+            let mut handle = project.register_task::<Zip>(name).unwrap();
+            let src = src.clone();
+            let archive = dir.path().join(format!("{name}.zip"));
+            let archive_clone = archive.clone();
+            handle
+                .configure_with(move |task, _| {
+                    task.spec.from.push(src);
+                    task.archive_file.set(archive_clone)?;
+                    Ok(())
+                })
+                .unwrap();
+            project.with(|p| handle.execute(p)).unwrap();
+            archives.push(std::fs::read(archive).unwrap());
+        }
+        assert_eq!(archives[0], archives[1]);
+
+        let mut zip = zip::ZipArchive::new(io::Cursor::new(&archives[0])).unwrap();
+        let names = zip.file_names().map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(names.len(), 3);
+        assert!(names.contains(&"nested/c.txt".to_string()));
+        assert_eq!(zip.by_index(0).unwrap().name(), "a.txt");
+    }
+
+    #[test]
+    fn gzip_tar_keeps_entry_order() {
+        let dir = TempDir::new().unwrap();
+        let src = sources(dir.path());
+        let project = Project::temp("archives");
+
+        let mut handle = project.register_task::<Tar>("tar").unwrap();
+        handle
+            .configure_with(move |task, _| {
+                task.spec.from.push(src);
+                task.spec.tokens.insert("version", "1.0".to_string());
+                task.compression.set(TarCompression::Gzip)?;
+                Ok(())
+            })
+            .unwrap();
+        let archive = handle.provides(|task| task.archive_file.get()).get();
+        assert!(archive.ends_with("build/distributions/tar.tar.gz"));
+        project.with(|p| handle.execute(p)).unwrap();
+
+        let decoder = flate2::read::GzDecoder::new(File::open(archive).unwrap());
+        let mut tar = tar::Archive::new(decoder);
+        let mut entries = vec![];
+        for entry in tar.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            assert_eq!(entry.header().mtime().unwrap(), 0);
+            let path = entry.path().unwrap().to_string_lossy().to_string();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            entries.push((path, content));
+        }
+        assert_eq!(
+            entries,
+            [
+                ("a.txt".to_string(), "version = 1.0".to_string()),
+                ("b.txt".to_string(), "b".to_string()),
+                ("nested/c.txt".to_string(), "c".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn extract_created_archive() {
+        let dir = TempDir::new().unwrap();
+        let src = sources(dir.path());
+        let project = Project::temp("archives");
+
+        let mut tar = project.register_task::<Tar>("tar").unwrap();
+        tar.configure_with(move |task, _| {
+            task.spec.from.push(src);
+            task.compression.set(TarCompression::Zstd)?;
+            Ok(())
+        })
+        .unwrap();
+        let mut extract = project.register_task::<Extract>("extract").unwrap();
+        let archive = tar.provides(|task| task.archive_file.get());
+        extract
+            .configure_with(move |task, _| {
+                task.archive.set_with(archive)?;
+                Ok(())
+            })
+            .unwrap();
+        project.with(|p| tar.execute(p)).unwrap();
+        project.with(|p| extract.execute(p)).unwrap();
+
+        let into = extract.provides(|task| task.into.get()).get();
+        assert!(into.ends_with("build/extracted/extract"));
+        assert_eq!(
+            std::fs::read_to_string(into.join("nested").join("c.txt")).unwrap(),
+            "c"
+        );
+    }
+}
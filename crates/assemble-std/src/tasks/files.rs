@@ -1,10 +1,22 @@
 //! Tasks that are related to files (copying, deleting, etc...)
 
+use crate::specs::copy_spec::CopySpec;
+use assemble_core::error::PayloadError;
 use assemble_core::exception::{BuildException, BuildResult};
+use assemble_core::identifier::TaskId;
+use assemble_core::lazy_evaluation::{Prop, Provider, VecProp};
+use assemble_core::project::error::ProjectResult;
 use assemble_core::project::Project;
-
-use std::path::PathBuf;
-use assemble_core::error::PayloadError;
+use assemble_core::task::create_task::CreateTask;
+use assemble_core::task::flags::{OptionDeclarationBuilder, OptionDeclarations, OptionsDecoder};
+use assemble_core::task::initialize_task::InitializeTask;
+use assemble_core::task::task_io::TaskIO;
+use assemble_core::task::up_to_date::UpToDate;
+use assemble_core::task::ExecutableTask;
+use assemble_core::{Executable, Task};
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
 
 /// Copies files
 #[derive(Default, Clone)]
@@ -18,5 +30,514 @@ fn dupe_files(dupe: &mut Dupe, _project: &Project) -> BuildResult {
     Ok(())
 }
 
-/// Deletes files
-pub struct Delete {}
+/// Deletes files and directories.
+///
+/// To protect against mistakes in build scripts, only paths within an
+/// [allowed root](Delete::allowed_roots) are deleted, which by default are the build directory of
+/// the project and the build directory of the root project. Deleting any other path fails unless
+/// [`force`](Delete::force) is set. The project directory and its ancestors are never deleted.
+///
+/// Symbolic links are never followed. Deleting a link removes the link, and a link is only
+/// considered within an allowed root if the directory containing it is.
+#[derive(Debug)]
+pub struct Delete {
+    /// The paths to delete. Relative paths are resolved against the project directory.
+    pub delete: VecProp<PathBuf>,
+    /// The directories paths can be deleted from without `force`
+    pub allowed_roots: VecProp<PathBuf>,
+    /// Delete paths outside of the allowed roots
+    pub force: Prop<bool>,
+    /// Only list what would be deleted, without deleting anything
+    pub dry_run: Prop<bool>,
+}
+
+impl CreateTask for Delete {
+    fn new(using_id: &TaskId, _project: &Project) -> ProjectResult<Self> {
+        Ok(Self {
+            delete: using_id.vec_prop("delete").map_err(PayloadError::new)?,
+            allowed_roots: using_id
+                .vec_prop("allowed_roots")
+                .map_err(PayloadError::new)?,
+            force: using_id.prop("force").map_err(PayloadError::new)?,
+            dry_run: using_id.prop("dry_run").map_err(PayloadError::new)?,
+        })
+    }
+
+    fn description() -> String {
+        "Deletes files and directories".to_string()
+    }
+
+    fn options_declarations() -> Option<OptionDeclarations> {
+        Some(OptionDeclarations::new::<Self, _>([
+            OptionDeclarationBuilder::flag("dry-run")
+                .help("List what would be deleted without deleting anything")
+                .build(),
+        ]))
+    }
+
+    fn try_set_from_decoder(&mut self, decoder: &OptionsDecoder) -> ProjectResult<()> {
+        if decoder.flag_present("dry-run").map_err(PayloadError::new)? {
+            self.dry_run.set(true)?;
+        }
+        Ok(())
+    }
+}
+
+impl TaskIO for Delete {}
+
+impl InitializeTask for Delete {
+    fn initialize(task: &mut Executable<Self>, project: &Project) -> ProjectResult {
+        task.allowed_roots.push_with(project.build_dir());
+        if project.parent_project().is_some() {
+            let root_build_dir = project.root_project().with(|root| root.build_dir());
+            task.allowed_roots.push_with(root_build_dir);
+        }
+        task.force.set(false)?;
+        task.dry_run.set(false)?;
+        Ok(())
+    }
+}
+
+impl UpToDate for Delete {
+    /// Deleted paths may have been recreated since the last run, so this is never up to date
+    fn up_to_date(&self) -> bool {
+        false
+    }
+}
+
+impl Task for Delete {
+    fn task_action(task: &mut Executable<Self>, project: &Project) -> BuildResult {
+        let project_dir = project.project_dir();
+        let force = task.force.get();
+        let dry_run = task.dry_run.get();
+        let allowed_roots = task
+            .allowed_roots
+            .get()
+            .into_iter()
+            .filter_map(|root| root.canonicalize().ok())
+            .collect::<Vec<_>>();
+        let protected = project_dir
+            .canonicalize()
+            .map_err(PayloadError::<BuildException>::new)?;
+
+        for path in task.delete.get() {
+            let path = project_dir.join(path);
+            let resolved = match resolve_without_following(&path)
+                .map_err(PayloadError::<BuildException>::new)?
+            {
+                Some(resolved) => resolved,
+                None => {
+                    trace!("{:?} does not exist", path);
+                    continue;
+                }
+            };
+
+            if protected.starts_with(&resolved) {
+                return Err(BuildException::custom(&format!(
+                    "refusing to delete {:?}, which contains the project directory",
+                    path
+                ))
+                .into());
+            }
+            let allowed = allowed_roots.iter().any(|root| resolved.starts_with(root));
+            if !allowed && !force {
+                return Err(BuildException::custom(&format!(
+                    "refusing to delete {:?}, which is outside of the allowed roots {:?}. Set force to delete it anyway",
+                    path, allowed_roots
+                ))
+                .into());
+            }
+
+            if dry_run {
+                info!("would delete {:?}", resolved);
+                continue;
+            }
+            debug!("deleting {:?}", resolved);
+            remove(&resolved).map_err(PayloadError::<BuildException>::new)?;
+        }
+        Ok(())
+    }
+}
+
+/// Deletes the build directory of the project, and the outputs declared by every other task in
+/// the project. Outputs outside of the root project directory are never deleted, and neither is
+/// the project directory or any of its ancestors.
+#[derive(Debug)]
+pub struct Clean {
+    /// The build directory to delete
+    pub build_dir: Prop<PathBuf>,
+    /// Only list what would be deleted, without deleting anything
+    pub dry_run: Prop<bool>,
+}
+
+impl CreateTask for Clean {
+    fn new(using_id: &TaskId, _project: &Project) -> ProjectResult<Self> {
+        Ok(Self {
+            build_dir: using_id.prop("build_dir").map_err(PayloadError::new)?,
+            dry_run: using_id.prop("dry_run").map_err(PayloadError::new)?,
+        })
+    }
+
+    fn description() -> String {
+        "Deletes the build directory and the outputs of every task in the project".to_string()
+    }
+
+    fn options_declarations() -> Option<OptionDeclarations> {
+        Some(OptionDeclarations::new::<Self, _>([
+            OptionDeclarationBuilder::flag("dry-run")
+                .help("List what would be deleted without deleting anything")
+                .build(),
+        ]))
+    }
+
+    fn try_set_from_decoder(&mut self, decoder: &OptionsDecoder) -> ProjectResult<()> {
+        if decoder.flag_present("dry-run").map_err(PayloadError::new)? {
+            self.dry_run.set(true)?;
+        }
+        Ok(())
+    }
+}
+
+impl TaskIO for Clean {}
+
+impl InitializeTask for Clean {
+    fn initialize(task: &mut Executable<Self>, project: &Project) -> ProjectResult {
+        task.build_dir.set_with(project.build_dir())?;
+        task.dry_run.set(false)?;
+        Ok(())
+    }
+}
+
+impl UpToDate for Clean {
+    /// Outputs may have been recreated since the last run, so this is never up to date
+    fn up_to_date(&self) -> bool {
+        false
+    }
+}
+
+impl Task for Clean {
+    fn task_action(task: &mut Executable<Self>, project: &Project) -> BuildResult {
+        let dry_run = task.dry_run.get();
+        let root_dir = project
+            .root_dir()
+            .canonicalize()
+            .map_err(PayloadError::<BuildException>::new)?;
+        let protected = project
+            .project_dir()
+            .canonicalize()
+            .map_err(PayloadError::<BuildException>::new)?;
+
+        let mut paths = BTreeSet::new();
+        for path in declared_outputs(project)?
+            .into_iter()
+            .chain(task.build_dir.try_get())
+        {
+            if let Some(resolved) =
+                resolve_without_following(&path).map_err(PayloadError::<BuildException>::new)?
+            {
+                paths.insert(resolved);
+            }
+        }
+
+        let mut deleted: Vec<PathBuf> = vec![];
+        for path in paths {
+            if deleted.iter().any(|parent| path.starts_with(parent)) {
+                continue;
+            }
+            if protected.starts_with(&path) {
+                warn!(
+                    "not deleting {:?}, which contains the project directory",
+                    path
+                );
+                continue;
+            }
+            if !path.starts_with(&root_dir) {
+                warn!(
+                    "not deleting {:?}, which is outside of the root project directory",
+                    path
+                );
+                continue;
+            }
+
+            if dry_run {
+                info!("would delete {:?}", path);
+            } else {
+                debug!("deleting {:?}", path);
+                remove(&path).map_err(PayloadError::<BuildException>::new)?;
+            }
+            deleted.push(path);
+        }
+        Ok(())
+    }
+}
+
+/// The outputs declared by the tasks of a project, other than clean tasks
+fn declared_outputs(project: &Project) -> ProjectResult<BTreeSet<PathBuf>> {
+    let container = project.task_container();
+    let mut outputs = BTreeSet::new();
+    for id in container.get_tasks() {
+        let mut handle = container.get_task(id).unwrap().clone();
+        if handle.is::<Clean>() {
+            continue;
+        }
+        let task = handle.resolve(project)?;
+        trace!("{} declares outputs {:?}", id, task.declared_outputs());
+        outputs.extend(task.declared_outputs());
+    }
+    Ok(outputs)
+}
+
+/// Canonicalizes the parent of a path, without following the path itself if it is a symbolic link.
+/// Returns `None` if nothing exists at the path.
+fn resolve_without_following(path: &Path) -> io::Result<Option<PathBuf>> {
+    if std::fs::symlink_metadata(path).is_err() {
+        return Ok(None);
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(file_name)) => Ok(Some(parent.canonicalize()?.join(file_name))),
+        _ => path.canonicalize().map(Some),
+    }
+}
+
+/// Removes a file, link or directory. Links are removed without removing what they point to.
+fn remove(path: &Path) -> io::Result<()> {
+    let metadata = std::fs::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+/// Copies files and directories into a destination directory. The files copied are selected
+/// by a [`CopySpec`](CopySpec).
+#[derive(Debug)]
+pub struct Copy {
+    /// The files to copy
+    pub spec: CopySpec,
+    /// The directory to copy into
+    pub into: Prop<PathBuf>,
+}
+
+impl CreateTask for Copy {
+    fn new(using_id: &TaskId, _project: &Project) -> ProjectResult<Self> {
+        Ok(Self {
+            spec: CopySpec::new(using_id)?,
+            into: using_id.prop("into").map_err(PayloadError::new)?,
+        })
+    }
+
+    fn description() -> String {
+        "Copies files into a directory".to_string()
+    }
+}
+
+impl TaskIO for Copy {
+    fn configure_io(task: &mut Executable<Self>) -> ProjectResult {
+        let spec = task.spec.clone();
+        let into = task.into.clone();
+
+        spec.configure_io(task.work())?;
+        task.work().add_output_provider(into);
+        Ok(())
+    }
+}
+
+impl InitializeTask for Copy {
+    fn initialize(task: &mut Executable<Self>, _project: &Project) -> ProjectResult {
+        let spec = task.spec.clone();
+        task.depends_on(spec);
+        Ok(())
+    }
+}
+
+impl UpToDate for Copy {}
+
+impl Task for Copy {
+    fn task_action(task: &mut Executable<Self>, _project: &Project) -> BuildResult {
+        let into = task.into.try_get().ok_or_else(|| {
+            BuildException::custom(&format!("no destination set for {}", task.task_id()))
+        })?;
+        let tokens = task.spec.tokens.get();
+
+        for entry in task.spec.entries()? {
+            let destination = into.join(&entry.destination);
+            trace!("copying {:?} to {:?}", entry.source, destination);
+
+            let write = || -> io::Result<()> {
+                if let Some(parent) = destination.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&destination, task.spec.read(&entry, &tokens)?)
+            };
+            write().map_err(PayloadError::<BuildException>::new)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assemble_core::task::ExecutableTask;
+    use tempfile::TempDir;
+
+    #[test]
+    fn copy_with_patterns_renames_and_tokens() {
+        let dir = TempDir::new().unwrap();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(src.join("nested")).unwrap();
+        std::fs::write(src.join("a.txt"), "version = @version@").unwrap();
+        std::fs::write(src.join("nested").join("b.txt"), "b").unwrap();
+        std::fs::write(src.join("nested").join("skip.log"), "skip").unwrap();
+        let out = dir.path().join("out");
+
+        let project = Project::temp("copy");
+        let mut handle = project.register_task::<Copy>("copy").unwrap();
+        let src_clone = src.clone();
+        let out_clone = out.clone();
+        handle
+            .configure_with(move |task, _| {
+                task.spec.from.push(src_clone);
+                task.into.set(out_clone)?;
+                task.spec.include.push("**/*.txt".to_string());
+                task.spec.exclude.push("nested/skip*".to_string());
+                task.spec.tokens.insert("version", "1.0".to_string());
+                task.spec
+                    .rename(|name| name.strip_prefix("a").map(|rest| format!("renamed{rest}")));
+                Ok(())
+            })
+            .unwrap();
+
+        project.with(|p| handle.execute(p)).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(out.join("renamed.txt")).unwrap(),
+            "version = 1.0"
+        );
+        assert_eq!(
+            std::fs::read_to_string(out.join("nested").join("b.txt")).unwrap(),
+            "b"
+        );
+        assert!(!out.join("nested").join("skip.log").exists());
+    }
+
+    #[test]
+    fn delete_only_within_allowed_roots() {
+        let project = Project::temp("delete");
+        let project_dir = project.with(|p| p.project_dir());
+        let build_dir = project.with(|p| p.build_dir().get());
+        std::fs::create_dir_all(&project_dir).unwrap();
+        std::fs::create_dir_all(build_dir.join("out")).unwrap();
+        std::fs::write(build_dir.join("out").join("a.txt"), "a").unwrap();
+        std::fs::write(project_dir.join("keep.txt"), "keep").unwrap();
+
+        let mut dry_run = project.register_task::<Delete>("dryRun").unwrap();
+        dry_run
+            .configure_with(|task, _| {
+                task.delete.push(PathBuf::from("build/out"));
+                task.dry_run.set(true)?;
+                Ok(())
+            })
+            .unwrap();
+        project.with(|p| dry_run.execute(p)).unwrap();
+        assert!(build_dir.join("out").exists());
+
+        let mut delete = project.register_task::<Delete>("delete").unwrap();
+        delete
+            .configure_with(|task, _| {
+                task.delete.push(PathBuf::from("build/out"));
+                Ok(())
+            })
+            .unwrap();
+        project.with(|p| delete.execute(p)).unwrap();
+        assert!(!build_dir.join("out").exists());
+
+        let mut outside = project.register_task::<Delete>("outside").unwrap();
+        outside
+            .configure_with(|task, _| {
+                task.delete.push(PathBuf::from("keep.txt"));
+                Ok(())
+            })
+            .unwrap();
+        assert!(project.with(|p| outside.execute(p)).is_err());
+        assert!(project_dir.join("keep.txt").exists());
+
+        let mut project_root = project.register_task::<Delete>("projectRoot").unwrap();
+        project_root
+            .configure_with(|task, _| {
+                task.delete.push(PathBuf::from("/"));
+                task.force.set(true)?;
+                Ok(())
+            })
+            .unwrap();
+        assert!(project.with(|p| project_root.execute(p)).is_err());
+    }
+
+    #[test]
+    fn clean_deletes_declared_outputs() {
+        let project = Project::temp("clean");
+        let project_dir = project.with(|p| p.project_dir());
+        let build_dir = project.with(|p| p.build_dir().get());
+        std::fs::create_dir_all(project_dir.join("src")).unwrap();
+        std::fs::create_dir_all(&build_dir).unwrap();
+        std::fs::write(project_dir.join("src").join("a.txt"), "a").unwrap();
+        std::fs::write(build_dir.join("b.txt"), "b").unwrap();
+
+        let mut copy = project.register_task::<Copy>("copy").unwrap();
+        let src = project_dir.join("src");
+        let dist = project_dir.join("dist");
+        let dist_clone = dist.clone();
+        copy.configure_with(move |task, _| {
+            task.spec.from.push(src);
+            task.into.set(dist_clone)?;
+            Ok(())
+        })
+        .unwrap();
+        project.with(|p| copy.execute(p)).unwrap();
+        assert!(dist.join("a.txt").exists());
+
+        let mut dry_run = project.register_task::<Clean>("dryRun").unwrap();
+        dry_run
+            .configure_with(|task, _| {
+                task.dry_run.set(true)?;
+                Ok(())
+            })
+            .unwrap();
+        project.with(|p| dry_run.execute(p)).unwrap();
+        assert!(dist.exists());
+        assert!(build_dir.exists());
+
+        let mut clean = project.register_task::<Clean>("clean").unwrap();
+        project.with(|p| clean.execute(p)).unwrap();
+        assert!(!dist.exists());
+        assert!(!build_dir.exists());
+        assert!(project_dir.join("src").join("a.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn delete_removes_links_not_targets() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("target.txt"), "target").unwrap();
+
+        let project = Project::temp("delete_link");
+        let build_dir = project.with(|p| p.build_dir().get());
+        std::fs::create_dir_all(&build_dir).unwrap();
+        let link = build_dir.join("link");
+        std::os::unix::fs::symlink(dir.path(), &link).unwrap();
+
+        let mut delete = project.register_task::<Delete>("delete").unwrap();
+        let link_clone = link.clone();
+        delete
+            .configure_with(move |task, _| {
+                task.delete.push(link_clone);
+                Ok(())
+            })
+            .unwrap();
+        project.with(|p| delete.execute(p)).unwrap();
+
+        assert!(std::fs::symlink_metadata(&link).is_err());
+        assert!(dir.path().join("target.txt").exists());
+    }
+}
@@ -0,0 +1,218 @@
+//! Contains the script task, which runs inline shell scripts
+
+use crate::ProjectExec;
+use assemble_core::error::PayloadError;
+use assemble_core::exception::{BuildException, BuildResult};
+use assemble_core::identifier::TaskId;
+use assemble_core::lazy_evaluation::{MapProp, Prop, Provider};
+use assemble_core::project::error::ProjectResult;
+use assemble_core::task::create_task::CreateTask;
+use assemble_core::task::initialize_task::InitializeTask;
+use assemble_core::task::task_io::TaskIO;
+use assemble_core::task::up_to_date::UpToDate;
+use assemble_core::{Executable, Project, Task};
+use log::Level;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// A shell a [`Script`](Script) can be run with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    /// `bash`
+    Bash,
+    /// `sh`
+    Sh,
+    /// `powershell`
+    PowerShell,
+}
+
+impl Shell {
+    /// The shell scripts are run with on this platform: powershell on windows, and bash elsewhere
+    /// if it's installed, otherwise sh.
+    pub fn platform_default() -> Self {
+        if cfg!(windows) {
+            Shell::PowerShell
+        } else if Path::new("/bin/bash").exists() {
+            Shell::Bash
+        } else {
+            Shell::Sh
+        }
+    }
+
+    /// The executable of the shell
+    pub fn executable(&self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Sh => "sh",
+            Shell::PowerShell => "powershell",
+        }
+    }
+
+    /// The extension of script files run by this shell
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Shell::Bash | Shell::Sh => "sh",
+            Shell::PowerShell => "ps1",
+        }
+    }
+
+    /// The contents of the script file written for a script. When `echo` is set, powershell
+    /// traces every line, as it has no command line flag for it.
+    pub fn script_file_contents(&self, script: &str, echo: bool) -> String {
+        match self {
+            Shell::PowerShell if echo => format!("Set-PSDebug -Trace 1\n{}", script),
+            _ => script.to_string(),
+        }
+    }
+
+    /// The arguments that run a script file. When `echo` is set, bash and sh print every line of
+    /// the script as it's read.
+    pub fn args(&self, script_file: &Path, echo: bool) -> Vec<OsString> {
+        let mut args: Vec<OsString> = match self {
+            Shell::Bash | Shell::Sh if echo => vec!["-v".into()],
+            Shell::Bash | Shell::Sh => vec![],
+            Shell::PowerShell => [
+                "-NoProfile",
+                "-NonInteractive",
+                "-ExecutionPolicy",
+                "Bypass",
+                "-File",
+            ]
+            .map(OsString::from)
+            .to_vec(),
+        };
+        args.push(script_file.into());
+        args
+    }
+}
+
+/// Runs an inline, multi-line shell script.
+///
+/// The script is written to a temporary file and run by its [`shell`](Script::shell), in the
+/// project directory by default. The environment of assemble is passed to the script, along with
+/// the variables in [`env`](Script::env), whose values can come from other providers.
+///
+/// By default the task fails if the script exits with a non-zero exit code. When
+/// [`ignore_exit_value`](Script::ignore_exit_value) is set, the exit code is only recorded in
+/// [`exit_code`](Script::exit_code).
+#[derive(Debug)]
+pub struct Script {
+    /// The script to run
+    pub script: Prop<String>,
+    /// The shell to run the script with. Defaults to [`Shell::platform_default`](Shell::platform_default).
+    pub shell: Prop<Shell>,
+    /// Environment variables added to the environment of the script
+    pub env: MapProp<String, String>,
+    /// The directory the script runs in. Relative paths are relative to the project directory.
+    /// Defaults to the project directory.
+    pub working_dir: Prop<PathBuf>,
+    /// Whether every line of the script is printed as it runs. Defaults to false.
+    pub echo: Prop<bool>,
+    /// Whether a non-zero exit code is ignored instead of failing the task. Defaults to false.
+    pub ignore_exit_value: Prop<bool>,
+    /// The exit code of the script, set once it has run
+    pub exit_code: Prop<i32>,
+}
+
+impl CreateTask for Script {
+    fn new(using_id: &TaskId, _project: &Project) -> ProjectResult<Self> {
+        Ok(Self {
+            script: using_id.prop("script").map_err(PayloadError::new)?,
+            shell: using_id.prop("shell").map_err(PayloadError::new)?,
+            env: using_id.map_prop("env").map_err(PayloadError::new)?,
+            working_dir: using_id.prop("working_dir").map_err(PayloadError::new)?,
+            echo: using_id.prop("echo").map_err(PayloadError::new)?,
+            ignore_exit_value: using_id
+                .prop("ignore_exit_value")
+                .map_err(PayloadError::new)?,
+            exit_code: using_id.prop("exit_code").map_err(PayloadError::new)?,
+        })
+    }
+
+    fn description() -> String {
+        "Runs a shell script".to_string()
+    }
+}
+
+impl TaskIO for Script {}
+
+impl InitializeTask for Script {
+    fn initialize(task: &mut Executable<Self>, project: &Project) -> ProjectResult {
+        task.shell.set(Shell::platform_default())?;
+        task.working_dir.set(project.project_dir())?;
+        task.echo.set(false)?;
+        task.ignore_exit_value.set(false)?;
+        Ok(())
+    }
+}
+
+impl UpToDate for Script {
+    /// Scripts declare no outputs, so they are always run
+    fn up_to_date(&self) -> bool {
+        false
+    }
+}
+
+impl Task for Script {
+    fn task_action(task: &mut Executable<Self>, project: &Project) -> BuildResult {
+        let script = task.script.fallible_get()?;
+        let shell = task.shell.fallible_get()?;
+        let env = task.env.fallible_get()?;
+        let working_dir = task.working_dir.fallible_get()?;
+        let echo = task.echo.fallible_get()?;
+        let ignore_exit_value = task.ignore_exit_value.fallible_get()?;
+
+        let mut script_file = tempfile::Builder::new()
+            .prefix("assemble-script-")
+            .suffix(&format!(".{}", shell.extension()))
+            .tempfile()
+            .map_err(PayloadError::<BuildException>::new)?;
+        script_file
+            .write_all(shell.script_file_contents(&script, echo).as_bytes())
+            .and_then(|_| script_file.flush())
+            .map_err(PayloadError::<BuildException>::new)?;
+        trace!("running script {:?} with {:?}", script_file.path(), shell);
+
+        let result = project.exec_with(|exec| {
+            exec.exec(shell.executable())
+                .args(shell.args(script_file.path(), echo))
+                .working_dir(&working_dir)
+                .extend_env(env)
+                .stdout(Level::Info)
+                // sh and bash echo lines to stderr
+                .stderr(if echo { Level::Info } else { Level::Warn });
+        })?;
+
+        let code = result.code().code().unwrap_or(-1);
+        task.exit_code.set(code)?;
+        if result.success() || ignore_exit_value {
+            Ok(())
+        } else {
+            Err(BuildException::custom(&format!("script exited with code {}", code)).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_args() {
+        let file = Path::new("script.sh");
+        assert_eq!(Shell::Bash.args(file, false), [OsString::from("script.sh")]);
+        assert_eq!(
+            Shell::Sh.args(file, true),
+            [OsString::from("-v"), OsString::from("script.sh")]
+        );
+        assert_eq!(
+            Shell::PowerShell.args(Path::new("script.ps1"), true).last(),
+            Some(&OsString::from("script.ps1"))
+        );
+        assert!(Shell::PowerShell
+            .script_file_contents("echo hi", true)
+            .starts_with("Set-PSDebug -Trace 1\n"));
+        assert_eq!(Shell::Bash.script_file_contents("echo hi", true), "echo hi");
+    }
+}
@@ -1,78 +1,250 @@
-//! Contains web-based tasks, like getting
+//! Contains web-based tasks, like downloading files
 
-use crate::assemble_core::lazy_evaluation::ProviderExt;
-
-use assemble_core::lazy_evaluation::{Prop, Provider};
+use assemble_core::cryptography::{hash_file_sha256, Sha256};
+use assemble_core::error::PayloadError;
+use assemble_core::exception::{BuildException, BuildResult};
+use assemble_core::humanize;
+use assemble_core::identifier::TaskId;
+use assemble_core::lazy_evaluation::{Prop, Provider, ProviderExt};
+use assemble_core::logging::LOGGING_CONTROL;
 use assemble_core::project::error::ProjectResult;
-
+use assemble_core::task::create_task::CreateTask;
 use assemble_core::task::initialize_task::InitializeTask;
+use assemble_core::task::task_io::TaskIO;
 use assemble_core::task::up_to_date::UpToDate;
-use assemble_core::{BuildResult, Executable, Project, Task};
-use std::path::PathBuf;
+use assemble_core::web::{CacheValidators, ConditionalDownload, WebClient};
+use assemble_core::{Executable, Project, Task};
+use indicatif::{ProgressBar, ProgressState, ProgressStyle};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
 use url::Url;
 
-/// Downloads a file
-#[derive(Debug, Clone, CreateTask, TaskIO)]
-pub struct DownloadFile {
+/// The directory files are downloaded into by default, relative to the build directory
+pub const DOWNLOADS_DIR: &str = "downloads";
+
+/// Downloads a file.
+///
+/// The `ETag` and `Last-Modified` headers of the response are saved next to the downloaded file.
+/// When the task runs again, they are sent back to the server, and the file is only downloaded
+/// again if it changed. The downloaded file is available as an output through
+/// [`destination`](Download::destination).
+#[derive(Debug)]
+pub struct Download {
     /// The url to download from
-    #[input]
     pub url: Prop<Url>,
-    /// The file name to download into
-    #[output]
-    pub fname: Prop<PathBuf>,
+    /// The file to download into. Defaults to `build/downloads/<file name of url>`.
+    pub destination: Prop<PathBuf>,
+    /// The expected sha256 checksum of the downloaded file. If set, a download that doesn't match
+    /// fails the task.
+    pub sha256: Prop<Sha256>,
 }
 
-impl InitializeTask for DownloadFile {
-    fn initialize(task: &mut Executable<Self>, project: &Project) -> ProjectResult {
-        let build_dir = project.build_dir();
+impl Download {
+    /// The downloaded file
+    pub fn downloaded_file(&self) -> impl Provider<PathBuf> + Clone {
+        self.destination.clone()
+    }
+}
+
+impl CreateTask for Download {
+    fn new(using_id: &TaskId, _project: &Project) -> ProjectResult<Self> {
+        Ok(Self {
+            url: using_id.prop("url").map_err(PayloadError::new)?,
+            destination: using_id.prop("destination").map_err(PayloadError::new)?,
+            sha256: using_id.prop("sha256").map_err(PayloadError::new)?,
+        })
+    }
 
-        let map = task
+    fn description() -> String {
+        "Downloads a file".to_string()
+    }
+}
+
+impl TaskIO for Download {
+    fn configure_io(task: &mut Executable<Self>) -> ProjectResult {
+        let url = task.url.clone();
+        let sha256 = task.sha256.clone();
+        let destination = task.destination.clone();
+
+        task.work()
+            .add_input("url", url.map(|url| url.to_string()))?;
+        task.work().add_input(
+            "sha256",
+            provider!(move || sha256.try_get().map(|sha| sha.to_string())),
+        )?;
+        task.work().add_output_provider(destination);
+        Ok(())
+    }
+}
+
+impl InitializeTask for Download {
+    fn initialize(task: &mut Executable<Self>, project: &Project) -> ProjectResult {
+        let destination = task
             .url
             .clone()
-            .zip(build_dir, |url: Url, build_dir: PathBuf| {
-                build_dir.join("downloads").join(
-                    url.path_segments()
-                        .and_then(|segs| segs.last())
-                        .and_then(|name| if name.is_empty() { None } else { Some(name) })
-                        .unwrap_or("tmp.bin"),
-                )
+            .zip(project.build_dir(), |url: Url, build_dir: PathBuf| {
+                build_dir.join(DOWNLOADS_DIR).join(file_name(&url))
             });
-        task.fname.set_with(map)?;
+        task.destination.set_with(destination)?;
         Ok(())
     }
 }
 
-impl UpToDate for DownloadFile {}
+impl UpToDate for Download {
+    /// Out of date if the resource changed on the server since it was downloaded. If the server
+    /// can't be reached, the downloaded file is assumed to still be up to date.
+    fn up_to_date(&self) -> bool {
+        let (url, destination) = match (self.url.try_get(), self.destination.try_get()) {
+            (Some(url), Some(destination)) => (url, destination),
+            _ => return false,
+        };
+        if !destination.exists() {
+            return false;
+        }
+        let validators = match read_validators(&destination) {
+            Some(validators) => validators,
+            None => return false,
+        };
+        match WebClient::new().is_modified(&url, &validators) {
+            Ok(modified) => !modified,
+            Err(e) => {
+                warn!("could not check whether {} changed: {}", url, e);
+                true
+            }
+        }
+    }
+}
 
-impl Task for DownloadFile {
+impl Task for Download {
     fn task_action(task: &mut Executable<Self>, _project: &Project) -> BuildResult {
-        let url = task.url.get();
-        println!("url = {}", url);
-        println!("fname = {:?}", task.fname.try_get().unwrap_or_default());
+        let url = task.url.fallible_get()?;
+        let destination = task.destination.fallible_get()?;
+        let validators = destination
+            .exists()
+            .then(|| read_validators(&destination))
+            .flatten();
+
+        let bar = LOGGING_CONTROL.progress_bar().map(|progress| {
+            let bar = progress.add(
+                ProgressBar::new_spinner().with_style(
+                    ProgressStyle::with_template("> {msg} {size}/{total_size} ({speed})")
+                        .unwrap()
+                        .with_key("size", |state: &ProgressState, w: &mut dyn fmt::Write| {
+                            let _ = write!(w, "{}", humanize::bytes(state.pos()));
+                        })
+                        .with_key(
+                            "total_size",
+                            |state: &ProgressState, w: &mut dyn fmt::Write| {
+                                let _ = match state.len() {
+                                    Some(len) => write!(w, "{}", humanize::bytes(len)),
+                                    None => write!(w, "?"),
+                                };
+                            },
+                        )
+                        .with_key("speed", |state: &ProgressState, w: &mut dyn fmt::Write| {
+                            let _ = write!(w, "{}/s", humanize::bytes(state.per_sec() as u64));
+                        }),
+                ),
+            );
+            bar.set_message(format!("downloading {}", url));
+            bar
+        });
+        let result = WebClient::new().download_if_modified(
+            &url,
+            &destination,
+            validators.as_ref(),
+            |downloaded, total| {
+                if let Some(bar) = &bar {
+                    if let Some(total) = total {
+                        bar.set_length(total);
+                    }
+                    bar.set_position(downloaded);
+                }
+            },
+        );
+        if let Some(bar) = bar {
+            bar.finish_and_clear();
+        }
+
+        match result.map_err(PayloadError::<BuildException>::new)? {
+            ConditionalDownload::NotModified => {
+                debug!("{} not modified since it was downloaded", url);
+            }
+            ConditionalDownload::Downloaded { size, validators } => {
+                debug!(
+                    "downloaded {} bytes from {} to {:?}",
+                    size, url, destination
+                );
+                write_validators(&destination, &validators)
+                    .map_err(PayloadError::<BuildException>::new)?;
+            }
+        }
 
+        if let Some(expected) = task.sha256.try_get() {
+            let found =
+                hash_file_sha256(&destination).map_err(PayloadError::<BuildException>::new)?;
+            if found != expected {
+                std::fs::remove_file(&destination).map_err(PayloadError::<BuildException>::new)?;
+                return Err(BuildException::custom(&format!(
+                    "{} has sha256 {} but {} was expected",
+                    url, found, expected
+                ))
+                .into());
+            }
+        }
         Ok(())
     }
 }
-//
-// impl CreateTask for DownloadFile {
-//     fn new(id: &TaskId, project: &Project) -> ProjectResult<Self> {
-//         let url = id.prop("url").unwrap();
-//         let mut fname: Prop<PathBuf> = id.prop("file_name").unwrap();
-//
-//         let build_dir = project.build_dir();
-//
-//         let map = url.zip(&build_dir, |url: Url, build_dir: PathBuf| {
-//             println!("Calculating fname");
-//             build_dir.join("downloads").join(
-//                 url.path_segments()
-//                     .and_then(|segs| segs.last())
-//                     .and_then(|name| if name.is_empty() { None } else { Some(name) })
-//                     .unwrap_or("tmp.bin"),
-//             )
-//         });
-//         fname.set_with(map).unwrap();
-//         Ok(Self { url, fname })
-//     }
-//
-//
-// }
+
+/// The file name of a url, or `download.bin` if the url has none
+fn file_name(url: &Url) -> &str {
+    url.path_segments()
+        .and_then(|segments| segments.last())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("download.bin")
+}
+
+/// The file the cache validators of a downloaded file are stored in
+fn validators_file(destination: &Path) -> PathBuf {
+    let mut file_name = destination
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    file_name.push(".validators.json");
+    destination.with_file_name(file_name)
+}
+
+fn read_validators(destination: &Path) -> Option<CacheValidators> {
+    let contents = std::fs::read_to_string(validators_file(destination)).ok()?;
+    serde_json::from_str(&contents)
+        .ok()
+        .filter(|validators: &CacheValidators| !validators.is_empty())
+}
+
+fn write_validators(destination: &Path, validators: &CacheValidators) -> io::Result<()> {
+    let json =
+        serde_json::to_string(validators).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    std::fs::write(validators_file(destination), json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_file_names() {
+        assert_eq!(
+            file_name(&Url::parse("https://example.com/files/tool.tar.gz").unwrap()),
+            "tool.tar.gz"
+        );
+        assert_eq!(
+            file_name(&Url::parse("https://example.com/").unwrap()),
+            "download.bin"
+        );
+        assert_eq!(
+            validators_file(Path::new("/build/downloads/tool.tar.gz")),
+            Path::new("/build/downloads/tool.tar.gz.validators.json")
+        );
+    }
+}
@@ -0,0 +1,484 @@
+//! Provision external tools.
+//!
+//! Plugins often need an external executable that may not be installed on the system. Instead of
+//! every plugin downloading these on its own, a plugin declares a [`Tool`](Tool) with a download
+//! for every platform it supports, and a [`ToolProvisioner`](ToolProvisioner) takes care of
+//! downloading, verifying, and unpacking it. Tools are installed under `ASSEMBLE_HOME/tools`, so
+//! they are shared by every build on the machine and only downloaded once.
+//!
+//! # Example
+//! ```no_run
+//! # use assemble_std::tools::{Platform, Tool, ToolArtifact, ToolProvisioner};
+//! # use assemble_core::lazy_evaluation::Provider;
+//! let tool = Tool::new("protoc", "21.12").artifact(
+//!     Platform::new("linux", "x86_64"),
+//!     ToolArtifact::zip(
+//!         "https://github.com/protocolbuffers/protobuf/releases/download/v21.12/protoc-21.12-linux-x86_64.zip",
+//!         "bin/protoc",
+//!     )
+//!     .unwrap(),
+//! );
+//! let protoc = ToolProvisioner::default().executable(tool);
+//! println!("protoc is at {:?}", protoc.get());
+//! ```
+
+use assemble_core::cryptography::{hash_file_sha256, ParseSha256Error, Sha256};
+use assemble_core::identifier::TaskId;
+use assemble_core::lazy_evaluation::Provider;
+use assemble_core::project::buildable::Buildable;
+use assemble_core::project::error::ProjectResult;
+use assemble_core::web::{WebClient, WebError};
+use assemble_core::{Project, ASSEMBLE_HOME};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Display, Formatter};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use url::Url;
+
+/// The name of the file written into an install directory once a tool is fully provisioned
+const PROVISIONED_MARKER: &str = ".provisioned";
+
+/// Only one tool is provisioned at a time, so that two tasks needing the same tool don't both
+/// download it.
+static PROVISIONING: Mutex<()> = parking_lot::const_mutex(());
+
+/// An operating system and architecture pair
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Platform {
+    os: String,
+    arch: String,
+}
+
+impl Platform {
+    /// Creates a new platform. The values should match those of
+    /// [`std::env::consts::OS`](std::env::consts::OS) and
+    /// [`std::env::consts::ARCH`](std::env::consts::ARCH).
+    pub fn new(os: &str, arch: &str) -> Self {
+        Self {
+            os: os.to_string(),
+            arch: arch.to_string(),
+        }
+    }
+
+    /// The platform assemble is running on
+    pub fn current() -> Self {
+        Self::new(std::env::consts::OS, std::env::consts::ARCH)
+    }
+
+    /// The operating system of this platform
+    pub fn os(&self) -> &str {
+        &self.os
+    }
+
+    /// The architecture of this platform
+    pub fn arch(&self) -> &str {
+        &self.arch
+    }
+}
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.os, self.arch)
+    }
+}
+
+/// How the executable of a tool is laid out in a downloaded artifact
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArtifactLayout {
+    /// The artifact is the executable itself
+    Executable,
+    /// The artifact is a gzipped tarball containing the executable at the given path
+    TarGz(PathBuf),
+    /// The artifact is a zip archive containing the executable at the given path
+    Zip(PathBuf),
+}
+
+/// A downloadable artifact that contains a tool for a single platform
+#[derive(Debug, Clone)]
+pub struct ToolArtifact {
+    url: Url,
+    sha256: Option<Sha256>,
+    layout: ArtifactLayout,
+}
+
+impl ToolArtifact {
+    /// Creates an artifact with the given layout
+    pub fn new(url: &str, layout: ArtifactLayout) -> Result<Self, url::ParseError> {
+        Ok(Self {
+            url: Url::parse(url)?,
+            sha256: None,
+            layout,
+        })
+    }
+
+    /// Creates an artifact that is the executable itself
+    pub fn executable(url: &str) -> Result<Self, url::ParseError> {
+        Self::new(url, ArtifactLayout::Executable)
+    }
+
+    /// Creates an artifact that is a `.tar.gz` archive with the executable at the given path
+    pub fn tar_gz<P: AsRef<Path>>(url: &str, executable: P) -> Result<Self, url::ParseError> {
+        Self::new(
+            url,
+            ArtifactLayout::TarGz(executable.as_ref().to_path_buf()),
+        )
+    }
+
+    /// Creates an artifact that is a `.zip` archive with the executable at the given path
+    pub fn zip<P: AsRef<Path>>(url: &str, executable: P) -> Result<Self, url::ParseError> {
+        Self::new(url, ArtifactLayout::Zip(executable.as_ref().to_path_buf()))
+    }
+
+    /// Sets the expected sha256 checksum of the artifact. Downloads that don't match are rejected.
+    pub fn sha256(mut self, checksum: &str) -> Result<Self, ParseSha256Error> {
+        self.sha256 = Some(Sha256::from_str(checksum)?);
+        Ok(self)
+    }
+
+    /// The url the artifact is downloaded from
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The layout of the artifact
+    pub fn layout(&self) -> &ArtifactLayout {
+        &self.layout
+    }
+
+    fn file_name(&self) -> String {
+        self.url
+            .path_segments()
+            .and_then(|segments| segments.last())
+            .filter(|name| !name.is_empty())
+            .unwrap_or("artifact")
+            .to_string()
+    }
+}
+
+/// An external tool, with artifacts for every platform it can be provisioned on
+#[derive(Debug, Clone)]
+pub struct Tool {
+    name: String,
+    version: String,
+    artifacts: HashMap<Platform, ToolArtifact>,
+}
+
+impl Tool {
+    /// Creates a new tool with no artifacts
+    pub fn new(name: &str, version: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            artifacts: HashMap::new(),
+        }
+    }
+
+    /// Adds the artifact used on a platform
+    pub fn artifact(mut self, platform: Platform, artifact: ToolArtifact) -> Self {
+        self.artifacts.insert(platform, artifact);
+        self
+    }
+
+    /// The name of the tool
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The version of the tool
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Gets the artifact for a platform, if the tool supports it
+    pub fn artifact_for(&self, platform: &Platform) -> Option<&ToolArtifact> {
+        self.artifacts.get(platform)
+    }
+}
+
+impl Display for Tool {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.name, self.version)
+    }
+}
+
+/// An error occurred while provisioning a tool
+#[derive(Debug, thiserror::Error)]
+pub enum ToolError {
+    /// The tool has no artifact for the current platform
+    #[error("{tool} is not available for {platform}")]
+    UnsupportedPlatform {
+        /// The tool
+        tool: String,
+        /// The current platform
+        platform: Platform,
+    },
+    /// The downloaded artifact doesn't match its checksum
+    #[error("checksum mismatch for {url}: expected {expected}, found {found}")]
+    ChecksumMismatch {
+        /// The url of the artifact
+        url: Url,
+        /// The expected checksum
+        expected: Sha256,
+        /// The checksum of the downloaded file
+        found: Sha256,
+    },
+    /// The executable isn't present in the artifact
+    #[error("{0:?} not found in downloaded artifact")]
+    MissingExecutable(PathBuf),
+    /// The artifact could not be downloaded
+    #[error(transparent)]
+    Web(#[from] WebError),
+    /// A zip archive could not be read
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+    /// Io error occurred
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Downloads, verifies, and installs tools into a directory, which is `ASSEMBLE_HOME/tools` by
+/// default.
+///
+/// Every version of a tool gets its own directory at `<root>/<name>/<version>`. A tool is
+/// unpacked into a temporary directory first and only moved into place once it is complete, so an
+/// interrupted download never leaves a broken install behind.
+#[derive(Debug, Clone)]
+pub struct ToolProvisioner {
+    root: PathBuf,
+    client: WebClient,
+}
+
+impl Default for ToolProvisioner {
+    fn default() -> Self {
+        Self::new(ASSEMBLE_HOME.path().join("tools"))
+    }
+}
+
+impl ToolProvisioner {
+    /// Creates a provisioner that installs tools into the given directory
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            client: WebClient::new(),
+        }
+    }
+
+    /// Sets the client used to download artifacts
+    pub fn with_client(mut self, client: WebClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// The directory tools are installed into
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The directory a tool is installed into
+    pub fn install_dir(&self, tool: &Tool) -> PathBuf {
+        self.root.join(&tool.name).join(&tool.version)
+    }
+
+    /// Provisions a tool for the current platform, returning the path to its executable. Tools
+    /// that are already installed are not downloaded again.
+    pub fn provision(&self, tool: &Tool) -> Result<PathBuf, ToolError> {
+        self.provision_for(tool, &Platform::current())
+    }
+
+    /// Provisions a tool for a platform, returning the path to its executable.
+    pub fn provision_for(&self, tool: &Tool, platform: &Platform) -> Result<PathBuf, ToolError> {
+        let artifact =
+            tool.artifact_for(platform)
+                .ok_or_else(|| ToolError::UnsupportedPlatform {
+                    tool: tool.to_string(),
+                    platform: platform.clone(),
+                })?;
+        let install_dir = self.install_dir(tool);
+        let executable = install_dir.join(executable_path(artifact));
+
+        let _guard = PROVISIONING.lock();
+        if install_dir.join(PROVISIONED_MARKER).exists() && executable.exists() {
+            trace!("{} already provisioned at {:?}", tool, install_dir);
+            return Ok(executable);
+        }
+
+        info!("provisioning {} for {}", tool, platform);
+        let parent = install_dir
+            .parent()
+            .expect("install dir always has a parent");
+        std::fs::create_dir_all(parent)?;
+        let staging = tempfile::Builder::new()
+            .prefix(&format!(".{}-", tool.version))
+            .tempdir_in(parent)?;
+
+        let download = staging.path().join(artifact.file_name());
+        self.client.download(&artifact.url, &download)?;
+        if let Some(expected) = artifact.sha256 {
+            let found = hash_file_sha256(&download)?;
+            if found != expected {
+                return Err(ToolError::ChecksumMismatch {
+                    url: artifact.url.clone(),
+                    expected,
+                    found,
+                });
+            }
+        }
+
+        let unpacked = staging.path().join("unpacked");
+        std::fs::create_dir_all(&unpacked)?;
+        unpack(artifact, &download, &unpacked)?;
+        let staged_executable = unpacked.join(executable_path(artifact));
+        if !staged_executable.is_file() {
+            return Err(ToolError::MissingExecutable(executable_path(artifact)));
+        }
+        make_executable(&staged_executable)?;
+        File::create(unpacked.join(PROVISIONED_MARKER))?;
+
+        if install_dir.exists() {
+            // left over from an earlier, incomplete install
+            std::fs::remove_dir_all(&install_dir)?;
+        }
+        std::fs::rename(&unpacked, &install_dir)?;
+        debug!("provisioned {} at {:?}", tool, install_dir);
+        Ok(executable)
+    }
+
+    /// Gets a provider of the path to the executable of a tool. The tool is provisioned the first
+    /// time the provider is queried.
+    pub fn executable(&self, tool: Tool) -> ToolProvider {
+        ToolProvider {
+            provisioner: self.clone(),
+            tool,
+            result: Arc::new(OnceCell::new()),
+        }
+    }
+}
+
+/// The path to the executable within an install directory
+fn executable_path(artifact: &ToolArtifact) -> PathBuf {
+    match &artifact.layout {
+        ArtifactLayout::Executable => PathBuf::from(artifact.file_name()),
+        ArtifactLayout::TarGz(path) | ArtifactLayout::Zip(path) => path.clone(),
+    }
+}
+
+fn unpack(artifact: &ToolArtifact, download: &Path, into: &Path) -> Result<(), ToolError> {
+    match &artifact.layout {
+        ArtifactLayout::Executable => {
+            std::fs::copy(download, into.join(artifact.file_name()))?;
+        }
+        ArtifactLayout::TarGz(_) => {
+            let decoder = flate2::read::GzDecoder::new(File::open(download)?);
+            tar::Archive::new(decoder).unpack(into)?;
+        }
+        ArtifactLayout::Zip(_) => {
+            zip::ZipArchive::new(File::open(download)?)?.extract(into)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o755);
+    std::fs::set_permissions(path, permissions)
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Provides the path to the executable of a tool, provisioning it when first queried.
+#[derive(Clone)]
+pub struct ToolProvider {
+    provisioner: ToolProvisioner,
+    tool: Tool,
+    result: Arc<OnceCell<Result<PathBuf, String>>>,
+}
+
+impl ToolProvider {
+    fn result(&self) -> &Result<PathBuf, String> {
+        self.result.get_or_init(|| {
+            self.provisioner
+                .provision(&self.tool)
+                .map_err(|e| e.to_string())
+        })
+    }
+}
+
+impl Debug for ToolProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ToolProvider({})", self.tool)
+    }
+}
+
+impl Buildable for ToolProvider {
+    fn get_dependencies(&self, _project: &Project) -> ProjectResult<HashSet<TaskId>> {
+        Ok(HashSet::new())
+    }
+}
+
+impl Provider<PathBuf> for ToolProvider {
+    fn missing_message(&self) -> String {
+        match self.result() {
+            Ok(_) => format!("{} was provisioned", self.tool),
+            Err(e) => format!("could not provision {}: {}", self.tool, e),
+        }
+    }
+
+    fn try_get(&self) -> Option<PathBuf> {
+        self.result().as_ref().ok().cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_platform() {
+        let tool = Tool::new("tool", "1.0.0").artifact(
+            Platform::new("plan9", "mips"),
+            ToolArtifact::executable("https://example.com/tool").unwrap(),
+        );
+        let temp = tempfile::tempdir().unwrap();
+        let provisioner = ToolProvisioner::new(temp.path());
+        assert!(matches!(
+            provisioner.provision_for(&tool, &Platform::new("linux", "x86_64")),
+            Err(ToolError::UnsupportedPlatform { .. })
+        ));
+    }
+
+    #[test]
+    fn already_provisioned_tools_are_reused() {
+        let platform = Platform::current();
+        let tool = Tool::new("tool", "1.0.0").artifact(
+            platform.clone(),
+            ToolArtifact::tar_gz("https://example.invalid/tool.tar.gz", "bin/tool").unwrap(),
+        );
+        let temp = tempfile::tempdir().unwrap();
+        let provisioner = ToolProvisioner::new(temp.path());
+        let install_dir = provisioner.install_dir(&tool);
+        std::fs::create_dir_all(install_dir.join("bin")).unwrap();
+        File::create(install_dir.join("bin/tool")).unwrap();
+        File::create(install_dir.join(PROVISIONED_MARKER)).unwrap();
+
+        let executable = provisioner.provision_for(&tool, &platform).unwrap();
+        assert_eq!(executable, temp.path().join("tool/1.0.0/bin/tool"));
+    }
+
+    #[test]
+    fn executable_artifacts_are_named_after_url() {
+        let artifact =
+            ToolArtifact::executable("https://example.com/dist/rustup-init.exe").unwrap();
+        assert_eq!(executable_path(&artifact), PathBuf::from("rustup-init.exe"));
+    }
+}
@@ -18,8 +18,10 @@ use static_assertions::assert_cfg;
 
 use assemble_core::__export::ProjectResult;
 use assemble_core::exception::BuildException;
+use assemble_core::humanize;
 use assemble_core::lazy_evaluation::Prop;
 use assemble_core::prelude::{Assemble, ProjectId, Provider, Settings, SettingsAware};
+use assemble_core::startup::init_scripts::{InitActions, InitScript};
 use assemble_core::task::initialize_task::InitializeTask;
 use assemble_core::task::up_to_date::UpToDate;
 use assemble_core::{BuildResult, Executable, Project, Task};
@@ -86,6 +88,17 @@ pub trait BuildConfigurator {
         setting: &mut S,
     ) -> StdResult<(), PayloadError<Self::Err>>;
 
+    /// Evaluates an init script, if this builder supports its language. Returns `None` for
+    /// unsupported languages. Yaml init scripts are always evaluated by
+    /// [`run_init_scripts`](crate::init_scripts::run_init_scripts) instead.
+    fn evaluate_init_script(
+        &self,
+        _script: &InitScript,
+        _assemble: &Arc<RwLock<Assemble>>,
+    ) -> StdResult<Option<InitActions>, PayloadError<Self::Err>> {
+        Ok(None)
+    }
+
     /// Attempt to find a project by searching up a directory. Creates a [`Settings`] instance.
     fn discover<P: AsRef<Path>>(
         &self,
@@ -162,7 +175,7 @@ impl<S: ScriptingLang + Send + Sync, C: CompileLang<S> + Send + Sync> Task
         let output_path = task.output_file.fallible_get()?;
         let compiled = C::compile(build_script, &output_path).map_err(BuildException::new)?;
         task.compiled.set(compiled)?;
-        debug!("compiled in {:.3} sec", instant.elapsed().as_secs_f32());
+        debug!("compiled in {}", humanize::duration(instant.elapsed()));
         Ok(())
     }
 }
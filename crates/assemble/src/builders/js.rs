@@ -5,14 +5,17 @@ use crate::build_logic::plugin::script::ScriptingLang;
 use crate::builders::js::error::JavascriptError;
 use std::fmt::{Debug, Formatter};
 
-use crate::builders::js::types::Settings as JsSettings;
+use crate::builders::js::types::{Init as JsInit, ProjectRule, Settings as JsSettings};
 use crate::BuildConfigurator;
 use assemble_core::prelude::{Assemble, AssembleAware, Settings, SettingsAware, StdResult};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 
 use crate::build_logic::{BuildLogic, NoOpBuildLogic};
 use crate::builders::js::build_logic::JsBuildLogic;
+use assemble_core::deprecations::DeprecationOrigin;
 use assemble_core::error::PayloadError;
+use assemble_core::startup::init_scripts::{InitActions, InitScript, InitScriptLang};
+use assemble_core::startup::trust::verify_build_logic;
 use assemble_js::javascript;
 use rquickjs::{Context, FromJs, IntoJs, Object, Runtime};
 use std::path::Path;
@@ -27,6 +30,8 @@ pub mod types;
 /// A java script builder
 pub struct JavascriptBuilder {
     runtime: Runtime,
+    project_rules: Mutex<Vec<ProjectRule>>,
+    init_rules: Mutex<Vec<ProjectRule>>,
 }
 
 impl Debug for JavascriptBuilder {
@@ -48,6 +53,8 @@ impl JavascriptBuilder {
     pub fn new() -> Self {
         Self {
             runtime: Runtime::new().expect("could not create js runtime"),
+            project_rules: Mutex::new(vec![]),
+            init_rules: Mutex::new(vec![]),
         }
     }
 
@@ -84,6 +91,7 @@ impl JavascriptBuilder {
             }
 
             trace!("evaluating script {:?}", path);
+            let _origin = DeprecationOrigin::enter(format_args!("script {}", path.display()));
             ctx.eval_file(path)?;
 
             let result: T = ctx.eval(var_name)?;
@@ -101,7 +109,14 @@ impl BuildConfigurator for JavascriptBuilder {
         &self,
         settings: &S,
     ) -> StdResult<Self::BuildLogic<S>, PayloadError<Self::Err>> {
-        Ok(JsBuildLogic::new(&self.runtime))
+        let project_rules = self
+            .init_rules
+            .lock()
+            .iter()
+            .chain(self.project_rules.lock().iter())
+            .cloned()
+            .collect();
+        Ok(JsBuildLogic::new(&self.runtime, project_rules))
     }
 
     fn configure_settings<S: SettingsAware>(
@@ -109,6 +124,8 @@ impl BuildConfigurator for JavascriptBuilder {
         setting: &mut S,
     ) -> StdResult<(), PayloadError<Self::Err>> {
         let settings_file = setting.with_settings(|p| p.settings_file().to_path_buf());
+        let trust_policy = setting.with_assemble(|a| a.start_parameter().trust_policy());
+        verify_build_logic(trust_policy, &settings_file).map_err(JavascriptError::from)?;
         let js_settings: JsSettings = self
             .configure_value(
                 "settings",
@@ -129,6 +146,7 @@ impl BuildConfigurator for JavascriptBuilder {
             .map_err(PayloadError::new)?;
 
         trace!("js settings: {:#?}", js_settings);
+        *self.project_rules.lock() = js_settings.project_rules;
         setting.with_settings_mut(|s| {
             s.root_project_mut()
                 .set_name(&js_settings.root_project.name);
@@ -137,10 +155,62 @@ impl BuildConfigurator for JavascriptBuilder {
                     pr.set_name(desc.name);
                 })
             }
+            for included in js_settings.included_builds {
+                let build = s.include_build(&included.path);
+                if let Some(name) = included.name {
+                    build.set_name(name);
+                }
+                for substitution in included.substitutions {
+                    build.substitute(substitution.module, substitution.project);
+                }
+            }
         });
         Ok(())
     }
 
+    /// Evaluates javascript init scripts. Rules declared with `init.allprojects` and
+    /// `init.subprojects` are applied before the rules of the settings script.
+    fn evaluate_init_script(
+        &self,
+        script: &InitScript,
+        assemble: &Arc<RwLock<Assemble>>,
+    ) -> StdResult<Option<InitActions>, PayloadError<Self::Err>> {
+        if script.lang() != InitScriptLang::Js {
+            return Ok(None);
+        }
+        let trust_policy = assemble.read().start_parameter().trust_policy();
+        verify_build_logic(trust_policy, script.path()).map_err(JavascriptError::from)?;
+        let js_init: JsInit = self
+            .configure_value(
+                "init",
+                script.path(),
+                [
+                    &format!(
+                        r#"
+                const current_dir = {:?};
+                require("assemble");
+            "#,
+                        assemble.read().current_dir()
+                    ),
+                    javascript::file_contents("init.js").map_err(PayloadError::new)?,
+                ],
+            )
+            .map_err(|e| JavascriptError::RQuickJsErrorWithFile(e, script.path().to_path_buf()))?;
+
+        trace!("js init: {:#?}", js_init);
+        let mut actions = InitActions::default();
+        for property in js_init.properties {
+            actions.properties.insert(property.key, property.value);
+        }
+        for repository in &js_init.plugin_repositories {
+            actions
+                .plugin_repository(repository)
+                .map_err(JavascriptError::from)?;
+        }
+        self.init_rules.lock().extend(js_init.project_rules);
+        Ok(Some(actions))
+    }
+
     fn discover<P: AsRef<Path>>(
         &self,
         path: P,
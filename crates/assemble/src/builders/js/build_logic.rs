@@ -1,11 +1,14 @@
 use crate::build_logic::BuildLogic;
 use crate::builders::js::error::JavascriptError;
+use crate::builders::js::types::ProjectRule;
+use assemble_core::deprecations::DeprecationOrigin;
 use assemble_core::error::PayloadError;
 use assemble_core::logging::LOGGING_CONTROL;
 use assemble_core::plugins::extensions::ExtensionAware;
-use assemble_core::prelude::SettingsAware;
+use assemble_core::prelude::{AssembleAware, SettingsAware};
 use assemble_core::project::shared::SharedProject;
 use assemble_core::project::GetProjectId;
+use assemble_core::startup::trust::verify_build_logic;
 use assemble_js::{javascript, Delegating, Engine, JsPlugin, JsPluginExtension};
 use rquickjs::Runtime;
 
@@ -13,14 +16,27 @@ use rquickjs::Runtime;
 #[derive(Debug)]
 pub struct JsBuildLogic {
     engine: Engine,
+    project_rules: Vec<ProjectRule>,
 }
 
 impl JsBuildLogic {
-    pub fn new(runtime: &Runtime) -> Self {
+    pub fn new(runtime: &Runtime, project_rules: Vec<ProjectRule>) -> Self {
         Self {
             engine: Engine::with_runtime(runtime).with_bindings::<javascript::project::Project>(),
+            project_rules,
         }
     }
+
+    /// The script applying the `allprojects` and `subprojects` rules that apply to a project, in
+    /// the order they were declared
+    fn rules_script(&self, project: &SharedProject) -> String {
+        let is_root = project.with(|p| p.is_root());
+        self.project_rules
+            .iter()
+            .filter(|rule| rule.applies_to(is_root))
+            .map(|rule| format!("({})(project);\n", rule.source))
+            .collect()
+    }
 }
 
 impl<S: SettingsAware> BuildLogic<S> for JsBuildLogic {
@@ -31,6 +47,29 @@ impl<S: SettingsAware> BuildLogic<S> for JsBuildLogic {
         settings: &S,
         project: &SharedProject,
     ) -> Result<(), PayloadError<Self::Err>> {
+        let profile = settings.with_assemble(|a| a.profile().clone());
+        profile.measure_project(&project.project_id(), || {
+            self.configure_project(settings, project)
+        })?;
+
+        project.with(|p| -> Result<(), PayloadError<Self::Err>> {
+            for sub in p.subprojects() {
+                self.configure(settings, sub)?;
+            }
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+}
+
+impl JsBuildLogic {
+    /// Configures a single project, without its subprojects
+    fn configure_project<S: SettingsAware>(
+        &self,
+        settings: &S,
+        project: &SharedProject,
+    ) -> Result<(), PayloadError<JavascriptError>> {
         LOGGING_CONTROL.in_project(project.project_id());
         trace!("configuring project {}", project);
         project
@@ -47,14 +86,16 @@ impl<S: SettingsAware> BuildLogic<S> for JsBuildLogic {
             .expect("build file must be set, even if it doesn't exist");
 
         trace!("found potential build file: {:?}", file);
-        if file.try_exists().map_err(|e| JavascriptError::from(e))? {
+        let rules_script = self.rules_script(project);
+        let file_exists = file.try_exists().map_err(|e| JavascriptError::from(e))?;
+        if file_exists || !rules_script.is_empty() {
             trace!(
                 "creating delegate of project {} from engine {:?}",
                 project,
                 self.engine
             );
 
-            let delegating = project.with(|p| -> Result<Delegating<_>, Self::Err> {
+            let delegating = project.with(|p| -> Result<Delegating<_>, JavascriptError> {
                 let js_ext = p.extension::<JsPluginExtension>().unwrap();
                 let mut engine = js_ext.engine().lock();
                 engine.using_bindings::<javascript::project::Project>();
@@ -64,23 +105,23 @@ impl<S: SettingsAware> BuildLogic<S> for JsBuildLogic {
                 )?)
             })?;
 
-            trace!("build file exists ({:?}), evaluating...", file);
+            // rules are applied before the build script of the project
+            let mut script = rules_script.into_bytes();
+            if file_exists {
+                trace!("build file exists ({:?}), evaluating...", file);
+                let trust_policy = settings.with_assemble(|a| a.start_parameter().trust_policy());
+                verify_build_logic(trust_policy, &file).map_err(JavascriptError::from)?;
+                script.extend(std::fs::read(&file).map_err(JavascriptError::from)?);
+            }
+            let _origin = DeprecationOrigin::enter(format_args!("build script {}", file.display()));
             delegating
-                .eval_file_once(&file)
+                .eval_once::<_, ()>(script)
                 .map_err(|e| JavascriptError::RQuickJsErrorWithFile(e, file))?;
         } else {
             debug!("no build file found for project {} at {:?}", project, file);
         }
 
         LOGGING_CONTROL.reset();
-
-        project.with(|p| -> Result<(), PayloadError<Self::Err>> {
-            for sub in p.subprojects() {
-                self.configure(settings, sub)?;
-            }
-            Ok(())
-        })?;
-
         Ok(())
     }
 }
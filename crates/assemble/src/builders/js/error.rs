@@ -1,5 +1,7 @@
 use crate::builders::js::JavascriptBuilder;
 use crate::error::AssembleError;
+use assemble_core::startup::init_scripts::InitScriptError;
+use assemble_core::startup::trust::TrustError;
 use assemble_js::javascript::FileError;
 use std::io;
 use std::path::PathBuf;
@@ -16,4 +18,8 @@ pub enum JavascriptError {
     FileError(#[from] assemble_js::javascript::FileError),
     #[error(transparent)]
     IoError(#[from] io::Error),
+    #[error(transparent)]
+    TrustError(#[from] TrustError),
+    #[error(transparent)]
+    InitScriptError(#[from] InitScriptError),
 }
@@ -3,6 +3,56 @@ use rquickjs::FromJs;
 #[derive(Debug, FromJs)]
 pub struct Settings {
     pub root_project: ProjectDescriptor,
+    pub project_rules: Vec<ProjectRule>,
+    pub included_builds: Vec<IncludedBuild>,
+}
+
+/// A build included with `settings.include_build`
+#[derive(Debug, FromJs)]
+pub struct IncludedBuild {
+    pub path: String,
+    pub name: Option<String>,
+    pub substitutions: Vec<Substitution>,
+}
+
+/// A module provided by a project of an included build
+#[derive(Debug, FromJs)]
+pub struct Substitution {
+    pub module: String,
+    pub project: String,
+}
+
+/// The actions declared by an init script
+#[derive(Debug, FromJs)]
+pub struct Init {
+    pub properties: Vec<InitProperty>,
+    pub plugin_repositories: Vec<String>,
+    pub project_rules: Vec<ProjectRule>,
+}
+
+/// A default project property set by an init script
+#[derive(Debug, FromJs)]
+pub struct InitProperty {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+/// An `allprojects` or `subprojects` rule declared in a settings or init script
+#[derive(Debug, Clone, FromJs)]
+pub struct ProjectRule {
+    pub scope: String,
+    pub source: String,
+}
+
+impl ProjectRule {
+    /// Checks whether this rule applies to a project
+    pub fn applies_to(&self, is_root: bool) -> bool {
+        match self.scope.as_str() {
+            "allprojects" => true,
+            "subprojects" => !is_root,
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, FromJs)]
@@ -1,9 +1,12 @@
 //! Error result
 
 use std::convert::Infallible;
+use assemble_core::error::category::{Categorize, ErrorCategory, ErrorCode};
+use assemble_core::error::hints::Hint;
 use assemble_core::error::PayloadError;
 use assemble_core::exception::BuildException;
 use assemble_core::project::ProjectError;
+use assemble_core::startup::init_scripts::InitScriptError;
 use crate::builders::BuildConfigurator;
 use assemble_freight::utils::FreightError;
 use crate::builders::js::error::JavascriptError;
@@ -14,9 +17,32 @@ pub enum AssembleError {
     FreightError(#[from] FreightError),
     #[error(transparent)]
     ProjectError(#[from] ProjectError),
+    #[error(transparent)]
+    InitScriptError(#[from] InitScriptError),
     #[cfg(feature = "js")]
     #[error(transparent)]
     JsError(#[from] JavascriptError),
     #[error(transparent)]
     Infallible(#[from] Infallible)
 }
+
+impl Categorize for AssembleError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            AssembleError::FreightError(e) => e.code(),
+            AssembleError::ProjectError(e) => e.code(),
+            AssembleError::InitScriptError(e) => e.code(),
+            #[cfg(feature = "js")]
+            AssembleError::JsError(_) => ErrorCode::new(ErrorCategory::Configuration, 40),
+            AssembleError::Infallible(_) => ErrorCode::new(ErrorCategory::Internal, 2),
+        }
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        match self {
+            AssembleError::FreightError(e) => e.hints(),
+            AssembleError::ProjectError(e) => e.hints(),
+            _ => vec![],
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Runs init scripts before settings are discovered.
+//!
+//! See [`assemble_core::startup::init_scripts`](assemble_core::startup::init_scripts) for where
+//! init scripts are found.
+
+use crate::builders::BuildConfigurator;
+use crate::error::AssembleError;
+use assemble_core::error::PayloadError;
+use assemble_core::prelude::Assemble;
+use assemble_core::startup::init_scripts::{init_scripts, InitScriptError, InitScriptLang};
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+/// Evaluates the init scripts of the build in order, applying the actions of each to the start
+/// parameter of the build.
+///
+/// Yaml init scripts are always supported, while other languages depend on the builder. Init
+/// scripts in `ASSEMBLE_HOME` the builder can't evaluate are skipped with a warning, but it's an
+/// error for scripts given with `--init-script`.
+pub fn run_init_scripts<B: BuildConfigurator>(
+    builder: &B,
+    assemble: &Arc<RwLock<Assemble>>,
+) -> crate::Result<()>
+where
+    B::Err: 'static + Into<AssembleError>,
+{
+    let explicit = assemble.read().start_parameter().init_scripts().to_vec();
+    let scripts = init_scripts(&explicit).map_err(PayloadError::new)?;
+
+    for script in scripts {
+        debug!("evaluating init script {:?}", script.path());
+        let actions = match script.lang() {
+            InitScriptLang::Yaml => Some(script.evaluate_yaml().map_err(PayloadError::new)?),
+            _ => builder
+                .evaluate_init_script(&script, assemble)
+                .map_err(PayloadError::into::<AssembleError>)?,
+        };
+
+        match actions {
+            Some(actions) => {
+                trace!("init actions: {:#?}", actions);
+                actions.apply_to(assemble.write().start_parameter_mut());
+            }
+            None => {
+                let error = InitScriptError::Unsupported {
+                    path: script.path().to_path_buf(),
+                    lang: script.lang(),
+                };
+                if script.is_explicit() {
+                    return Err(PayloadError::new(error));
+                }
+                warn!("skipping init script: {}", error);
+            }
+        }
+    }
+    Ok(())
+}
@@ -8,13 +8,29 @@ extern crate serde;
 use std::panic;
 use std::sync::Arc;
 
+use assemble_core::cache::manager::{CacheManager, EvictionPolicy};
+use assemble_core::deprecations::{self, set_warning_mode, WarningMode};
+use assemble_core::error::category::Categorize;
+use assemble_core::error::hints::{hints_for, Failure};
 use assemble_core::error::PayloadError;
+use assemble_core::file_lock::FileLock;
 use parking_lot::RwLock;
 
+use assemble_core::logging::json_stream::JsonEvent;
 use assemble_core::logging::LOGGING_CONTROL;
 use assemble_core::prelude::{
-    self, Assemble, AssembleAware, CreateProject, Settings, StartParameter, TaskId,
+    self, Assemble, AssembleAware, BacktraceEmit, CreateProject, ProjectError, Settings,
+    StartParameter, TaskId,
 };
+use assemble_core::startup::cancellation::{
+    build_cancellation, CancellationReason, INTERRUPTED_EXIT_CODE,
+};
+use assemble_core::startup::control::{request_cancel, BuildId, ControlResponse, ControlServer};
+use assemble_core::startup::initialization::ConfiguredBuild;
+use assemble_core::startup::profile::BuildPhase;
+use assemble_core::startup::trust::TrustStore;
+use assemble_core::startup::updates::{update_notice, ReleaseChannel, UpdateChecker};
+use assemble_core::startup::watchdog::{Watchdog, WatchedPhase};
 use assemble_core::text_factory::list::TextListFactory;
 use assemble_core::Project;
 use assemble_freight::core::ConstructionError;
@@ -26,19 +42,23 @@ use build_logic::BuildLogic;
 
 use crate::builders::BuildConfigurator;
 use crate::error::AssembleError;
+use crate::init_scripts::run_init_scripts;
 
 pub mod build_logic;
 pub mod builders;
 #[cfg(debug_assertions)]
 pub mod dev;
 pub mod error;
+pub mod init_scripts;
 
 pub type Result<T> = std::result::Result<T, PayloadError<AssembleError>>;
+use assemble_core::project::evaluation::evaluate_projects;
 use assemble_core::project::finder::{ProjectFinder, ProjectPath, ProjectPathBuf};
 use assemble_core::project::shared::SharedProject;
 use log::Level;
+use std::path::PathBuf;
 pub use std::result::Result as StdResult;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 pub fn execute_v2() -> std::result::Result<(), ()> {
     let freight_args: FreightArgs = FreightArgs::from_env();
@@ -48,60 +68,318 @@ pub fn execute_v2() -> std::result::Result<(), ()> {
         .map_err(|_| ())?
         .expect("this should be top level entry");
 
+    if let Some(build_id) = freight_args.cancel() {
+        let output = cancel_build(build_id);
+        LOGGING_CONTROL.stop_logging();
+        join_handle.join().expect("should be able to join here");
+        return output;
+    }
+
+    if !freight_args.trust().is_empty() {
+        let output = trust_build_logic(freight_args.trust());
+        LOGGING_CONTROL.stop_logging();
+        join_handle.join().expect("should be able to join here");
+        return output;
+    }
+
+    if freight_args.self_update() {
+        let output = self_update(freight_args.update_channel());
+        LOGGING_CONTROL.stop_logging();
+        join_handle.join().expect("should be able to join here");
+        return output;
+    }
+
+    if freight_args.clean_caches() {
+        let output = clean_caches();
+        LOGGING_CONTROL.stop_logging();
+        join_handle.join().expect("should be able to join here");
+        return output;
+    }
+
     let mut start_param = StartParameter::from(freight_args);
 
     trace!("start param: {:#?}", start_param);
     let builder = builders::builder();
     let show_backtrace = start_param.backtrace();
 
+    install_interrupt_handler();
+    let _control_server = start_control_server(&start_param);
+    let update_check = start_param.update_check();
+    LOGGING_CONTROL.emit_event(JsonEvent::build_started());
+    let build_start = Instant::now();
     let output = build(start_param, &builder);
+    let build_finished = build_finished_event(&output, build_start.elapsed());
 
     let output = if let Err(e) = output {
-        error!("{:#}", e);
-        show_backtrace.emit(Level::Error, e.backtrace());
+        if build_cancellation().is_cancelled() {
+            warn!("{}", e);
+        } else {
+            error!("{} [{}]: {:#}", e.category(), e.code(), e);
+            let show_backtrace = if e.is_user_error() {
+                show_backtrace
+            } else {
+                // bugs always get a backtrace, so that they can be reported
+                BacktraceEmit::Long
+            };
+            show_backtrace.emit(Level::Error, e.backtrace());
+            // the hints of failed tasks were already shown with their failures
+            if !matches!(
+                e.kind(),
+                AssembleError::FreightError(FreightError::TasksFailed(_))
+            ) {
+                let failure = Failure::new(&e, show_backtrace != BacktraceEmit::None);
+                for hint in hints_for(&e, &failure) {
+                    error!("hint: {}", hint);
+                }
+            }
+        }
         Err(())
     } else {
         Ok(())
     };
+    if let Some(channel) = update_check {
+        notify_update(channel);
+    }
+    LOGGING_CONTROL.emit_event(build_finished);
     LOGGING_CONTROL.stop_logging();
     join_handle.join().expect("should be able to join here");
     output
 }
 
+/// The event reporting the result of the build in the json log stream
+fn build_finished_event(output: &Result<()>, duration: Duration) -> JsonEvent {
+    let duration_ms = duration.as_millis() as u64;
+    match output {
+        Ok(()) => JsonEvent::BuildFinished {
+            success: true,
+            duration_ms,
+            failed_tasks: vec![],
+            error: None,
+            code: None,
+        },
+        Err(e) => {
+            let failed_tasks = match e.kind() {
+                AssembleError::FreightError(FreightError::TasksFailed(failed)) => failed.clone(),
+                _ => vec![],
+            };
+            JsonEvent::BuildFinished {
+                success: false,
+                duration_ms,
+                failed_tasks,
+                error: Some(e.to_string()),
+                code: Some(e.code().to_string()),
+            }
+        }
+    }
+}
+
+/// Cancels the build on the first Ctrl-C. Running tasks are allowed to finish, but no new tasks
+/// are started. A second Ctrl-C aborts the build immediately.
+fn install_interrupt_handler() {
+    let cancellation = build_cancellation();
+    let result = ctrlc::set_handler(move || {
+        if cancellation.cancel(CancellationReason::Interrupted) {
+            warn!("cancelling build after running tasks finish, press Ctrl-C again to abort");
+        } else {
+            LOGGING_CONTROL.stop_logging();
+            std::process::exit(INTERRUPTED_EXIT_CODE);
+        }
+    });
+    if let Err(e) = result {
+        warn!("could not install Ctrl-C handler: {}", e);
+    }
+}
+
+/// Opens the control endpoint of the build, so other processes can cancel it. The build still
+/// runs if the endpoint can't be opened.
+fn start_control_server(start_param: &StartParameter) -> Option<ControlServer> {
+    match ControlServer::start(start_param.build_id().clone(), build_cancellation()) {
+        Ok(server) => {
+            info!("build id: {}", server.registration().build_id);
+            Some(server)
+        }
+        Err(e) => {
+            warn!("could not open build control endpoint: {}", e);
+            None
+        }
+    }
+}
+
+/// Records the current checksums of build logic files as trusted
+fn trust_build_logic(paths: &[PathBuf]) -> std::result::Result<(), ()> {
+    let mut store = TrustStore::open().map_err(|e| error!("could not open trust store: {}", e))?;
+    for path in paths {
+        match store.trust(path) {
+            Ok(checksum) => info!("trusted {:?} (sha256 {})", path, checksum),
+            Err(e) => {
+                error!("could not trust {:?}: {}", path, e);
+                return Err(());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Shows a notice if a newer version of assemble is available. Failing to check for updates
+/// never fails the build.
+fn notify_update(channel: ReleaseChannel) {
+    let checker = UpdateChecker::new(channel);
+    match checker.check() {
+        Ok(Some(release)) => info!("{}", update_notice(checker.current(), &release, channel)),
+        Ok(None) => debug!("assemble is up to date on the {} channel", channel),
+        Err(e) => debug!("could not check for updates: {}", e),
+    }
+}
+
+/// Installs the newest release of assemble on a channel with cargo
+fn self_update(channel: ReleaseChannel) -> std::result::Result<(), ()> {
+    let checker = UpdateChecker::new(channel);
+    checker
+        .invalidate()
+        .map_err(|e| error!("could not check for updates: {}", e))?;
+    let release = match checker.check() {
+        Ok(Some(release)) => release,
+        Ok(None) => {
+            info!(
+                "assemble {} is the newest version on the {} channel",
+                checker.current(),
+                channel
+            );
+            return Ok(());
+        }
+        Err(e) => {
+            error!("could not check for updates: {}", e);
+            return Err(());
+        }
+    };
+
+    info!("installing assemble {}", release.version);
+    let status = std::process::Command::new("cargo")
+        .args(["install", env!("CARGO_PKG_NAME"), "--locked", "--version"])
+        .arg(release.version.to_string())
+        .status()
+        .map_err(|e| error!("could not run cargo: {}", e))?;
+    if status.success() {
+        info!("updated assemble to {}", release.version);
+        Ok(())
+    } else {
+        error!("cargo failed to install assemble {}", release.version);
+        Err(())
+    }
+}
+
+/// Evicts old and unused entries from the caches in the assemble home
+fn clean_caches() -> std::result::Result<(), ()> {
+    let manager = CacheManager::default();
+    match manager.clean(&EvictionPolicy::default()) {
+        Ok(Some(report)) => {
+            info!("cleaned caches in {:?}: {}", manager.root(), report);
+            Ok(())
+        }
+        Ok(None) => {
+            warn!("another assemble process is already cleaning the caches");
+            Ok(())
+        }
+        Err(e) => {
+            error!("could not clean caches in {:?}: {}", manager.root(), e);
+            Err(())
+        }
+    }
+}
+
+/// Requests that a running build is cancelled
+fn cancel_build(build_id: &BuildId) -> std::result::Result<(), ()> {
+    match request_cancel(build_id) {
+        Ok(ControlResponse::Status {
+            cancelled: Some(reason),
+            ..
+        }) => {
+            info!("build {} is cancelling: {}", build_id, reason);
+            Ok(())
+        }
+        Ok(response) => {
+            error!("build {} could not be cancelled: {:?}", build_id, response);
+            Err(())
+        }
+        Err(e) => {
+            error!("could not cancel build {}: {}", build_id, e);
+            Err(())
+        }
+    }
+}
+
 pub fn build<B: BuildConfigurator>(start_parameter: StartParameter, builder: &B) -> Result<()>
 where
     B::Err: 'static + Into<AssembleError>,
 {
+    let start = Instant::now();
     let join_handle = start_parameter.logging().init_root_logger();
     let _properties = start_parameter.properties();
+    set_warning_mode(start_parameter.warning_mode());
 
     let mut assemble: Arc<RwLock<Assemble>> = Arc::new(RwLock::new(
         init_assemble(start_parameter.clone()).expect("couldn't init assemble"),
     ));
     trace!("assemble: {:#?}", assemble);
 
+    let watchdog = Watchdog::start(start_parameter.timeouts().clone(), build_cancellation());
+    let show_backtrace = start_parameter.backtrace() != BacktraceEmit::None;
+    let profile = assemble.read().profile().clone();
+    profile.record(BuildPhase::Initialization, start.elapsed());
+    let build_profile = profile.clone();
+
     let ret = (move || -> Result<()> {
+        watchdog.enter_phase(WatchedPhase::Configuration);
+        run_init_scripts(builder, &assemble)?;
+
         let mut settings: Arc<RwLock<Settings>> = Arc::new(RwLock::new(
-            builder
-                .discover(assemble.read().current_dir(), &assemble)
+            profile
+                .measure(BuildPhase::ScriptDiscovery, || {
+                    builder.discover(assemble.read().current_dir(), &assemble)
+                })
                 .map_err(|e| e.into())?,
         ));
 
-        builder
-            .configure_settings(&mut settings)
-            .map_err(|e| e.into())?;
+        let root_dir = settings.read().root_dir().to_path_buf();
         assemble
-            .with_assemble_mut(|ass| ass.settings_evaluated(settings.clone()))
-            .map_err(|e| e.into())?;
+            .write()
+            .load_properties(&root_dir)
+            .map_err(|e| PayloadError::new(ProjectError::from(e)).into::<AssembleError>())?;
+
+        profile.measure(BuildPhase::SettingsEvaluation, || -> Result<()> {
+            builder
+                .configure_settings(&mut settings)
+                .map_err(|e| e.into())?;
+            assemble
+                .with_assemble_mut(|ass| ass.settings_evaluated(settings.clone()))
+                .map_err(|e| e.into())?;
+            Ok(())
+        })?;
         trace!("settings: {:#?}", settings);
         trace!("project graph:\n{}", settings.read().project_graph());
 
-        let mut build_logic = configure_build_logic(&settings, builder).map_err(|e| e.into())?;
-        let mut project = CreateProject::create_project(&settings).map_err(|e| e.into())?;
+        let project = profile.measure(
+            BuildPhase::BuildLogicConfiguration,
+            || -> Result<SharedProject> {
+                let mut build_logic =
+                    configure_build_logic(&settings, builder).map_err(|e| e.into())?;
+                configure_included_builds(&settings, builder)?;
+                let project = CreateProject::create_project(&settings).map_err(|e| e.into())?;
+                assemble
+                    .with_assemble_mut(|ass| ass.projects_loaded(&project))
+                    .map_err(|e| e.into())?;
 
-        build_logic
-            .configure(&settings, &project)
-            .map_err(|e| e.into::<AssembleError>())?;
+                evaluate_projects(&project, || {
+                    build_logic
+                        .configure(&settings, &project)
+                        .map_err(|e| e.into::<AssembleError>())
+                })?;
+                assemble
+                    .with_assemble_mut(|ass| ass.projects_evaluated(&project))
+                    .map_err(|e| e.into())?;
+                Ok(project)
+            },
+        )?;
 
         trace!("root = {:#?}", project);
         trace!("determining project from current dir");
@@ -126,11 +404,40 @@ where
         }
         trace!("current = {:#?}", project);
         debug!("finished configuring project\n");
-        execute_tasks2(&project, &current, &settings).map_err(PayloadError::into)?;
+        if let Some(reason) = build_cancellation().reason() {
+            return Err(PayloadError::new(FreightError::Cancelled(reason)));
+        }
+
+        // builds of the same project in other processes would write to the same build directories
+        let _build_lock = FileLock::acquire(
+            root_dir.join(".assemble").join("build.lock"),
+            "execute tasks",
+        )
+        .map_err(|e| PayloadError::new(FreightError::from(e)))?;
+
+        watchdog.enter_phase(WatchedPhase::Execution);
+        let results = execute_tasks2(&project, &current, &settings).map_err(PayloadError::into)?;
+        let mut failed = vec![];
+        emit_task_results(&results, &mut failed, show_backtrace);
+        if !failed.is_empty() {
+            return Err(PayloadError::new(FreightError::TasksFailed(failed)).into());
+        }
 
         Ok(())
     })();
 
+    let deprecations_used = deprecations::report();
+    let ret = ret.and_then(|()| {
+        if start_parameter.warning_mode() == WarningMode::Fail && !deprecations_used.is_empty() {
+            Err(PayloadError::new(FreightError::DeprecationsUsed(deprecations_used.len())).into())
+        } else {
+            Ok(())
+        }
+    });
+
+    build_profile.finish_build(ret.is_ok());
+    info!("{}", build_profile.summary());
+
     if let Ok(Some(join_h)) = join_handle {
         LOGGING_CONTROL.stop_logging();
         join_h.join().expect("should be able to join here")
@@ -149,6 +456,56 @@ where
     builder.get_build_logic(settings)
 }
 
+/// Configures the builds included in a build, in isolation from the including build. Included
+/// builds are configured before the projects of the including build are created, so the
+/// substitutions they declare apply to every project.
+fn configure_included_builds<B: BuildConfigurator>(
+    settings: &Arc<RwLock<Settings>>,
+    builder: &B,
+) -> Result<()>
+where
+    B::Err: 'static + Into<AssembleError>,
+{
+    let assemble = settings.read().assemble().clone();
+    let included_builds = settings.read().included_builds().to_vec();
+    for included in included_builds {
+        debug!("configuring included build at {:?}", included.root_dir());
+        let included_settings = builder
+            .discover(included.root_dir(), &assemble)
+            .map_err(|e| e.into())?;
+        if included_settings.root_dir() != included.root_dir() {
+            return Err(PayloadError::new(ProjectError::custom(format!(
+                "no settings file found in included build {:?}",
+                included.root_dir()
+            )))
+            .into::<AssembleError>());
+        }
+        let mut included_settings = Arc::new(RwLock::new(included_settings));
+        builder
+            .configure_settings(&mut included_settings)
+            .map_err(|e| e.into())?;
+        if let Some(name) = included.name() {
+            included_settings.write().root_project_mut().set_name(name);
+        }
+
+        let mut build_logic =
+            configure_build_logic(&included_settings, builder).map_err(|e| e.into())?;
+        configure_included_builds(&included_settings, builder)?;
+        let root = CreateProject::create_project(&included_settings).map_err(|e| e.into())?;
+        evaluate_projects(&root, || {
+            build_logic
+                .configure(&included_settings, &root)
+                .map_err(|e| e.into::<AssembleError>())
+        })?;
+
+        settings
+            .write()
+            .add_configured_build(&included, ConfiguredBuild::new(&included_settings, &root))
+            .map_err(|e| e.into())?;
+    }
+    Ok(())
+}
+
 //
 // pub fn with_args(freight_args: FreightArgs) -> Result<()> {
 //     let join_handle = freight_args.logging().init_root_logger();
@@ -237,7 +594,7 @@ where
 //     ret
 // }
 
-/// Emits task results.
+/// Emits task results, along with hints on how to resolve failures.
 ///
 /// extends a list of failed task ids
 fn emit_task_results(results: &Vec<TaskResult>, failed: &mut Vec<TaskId>, show_backtrace: bool) {
@@ -251,11 +608,17 @@ fn emit_task_results(results: &Vec<TaskResult>, failed: &mut Vec<TaskId>, show_b
                 .element(format!("Task {} failed", task_r.id))
                 .sublist(|sub| {
                     let sub = sub.element(format!("{}", err.kind()));
-                    if show_backtrace {
+                    let sub = if show_backtrace {
                         sub.element(format!("{:?}", err.backtrace()))
                     } else {
                         sub
-                    }
+                    };
+                    let failure = Failure::of_task(&task_r.id, err, show_backtrace);
+                    sub.elements(
+                        hints_for(err, &failure)
+                            .into_iter()
+                            .map(|hint| format!("hint: {}", hint)),
+                    )
                 });
             failed.push(task_r.id.clone());
         }
@@ -1,17 +1,23 @@
 use assemble::execute_v2;
 
+use assemble_core::startup::cancellation::build_cancellation;
+use assemble_core::text_factory::BuildResultString;
 use std::process::ExitCode;
 use std::time::Instant;
-use assemble_core::text_factory::BuildResultString;
 
 fn main() -> ExitCode {
     let start = Instant::now();
     let res = execute_v2();
-    let status = BuildResultString::new(res.is_ok(), start.elapsed());
+    let cancelled = build_cancellation().reason();
+    let status = match cancelled {
+        Some(_) => BuildResultString::cancelled(start.elapsed()),
+        None => BuildResultString::new(res.is_ok(), start.elapsed()),
+    };
     println!();
     println!("{}", status);
-    match res {
-        Ok(_) => ExitCode::SUCCESS,
-        Err(_) => ExitCode::FAILURE,
+    match (res, cancelled) {
+        (_, Some(reason)) => ExitCode::from(reason.exit_code() as u8),
+        (Ok(_), None) => ExitCode::SUCCESS,
+        (Err(_), None) => ExitCode::FAILURE,
     }
 }